@@ -0,0 +1,117 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::gen_credentials::{generate_node_id, generate_secure_password};
+
+const CREDENTIALS_FILE: &str = "credentials";
+
+/// The self-hosted node's credentials as kept between restarts.
+pub struct Credentials {
+    pub node_id: String,
+    pub password: String,
+}
+
+impl Credentials {
+    fn generate() -> Self {
+        Self {
+            node_id: generate_node_id(),
+            password: generate_secure_password(),
+        }
+    }
+}
+
+/// Where the credentials live: the configured state directory, else
+/// `$XDG_STATE_HOME/docklord`, else `~/.local/state/docklord`.
+pub fn credentials_path(state_dir: Option<&Path>) -> PathBuf {
+    let dir = match state_dir {
+        Some(dir) => dir.to_path_buf(),
+        None => xdg_state_dir(),
+    };
+    dir.join(CREDENTIALS_FILE)
+}
+
+fn xdg_state_dir() -> PathBuf {
+    let base = std::env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME")
+                .filter(|dir| !dir.is_empty())
+                .map(|home| PathBuf::from(home).join(".local/state"))
+        });
+    match base {
+        Some(base) => base.join("docklord"),
+        // No home, e.g. in a scratch container
+        None => PathBuf::from("docklord-state"),
+    }
+}
+
+/// Reads the saved credentials, `None` if there are none yet.
+pub fn load(path: &Path) -> io::Result<Option<Credentials>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    let mut node_id = None;
+    let mut password = None;
+    for line in content.lines() {
+        match line.split_once('=') {
+            Some(("node_id", value)) => node_id = Some(value.trim().to_string()),
+            Some(("password", value)) => password = Some(value.trim().to_string()),
+            _ => {}
+        }
+    }
+    match (node_id, password) {
+        (Some(node_id), Some(password)) if !node_id.is_empty() && !password.is_empty() => {
+            Ok(Some(Credentials { node_id, password }))
+        }
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} is missing node_id or password", path.display()),
+        )),
+    }
+}
+
+/// Returns the saved credentials, generating and saving new ones on first start.
+pub fn load_or_create(path: &Path) -> io::Result<Credentials> {
+    match load(path)? {
+        Some(credentials) => Ok(credentials),
+        None => {
+            let credentials = Credentials::generate();
+            save(path, &credentials)?;
+            Ok(credentials)
+        }
+    }
+}
+
+/// Replaces the saved credentials with new ones.
+pub fn reset(path: &Path) -> io::Result<Credentials> {
+    let credentials = Credentials::generate();
+    save(path, &credentials)?;
+    Ok(credentials)
+}
+
+fn save(path: &Path, credentials: &Credentials) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let content = format!(
+        "node_id={}\npassword={}\n",
+        credentials.node_id, credentials.password
+    );
+
+    // Written aside and renamed so a crash never leaves half a file behind
+    let tmp = path.with_extension("tmp");
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    io::Write::write_all(&mut options.open(&tmp)?, content.as_bytes())?;
+    fs::rename(tmp, path)
+}
@@ -0,0 +1,321 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use clap::parser::ValueSource;
+use clap::{ArgAction, ArgMatches, Command};
+
+use crate::gen_credentials::{generate_node_id, generate_secure_password};
+
+// Where the compose service keeps its state and mounts TLS material
+const CONTAINER_STATE_DIR: &str = "/state";
+const CONTAINER_TLS_DIR: &str = "/tls";
+// Where the systemd unit keeps its state, from `StateDirectory=docklord`
+const SYSTEMD_STATE_DIR: &str = "/var/lib/docklord";
+const DEFAULT_SECRETS_DIR: &str = "/run/docklord/secrets";
+const DEFAULT_DOCKER_SOCKET: &str = "/var/run/docker.sock";
+
+// Flags naming a file the process reads, mounted read-only into the container
+const FILE_FLAGS: &[&str] = &[
+    "grpc-tls-cert",
+    "grpc-tls-key",
+    "grpc-tls-ca",
+    "docker-tls-ca",
+    "docker-tls-cert",
+    "docker-tls-key",
+];
+// Flags the generator sets itself
+const OWN_FLAGS: &[&str] = &["type", "state-dir", "api-port", "grpc-port"];
+
+/// One command line flag as given, `value` is `None` for switches.
+struct Flag {
+    name: String,
+    value: Option<String>,
+}
+
+/// What to deploy: the launch type and the flags it was started with.
+pub struct Setup {
+    mode: String,
+    flags: Vec<Flag>,
+    api_port: u16,
+    grpc_port: u16,
+    state_dir: Option<PathBuf>,
+}
+
+impl Setup {
+    /// Takes the flags given on the command line, not those from the
+    /// environment. A node without credentials gets new ones here, so it
+    /// keeps them across restarts.
+    pub fn from_matches(
+        command: &Command,
+        matches: &ArgMatches,
+        api_port: u16,
+        grpc_port: u16,
+        state_dir: Option<PathBuf>,
+    ) -> Result<Self, String> {
+        let mut flags = Vec::new();
+        for arg in command.get_arguments() {
+            let (Some(name), id) = (arg.get_long(), arg.get_id().as_str()) else {
+                continue;
+            };
+            if OWN_FLAGS.contains(&name)
+                || matches.value_source(id) != Some(ValueSource::CommandLine)
+            {
+                continue;
+            }
+            if matches!(arg.get_action(), ArgAction::SetTrue) {
+                flags.push(Flag {
+                    name: name.to_string(),
+                    value: None,
+                });
+                continue;
+            }
+            for value in matches.get_raw(id).into_iter().flatten() {
+                flags.push(Flag {
+                    name: name.to_string(),
+                    value: Some(value.to_string_lossy().into_owned()),
+                });
+            }
+        }
+
+        let mode = matches
+            .get_one::<String>("mode")
+            .cloned()
+            .unwrap_or_else(|| "self-hosted".to_string());
+        let mut setup = Self {
+            mode,
+            flags,
+            api_port,
+            grpc_port,
+            state_dir,
+        };
+        if setup.mode == "node" {
+            if setup.flag("coordinator-addr").is_none() {
+                return Err("A node needs --coordinator-addr to know where to connect".into());
+            }
+            if setup.flag("node-id").is_none() {
+                setup.push("node-id", generate_node_id());
+            }
+            if setup.flag("password").is_none() {
+                setup.push("password", generate_secure_password());
+            }
+        }
+        Ok(setup)
+    }
+
+    fn flag(&self, name: &str) -> Option<&str> {
+        self.flags
+            .iter()
+            .find(|flag| flag.name == name)
+            .and_then(|flag| flag.value.as_deref())
+    }
+
+    fn push(&mut self, name: &str, value: String) {
+        self.flags.push(Flag {
+            name: name.to_string(),
+            value: Some(value),
+        });
+    }
+
+    fn runs_node(&self) -> bool {
+        self.mode != "coordinator"
+    }
+
+    fn runs_coordinator(&self) -> bool {
+        self.mode != "node"
+    }
+
+    // Ports the host publishes
+    fn published_ports(&self) -> Vec<u16> {
+        match self.mode.as_str() {
+            "coordinator" => vec![self.api_port, self.grpc_port],
+            "self-hosted" if self.flags.iter().any(|flag| flag.name == "expose-grpc") => {
+                vec![self.api_port, self.grpc_port]
+            }
+            "self-hosted" => vec![self.api_port],
+            _ => Vec::new(),
+        }
+    }
+
+    // Flags for the process, with `rewrite` mapping the file flags' paths
+    fn args(&self, state_dir: &str, rewrite: impl Fn(&Flag) -> String) -> Vec<String> {
+        let mut args = vec!["--type".to_string(), self.mode.clone()];
+        if self.runs_coordinator() {
+            args.extend([
+                "--api-port".to_string(),
+                self.api_port.to_string(),
+                "--grpc-port".to_string(),
+                self.grpc_port.to_string(),
+                "--state-dir".to_string(),
+                state_dir.to_string(),
+            ]);
+        }
+        for flag in &self.flags {
+            args.push(format!("--{}", flag.name));
+            if flag.value.is_some() {
+                args.push(rewrite(flag));
+            }
+        }
+        args
+    }
+
+    // The node's Docker socket, when it talks to a local daemon
+    fn docker_socket(&self) -> Option<String> {
+        match self.flag("docker-host") {
+            Some(host) => host.strip_prefix("unix://").map(str::to_string),
+            None => Some(DEFAULT_DOCKER_SOCKET.to_string()),
+        }
+    }
+}
+
+/// A docker-compose.yml with one service running the setup from `image`.
+/// State lives in the configured directory, else in a named volume, and TLS
+/// files are mounted read-only.
+pub fn compose(setup: &Setup, image: &str) -> io::Result<String> {
+    let mut volumes = Vec::new();
+    let mut tls_files = Vec::new();
+    for flag in &setup.flags {
+        if let (true, Some(path)) = (FILE_FLAGS.contains(&flag.name.as_str()), &flag.value) {
+            let host = std::path::absolute(Path::new(path))?;
+            volumes.push(format!("{}:{}:ro", host.display(), tls_path(&flag.name)));
+            tls_files.push(flag.name.clone());
+        }
+    }
+    let args = setup.args(CONTAINER_STATE_DIR, |flag| {
+        if tls_files.contains(&flag.name) {
+            tls_path(&flag.name)
+        } else {
+            flag.value.clone().unwrap_or_default()
+        }
+    });
+    let named_volume = setup.runs_coordinator() && setup.state_dir.is_none();
+    if setup.runs_coordinator() {
+        let source = match &setup.state_dir {
+            Some(dir) => std::path::absolute(dir)?.display().to_string(),
+            None => "docklord-state".to_string(),
+        };
+        volumes.push(format!("{}:{}", source, CONTAINER_STATE_DIR));
+    }
+    if setup.runs_node() {
+        if let Some(socket) = setup.docker_socket() {
+            volumes.push(format!("{0}:{0}", socket));
+        }
+        // Docker resolves the secret mounts on the host, so the paths must match
+        volumes.push(format!(
+            "{0}:{0}",
+            setup.flag("secrets-dir").unwrap_or(DEFAULT_SECRETS_DIR)
+        ));
+    }
+
+    let service = format!("docklord-{}", setup.mode);
+    let mut yaml = String::new();
+    yaml.push_str("# Generated by `docklord generate compose`\n");
+    yaml.push_str("services:\n");
+    yaml.push_str(&format!("  {}:\n", service));
+    yaml.push_str(&format!("    container_name: {}\n", service));
+    yaml.push_str(&format!("    image: {}\n", yaml_string(image)));
+    yaml.push_str("    restart: unless-stopped\n");
+    let quoted: Vec<String> = args.iter().map(|arg| yaml_string(arg)).collect();
+    yaml.push_str(&format!("    command: [{}]\n", quoted.join(", ")));
+    yaml.push_str("    environment:\n      - RUST_LOG=info\n");
+    let ports = setup.published_ports();
+    if !ports.is_empty() {
+        yaml.push_str("    ports:\n");
+        for port in ports {
+            yaml.push_str(&format!("      - \"{0}:{0}\"\n", port));
+        }
+    }
+    yaml.push_str("    volumes:\n");
+    for volume in &volumes {
+        yaml.push_str(&format!("      - {}\n", yaml_string(volume)));
+    }
+    if named_volume {
+        yaml.push_str("\nvolumes:\n  docklord-state:\n");
+    }
+    Ok(yaml)
+}
+
+/// A systemd unit running the setup from `binary`. State goes to the
+/// configured directory, else to the unit's `StateDirectory`.
+pub fn systemd(setup: &Setup, binary: &Path) -> io::Result<String> {
+    let state_dir = match &setup.state_dir {
+        Some(dir) => std::path::absolute(dir)?,
+        None => PathBuf::from(SYSTEMD_STATE_DIR),
+    };
+    let mut files = Vec::new();
+    for flag in &setup.flags {
+        if let (true, Some(path)) = (FILE_FLAGS.contains(&flag.name.as_str()), &flag.value) {
+            files.push((flag.name.clone(), std::path::absolute(Path::new(path))?));
+        }
+    }
+    let args = setup.args(&state_dir.to_string_lossy(), |flag| {
+        match files.iter().find(|(name, _)| *name == flag.name) {
+            Some((_, path)) => path.to_string_lossy().into_owned(),
+            None => flag.value.clone().unwrap_or_default(),
+        }
+    });
+    let mut command = vec![systemd_word(
+        &std::path::absolute(binary)?.to_string_lossy(),
+    )];
+    command.extend(args.iter().map(|arg| systemd_word(arg)));
+
+    let after = if setup.runs_node() {
+        "network-online.target docker.service"
+    } else {
+        "network-online.target"
+    };
+    let mut unit = String::new();
+    unit.push_str("# Generated by `docklord generate systemd`\n");
+    unit.push_str("[Unit]\n");
+    unit.push_str(&format!("Description=Docklord {}\n", setup.mode));
+    unit.push_str(&format!("After={}\n", after));
+    unit.push_str("Wants=network-online.target\n");
+    if setup.runs_node() {
+        unit.push_str("Requires=docker.service\n");
+    }
+    unit.push_str("\n[Service]\n");
+    unit.push_str(&format!("ExecStart={}\n", command.join(" ")));
+    unit.push_str("Environment=RUST_LOG=info\n");
+    if setup.runs_coordinator() && setup.state_dir.is_none() {
+        unit.push_str("StateDirectory=docklord\n");
+        unit.push_str("StateDirectoryMode=0700\n");
+    }
+    unit.push_str("Restart=on-failure\n");
+    unit.push_str("RestartSec=5\n");
+    unit.push_str("\n[Install]\n");
+    unit.push_str("WantedBy=multi-user.target\n");
+    Ok(unit)
+}
+
+fn tls_path(flag: &str) -> String {
+    format!("{}/{}.pem", CONTAINER_TLS_DIR, flag)
+}
+
+// Double-quoted so any value is a plain string; `$` would be interpolated by compose
+fn yaml_string(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '$' => quoted.push_str("$$"),
+            '\n' => quoted.push_str("\\n"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// Quoted when needed; `$` and `%` would be expanded by systemd
+fn systemd_word(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('$', "$$")
+        .replace('%', "%%");
+    if !value.is_empty() && !value.contains(|c: char| c.is_whitespace() || c == '"' || c == '\'') {
+        escaped
+    } else {
+        format!("\"{}\"", escaped)
+    }
+}
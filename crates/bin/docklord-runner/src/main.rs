@@ -1,15 +1,21 @@
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
 use std::env;
-use tracing::{error, info};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
 
+mod credentials;
 mod gen_credentials;
+mod generate;
 use gen_credentials::{generate_node_id, generate_secure_password};
 
 #[derive(Parser)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, subcommand_negates_reqs = true)]
 struct Cli {
-    #[arg(long = "type", value_parser = ["node", "coordinator", "self-hosted"], help = "Launch type: node, coordinator, or self-hosted (node with built-in coordinator)")]
-    mode: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[arg(long = "type", required = true, value_parser = ["node", "coordinator", "self-hosted"], help = "Launch type: node, coordinator, or self-hosted (node with built-in coordinator)")]
+    mode: Option<String>,
 
     // Coordinator options
     #[arg(long, help = "gRPC port for (node-coordinator communication)")]
@@ -18,15 +24,501 @@ struct Cli {
     #[arg(long, help = "API port for coordinator (user API)")]
     api_port: Option<u16>,
 
+    #[arg(
+        long,
+        help = "Admin token for cluster-wide API routes (disabled if not specified)"
+    )]
+    admin_token: Option<String>,
+
+    #[arg(
+        long,
+        global = true,
+        help = "Directory for persisted coordinator state (default: docklord-state) and self-hosted credentials (default: $XDG_STATE_HOME/docklord)"
+    )]
+    state_dir: Option<std::path::PathBuf>,
+
+    #[arg(long, help = "Record interactive exec/attach sessions for audit")]
+    record_sessions: bool,
+
+    #[arg(
+        long,
+        help = "HTTP policy endpoint (e.g. OPA) consulted before each node command"
+    )]
+    authz_webhook: Option<String>,
+
+    #[arg(long, help = "Allow commands when the policy endpoint is unreachable")]
+    authz_fail_open: bool,
+
+    #[arg(
+        long,
+        help = "HTTP endpoint receiving container health transitions (healthy/unhealthy)"
+    )]
+    health_webhook: Option<String>,
+
+    #[arg(
+        long,
+        help = "Comma-separated HTTP endpoints receiving every container event and node connect/disconnect"
+    )]
+    event_webhook: Option<String>,
+
+    #[arg(
+        long,
+        help = "Syslog server receiving every event, as udp://host:port or tcp://host:port"
+    )]
+    event_syslog: Option<String>,
+
+    #[arg(
+        long,
+        help = "Serve event counters, node connection gauges and node latency histograms for Prometheus on /metrics"
+    )]
+    prometheus_metrics: bool,
+
+    #[arg(
+        long,
+        help = "Largest gRPC message between coordinator and node, in bytes (default: 16 MiB)"
+    )]
+    grpc_max_message_size: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Compress node responses over 64 KiB inside the protocol: none, gzip or zstd (default: none). For proxies that do not pass gRPC compression; needs an up to date coordinator"
+    )]
+    compress_responses: Option<String>,
+
+    #[arg(
+        long,
+        help = "PEM certificate chain for serving gRPC over TLS (needs --grpc-tls-key)"
+    )]
+    grpc_tls_cert: Option<std::path::PathBuf>,
+
+    #[arg(long, help = "PEM private key for --grpc-tls-cert")]
+    grpc_tls_key: Option<std::path::PathBuf>,
+
+    #[arg(
+        long,
+        help = "REST requests per second allowed per node, 0 to disable (default: 50)"
+    )]
+    node_rate_limit: Option<f64>,
+
+    #[arg(
+        long,
+        help = "Requests a node may burst above its rate (default: twice the rate)"
+    )]
+    node_rate_burst: Option<f64>,
+
+    #[arg(
+        long,
+        help = "REST requests per second allowed per client IP address, 0 to disable (default: 100)"
+    )]
+    client_rate_limit: Option<f64>,
+
+    #[arg(
+        long,
+        help = "Requests a client IP address may burst above its rate (default: twice the rate)"
+    )]
+    client_rate_burst: Option<f64>,
+
+    #[arg(
+        long,
+        help = "Requests that may wait for node replies at once, 0 to disable (default: 10000)"
+    )]
+    max_pending_requests: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Requests that may wait for one node's replies at once, 0 to disable (default: 1000)"
+    )]
+    max_pending_per_node: Option<usize>,
+
+    #[arg(
+        long,
+        help = "WebSocket connections open at once, 0 to disable (default: 2000)"
+    )]
+    max_ws_connections: Option<usize>,
+
+    #[arg(
+        long,
+        help = "WebSocket connections one credential may keep open, 0 to disable (default: 50)"
+    )]
+    max_ws_per_credential: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Milliseconds status responses are served from the cache, 0 to disable (default: 1000)"
+    )]
+    status_cache_ttl_ms: Option<u64>,
+
+    #[arg(
+        long,
+        help = "Milliseconds an expired status response is still served while it is refreshed (default: 10000)"
+    )]
+    status_cache_stale_ms: Option<u64>,
+
+    #[arg(
+        long,
+        help = "Bind with SO_REUSEPORT so a new coordinator can start on the same ports; SIGUSR2 then hands over"
+    )]
+    reuse_port: bool,
+
+    #[arg(
+        long,
+        help = "Seconds a handed-over coordinator waits for in-flight requests before exiting (default: 30)"
+    )]
+    drain_timeout: Option<u64>,
+
+    #[arg(
+        long,
+        help = "Comma-separated node ids (or *) whose destructive actions need approval by a second credential"
+    )]
+    approval_nodes: Option<String>,
+
+    #[arg(
+        long,
+        help = "Seconds a destructive action waits for approval before it is dropped (default: 900)"
+    )]
+    approval_ttl: Option<u64>,
+
+    #[arg(
+        long,
+        help = "Write an HTTP access log in this format: common or json (default: off)"
+    )]
+    access_log: Option<String>,
+
+    #[arg(long, help = "File the access log is appended to (default: stdout)")]
+    access_log_file: Option<std::path::PathBuf>,
+
+    #[arg(
+        long,
+        help = "Share of successful requests in the access log, 0 to 1; errors are always logged (default: 1)"
+    )]
+    access_log_sample: Option<f64>,
+
+    #[arg(
+        long,
+        help = "Serve several tenants: requests need a tenant API key and only reach that tenant's nodes"
+    )]
+    multi_tenant: bool,
+
+    #[arg(
+        long,
+        help = "File with the base64 key encrypting stored secrets, outside the state directory (SECRETS_KEY takes precedence)"
+    )]
+    secrets_key_file: Option<std::path::PathBuf>,
+
+    #[arg(
+        long,
+        help = "Refuse node_id/password query parameters; clients must use bearer tokens from /api/auth/token"
+    )]
+    disable_query_auth: bool,
+
+    #[arg(
+        long,
+        help = "Leave Secure off the browser session cookies, for a coordinator served over plain HTTP"
+    )]
+    insecure_session_cookies: bool,
+
+    #[arg(
+        long,
+        help = "In self-hosted mode, accept node connections from other hosts on the gRPC port (default: localhost only)"
+    )]
+    expose_grpc: bool,
+
+    #[arg(
+        long,
+        help = "In self-hosted mode, generate new credentials on every start instead of saving them"
+    )]
+    ephemeral_credentials: bool,
+
     // Node options
-    #[arg(long, help = "Coordinator gRPC address")]
+    #[arg(
+        long,
+        help = "Coordinator gRPC address, or comma-separated addresses of a primary and its standbys in failover order"
+    )]
     coordinator_addr: Option<String>,
 
+    #[arg(
+        long,
+        help = "PEM CA certificate the node trusts for an https:// coordinator (default: system roots)"
+    )]
+    grpc_tls_ca: Option<std::path::PathBuf>,
+
+    #[arg(
+        long,
+        help = "Name the coordinator certificate must be valid for (default: the address host)"
+    )]
+    grpc_tls_domain: Option<String>,
+
     #[arg(long, help = "Node ID (auto-generated if not specified)")]
     node_id: Option<String>,
 
     #[arg(long, help = "Node password (auto-generated if not specified)")]
     password: Option<String>,
+
+    #[arg(
+        long,
+        help = "Docker API calls the node runs at once; more wait in line (default: 8)"
+    )]
+    docker_max_concurrency: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Seconds a Docker call waits for a free slot before it fails (default: 30)"
+    )]
+    docker_queue_timeout: Option<u64>,
+
+    #[arg(
+        long,
+        help = "Docker API of the node: unix:///path, tcp://host[:port], https://host[:port] or ssh://[user@]host[:port] (default: DOCKER_HOST, else the local socket)"
+    )]
+    docker_host: Option<String>,
+
+    #[arg(
+        long,
+        help = "PEM CA certificate the Docker daemon's TLS certificate is checked against; serves tcp:// over HTTPS"
+    )]
+    docker_tls_ca: Option<std::path::PathBuf>,
+
+    #[arg(
+        long,
+        help = "PEM client certificate for a Docker daemon run with --tlsverify"
+    )]
+    docker_tls_cert: Option<std::path::PathBuf>,
+
+    #[arg(long, help = "PEM private key for --docker-tls-cert")]
+    docker_tls_key: Option<std::path::PathBuf>,
+
+    #[arg(
+        long,
+        help = "Host directory for secret files mounted into containers (default: /run/docklord/secrets)"
+    )]
+    secrets_dir: Option<std::path::PathBuf>,
+
+    #[arg(
+        long = "host-script",
+        value_name = "NAME=PATH",
+        help = "Host script the coordinator may run by name; repeat for more (none by default)"
+    )]
+    host_scripts: Vec<String>,
+
+    #[arg(
+        long,
+        help = "Seconds a host script may run before it is killed (default: 300, at most 600)"
+    )]
+    host_script_timeout: Option<u64>,
+
+    #[arg(
+        long,
+        help = "Comma-separated Docker container actions that refresh the node's container lists (default: start,stop,die,destroy,create)"
+    )]
+    watch_actions: Option<String>,
+
+    #[arg(
+        long,
+        help = "Milliseconds of quiet after container events before the lists are refreshed (default: 500)"
+    )]
+    event_debounce_ms: Option<u64>,
+
+    #[arg(
+        long,
+        help = "Fewest milliseconds between event-triggered container list refreshes (default: 1000, 0 = no limit)"
+    )]
+    min_refresh_interval_ms: Option<u64>,
+
+    #[arg(
+        long,
+        help = "Comma-separated container names whose recent log lines the node keeps in memory for instant tails"
+    )]
+    log_buffer_containers: Option<String>,
+
+    #[arg(
+        long,
+        help = "Log lines kept per buffered container (default: 1000, at most 100000)"
+    )]
+    log_buffer_lines: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Seconds between desired-state reconcile passes on the coordinator (default: 30)"
+    )]
+    reconcile_interval: Option<u64>,
+
+    #[arg(
+        long,
+        help = "Git repository with the desired containers; enables GitOps on the coordinator"
+    )]
+    gitops_repo: Option<String>,
+
+    #[arg(long, help = "Branch of the GitOps repository (default: main)")]
+    gitops_branch: Option<String>,
+
+    #[arg(
+        long,
+        help = "Directory inside the GitOps repository holding the definitions (default: the root)"
+    )]
+    gitops_path: Option<String>,
+
+    #[arg(long, help = "Seconds between GitOps syncs (default: 60)")]
+    gitops_interval: Option<u64>,
+
+    #[arg(
+        long,
+        help = "GitOps mode: dry-run, manual (apply after approval) or auto (default: manual)"
+    )]
+    gitops_mode: Option<String>,
+
+    #[arg(long, help = "Generate daily container reports on the coordinator")]
+    reports: bool,
+
+    #[arg(long, help = "UTC time (HH:MM) of the daily report (default: 00:05)")]
+    report_time: Option<String>,
+
+    #[arg(
+        long,
+        help = "Seconds between resource usage samples for report peaks (default: 300)"
+    )]
+    report_sample_interval: Option<u64>,
+
+    #[arg(long, help = "http:// URL the daily report JSON is POSTed to")]
+    report_webhook: Option<String>,
+
+    #[arg(
+        long,
+        help = "host:port of an SMTP relay (no TLS or authentication) to mail reports through"
+    )]
+    report_smtp: Option<String>,
+
+    #[arg(long, help = "Sender address of report emails")]
+    report_email_from: Option<String>,
+
+    #[arg(long, help = "Comma-separated recipients of report emails")]
+    report_email_to: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Manage the saved self-hosted node credentials
+    Credentials {
+        #[command(subcommand)]
+        action: CredentialsAction,
+    },
+    /// Print a deployment of the launch type and flags given before the subcommand
+    Generate {
+        #[command(subcommand)]
+        format: GenerateFormat,
+    },
+}
+
+#[derive(Subcommand)]
+enum CredentialsAction {
+    /// Print the saved node_id and password
+    Show,
+    /// Replace the saved credentials with new ones; takes effect on the next start
+    Reset,
+}
+
+#[derive(Subcommand)]
+enum GenerateFormat {
+    /// A docker-compose.yml with volumes for the state and the TLS files
+    Compose {
+        #[arg(long, default_value = "docklord", help = "Image the service runs")]
+        image: String,
+        #[arg(long, help = "File to write instead of printing")]
+        output: Option<std::path::PathBuf>,
+    },
+    /// A systemd unit running this binary
+    Systemd {
+        #[arg(long, help = "Binary the unit runs (default: this one)")]
+        binary: Option<std::path::PathBuf>,
+        #[arg(long, help = "File to write instead of printing")]
+        output: Option<std::path::PathBuf>,
+    },
+}
+
+fn generate_command(
+    format: GenerateFormat,
+    setup: generate::Setup,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let (content, output) = match format {
+        GenerateFormat::Compose { image, output } => (generate::compose(&setup, &image)?, output),
+        GenerateFormat::Systemd { binary, output } => {
+            let binary = match binary {
+                Some(binary) => binary,
+                None => env::current_exe()?,
+            };
+            (generate::systemd(&setup, &binary)?, output)
+        }
+    };
+    match output {
+        Some(path) => {
+            std::fs::write(&path, content)?;
+            println!("Written to {}", path.display());
+        }
+        None => print!("{}", content),
+    }
+    Ok(())
+}
+
+fn credentials_command(
+    action: CredentialsAction,
+    state_dir: Option<&std::path::Path>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let path = credentials::credentials_path(state_dir);
+    let saved = match action {
+        CredentialsAction::Show => match credentials::load(&path)? {
+            Some(saved) => saved,
+            None => {
+                return Err(format!(
+                    "No credentials saved at {}; they are created on the first self-hosted start",
+                    path.display()
+                )
+                .into());
+            }
+        },
+        CredentialsAction::Reset => {
+            let saved = credentials::reset(&path)?;
+            println!("New credentials saved, restart the self-hosted node to use them.");
+            saved
+        }
+    };
+    println!("file:      {}", path.display());
+    println!("node_id:   {}", saved.node_id);
+    println!("password:  {}", saved.password);
+    Ok(())
+}
+
+/// Cancelled on `SIGTERM` or Ctrl+C, which starts a graceful shutdown; a
+/// second signal exits at once.
+fn shutdown_on_signal() -> CancellationToken {
+    let shutdown = CancellationToken::new();
+    let token = shutdown.clone();
+    tokio::spawn(async move {
+        wait_for_signal().await;
+        info!("Shutting down gracefully; send the signal again to exit at once");
+        token.cancel();
+        wait_for_signal().await;
+        warn!("Exiting without finishing the shutdown");
+        std::process::exit(1);
+    });
+    shutdown
+}
+
+async fn wait_for_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = terminate.recv() => {}
+                    _ = tokio::signal::ctrl_c() => {}
+                }
+                return;
+            }
+            Err(e) => warn!("Cannot listen for SIGTERM: {}", e),
+        }
+    }
+    let _ = tokio::signal::ctrl_c().await;
 }
 
 fn get_port_from_env_or_default(env_var: &str, default: u16) -> u16 {
@@ -40,6 +532,41 @@ fn get_coordinator_addr_from_env_or_default() -> String {
     env::var("COORDINATOR_ADDR").unwrap_or_else(|_| "http://localhost:50051".to_string())
 }
 
+/// The node's Docker endpoint from the flags or `DOCKER_HOST`, `None` to keep
+/// the local defaults. Like the docker CLI, `DOCKER_TLS_VERIFY` takes the
+/// certificates from `DOCKER_CERT_PATH` when none are given.
+fn docker_endpoint(
+    host: Option<String>,
+    ca: Option<std::path::PathBuf>,
+    cert: Option<std::path::PathBuf>,
+    key: Option<std::path::PathBuf>,
+) -> Result<Option<node_runner::DockerEndpoint>, String> {
+    let env_path = |name: &str| env::var(name).ok().map(std::path::PathBuf::from);
+    let mut ca = ca.or_else(|| env_path("DOCKER_TLS_CA"));
+    let mut cert = cert.or_else(|| env_path("DOCKER_TLS_CERT"));
+    let mut key = key.or_else(|| env_path("DOCKER_TLS_KEY"));
+    if ca.is_none()
+        && env::var("DOCKER_TLS_VERIFY").is_ok_and(|v| !v.is_empty() && v != "0")
+        && let Some(dir) = env_path("DOCKER_CERT_PATH")
+    {
+        ca = Some(dir.join("ca.pem"));
+        cert = cert.or_else(|| Some(dir.join("cert.pem")));
+        key = key.or_else(|| Some(dir.join("key.pem")));
+    }
+    let tls = match ca {
+        Some(ca) => Some(node_runner::DockerTls::new(ca, cert, key)?),
+        None if cert.is_some() || key.is_some() => {
+            return Err("--docker-tls-cert and --docker-tls-key need --docker-tls-ca".to_string());
+        }
+        None => None,
+    };
+    match host.or_else(|| env::var("DOCKER_HOST").ok()) {
+        Some(host) => node_runner::DockerEndpoint::parse(&host, tls).map(Some),
+        None if tls.is_some() => Err("--docker-tls-ca needs --docker-host".to_string()),
+        None => Ok(None),
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     tracing_subscriber::fmt()
@@ -47,7 +574,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         .with_target(false)
         .init();
 
-    let cli = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+    // Only an explicitly configured directory; each state has its own default
+    let state_dir = cli
+        .state_dir
+        .or_else(|| env::var("STATE_DIR").ok().map(Into::into));
+
+    if let Some(Command::Credentials { action }) = cli.command {
+        return credentials_command(action, state_dir.as_deref());
+    }
 
     // Get ports from environment variables or CLI args or defaults
     let grpc_port = cli
@@ -57,74 +594,617 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         .api_port
         .unwrap_or_else(|| get_port_from_env_or_default("API_PORT", 3000));
 
+    if let Some(Command::Generate { format }) = cli.command {
+        let setup = generate::Setup::from_matches(
+            &Cli::command(),
+            &matches,
+            api_port,
+            grpc_port,
+            state_dir,
+        )?;
+        return generate_command(format, setup);
+    }
+
     // Get coordinator address from environment or CLI args
     let coordinator_addr = cli
         .coordinator_addr
         .unwrap_or_else(get_coordinator_addr_from_env_or_default);
 
+    let max_message_size = cli.grpc_max_message_size.or_else(|| {
+        env::var("GRPC_MAX_MESSAGE_SIZE")
+            .ok()
+            .and_then(|s| s.parse().ok())
+    });
+
+    let grpc_tls_cert = cli
+        .grpc_tls_cert
+        .or_else(|| env::var("GRPC_TLS_CERT").ok().map(Into::into));
+    let grpc_tls_key = cli
+        .grpc_tls_key
+        .or_else(|| env::var("GRPC_TLS_KEY").ok().map(Into::into));
+    let grpc_server_tls = match (grpc_tls_cert, grpc_tls_key) {
+        (Some(cert_path), Some(key_path)) => Some(coordinator_runner::GrpcServerTls {
+            cert_path,
+            key_path,
+        }),
+        (None, None) => None,
+        _ => return Err("--grpc-tls-cert and --grpc-tls-key must be set together".into()),
+    };
+    let grpc_client_tls = node_runner::GrpcClientTls {
+        ca_path: cli
+            .grpc_tls_ca
+            .or_else(|| env::var("GRPC_TLS_CA").ok().map(Into::into)),
+        domain: cli
+            .grpc_tls_domain
+            .or_else(|| env::var("GRPC_TLS_DOMAIN").ok()),
+    };
+
+    let node_rate_limit = cli
+        .node_rate_limit
+        .or_else(|| {
+            env::var("NODE_RATE_LIMIT")
+                .ok()
+                .and_then(|s| s.parse().ok())
+        })
+        .unwrap_or(coordinator_runner::RateLimit::NODE.per_second);
+    let node_rate_burst = cli
+        .node_rate_burst
+        .or_else(|| {
+            env::var("NODE_RATE_BURST")
+                .ok()
+                .and_then(|s| s.parse().ok())
+        })
+        .unwrap_or(node_rate_limit * 2.0);
+    let client_rate_limit = cli
+        .client_rate_limit
+        .or_else(|| {
+            env::var("CLIENT_RATE_LIMIT")
+                .ok()
+                .and_then(|s| s.parse().ok())
+        })
+        .unwrap_or(coordinator_runner::RateLimit::CLIENT.per_second);
+    let client_rate_burst = cli
+        .client_rate_burst
+        .or_else(|| {
+            env::var("CLIENT_RATE_BURST")
+                .ok()
+                .and_then(|s| s.parse().ok())
+        })
+        .unwrap_or(client_rate_limit * 2.0);
+    let pending_limit = coordinator_runner::PendingLimit {
+        total: cli
+            .max_pending_requests
+            .or_else(|| {
+                env::var("MAX_PENDING_REQUESTS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+            })
+            .unwrap_or(coordinator_runner::PendingLimit::default().total),
+        per_node: cli
+            .max_pending_per_node
+            .or_else(|| {
+                env::var("MAX_PENDING_PER_NODE")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+            })
+            .unwrap_or(coordinator_runner::PendingLimit::default().per_node),
+    };
+    let ws_limit = coordinator_runner::WsLimit {
+        total: cli
+            .max_ws_connections
+            .or_else(|| {
+                env::var("MAX_WS_CONNECTIONS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+            })
+            .unwrap_or(coordinator_runner::WsLimit::default().total),
+        per_credential: cli
+            .max_ws_per_credential
+            .or_else(|| {
+                env::var("MAX_WS_PER_CREDENTIAL")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+            })
+            .unwrap_or(coordinator_runner::WsLimit::default().per_credential),
+    };
+    let response_cache = coordinator_runner::ResponseCacheConfig {
+        ttl: cli
+            .status_cache_ttl_ms
+            .or_else(|| {
+                env::var("STATUS_CACHE_TTL_MS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+            })
+            .map(std::time::Duration::from_millis)
+            .unwrap_or(coordinator_runner::ResponseCacheConfig::default().ttl),
+        stale: cli
+            .status_cache_stale_ms
+            .or_else(|| {
+                env::var("STATUS_CACHE_STALE_MS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+            })
+            .map(std::time::Duration::from_millis)
+            .unwrap_or(coordinator_runner::ResponseCacheConfig::default().stale),
+    };
+
+    let access_log = match cli.access_log.or_else(|| env::var("ACCESS_LOG").ok()) {
+        Some(format) if !format.is_empty() => {
+            let sample_rate = cli
+                .access_log_sample
+                .or_else(|| {
+                    env::var("ACCESS_LOG_SAMPLE")
+                        .ok()
+                        .and_then(|s| s.parse().ok())
+                })
+                .unwrap_or(1.0);
+            if !(0.0..=1.0).contains(&sample_rate) {
+                return Err("--access-log-sample must be between 0 and 1".into());
+            }
+            Some(coordinator_runner::AccessLogConfig {
+                format: format.parse::<coordinator_runner::AccessLogFormat>()?,
+                path: cli.access_log_file.or_else(|| {
+                    env::var("ACCESS_LOG_FILE")
+                        .ok()
+                        .map(std::path::PathBuf::from)
+                }),
+                sample_rate,
+            })
+        }
+        _ => None,
+    };
+
+    let approval_nodes: Vec<String> = cli
+        .approval_nodes
+        .or_else(|| env::var("APPROVAL_NODES").ok())
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|node| !node.is_empty())
+        .map(str::to_string)
+        .collect();
+    let approval_policy =
+        (!approval_nodes.is_empty()).then(|| coordinator_runner::ApprovalPolicy {
+            nodes: approval_nodes,
+            ttl: std::time::Duration::from_secs(
+                cli.approval_ttl
+                    .or_else(|| env::var("APPROVAL_TTL").ok().and_then(|s| s.parse().ok()))
+                    .unwrap_or(900)
+                    .max(1),
+            ),
+        });
+
+    let mut exporters = coordinator_runner::ExporterRegistry::new();
+    let event_webhooks = cli
+        .event_webhook
+        .or_else(|| env::var("EVENT_WEBHOOK").ok())
+        .unwrap_or_default();
+    for url in event_webhooks.split(',').map(str::trim) {
+        if !url.is_empty() {
+            exporters.register(coordinator_runner::WebhookExporter::new(url)?);
+        }
+    }
+    if let Some(target) = cli
+        .event_syslog
+        .or_else(|| env::var("EVENT_SYSLOG").ok())
+        .filter(|target| !target.is_empty())
+    {
+        exporters.register(coordinator_runner::SyslogExporter::new(&target)?);
+    }
+    if cli.prometheus_metrics
+        || env::var("PROMETHEUS_METRICS").is_ok_and(|v| v == "1" || v == "true")
+    {
+        exporters.register(coordinator_runner::PrometheusExporter::default());
+    }
+
+    let gitops = match cli.gitops_repo.or_else(|| env::var("GITOPS_REPO").ok()) {
+        Some(repo) if !repo.is_empty() => {
+            let mode = cli
+                .gitops_mode
+                .or_else(|| env::var("GITOPS_MODE").ok())
+                .map(|mode| mode.parse::<coordinator_runner::GitOpsMode>())
+                .transpose()?
+                .unwrap_or(coordinator_runner::GitOpsMode::Manual);
+            let interval = cli
+                .gitops_interval
+                .or_else(|| {
+                    env::var("GITOPS_INTERVAL")
+                        .ok()
+                        .and_then(|s| s.parse().ok())
+                })
+                .unwrap_or(60)
+                .max(1);
+            Some(coordinator_runner::GitOpsConfig {
+                repo,
+                branch: cli
+                    .gitops_branch
+                    .or_else(|| env::var("GITOPS_BRANCH").ok())
+                    .unwrap_or_else(|| "main".to_string()),
+                path: cli
+                    .gitops_path
+                    .or_else(|| env::var("GITOPS_PATH").ok())
+                    .unwrap_or_default(),
+                interval: std::time::Duration::from_secs(interval),
+                mode,
+            })
+        }
+        _ => None,
+    };
+
+    let reports = if cli.reports || env::var("REPORTS").is_ok_and(|v| v == "1" || v == "true") {
+        let at = coordinator_runner::parse_report_time(
+            &cli.report_time
+                .or_else(|| env::var("REPORT_TIME").ok())
+                .unwrap_or_else(|| "00:05".to_string()),
+        )?;
+        let sample_interval = cli
+            .report_sample_interval
+            .or_else(|| {
+                env::var("REPORT_SAMPLE_INTERVAL")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+            })
+            .unwrap_or(300)
+            .max(10);
+        let email = cli
+            .report_smtp
+            .or_else(|| env::var("REPORT_SMTP").ok())
+            .filter(|smtp| !smtp.is_empty())
+            .map(|smtp| coordinator_runner::EmailConfig {
+                smtp,
+                from: cli
+                    .report_email_from
+                    .or_else(|| env::var("REPORT_EMAIL_FROM").ok())
+                    .unwrap_or_default(),
+                to: cli
+                    .report_email_to
+                    .or_else(|| env::var("REPORT_EMAIL_TO").ok())
+                    .unwrap_or_default()
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|to| !to.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+            });
+        Some(coordinator_runner::ReportConfig {
+            at,
+            sample_interval: std::time::Duration::from_secs(sample_interval),
+            webhook: cli
+                .report_webhook
+                .or_else(|| env::var("REPORT_WEBHOOK").ok())
+                .filter(|url| !url.is_empty()),
+            email,
+        })
+    } else {
+        None
+    };
+
+    let shutdown = shutdown_on_signal();
+    let coordinator_options = coordinator_runner::CoordinatorOptions {
+        admin_token: cli.admin_token.or_else(|| env::var("ADMIN_TOKEN").ok()),
+        state_dir: Some(state_dir.clone().unwrap_or_else(|| "docklord-state".into())),
+        record_sessions: cli.record_sessions
+            || env::var("RECORD_SESSIONS").is_ok_and(|v| v == "1" || v == "true"),
+        authz_webhook: cli.authz_webhook.or_else(|| env::var("AUTHZ_WEBHOOK").ok()),
+        authz_fail_open: cli.authz_fail_open
+            || env::var("AUTHZ_FAIL_OPEN").is_ok_and(|v| v == "1" || v == "true"),
+        health_webhook: cli
+            .health_webhook
+            .or_else(|| env::var("HEALTH_WEBHOOK").ok()),
+        exporters,
+        max_message_size,
+        grpc_tls: grpc_server_tls.clone(),
+        node_rate_limit: (node_rate_limit > 0.0).then_some(coordinator_runner::RateLimit {
+            per_second: node_rate_limit,
+            burst: node_rate_burst.max(1.0),
+        }),
+        client_rate_limit: (client_rate_limit > 0.0).then_some(coordinator_runner::RateLimit {
+            per_second: client_rate_limit,
+            burst: client_rate_burst.max(1.0),
+        }),
+        pending_limit,
+        ws_limit,
+        response_cache,
+        multi_tenant: cli.multi_tenant
+            || env::var("MULTI_TENANT").is_ok_and(|v| v == "1" || v == "true"),
+        secrets_key: env::var("SECRETS_KEY").ok(),
+        secrets_key_file: cli.secrets_key_file.or_else(|| {
+            env::var("SECRETS_KEY_FILE")
+                .ok()
+                .map(std::path::PathBuf::from)
+        }),
+        gitops,
+        reconcile_interval: cli.reconcile_interval.or_else(|| {
+            env::var("RECONCILE_INTERVAL")
+                .ok()
+                .and_then(|s| s.parse().ok())
+        }),
+        reports,
+        disable_query_auth: cli.disable_query_auth
+            || env::var("DISABLE_QUERY_AUTH").is_ok_and(|v| v == "1" || v == "true"),
+        insecure_session_cookies: cli.insecure_session_cookies
+            || env::var("INSECURE_SESSION_COOKIES").is_ok_and(|v| v == "1" || v == "true"),
+        reuse_port: cli.reuse_port || env::var("REUSE_PORT").is_ok_and(|v| v == "1" || v == "true"),
+        drain_timeout: cli
+            .drain_timeout
+            .or_else(|| env::var("DRAIN_TIMEOUT").ok().and_then(|s| s.parse().ok())),
+        access_log,
+        approval_policy,
+        shutdown: shutdown.clone(),
+    };
+    let max_message_size = max_message_size.unwrap_or(proto::DEFAULT_MAX_MESSAGE_SIZE);
+    let compression = match cli
+        .compress_responses
+        .or_else(|| env::var("COMPRESS_RESPONSES").ok())
+        .as_deref()
+    {
+        None | Some("none") => node_runner::Compression::None,
+        Some("gzip") => node_runner::Compression::Gzip,
+        Some("zstd") => node_runner::Compression::Zstd,
+        Some(other) => {
+            return Err(format!(
+                "Unknown --compress-responses '{}', expected none, gzip or zstd",
+                other
+            )
+            .into());
+        }
+    };
+
+    let mut docker_limits = node_runner::DockerLimits::default();
+    if let Some(max_concurrent) = cli.docker_max_concurrency.or_else(|| {
+        env::var("DOCKER_MAX_CONCURRENCY")
+            .ok()
+            .and_then(|s| s.parse().ok())
+    }) {
+        docker_limits.max_concurrent = max_concurrent;
+    }
+    if let Some(secs) = cli.docker_queue_timeout.or_else(|| {
+        env::var("DOCKER_QUEUE_TIMEOUT")
+            .ok()
+            .and_then(|s| s.parse().ok())
+    }) {
+        docker_limits.queue_timeout = std::time::Duration::from_secs(secs);
+    }
+    if cli.mode.as_deref() != Some("coordinator")
+        && let Some(endpoint) = docker_endpoint(
+            cli.docker_host,
+            cli.docker_tls_ca,
+            cli.docker_tls_cert,
+            cli.docker_tls_key,
+        )?
+    {
+        node_runner::configure_docker_endpoint(endpoint);
+    }
+    if let Some(dir) = cli
+        .secrets_dir
+        .or_else(|| env::var("SECRETS_DIR").ok().map(std::path::PathBuf::from))
+    {
+        node_runner::configure_secrets_dir(dir);
+    }
+    let mut host_scripts = node_runner::HostScripts::default();
+    let script_specs = if cli.host_scripts.is_empty() {
+        env::var("HOST_SCRIPTS")
+            .map(|specs| specs.split(',').map(String::from).collect())
+            .unwrap_or_default()
+    } else {
+        cli.host_scripts
+    };
+    for spec in script_specs.iter().filter(|spec| !spec.trim().is_empty()) {
+        host_scripts.add(spec)?;
+    }
+    if let Some(secs) = cli.host_script_timeout.or_else(|| {
+        env::var("HOST_SCRIPT_TIMEOUT")
+            .ok()
+            .and_then(|s| s.parse().ok())
+    }) {
+        host_scripts.timeout = std::time::Duration::from_secs(secs);
+    }
+    node_runner::configure_host_scripts(host_scripts);
+    let mut event_watch = node_runner::EventWatchConfig::default();
+    if let Some(actions) = cli.watch_actions.or_else(|| env::var("WATCH_ACTIONS").ok()) {
+        let actions: Vec<String> = actions
+            .split(',')
+            .map(|action| action.trim().to_string())
+            .filter(|action| !action.is_empty())
+            .collect();
+        if actions.is_empty() {
+            return Err("--watch-actions needs at least one action".into());
+        }
+        event_watch.actions = actions;
+    }
+    if let Some(ms) = cli.event_debounce_ms.or_else(|| {
+        env::var("EVENT_DEBOUNCE_MS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+    }) {
+        event_watch.debounce = std::time::Duration::from_millis(ms);
+    }
+    if let Some(ms) = cli.min_refresh_interval_ms.or_else(|| {
+        env::var("MIN_REFRESH_INTERVAL_MS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+    }) {
+        event_watch.min_refresh_interval = std::time::Duration::from_millis(ms);
+    }
+    node_runner::configure_event_watch(event_watch);
+    let mut log_buffer = node_runner::LogBufferConfig::default();
+    if let Some(containers) = cli
+        .log_buffer_containers
+        .or_else(|| env::var("LOG_BUFFER_CONTAINERS").ok())
+    {
+        log_buffer.containers = containers
+            .split(',')
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+            .collect();
+    }
+    if let Some(lines) = cli.log_buffer_lines.or_else(|| {
+        env::var("LOG_BUFFER_LINES")
+            .ok()
+            .and_then(|s| s.parse().ok())
+    }) {
+        if !(1..=100_000).contains(&lines) {
+            return Err("--log-buffer-lines must be between 1 and 100000".into());
+        }
+        log_buffer.lines = lines;
+    }
+    node_runner::configure_log_buffer(log_buffer);
+
+    let mode = cli.mode.unwrap_or_default();
+
+    // Self-hosted credentials survive restarts so saved client configurations keep working
+    let ephemeral_credentials = cli.ephemeral_credentials
+        || env::var("EPHEMERAL_CREDENTIALS").is_ok_and(|v| v == "1" || v == "true");
+    let saved = if mode == "self-hosted"
+        && !ephemeral_credentials
+        && (cli.node_id.is_none() || cli.password.is_none())
+    {
+        let path = credentials::credentials_path(state_dir.as_deref());
+        let saved = credentials::load_or_create(&path).map_err(|e| {
+            format!(
+                "Failed to load credentials from {}: {} (use --ephemeral-credentials to skip saving)",
+                path.display(),
+                e
+            )
+        })?;
+        info!("Using credentials saved in {}", path.display());
+        Some(saved)
+    } else {
+        None
+    };
+
     // Generate node_id and password if they do not exist
-    let node_id = cli.node_id.unwrap_or_else(generate_node_id);
-    let password = cli.password.unwrap_or_else(generate_secure_password);
+    let (saved_node_id, saved_password) =
+        saved.map(|saved| (saved.node_id, saved.password)).unzip();
+    let node_id = cli
+        .node_id
+        .or(saved_node_id)
+        .unwrap_or_else(generate_node_id);
+    let password = cli
+        .password
+        .or(saved_password)
+        .unwrap_or_else(generate_secure_password);
 
-    match cli.mode.as_str() {
+    match mode.as_str() {
         "coordinator" => {
             info!("Running Coordinator");
             info!("gRPC port: {}", grpc_port);
             info!("API port: {}", api_port);
-            println!("");
+            println!();
 
             let grpc_addr = format!("0.0.0.0:{}", grpc_port);
             let api_addr = format!("0.0.0.0:{}", api_port);
 
-            coordinator_runner::run(&grpc_addr, &api_addr).await?;
+            coordinator_runner::run(&grpc_addr, &api_addr, coordinator_options).await?;
         }
         "node" => {
             info!("Running Node");
             info!("Coordinator address: {}", coordinator_addr);
-            println!("");
+            println!();
 
-            node_runner::run(&coordinator_addr, &node_id, &password, false).await?;
+            let coordinator_addrs: Vec<String> = coordinator_addr
+                .split(',')
+                .map(str::trim)
+                .filter(|addr| !addr.is_empty())
+                .map(str::to_string)
+                .collect();
+            // Set TLS options make a plain http:// address fail instead of being ignored
+            let tls = (coordinator_addrs
+                .iter()
+                .any(|addr| addr.starts_with("https://"))
+                || grpc_client_tls.ca_path.is_some()
+                || grpc_client_tls.domain.is_some())
+            .then_some(grpc_client_tls);
+            node_runner::run(
+                &coordinator_addrs,
+                &node_id,
+                &password,
+                false,
+                max_message_size,
+                compression,
+                docker_limits,
+                tls,
+                shutdown,
+            )
+            .await?;
         }
         "self-hosted" => {
             info!("Running Self-Hosted Node (Coordinator + Node)");
-            println!("");
+            println!();
 
-            info!("gRPC port: {}", grpc_port);
+            // The built-in node connects over loopback, so the node protocol
+            // stays off the network unless other nodes should join
+            let expose_grpc =
+                cli.expose_grpc || env::var("EXPOSE_GRPC").is_ok_and(|v| v == "1" || v == "true");
+            let grpc_host = if expose_grpc { "0.0.0.0" } else { "127.0.0.1" };
+
+            info!("gRPC port: {} (bound to {})", grpc_port, grpc_host);
             info!("API port: {}", api_port);
-            println!("");
+            println!();
 
-            let grpc_addr = format!("0.0.0.0:{}", grpc_port);
+            let grpc_addr = format!("{}:{}", grpc_host, grpc_port);
             let api_addr = format!("0.0.0.0:{}", api_port);
-            let local_coordinator_addr = format!("http://localhost:{}", grpc_port);
+            // With TLS the built-in node trusts the served certificate itself, which
+            // works for self-signed ones; otherwise set --grpc-tls-ca
+            let local_tls = grpc_server_tls.map(|server_tls| node_runner::GrpcClientTls {
+                ca_path: grpc_client_tls.ca_path.or(Some(server_tls.cert_path)),
+                domain: grpc_client_tls
+                    .domain
+                    .or_else(|| Some("localhost".to_string())),
+            });
+            let scheme = if local_tls.is_some() { "https" } else { "http" };
+            let local_coordinator_addr = format!("{}://127.0.0.1:{}", scheme, grpc_port);
 
             let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
 
+            // Either half stopping, on a signal or an error, stops the other
+            let coordinator_shutdown = shutdown.clone();
             let coordinator_handle = tokio::spawn(async move {
-                coordinator_runner::run_with_ready_callback(&grpc_addr, &api_addr, move || {
-                    let _ = ready_tx.send(());
-                })
-                .await
+                let result = coordinator_runner::run_with_ready_callback(
+                    &grpc_addr,
+                    &api_addr,
+                    coordinator_options,
+                    move || {
+                        let _ = ready_tx.send(());
+                    },
+                )
+                .await;
+                coordinator_shutdown.cancel();
+                result
             });
 
             let _ = ready_rx.await;
             info!("Coordinator is ready, starting node...");
-            println!("");
+            println!();
 
+            let node_shutdown = shutdown.clone();
             let node_handle = tokio::spawn(async move {
-                node_runner::run(&local_coordinator_addr, &node_id, &password, true).await
+                let result = node_runner::run(
+                    &[local_coordinator_addr],
+                    &node_id,
+                    &password,
+                    true,
+                    max_message_size,
+                    compression,
+                    docker_limits,
+                    local_tls,
+                    node_shutdown.clone(),
+                )
+                .await;
+                node_shutdown.cancel();
+                result
             });
 
-            tokio::select! {
-                result = coordinator_handle => {
-                    if let Err(e) = result {
-                        error!("Coordinator failed: {:?}", e);
-                    }
-                }
-                result = node_handle => {
-                    if let Err(e) = result {
-                        error!("Node failed: {:?}", e);
-                    }
-                }
+            let (coordinator_result, node_result) = tokio::join!(coordinator_handle, node_handle);
+            match coordinator_result {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => error!("Coordinator failed: {}", e),
+                Err(e) => error!("Coordinator failed: {:?}", e),
+            }
+            match node_result {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => error!("Node failed: {}", e),
+                Err(e) => error!("Node failed: {:?}", e),
             }
         }
         _ => unreachable!(),
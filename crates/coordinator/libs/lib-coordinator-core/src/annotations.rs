@@ -0,0 +1,64 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::json_store::JsonStore;
+
+pub type Annotations = BTreeMap<String, String>;
+
+// node id -> container name -> annotations
+type AnnotationMap = BTreeMap<String, BTreeMap<String, Annotations>>;
+
+/// Coordinator-side key/value annotations on containers, persisted in `annotations.json`.
+/// Docker labels are fixed at create time, these can change at any moment.
+#[derive(Clone)]
+pub struct AnnotationStore {
+    store: Arc<JsonStore<AnnotationMap>>,
+}
+
+impl AnnotationStore {
+    pub fn open(state_dir: Option<&Path>) -> Self {
+        Self {
+            store: Arc::new(JsonStore::open(state_dir, "annotations.json")),
+        }
+    }
+
+    pub fn get(&self, node_id: &str, container_id: &str) -> Annotations {
+        self.store.read(|map| {
+            map.get(node_id)
+                .and_then(|containers| containers.get(container_id))
+                .cloned()
+                .unwrap_or_default()
+        })
+    }
+
+    /// All annotated containers of a node, for merging into listings.
+    pub fn for_node(&self, node_id: &str) -> BTreeMap<String, Annotations> {
+        self.store
+            .read(|map| map.get(node_id).cloned().unwrap_or_default())
+    }
+
+    /// Replaces the annotations of a container; an empty map removes them.
+    pub fn set(
+        &self,
+        node_id: &str,
+        container_id: &str,
+        annotations: Annotations,
+    ) -> io::Result<()> {
+        self.store.update(|map| {
+            if annotations.is_empty() {
+                if let Some(containers) = map.get_mut(node_id) {
+                    containers.remove(container_id);
+                    if containers.is_empty() {
+                        map.remove(node_id);
+                    }
+                }
+            } else {
+                map.entry(node_id.to_string())
+                    .or_default()
+                    .insert(container_id.to_string(), annotations);
+            }
+        })
+    }
+}
@@ -1,30 +1,154 @@
+use std::hash::{BuildHasher, RandomState};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use argon2::Argon2;
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use proto::generated::Envelope;
+use subtle::ConstantTimeEq;
+use tokio::sync::{Semaphore, broadcast, mpsc};
+
+use crate::{NodeChannels, ServerRequestByUser};
+
+// Keys the tags of verified passwords. Random per process, so a tag means
+// nothing outside it.
+static TAG_KEY: LazyLock<RandomState> = LazyLock::new(RandomState::new);
+// Argon2 checks running at once, so wrong passwords cannot take every core
+static HASHING: LazyLock<Semaphore> = LazyLock::new(|| Semaphore::new(4));
+// Wrong passwords a node takes per window; past that, checks fail without hashing
+const MAX_FAILURES: u32 = 10;
+const FAILURE_WINDOW: Duration = Duration::from_secs(10);
+
+/// A connected node: the credentials it connected with, its stream's command
+/// queue and the channel for its pushed updates. The password is only kept
+/// as an argon2 hash.
+pub struct ConnectedNode {
+    password_hash: String,
+    internal_token: String,
+    // Tag of the last password that verified, so each request does not pay for argon2
+    verified: Mutex<Option<u64>>,
+    // Start of the current window and the wrong passwords seen in it
+    failures: Mutex<(Instant, u32)>,
+    pub(crate) commands: mpsc::Sender<ServerRequestByUser>,
+    pub updates: broadcast::Sender<Envelope>,
+}
+
+impl ConnectedNode {
+    pub fn new(
+        password: &str,
+        commands: mpsc::Sender<ServerRequestByUser>,
+        updates: broadcast::Sender<Envelope>,
+    ) -> Result<Self, argon2::password_hash::Error> {
+        let salt = SaltString::generate(&mut OsRng);
+        let password_hash = Argon2::default()
+            .hash_password(password.as_bytes(), &salt)?
+            .to_string();
+        let mut token = [0u8; 32];
+        OsRng.fill_bytes(&mut token);
+        Ok(Self {
+            password_hash,
+            internal_token: URL_SAFE_NO_PAD.encode(token),
+            verified: Mutex::new(None),
+            failures: Mutex::new((Instant::now(), 0)),
+            commands,
+            updates,
+        })
+    }
+
+    /// Whether `password` is the one the node connected with, or its internal token.
+    /// Argon2 runs on the blocking pool; once a node saw too many wrong passwords
+    /// in a short time, passwords that did not verify before are refused unchecked.
+    pub async fn verify(&self, password: &str) -> bool {
+        if bool::from(password.as_bytes().ct_eq(self.internal_token.as_bytes())) {
+            return true;
+        }
+        let tag = TAG_KEY.hash_one(password);
+        if *self.verified.lock().unwrap_or_else(|e| e.into_inner()) == Some(tag) {
+            return true;
+        }
+        if !self.may_check() {
+            return false;
+        }
+
+        let Ok(_permit) = HASHING.acquire().await else {
+            return false;
+        };
+        let password_hash = self.password_hash.clone();
+        let password = password.to_string();
+        let matches = tokio::task::spawn_blocking(move || {
+            PasswordHash::new(&password_hash).is_ok_and(|hash| {
+                Argon2::default()
+                    .verify_password(password.as_bytes(), &hash)
+                    .is_ok()
+            })
+        })
+        .await
+        .unwrap_or(false);
+        if matches {
+            *self.verified.lock().unwrap_or_else(|e| e.into_inner()) = Some(tag);
+        } else {
+            self.failures.lock().unwrap_or_else(|e| e.into_inner()).1 += 1;
+        }
+        matches
+    }
+
+    // Whether the node's recent wrong passwords leave room for another check
+    fn may_check(&self) -> bool {
+        let mut failures = self.failures.lock().unwrap_or_else(|e| e.into_inner());
+        if failures.0.elapsed() >= FAILURE_WINDOW {
+            *failures = (Instant::now(), 0);
+        }
+        failures.1 < MAX_FAILURES
+    }
+
+    /// What the coordinator's own requests to the node use in place of its
+    /// password. Random, and only valid while this connection lasts.
+    pub fn internal_token(&self) -> &str {
+        &self.internal_token
+    }
+}
+
+/// Whether `node_id` is connected and `password` is its password.
+pub async fn verify_node(nodes: &NodeChannels, node_id: &str, password: &str) -> bool {
+    // Not holding the map's lock while hashing
+    let Some(node) = nodes.get(node_id).map(|node| node.clone()) else {
+        return false;
+    };
+    node.verify(password).await
+}
+
 // Helper structures and functions
 #[derive(Default)]
 pub struct AuthState {
     pub id: Option<String>,
-    pub password: Option<String>,
+    pub node: Option<Arc<ConnectedNode>>,
 }
 
 impl AuthState {
-    pub fn authenticate(&mut self, id: String, password: String) {
+    pub fn authenticate(&mut self, id: String, node: Arc<ConnectedNode>) {
         self.id = Some(id);
-        self.password = Some(password);
+        self.node = Some(node);
     }
 
     pub fn is_authenticated(&self) -> bool {
-        self.id.is_some() && self.password.is_some()
+        self.id.is_some() && self.node.is_some()
     }
 
-    pub fn is_match(&self, id: &str, password: &str) -> bool {
-        match (&self.id, &self.password) {
-            (Some(a), Some(b)) => a == id && b == password,
-            _ => false,
+    /// The node, if it is the one authenticated as `id`; its password is
+    /// checked after the state's lock is released.
+    pub fn node_for(&self, id: &str) -> Option<Arc<ConnectedNode>> {
+        match (&self.id, &self.node) {
+            (Some(a), Some(node)) if a == id => Some(node.clone()),
+            _ => None,
         }
     }
 
-    pub fn take_credentials(&mut self) -> Option<(String, String)> {
+    pub fn take_credentials(&mut self) -> Option<(String, Arc<ConnectedNode>)> {
         if self.is_authenticated() {
-            Some((self.id.take().unwrap(), self.password.take().unwrap()))
+            Some((self.id.take().unwrap(), self.node.take().unwrap()))
         } else {
             None
         }
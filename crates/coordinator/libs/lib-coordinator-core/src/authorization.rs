@@ -0,0 +1,509 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use http_body_util::{BodyExt, Full};
+use hyper::body::Bytes;
+use hyper::{Request, Uri, header};
+use hyper_util::client::legacy::Client;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::rt::TokioExecutor;
+use proto::generated::{RequestType, node_command::Kind};
+use serde::Serialize;
+use serde_json::{Value, json};
+use tracing::warn;
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A node command about to be sent, as seen by the policy.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuthzRequest {
+    pub node_id: String,
+    /// Snake-case command name: "start_container", "build_image", ...
+    pub action: &'static str,
+    pub request_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    /// Unix seconds, so policies can enforce time windows without their own clock.
+    pub time: i64,
+    #[serde(skip)]
+    pub request_type: RequestType,
+}
+
+impl AuthzRequest {
+    /// Describes a command for the policy; `None` for messages within an already
+    /// authorized session (input, resize, detach) and cancellations, which are not
+    /// checked again.
+    pub fn for_command(node_id: &str, command: &Kind) -> Option<Self> {
+        let (action, request_type, request_id, container_id, image) = match command {
+            Kind::GetNodeContainers(c) => (
+                "get_containers",
+                RequestType::GetContainers,
+                &c.request_id,
+                None,
+                None,
+            ),
+            Kind::GetNodeContainersWithStatus(c) => (
+                "get_containers_with_status",
+                RequestType::GetContainersWithStatus,
+                &c.request_id,
+                None,
+                None,
+            ),
+            Kind::GetContainerStatus(c) => (
+                "get_container_status",
+                RequestType::GetContainerStatus,
+                &c.request_id,
+                Some(&c.container_id),
+                None,
+            ),
+            Kind::StartContainer(c) => (
+                "start_container",
+                RequestType::StartContainer,
+                &c.request_id,
+                Some(&c.container_id),
+                None,
+            ),
+            Kind::StopContainer(c) => (
+                "stop_container",
+                RequestType::StopContainer,
+                &c.request_id,
+                Some(&c.container_id),
+                None,
+            ),
+            Kind::RestartContainer(c) => (
+                "restart_container",
+                RequestType::RestartContainer,
+                &c.request_id,
+                Some(&c.container_id),
+                None,
+            ),
+            Kind::PauseContainer(c) => (
+                "pause_container",
+                RequestType::PauseContainer,
+                &c.request_id,
+                Some(&c.container_id),
+                None,
+            ),
+            Kind::UnpauseContainer(c) => (
+                "unpause_container",
+                RequestType::UnpauseContainer,
+                &c.request_id,
+                Some(&c.container_id),
+                None,
+            ),
+            Kind::DeleteContainer(c) => (
+                "delete_container",
+                RequestType::DeleteContainer,
+                &c.request_id,
+                Some(&c.container_id),
+                None,
+            ),
+            Kind::GetContainerArchive(c) => (
+                "get_container_archive",
+                RequestType::GetContainerArchive,
+                &c.request_id,
+                Some(&c.container_id),
+                None,
+            ),
+            Kind::PutContainerArchive(c) => (
+                "put_container_archive",
+                RequestType::PutContainerArchive,
+                &c.request_id,
+                Some(&c.container_id),
+                None,
+            ),
+            Kind::GetContainerLogs(c) => (
+                "get_container_logs",
+                RequestType::GetContainerLogs,
+                &c.request_id,
+                Some(&c.container_id),
+                None,
+            ),
+            Kind::BuildImage(c) => (
+                "build_image",
+                RequestType::BuildImage,
+                &c.request_id,
+                None,
+                Some(&c.tag),
+            ),
+            Kind::InspectImageManifest(c) => (
+                "inspect_image_manifest",
+                RequestType::InspectImageManifest,
+                &c.request_id,
+                None,
+                Some(&c.image),
+            ),
+            Kind::GetImageHistory(c) => (
+                "get_image_history",
+                RequestType::GetImageHistory,
+                &c.request_id,
+                None,
+                Some(&c.image),
+            ),
+            Kind::ApplyAutostart(c) => (
+                "apply_autostart",
+                RequestType::ApplyAutostart,
+                &c.request_id,
+                None,
+                None,
+            ),
+            Kind::CreateContainer(c) => (
+                "create_container",
+                RequestType::CreateContainer,
+                &c.request_id,
+                (!c.name.is_empty()).then_some(&c.name),
+                Some(&c.image),
+            ),
+            Kind::CloneContainer(c) => (
+                "clone_container",
+                RequestType::CloneContainer,
+                &c.request_id,
+                Some(&c.container_id),
+                None,
+            ),
+            Kind::GetContainerGraph(c) => (
+                "get_container_graph",
+                RequestType::GetContainerGraph,
+                &c.request_id,
+                None,
+                None,
+            ),
+            Kind::GetDiskUsage(c) => (
+                "get_disk_usage",
+                RequestType::GetDiskUsage,
+                &c.request_id,
+                None,
+                None,
+            ),
+            Kind::ConfigureLogForwarding(c) => (
+                "configure_log_forwarding",
+                RequestType::ConfigureLogForwarding,
+                &c.request_id,
+                None,
+                None,
+            ),
+            Kind::GetLogForwarding(c) => (
+                "get_log_forwarding",
+                RequestType::GetLogForwarding,
+                &c.request_id,
+                None,
+                None,
+            ),
+            Kind::ConfigureCleanup(c) => (
+                "configure_cleanup",
+                RequestType::ConfigureCleanup,
+                &c.request_id,
+                None,
+                None,
+            ),
+            Kind::GetCleanup(c) => (
+                "get_cleanup",
+                RequestType::GetCleanup,
+                &c.request_id,
+                None,
+                None,
+            ),
+            Kind::ConfigureTrash(c) => (
+                "configure_trash",
+                RequestType::ConfigureTrash,
+                &c.request_id,
+                None,
+                None,
+            ),
+            Kind::GetTrash(c) => (
+                "get_trash",
+                RequestType::GetTrash,
+                &c.request_id,
+                None,
+                None,
+            ),
+            Kind::RestoreContainer(c) => (
+                "restore_container",
+                RequestType::RestoreContainer,
+                &c.request_id,
+                Some(&c.container_id),
+                None,
+            ),
+            Kind::ExportContainer(c) => (
+                "export_container",
+                RequestType::ExportContainer,
+                &c.request_id,
+                Some(&c.container_id),
+                None,
+            ),
+            Kind::ImportContainer(c) => (
+                "import_container",
+                RequestType::ImportContainer,
+                &c.request_id,
+                None,
+                None,
+            ),
+            Kind::ListImages(c) => (
+                "list_images",
+                RequestType::ListImages,
+                &c.request_id,
+                None,
+                None,
+            ),
+            Kind::PullImage(c) => (
+                "pull_image",
+                RequestType::PullImage,
+                &c.request_id,
+                None,
+                Some(&c.image),
+            ),
+            Kind::RemoveImage(c) => (
+                "remove_image",
+                RequestType::RemoveImage,
+                &c.request_id,
+                None,
+                Some(&c.image),
+            ),
+            Kind::PruneImages(c) => (
+                "prune_images",
+                RequestType::PruneImages,
+                &c.request_id,
+                None,
+                None,
+            ),
+            Kind::ListNetworks(c) => (
+                "list_networks",
+                RequestType::ListNetworks,
+                &c.request_id,
+                None,
+                None,
+            ),
+            Kind::InspectNetwork(c) => (
+                "inspect_network",
+                RequestType::InspectNetwork,
+                &c.request_id,
+                None,
+                None,
+            ),
+            Kind::CreateNetwork(c) => (
+                "create_network",
+                RequestType::CreateNetwork,
+                &c.request_id,
+                None,
+                None,
+            ),
+            Kind::RemoveNetwork(c) => (
+                "remove_network",
+                RequestType::RemoveNetwork,
+                &c.request_id,
+                None,
+                None,
+            ),
+            Kind::GetNodeMetrics(c) => (
+                "get_node_metrics",
+                RequestType::GetNodeMetrics,
+                &c.request_id,
+                None,
+                None,
+            ),
+            Kind::GetSystemInfo(c) => (
+                "get_system_info",
+                RequestType::GetSystemInfo,
+                &c.request_id,
+                None,
+                None,
+            ),
+            Kind::GetSystemDiskUsage(c) => (
+                "get_system_disk_usage",
+                RequestType::GetSystemDiskUsage,
+                &c.request_id,
+                None,
+                None,
+            ),
+            Kind::PruneSystem(c) => (
+                "prune_system",
+                RequestType::PruneSystem,
+                &c.request_id,
+                None,
+                None,
+            ),
+            Kind::GetContainerStats(c) => (
+                "get_container_stats",
+                RequestType::GetContainerStats,
+                &c.request_id,
+                Some(&c.container_id),
+                None,
+            ),
+            Kind::GetTopContainers(c) => (
+                "get_top_containers",
+                RequestType::GetTopContainers,
+                &c.request_id,
+                None,
+                None,
+            ),
+            Kind::Ping(c) => ("ping", RequestType::Ping, &c.request_id, None, None),
+            Kind::AttachContainer(c) => (
+                "attach_container",
+                RequestType::AttachContainer,
+                &c.request_id,
+                Some(&c.container_id),
+                None,
+            ),
+            Kind::ExecContainer(c) => (
+                "exec_container",
+                RequestType::ExecContainer,
+                &c.request_id,
+                Some(&c.container_id),
+                None,
+            ),
+            Kind::FollowContainerLogs(c) => (
+                "follow_container_logs",
+                RequestType::FollowContainerLogs,
+                &c.request_id,
+                Some(&c.container_id),
+                None,
+            ),
+            Kind::RunHostScript(c) => (
+                "run_host_script",
+                RequestType::RunHostScript,
+                &c.request_id,
+                None,
+                None,
+            ),
+            Kind::SessionInput(_)
+            | Kind::CloseSession(_)
+            | Kind::ResizeSession(_)
+            | Kind::CancelRequest(_) => {
+                return None;
+            }
+        };
+
+        Some(Self {
+            node_id: node_id.to_string(),
+            action,
+            request_id: request_id.clone(),
+            container_id: container_id.cloned(),
+            image: image.filter(|image| !image.is_empty()).cloned(),
+            time: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or(0),
+            request_type,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthzDecision {
+    Allow,
+    Deny(String),
+}
+
+/// Policy consulted before a node command is sent to the node.
+#[async_trait]
+pub trait Authorizer: Send + Sync {
+    async fn authorize(&self, request: &AuthzRequest) -> AuthzDecision;
+}
+
+pub type SharedAuthorizer = Arc<dyn Authorizer>;
+
+/// Default policy: everything that passed node authentication is allowed.
+pub struct AllowAll;
+
+#[async_trait]
+impl Authorizer for AllowAll {
+    async fn authorize(&self, _request: &AuthzRequest) -> AuthzDecision {
+        AuthzDecision::Allow
+    }
+}
+
+/// Asks an HTTP policy endpoint, e.g. OPA's data API (`/v1/data/docklord/allow`).
+///
+/// The request is POSTed as `{"input": {...}}`. The response must carry `result`,
+/// either a boolean or an object with `allow` and an optional `reason`.
+pub struct WebhookAuthorizer {
+    url: Uri,
+    /// Allow commands when the policy endpoint cannot be reached.
+    fail_open: bool,
+    client: Client<HttpConnector, Full<Bytes>>,
+}
+
+impl WebhookAuthorizer {
+    pub fn new(url: &str, fail_open: bool) -> Result<Self, String> {
+        let url: Uri = url
+            .parse()
+            .map_err(|e| format!("Invalid authorization webhook URL: {}", e))?;
+        if url.scheme_str() != Some("http") {
+            return Err("Authorization webhook must be an http:// URL".to_string());
+        }
+
+        Ok(Self {
+            url,
+            fail_open,
+            client: Client::builder(TokioExecutor::new()).build_http(),
+        })
+    }
+
+    async fn query(&self, request: &AuthzRequest) -> Result<AuthzDecision, String> {
+        let body = json!({ "input": request }).to_string();
+        let http_request = Request::post(self.url.clone())
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Full::new(Bytes::from(body)))
+            .map_err(|e| e.to_string())?;
+
+        let response = tokio::time::timeout(WEBHOOK_TIMEOUT, self.client.request(http_request))
+            .await
+            .map_err(|_| "timed out".to_string())?
+            .map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("status {}", response.status()));
+        }
+        let body = tokio::time::timeout(WEBHOOK_TIMEOUT, response.into_body().collect())
+            .await
+            .map_err(|_| "timed out".to_string())?
+            .map_err(|e| e.to_string())?
+            .to_bytes();
+        let body: Value = serde_json::from_slice(&body).map_err(|e| e.to_string())?;
+
+        parse_decision(&body).ok_or_else(|| "response has no boolean `result`".to_string())
+    }
+}
+
+#[async_trait]
+impl Authorizer for WebhookAuthorizer {
+    async fn authorize(&self, request: &AuthzRequest) -> AuthzDecision {
+        match self.query(request).await {
+            Ok(decision) => decision,
+            Err(e) if self.fail_open => {
+                warn!(
+                    "Authorization webhook failed, allowing {}: {}",
+                    request.action, e
+                );
+                AuthzDecision::Allow
+            }
+            Err(e) => {
+                warn!(
+                    "Authorization webhook failed, denying {}: {}",
+                    request.action, e
+                );
+                AuthzDecision::Deny("authorization service unavailable".to_string())
+            }
+        }
+    }
+}
+
+fn parse_decision(body: &Value) -> Option<AuthzDecision> {
+    let result = body.get("result")?;
+    let (allow, reason) = match result {
+        Value::Bool(allow) => (*allow, None),
+        Value::Object(result) => (
+            result.get("allow")?.as_bool()?,
+            result.get("reason").and_then(Value::as_str),
+        ),
+        _ => return None,
+    };
+
+    Some(if allow {
+        AuthzDecision::Allow
+    } else {
+        AuthzDecision::Deny(reason.unwrap_or("denied by policy").to_string())
+    })
+}
@@ -0,0 +1,150 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use proto::generated::{ApplyAutostart, AutostartReport};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::json_store::JsonStore;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct AutostartEntry {
+    pub container_id: String,
+    /// Seconds to wait before starting this container.
+    #[serde(default)]
+    pub delay_secs: u32,
+}
+
+/// Containers a node starts, in order, when it (re)connects to the coordinator.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AutostartPlan {
+    pub containers: Vec<AutostartEntry>,
+    /// Only apply when the node's host booted since the last run, not on every reconnect.
+    #[serde(default)]
+    pub only_after_reboot: bool,
+    /// Outcome of the most recent run.
+    #[serde(default)]
+    pub last_run: Option<AutostartRun>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutostartRun {
+    /// Unix seconds.
+    pub finished_at: i64,
+    /// Host boot time the node reported, unix seconds; 0 if unknown.
+    pub boot_time: i64,
+    /// The host had not rebooted, so nothing was started.
+    pub skipped: bool,
+    pub results: Vec<AutostartOutcome>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutostartOutcome {
+    pub container_id: String,
+    /// `started`, `already_running` or `failed`.
+    pub outcome: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl AutostartPlan {
+    /// The node command applying this plan. `from_reconnect` honours
+    /// `only_after_reboot`; a run requested by an operator always applies.
+    pub fn command(&self, request_id: &str, from_reconnect: bool) -> ApplyAutostart {
+        ApplyAutostart {
+            request_id: request_id.to_string(),
+            containers: self
+                .containers
+                .iter()
+                .map(|entry| proto::generated::AutostartEntry {
+                    container_id: entry.container_id.clone(),
+                    delay_secs: entry.delay_secs,
+                })
+                .collect(),
+            only_after_reboot: from_reconnect && self.only_after_reboot,
+            last_boot_time: self.last_run.as_ref().map_or(0, |run| run.boot_time),
+        }
+    }
+
+    /// How long a run may take: every delay plus time to start each container.
+    pub fn timeout(&self) -> Duration {
+        let delays: u64 = self
+            .containers
+            .iter()
+            .map(|entry| u64::from(entry.delay_secs))
+            .sum();
+        Duration::from_secs(30 + delays + 60 * self.containers.len() as u64)
+    }
+}
+
+impl From<&AutostartReport> for AutostartRun {
+    fn from(report: &AutostartReport) -> Self {
+        Self {
+            finished_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or(0),
+            boot_time: report.boot_time,
+            skipped: report.skipped,
+            results: report
+                .results
+                .iter()
+                .map(|result| AutostartOutcome {
+                    container_id: result.container_id.clone(),
+                    outcome: result.outcome.clone(),
+                    error: (!result.error.is_empty()).then(|| result.error.clone()),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Per-node autostart lists, persisted in `autostart.json`. They are applied by
+/// the node itself, independent of Docker restart policies.
+#[derive(Clone)]
+pub struct AutostartStore {
+    store: Arc<JsonStore<BTreeMap<String, AutostartPlan>>>,
+}
+
+impl AutostartStore {
+    pub fn open(state_dir: Option<&Path>) -> Self {
+        Self {
+            store: Arc::new(JsonStore::open(state_dir, "autostart.json")),
+        }
+    }
+
+    pub fn get(&self, node_id: &str) -> Option<AutostartPlan> {
+        self.store.read(|plans| plans.get(node_id).cloned())
+    }
+
+    /// Replaces the node's list, keeping the record of the last run. An empty
+    /// list removes the plan.
+    pub fn set(
+        &self,
+        node_id: &str,
+        containers: Vec<AutostartEntry>,
+        only_after_reboot: bool,
+    ) -> io::Result<()> {
+        self.store.update(|plans| {
+            if containers.is_empty() {
+                plans.remove(node_id);
+                return;
+            }
+            let plan = plans.entry(node_id.to_string()).or_default();
+            plan.containers = containers;
+            plan.only_after_reboot = only_after_reboot;
+        })
+    }
+
+    /// Records the outcome of a run, if the node still has a plan.
+    pub fn record_run(&self, node_id: &str, run: AutostartRun) -> io::Result<()> {
+        self.store.update(|plans| {
+            if let Some(plan) = plans.get_mut(node_id) {
+                plan.last_run = Some(run);
+            }
+        })
+    }
+}
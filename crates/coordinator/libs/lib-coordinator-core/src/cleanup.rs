@@ -0,0 +1,102 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use proto::generated::{CleanupRule, ConfigureCleanup};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::json_store::JsonStore;
+
+/// How often nodes sweep unless the policy says otherwise.
+pub const DEFAULT_CLEANUP_INTERVAL_SECS: u32 = 3600;
+
+/// Exited containers one rule removes. A container goes when it is older than
+/// `older_than_secs` and not among the `keep_last` most recently exited of its
+/// compose service (or image, outside compose).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CleanupRuleConfig {
+    pub name: String,
+    /// Container names; a trailing `*` matches every name with that prefix.
+    /// Empty matches every container.
+    #[serde(default)]
+    pub containers: Vec<String>,
+    /// Seconds since the container exited; 0 removes by `keep_last` alone.
+    #[serde(default)]
+    pub older_than_secs: u64,
+    /// Most recently exited containers kept per group whatever their age.
+    #[serde(default)]
+    pub keep_last: u32,
+}
+
+/// Retention rules of one node. A container is removed when any rule selects it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CleanupPolicy {
+    pub rules: Vec<CleanupRuleConfig>,
+    /// Seconds between sweeps.
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u32,
+}
+
+fn default_interval_secs() -> u32 {
+    DEFAULT_CLEANUP_INTERVAL_SECS
+}
+
+/// Per-node cleanup policies, persisted in `cleanup.json`. The node sweeps;
+/// the coordinator sends the policy whenever the node connects.
+#[derive(Clone)]
+pub struct CleanupStore {
+    store: Arc<JsonStore<BTreeMap<String, CleanupPolicy>>>,
+}
+
+impl CleanupStore {
+    pub fn open(state_dir: Option<&Path>) -> Self {
+        Self {
+            store: Arc::new(JsonStore::open(state_dir, "cleanup.json")),
+        }
+    }
+
+    pub fn get(&self, node_id: &str) -> Option<CleanupPolicy> {
+        self.store.read(|policies| policies.get(node_id).cloned())
+    }
+
+    /// Replaces the node's policy; `None` or one without rules removes it.
+    pub fn set(&self, node_id: &str, policy: Option<CleanupPolicy>) -> io::Result<()> {
+        self.store.update(|policies| match policy {
+            Some(policy) if !policy.rules.is_empty() => {
+                policies.insert(node_id.to_string(), policy);
+            }
+            _ => {
+                policies.remove(node_id);
+            }
+        })
+    }
+
+    /// The node command installing `policy`; `None` stops the sweeps.
+    pub fn command(request_id: &str, policy: Option<&CleanupPolicy>) -> ConfigureCleanup {
+        let Some(policy) = policy else {
+            return ConfigureCleanup {
+                request_id: request_id.to_string(),
+                rules: Vec::new(),
+                interval_secs: DEFAULT_CLEANUP_INTERVAL_SECS,
+            };
+        };
+        ConfigureCleanup {
+            request_id: request_id.to_string(),
+            rules: policy
+                .rules
+                .iter()
+                .map(|rule| CleanupRule {
+                    name: rule.name.clone(),
+                    containers: rule.containers.clone(),
+                    older_than_secs: rule.older_than_secs,
+                    keep_last: rule.keep_last,
+                })
+                .collect(),
+            interval_secs: policy.interval_secs,
+        }
+    }
+}
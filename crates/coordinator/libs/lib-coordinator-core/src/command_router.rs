@@ -0,0 +1,52 @@
+use std::fmt;
+
+use tokio::sync::mpsc::error::TrySendError;
+
+use crate::{NodeChannels, ServerRequestByUser};
+
+#[derive(Debug)]
+pub enum RouteError {
+    /// No node with that id is connected.
+    NotConnected(String),
+    /// The node's stream is not keeping up with its commands.
+    Full(String),
+    /// The node's stream ended while the command was on its way.
+    Closed(String),
+}
+
+impl fmt::Display for RouteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RouteError::NotConnected(id) => write!(f, "node {} is not connected", id),
+            RouteError::Full(id) => write!(f, "too many commands queued for node {}", id),
+            RouteError::Closed(id) => write!(f, "the stream of node {} closed", id),
+        }
+    }
+}
+
+impl std::error::Error for RouteError {}
+
+/// Delivers each command only to the stream of the node it names, which
+/// still checks the password before passing it on.
+#[derive(Clone)]
+pub struct CommandRouter {
+    nodes: NodeChannels,
+}
+
+impl CommandRouter {
+    pub fn new(nodes: NodeChannels) -> Self {
+        Self { nodes }
+    }
+
+    /// Queues the command without waiting; a node that fell behind gets
+    /// `Full` rather than slowing down the sender.
+    pub fn send(&self, request: ServerRequestByUser) -> Result<(), RouteError> {
+        let Some(node) = self.nodes.get(&request.id) else {
+            return Err(RouteError::NotConnected(request.id));
+        };
+        node.commands.try_send(request).map_err(|e| match e {
+            TrySendError::Full(request) => RouteError::Full(request.id),
+            TrySendError::Closed(request) => RouteError::Closed(request.id),
+        })
+    }
+}
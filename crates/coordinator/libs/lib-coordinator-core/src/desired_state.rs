@@ -0,0 +1,293 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use proto::generated::{ContainerStatus, CreateContainer};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::json_store::JsonStore;
+use crate::node_state::NodeState;
+use crate::secrets::SecretRef;
+use crate::templates::{Template, TemplateProblem};
+
+/// Label on containers created by the desired-state reconciler, holding their spec hash.
+pub const DESIRED_LABEL: &str = "docklord.desired";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DesiredRunState {
+    #[default]
+    Running,
+    Stopped,
+}
+
+impl DesiredRunState {
+    fn is_running(&self) -> bool {
+        *self == DesiredRunState::Running
+    }
+}
+
+/// A container that should exist on a node, with the spec it should have.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct DesiredContainer {
+    #[serde(default)]
+    pub node_id: String,
+    /// Container name; identifies the container on its node.
+    pub name: String,
+    pub image: String,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    #[serde(default)]
+    pub ports: Vec<String>,
+    #[serde(default)]
+    pub volumes: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restart_policy: Option<String>,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default)]
+    pub secrets: Vec<SecretRef>,
+    /// Whether the container should be running; not part of the spec hash.
+    #[serde(default, skip_serializing_if = "DesiredRunState::is_running")]
+    pub state: DesiredRunState,
+}
+
+impl DesiredContainer {
+    /// Stable hash of the spec; a container whose label differs is recreated.
+    /// Secret values are not part of it, only their references.
+    pub fn spec_hash(&self) -> String {
+        let spec = DesiredContainer {
+            state: DesiredRunState::Running,
+            ..self.clone()
+        };
+        // FNV-1a: stable across builds, unlike the std hasher
+        let bytes = serde_json::to_vec(&spec).unwrap_or_default();
+        let hash = bytes.iter().fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+        });
+        format!("{:016x}", hash)
+    }
+
+    /// Problems that would keep the container from being created.
+    pub fn problems(&self) -> Vec<TemplateProblem> {
+        match self.command("", "") {
+            Ok(_) => Vec::new(),
+            Err(problems) => problems,
+        }
+    }
+
+    /// The create command for this container, with its spec hash under `label`.
+    /// Secrets are left for the caller to resolve against the node.
+    pub fn command(
+        &self,
+        request_id: &str,
+        label: &str,
+    ) -> Result<CreateContainer, Vec<TemplateProblem>> {
+        let template = self.as_template();
+        let problems = template.problems();
+        if !problems.is_empty() {
+            return Err(problems);
+        }
+        let mut command = template.render(request_id, &BTreeMap::new())?;
+        command.labels.insert(label.to_string(), self.spec_hash());
+        command.start = self.state.is_running();
+        Ok(command)
+    }
+
+    /// The container as a template without variables; `$` is escaped so
+    /// values are taken literally.
+    fn as_template(&self) -> Template {
+        let literal = |value: &String| value.replace('$', "$$");
+        Template {
+            name: self.name.clone(),
+            description: String::new(),
+            image: literal(&self.image),
+            container_name: Some(literal(&self.name)),
+            env: self
+                .env
+                .iter()
+                .map(|(key, value)| (key.clone(), literal(value)))
+                .collect(),
+            ports: self.ports.iter().map(literal).collect(),
+            volumes: self.volumes.iter().map(literal).collect(),
+            restart_policy: self.restart_policy.clone(),
+            labels: self.labels.clone(),
+            secrets: self.secrets.clone(),
+            variables: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeAction {
+    Create,
+    /// Stop and delete the existing container, then create it again.
+    Recreate,
+    Start,
+    Stop,
+    /// Stop and delete a managed container that is no longer desired.
+    Remove,
+}
+
+impl ChangeAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeAction::Create => "create",
+            ChangeAction::Recreate => "recreate",
+            ChangeAction::Start => "start",
+            ChangeAction::Stop => "stop",
+            ChangeAction::Remove => "remove",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedChange {
+    pub node_id: String,
+    pub name: String,
+    pub action: ChangeAction,
+    #[serde(skip)]
+    pub desired: Option<DesiredContainer>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangeResult {
+    pub node_id: String,
+    pub name: String,
+    pub action: ChangeAction,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Whether the node is connected and has reported its containers, so a plan
+/// against its cached state is meaningful.
+pub fn has_reported(state: &NodeState) -> bool {
+    state.online && state.containers_updated_at.is_some()
+}
+
+/// Diffs one node's desired containers against the containers it last
+/// reported. Only containers carrying `label` are managed: one without it is
+/// never touched, even when its name is desired.
+pub fn plan_node(
+    node_id: &str,
+    desired: &[&DesiredContainer],
+    containers: &[ContainerStatus],
+    label: &str,
+) -> (Vec<PlannedChange>, Vec<String>) {
+    let mut changes = Vec::new();
+    let mut skipped = Vec::new();
+    let mut change = |name: &str, action, desired: Option<&DesiredContainer>| {
+        changes.push(PlannedChange {
+            node_id: node_id.to_string(),
+            name: name.to_string(),
+            action,
+            desired: desired.cloned(),
+        })
+    };
+
+    for wanted in desired {
+        let Some(existing) = containers.iter().find(|c| c.container_id == wanted.name) else {
+            change(&wanted.name, ChangeAction::Create, Some(wanted));
+            continue;
+        };
+        match existing.labels.get(label) {
+            None => skipped.push(format!(
+                "container '{}' on node '{}' exists but is not managed",
+                wanted.name, node_id
+            )),
+            Some(hash) if *hash != wanted.spec_hash() => {
+                change(&wanted.name, ChangeAction::Recreate, Some(wanted))
+            }
+            Some(_) => {
+                let running = matches!(
+                    existing.status.as_str(),
+                    "running" | "restarting" | "paused"
+                );
+                match wanted.state {
+                    DesiredRunState::Running if !running => {
+                        change(&wanted.name, ChangeAction::Start, Some(wanted))
+                    }
+                    DesiredRunState::Stopped if running => {
+                        change(&wanted.name, ChangeAction::Stop, Some(wanted))
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    for container in containers {
+        let desired = desired.iter().any(|d| d.name == container.container_id);
+        if container.labels.contains_key(label) && !desired {
+            change(&container.container_id, ChangeAction::Remove, None);
+        }
+    }
+    (changes, skipped)
+}
+
+/// A node's desired containers.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NodeDesiredState {
+    pub containers: Vec<DesiredContainer>,
+    /// Unix seconds.
+    pub updated_at: i64,
+}
+
+/// Per-node desired containers, persisted in `desired_state.json`. Nodes
+/// without an entry are not reconciled.
+#[derive(Clone)]
+pub struct DesiredStateStore {
+    store: Arc<JsonStore<BTreeMap<String, NodeDesiredState>>>,
+}
+
+impl DesiredStateStore {
+    pub fn open(state_dir: Option<&Path>) -> Self {
+        Self {
+            store: Arc::new(JsonStore::open(state_dir, "desired_state.json")),
+        }
+    }
+
+    pub fn get(&self, node_id: &str) -> Option<NodeDesiredState> {
+        self.store.read(|nodes| nodes.get(node_id).cloned())
+    }
+
+    pub fn all(&self) -> Vec<(String, NodeDesiredState)> {
+        self.store.read(|nodes| {
+            nodes
+                .iter()
+                .map(|(node_id, state)| (node_id.clone(), state.clone()))
+                .collect()
+        })
+    }
+
+    /// Replaces the node's desired containers. An empty list is kept: it
+    /// means the node's managed containers should all be removed.
+    pub fn set(&self, node_id: &str, mut containers: Vec<DesiredContainer>) -> io::Result<()> {
+        for container in &mut containers {
+            container.node_id = node_id.to_string();
+        }
+        let updated_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        self.store.update(|nodes| {
+            nodes.insert(
+                node_id.to_string(),
+                NodeDesiredState {
+                    containers,
+                    updated_at,
+                },
+            );
+        })
+    }
+
+    /// Stops reconciling the node; its containers are left as they are.
+    pub fn remove(&self, node_id: &str) -> io::Result<bool> {
+        self.store.update(|nodes| nodes.remove(node_id).is_some())
+    }
+}
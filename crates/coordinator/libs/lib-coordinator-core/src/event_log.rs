@@ -0,0 +1,213 @@
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tracing::warn;
+
+const EVENT_LOG_FILE: &str = "events.jsonl";
+// Events kept in memory and on disk; older ones are dropped on compaction
+const MAX_EVENTS: usize = 50_000;
+// Appended events buffered per subscriber; slower ones miss the oldest
+const APPENDED_EVENTS_CAPACITY: usize = 1024;
+
+/// Container, image or volume lifecycle event as stored in the history, a
+/// host script run, or a REST request acting on a container.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredEvent {
+    /// Monotonic sequence number, used as the pagination cursor.
+    pub seq: u64,
+    pub node_id: String,
+    /// Docker object type: "container", "image" or "volume"; "script" for host
+    /// scripts and "action" for REST requests.
+    #[serde(rename = "type", default = "container_kind")]
+    pub kind: String,
+    /// Container id, image id, volume name or script name.
+    pub container_id: String,
+    pub name: String,
+    pub action: String,
+    pub time: i64,
+    pub exit_code: i32,
+    pub image: String,
+    /// New health (`healthy`, `unhealthy`, `starting`) for `health_status` events.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub health: String,
+    /// Credential that sent an `action` (same labels as usage accounting).
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub actor: String,
+    /// HTTP status an `action` was answered with.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub status: u16,
+}
+
+fn is_zero(status: &u16) -> bool {
+    *status == 0
+}
+
+fn container_kind() -> String {
+    "container".to_string()
+}
+
+/// Filters for `EventLog::query`; `None` matches everything.
+#[derive(Debug, Default)]
+pub struct EventQuery<'a> {
+    pub node_id: Option<&'a str>,
+    /// "container", "image", "volume", "script" or "action".
+    pub kind: Option<&'a str>,
+    /// Matches the Docker id (or a prefix of it) or the container name.
+    pub container_id: Option<&'a str>,
+    pub action: Option<&'a str>,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    /// Only events with a smaller sequence number (the previous page's `next_cursor`).
+    pub before: Option<u64>,
+    pub limit: usize,
+}
+
+/// Append-only container event history, persisted as JSON lines in `events.jsonl`.
+#[derive(Clone)]
+pub struct EventLog {
+    inner: Arc<Mutex<EventLogInner>>,
+    appended: broadcast::Sender<StoredEvent>,
+}
+
+struct EventLogInner {
+    path: Option<PathBuf>,
+    file: Option<File>,
+    events: VecDeque<StoredEvent>,
+    lines_in_file: usize,
+    next_seq: u64,
+}
+
+impl EventLog {
+    pub fn open(state_dir: Option<&Path>) -> Self {
+        let path = state_dir.map(|dir| dir.join(EVENT_LOG_FILE));
+        let mut events = VecDeque::new();
+        let mut lines_in_file = 0;
+
+        if let Some(path) = &path
+            && let Ok(file) = File::open(path)
+        {
+            for line in BufReader::new(file).lines() {
+                let Ok(line) = line else { break };
+                lines_in_file += 1;
+                match serde_json::from_str::<StoredEvent>(&line) {
+                    Ok(event) => {
+                        events.push_back(event);
+                        if events.len() > MAX_EVENTS {
+                            events.pop_front();
+                        }
+                    }
+                    Err(e) => warn!("Skipping corrupt event history line: {}", e),
+                }
+            }
+        }
+
+        let next_seq = events.back().map(|e| e.seq + 1).unwrap_or(1);
+        let file = path.as_deref().and_then(|path| {
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .inspect_err(|e| warn!("Event history will not be persisted: {}", e))
+                .ok()
+        });
+
+        Self {
+            inner: Arc::new(Mutex::new(EventLogInner {
+                path,
+                file,
+                events,
+                lines_in_file,
+                next_seq,
+            })),
+            appended: broadcast::channel(APPENDED_EVENTS_CAPACITY).0,
+        }
+    }
+
+    /// Every event appended from now on, with its sequence number.
+    pub fn subscribe(&self) -> broadcast::Receiver<StoredEvent> {
+        self.appended.subscribe()
+    }
+
+    /// Assigns the next sequence number and appends the event.
+    pub fn append(&self, mut event: StoredEvent) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        event.seq = inner.next_seq;
+        inner.next_seq += 1;
+
+        if let Err(e) = inner.write(&event) {
+            warn!("Failed to persist container event: {}", e);
+        }
+        // No subscribers is fine
+        let _ = self.appended.send(event.clone());
+        inner.events.push_back(event);
+        if inner.events.len() > MAX_EVENTS {
+            inner.events.pop_front();
+        }
+        if inner.lines_in_file > MAX_EVENTS * 2
+            && let Err(e) = inner.compact()
+        {
+            warn!("Failed to compact event history: {}", e);
+        }
+    }
+
+    /// Matching events, newest first.
+    pub fn query(&self, query: &EventQuery) -> Vec<StoredEvent> {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner
+            .events
+            .iter()
+            .rev()
+            .filter(|e| query.before.is_none_or(|before| e.seq < before))
+            .filter(|e| query.node_id.is_none_or(|id| e.node_id == id))
+            .filter(|e| query.kind.is_none_or(|kind| e.kind == kind))
+            .filter(|e| {
+                query.container_id.is_none_or(|id| {
+                    e.name == id || (!id.is_empty() && e.container_id.starts_with(id))
+                })
+            })
+            .filter(|e| query.action.is_none_or(|action| e.action == action))
+            .filter(|e| query.from.is_none_or(|from| e.time >= from))
+            .filter(|e| query.to.is_none_or(|to| e.time <= to))
+            .take(query.limit)
+            .cloned()
+            .collect()
+    }
+}
+
+impl EventLogInner {
+    fn write(&mut self, event: &StoredEvent) -> io::Result<()> {
+        let Some(file) = &mut self.file else {
+            return Ok(());
+        };
+        let mut line = serde_json::to_vec(event).map_err(io::Error::other)?;
+        line.push(b'\n');
+        file.write_all(&line)?;
+        self.lines_in_file += 1;
+        Ok(())
+    }
+
+    /// Rewrites the file with only the events still kept in memory.
+    fn compact(&mut self) -> io::Result<()> {
+        let Some(path) = self.path.clone() else {
+            return Ok(());
+        };
+        let tmp_path = path.with_extension("jsonl.tmp");
+        let mut tmp = File::create(&tmp_path)?;
+        for event in &self.events {
+            let mut line = serde_json::to_vec(event).map_err(io::Error::other)?;
+            line.push(b'\n');
+            tmp.write_all(&line)?;
+        }
+        tmp.sync_all()?;
+        std::fs::rename(&tmp_path, &path)?;
+
+        self.file = Some(OpenOptions::new().append(true).open(&path)?);
+        self.lines_in_file = self.events.len();
+        Ok(())
+    }
+}
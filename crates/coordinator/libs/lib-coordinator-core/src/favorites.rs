@@ -0,0 +1,86 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::json_store::JsonStore;
+use crate::tenants::{KEY_HASH_PREFIX, api_key_hash};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Favorite {
+    pub node_id: String,
+    pub container_id: String,
+}
+
+/// Pinned containers per API key, persisted in `favorites.json` under the
+/// SHA-256 of the key rather than the key itself.
+#[derive(Clone)]
+pub struct FavoritesStore {
+    store: Arc<JsonStore<BTreeMap<String, Vec<Favorite>>>>,
+}
+
+impl FavoritesStore {
+    pub fn open(state_dir: Option<&Path>) -> Self {
+        let store = JsonStore::open(state_dir, "favorites.json");
+        // Keys stored in plain text by earlier versions are hashed once
+        let plain = store.read(|map: &BTreeMap<String, Vec<Favorite>>| {
+            map.keys().any(|key| !key.starts_with(KEY_HASH_PREFIX))
+        });
+        if plain {
+            let hashed = store.update(|map| {
+                for (key, favorites) in std::mem::take(map) {
+                    let key = if key.starts_with(KEY_HASH_PREFIX) {
+                        key
+                    } else {
+                        api_key_hash(&key)
+                    };
+                    map.entry(key).or_default().extend(favorites);
+                }
+            });
+            if let Err(e) = hashed {
+                warn!("Failed to store hashed favorites keys: {}", e);
+            }
+        }
+        Self {
+            store: Arc::new(store),
+        }
+    }
+
+    pub fn list(&self, api_key: &str) -> Vec<Favorite> {
+        let key = api_key_hash(api_key);
+        self.store
+            .read(|map| map.get(&key).cloned().unwrap_or_default())
+    }
+
+    /// Adds a favorite unless it is already pinned. Returns the resulting list length.
+    pub fn add(&self, api_key: &str, favorite: Favorite) -> io::Result<usize> {
+        let key = api_key_hash(api_key);
+        self.store.update(|map| {
+            let favorites = map.entry(key).or_default();
+            if !favorites.contains(&favorite) {
+                favorites.push(favorite);
+            }
+            favorites.len()
+        })
+    }
+
+    /// Returns whether the favorite existed.
+    pub fn remove(&self, api_key: &str, favorite: &Favorite) -> io::Result<bool> {
+        let key = api_key_hash(api_key);
+        self.store.update(|map| {
+            let Some(favorites) = map.get_mut(&key) else {
+                return false;
+            };
+            let before = favorites.len();
+            favorites.retain(|f| f != favorite);
+            let removed = favorites.len() != before;
+            if favorites.is_empty() {
+                map.remove(&key);
+            }
+            removed
+        })
+    }
+}
@@ -0,0 +1,250 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use chrono::{DateTime, Datelike, Duration, DurationRound, TimeDelta, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::json_store::JsonStore;
+
+/// Longest allowed window, so finding the current one stays cheap.
+pub const MAX_FREEZE_MINUTES: u32 = 7 * 24 * 60;
+// Long enough for any schedule that fires at all, e.g. 29 February
+const MAX_SCHEDULE_SEARCH_DAYS: i64 = 4 * 366;
+
+const MONTH_NAMES: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const DAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// A recurring period during which mutating requests to its nodes are refused.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FreezeWindow {
+    /// When windows start, as a cron expression in UTC: minute, hour, day of
+    /// month, month and day of week.
+    pub schedule: String,
+    /// Length of each window.
+    pub duration_minutes: u32,
+    /// Frozen nodes; `*` freezes every node.
+    #[serde(default)]
+    pub nodes: Vec<String>,
+    /// Tenants whose nodes are frozen.
+    #[serde(default)]
+    pub tenants: Vec<String>,
+    /// Shown to callers whose requests are refused.
+    #[serde(default)]
+    pub reason: String,
+}
+
+impl FreezeWindow {
+    fn covers(&self, node_id: &str, tenant: Option<&str>) -> bool {
+        self.nodes.iter().any(|node| node == "*" || node == node_id)
+            || tenant.is_some_and(|tenant| self.tenants.iter().any(|t| t == tenant))
+    }
+
+    /// End of the window `now` falls in, if any.
+    pub fn active_until(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let schedule = CronSchedule::parse(&self.schedule).ok()?;
+        let minutes = i64::from(self.duration_minutes.min(MAX_FREEZE_MINUTES));
+        let minute = now.duration_trunc(TimeDelta::minutes(1)).ok()?;
+        // The latest start decides the end when windows overlap
+        (0..minutes)
+            .map(|back| minute - Duration::minutes(back))
+            .find(|start| schedule.matches(*start))
+            .map(|start| start + Duration::minutes(minutes))
+    }
+
+    /// Start of the next window after `now`.
+    pub fn next_start(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        CronSchedule::parse(&self.schedule).ok()?.next_after(now)
+    }
+}
+
+/// A freeze window in force for a node.
+#[derive(Debug, Clone)]
+pub struct ActiveFreeze {
+    pub name: String,
+    pub reason: String,
+    pub until: DateTime<Utc>,
+}
+
+/// Freeze windows by name, persisted in `freeze_windows.json`.
+#[derive(Clone)]
+pub struct FreezeStore {
+    store: Arc<JsonStore<BTreeMap<String, FreezeWindow>>>,
+}
+
+impl FreezeStore {
+    pub fn open(state_dir: Option<&Path>) -> Self {
+        Self {
+            store: Arc::new(JsonStore::open(state_dir, "freeze_windows.json")),
+        }
+    }
+
+    pub fn list(&self) -> BTreeMap<String, FreezeWindow> {
+        self.store.read(|windows| windows.clone())
+    }
+
+    /// Returns whether the window was created (false if it replaced one).
+    pub fn put(&self, name: &str, window: FreezeWindow) -> io::Result<bool> {
+        self.store
+            .update(|windows| windows.insert(name.to_string(), window).is_none())
+    }
+
+    /// Returns whether the window existed.
+    pub fn delete(&self, name: &str) -> io::Result<bool> {
+        self.store.update(|windows| windows.remove(name).is_some())
+    }
+
+    /// The window freezing `node_id` (owned by `tenant`) at `now` that ends
+    /// last, if any.
+    pub fn active(
+        &self,
+        node_id: &str,
+        tenant: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> Option<ActiveFreeze> {
+        self.store.read(|windows| {
+            windows
+                .iter()
+                .filter(|(_, window)| window.covers(node_id, tenant))
+                .filter_map(|(name, window)| {
+                    window.active_until(now).map(|until| ActiveFreeze {
+                        name: name.clone(),
+                        reason: window.reason.clone(),
+                        until,
+                    })
+                })
+                .max_by_key(|freeze| freeze.until)
+        })
+    }
+}
+
+/// A five-field cron expression. Fields take `*`, numbers, `a-b` ranges,
+/// `/step` and comma-separated lists; months and weekdays also take English
+/// three-letter names, and weekday 7 is Sunday like 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// As in cron, a day matches either restricted day field when both are.
+    any_day: bool,
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(
+                "must have five fields: minute, hour, day of month, month and day of week"
+                    .to_string(),
+            );
+        };
+        let mut weekdays = parse_field(weekday, "day of week", 0, 7, &DAY_NAMES)?;
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            minutes: parse_field(minute, "minute", 0, 59, &[])?,
+            hours: parse_field(hour, "hour", 0, 23, &[])?,
+            days: parse_field(day, "day of month", 1, 31, &[])?,
+            months: parse_field(month, "month", 1, 12, &MONTH_NAMES)?,
+            weekdays,
+            any_day: day.starts_with('*') || weekday.starts_with('*'),
+        })
+    }
+
+    pub fn matches(&self, time: DateTime<Utc>) -> bool {
+        self.matches_day(time) && bit(self.hours, time.hour()) && bit(self.minutes, time.minute())
+    }
+
+    fn matches_day(&self, time: DateTime<Utc>) -> bool {
+        if !bit(self.months, time.month()) {
+            return false;
+        }
+        let day = bit(self.days, time.day());
+        let weekday = bit(self.weekdays, time.weekday().num_days_from_sunday());
+        if self.any_day {
+            day && weekday
+        } else {
+            day || weekday
+        }
+    }
+
+    /// First matching minute after `after`.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let limit = after + Duration::days(MAX_SCHEDULE_SEARCH_DAYS);
+        let mut time = after.duration_trunc(TimeDelta::minutes(1)).ok()? + Duration::minutes(1);
+        // Skip whole days and hours that cannot match
+        while time < limit {
+            if !self.matches_day(time) {
+                time = time.duration_trunc(TimeDelta::days(1)).ok()? + Duration::days(1);
+            } else if !bit(self.hours, time.hour()) {
+                time = time.duration_trunc(TimeDelta::hours(1)).ok()? + Duration::hours(1);
+            } else if !bit(self.minutes, time.minute()) {
+                time += Duration::minutes(1);
+            } else {
+                return Some(time);
+            }
+        }
+        None
+    }
+}
+
+fn bit(set: u64, value: u32) -> bool {
+    set & (1 << value) != 0
+}
+
+fn parse_field(field: &str, name: &str, min: u32, max: u32, names: &[&str]) -> Result<u64, String> {
+    let value = |text: &str| -> Result<u32, String> {
+        let lower = text.to_ascii_lowercase();
+        let parsed = match names.iter().position(|n| *n == lower) {
+            // Month names count from 1, day names from 0
+            Some(index) => index as u32 + if names.len() == 12 { 1 } else { 0 },
+            None => text
+                .parse()
+                .map_err(|_| format!("{}: '{}' is not a number", name, text))?,
+        };
+        if parsed < min || parsed > max {
+            return Err(format!("{}: {} is outside {}-{}", name, parsed, min, max));
+        }
+        Ok(parsed)
+    };
+
+    let mut set = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step.parse().ok().filter(|step| *step > 0).ok_or_else(|| {
+                    format!("{}: step '{}' must be a positive number", name, step)
+                })?;
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (value(start)?, value(end)?),
+                // `5/15` runs from 5 to the end
+                None if step > 1 => (value(range)?, max),
+                None => {
+                    let single = value(range)?;
+                    (single, single)
+                }
+            },
+        };
+        if start > end {
+            return Err(format!("{}: range {} is backwards", name, range));
+        }
+        for v in (start..=end).step_by(step as usize) {
+            set |= 1 << v;
+        }
+    }
+    Ok(set)
+}
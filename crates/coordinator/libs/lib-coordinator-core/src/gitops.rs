@@ -0,0 +1,401 @@
+use std::collections::{BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+use crate::desired_state::{
+    ChangeResult, DesiredContainer, PlannedChange, has_reported, plan_node,
+};
+use crate::node_state::NodeStateCache;
+use crate::templates::TemplateProblem;
+
+/// Label on containers created by the GitOps reconciler, holding their spec hash.
+pub const GITOPS_LABEL: &str = "docklord.gitops";
+
+const GIT_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GitOpsMode {
+    /// Only compute plans.
+    DryRun,
+    /// Apply a plan once an operator approves it.
+    Manual,
+    /// Apply every plan right away.
+    Auto,
+}
+
+impl FromStr for GitOpsMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "dry-run" => Ok(GitOpsMode::DryRun),
+            "manual" => Ok(GitOpsMode::Manual),
+            "auto" => Ok(GitOpsMode::Auto),
+            other => Err(format!(
+                "unknown GitOps mode '{}' (dry-run, manual or auto)",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GitOpsConfig {
+    /// Anything `git clone` accepts.
+    pub repo: String,
+    pub branch: String,
+    /// Directory inside the repository holding the definitions; the root when empty.
+    pub path: String,
+    #[serde(serialize_with = "serialize_secs")]
+    pub interval: Duration,
+    pub mode: GitOpsMode,
+}
+
+fn serialize_secs<S: serde::Serializer>(
+    value: &Duration,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(value.as_secs())
+}
+
+/// A definition file: a stack of containers, possibly spread over several nodes.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StackFile {
+    containers: Vec<DesiredContainer>,
+}
+
+/// Reads every `*.json` stack file below `dir` (`.git` excluded), in path order.
+pub fn load_desired(dir: &Path) -> Result<Vec<DesiredContainer>, String> {
+    let mut files = Vec::new();
+    collect_json_files(dir, &mut files)
+        .map_err(|e| format!("cannot read {}: {}", dir.display(), e))?;
+    files.sort();
+
+    let mut desired = Vec::new();
+    let mut seen = HashSet::new();
+    for file in files {
+        let relative = file
+            .strip_prefix(dir)
+            .unwrap_or(&file)
+            .display()
+            .to_string();
+        let bytes = std::fs::read(&file).map_err(|e| format!("cannot read {}: {}", relative, e))?;
+        let stack: StackFile =
+            serde_json::from_slice(&bytes).map_err(|e| format!("{}: {}", relative, e))?;
+        for container in stack.containers {
+            let mut problems = container.problems();
+            if container.node_id.is_empty() {
+                problems.push(TemplateProblem::new("node_id", "is required"));
+            }
+            if !problems.is_empty() {
+                let detail: Vec<String> = problems
+                    .iter()
+                    .map(|p| format!("{}: {}", p.field, p.message))
+                    .collect();
+                return Err(format!(
+                    "{}: container '{}': {}",
+                    relative,
+                    container.name,
+                    detail.join("; ")
+                ));
+            }
+            if !seen.insert((container.node_id.clone(), container.name.clone())) {
+                return Err(format!(
+                    "{}: container '{}' on node '{}' is defined twice",
+                    relative, container.name, container.node_id
+                ));
+            }
+            desired.push(container);
+        }
+    }
+    Ok(desired)
+}
+
+fn collect_json_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            if entry.file_name() != ".git" {
+                collect_json_files(&path, files)?;
+            }
+        } else if path.extension().is_some_and(|ext| ext == "json") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// What has to change on the nodes to match a commit.
+#[derive(Debug, Clone, Serialize)]
+pub struct GitOpsPlan {
+    pub id: String,
+    pub commit: String,
+    pub created_at: i64,
+    pub changes: Vec<PlannedChange>,
+}
+
+/// Diffs the desired containers against the coordinator's cached node state.
+/// Containers not created by GitOps are never touched.
+pub fn plan_changes(
+    desired: &[DesiredContainer],
+    node_states: &NodeStateCache,
+) -> (Vec<PlannedChange>, Vec<String>) {
+    let mut node_ids: BTreeSet<String> = desired.iter().map(|d| d.node_id.clone()).collect();
+    node_ids.extend(
+        node_states
+            .snapshot()
+            .into_iter()
+            .map(|(node_id, _)| node_id),
+    );
+
+    let mut changes = Vec::new();
+    let mut skipped = Vec::new();
+    for node_id in node_ids {
+        let wanted: Vec<&DesiredContainer> =
+            desired.iter().filter(|d| d.node_id == node_id).collect();
+        let state = node_states.get(&node_id).filter(has_reported);
+        let Some(state) = state else {
+            if !wanted.is_empty() {
+                skipped.push(format!("node '{}' is not connected", node_id));
+            }
+            continue;
+        };
+        let (node_changes, node_skipped) =
+            plan_node(&node_id, &wanted, &state.containers, GITOPS_LABEL);
+        changes.extend(node_changes);
+        skipped.extend(node_skipped);
+    }
+    (changes, skipped)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncRecord {
+    pub at: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Definitions that could not be planned, e.g. for offline nodes.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ApplyRecord {
+    pub at: i64,
+    pub plan_id: String,
+    pub commit: String,
+    pub results: Vec<ChangeResult>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GitOpsState {
+    pub last_sync: Option<SyncRecord>,
+    /// Latest plan with changes; in manual mode it waits for approval.
+    pub plan: Option<GitOpsPlan>,
+    pub last_apply: Option<ApplyRecord>,
+}
+
+/// Shared handle of the GitOps reconciler: its configuration, the checkout
+/// and what the last sync found.
+#[derive(Clone)]
+pub struct GitOps {
+    pub config: Arc<GitOpsConfig>,
+    checkout: Arc<PathBuf>,
+    state: Arc<Mutex<GitOpsState>>,
+    // One sync or apply at a time
+    busy: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl GitOps {
+    /// The repository is checked out into `gitops` in the state directory.
+    pub fn new(config: GitOpsConfig, state_dir: Option<&Path>) -> Self {
+        let checkout = state_dir
+            .map(Path::to_path_buf)
+            .unwrap_or_else(std::env::temp_dir)
+            .join("gitops");
+        Self {
+            config: Arc::new(config),
+            checkout: Arc::new(checkout),
+            state: Arc::new(Mutex::new(GitOpsState::default())),
+            busy: Arc::new(tokio::sync::Mutex::new(())),
+        }
+    }
+
+    pub fn state(&self) -> GitOpsState {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn update_state<R>(&self, f: impl FnOnce(&mut GitOpsState) -> R) -> R {
+        f(&mut self.state.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    pub fn record_sync_error(&self, error: String) {
+        self.update_state(|state| {
+            state.last_sync = Some(SyncRecord {
+                at: now_secs(),
+                commit: None,
+                error: Some(error),
+                skipped: Vec::new(),
+            });
+        });
+    }
+
+    /// Records a successful sync and its plan, which replaces any earlier one.
+    /// A plan with the same commit and changes keeps its id, so an approval
+    /// given for it stays valid across syncs. Returns the plan if there is
+    /// anything to change.
+    pub fn record_plan(
+        &self,
+        commit: String,
+        changes: Vec<PlannedChange>,
+        skipped: Vec<String>,
+    ) -> Option<GitOpsPlan> {
+        let now = now_secs();
+        self.update_state(|state| {
+            state.last_sync = Some(SyncRecord {
+                at: now,
+                commit: Some(commit.clone()),
+                error: None,
+                skipped,
+            });
+            if changes.is_empty() {
+                state.plan = None;
+                return None;
+            }
+            let unchanged = state
+                .plan
+                .as_ref()
+                .filter(|plan| plan.commit == commit && same_changes(&plan.changes, &changes));
+            let (id, created_at) = match unchanged {
+                Some(plan) => (plan.id.clone(), plan.created_at),
+                None => (
+                    format!("{}-{:x}", &commit[..commit.len().min(12)], now),
+                    now,
+                ),
+            };
+            let plan = GitOpsPlan {
+                id,
+                commit,
+                created_at,
+                changes,
+            };
+            state.plan = Some(plan.clone());
+            Some(plan)
+        })
+    }
+
+    /// The current plan if its id is `plan_id`.
+    pub fn plan(&self, plan_id: &str) -> Option<GitOpsPlan> {
+        self.state().plan.filter(|plan| plan.id == plan_id)
+    }
+
+    /// Records the outcome of applying `plan`, which is then done with.
+    pub fn record_apply(&self, plan: &GitOpsPlan, results: Vec<ChangeResult>) {
+        self.update_state(|state| {
+            if state.plan.as_ref().is_some_and(|p| p.id == plan.id) {
+                state.plan = None;
+            }
+            state.last_apply = Some(ApplyRecord {
+                at: now_secs(),
+                plan_id: plan.id.clone(),
+                commit: plan.commit.clone(),
+                results,
+            });
+        });
+    }
+
+    /// Held while syncing or applying.
+    pub async fn lock(&self) -> tokio::sync::MutexGuard<'_, ()> {
+        self.busy.lock().await
+    }
+
+    /// Clones or fast-forwards the checkout to the tip of the branch and
+    /// returns the commit with the definitions it holds.
+    pub async fn fetch_desired(&self) -> Result<(String, Vec<DesiredContainer>), String> {
+        let checkout = self.checkout.as_path();
+        if checkout.join(".git").is_dir() {
+            git(
+                Some(checkout),
+                &["fetch", "--depth", "1", "origin", &self.config.branch],
+            )
+            .await?;
+            git(Some(checkout), &["reset", "--hard", "FETCH_HEAD"]).await?;
+        } else {
+            let _ = tokio::fs::remove_dir_all(checkout).await;
+            let target = checkout.display().to_string();
+            git(
+                None,
+                &[
+                    "clone",
+                    "--depth",
+                    "1",
+                    "--branch",
+                    &self.config.branch,
+                    &self.config.repo,
+                    &target,
+                ],
+            )
+            .await?;
+        }
+        let commit = git(Some(checkout), &["rev-parse", "HEAD"]).await?;
+
+        let dir = checkout.join(&self.config.path);
+        let desired = tokio::task::spawn_blocking(move || load_desired(&dir))
+            .await
+            .map_err(|e| e.to_string())??;
+        Ok((commit, desired))
+    }
+}
+
+fn same_changes(a: &[PlannedChange], b: &[PlannedChange]) -> bool {
+    let key = |change: &PlannedChange| {
+        (
+            change.node_id.clone(),
+            change.name.clone(),
+            change.action,
+            change.desired.as_ref().map(DesiredContainer::spec_hash),
+        )
+    };
+    a.len() == b.len() && a.iter().map(key).eq(b.iter().map(key))
+}
+
+async fn git(dir: Option<&Path>, args: &[&str]) -> Result<String, String> {
+    let mut command = Command::new("git");
+    if let Some(dir) = dir {
+        command.arg("-C").arg(dir);
+    }
+    command
+        .args(args)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null())
+        .kill_on_drop(true);
+    let output = tokio::time::timeout(GIT_TIMEOUT, command.output())
+        .await
+        .map_err(|_| format!("git {} timed out", args[0]))?
+        .map_err(|e| format!("cannot run git: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
@@ -0,0 +1,56 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use serde::{Serialize, de::DeserializeOwned};
+use tracing::warn;
+
+/// In-memory value mirrored to a JSON file in the coordinator's state directory.
+/// Without a path it behaves as a plain in-memory value.
+pub struct JsonStore<T> {
+    path: Option<PathBuf>,
+    data: RwLock<T>,
+}
+
+impl<T: Serialize + DeserializeOwned + Default> JsonStore<T> {
+    /// Loads `state_dir/file_name`, starting empty if it is missing or unreadable.
+    pub fn open(state_dir: Option<&Path>, file_name: &str) -> Self {
+        let path = state_dir.map(|dir| dir.join(file_name));
+        let data = path
+            .as_deref()
+            .and_then(|path| match std::fs::read(path) {
+                Ok(bytes) => serde_json::from_slice(&bytes)
+                    .inspect_err(|e| warn!("Ignoring corrupt state file {:?}: {}", path, e))
+                    .ok(),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+                Err(e) => {
+                    warn!("Failed to read state file {:?}: {}", path, e);
+                    None
+                }
+            })
+            .unwrap_or_default();
+
+        Self {
+            path,
+            data: RwLock::new(data),
+        }
+    }
+
+    pub fn read<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        f(&self.data.read().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// Applies `f` and writes the result to disk (temp file + rename) before returning.
+    pub fn update<R>(&self, f: impl FnOnce(&mut T) -> R) -> io::Result<R> {
+        let mut data = self.data.write().unwrap_or_else(|e| e.into_inner());
+        let result = f(&mut data);
+
+        if let Some(path) = &self.path {
+            let bytes = serde_json::to_vec_pretty(&*data).map_err(io::Error::other)?;
+            let tmp_path = path.with_extension("json.tmp");
+            std::fs::write(&tmp_path, bytes)?;
+            std::fs::rename(&tmp_path, path)?;
+        }
+        Ok(result)
+    }
+}
@@ -0,0 +1,73 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use proto::generated::RequestType;
+
+/// Upper bounds of the latency buckets, in seconds. Node round trips range
+/// from a few milliseconds for cached lookups to minutes for pulls and prunes.
+pub const LATENCY_BUCKETS: [f64; 15] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0,
+];
+
+/// Cumulative counts per bucket, as Prometheus histograms keep them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LatencyHistogram {
+    /// Requests that took at most `LATENCY_BUCKETS[i]`.
+    pub buckets: [u64; LATENCY_BUCKETS.len()],
+    pub count: u64,
+    /// Seconds, summed over every request.
+    pub sum: f64,
+}
+
+impl LatencyHistogram {
+    fn observe(&mut self, seconds: f64) {
+        for (bucket, bound) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+        self.count += 1;
+        self.sum += seconds;
+    }
+}
+
+/// Time from sending a request to a node until its reply arrived, by node
+/// and request type. Counts start at zero with each coordinator.
+#[derive(Debug, Clone, Default)]
+pub struct RequestLatency {
+    histograms: Arc<Mutex<BTreeMap<(String, i32), LatencyHistogram>>>,
+}
+
+impl RequestLatency {
+    pub fn observe(&self, node_id: &str, request_type: i32, elapsed: Duration) {
+        self.histograms
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry((node_id.to_string(), request_type))
+            .or_default()
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Every histogram by node id and request type name, e.g. `get_containers`.
+    pub fn snapshot(&self) -> Vec<(String, String, LatencyHistogram)> {
+        self.histograms
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|((node_id, request_type), histogram)| {
+                (
+                    node_id.clone(),
+                    request_type_name(*request_type),
+                    histogram.clone(),
+                )
+            })
+            .collect()
+    }
+}
+
+pub(crate) fn request_type_name(request_type: i32) -> String {
+    RequestType::try_from(request_type)
+        .map(|request_type| request_type.as_str_name().to_ascii_lowercase())
+        .unwrap_or_else(|_| request_type.to_string())
+}
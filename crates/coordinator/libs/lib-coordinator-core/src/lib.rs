@@ -1,14 +1,127 @@
+pub mod annotations;
 pub mod auth_state;
+pub mod authorization;
+pub mod autostart;
+pub mod cleanup;
+pub mod command_router;
+pub mod desired_state;
+pub mod event_log;
+pub mod favorites;
+pub mod freeze_windows;
+pub mod gitops;
+pub mod json_store;
+pub mod latency;
+pub mod log_export;
+pub mod log_forwarding;
+pub mod node_groups;
+pub mod node_state;
+pub mod pending;
+pub mod report_delivery;
+pub mod reports;
+pub mod secrets;
+pub mod session_recording;
+pub mod templates;
+pub mod tenants;
+pub mod trace;
+pub mod trash;
+pub mod ws_connections;
 
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use dashmap::DashMap;
 use proto::generated::Envelope;
-use tokio::sync::oneshot;
+use tokio::sync::{mpsc, oneshot};
 
-pub use auth_state::AuthState;
+pub use annotations::{AnnotationStore, Annotations};
+pub use auth_state::{AuthState, ConnectedNode, verify_node};
+pub use authorization::{
+    AllowAll, Authorizer, AuthzDecision, AuthzRequest, SharedAuthorizer, WebhookAuthorizer,
+};
+pub use autostart::{
+    AutostartEntry, AutostartOutcome, AutostartPlan, AutostartRun, AutostartStore,
+};
+pub use cleanup::{CleanupPolicy, CleanupRuleConfig, CleanupStore, DEFAULT_CLEANUP_INTERVAL_SECS};
+pub use command_router::{CommandRouter, RouteError};
+pub use desired_state::{
+    ChangeAction, ChangeResult, DESIRED_LABEL, DesiredContainer, DesiredRunState,
+    DesiredStateStore, NodeDesiredState, PlannedChange,
+};
+pub use event_log::{EventLog, EventQuery, StoredEvent};
+pub use favorites::{Favorite, FavoritesStore};
+pub use freeze_windows::{
+    ActiveFreeze, CronSchedule, FreezeStore, FreezeWindow, MAX_FREEZE_MINUTES,
+};
+pub use gitops::{
+    ApplyRecord, GITOPS_LABEL, GitOps, GitOpsConfig, GitOpsMode, GitOpsPlan, GitOpsState,
+    SyncRecord,
+};
+pub use json_store::JsonStore;
+pub use latency::{LATENCY_BUCKETS, LatencyHistogram, RequestLatency};
+pub use log_export::{ExportCursor, LogExportCursors};
+pub use log_forwarding::{LogForwardConfig, LogForwardingStore, LogSinkKind, NODE_ID_LOG_LABEL};
+pub use node_groups::NodeGroupStore;
+pub use node_state::{
+    ContainerCounts, MemorySample, NodeCapability, NodeConnectionEvent, NodeState, NodeStateCache,
+};
+pub use pending::{
+    DEFAULT_PENDING_TTL, ExpiredRequests, ExpiryReason, PendingReply, spawn_pending_sweeper,
+};
+pub use report_delivery::ReportDelivery;
+pub use reports::{
+    DailyReport, EmailConfig, ReportConfig, ReportRow, ReportStore, ReportSummary, ResourcePeaks,
+};
+pub use secrets::{SecretError, SecretInfo, SecretRef, SecretStore};
+pub use session_recording::{RecordingInfo, SessionRecorder, SessionRecording};
+pub use templates::{
+    ContainerSpec, StoredTemplate, TEMPLATE_LABEL, Template, TemplateProblem, TemplateStore,
+    TemplateVariable, parse_port,
+};
+pub use tenants::{NodeAssignment, Tenant, TenantQuota, TenantStore, api_key_hash};
+pub use trace::TraceContext;
+pub use trash::{TrashPolicy, TrashStore};
+pub use ws_connections::{WsCap, WsConnection, WsConnections};
 
-pub type PendingResponses = Arc<DashMap<(String, i32), oneshot::Sender<Envelope>>>;
+/// A request waiting for its reply. Only the node it was sent to may answer it.
+#[derive(Debug)]
+pub struct PendingRequest<T> {
+    pub node_id: String,
+    pub reply: T,
+    /// When the request was registered, just before it was sent.
+    pub sent_at: Instant,
+    /// After this the sweeper drops the request; `None` keeps it for as long
+    /// as someone waits for the reply.
+    pub deadline: Option<Instant>,
+}
+
+impl<T> PendingRequest<T> {
+    /// Waits at most `DEFAULT_PENDING_TTL`.
+    pub fn new(node_id: &str, reply: T) -> Self {
+        let sent_at = Instant::now();
+        Self {
+            node_id: node_id.to_string(),
+            reply,
+            sent_at,
+            deadline: Some(sent_at + DEFAULT_PENDING_TTL),
+        }
+    }
+
+    /// Waits at most `ttl`, or without a deadline for `None`, e.g. for
+    /// streams that last as long as their client.
+    pub fn with_ttl(mut self, ttl: Option<Duration>) -> Self {
+        self.deadline = ttl.map(|ttl| self.sent_at + ttl);
+        self
+    }
+}
+
+pub type PendingResponses = Arc<DashMap<(String, i32), PendingRequest<oneshot::Sender<Envelope>>>>;
+
+/// Requests whose node replies arrive as a sequence of envelopes (build output, log follow, ...).
+pub type PendingStreams = Arc<DashMap<(String, i32), PendingRequest<mpsc::Sender<Envelope>>>>;
+
+/// Connected nodes by id, each with its credentials, the channel its commands
+/// are routed through and a channel for pushed updates.
+pub type NodeChannels = Arc<DashMap<String, Arc<ConnectedNode>>>;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ServerRequestByUser {
@@ -16,3 +129,18 @@ pub struct ServerRequestByUser {
     pub id: String,
     pub password: String,
 }
+
+impl ServerRequestByUser {
+    /// A command for the node with these credentials, carrying the ids of the
+    /// request being served so the node's logs and replies can be matched to it.
+    pub fn new(id: String, password: String, mut envelope: Envelope) -> Self {
+        if let Some(trace) = TraceContext::current() {
+            trace.stamp(&mut envelope);
+        }
+        Self {
+            envelope,
+            id,
+            password,
+        }
+    }
+}
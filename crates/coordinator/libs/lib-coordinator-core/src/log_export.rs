@@ -0,0 +1,78 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::json_store::JsonStore;
+
+/// Position after the last exported line of a container: its timestamp and how
+/// many lines with that same timestamp were already exported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ExportCursor {
+    pub time_nanos: i64,
+    pub line: usize,
+}
+
+/// How far each log consumer (a Vector or Fluent Bit instance) has read, per
+/// node and container. Persisted in `log_export_cursors.json`.
+#[derive(Clone)]
+pub struct LogExportCursors {
+    store: Arc<JsonStore<BTreeMap<String, BTreeMap<String, ExportCursor>>>>,
+}
+
+impl LogExportCursors {
+    pub fn open(state_dir: Option<&Path>) -> Self {
+        Self {
+            store: Arc::new(JsonStore::open(state_dir, "log_export_cursors.json")),
+        }
+    }
+
+    pub fn get(&self, consumer: &str, node_id: &str, container: &str) -> Option<ExportCursor> {
+        self.store.read(|consumers| {
+            consumers
+                .get(consumer)
+                .and_then(|cursors| cursors.get(&key(node_id, container)))
+                .copied()
+        })
+    }
+
+    /// Moves the consumer's cursors forward after an export.
+    pub fn advance(
+        &self,
+        consumer: &str,
+        cursors: Vec<(String, String, ExportCursor)>,
+    ) -> io::Result<()> {
+        if cursors.is_empty() {
+            return Ok(());
+        }
+        self.store.update(|consumers| {
+            let stored = consumers.entry(consumer.to_string()).or_default();
+            for (node_id, container, cursor) in cursors {
+                let entry = stored.entry(key(&node_id, &container)).or_insert(cursor);
+                *entry = (*entry).max(cursor);
+            }
+        })
+    }
+
+    /// Consumers with the number of containers each has read from.
+    pub fn consumers(&self) -> Vec<(String, usize)> {
+        self.store.read(|consumers| {
+            consumers
+                .iter()
+                .map(|(consumer, cursors)| (consumer.clone(), cursors.len()))
+                .collect()
+        })
+    }
+
+    /// Forgets the consumer; its next export starts over. Returns whether it existed.
+    pub fn reset(&self, consumer: &str) -> io::Result<bool> {
+        self.store
+            .update(|consumers| consumers.remove(consumer).is_some())
+    }
+}
+
+fn key(node_id: &str, container: &str) -> String {
+    format!("{}/{}", node_id, container)
+}
@@ -0,0 +1,107 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use proto::generated::{ConfigureLogForwarding, LogForward};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::json_store::JsonStore;
+
+/// Label the coordinator adds to every forwarded line, naming the node it came from.
+pub const NODE_ID_LOG_LABEL: &str = "node_id";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LogSinkKind {
+    /// Loki push API (`http://loki:3100/loki/api/v1/push`).
+    Loki,
+    /// RFC 5424 over `udp://host:port` or `tcp://host:port`.
+    Syslog,
+    /// JSON lines appended to a file on the node's host.
+    File,
+}
+
+impl LogSinkKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogSinkKind::Loki => "loki",
+            LogSinkKind::Syslog => "syslog",
+            LogSinkKind::File => "file",
+        }
+    }
+}
+
+/// Containers of one node whose logs are shipped to one sink.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct LogForwardConfig {
+    pub name: String,
+    /// Container names; a trailing `*` matches every name with that prefix.
+    pub containers: Vec<String>,
+    pub sink: LogSinkKind,
+    pub target: String,
+    /// Added to every line (Loki stream labels, syslog structured data, file fields).
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+}
+
+/// Per-node log forwards, persisted in `log_forwarding.json`. The node runs
+/// them; the coordinator sends them whenever the node connects.
+#[derive(Clone)]
+pub struct LogForwardingStore {
+    store: Arc<JsonStore<BTreeMap<String, Vec<LogForwardConfig>>>>,
+}
+
+impl LogForwardingStore {
+    pub fn open(state_dir: Option<&Path>) -> Self {
+        Self {
+            store: Arc::new(JsonStore::open(state_dir, "log_forwarding.json")),
+        }
+    }
+
+    pub fn get(&self, node_id: &str) -> Vec<LogForwardConfig> {
+        self.store
+            .read(|forwards| forwards.get(node_id).cloned().unwrap_or_default())
+    }
+
+    /// Replaces the node's forwards; an empty list removes them.
+    pub fn set(&self, node_id: &str, configs: Vec<LogForwardConfig>) -> io::Result<()> {
+        self.store.update(|forwards| {
+            if configs.is_empty() {
+                forwards.remove(node_id);
+            } else {
+                forwards.insert(node_id.to_string(), configs);
+            }
+        })
+    }
+
+    /// The node command installing `configs`, each labelled with the node id.
+    pub fn command(
+        node_id: &str,
+        request_id: &str,
+        configs: &[LogForwardConfig],
+    ) -> ConfigureLogForwarding {
+        ConfigureLogForwarding {
+            request_id: request_id.to_string(),
+            forwards: configs
+                .iter()
+                .map(|config| {
+                    let mut labels: std::collections::HashMap<String, String> =
+                        config.labels.clone().into_iter().collect();
+                    labels
+                        .entry(NODE_ID_LOG_LABEL.to_string())
+                        .or_insert_with(|| node_id.to_string());
+                    LogForward {
+                        name: config.name.clone(),
+                        containers: config.containers.clone(),
+                        sink: config.sink.as_str().to_string(),
+                        target: config.target.clone(),
+                        labels,
+                    }
+                })
+                .collect(),
+        }
+    }
+}
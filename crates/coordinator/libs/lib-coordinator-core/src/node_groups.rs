@@ -0,0 +1,40 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::json_store::JsonStore;
+
+/// Named sets of node ids that placements choose from, persisted in
+/// `node_groups.json`. A node may be in several groups.
+#[derive(Clone)]
+pub struct NodeGroupStore {
+    store: Arc<JsonStore<BTreeMap<String, Vec<String>>>>,
+}
+
+impl NodeGroupStore {
+    pub fn open(state_dir: Option<&Path>) -> Self {
+        Self {
+            store: Arc::new(JsonStore::open(state_dir, "node_groups.json")),
+        }
+    }
+
+    pub fn list(&self) -> BTreeMap<String, Vec<String>> {
+        self.store.read(|groups| groups.clone())
+    }
+
+    pub fn get(&self, name: &str) -> Option<Vec<String>> {
+        self.store.read(|groups| groups.get(name).cloned())
+    }
+
+    /// Returns whether the group was created (false if it replaced one).
+    pub fn put(&self, name: &str, nodes: Vec<String>) -> io::Result<bool> {
+        self.store
+            .update(|groups| groups.insert(name.to_string(), nodes).is_none())
+    }
+
+    /// Returns whether the group existed.
+    pub fn delete(&self, name: &str) -> io::Result<bool> {
+        self.store.update(|groups| groups.remove(name).is_some())
+    }
+}
@@ -0,0 +1,398 @@
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use dashmap::DashMap;
+use proto::generated::{ContainerDelta, ContainerStatus, CoordinatorLink, NodeCapabilities};
+use tokio::sync::broadcast;
+
+use crate::latency::RequestLatency;
+use crate::pending::ExpiredRequests;
+use crate::ws_connections::WsConnections;
+
+// Container events older than this are dropped from the per-node history
+const EVENT_HISTORY_WINDOW: Duration = Duration::from_secs(3600);
+// Connection changes kept for slow subscribers; they only need the latest per node
+const CONNECTION_EVENTS_CAPACITY: usize = 256;
+// A node that misses this many heartbeats in a row is taken to be unresponsive
+const MISSED_HEARTBEATS: u32 = 3;
+
+/// A node connecting to or disconnecting from the coordinator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeConnectionEvent {
+    pub node_id: String,
+    pub connected: bool,
+}
+
+/// Per-state container counts; `other` covers created, dead, removing, ...
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ContainerCounts {
+    pub total: usize,
+    pub running: usize,
+    pub exited: usize,
+    pub paused: usize,
+    pub restarting: usize,
+    pub other: usize,
+}
+
+impl std::ops::AddAssign for ContainerCounts {
+    fn add_assign(&mut self, other: Self) {
+        self.total += other.total;
+        self.running += other.running;
+        self.exited += other.exited;
+        self.paused += other.paused;
+        self.restarting += other.restarting;
+        self.other += other.other;
+    }
+}
+
+/// Optional operations a node advertises support for when it connects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeCapability {
+    ExecTty,
+    Stats,
+    Checkpoint,
+}
+
+impl NodeCapability {
+    pub fn name(self) -> &'static str {
+        match self {
+            NodeCapability::ExecTty => "exec with a TTY",
+            NodeCapability::Stats => "container stats",
+            NodeCapability::Checkpoint => "container checkpoints",
+        }
+    }
+
+    pub fn supported_by(self, capabilities: &NodeCapabilities) -> bool {
+        match self {
+            NodeCapability::ExecTty => capabilities.exec_tty,
+            NodeCapability::Stats => capabilities.stats,
+            NodeCapability::Checkpoint => capabilities.checkpoint,
+        }
+    }
+}
+
+/// Host memory from the node's last metrics, in bytes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MemorySample {
+    pub total: u64,
+    pub available: u64,
+    /// Memory limits of the running containers, summed.
+    pub reserved: u64,
+    pub sampled_at: SystemTime,
+}
+
+impl MemorySample {
+    /// What a new container can count on: free now, and not promised to the
+    /// running containers' limits.
+    pub fn free(&self) -> u64 {
+        self.available.min(self.total.saturating_sub(self.reserved))
+    }
+}
+
+/// What the coordinator last heard from a node, kept after it disconnects.
+#[derive(Debug, Clone)]
+pub struct NodeState {
+    pub online: bool,
+    pub connected_at: SystemTime,
+    pub last_seen: SystemTime,
+    pub disconnected_at: Option<SystemTime>,
+    /// Address the current (or last) connection came from, if the transport reports one.
+    pub remote_addr: Option<SocketAddr>,
+    /// How often the node connected since the coordinator started.
+    pub connections: u32,
+    /// Platform and optional operations the node advertised on its last connect;
+    /// `None` for nodes that do not advertise them.
+    pub capabilities: Option<NodeCapabilities>,
+    /// How often the node sends heartbeats on this connection; `None` until
+    /// its first one, and for nodes that do not send them.
+    pub heartbeat_interval: Option<Duration>,
+    /// Which of its configured coordinators the node reached and how often it
+    /// failed over; `None` for nodes that do not report it.
+    pub link: Option<CoordinatorLink>,
+    /// Latest container states pushed by the node (name, state, created only).
+    pub containers: Vec<ContainerStatus>,
+    /// When `containers` was last pushed; `None` until the node's first report.
+    pub containers_updated_at: Option<SystemTime>,
+    /// `None` until the node's metrics were fetched.
+    pub memory: Option<MemorySample>,
+    recent_events: VecDeque<SystemTime>,
+}
+
+impl NodeState {
+    fn new(
+        now: SystemTime,
+        remote_addr: Option<SocketAddr>,
+        capabilities: Option<NodeCapabilities>,
+        link: Option<CoordinatorLink>,
+    ) -> Self {
+        Self {
+            online: true,
+            connected_at: now,
+            last_seen: now,
+            disconnected_at: None,
+            remote_addr,
+            connections: 1,
+            capabilities,
+            heartbeat_interval: None,
+            link,
+            containers: Vec::new(),
+            containers_updated_at: None,
+            memory: None,
+            recent_events: VecDeque::new(),
+        }
+    }
+
+    /// How long a connected node has been silent, once it missed enough
+    /// heartbeats to be taken for hung: its connection may still look open
+    /// while requests to it would only time out. `None` while it keeps up,
+    /// and for nodes that do not send heartbeats.
+    pub fn unresponsive_for(&self) -> Option<Duration> {
+        let interval = self.heartbeat_interval?;
+        let silent = self.last_seen.elapsed().unwrap_or_default();
+        (self.online && silent > interval * MISSED_HEARTBEATS).then_some(silent)
+    }
+
+    /// Fixed breakdown of the cached containers for node listings.
+    pub fn container_counts(&self) -> ContainerCounts {
+        let mut counts = ContainerCounts {
+            total: self.containers.len(),
+            ..Default::default()
+        };
+        for container in &self.containers {
+            match container.status.as_str() {
+                "running" => counts.running += 1,
+                "exited" => counts.exited += 1,
+                "paused" => counts.paused += 1,
+                "restarting" => counts.restarting += 1,
+                _ => counts.other += 1,
+            }
+        }
+        counts
+    }
+
+    /// Container counts grouped by state ("running", "exited", ...).
+    pub fn state_counts(&self) -> HashMap<String, usize> {
+        let mut counts = HashMap::new();
+        for container in &self.containers {
+            *counts.entry(container.status.clone()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Number of container events received within `window` (capped at one hour).
+    pub fn events_within(&self, window: Duration) -> usize {
+        let Some(since) = SystemTime::now().checked_sub(window) else {
+            return self.recent_events.len();
+        };
+        self.recent_events.iter().filter(|at| **at >= since).count()
+    }
+}
+
+/// Per-node state cache fed by the gRPC service, keyed by node id.
+#[derive(Debug, Clone)]
+pub struct NodeStateCache {
+    nodes: Arc<DashMap<String, NodeState>>,
+    connections: broadcast::Sender<NodeConnectionEvent>,
+    latency: RequestLatency,
+    expired: ExpiredRequests,
+    ws_connections: WsConnections,
+}
+
+impl Default for NodeStateCache {
+    fn default() -> Self {
+        Self {
+            nodes: Arc::default(),
+            connections: broadcast::channel(CONNECTION_EVENTS_CAPACITY).0,
+            latency: RequestLatency::default(),
+            expired: ExpiredRequests::default(),
+            ws_connections: WsConnections::default(),
+        }
+    }
+}
+
+impl NodeStateCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Round-trip times of the requests nodes answered.
+    pub fn latency(&self) -> &RequestLatency {
+        &self.latency
+    }
+
+    /// Requests dropped unanswered by the pending request sweeper.
+    pub fn expired_requests(&self) -> &ExpiredRequests {
+        &self.expired
+    }
+
+    /// WebSocket clients connected to the coordinator, by credential.
+    pub fn ws_connections(&self) -> &WsConnections {
+        &self.ws_connections
+    }
+
+    /// Connects and disconnects from now on. A connect is announced once the
+    /// node's channel is in `NodeChannels`.
+    pub fn subscribe_connections(&self) -> broadcast::Receiver<NodeConnectionEvent> {
+        self.connections.subscribe()
+    }
+
+    pub fn mark_connected(
+        &self,
+        node_id: &str,
+        remote_addr: Option<SocketAddr>,
+        capabilities: Option<NodeCapabilities>,
+        link: Option<CoordinatorLink>,
+    ) {
+        let now = SystemTime::now();
+        self.nodes
+            .entry(node_id.to_string())
+            .and_modify(|state| {
+                state.online = true;
+                state.connected_at = now;
+                state.last_seen = now;
+                state.disconnected_at = None;
+                state.remote_addr = remote_addr;
+                state.connections += 1;
+                state.capabilities = capabilities.clone();
+                state.heartbeat_interval = None;
+                state.link = link.clone();
+            })
+            .or_insert_with(|| NodeState::new(now, remote_addr, capabilities, link));
+        self.announce(node_id, true);
+    }
+
+    pub fn mark_disconnected(&self, node_id: &str) {
+        if let Some(mut state) = self.nodes.get_mut(node_id) {
+            state.online = false;
+            state.disconnected_at = Some(SystemTime::now());
+        }
+        self.announce(node_id, false);
+    }
+
+    fn announce(&self, node_id: &str, connected: bool) {
+        // Fails only when nobody listens
+        let _ = self.connections.send(NodeConnectionEvent {
+            node_id: node_id.to_string(),
+            connected,
+        });
+    }
+
+    pub fn touch(&self, node_id: &str) {
+        if let Some(mut state) = self.nodes.get_mut(node_id) {
+            state.last_seen = SystemTime::now();
+        }
+    }
+
+    /// Records a heartbeat, promising the next one within `interval`.
+    pub fn heartbeat(&self, node_id: &str, interval: Duration) {
+        if let Some(mut state) = self.nodes.get_mut(node_id) {
+            state.last_seen = SystemTime::now();
+            state.heartbeat_interval = Some(interval).filter(|interval| !interval.is_zero());
+        }
+    }
+
+    /// See `NodeState::unresponsive_for`.
+    pub fn unresponsive_for(&self, node_id: &str) -> Option<Duration> {
+        self.nodes.get(node_id)?.unresponsive_for()
+    }
+
+    pub fn update_containers(&self, node_id: &str, containers: Vec<ContainerStatus>) {
+        if let Some(mut state) = self.nodes.get_mut(node_id) {
+            state.containers = containers;
+            state.containers_updated_at = Some(SystemTime::now());
+        }
+    }
+
+    /// Applies a pushed delta to the cached containers and returns the names
+    /// of all of them. `None` until the node has sent a full snapshot, as a
+    /// delta says nothing about the containers it leaves out.
+    pub fn apply_container_delta(
+        &self,
+        node_id: &str,
+        delta: &ContainerDelta,
+    ) -> Option<Vec<String>> {
+        let mut state = self.nodes.get_mut(node_id)?;
+        state.containers_updated_at?;
+        state
+            .containers
+            .retain(|container| !delta.removed.contains(&container.container_id));
+        for changed in delta.changed.iter().chain(&delta.added) {
+            match state
+                .containers
+                .iter_mut()
+                .find(|container| container.container_id == changed.container_id)
+            {
+                Some(container) => *container = changed.clone(),
+                None => state.containers.push(changed.clone()),
+            }
+        }
+        state.containers_updated_at = Some(SystemTime::now());
+        Some(
+            state
+                .containers
+                .iter()
+                .map(|container| container.container_id.clone())
+                .collect(),
+        )
+    }
+
+    /// Keeps the host memory from metrics the node just sent.
+    pub fn record_memory(&self, node_id: &str, total: u64, available: u64, reserved: u64) {
+        if let Some(mut state) = self.nodes.get_mut(node_id) {
+            state.memory = Some(MemorySample {
+                total,
+                available,
+                reserved,
+                sampled_at: SystemTime::now(),
+            });
+        }
+    }
+
+    /// Counts `bytes` as reserved until the next sample, so placements made
+    /// before the node reports their containers do not all pick it.
+    pub fn reserve_memory(&self, node_id: &str, bytes: u64) {
+        if let Some(mut state) = self.nodes.get_mut(node_id)
+            && let Some(memory) = state.memory.as_mut()
+        {
+            memory.reserved = memory.reserved.saturating_add(bytes);
+        }
+    }
+
+    pub fn record_event(&self, node_id: &str) {
+        if let Some(mut state) = self.nodes.get_mut(node_id) {
+            let now = SystemTime::now();
+            state.recent_events.push_back(now);
+            if let Some(cutoff) = now.checked_sub(EVENT_HISTORY_WINDOW) {
+                while state.recent_events.front().is_some_and(|at| *at < cutoff) {
+                    state.recent_events.pop_front();
+                }
+            }
+        }
+    }
+
+    /// The node's platform, when it advertised `capability` as unsupported.
+    /// Nodes that advertise nothing are assumed to support everything.
+    pub fn lacks(&self, node_id: &str, capability: NodeCapability) -> Option<String> {
+        let state = self.nodes.get(node_id)?;
+        let capabilities = state.capabilities.as_ref()?;
+        (!capability.supported_by(capabilities))
+            .then(|| format!("{}/{}", capabilities.os, capabilities.arch))
+    }
+
+    pub fn get(&self, node_id: &str) -> Option<NodeState> {
+        self.nodes.get(node_id).map(|state| state.value().clone())
+    }
+
+    /// Snapshot of all known nodes, sorted by node id.
+    pub fn snapshot(&self) -> Vec<(String, NodeState)> {
+        let mut nodes: Vec<_> = self
+            .nodes
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect();
+        nodes.sort_by(|a, b| a.0.cmp(&b.0));
+        nodes
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+use crate::latency::request_type_name;
+use crate::{PendingRequest, PendingResponses, PendingStreams};
+
+/// How long a request may wait for its node unless registered with its own
+/// deadline; longer than any request timeout.
+pub const DEFAULT_PENDING_TTL: Duration = Duration::from_secs(30 * 60);
+const SWEEP_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The waiting side of a pending request.
+pub trait PendingReply {
+    /// Whether nobody waits for the reply anymore.
+    fn is_closed(&self) -> bool;
+}
+
+impl<T> PendingReply for oneshot::Sender<T> {
+    fn is_closed(&self) -> bool {
+        oneshot::Sender::is_closed(self)
+    }
+}
+
+impl<T> PendingReply for mpsc::Sender<T> {
+    fn is_closed(&self) -> bool {
+        mpsc::Sender::is_closed(self)
+    }
+}
+
+/// Why the sweeper dropped a pending request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ExpiryReason {
+    /// The node did not answer before the deadline.
+    Deadline,
+    /// The waiter went away without removing the entry.
+    Abandoned,
+}
+
+impl ExpiryReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExpiryReason::Deadline => "deadline",
+            ExpiryReason::Abandoned => "abandoned",
+        }
+    }
+}
+
+/// Node id, request type and reason.
+type ExpiryKey = (String, i32, ExpiryReason);
+
+/// Pending requests the sweeper dropped, by node, request type and reason.
+/// Counts start at zero with each coordinator.
+#[derive(Debug, Clone, Default)]
+pub struct ExpiredRequests {
+    counts: Arc<Mutex<BTreeMap<ExpiryKey, u64>>>,
+}
+
+impl ExpiredRequests {
+    fn record(&self, node_id: &str, request_type: i32, reason: ExpiryReason) {
+        *self
+            .counts
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry((node_id.to_string(), request_type, reason))
+            .or_default() += 1;
+    }
+
+    /// Every count by node id, request type name and reason.
+    pub fn snapshot(&self) -> Vec<(String, String, ExpiryReason, u64)> {
+        self.counts
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|((node_id, request_type, reason), count)| {
+                (
+                    node_id.clone(),
+                    request_type_name(*request_type),
+                    *reason,
+                    *count,
+                )
+            })
+            .collect()
+    }
+}
+
+/// Drops pending requests past their deadline or nobody waits for anymore,
+/// every 30 seconds, so handlers that return early cannot leak entries.
+pub fn spawn_pending_sweeper(
+    pending: PendingResponses,
+    pending_streams: PendingStreams,
+    expired: ExpiredRequests,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(SWEEP_INTERVAL);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            let now = Instant::now();
+            let dropped = sweep(&pending, &expired, now) + sweep(&pending_streams, &expired, now);
+            if dropped > 0 {
+                warn!("Dropped {} pending requests nodes never answered", dropped);
+            }
+        }
+    })
+}
+
+fn sweep<T: PendingReply>(
+    map: &DashMap<(String, i32), PendingRequest<T>>,
+    expired: &ExpiredRequests,
+    now: Instant,
+) -> usize {
+    let mut dropped = 0;
+    map.retain(|(_, request_type), request| {
+        let reason = if request.reply.is_closed() {
+            ExpiryReason::Abandoned
+        } else if request.deadline.is_some_and(|deadline| deadline <= now) {
+            ExpiryReason::Deadline
+        } else {
+            return true;
+        };
+        expired.record(&request.node_id, *request_type, reason);
+        dropped += 1;
+        false
+    });
+    dropped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proto::generated::Envelope;
+
+    fn request(
+        node_id: &str,
+        deadline: Option<Instant>,
+    ) -> (
+        PendingRequest<oneshot::Sender<Envelope>>,
+        oneshot::Receiver<Envelope>,
+    ) {
+        let (reply, rx) = oneshot::channel();
+        let request = PendingRequest {
+            node_id: node_id.to_string(),
+            reply,
+            sent_at: Instant::now(),
+            deadline,
+        };
+        (request, rx)
+    }
+
+    #[test]
+    fn drops_requests_past_their_deadline_or_abandoned() {
+        let now = Instant::now();
+        let map = DashMap::new();
+        let (late, _late_rx) = request("n1", Some(now - Duration::from_secs(1)));
+        let (abandoned, abandoned_rx) = request("n1", None);
+        let (waiting, _waiting_rx) = request("n2", Some(now + Duration::from_secs(60)));
+        let (forever, _forever_rx) = request("n2", None);
+        map.insert(("late".to_string(), 1), late);
+        map.insert(("abandoned".to_string(), 2), abandoned);
+        map.insert(("waiting".to_string(), 3), waiting);
+        map.insert(("forever".to_string(), 4), forever);
+        drop(abandoned_rx);
+
+        let expired = ExpiredRequests::default();
+        assert_eq!(sweep(&map, &expired, now), 2);
+
+        let mut left: Vec<_> = map.iter().map(|entry| entry.key().0.clone()).collect();
+        left.sort();
+        assert_eq!(left, ["forever", "waiting"]);
+        let counts = expired.counts.lock().unwrap().clone();
+        assert_eq!(
+            counts,
+            BTreeMap::from([
+                (("n1".to_string(), 1, ExpiryReason::Deadline), 1),
+                (("n1".to_string(), 2, ExpiryReason::Abandoned), 1),
+            ])
+        );
+    }
+
+    // A waiter that is gone counts as abandoned even before its deadline
+    #[test]
+    fn abandoned_wins_over_a_passed_deadline() {
+        let now = Instant::now();
+        let map = DashMap::new();
+        let (late, rx) = request("n1", Some(now - Duration::from_secs(1)));
+        map.insert(("late".to_string(), 1), late);
+        drop(rx);
+
+        let expired = ExpiredRequests::default();
+        assert_eq!(sweep(&map, &expired, now), 1);
+        assert_eq!(expired.snapshot()[0].2, ExpiryReason::Abandoned);
+    }
+}
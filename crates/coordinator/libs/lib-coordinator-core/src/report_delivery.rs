@@ -0,0 +1,206 @@
+use std::time::Duration;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use http_body_util::Full;
+use hyper::body::Bytes;
+use hyper::{Request, Uri, header};
+use hyper_util::client::legacy::Client;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::rt::TokioExecutor;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+use crate::reports::{DailyReport, EmailConfig, ReportConfig};
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+const SMTP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Sends finished reports to the configured webhook and mailbox.
+#[derive(Clone)]
+pub struct ReportDelivery {
+    webhook: Option<Uri>,
+    email: Option<EmailConfig>,
+    client: Client<HttpConnector, Full<Bytes>>,
+}
+
+impl ReportDelivery {
+    pub fn new(config: &ReportConfig) -> Result<Self, String> {
+        let webhook = match &config.webhook {
+            Some(url) => {
+                let url: Uri = url
+                    .parse()
+                    .map_err(|e| format!("Invalid report webhook URL: {}", e))?;
+                if url.scheme_str() != Some("http") {
+                    return Err("Report webhook must be an http:// URL".to_string());
+                }
+                Some(url)
+            }
+            None => None,
+        };
+        if let Some(email) = &config.email
+            && (email.from.is_empty() || email.to.is_empty())
+        {
+            return Err("Report email needs a sender and at least one recipient".to_string());
+        }
+
+        Ok(Self {
+            webhook,
+            email: config.email.clone(),
+            client: Client::builder(TokioExecutor::new()).build_http(),
+        })
+    }
+
+    /// Delivers the report everywhere configured; returns one message per failed target.
+    pub async fn deliver(&self, report: &DailyReport) -> Vec<String> {
+        let mut errors = Vec::new();
+        if let Some(url) = &self.webhook
+            && let Err(e) = self.post(url, report).await
+        {
+            errors.push(format!("webhook: {}", e));
+        }
+        if let Some(email) = &self.email
+            && let Err(e) = send_email(email, report).await
+        {
+            errors.push(format!("email: {}", e));
+        }
+        errors
+    }
+
+    async fn post(&self, url: &Uri, report: &DailyReport) -> Result<(), String> {
+        let body = serde_json::to_vec(report).map_err(|e| e.to_string())?;
+        let request = Request::post(url.clone())
+            .header(header::CONTENT_TYPE, "application/json")
+            .header("X-Docklord-Report", report.date.as_str())
+            .body(Full::new(Bytes::from(body)))
+            .map_err(|e| e.to_string())?;
+
+        let response = tokio::time::timeout(WEBHOOK_TIMEOUT, self.client.request(request))
+            .await
+            .map_err(|_| "timed out".to_string())?
+            .map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("status {}", response.status()));
+        }
+        Ok(())
+    }
+}
+
+async fn send_email(email: &EmailConfig, report: &DailyReport) -> Result<(), String> {
+    tokio::time::timeout(SMTP_TIMEOUT, smtp_send(email, &message(email, report)))
+        .await
+        .map_err(|_| "timed out".to_string())?
+}
+
+/// Minimal SMTP exchange with a relay that accepts mail without authentication.
+async fn smtp_send(email: &EmailConfig, message: &str) -> Result<(), String> {
+    let stream = TcpStream::connect(&email.smtp)
+        .await
+        .map_err(|e| format!("connect to {}: {}", email.smtp, e))?;
+    let (read, mut write) = stream.into_split();
+    let mut read = BufReader::new(read);
+
+    expect_reply(&mut read, 220).await?;
+    let mut commands = vec![
+        "EHLO docklord".to_string(),
+        format!("MAIL FROM:<{}>", email.from),
+    ];
+    commands.extend(email.to.iter().map(|to| format!("RCPT TO:<{}>", to)));
+    for command in commands {
+        write_line(&mut write, &command).await?;
+        expect_reply(&mut read, 250).await?;
+    }
+    write_line(&mut write, "DATA").await?;
+    expect_reply(&mut read, 354).await?;
+
+    // Dot-stuffing: a line starting with '.' gets a second one
+    let mut data = String::with_capacity(message.len() + 16);
+    for line in message.split("\r\n") {
+        if line.starts_with('.') {
+            data.push('.');
+        }
+        data.push_str(line);
+        data.push_str("\r\n");
+    }
+    data.push_str(".\r\n");
+    write
+        .write_all(data.as_bytes())
+        .await
+        .map_err(|e| e.to_string())?;
+    expect_reply(&mut read, 250).await?;
+
+    let _ = write_line(&mut write, "QUIT").await;
+    Ok(())
+}
+
+async fn write_line(write: &mut tokio::net::tcp::OwnedWriteHalf, line: &str) -> Result<(), String> {
+    write
+        .write_all(format!("{}\r\n", line).as_bytes())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Reads a (possibly multi-line) reply and checks its code.
+async fn expect_reply(
+    read: &mut BufReader<tokio::net::tcp::OwnedReadHalf>,
+    code: u16,
+) -> Result<(), String> {
+    loop {
+        let mut line = String::new();
+        if read.read_line(&mut line).await.map_err(|e| e.to_string())? == 0 {
+            return Err("connection closed by the SMTP server".to_string());
+        }
+        let line = line.trim_end();
+        // "250-..." continues the reply, "250 ..." ends it
+        if line.as_bytes().get(3) == Some(&b'-') {
+            continue;
+        }
+        return match line.get(..3).and_then(|c| c.parse::<u16>().ok()) {
+            Some(got) if got == code => Ok(()),
+            _ => Err(format!("unexpected reply: {}", line)),
+        };
+    }
+}
+
+/// Plain-text summary with the CSV report attached.
+fn message(email: &EmailConfig, report: &DailyReport) -> String {
+    let summary = report.summary();
+    let boundary = format!("docklord-report-{}", report.generated_at);
+    let body = format!(
+        "Docklord container report for {} (UTC).\r\n\r\n\
+         Containers: {}\r\nRestarts: {}\r\nCrashes: {}\r\nOOM kills: {}\r\n",
+        summary.date, summary.containers, summary.restarts, summary.crashes, summary.oom_kills
+    );
+    let attachment = STANDARD.encode(report.to_csv());
+    let attachment = attachment
+        .as_bytes()
+        .chunks(76)
+        .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+        .collect::<Vec<_>>()
+        .join("\r\n");
+
+    [
+        format!("From: {}", email.from),
+        format!("To: {}", email.to.join(", ")),
+        format!("Subject: Docklord container report {}", report.date),
+        format!("Date: {}", chrono::Utc::now().to_rfc2822()),
+        "MIME-Version: 1.0".to_string(),
+        format!("Content-Type: multipart/mixed; boundary=\"{}\"", boundary),
+        String::new(),
+        format!("--{}", boundary),
+        "Content-Type: text/plain; charset=utf-8".to_string(),
+        String::new(),
+        body,
+        format!("--{}", boundary),
+        "Content-Type: text/csv; charset=utf-8".to_string(),
+        "Content-Transfer-Encoding: base64".to_string(),
+        format!(
+            "Content-Disposition: attachment; filename=\"docklord-report-{}.csv\"",
+            report.date
+        ),
+        String::new(),
+        attachment,
+        format!("--{}--", boundary),
+    ]
+    .join("\r\n")
+}
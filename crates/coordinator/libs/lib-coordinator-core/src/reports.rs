@@ -0,0 +1,396 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chrono::{NaiveDate, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::event_log::{EventLog, EventQuery};
+use crate::json_store::JsonStore;
+use crate::node_state::NodeStateCache;
+
+const SECONDS_PER_DAY: i64 = 86_400;
+// Peaks older than this are pruned; reports can only be regenerated this far back
+const PEAK_RETENTION_DAYS: i64 = 31;
+const REPORT_RETENTION_DAYS: i64 = 90;
+
+/// When and how daily reports are generated and delivered.
+#[derive(Debug, Clone)]
+pub struct ReportConfig {
+    /// UTC time of day at which the previous day's report is generated.
+    pub at: NaiveTime,
+    /// How often resource usage is sampled for the peaks.
+    pub sample_interval: Duration,
+    /// POST target for the report JSON (http:// only).
+    pub webhook: Option<String>,
+    pub email: Option<EmailConfig>,
+}
+
+/// Parses the `HH:MM` (UTC) time of day reports are generated at.
+pub fn parse_report_time(value: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(value, "%H:%M")
+        .map_err(|_| format!("Invalid report time '{}', expected HH:MM", value))
+}
+
+/// Plain SMTP relay the CSV report is mailed through (no TLS or authentication).
+#[derive(Debug, Clone)]
+pub struct EmailConfig {
+    /// `host:port` of the relay.
+    pub smtp: String,
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+/// One container's day: how long it ran, how often it restarted or crashed
+/// and the highest CPU and memory use seen by the sampler.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportRow {
+    pub node_id: String,
+    pub container: String,
+    pub uptime_secs: i64,
+    /// Share of the covered part of the day the container was running, 0 to 1.
+    pub uptime_ratio: f64,
+    pub starts: u32,
+    /// Starts that followed the container dying, by hand or by its restart policy.
+    pub restarts: u32,
+    /// Deaths with a non-zero exit code.
+    pub crashes: u32,
+    pub oom_kills: u32,
+    /// Cores; `None` when no sample was taken while it was running.
+    pub peak_cpu: Option<f64>,
+    /// Bytes.
+    pub peak_memory: Option<u64>,
+    pub samples: u32,
+}
+
+/// Uptime, restarts and resource peaks of every container seen during one UTC day.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyReport {
+    /// `YYYY-MM-DD`.
+    pub date: String,
+    pub from: i64,
+    pub to: i64,
+    pub generated_at: i64,
+    /// False for a report of the current day, which only covers it up to `generated_at`.
+    pub complete: bool,
+    pub containers: Vec<ReportRow>,
+}
+
+/// Totals of a report, listed by `/api/reports` and used as the email body.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportSummary {
+    pub date: String,
+    pub generated_at: i64,
+    pub complete: bool,
+    pub containers: usize,
+    pub restarts: u32,
+    pub crashes: u32,
+    pub oom_kills: u32,
+}
+
+const CSV_HEADER: &str = "date,node_id,container,uptime_secs,uptime_ratio,starts,restarts,crashes,oom_kills,peak_cpu_cores,peak_memory_bytes,samples";
+
+impl DailyReport {
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(CSV_HEADER);
+        csv.push('\n');
+        for row in &self.containers {
+            let fields = [
+                self.date.clone(),
+                csv_field(&row.node_id),
+                csv_field(&row.container),
+                row.uptime_secs.to_string(),
+                format!("{:.4}", row.uptime_ratio),
+                row.starts.to_string(),
+                row.restarts.to_string(),
+                row.crashes.to_string(),
+                row.oom_kills.to_string(),
+                row.peak_cpu
+                    .map(|cpu| format!("{:.3}", cpu))
+                    .unwrap_or_default(),
+                row.peak_memory.map(|m| m.to_string()).unwrap_or_default(),
+                row.samples.to_string(),
+            ];
+            csv.push_str(&fields.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+
+    pub fn summary(&self) -> ReportSummary {
+        ReportSummary {
+            date: self.date.clone(),
+            generated_at: self.generated_at,
+            complete: self.complete,
+            containers: self.containers.len(),
+            restarts: self.containers.iter().map(|row| row.restarts).sum(),
+            crashes: self.containers.iter().map(|row| row.crashes).sum(),
+            oom_kills: self.containers.iter().map(|row| row.oom_kills).sum(),
+        }
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Start and end (exclusive) of a UTC day as unix seconds.
+pub fn day_bounds(date: NaiveDate) -> (i64, i64) {
+    let from = date.and_time(NaiveTime::MIN).and_utc().timestamp();
+    (from, from + SECONDS_PER_DAY)
+}
+
+pub fn today() -> NaiveDate {
+    Utc::now().date_naive()
+}
+
+/// Builds the report for `date` from the event history, the containers the
+/// nodes last reported and the sampled peaks.
+pub fn build_daily_report(
+    date: NaiveDate,
+    event_log: &EventLog,
+    node_states: &NodeStateCache,
+    peaks: &ResourcePeaks,
+) -> DailyReport {
+    let (from, to) = day_bounds(date);
+    let now = now_secs();
+    let end = to.min(now);
+
+    // Oldest first; everything up to the end of the day is needed to know
+    // whether a container was already running when the day began
+    let mut events = event_log.query(&EventQuery {
+        kind: Some("container"),
+        to: Some(to - 1),
+        limit: usize::MAX,
+        ..Default::default()
+    });
+    events.reverse();
+
+    let mut activity: BTreeMap<(String, String), Activity> = BTreeMap::new();
+    for event in &events {
+        if event.name.is_empty() {
+            continue;
+        }
+        let key = (event.node_id.clone(), event.name.clone());
+        let in_day = event.time >= from;
+        if !in_day && !matches!(event.action.as_str(), "start" | "die" | "destroy") {
+            continue;
+        }
+        let entry = activity.entry(key).or_default();
+        if !in_day {
+            entry.running = event.action == "start";
+            entry.died = event.action == "die";
+            continue;
+        }
+        entry.seen = true;
+        match event.action.as_str() {
+            "start" => {
+                entry.starts += 1;
+                if entry.died {
+                    entry.restarts += 1;
+                }
+                entry.died = false;
+                if !entry.running {
+                    entry.running = true;
+                    entry.up_since = event.time;
+                }
+            }
+            "die" | "destroy" => {
+                if event.action == "die" {
+                    entry.died = true;
+                    if event.exit_code != 0 && !entry.killed {
+                        entry.crashes += 1;
+                    }
+                }
+                entry.killed = false;
+                if entry.running {
+                    entry.running = false;
+                    entry.uptime += event.time - entry.up_since.max(from);
+                }
+            }
+            // `docker stop` and `docker kill` signal the container before it dies
+            "kill" => entry.killed = true,
+            "oom" => entry.oom_kills += 1,
+            _ => {}
+        }
+    }
+
+    // Containers that ran all day without an event in the history
+    for (node_id, state) in node_states.snapshot() {
+        for container in &state.containers {
+            if container.status != "running" || container.started_at >= to {
+                continue;
+            }
+            activity
+                .entry((node_id.clone(), container.container_id.clone()))
+                .or_insert_with(|| Activity {
+                    running: true,
+                    up_since: container.started_at,
+                    ..Default::default()
+                });
+        }
+    }
+
+    let day_peaks = peaks.day(date);
+    for key in day_peaks.keys() {
+        activity.entry(key.clone()).or_default().seen = true;
+    }
+
+    let covered = (end - from).max(1);
+    let containers = activity
+        .into_iter()
+        .filter_map(|((node_id, container), mut entry)| {
+            if entry.running {
+                entry.uptime += end - entry.up_since.max(from);
+            }
+            if !entry.seen && entry.uptime == 0 {
+                return None;
+            }
+            let peak = day_peaks.get(&(node_id.clone(), container.clone()));
+            Some(ReportRow {
+                uptime_ratio: (entry.uptime as f64 / covered as f64).clamp(0.0, 1.0),
+                uptime_secs: entry.uptime,
+                starts: entry.starts,
+                restarts: entry.restarts,
+                crashes: entry.crashes,
+                oom_kills: entry.oom_kills,
+                peak_cpu: peak.map(|p| p.cpu),
+                peak_memory: peak.map(|p| p.memory),
+                samples: peak.map(|p| p.samples).unwrap_or(0),
+                node_id,
+                container,
+            })
+        })
+        .collect();
+
+    DailyReport {
+        date: date.format("%Y-%m-%d").to_string(),
+        from,
+        to,
+        generated_at: now,
+        complete: now >= to,
+        containers,
+    }
+}
+
+#[derive(Default)]
+struct Activity {
+    seen: bool,
+    running: bool,
+    up_since: i64,
+    uptime: i64,
+    died: bool,
+    killed: bool,
+    starts: u32,
+    restarts: u32,
+    crashes: u32,
+    oom_kills: u32,
+}
+
+/// Highest usage sampled for a container during one day.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Peak {
+    pub cpu: f64,
+    pub memory: u64,
+    pub samples: u32,
+}
+
+/// Peaks keyed by date, then by `node_id/container`.
+type PeakDays = BTreeMap<String, BTreeMap<String, Peak>>;
+
+/// Daily resource peaks per container, persisted in `resource_peaks.json`.
+/// Docker keeps no usage history, so the report scheduler samples it.
+#[derive(Clone)]
+pub struct ResourcePeaks {
+    store: Arc<JsonStore<PeakDays>>,
+}
+
+impl ResourcePeaks {
+    pub fn open(state_dir: Option<&Path>) -> Self {
+        Self {
+            store: Arc::new(JsonStore::open(state_dir, "resource_peaks.json")),
+        }
+    }
+
+    /// Folds one sample per container (name, cores, bytes) into today's peaks.
+    pub fn record(&self, node_id: &str, samples: &[(String, f64, u64)]) -> io::Result<()> {
+        let today = today();
+        let oldest = (today - chrono::Days::new(PEAK_RETENTION_DAYS as u64))
+            .format("%Y-%m-%d")
+            .to_string();
+        self.store.update(|days| {
+            days.retain(|date, _| *date >= oldest);
+            let day = days
+                .entry(today.format("%Y-%m-%d").to_string())
+                .or_default();
+            for (container, cpu, memory) in samples {
+                let peak = day.entry(format!("{}/{}", node_id, container)).or_default();
+                peak.cpu = peak.cpu.max(*cpu);
+                peak.memory = peak.memory.max(*memory);
+                peak.samples += 1;
+            }
+        })
+    }
+
+    fn day(&self, date: NaiveDate) -> HashMap<(String, String), Peak> {
+        let date = date.format("%Y-%m-%d").to_string();
+        self.store.read(|days| {
+            days.get(&date)
+                .into_iter()
+                .flatten()
+                .filter_map(|(key, peak)| {
+                    let (node_id, container) = key.split_once('/')?;
+                    Some(((node_id.to_string(), container.to_string()), *peak))
+                })
+                .collect()
+        })
+    }
+}
+
+/// Generated reports by date, persisted in `reports.json`.
+#[derive(Clone)]
+pub struct ReportStore {
+    store: Arc<JsonStore<BTreeMap<String, DailyReport>>>,
+}
+
+impl ReportStore {
+    pub fn open(state_dir: Option<&Path>) -> Self {
+        Self {
+            store: Arc::new(JsonStore::open(state_dir, "reports.json")),
+        }
+    }
+
+    /// Report summaries, newest first.
+    pub fn list(&self) -> Vec<ReportSummary> {
+        self.store
+            .read(|reports| reports.values().rev().map(DailyReport::summary).collect())
+    }
+
+    pub fn get(&self, date: &str) -> Option<DailyReport> {
+        self.store.read(|reports| reports.get(date).cloned())
+    }
+
+    /// Stores the report, replacing an earlier one for the same day.
+    pub fn insert(&self, report: DailyReport) -> io::Result<()> {
+        let oldest = (today() - chrono::Days::new(REPORT_RETENTION_DAYS as u64))
+            .format("%Y-%m-%d")
+            .to_string();
+        self.store.update(|reports| {
+            reports.retain(|date, _| *date >= oldest);
+            reports.insert(report.date.clone(), report);
+        })
+    }
+}
+
+fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
@@ -0,0 +1,262 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use proto::generated::InjectedSecret;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::json_store::JsonStore;
+
+// Where keys used to be generated; no longer read
+const LEGACY_KEY_FILE: &str = "secrets.key";
+
+const NO_KEY: &str = "no secrets key is configured (SECRETS_KEY or --secrets-key-file)";
+
+#[derive(Clone, Serialize, Deserialize)]
+struct StoredSecret {
+    /// Base64 of the 12-byte nonce.
+    nonce: String,
+    /// Base64 of the ChaCha20-Poly1305 ciphertext.
+    ciphertext: String,
+    updated_at: i64,
+}
+
+/// What the API tells about a secret; the value itself is never returned.
+#[derive(Debug, Clone, Serialize)]
+pub struct SecretInfo {
+    pub name: String,
+    /// Unix seconds.
+    pub updated_at: i64,
+}
+
+/// A secret referenced by a container: where its value goes inside the container.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SecretRef {
+    pub name: String,
+    /// Environment variable receiving the value.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env: Option<String>,
+    /// Absolute path of a read-only file holding the value.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum SecretError {
+    /// No secret of that name for the node.
+    Unknown(String),
+    /// The stored value could not be decrypted, typically because the key changed.
+    Undecryptable(String),
+    /// The coordinator runs without a secrets key.
+    NoKey,
+}
+
+impl fmt::Display for SecretError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SecretError::Unknown(name) => write!(f, "unknown secret '{}'", name),
+            SecretError::Undecryptable(name) => {
+                write!(
+                    f,
+                    "secret '{}' cannot be decrypted with the current key",
+                    name
+                )
+            }
+            SecretError::NoKey => write!(f, "{}", NO_KEY),
+        }
+    }
+}
+
+impl std::error::Error for SecretError {}
+
+/// Per-node secrets, encrypted at rest in `secrets.json`. Values only leave the
+/// coordinator inside the command creating a container on that node.
+#[derive(Clone)]
+pub struct SecretStore {
+    store: Arc<JsonStore<BTreeMap<String, BTreeMap<String, StoredSecret>>>>,
+    /// `None` without a key: secrets can then be listed and deleted, not stored or used.
+    cipher: Option<Arc<ChaCha20Poly1305>>,
+}
+
+impl SecretStore {
+    /// `key` is a base64 32-byte key, else it is read from `key_file`, which
+    /// must live outside the state directory so that a copy of the stored
+    /// secrets does not carry their key along. Without either, secrets cannot
+    /// be stored; without a state directory nothing outlives the process and a
+    /// key is generated in memory.
+    pub fn open(
+        state_dir: Option<&Path>,
+        key: Option<&str>,
+        key_file: Option<&Path>,
+    ) -> io::Result<Self> {
+        let key = match (key, key_file) {
+            (Some(key), _) => Some(decode_key(key)?),
+            (None, Some(key_file)) => Some(read_key_file(state_dir, key_file)?),
+            (None, None) => match state_dir {
+                Some(state_dir) => {
+                    if state_dir.join(LEGACY_KEY_FILE).exists() {
+                        warn!(
+                            "{:?} is no longer read; move it out of the state directory and pass --secrets-key-file",
+                            state_dir.join(LEGACY_KEY_FILE)
+                        );
+                    }
+                    warn!("No secrets key; set SECRETS_KEY or --secrets-key-file to store secrets");
+                    None
+                }
+                None => {
+                    warn!(
+                        "No state directory; secrets are encrypted with a key kept in memory only"
+                    );
+                    Some(ChaCha20Poly1305::generate_key(&mut OsRng))
+                }
+            },
+        };
+        Ok(Self {
+            store: Arc::new(JsonStore::open(state_dir, "secrets.json")),
+            cipher: key.map(|key| Arc::new(ChaCha20Poly1305::new(&key))),
+        })
+    }
+
+    pub fn list(&self, node_id: &str) -> Vec<SecretInfo> {
+        self.store.read(|nodes| {
+            nodes
+                .get(node_id)
+                .into_iter()
+                .flatten()
+                .map(|(name, secret)| SecretInfo {
+                    name: name.clone(),
+                    updated_at: secret.updated_at,
+                })
+                .collect()
+        })
+    }
+
+    /// Creates or replaces a secret.
+    pub fn set(&self, node_id: &str, name: &str, value: &[u8]) -> io::Result<SecretInfo> {
+        let cipher = self
+            .cipher
+            .as_ref()
+            .ok_or_else(|| io::Error::other(NO_KEY))?;
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, value)
+            .map_err(|_| io::Error::other("failed to encrypt secret"))?;
+        let secret = StoredSecret {
+            nonce: STANDARD.encode(nonce),
+            ciphertext: STANDARD.encode(ciphertext),
+            updated_at: now_secs(),
+        };
+        let updated_at = secret.updated_at;
+        self.store.update(|nodes| {
+            nodes
+                .entry(node_id.to_string())
+                .or_default()
+                .insert(name.to_string(), secret);
+        })?;
+        Ok(SecretInfo {
+            name: name.to_string(),
+            updated_at,
+        })
+    }
+
+    /// Returns whether the secret existed.
+    pub fn delete(&self, node_id: &str, name: &str) -> io::Result<bool> {
+        self.store.update(|nodes| {
+            let Some(secrets) = nodes.get_mut(node_id) else {
+                return false;
+            };
+            let removed = secrets.remove(name).is_some();
+            if secrets.is_empty() {
+                nodes.remove(node_id);
+            }
+            removed
+        })
+    }
+
+    /// Decrypts the referenced secrets for a command sent to `node_id`.
+    pub fn resolve(
+        &self,
+        node_id: &str,
+        refs: &[SecretRef],
+    ) -> Result<Vec<InjectedSecret>, SecretError> {
+        self.store.read(|nodes| {
+            let secrets = nodes.get(node_id);
+            refs.iter()
+                .map(|secret_ref| {
+                    let stored = secrets
+                        .and_then(|secrets| secrets.get(&secret_ref.name))
+                        .ok_or_else(|| SecretError::Unknown(secret_ref.name.clone()))?;
+                    let cipher = self.cipher.as_ref().ok_or(SecretError::NoKey)?;
+                    let value = decrypt(cipher, stored)
+                        .ok_or_else(|| SecretError::Undecryptable(secret_ref.name.clone()))?;
+                    Ok(InjectedSecret {
+                        name: secret_ref.name.clone(),
+                        env: secret_ref.env.clone().unwrap_or_default(),
+                        file_path: secret_ref.file.clone().unwrap_or_default(),
+                        value,
+                    })
+                })
+                .collect()
+        })
+    }
+}
+
+fn decode_key(encoded: &str) -> io::Result<Key> {
+    let bytes = STANDARD
+        .decode(encoded.trim())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("secrets key: {}", e)))?;
+    if bytes.len() != 32 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "secrets key must be 32 bytes, base64-encoded",
+        ));
+    }
+    Ok(*Key::from_slice(&bytes))
+}
+
+fn read_key_file(state_dir: Option<&Path>, key_file: &Path) -> io::Result<Key> {
+    // Compared resolved where they exist, so `./state/../state/key` is caught too
+    let resolved = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    if let Some(state_dir) = state_dir
+        && resolved(key_file).starts_with(resolved(state_dir))
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "secrets key file {:?} must not be inside the state directory {:?}",
+                key_file, state_dir
+            ),
+        ));
+    }
+    let encoded = std::fs::read_to_string(key_file)
+        .map_err(|e| io::Error::new(e.kind(), format!("{:?}: {}", key_file, e)))?;
+    let key = decode_key(&encoded)?;
+    info!("Read secrets key from {:?}", key_file);
+    Ok(key)
+}
+
+fn decrypt(cipher: &ChaCha20Poly1305, stored: &StoredSecret) -> Option<Vec<u8>> {
+    let nonce = STANDARD.decode(&stored.nonce).ok()?;
+    let ciphertext = STANDARD.decode(&stored.ciphertext).ok()?;
+    if nonce.len() != 12 {
+        return None;
+    }
+    cipher
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+        .ok()
+}
+
+fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
@@ -0,0 +1,242 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::warn;
+
+use crate::json_store::JsonStore;
+
+const RECORDINGS_DIR: &str = "recordings";
+const RECORDINGS_INDEX: &str = "recordings.json";
+
+/// Audit metadata of a recorded interactive session (exec, attach).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingInfo {
+    pub id: String,
+    /// "exec" or "attach".
+    pub kind: String,
+    pub node_id: String,
+    pub container_id: String,
+    pub command: Vec<String>,
+    /// Credential that opened the session (same labels as usage accounting).
+    pub opened_by: String,
+    pub started_at: i64,
+    pub ended_at: Option<i64>,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+}
+
+/// Where sessions are recorded and whether recording is on.
+/// Recordings are asciicast v2 files, playable with `asciinema play` or asciinema-player.
+#[derive(Clone)]
+pub struct SessionRecorder {
+    dir: Option<PathBuf>,
+    enabled: bool,
+    index: Arc<JsonStore<Vec<RecordingInfo>>>,
+}
+
+impl SessionRecorder {
+    /// Recording is enabled only with `enabled` and a state directory to write to.
+    pub fn open(state_dir: Option<&Path>, enabled: bool) -> Self {
+        // Past recordings stay readable even when recording is switched off
+        let dir = state_dir.map(|state_dir| state_dir.join(RECORDINGS_DIR));
+        let enabled = match (&dir, enabled) {
+            (Some(dir), true) => match std::fs::create_dir_all(dir) {
+                Ok(()) => true,
+                Err(e) => {
+                    warn!("Session recording disabled, cannot create {:?}: {}", dir, e);
+                    false
+                }
+            },
+            (None, true) => {
+                warn!("Session recording needs a state directory, recording disabled");
+                false
+            }
+            (_, false) => false,
+        };
+
+        Self {
+            dir,
+            enabled,
+            index: Arc::new(JsonStore::open(state_dir, RECORDINGS_INDEX)),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Starts recording a session; `None` when recording is disabled or the file cannot be created.
+    pub fn start(
+        &self,
+        mut info: RecordingInfo,
+        width: u16,
+        height: u16,
+    ) -> Option<SessionRecording> {
+        if !self.enabled {
+            return None;
+        }
+        let dir = self.dir.as_ref()?;
+        info.started_at = unix_secs();
+        info.ended_at = None;
+
+        let file = match File::create(dir.join(format!("{}.cast", info.id))) {
+            Ok(file) => file,
+            Err(e) => {
+                warn!("Failed to create recording {}: {}", info.id, e);
+                return None;
+            }
+        };
+        let mut writer = BufWriter::new(file);
+        let header = json!({
+            "version": 2,
+            "width": width,
+            "height": height,
+            "timestamp": info.started_at,
+            "title": format!("{} {} on {}", info.kind, info.container_id, info.node_id),
+        });
+        if let Err(e) = writeln!(writer, "{}", header) {
+            warn!("Failed to write recording header {}: {}", info.id, e);
+            return None;
+        }
+
+        if let Err(e) = self.index.update(|index| index.push(info.clone())) {
+            warn!("Failed to index recording {}: {}", info.id, e);
+        }
+
+        Some(SessionRecording {
+            recorder: self.clone(),
+            inner: Mutex::new(RecordingInner {
+                info,
+                writer,
+                started: Instant::now(),
+                pending_output: Vec::new(),
+                pending_input: Vec::new(),
+            }),
+        })
+    }
+
+    /// All recordings, newest first.
+    pub fn list(&self) -> Vec<RecordingInfo> {
+        self.index
+            .read(|index| index.iter().rev().cloned().collect())
+    }
+
+    pub fn get(&self, id: &str) -> Option<RecordingInfo> {
+        self.index
+            .read(|index| index.iter().find(|info| info.id == id).cloned())
+    }
+
+    /// Path of the asciicast file, if the recording exists.
+    pub fn cast_path(&self, id: &str) -> Option<PathBuf> {
+        let info = self.get(id)?;
+        let path = self.dir.as_ref()?.join(format!("{}.cast", info.id));
+        path.exists().then_some(path)
+    }
+
+    fn finish(&self, info: RecordingInfo) {
+        let result = self.index.update(|index| {
+            if let Some(entry) = index.iter_mut().find(|entry| entry.id == info.id) {
+                *entry = info.clone();
+            }
+        });
+        if let Err(e) = result {
+            warn!("Failed to update recording index for {}: {}", info.id, e);
+        }
+    }
+}
+
+/// An in-progress recording. Finished (and the index updated) when dropped.
+pub struct SessionRecording {
+    recorder: SessionRecorder,
+    inner: Mutex<RecordingInner>,
+}
+
+struct RecordingInner {
+    info: RecordingInfo,
+    writer: BufWriter<File>,
+    started: Instant,
+    // Trailing bytes of a UTF-8 sequence split across chunks
+    pending_output: Vec<u8>,
+    pending_input: Vec<u8>,
+}
+
+impl SessionRecording {
+    /// Records bytes written by the container to the terminal.
+    pub fn output(&self, bytes: &[u8]) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.info.bytes_out += bytes.len() as u64;
+        let text = take_utf8(&mut inner.pending_output, bytes);
+        inner.write_event("o", &text);
+    }
+
+    /// Records bytes typed by the user.
+    pub fn input(&self, bytes: &[u8]) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.info.bytes_in += bytes.len() as u64;
+        let text = take_utf8(&mut inner.pending_input, bytes);
+        inner.write_event("i", &text);
+    }
+
+    pub fn resize(&self, width: u16, height: u16) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.write_event("r", &format!("{}x{}", width, height));
+    }
+}
+
+impl RecordingInner {
+    fn write_event(&mut self, code: &str, data: &str) {
+        if data.is_empty() {
+            return;
+        }
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let line = json!([elapsed, code, data]);
+        if let Err(e) = writeln!(self.writer, "{}", line) {
+            warn!("Failed to write recording {}: {}", self.info.id, e);
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl Drop for SessionRecording {
+    fn drop(&mut self) {
+        let inner = self.inner.get_mut().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = inner.flush() {
+            warn!("Failed to flush recording {}: {}", inner.info.id, e);
+        }
+        inner.info.ended_at = Some(unix_secs());
+        self.recorder.finish(inner.info.clone());
+    }
+}
+
+/// Decodes `bytes` after any bytes left over from the previous chunk, keeping an
+/// incomplete trailing UTF-8 sequence for the next call.
+fn take_utf8(pending: &mut Vec<u8>, bytes: &[u8]) -> String {
+    pending.extend_from_slice(bytes);
+    let valid_up_to = match std::str::from_utf8(pending) {
+        Ok(_) => pending.len(),
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        Err(_) => {
+            // Invalid (not just incomplete) data: emit it lossily
+            let text = String::from_utf8_lossy(pending).into_owned();
+            pending.clear();
+            return text;
+        }
+    };
+    let rest = pending.split_off(valid_up_to);
+    String::from_utf8(std::mem::replace(pending, rest)).unwrap_or_default()
+}
+
+fn unix_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
@@ -0,0 +1,550 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use proto::generated::{CreateContainer, PortMapping};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::json_store::JsonStore;
+use crate::secrets::SecretRef;
+
+/// Label set on containers deployed from a template, holding the template id.
+pub const TEMPLATE_LABEL: &str = "docklord.template";
+
+const RESTART_POLICIES: [&str; 4] = ["no", "always", "unless-stopped", "on-failure"];
+
+/// A parameterized container spec. `${NAME}` in the image, container name, env
+/// values, ports and volumes is replaced by the variable's value on deploy;
+/// `$$` stands for a literal `$`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Template {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub image: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container_name: Option<String>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// `[host_ip:][host_port:]container_port[/tcp|udp]`, as in `docker run -p`.
+    #[serde(default)]
+    pub ports: Vec<String>,
+    /// `source:container_path[:ro]`, as in `docker run -v`.
+    #[serde(default)]
+    pub volumes: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restart_policy: Option<String>,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    /// Secrets of the target node to inject (see [`crate::SecretStore`]).
+    #[serde(default)]
+    pub secrets: Vec<SecretRef>,
+    #[serde(default)]
+    pub variables: BTreeMap<String, TemplateVariable>,
+}
+
+/// A container to create as given, without placeholders. Field formats are
+/// the same as in [`Template`].
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ContainerSpec {
+    pub image: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    #[serde(default)]
+    pub ports: Vec<String>,
+    #[serde(default)]
+    pub volumes: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restart_policy: Option<String>,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default)]
+    pub secrets: Vec<SecretRef>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct TemplateVariable {
+    /// Value used when the deploy request does not set one; without it the variable is required.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+    #[serde(default)]
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredTemplate {
+    pub id: String,
+    /// Owning tenant in multi-tenant mode; other tenants do not see the template.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+    #[serde(flatten)]
+    pub template: Template,
+}
+
+/// Something wrong with a template or a deploy request, by field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateProblem {
+    pub field: String,
+    pub message: String,
+}
+
+impl TemplateProblem {
+    pub(crate) fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl Template {
+    /// Problems detectable without variable values: undeclared variables,
+    /// malformed placeholders, unknown restart policies and env names.
+    pub fn problems(&self) -> Vec<TemplateProblem> {
+        let mut problems = Vec::new();
+        if self.name.trim().is_empty() {
+            problems.push(TemplateProblem::new("name", "must not be empty"));
+        }
+        for name in self.variables.keys() {
+            if !is_variable_name(name) {
+                problems.push(TemplateProblem::new(
+                    format!("variables.{}", name),
+                    "must be letters, digits and '_', not starting with a digit",
+                ));
+            }
+        }
+        for (field, value) in self.substituted_fields() {
+            match variables_in(&value) {
+                Ok(used) => {
+                    for name in used.difference(&self.variables.keys().cloned().collect()) {
+                        problems.push(TemplateProblem::new(
+                            field.clone(),
+                            format!("uses undeclared variable '{}'", name),
+                        ));
+                    }
+                }
+                Err(message) => problems.push(TemplateProblem::new(field, message)),
+            }
+        }
+        for key in self.env.keys() {
+            if !is_variable_name(key) {
+                problems.push(TemplateProblem::new(
+                    format!("env.{}", key),
+                    "must be letters, digits and '_', not starting with a digit",
+                ));
+            }
+        }
+        if let Some(policy) = &self.restart_policy
+            && !RESTART_POLICIES.contains(&policy.as_str())
+        {
+            problems.push(TemplateProblem::new(
+                "restart_policy",
+                format!("must be one of {}", RESTART_POLICIES.join(", ")),
+            ));
+        }
+        for (i, secret) in self.secrets.iter().enumerate() {
+            if secret.env.is_none() && secret.file.is_none() {
+                problems.push(TemplateProblem::new(
+                    format!("secrets[{}]", i),
+                    "needs an env var name, a file path or both",
+                ));
+            }
+            if let Some(env) = &secret.env
+                && !is_variable_name(env)
+            {
+                problems.push(TemplateProblem::new(
+                    format!("secrets[{}].env", i),
+                    "must be letters, digits and '_', not starting with a digit",
+                ));
+            }
+            if let Some(file) = &secret.file
+                && !file.starts_with('/')
+            {
+                problems.push(TemplateProblem::new(
+                    format!("secrets[{}].file", i),
+                    "must be an absolute path",
+                ));
+            }
+        }
+        problems
+    }
+
+    /// The create command for this template with `values` filled in. Secrets
+    /// are left for the caller to resolve against the target node.
+    pub fn render(
+        &self,
+        request_id: &str,
+        values: &BTreeMap<String, String>,
+    ) -> Result<CreateContainer, Vec<TemplateProblem>> {
+        let mut problems = Vec::new();
+        for name in values.keys() {
+            if !self.variables.contains_key(name) {
+                problems.push(TemplateProblem::new(
+                    format!("variables.{}", name),
+                    "is not a variable of this template",
+                ));
+            }
+        }
+        let mut resolved = BTreeMap::new();
+        for (name, variable) in &self.variables {
+            match values.get(name).or(variable.default.as_ref()) {
+                Some(value) => {
+                    resolved.insert(name.clone(), value.clone());
+                }
+                None => problems.push(TemplateProblem::new(
+                    format!("variables.{}", name),
+                    "is required",
+                )),
+            }
+        }
+        if !problems.is_empty() {
+            return Err(problems);
+        }
+
+        let mut fill = |field: String, value: &str| match substitute(value, &resolved) {
+            Ok(value) => value,
+            Err(message) => {
+                problems.push(TemplateProblem::new(field, message));
+                String::new()
+            }
+        };
+        let image = fill("image".to_string(), &self.image);
+        let name = self
+            .container_name
+            .as_deref()
+            .map(|name| fill("container_name".to_string(), name))
+            .unwrap_or_default();
+        let env: Vec<String> = self
+            .env
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, fill(format!("env.{}", key), value)))
+            .collect();
+        let ports: Vec<(String, String)> = self
+            .ports
+            .iter()
+            .enumerate()
+            .map(|(i, port)| (format!("ports[{}]", i), fill(format!("ports[{}]", i), port)))
+            .collect();
+        let volumes: Vec<String> = self
+            .volumes
+            .iter()
+            .enumerate()
+            .map(|(i, volume)| fill(format!("volumes[{}]", i), volume))
+            .collect();
+
+        let ports: Vec<PortMapping> = ports
+            .into_iter()
+            .filter_map(|(field, spec)| {
+                parse_port(&spec)
+                    .map_err(|message| problems.push(TemplateProblem::new(field, message)))
+                    .ok()
+            })
+            .collect();
+        for (i, volume) in volumes.iter().enumerate() {
+            if let Err(message) = check_volume(volume) {
+                problems.push(TemplateProblem::new(format!("volumes[{}]", i), message));
+            }
+        }
+        if !problems.is_empty() {
+            return Err(problems);
+        }
+
+        let labels = self
+            .labels
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+
+        Ok(CreateContainer {
+            request_id: request_id.to_string(),
+            image,
+            name,
+            env,
+            ports,
+            volumes,
+            restart_policy: self.restart_policy.clone().unwrap_or_default(),
+            secrets: Vec::new(),
+            labels,
+            start: true,
+        })
+    }
+
+    fn substituted_fields(&self) -> Vec<(String, String)> {
+        let mut fields = vec![("image".to_string(), self.image.clone())];
+        if let Some(name) = &self.container_name {
+            fields.push(("container_name".to_string(), name.clone()));
+        }
+        fields.extend(
+            self.env
+                .iter()
+                .map(|(key, value)| (format!("env.{}", key), value.clone())),
+        );
+        fields.extend(
+            self.ports
+                .iter()
+                .enumerate()
+                .map(|(i, port)| (format!("ports[{}]", i), port.clone())),
+        );
+        fields.extend(
+            self.volumes
+                .iter()
+                .enumerate()
+                .map(|(i, volume)| (format!("volumes[{}]", i), volume.clone())),
+        );
+        fields
+    }
+}
+
+impl ContainerSpec {
+    /// The create command for this spec. Secrets are left for the caller to
+    /// resolve against the target node.
+    pub fn command(&self, request_id: &str) -> Result<CreateContainer, Vec<TemplateProblem>> {
+        // `$` has no special meaning here, so it is escaped for the template checks
+        let template = Template {
+            name: "container".to_string(),
+            description: String::new(),
+            image: self.image.replace('$', "$$"),
+            container_name: self.name.as_ref().map(|name| name.replace('$', "$$")),
+            env: self
+                .env
+                .iter()
+                .map(|(key, value)| (key.clone(), value.replace('$', "$$")))
+                .collect(),
+            ports: self
+                .ports
+                .iter()
+                .map(|port| port.replace('$', "$$"))
+                .collect(),
+            volumes: self
+                .volumes
+                .iter()
+                .map(|volume| volume.replace('$', "$$"))
+                .collect(),
+            restart_policy: self.restart_policy.clone(),
+            labels: self.labels.clone(),
+            secrets: self.secrets.clone(),
+            variables: BTreeMap::new(),
+        };
+        let mut problems = template.problems();
+        for problem in &mut problems {
+            if problem.field == "container_name" {
+                problem.field = "name".to_string();
+            }
+        }
+        match template.render(request_id, &BTreeMap::new()) {
+            Ok(command) if problems.is_empty() => Ok(command),
+            Ok(_) => Err(problems),
+            Err(more) => {
+                problems.extend(more);
+                Err(problems)
+            }
+        }
+    }
+}
+
+/// Parses a `docker run -p` style port spec.
+pub fn parse_port(spec: &str) -> Result<PortMapping, String> {
+    let (addr, protocol) = match spec.rsplit_once('/') {
+        Some((addr, protocol)) => (addr, protocol),
+        None => (spec, "tcp"),
+    };
+    if protocol != "tcp" && protocol != "udp" {
+        return Err(format!("protocol must be tcp or udp, got \"{}\"", protocol));
+    }
+    let parts: Vec<&str> = addr.rsplitn(3, ':').collect();
+    let port = |value: &str| {
+        value
+            .parse::<u16>()
+            .ok()
+            .filter(|port| *port != 0)
+            .map(u32::from)
+            .ok_or_else(|| format!("\"{}\" is not a port number", value))
+    };
+    let (host_ip, host_port, container_port) = match parts.as_slice() {
+        [container] => ("", 0, port(container)?),
+        [container, host] => ("", port(host)?, port(container)?),
+        [container, host, ip] => {
+            let host_port = if host.is_empty() { 0 } else { port(host)? };
+            (*ip, host_port, port(container)?)
+        }
+        _ => return Err(format!("\"{}\" is not a port spec", spec)),
+    };
+    Ok(PortMapping {
+        container_port,
+        host_port,
+        protocol: protocol.to_string(),
+        host_ip: host_ip.to_string(),
+    })
+}
+
+fn check_volume(volume: &str) -> Result<(), String> {
+    let parts: Vec<&str> = volume.split(':').collect();
+    let valid = match parts.as_slice() {
+        [source, target] => !source.is_empty() && target.starts_with('/'),
+        [source, target, mode] => {
+            !source.is_empty() && target.starts_with('/') && matches!(*mode, "ro" | "rw")
+        }
+        _ => false,
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "\"{}\" must be source:/container/path with an optional :ro or :rw",
+            volume
+        ))
+    }
+}
+
+fn is_variable_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Names of the `${NAME}` placeholders in `value`.
+fn variables_in(value: &str) -> Result<BTreeSet<String>, String> {
+    let mut names = BTreeSet::new();
+    substitute_with(value, |name| {
+        names.insert(name.to_string());
+        Some(String::new())
+    })?;
+    Ok(names)
+}
+
+fn substitute(value: &str, values: &BTreeMap<String, String>) -> Result<String, String> {
+    substitute_with(value, |name| values.get(name).cloned())
+}
+
+fn substitute_with(
+    value: &str,
+    mut lookup: impl FnMut(&str) -> Option<String>,
+) -> Result<String, String> {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(at) = rest.find('$') {
+        out.push_str(&rest[..at]);
+        rest = &rest[at + 1..];
+        if let Some(after) = rest.strip_prefix('$') {
+            out.push('$');
+            rest = after;
+            continue;
+        }
+        let Some(after) = rest.strip_prefix('{') else {
+            return Err("'$' must start ${NAME} or be written as $$".to_string());
+        };
+        let Some(end) = after.find('}') else {
+            return Err("unterminated ${".to_string());
+        };
+        let name = &after[..end];
+        if !is_variable_name(name) {
+            return Err(format!("\"${{{}}}\" is not a valid variable", name));
+        }
+        out.push_str(&lookup(name).ok_or_else(|| format!("unknown variable '{}'", name))?);
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Container templates, persisted in `templates.json`.
+#[derive(Clone)]
+pub struct TemplateStore {
+    store: Arc<JsonStore<BTreeMap<String, StoredTemplate>>>,
+}
+
+impl TemplateStore {
+    pub fn open(state_dir: Option<&Path>) -> Self {
+        Self {
+            store: Arc::new(JsonStore::open(state_dir, "templates.json")),
+        }
+    }
+
+    /// Templates visible to `tenant` (all untenanted ones without multi-tenancy).
+    pub fn list(&self, tenant: Option<&str>) -> Vec<StoredTemplate> {
+        self.store.read(|templates| {
+            templates
+                .values()
+                .filter(|stored| stored.tenant.as_deref() == tenant)
+                .cloned()
+                .collect()
+        })
+    }
+
+    pub fn get(&self, id: &str, tenant: Option<&str>) -> Option<StoredTemplate> {
+        self.store.read(|templates| {
+            templates
+                .get(id)
+                .filter(|stored| stored.tenant.as_deref() == tenant)
+                .cloned()
+        })
+    }
+
+    pub fn create(
+        &self,
+        id: &str,
+        tenant: Option<&str>,
+        template: Template,
+    ) -> io::Result<StoredTemplate> {
+        let now = now_secs();
+        let stored = StoredTemplate {
+            id: id.to_string(),
+            tenant: tenant.map(str::to_string),
+            created_at: now,
+            updated_at: now,
+            template,
+        };
+        self.store.update(|templates| {
+            templates.insert(id.to_string(), stored.clone());
+        })?;
+        Ok(stored)
+    }
+
+    /// Replaces the spec of a visible template. `None` if there is none.
+    pub fn replace(
+        &self,
+        id: &str,
+        tenant: Option<&str>,
+        template: Template,
+    ) -> io::Result<Option<StoredTemplate>> {
+        self.store.update(|templates| {
+            let stored = templates
+                .get_mut(id)
+                .filter(|stored| stored.tenant.as_deref() == tenant)?;
+            stored.template = template;
+            stored.updated_at = now_secs();
+            Some(stored.clone())
+        })
+    }
+
+    /// Returns whether a visible template was deleted.
+    pub fn delete(&self, id: &str, tenant: Option<&str>) -> io::Result<bool> {
+        self.store.update(|templates| {
+            if templates
+                .get(id)
+                .is_some_and(|stored| stored.tenant.as_deref() == tenant)
+            {
+                templates.remove(id);
+                true
+            } else {
+                false
+            }
+        })
+    }
+}
+
+fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
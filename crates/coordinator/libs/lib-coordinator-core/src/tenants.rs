@@ -0,0 +1,299 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+use tracing::warn;
+use utoipa::ToSchema;
+
+use crate::json_store::JsonStore;
+
+pub(crate) const KEY_HASH_PREFIX: &str = "sha256:";
+
+/// How an API key is stored and compared: its SHA-256. Issued keys are long
+/// and random, so a plain hash keeps them out of state files without a salt.
+pub fn api_key_hash(api_key: &str) -> String {
+    format!(
+        "{}{}",
+        KEY_HASH_PREFIX,
+        URL_SAFE_NO_PAD.encode(Sha256::digest(api_key.as_bytes()))
+    )
+}
+
+/// An account owning a set of nodes, reached with any of its API keys.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Tenant {
+    /// Hashes of the API keys by key id; the id is what gets listed and revoked.
+    #[serde(default)]
+    pub api_keys: BTreeMap<String, String>,
+    #[serde(default)]
+    pub nodes: BTreeSet<String>,
+    #[serde(default)]
+    pub quota: TenantQuota,
+}
+
+/// Limits of one tenant; `None` is unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct TenantQuota {
+    pub max_nodes: Option<usize>,
+    pub max_requests_per_minute: Option<u32>,
+    pub max_ws_connections: Option<usize>,
+}
+
+/// Outcome of [`TenantStore::assign_node`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodeAssignment {
+    Assigned,
+    UnknownTenant,
+    /// Another tenant has the node.
+    OwnedBy(String),
+    /// The tenant already has its maximum number of nodes.
+    QuotaExceeded(usize),
+}
+
+/// Tenants by name, persisted in `tenants.json`. A node belongs to at most one
+/// tenant. With multi-tenancy disabled the store is never consulted.
+#[derive(Clone)]
+pub struct TenantStore {
+    store: Arc<JsonStore<BTreeMap<String, Tenant>>>,
+    enabled: bool,
+}
+
+impl TenantStore {
+    pub fn open(state_dir: Option<&Path>, enabled: bool) -> Self {
+        let store = JsonStore::open(state_dir, "tenants.json");
+        // Keys stored in plain text by earlier versions are hashed once
+        let plain = store.read(|tenants: &BTreeMap<String, Tenant>| {
+            tenants
+                .values()
+                .flat_map(|tenant| tenant.api_keys.values())
+                .any(|key| !key.starts_with(KEY_HASH_PREFIX))
+        });
+        if plain {
+            let hashed = store.update(|tenants| {
+                for key in tenants
+                    .values_mut()
+                    .flat_map(|tenant| tenant.api_keys.values_mut())
+                    .filter(|key| !key.starts_with(KEY_HASH_PREFIX))
+                {
+                    *key = api_key_hash(key);
+                }
+            });
+            if let Err(e) = hashed {
+                warn!("Failed to store hashed tenant keys: {}", e);
+            }
+        }
+        Self {
+            store: Arc::new(store),
+            enabled,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn list(&self) -> BTreeMap<String, Tenant> {
+        self.store.read(|tenants| tenants.clone())
+    }
+
+    pub fn get(&self, name: &str) -> Option<Tenant> {
+        self.store.read(|tenants| tenants.get(name).cloned())
+    }
+
+    /// Name of the tenant `api_key` belongs to, with the key's id. Hashes are
+    /// compared in constant time.
+    pub fn tenant_for_key(&self, api_key: &str) -> Option<(String, String)> {
+        let hash = api_key_hash(api_key);
+        self.store.read(|tenants| {
+            tenants.iter().find_map(|(name, tenant)| {
+                tenant
+                    .api_keys
+                    .iter()
+                    .find(|(_, key)| bool::from(key.as_bytes().ct_eq(hash.as_bytes())))
+                    .map(|(key_id, _)| (name.clone(), key_id.clone()))
+            })
+        })
+    }
+
+    /// Name of the tenant owning `node_id`.
+    pub fn owner_of(&self, node_id: &str) -> Option<String> {
+        self.store.read(|tenants| {
+            tenants
+                .iter()
+                .find(|(_, tenant)| tenant.nodes.contains(node_id))
+                .map(|(name, _)| name.clone())
+        })
+    }
+
+    /// Returns whether the tenant was created (false if it already existed).
+    pub fn create(&self, name: &str) -> io::Result<bool> {
+        self.store.update(|tenants| {
+            if tenants.contains_key(name) {
+                return false;
+            }
+            tenants.insert(name.to_string(), Tenant::default());
+            true
+        })
+    }
+
+    /// Removes the tenant with its keys, releasing its nodes. Returns whether it existed.
+    pub fn delete(&self, name: &str) -> io::Result<bool> {
+        self.store.update(|tenants| tenants.remove(name).is_some())
+    }
+
+    /// Stores the key's hash. Returns whether the tenant exists.
+    pub fn add_key(&self, name: &str, key_id: &str, api_key: &str) -> io::Result<bool> {
+        self.store.update(|tenants| match tenants.get_mut(name) {
+            Some(tenant) => {
+                tenant
+                    .api_keys
+                    .insert(key_id.to_string(), api_key_hash(api_key));
+                true
+            }
+            None => false,
+        })
+    }
+
+    /// Returns whether the key existed.
+    pub fn revoke_key(&self, name: &str, key_id: &str) -> io::Result<bool> {
+        self.store.update(|tenants| {
+            tenants
+                .get_mut(name)
+                .is_some_and(|tenant| tenant.api_keys.remove(key_id).is_some())
+        })
+    }
+
+    /// Gives `node_id` to the tenant unless another tenant has it or the
+    /// tenant's node quota is used up.
+    pub fn assign_node(&self, name: &str, node_id: &str) -> io::Result<NodeAssignment> {
+        self.store.update(|tenants| {
+            if let Some((owner, _)) = tenants
+                .iter()
+                .find(|(owner, tenant)| *owner != name && tenant.nodes.contains(node_id))
+            {
+                return NodeAssignment::OwnedBy(owner.clone());
+            }
+            let Some(tenant) = tenants.get_mut(name) else {
+                return NodeAssignment::UnknownTenant;
+            };
+            if let Some(max_nodes) = tenant.quota.max_nodes
+                && !tenant.nodes.contains(node_id)
+                && tenant.nodes.len() >= max_nodes
+            {
+                return NodeAssignment::QuotaExceeded(max_nodes);
+            }
+            tenant.nodes.insert(node_id.to_string());
+            NodeAssignment::Assigned
+        })
+    }
+
+    /// Returns whether the tenant exists.
+    pub fn set_quota(&self, name: &str, quota: TenantQuota) -> io::Result<bool> {
+        self.store.update(|tenants| match tenants.get_mut(name) {
+            Some(tenant) => {
+                tenant.quota = quota;
+                true
+            }
+            None => false,
+        })
+    }
+
+    pub fn quota(&self, name: &str) -> TenantQuota {
+        self.store
+            .read(|tenants| tenants.get(name).map(|tenant| tenant.quota))
+            .unwrap_or_default()
+    }
+
+    /// Returns whether the tenant had the node.
+    pub fn release_node(&self, name: &str, node_id: &str) -> io::Result<bool> {
+        self.store.update(|tenants| {
+            tenants
+                .get_mut(name)
+                .is_some_and(|tenant| tenant.nodes.remove(node_id))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> TenantStore {
+        let tenants = TenantStore::open(None, true);
+        tenants.create("red").unwrap();
+        tenants.create("blue").unwrap();
+        tenants
+    }
+
+    #[test]
+    fn a_node_belongs_to_one_tenant() {
+        let tenants = store();
+        assert_eq!(
+            tenants.assign_node("red", "web").unwrap(),
+            NodeAssignment::Assigned
+        );
+        assert_eq!(
+            tenants.assign_node("blue", "web").unwrap(),
+            NodeAssignment::OwnedBy("red".to_string())
+        );
+        assert_eq!(tenants.owner_of("web").as_deref(), Some("red"));
+
+        assert!(!tenants.release_node("blue", "web").unwrap());
+        assert!(tenants.release_node("red", "web").unwrap());
+        assert_eq!(
+            tenants.assign_node("blue", "web").unwrap(),
+            NodeAssignment::Assigned
+        );
+    }
+
+    #[test]
+    fn node_quota_stops_new_nodes_only() {
+        let tenants = store();
+        let quota = TenantQuota {
+            max_nodes: Some(1),
+            ..Default::default()
+        };
+        assert!(tenants.set_quota("red", quota).unwrap());
+        assert_eq!(
+            tenants.assign_node("red", "web").unwrap(),
+            NodeAssignment::Assigned
+        );
+        assert_eq!(
+            tenants.assign_node("red", "db").unwrap(),
+            NodeAssignment::QuotaExceeded(1)
+        );
+        // Assigning a node it already has is not a new node
+        assert_eq!(
+            tenants.assign_node("red", "web").unwrap(),
+            NodeAssignment::Assigned
+        );
+        assert_eq!(
+            tenants.assign_node("green", "db").unwrap(),
+            NodeAssignment::UnknownTenant
+        );
+    }
+
+    #[test]
+    fn keys_are_stored_hashed_and_resolve_to_their_tenant() {
+        let tenants = store();
+        assert!(tenants.add_key("red", "k1", "secret-red").unwrap());
+        assert!(tenants.add_key("blue", "k2", "secret-blue").unwrap());
+
+        let red = tenants.get("red").unwrap();
+        assert_eq!(red.api_keys["k1"], api_key_hash("secret-red"));
+        assert_eq!(
+            tenants.tenant_for_key("secret-blue"),
+            Some(("blue".to_string(), "k2".to_string()))
+        );
+        assert_eq!(tenants.tenant_for_key("secret"), None);
+
+        assert!(tenants.revoke_key("red", "k1").unwrap());
+        assert_eq!(tenants.tenant_for_key("secret-red"), None);
+    }
+}
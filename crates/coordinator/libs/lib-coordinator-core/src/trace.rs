@@ -0,0 +1,55 @@
+use std::future::Future;
+
+use proto::generated::{Envelope, NodeCommand, envelope::Payload};
+use proto::{METADATA_ACTOR, METADATA_REQUEST_ID, METADATA_TRACE_ID};
+
+tokio::task_local! {
+    static CURRENT: TraceContext;
+}
+
+/// The REST or WebSocket request being served, copied into the metadata of
+/// every command it sends to a node so both sides log the same ids.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceContext {
+    /// The request's `X-Request-Id`, sent by the client or generated.
+    pub trace_id: String,
+    /// Credential the request was made with, e.g. `node:web-1` or `admin`; never a secret.
+    pub actor: String,
+}
+
+impl TraceContext {
+    /// Runs `future` with this as the current trace.
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        CURRENT.scope(self, future).await
+    }
+
+    /// The trace of the request this task serves, if any.
+    pub fn current() -> Option<Self> {
+        CURRENT.try_with(Clone::clone).ok()
+    }
+
+    /// Runs `future` under `trace` when there is one, e.g. one taken with
+    /// `current` before a task is spawned.
+    pub async fn resume<F: Future>(trace: Option<Self>, future: F) -> F::Output {
+        match trace {
+            Some(trace) => trace.scope(future).await,
+            None => future.await,
+        }
+    }
+
+    /// Sets the correlation metadata on a command for a node.
+    pub(crate) fn stamp(&self, envelope: &mut Envelope) {
+        if let Some(Payload::NodeCommand(NodeCommand { kind: Some(kind) })) = &envelope.payload {
+            envelope.metadata.insert(
+                METADATA_REQUEST_ID.to_string(),
+                kind.request_id().to_string(),
+            );
+        }
+        envelope
+            .metadata
+            .insert(METADATA_TRACE_ID.to_string(), self.trace_id.clone());
+        envelope
+            .metadata
+            .insert(METADATA_ACTOR.to_string(), self.actor.clone());
+    }
+}
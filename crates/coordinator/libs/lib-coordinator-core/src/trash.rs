@@ -0,0 +1,57 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use proto::generated::ConfigureTrash;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::json_store::JsonStore;
+
+/// How long one node keeps deleted containers before removing them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TrashPolicy {
+    /// Seconds a deleted container stays in the trash.
+    pub retention_secs: u64,
+}
+
+/// Per-node trash retention, persisted in `trash.json`. The node keeps the
+/// trash; the coordinator sends the retention whenever the node connects.
+#[derive(Clone)]
+pub struct TrashStore {
+    store: Arc<JsonStore<BTreeMap<String, TrashPolicy>>>,
+}
+
+impl TrashStore {
+    pub fn open(state_dir: Option<&Path>) -> Self {
+        Self {
+            store: Arc::new(JsonStore::open(state_dir, "trash.json")),
+        }
+    }
+
+    pub fn get(&self, node_id: &str) -> Option<TrashPolicy> {
+        self.store.read(|policies| policies.get(node_id).cloned())
+    }
+
+    /// Replaces the node's retention; `None` turns the trash off.
+    pub fn set(&self, node_id: &str, policy: Option<TrashPolicy>) -> io::Result<()> {
+        self.store.update(|policies| match policy {
+            Some(policy) => {
+                policies.insert(node_id.to_string(), policy);
+            }
+            None => {
+                policies.remove(node_id);
+            }
+        })
+    }
+
+    /// The node command installing `policy`; `None` deletes right away.
+    pub fn command(request_id: &str, policy: Option<&TrashPolicy>) -> ConfigureTrash {
+        ConfigureTrash {
+            request_id: request_id.to_string(),
+            retention_secs: policy.map_or(0, |policy| policy.retention_secs),
+        }
+    }
+}
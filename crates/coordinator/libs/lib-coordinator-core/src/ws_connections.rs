@@ -0,0 +1,97 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// The cap a refused WebSocket connection ran into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WsCap {
+    Total,
+    PerCredential,
+}
+
+#[derive(Debug, Default)]
+struct CredentialCounts {
+    open: usize,
+    rejected: u64,
+}
+
+/// Open WebSocket connections by credential, and how many each had turned
+/// away for being over a cap since the coordinator started.
+#[derive(Debug, Clone, Default)]
+pub struct WsConnections {
+    counts: Arc<Mutex<BTreeMap<String, CredentialCounts>>>,
+}
+
+impl WsConnections {
+    fn counts(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, CredentialCounts>> {
+        self.counts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Takes a connection slot for `credential`, unless `total` connections
+    /// are open overall or `per_credential` for it. `0` leaves that cap off.
+    pub fn open(
+        &self,
+        credential: &str,
+        total: usize,
+        per_credential: usize,
+    ) -> Result<WsConnection, WsCap> {
+        let mut counts = self.counts();
+        let open: usize = counts.values().map(|counts| counts.open).sum();
+        let entry = counts.entry(credential.to_string()).or_default();
+        let cap = if total > 0 && open >= total {
+            Some(WsCap::Total)
+        } else if per_credential > 0 && entry.open >= per_credential {
+            Some(WsCap::PerCredential)
+        } else {
+            None
+        };
+        if let Some(cap) = cap {
+            entry.rejected += 1;
+            return Err(cap);
+        }
+        entry.open += 1;
+        Ok(WsConnection {
+            _slot: Arc::new(Slot {
+                connections: self.clone(),
+                credential: credential.to_string(),
+            }),
+        })
+    }
+
+    /// Connections open right now, overall.
+    pub fn open_total(&self) -> usize {
+        self.counts().values().map(|counts| counts.open).sum()
+    }
+
+    /// Open and refused connections by credential.
+    pub fn snapshot(&self) -> Vec<(String, usize, u64)> {
+        self.counts()
+            .iter()
+            .map(|(credential, counts)| (credential.clone(), counts.open, counts.rejected))
+            .collect()
+    }
+}
+
+/// An open WebSocket connection. Handlers keep it for the lifetime of the
+/// socket; the slot is freed when the last clone is dropped.
+#[derive(Clone)]
+pub struct WsConnection {
+    _slot: Arc<Slot>,
+}
+
+struct Slot {
+    connections: WsConnections,
+    credential: String,
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        let mut counts = self.connections.counts();
+        if let Some(entry) = counts.get_mut(&self.credential) {
+            entry.open = entry.open.saturating_sub(1);
+            // Credentials that never ran into a cap are not worth keeping
+            if entry.open == 0 && entry.rejected == 0 {
+                counts.remove(&self.credential);
+            }
+        }
+    }
+}
@@ -1,15 +1,58 @@
+use std::future::Future;
+use std::path::PathBuf;
+
 use proto::generated::conversation_service_server::ConversationServiceServer;
-use tonic::transport::Server;
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::transport::{Identity, Server, ServerTlsConfig};
+use tracing::info;
 
 use crate::grpc_server_service::CoordinatorServiceImpl;
 
+/// PEM certificate chain and private key the gRPC server presents to nodes.
+#[derive(Debug, Clone)]
+pub struct GrpcServerTls {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+}
+
+impl GrpcServerTls {
+    async fn load(&self) -> Result<ServerTlsConfig, Box<dyn std::error::Error + Send + Sync>> {
+        let cert = tokio::fs::read(&self.cert_path).await.map_err(|e| {
+            format!(
+                "Failed to read TLS certificate {}: {}",
+                self.cert_path.display(),
+                e
+            )
+        })?;
+        let key = tokio::fs::read(&self.key_path)
+            .await
+            .map_err(|e| format!("Failed to read TLS key {}: {}", self.key_path.display(), e))?;
+        Ok(ServerTlsConfig::new().identity(Identity::from_pem(cert, key)))
+    }
+}
+
+/// Serves the node protocol on `listener`, over TLS when `tls` is set. Once
+/// `stop_accepting` completes no new nodes are accepted; connected ones stay
+/// until they disconnect.
 pub async fn run_grpc_server(
     coordinator_service: CoordinatorServiceImpl,
-    grpc_coordinator_addr: std::net::SocketAddr,
+    listener: TcpListener,
+    max_message_size: usize,
+    tls: Option<GrpcServerTls>,
+    stop_accepting: impl Future<Output = ()>,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    Server::builder()
-        .add_service(ConversationServiceServer::new(coordinator_service))
-        .serve(grpc_coordinator_addr)
+    let service = ConversationServiceServer::new(coordinator_service)
+        .max_decoding_message_size(max_message_size)
+        .max_encoding_message_size(max_message_size);
+    let mut builder = Server::builder();
+    if let Some(tls) = tls {
+        builder = builder.tls_config(tls.load().await?)?;
+        info!("gRPC server uses TLS ({})", tls.cert_path.display());
+    }
+    builder
+        .add_service(service)
+        .serve_with_incoming_shutdown(TcpListenerStream::new(listener), stop_accepting)
         .await?;
     Ok(())
 }
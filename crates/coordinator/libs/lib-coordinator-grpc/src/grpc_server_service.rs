@@ -1,48 +1,118 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-use dashmap::DashMap;
 use futures_util::StreamExt;
 use futures_util::stream::BoxStream;
-use proto::generated::ServerCommand;
+use proto::METADATA_TRACE_ID;
 use proto::generated::envelope::Payload;
 use proto::generated::node_response::Kind;
 use proto::generated::request_key::RequestId;
+use proto::generated::{
+    ContainerLogs, ContainerLogsChunk, ErrorCode, NodeCommand, NodeContainers, NodeError,
+    NodeResponse, RequestKey, RequestType, ServerCommand, node_command,
+};
+use tokio::sync::oneshot::error::TryRecvError;
 use tokio::sync::{Mutex, broadcast, mpsc, oneshot};
 use tokio_stream::wrappers::ReceiverStream;
 use tonic::{Request, Response, Status};
 use tracing::{info, instrument, warn};
 
-use lib_coordinator_core::{AuthState, PendingResponses, ServerRequestByUser};
+use lib_coordinator_core::{
+    AuthState, AuthzDecision, AuthzRequest, AutostartStore, CleanupStore, ConnectedNode, EventLog,
+    LogForwardingStore, NodeChannels, NodeStateCache, PendingRequest, PendingResponses,
+    PendingStreams, ServerRequestByUser, SharedAuthorizer, StoredEvent, TrashStore,
+};
 use proto::generated::{
     Envelope, ServerResponse, ServerStatus, conversation_service_server::ConversationService,
     server_command, server_response,
 };
 
 const NODE_CHANNEL_CAPACITY: usize = 1024;
+// Commands routed to one node that its stream has not passed on yet
+const COMMAND_CHANNEL_CAPACITY: usize = 1024;
 const SERVER_CHANNEL_CAPACITY: usize = 32;
+const LOG_FORWARDING_TIMEOUT: Duration = Duration::from_secs(30);
+const CLEANUP_TIMEOUT: Duration = Duration::from_secs(30);
+const TRASH_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Lines one chunked log answer may join up to. Well above the largest page a
+// REST request asks for, so only a node that never stops sending reaches it
+const MAX_JOINED_LOG_BYTES: usize = 32 * 1024 * 1024;
+
+// Chunked log answers of one node being joined, by request
+type LogChunks = HashMap<(String, i32), JoinedLogs>;
+
+struct JoinedLogs {
+    // Sequence number of the next chunk
+    next: u32,
+    bytes: usize,
+    logs: ContainerLogs,
+}
 
 pub struct CoordinatorServiceImpl {
-    server_cmd_tx: broadcast::Sender<ServerRequestByUser>,
-    nodes: Arc<DashMap<(String, String), broadcast::Sender<Envelope>>>,
+    nodes: NodeChannels,
     start_time: Instant,
     pending: PendingResponses,
+    pending_streams: PendingStreams,
+    node_states: NodeStateCache,
+    event_log: EventLog,
+    authorizer: SharedAuthorizer,
+    autostart: Option<AutostartStore>,
+    log_forwarding: Option<LogForwardingStore>,
+    cleanup: Option<CleanupStore>,
+    trash: Option<TrashStore>,
 }
 
 impl CoordinatorServiceImpl {
     pub fn new(
-        nodes: Arc<DashMap<(String, String), broadcast::Sender<Envelope>>>,
-        server_cmd_tx: broadcast::Sender<ServerRequestByUser>,
+        nodes: NodeChannels,
         pending: PendingResponses,
+        pending_streams: PendingStreams,
+        node_states: NodeStateCache,
+        event_log: EventLog,
+        authorizer: SharedAuthorizer,
     ) -> Self {
         Self {
             nodes,
-            server_cmd_tx,
             start_time: Instant::now(),
             pending,
+            pending_streams,
+            node_states,
+            event_log,
+            authorizer,
+            autostart: None,
+            log_forwarding: None,
+            cleanup: None,
+            trash: None,
         }
     }
 
+    /// Sends each node its autostart list when it (re)connects.
+    pub fn with_autostart(mut self, autostart: AutostartStore) -> Self {
+        self.autostart = Some(autostart);
+        self
+    }
+
+    /// Sends each node its log forwards when it (re)connects.
+    pub fn with_log_forwarding(mut self, log_forwarding: LogForwardingStore) -> Self {
+        self.log_forwarding = Some(log_forwarding);
+        self
+    }
+
+    /// Sends each node its cleanup policy when it (re)connects.
+    pub fn with_cleanup(mut self, cleanup: CleanupStore) -> Self {
+        self.cleanup = Some(cleanup);
+        self
+    }
+
+    /// Sends each node its trash retention when it (re)connects.
+    pub fn with_trash(mut self, trash: TrashStore) -> Self {
+        self.trash = Some(trash);
+        self
+    }
+
     fn format_uptime(duration: Duration) -> String {
         let secs = duration.as_secs();
         format!(
@@ -64,39 +134,119 @@ impl ConversationService for CoordinatorServiceImpl {
         request: Request<tonic::Streaming<Envelope>>,
     ) -> Result<Response<Self::ConversationStream>, Status> {
         let auth_state = Arc::new(Mutex::new(AuthState::default()));
+        let remote_addr = request.remote_addr();
         let mut inbound = request.into_inner();
         let (outbound_tx, outbound_rx) = mpsc::channel(SERVER_CHANNEL_CAPACITY);
+        // Routed here by node id once the node authenticated
+        let (command_tx, mut command_rx) =
+            mpsc::channel::<ServerRequestByUser>(COMMAND_CHANNEL_CAPACITY);
 
-        let server_cmd_tx = self.server_cmd_tx.clone();
         let nodes = self.nodes.clone();
         let pending = self.pending.clone();
+        let pending_streams = self.pending_streams.clone();
+        let node_states = self.node_states.clone();
+        let event_log = self.event_log.clone();
+        let authorizer = self.authorizer.clone();
+        let autostart = self.autostart.clone();
+        let log_forwarding = self.log_forwarding.clone();
+        let cleanup = self.cleanup.clone();
+        let trash = self.trash.clone();
         let start_time = self.start_time;
 
         // Task 1: Handle server commands -> node
         let server_to_node_handle = {
             let auth_state = auth_state.clone();
             let outbound_tx = outbound_tx.clone();
+            let pending = pending.clone();
+            let pending_streams = pending_streams.clone();
 
             tokio::spawn(async move {
-                let mut server_cmd_rx = server_cmd_tx.subscribe();
-                loop {
-                    match server_cmd_rx.recv().await {
-                        Ok(request) => {
-                            let auth = auth_state.lock().await;
-                            if auth.is_match(&request.id, &request.password) {
-                                if let Some(Payload::NodeCommand(_)) = &request.envelope.payload {
-                                    if let Err(e) = outbound_tx.send(Ok(request.envelope)).await {
-                                        warn!("Failed to send server command: {}", e);
-                                        break;
-                                    }
-                                }
+                // Per request id, the last command still being checked. Later
+                // commands with that id (session input after its attach, a
+                // cancel after its request) wait for it, so none overtakes it
+                let mut in_check: HashMap<String, oneshot::Receiver<()>> = HashMap::new();
+
+                while let Some(request) = command_rx.recv().await {
+                    let node = auth_state.lock().await.node_for(&request.id);
+                    let Some(node) = node else {
+                        continue;
+                    };
+                    if !node.verify(&request.password).await {
+                        continue;
+                    }
+                    let command = match &request.envelope.payload {
+                        Some(Payload::NodeCommand(command)) => command,
+                        // Broadcasts come from the admin API, which checked the token
+                        // already, and shutdown notices from the coordinator itself
+                        Some(Payload::ServerCommand(ServerCommand {
+                            kind:
+                                Some(
+                                    server_command::Kind::BroadcastNotice(_)
+                                    | server_command::Kind::CoordinatorShutdown(_),
+                                ),
+                        })) => {
+                            if let Err(e) = outbound_tx.send(Ok(request.envelope)).await {
+                                warn!("Failed to send broadcast: {}", e);
+                                break;
                             }
+                            continue;
                         }
-                        Err(e) => {
-                            warn!("Server command channel error: {}", e);
+                        _ => continue,
+                    };
+
+                    let authz = command
+                        .kind
+                        .as_ref()
+                        .and_then(|kind| AuthzRequest::for_command(&request.id, kind));
+                    let request_id = authz
+                        .as_ref()
+                        .map(|authz| authz.request_id.clone())
+                        .or_else(|| {
+                            command
+                                .kind
+                                .as_ref()
+                                .and_then(command_request_id)
+                                .map(str::to_string)
+                        });
+
+                    // Pending while the earlier command is still in its check
+                    in_check.retain(|_, done| done.try_recv() == Err(TryRecvError::Empty));
+                    let earlier = request_id.as_ref().and_then(|id| in_check.remove(id));
+                    if authz.is_none() && earlier.is_none() {
+                        if let Err(e) = outbound_tx.send(Ok(request.envelope)).await {
+                            warn!("Failed to send server command: {}", e);
                             break;
                         }
+                        continue;
                     }
+
+                    // The policy may take a while to answer, so commands are
+                    // checked concurrently rather than one after another
+                    let (done_tx, done_rx) = oneshot::channel();
+                    if let Some(request_id) = request_id {
+                        in_check.insert(request_id, done_rx);
+                    }
+                    let authorizer = authorizer.clone();
+                    let outbound_tx = outbound_tx.clone();
+                    let pending = pending.clone();
+                    let pending_streams = pending_streams.clone();
+                    tokio::spawn(async move {
+                        if let Some(earlier) = earlier {
+                            let _ = earlier.await;
+                        }
+                        if let Some(authz) = authz
+                            && let AuthzDecision::Deny(reason) = authorizer.authorize(&authz).await
+                        {
+                            info!(
+                                "Denied {} on node {}: {}",
+                                authz.action, authz.node_id, reason
+                            );
+                            reject_command(&authz, reason, &pending, &pending_streams).await;
+                        } else if let Err(e) = outbound_tx.send(Ok(request.envelope)).await {
+                            warn!("Failed to send server command: {}", e);
+                        }
+                        drop(done_tx);
+                    });
                 }
                 info!("Server->Node task terminated");
             })
@@ -111,6 +261,7 @@ impl ConversationService for CoordinatorServiceImpl {
 
             tokio::spawn(async move {
                 let mut shutdown_signal = Some(shutdown_tx);
+                let mut log_chunks = LogChunks::new();
 
                 while let Some(msg) = inbound.next().await {
                     let envelope = match msg {
@@ -120,25 +271,91 @@ impl ConversationService for CoordinatorServiceImpl {
                             break;
                         }
                     };
+                    // Nodes may compress large responses themselves
+                    let envelope = match envelope.decompress() {
+                        Ok(envelope) => envelope,
+                        Err(e) => {
+                            warn!("Dropped a node response that failed to decompress: {}", e);
+                            continue;
+                        }
+                    };
 
-                    let mut auth = auth_state.lock().await;
                     match envelope.payload {
                         Some(Payload::ServerCommand(cmd)) => {
-                            handle_server_command(&mut auth, cmd, &outbound_tx, &nodes, start_time)
+                            let mut auth = auth_state.lock().await;
+                            let authenticated = handle_server_command(
+                                &mut auth,
+                                cmd,
+                                &outbound_tx,
+                                &command_tx,
+                                &nodes,
+                                &node_states,
+                                remote_addr,
+                                start_time,
+                            )
+                            .await;
+                            if authenticated
+                                && let (Some(autostart), Some(node_id)) = (&autostart, &auth.id)
+                            {
+                                send_autostart(autostart, node_id, &outbound_tx, &pending).await;
+                            }
+                            if authenticated
+                                && let (Some(log_forwarding), Some(node_id)) =
+                                    (&log_forwarding, &auth.id)
+                            {
+                                send_log_forwarding(
+                                    log_forwarding,
+                                    node_id,
+                                    &outbound_tx,
+                                    &pending,
+                                )
                                 .await;
+                            }
+                            if authenticated
+                                && let (Some(cleanup), Some(node_id)) = (&cleanup, &auth.id)
+                            {
+                                send_cleanup(cleanup, node_id, &outbound_tx, &pending).await;
+                            }
+                            if authenticated
+                                && let (Some(trash), Some(node_id)) = (&trash, &auth.id)
+                            {
+                                send_trash(trash, node_id, &outbound_tx, &pending).await;
+                            }
                         }
                         Some(Payload::NodeResponse(resp)) => {
-                            if auth.is_authenticated() {
-                                handle_node_response(resp, &pending, &auth, &nodes).await;
-                            }
+                            // Not held while forwarding, so Task 1 never waits on a reply
+                            let sender = {
+                                let auth = auth_state.lock().await;
+                                auth.id.clone().zip(auth.node.clone())
+                            };
+                            let Some((node_id, node)) = sender else {
+                                continue;
+                            };
+                            handle_node_response(
+                                resp,
+                                envelope.metadata,
+                                &pending,
+                                &pending_streams,
+                                &mut log_chunks,
+                                &node_id,
+                                &node,
+                                &node_states,
+                                &event_log,
+                            );
                         }
                         _ => {}
                     }
                 }
 
                 // Cleanup on disconnect
-                if let Some((id, password)) = auth_state.lock().await.take_credentials() {
-                    nodes.remove(&(id.clone(), password));
+                if let Some((id, node)) = auth_state.lock().await.take_credentials() {
+                    // A newer connection with the same id has replaced this one
+                    let removed = nodes
+                        .remove_if(&id, |_, current| Arc::ptr_eq(current, &node))
+                        .is_some();
+                    if removed {
+                        node_states.mark_disconnected(&id);
+                    }
                     info!("Node {} disconnected and removed", id);
                 }
 
@@ -160,28 +377,46 @@ impl ConversationService for CoordinatorServiceImpl {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn handle_server_command(
     auth: &mut AuthState,
     cmd: ServerCommand,
     outbound_tx: &mpsc::Sender<Result<Envelope, Status>>,
-    nodes: &DashMap<(String, String), broadcast::Sender<Envelope>>,
+    command_tx: &mpsc::Sender<ServerRequestByUser>,
+    nodes: &NodeChannels,
+    node_states: &NodeStateCache,
+    remote_addr: Option<SocketAddr>,
     start_time: Instant,
-) {
+) -> bool {
     // Handle authentication
     if !auth.is_authenticated() {
         if let Some(server_command::Kind::AuthRequest(auth_req)) = cmd.kind {
             let id = auth_req.node_id;
-            let password = auth_req.password;
-            auth.authenticate(id.clone(), password.clone());
-
-            // Register new node
             let (tx, _) = broadcast::channel(NODE_CHANNEL_CAPACITY);
-            nodes.insert((id, password), tx);
+            // Only the hash of the password is kept
+            let node = match ConnectedNode::new(&auth_req.password, command_tx.clone(), tx) {
+                Ok(node) => Arc::new(node),
+                Err(e) => {
+                    warn!("Failed to hash the password of node {}: {}", id, e);
+                    return false;
+                }
+            };
+            auth.authenticate(id.clone(), node.clone());
+
+            // Register new node; the channel goes first so observers told
+            // about the connect can subscribe to it
+            nodes.insert(id.clone(), node);
+            node_states.mark_connected(&id, remote_addr, auth_req.capabilities, auth_req.link);
+            return true;
         }
-        return;
+        return false;
     }
 
     // Handle server commands
+    if let (Some(server_command::Kind::Heartbeat(heartbeat)), Some(id)) = (&cmd.kind, &auth.id) {
+        node_states.heartbeat(id, Duration::from_secs(heartbeat.interval_secs.into()));
+        return false;
+    }
     if let Some(server_command::Kind::GetServerStatus(_)) = cmd.kind {
         let response = Envelope {
             payload: Some(Payload::ServerResponse(ServerResponse {
@@ -190,70 +425,616 @@ async fn handle_server_command(
                     uptime: CoordinatorServiceImpl::format_uptime(start_time.elapsed()),
                 })),
             })),
+            ..Default::default()
         };
 
         if let Err(e) = outbound_tx.send(Ok(response)).await {
             warn!("Failed to send server status: {}", e);
         }
     }
+    false
+}
+
+// Starts the node's autostart list right after it authenticated and records
+// the report once the node is done
+async fn send_autostart(
+    autostart: &AutostartStore,
+    node_id: &str,
+    outbound_tx: &mpsc::Sender<Result<Envelope, Status>>,
+    pending: &PendingResponses,
+) {
+    let Some(plan) = autostart.get(node_id) else {
+        return;
+    };
+    let store = autostart.clone();
+    let owner = node_id.to_string();
+    let sent = send_on_connect(
+        node_id,
+        outbound_tx,
+        pending,
+        RequestType::ApplyAutostart,
+        |request_id| node_command::Kind::ApplyAutostart(plan.command(request_id, true)),
+        "autostart list",
+        plan.timeout(),
+        move |reply| match reply {
+            Kind::AutostartReport(report) => {
+                if let Err(e) = store.record_run(&owner, (&report).into()) {
+                    warn!("Failed to record autostart run of {}: {}", owner, e);
+                }
+            }
+            Kind::Error(e) => warn!("Autostart on node {} failed: {}", owner, e.message),
+            _ => {}
+        },
+    )
+    .await;
+    if sent {
+        info!(
+            "Sent autostart list of {} containers to node {}",
+            plan.containers.len(),
+            node_id
+        );
+    }
+}
+
+// Installs the node's log forwards right after it authenticated; the node
+// keeps forwards that did not change running
+async fn send_log_forwarding(
+    log_forwarding: &LogForwardingStore,
+    node_id: &str,
+    outbound_tx: &mpsc::Sender<Result<Envelope, Status>>,
+    pending: &PendingResponses,
+) {
+    let configs = log_forwarding.get(node_id);
+    if configs.is_empty() {
+        return;
+    }
+    send_on_connect(
+        node_id,
+        outbound_tx,
+        pending,
+        RequestType::ConfigureLogForwarding,
+        |request_id| {
+            node_command::Kind::ConfigureLogForwarding(LogForwardingStore::command(
+                node_id, request_id, &configs,
+            ))
+        },
+        "log forwarding",
+        LOG_FORWARDING_TIMEOUT,
+        log_confirmation(node_id, "log forwarding"),
+    )
+    .await;
+}
+
+// Installs the node's cleanup policy right after it authenticated; an
+// unchanged policy keeps the node's sweep schedule
+async fn send_cleanup(
+    cleanup: &CleanupStore,
+    node_id: &str,
+    outbound_tx: &mpsc::Sender<Result<Envelope, Status>>,
+    pending: &PendingResponses,
+) {
+    let Some(policy) = cleanup.get(node_id) else {
+        return;
+    };
+    send_on_connect(
+        node_id,
+        outbound_tx,
+        pending,
+        RequestType::ConfigureCleanup,
+        |request_id| {
+            node_command::Kind::ConfigureCleanup(CleanupStore::command(request_id, Some(&policy)))
+        },
+        "cleanup policy",
+        CLEANUP_TIMEOUT,
+        log_confirmation(node_id, "cleanup policy"),
+    )
+    .await;
+}
+
+// Installs the node's trash retention right after it authenticated; without
+// one the node deletes right away, as it does after a restart
+async fn send_trash(
+    trash: &TrashStore,
+    node_id: &str,
+    outbound_tx: &mpsc::Sender<Result<Envelope, Status>>,
+    pending: &PendingResponses,
+) {
+    let Some(policy) = trash.get(node_id) else {
+        return;
+    };
+    send_on_connect(
+        node_id,
+        outbound_tx,
+        pending,
+        RequestType::ConfigureTrash,
+        |request_id| {
+            node_command::Kind::ConfigureTrash(TrashStore::command(request_id, Some(&policy)))
+        },
+        "trash retention",
+        TRASH_TIMEOUT,
+        log_confirmation(node_id, "trash retention"),
+    )
+    .await;
+}
+
+// Sends the command `payload` builds for a new request id, registered as
+// pending under `request_type`, and hands the node's answer to `on_reply` in
+// the background. An answer later than `timeout` is given up on. Returns
+// whether the command went out.
+#[allow(clippy::too_many_arguments)]
+async fn send_on_connect(
+    node_id: &str,
+    outbound_tx: &mpsc::Sender<Result<Envelope, Status>>,
+    pending: &PendingResponses,
+    request_type: RequestType,
+    payload: impl FnOnce(&str) -> node_command::Kind,
+    what: &'static str,
+    timeout: Duration,
+    on_reply: impl FnOnce(Kind) + Send + 'static,
+) -> bool {
+    let request_id = uuid::Uuid::new_v4().to_string();
+    let key = (request_id.clone(), request_type as i32);
+    let (response_tx, response_rx) = oneshot::channel();
+    pending.insert(key.clone(), PendingRequest::new(node_id, response_tx));
+
+    let envelope = Envelope {
+        payload: Some(Payload::NodeCommand(NodeCommand {
+            kind: Some(payload(&request_id)),
+        })),
+        ..Default::default()
+    };
+    if let Err(e) = outbound_tx.send(Ok(envelope)).await {
+        warn!("Failed to send {}: {}", what, e);
+        pending.remove(&key);
+        return false;
+    }
+
+    let pending = pending.clone();
+    let node_id = node_id.to_string();
+    tokio::spawn(async move {
+        match tokio::time::timeout(timeout, response_rx).await {
+            Ok(Ok(Envelope {
+                payload: Some(Payload::NodeResponse(NodeResponse { kind: Some(kind) })),
+                ..
+            })) => on_reply(kind),
+            // The node went away first
+            Ok(_) => {}
+            Err(_) => {
+                pending.remove(&key);
+                warn!("Node {} did not answer its {} in time", node_id, what);
+            }
+        }
+    });
+    true
+}
+
+// Logs whether the node took the configuration it was sent
+fn log_confirmation(node_id: &str, what: &'static str) -> impl FnOnce(Kind) + Send + 'static {
+    let node_id = node_id.to_string();
+    move |reply| match reply {
+        Kind::Error(e) => warn!("Node {} rejected its {}: {}", node_id, what, e.message),
+        _ => info!("Sent {} to node {}", what, node_id),
+    }
 }
 
-async fn handle_node_response(
-    resp: proto::generated::NodeResponse,
+// The stream task's shared state, passed piece by piece
+#[allow(clippy::too_many_arguments)]
+fn handle_node_response(
+    mut resp: proto::generated::NodeResponse,
+    metadata: HashMap<String, String>,
     pending: &PendingResponses,
-    auth: &AuthState,
-    nodes: &DashMap<(String, String), broadcast::Sender<Envelope>>,
+    pending_streams: &PendingStreams,
+    log_chunks: &mut LogChunks,
+    sender_id: &str,
+    sender: &ConnectedNode,
+    node_states: &NodeStateCache,
+    event_log: &EventLog,
 ) {
+    node_states.touch(sender_id);
+
+    // Answered below like any other response once all chunks are in
+    if let Some(Kind::ContainerLogsChunk(chunk)) = &mut resp.kind {
+        match join_log_chunk(log_chunks, pending, std::mem::take(chunk)) {
+            Some(kind) => resp.kind = Some(kind),
+            None => return,
+        }
+    }
+
+    // A node only answers requests sent to it, never another node's
+    let is_sender = |node_id: &str| node_id == sender_id;
+
+    if let (Some(Kind::Error(error)), Some(trace_id)) =
+        (&resp.kind, metadata.get(METADATA_TRACE_ID))
+    {
+        warn!(
+            "Node {} failed request of trace {}: {}",
+            sender_id, trace_id, error.message
+        );
+    }
+
     // Handle pending responses
-    if let Some(request_key) = extract_request_key(&resp) {
-        if let Some(RequestId::Value(ref id_str)) = request_key.request_id {
-            if let Some((_, response_tx)) =
-                pending.remove(&(id_str.clone(), request_key.request_type))
-            {
-                let envelope = Envelope {
-                    payload: Some(Payload::NodeResponse(resp)),
-                };
-                if response_tx.send(envelope).is_err() {
+    if let Some(request_key) = extract_request_key(&resp)
+        && let Some(RequestId::Value(ref id_str)) = request_key.request_id
+        && let Some((_, request)) = pending
+            .remove_if(&(id_str.clone(), request_key.request_type), |_, request| {
+                is_sender(&request.node_id)
+            })
+    {
+        node_states.latency().observe(
+            &request.node_id,
+            request_key.request_type,
+            request.sent_at.elapsed(),
+        );
+        let envelope = Envelope {
+            payload: Some(Payload::NodeResponse(resp)),
+            metadata,
+            ..Default::default()
+        };
+        if request.reply.send(envelope).is_err() {
+            warn!(
+                "Pending response channel closed for request {:?}",
+                request_key
+            );
+        }
+        return;
+    }
+
+    // Handle streamed responses (several envelopes per request)
+    if let Some(request_key) = extract_request_key(&resp)
+        && let Some(RequestId::Value(ref id_str)) = request_key.request_id
+    {
+        let key = (id_str.clone(), request_key.request_type);
+        let stream_tx = pending_streams
+            .get(&key)
+            .filter(|request| is_sender(&request.node_id))
+            .map(|request| request.reply.clone());
+        if let Some(stream_tx) = stream_tx {
+            let envelope = Envelope {
+                payload: Some(Payload::NodeResponse(resp)),
+                metadata,
+                ..Default::default()
+            };
+            // A consumer that fell this far behind has its stream closed, so
+            // it cannot hold up the node's other replies
+            match stream_tx.try_send(envelope) {
+                Ok(()) => {}
+                Err(mpsc::error::TrySendError::Full(_)) => {
                     warn!(
-                        "Pending response channel closed for request {:?}",
+                        "Pending stream for request {:?} is full, closing it",
                         request_key
                     );
+                    pending_streams.remove(&key);
+                }
+                Err(mpsc::error::TrySendError::Closed(_)) => {
+                    warn!("Pending stream closed for request {:?}", request_key);
+                    pending_streams.remove(&key);
                 }
-                return;
             }
+            return;
         }
     }
 
     info!("Get updates of containers: {:?}", resp);
 
+    // Keep the coordinator's per-node cache in sync with pushed updates
+    let is_update = extract_request_key(&resp)
+        .is_some_and(|key| key.request_type == RequestType::UpdateContainerInfo as i32);
+    if is_update {
+        let mut names = None;
+        match &resp.kind {
+            Some(Kind::NodeContainers(_)) => node_states.record_event(sender_id),
+            Some(Kind::ContainerDelta(delta)) => {
+                node_states.record_event(sender_id);
+                names = node_states
+                    .apply_container_delta(sender_id, delta)
+                    .map(|containers| (delta.request_key.clone(), containers));
+            }
+            Some(Kind::NodeContainersWithStatus(update)) => {
+                node_states.update_containers(sender_id, update.containers.clone())
+            }
+            Some(Kind::ContainerEvent(event)) => {
+                let stored = StoredEvent {
+                    seq: 0,
+                    node_id: sender_id.to_string(),
+                    kind: if event.r#type.is_empty() {
+                        "container".to_string()
+                    } else {
+                        event.r#type.clone()
+                    },
+                    container_id: event.container_id.clone(),
+                    name: event.name.clone(),
+                    action: event.action.clone(),
+                    time: event.time,
+                    exit_code: event.exit_code,
+                    image: event.image.clone(),
+                    health: event.health.clone(),
+                    actor: String::new(),
+                    status: 0,
+                };
+                event_log.append(stored);
+            }
+            _ => {}
+        }
+        // Observers keep getting the full name list on each change
+        if let Some((request_key, containers)) = names {
+            resp = NodeResponse {
+                kind: Some(Kind::NodeContainers(NodeContainers {
+                    request_key,
+                    containers,
+                })),
+            };
+        }
+    }
+
     // Broadcast to node
     // If it's not the rest request
-    if let (Some(id), Some(password)) = (&auth.id, &auth.password) {
-        if let Some(node) = nodes.get(&(id.clone(), password.clone())) {
-            info!("Get updates of containers: {:?}", resp);
+    info!("Get updates of containers: {:?}", resp);
 
-            let envelope = Envelope {
-                payload: Some(Payload::NodeResponse(resp)),
-            };
+    let envelope = Envelope {
+        payload: Some(Payload::NodeResponse(resp)),
+        ..Default::default()
+    };
 
-            // TODO: fix
-            if node.send(envelope).is_err() {
-                warn!("Node channel closed for {}", id);
-            }
-        }
+    // TODO: fix
+    if sender.updates.send(envelope).is_err() {
+        warn!("Node channel closed for {}", sender_id);
+    }
+}
+
+/// Adds a chunk to the logs joined so far. Returns the whole ContainerLogs
+/// after the last chunk, or an error when a chunk went missing or the lines
+/// grew past `MAX_JOINED_LOG_BYTES`. Chunks of a request nobody waits for
+/// anymore are dropped.
+fn join_log_chunk(
+    log_chunks: &mut LogChunks,
+    pending: &PendingResponses,
+    chunk: ContainerLogsChunk,
+) -> Option<Kind> {
+    let request_key = chunk.request_key.clone()?;
+    let Some(RequestId::Value(request_id)) = &request_key.request_id else {
+        return None;
+    };
+    let key = (request_id.clone(), request_key.request_type);
+    if !pending.contains_key(&key) {
+        log_chunks.remove(&key);
+        return None;
+    }
+
+    let joined = log_chunks.entry(key.clone()).or_insert_with(|| JoinedLogs {
+        next: 0,
+        bytes: 0,
+        logs: ContainerLogs {
+            request_key: Some(request_key.clone()),
+            container_id: chunk.container_id.clone(),
+            buffered: chunk.buffered,
+            ..Default::default()
+        },
+    });
+    if chunk.sequence != joined.next {
+        warn!(
+            "Log chunk {} of request {} arrived, expected {}",
+            chunk.sequence, request_id, joined.next
+        );
+        let message = format!(
+            "Log chunk {} arrived instead of {}",
+            chunk.sequence, joined.next
+        );
+        log_chunks.remove(&key);
+        return Some(log_chunk_error(request_key, message));
+    }
+    joined.next += 1;
+    joined.bytes += chunk
+        .lines
+        .iter()
+        .map(|line| line.text.len() + line.stream.len() + line.timestamp.len())
+        .sum::<usize>();
+    if joined.bytes > MAX_JOINED_LOG_BYTES {
+        warn!(
+            "Log chunks of request {} passed {} bytes, dropping them",
+            request_id, MAX_JOINED_LOG_BYTES
+        );
+        log_chunks.remove(&key);
+        let message = format!("Logs larger than {} bytes", MAX_JOINED_LOG_BYTES);
+        return Some(log_chunk_error(request_key, message));
+    }
+    joined.logs.lines.extend(chunk.lines);
+    if !chunk.is_last {
+        return None;
+    }
+
+    let mut logs = log_chunks.remove(&key)?.logs;
+    logs.truncated = chunk.truncated;
+    Some(Kind::ContainerLogs(logs))
+}
+
+fn log_chunk_error(request_key: RequestKey, message: String) -> Kind {
+    Kind::Error(NodeError {
+        request_key: Some(request_key),
+        message,
+        code: ErrorCode::Unspecified.into(),
+    })
+}
+
+/// Request id of the commands that are not checked by the policy: a session's
+/// input, resizes and close carry the session's, a cancel that of its request.
+fn command_request_id(kind: &node_command::Kind) -> Option<&str> {
+    match kind {
+        node_command::Kind::SessionInput(c) => Some(&c.request_id),
+        node_command::Kind::CloseSession(c) => Some(&c.request_id),
+        node_command::Kind::ResizeSession(c) => Some(&c.request_id),
+        node_command::Kind::CancelRequest(c) => Some(&c.request_id),
+        _ => None,
+    }
+}
+
+// Answers a denied command with a NodeError, as if the node had refused it
+async fn reject_command(
+    authz: &AuthzRequest,
+    reason: String,
+    pending: &PendingResponses,
+    pending_streams: &PendingStreams,
+) {
+    let envelope = Envelope {
+        payload: Some(Payload::NodeResponse(NodeResponse {
+            kind: Some(Kind::Error(NodeError {
+                request_key: Some(RequestKey {
+                    request_type: authz.request_type as i32,
+                    request_id: Some(RequestId::Value(authz.request_id.clone())),
+                }),
+                message: format!("Denied by policy: {}", reason),
+                code: ErrorCode::PermissionDenied.into(),
+            })),
+        })),
+        ..Default::default()
+    };
+
+    let key = (authz.request_id.clone(), authz.request_type as i32);
+    if let Some((_, request)) = pending.remove(&key) {
+        let _ = request.reply.send(envelope);
+        return;
+    }
+    let stream_tx = pending_streams
+        .get(&key)
+        .map(|request| request.reply.clone());
+    if let Some(stream_tx) = stream_tx {
+        let _ = stream_tx.send(envelope).await;
     }
 }
 
 fn extract_request_key(
     response: &proto::generated::NodeResponse,
 ) -> Option<proto::generated::RequestKey> {
-    match &response.kind {
-        Some(Kind::NodeContainers(c)) => c.request_key.clone(),
-        Some(Kind::NodeContainersWithStatus(c)) => c.request_key.clone(),
-        Some(Kind::ContainerStatus(c)) => c.request_key.clone(),
-        Some(Kind::ContainerLogs(c)) => c.request_key.clone(),
-        Some(Kind::ContainerAction(c)) => c.request_key.clone(),
-        Some(Kind::Error(c)) => c.request_key.clone(),
-        _ => None,
+    response.request_key().cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proto::generated::LogLine;
+
+    const LOGS: i32 = RequestType::GetContainerLogs as i32;
+
+    fn waiting_for_logs() -> PendingResponses {
+        let pending = PendingResponses::default();
+        let (reply, _) = oneshot::channel();
+        pending.insert(("r1".to_string(), LOGS), PendingRequest::new("n1", reply));
+        pending
+    }
+
+    fn chunk(sequence: u32, texts: &[&str], is_last: bool) -> ContainerLogsChunk {
+        ContainerLogsChunk {
+            request_key: Some(RequestKey {
+                request_type: LOGS,
+                request_id: Some(RequestId::Value("r1".to_string())),
+            }),
+            container_id: "c1".to_string(),
+            sequence,
+            lines: texts
+                .iter()
+                .map(|text| LogLine {
+                    text: text.to_string(),
+                    stream: "stdout".to_string(),
+                    timestamp: String::new(),
+                })
+                .collect(),
+            is_last,
+            truncated: is_last,
+            buffered: false,
+        }
+    }
+
+    fn texts(kind: Option<Kind>) -> Vec<String> {
+        match kind {
+            Some(Kind::ContainerLogs(logs)) => logs.lines.into_iter().map(|l| l.text).collect(),
+            other => panic!("expected joined logs, got {:?}", other),
+        }
+    }
+
+    fn is_error(kind: &Option<Kind>) -> bool {
+        matches!(kind, Some(Kind::Error(_)))
+    }
+
+    #[test]
+    fn joins_chunks_in_order_once_the_last_arrives() {
+        let pending = waiting_for_logs();
+        let mut log_chunks = LogChunks::new();
+        assert!(join_log_chunk(&mut log_chunks, &pending, chunk(0, &["a", "b"], false)).is_none());
+        assert!(join_log_chunk(&mut log_chunks, &pending, chunk(1, &["c"], false)).is_none());
+        let joined = join_log_chunk(&mut log_chunks, &pending, chunk(2, &["d"], true));
+        assert_eq!(texts(joined), ["a", "b", "c", "d"]);
+        assert!(log_chunks.is_empty());
+    }
+
+    #[test]
+    fn keeps_the_last_chunk_flags() {
+        let pending = waiting_for_logs();
+        let mut log_chunks = LogChunks::new();
+        match join_log_chunk(&mut log_chunks, &pending, chunk(0, &["a"], true)) {
+            Some(Kind::ContainerLogs(logs)) => {
+                assert!(logs.truncated);
+                assert_eq!(logs.container_id, "c1");
+            }
+            other => panic!("expected joined logs, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reports_a_missing_chunk() {
+        let pending = waiting_for_logs();
+        let mut log_chunks = LogChunks::new();
+        join_log_chunk(&mut log_chunks, &pending, chunk(0, &["a"], false));
+        let joined = join_log_chunk(&mut log_chunks, &pending, chunk(2, &["c"], true));
+        assert!(is_error(&joined));
+        assert!(log_chunks.is_empty());
+    }
+
+    #[test]
+    fn reports_a_repeated_chunk() {
+        let pending = waiting_for_logs();
+        let mut log_chunks = LogChunks::new();
+        join_log_chunk(&mut log_chunks, &pending, chunk(0, &["a"], false));
+        let joined = join_log_chunk(&mut log_chunks, &pending, chunk(0, &["a"], false));
+        assert!(is_error(&joined));
+        assert!(log_chunks.is_empty());
+    }
+
+    #[test]
+    fn reports_a_first_chunk_that_is_not_first() {
+        let pending = waiting_for_logs();
+        let mut log_chunks = LogChunks::new();
+        let joined = join_log_chunk(&mut log_chunks, &pending, chunk(1, &["b"], true));
+        assert!(is_error(&joined));
+    }
+
+    #[test]
+    fn drops_chunks_nobody_waits_for() {
+        let pending = PendingResponses::default();
+        let mut log_chunks = LogChunks::new();
+        assert!(join_log_chunk(&mut log_chunks, &pending, chunk(0, &["a"], false)).is_none());
+        assert!(log_chunks.is_empty());
+    }
+
+    #[test]
+    fn stops_joining_past_the_size_cap() {
+        let pending = waiting_for_logs();
+        let mut log_chunks = LogChunks::new();
+        let line = "x".repeat(1024 * 1024);
+        let mut sequence = 0;
+        let joined = loop {
+            let joined = join_log_chunk(
+                &mut log_chunks,
+                &pending,
+                chunk(sequence, &[line.as_str()], false),
+            );
+            if joined.is_some() {
+                break joined;
+            }
+            sequence += 1;
+        };
+        assert!(is_error(&joined));
+        // The chunk that takes the lines and their stream tags past the cap
+        let per_chunk = line.len() + "stdout".len();
+        assert_eq!(sequence as usize, MAX_JOINED_LOG_BYTES / per_chunk);
+        assert!(log_chunks.is_empty());
     }
 }
@@ -1,4 +1,4 @@
 pub mod grpc_server;
 pub mod grpc_server_service;
 
-pub use grpc_server::run_grpc_server;
+pub use grpc_server::{GrpcServerTls, run_grpc_server};
@@ -0,0 +1,215 @@
+//! Exporters turn coordinator events into side effects: webhooks, metrics,
+//! syslog lines, or whatever an embedder registers. Each one follows the
+//! event history and node connections on its own, so core routing code never
+//! needs to know about them.
+
+pub mod prometheus;
+pub mod syslog;
+pub mod webhook;
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use axum::Router;
+use lib_coordinator_core::{EventLog, NodeConnectionEvent, NodeStateCache, StoredEvent};
+use serde_json::{Value, json};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{info, warn};
+
+pub use prometheus::PrometheusExporter;
+pub use syslog::SyslogExporter;
+pub use webhook::WebhookExporter;
+
+/// What exporters are told about.
+#[derive(Debug, Clone)]
+pub enum IntegrationEvent {
+    /// A Docker or script event reported by a node, as kept in the event history.
+    Container(StoredEvent),
+    /// A node connected to or disconnected from this coordinator.
+    NodeConnection {
+        node_id: String,
+        connected: bool,
+        time: i64,
+    },
+}
+
+impl IntegrationEvent {
+    pub fn node_id(&self) -> &str {
+        match self {
+            IntegrationEvent::Container(event) => &event.node_id,
+            IntegrationEvent::NodeConnection { node_id, .. } => node_id,
+        }
+    }
+
+    /// The event's `type`: "container", "image", "volume" or "script" as in
+    /// the event history, "node" for connections.
+    pub fn kind(&self) -> &str {
+        match self {
+            IntegrationEvent::Container(event) => &event.kind,
+            IntegrationEvent::NodeConnection { .. } => "node",
+        }
+    }
+
+    /// Docker's action, e.g. "die" or "health_status"; "connect" or
+    /// "disconnect" for nodes.
+    pub fn action(&self) -> &str {
+        match self {
+            IntegrationEvent::Container(event) => &event.action,
+            IntegrationEvent::NodeConnection {
+                connected: true, ..
+            } => "connect",
+            IntegrationEvent::NodeConnection { .. } => "disconnect",
+        }
+    }
+
+    /// Unix seconds.
+    pub fn time(&self) -> i64 {
+        match self {
+            IntegrationEvent::Container(event) => event.time,
+            IntegrationEvent::NodeConnection { time, .. } => *time,
+        }
+    }
+
+    /// Container events as in `/api/events/history`; node connections with
+    /// the same `node_id`, `type`, `action` and `time` fields.
+    pub fn to_json(&self) -> Value {
+        match self {
+            IntegrationEvent::Container(event) => json!(event),
+            IntegrationEvent::NodeConnection { node_id, time, .. } => json!({
+                "node_id": node_id,
+                "type": self.kind(),
+                "action": self.action(),
+                "time": time,
+            }),
+        }
+    }
+}
+
+/// Receives coordinator events and acts on them. Implementations only need
+/// `export`; the rest have defaults.
+#[async_trait]
+pub trait Exporter: Send + Sync {
+    /// Used in logs, e.g. "webhook http://alerts:8080/hook".
+    fn name(&self) -> &str;
+
+    /// Whether `export` is called for this event; every event by default.
+    fn accepts(&self, _event: &IntegrationEvent) -> bool {
+        true
+    }
+
+    /// Called once before the first event, for exporters that read the
+    /// coordinator's node state too, e.g. request latencies.
+    fn start(&self, _node_states: &NodeStateCache) {}
+
+    /// Called once per accepted event, in order. Errors are logged and the
+    /// event is not retried.
+    async fn export(&self, event: &IntegrationEvent) -> Result<(), String>;
+
+    /// HTTP routes served next to the REST API, e.g. a scrape endpoint.
+    fn routes(&self) -> Option<Router> {
+        None
+    }
+}
+
+pub type SharedExporter = Arc<dyn Exporter>;
+
+/// The exporters a coordinator runs. Register them before the coordinator
+/// starts; each gets its own task, so a slow one falls behind (and skips
+/// events, with a warning) without holding up the others.
+#[derive(Clone, Default)]
+pub struct ExporterRegistry {
+    exporters: Vec<SharedExporter>,
+}
+
+impl std::fmt::Debug for ExporterRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.exporters.iter().map(|exporter| exporter.name()))
+            .finish()
+    }
+}
+
+impl ExporterRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, exporter: impl Exporter + 'static) -> &mut Self {
+        self.exporters.push(Arc::new(exporter));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.exporters.is_empty()
+    }
+
+    /// Every exporter's routes, merged.
+    pub fn routes(&self) -> Router {
+        self.exporters
+            .iter()
+            .filter_map(|exporter| exporter.routes())
+            .fold(Router::new(), Router::merge)
+    }
+
+    /// Starts feeding every exporter the events appended to `event_log` and
+    /// the connects and disconnects seen by `node_states`.
+    pub fn spawn(&self, event_log: &EventLog, node_states: &NodeStateCache) {
+        for exporter in &self.exporters {
+            info!("Exporting coordinator events to {}", exporter.name());
+            exporter.start(node_states);
+            tokio::spawn(run_exporter(
+                exporter.clone(),
+                event_log.subscribe(),
+                node_states.subscribe_connections(),
+            ));
+        }
+    }
+}
+
+async fn run_exporter(
+    exporter: SharedExporter,
+    mut events: broadcast::Receiver<StoredEvent>,
+    mut connections: broadcast::Receiver<NodeConnectionEvent>,
+) {
+    loop {
+        let event = tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => IntegrationEvent::Container(event),
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("{} fell behind, skipped {} events", exporter.name(), skipped);
+                    continue;
+                }
+                Err(RecvError::Closed) => return,
+            },
+            connection = connections.recv() => match connection {
+                Ok(connection) => IntegrationEvent::NodeConnection {
+                    node_id: connection.node_id,
+                    connected: connection.connected,
+                    time: chrono::Utc::now().timestamp(),
+                },
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!(
+                        "{} fell behind, skipped {} node connections",
+                        exporter.name(),
+                        skipped
+                    );
+                    continue;
+                }
+                Err(RecvError::Closed) => return,
+            },
+        };
+        if !exporter.accepts(&event) {
+            continue;
+        }
+        if let Err(e) = exporter.export(&event).await {
+            warn!(
+                "{} failed on {} {} of {}: {}",
+                exporter.name(),
+                event.kind(),
+                event.action(),
+                event.node_id(),
+                e
+            );
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use async_trait::async_trait;
+use axum::{Router, http::header, response::IntoResponse, routing::get};
+use lib_coordinator_core::{LATENCY_BUCKETS, NodeStateCache};
+
+use crate::{Exporter, IntegrationEvent};
+
+pub const DEFAULT_METRICS_PATH: &str = "/metrics";
+
+#[derive(Default)]
+struct Metrics {
+    /// By node, type and action.
+    events: BTreeMap<(String, String, String), u64>,
+    connected: BTreeMap<String, bool>,
+}
+
+/// Counts events, tracks node connections and node round-trip times, served
+/// in the Prometheus text format for scraping. Counters start at zero with
+/// each coordinator.
+#[derive(Clone)]
+pub struct PrometheusExporter {
+    path: String,
+    metrics: Arc<Mutex<Metrics>>,
+    node_states: Arc<OnceLock<NodeStateCache>>,
+}
+
+impl Default for PrometheusExporter {
+    fn default() -> Self {
+        Self::new(DEFAULT_METRICS_PATH)
+    }
+}
+
+impl PrometheusExporter {
+    /// Serves the metrics on `path`, e.g. `/metrics`.
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            metrics: Arc::default(),
+            node_states: Arc::default(),
+        }
+    }
+
+    fn metrics(&self) -> std::sync::MutexGuard<'_, Metrics> {
+        self.metrics.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The metrics in the text exposition format.
+    pub fn render(&self) -> String {
+        let metrics = self.metrics();
+        let mut out = String::new();
+        out.push_str("# HELP docklord_events_total Events reported by nodes since the coordinator started.\n");
+        out.push_str("# TYPE docklord_events_total counter\n");
+        for ((node_id, kind, action), count) in &metrics.events {
+            let _ = writeln!(
+                out,
+                "docklord_events_total{{node_id=\"{}\",type=\"{}\",action=\"{}\"}} {}",
+                label(node_id),
+                label(kind),
+                label(action),
+                count
+            );
+        }
+        out.push_str(
+            "# HELP docklord_node_connected Whether the node is connected to this coordinator.\n",
+        );
+        out.push_str("# TYPE docklord_node_connected gauge\n");
+        for (node_id, connected) in &metrics.connected {
+            let _ = writeln!(
+                out,
+                "docklord_node_connected{{node_id=\"{}\"}} {}",
+                label(node_id),
+                u8::from(*connected)
+            );
+        }
+        drop(metrics);
+
+        let Some(node_states) = self.node_states.get() else {
+            return out;
+        };
+        out.push_str("# HELP docklord_pending_requests_expired_total Requests dropped unanswered, past their deadline or abandoned by their waiter.\n");
+        out.push_str("# TYPE docklord_pending_requests_expired_total counter\n");
+        for (node_id, request_type, reason, count) in node_states.expired_requests().snapshot() {
+            let _ = writeln!(
+                out,
+                "docklord_pending_requests_expired_total{{node_id=\"{}\",request_type=\"{}\",reason=\"{}\"}} {}",
+                label(&node_id),
+                label(&request_type),
+                reason.as_str(),
+                count
+            );
+        }
+        out.push_str("# HELP docklord_node_request_duration_seconds Time from sending a request to a node until its reply arrived.\n");
+        out.push_str("# TYPE docklord_node_request_duration_seconds histogram\n");
+        for (node_id, request_type, histogram) in node_states.latency().snapshot() {
+            let labels = format!(
+                "node_id=\"{}\",request_type=\"{}\"",
+                label(&node_id),
+                label(&request_type)
+            );
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets) {
+                let _ = writeln!(
+                    out,
+                    "docklord_node_request_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+                    labels, bound, count
+                );
+            }
+            let _ = writeln!(
+                out,
+                "docklord_node_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}",
+                labels, histogram.count
+            );
+            let _ = writeln!(
+                out,
+                "docklord_node_request_duration_seconds_sum{{{}}} {}",
+                labels, histogram.sum
+            );
+            let _ = writeln!(
+                out,
+                "docklord_node_request_duration_seconds_count{{{}}} {}",
+                labels, histogram.count
+            );
+        }
+        out.push_str(
+            "# HELP docklord_ws_connections WebSocket connections open on this coordinator.\n",
+        );
+        out.push_str("# TYPE docklord_ws_connections gauge\n");
+        let ws_connections = node_states.ws_connections().snapshot();
+        for (credential, open, _) in &ws_connections {
+            let _ = writeln!(
+                out,
+                "docklord_ws_connections{{credential=\"{}\"}} {}",
+                label(credential),
+                open
+            );
+        }
+        out.push_str("# HELP docklord_ws_connections_rejected_total WebSocket connections closed for being over a cap.\n");
+        out.push_str("# TYPE docklord_ws_connections_rejected_total counter\n");
+        for (credential, _, rejected) in &ws_connections {
+            let _ = writeln!(
+                out,
+                "docklord_ws_connections_rejected_total{{credential=\"{}\"}} {}",
+                label(credential),
+                rejected
+            );
+        }
+        out
+    }
+}
+
+#[async_trait]
+impl Exporter for PrometheusExporter {
+    fn name(&self) -> &str {
+        "prometheus"
+    }
+
+    fn start(&self, node_states: &NodeStateCache) {
+        let _ = self.node_states.set(node_states.clone());
+    }
+
+    async fn export(&self, event: &IntegrationEvent) -> Result<(), String> {
+        let mut metrics = self.metrics();
+        match event {
+            IntegrationEvent::Container(stored) => {
+                *metrics
+                    .events
+                    .entry((
+                        stored.node_id.clone(),
+                        stored.kind.clone(),
+                        stored.action.clone(),
+                    ))
+                    .or_default() += 1;
+            }
+            IntegrationEvent::NodeConnection {
+                node_id, connected, ..
+            } => {
+                metrics.connected.insert(node_id.clone(), *connected);
+            }
+        }
+        Ok(())
+    }
+
+    fn routes(&self) -> Option<Router> {
+        let exporter = self.clone();
+        let scrape = move || async move {
+            (
+                [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+                exporter.render(),
+            )
+                .into_response()
+        };
+        Some(Router::new().route(&self.path, get(scrape)))
+    }
+}
+
+fn label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
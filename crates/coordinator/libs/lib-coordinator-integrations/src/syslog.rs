@@ -0,0 +1,131 @@
+use async_trait::async_trait;
+use chrono::{DateTime, SecondsFormat};
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpStream, UdpSocket};
+
+use crate::{Exporter, IntegrationEvent};
+
+// RFC 5424 limits HOSTNAME to 255 and MSGID to 32 characters
+const SYSLOG_HOSTNAME_MAX: usize = 255;
+const SYSLOG_MSGID_MAX: usize = 32;
+const SYSLOG_SD_ID: &str = "docklord@32473";
+
+enum Transport {
+    Udp(String),
+    Tcp(String),
+}
+
+/// Sends each event as an RFC 5424 message, with the node as hostname, the
+/// action as message id and the event's JSON as message.
+pub struct SyslogExporter {
+    name: String,
+    transport: Transport,
+}
+
+impl SyslogExporter {
+    /// `target` is `udp://host:port` or `tcp://host:port`.
+    pub fn new(target: &str) -> Result<Self, String> {
+        let transport = match target.split_once("://") {
+            Some(("udp", address)) if !address.is_empty() => Transport::Udp(address.to_string()),
+            Some(("tcp", address)) if !address.is_empty() => Transport::Tcp(address.to_string()),
+            _ => {
+                return Err("Syslog target must be udp://host:port or tcp://host:port".to_string());
+            }
+        };
+        Ok(Self {
+            name: format!("syslog {}", target),
+            transport,
+        })
+    }
+}
+
+#[async_trait]
+impl Exporter for SyslogExporter {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn export(&self, event: &IntegrationEvent) -> Result<(), String> {
+        let message = syslog_message(event);
+        match &self.transport {
+            Transport::Udp(address) => {
+                let socket = UdpSocket::bind("0.0.0.0:0")
+                    .await
+                    .map_err(|e| e.to_string())?;
+                socket.connect(address).await.map_err(|e| e.to_string())?;
+                socket
+                    .send(message.as_bytes())
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+            Transport::Tcp(address) => {
+                // Octet-counting framing (RFC 6587)
+                let frame = format!("{} {}", message.len(), message);
+                let mut stream = TcpStream::connect(address)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                stream
+                    .write_all(frame.as_bytes())
+                    .await
+                    .map_err(|e| e.to_string())?;
+                stream.flush().await.map_err(|e| e.to_string())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn syslog_message(event: &IntegrationEvent) -> String {
+    // Facility daemon (3); warning for failures and lost nodes, notice otherwise
+    let failed = match event {
+        IntegrationEvent::Container(stored) => {
+            matches!(stored.action.as_str(), "oom" | "kill")
+                || (stored.action == "die" && stored.exit_code != 0)
+                || stored.health == "unhealthy"
+        }
+        IntegrationEvent::NodeConnection { connected, .. } => !connected,
+    };
+    let priority = if failed { 28 } else { 29 };
+    let time = DateTime::from_timestamp(event.time(), 0)
+        .map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true))
+        .unwrap_or_else(|| "-".to_string());
+    let mut params = format!(" type=\"{}\"", sd_value(event.kind()));
+    if let IntegrationEvent::Container(stored) = event {
+        params.push_str(&format!(
+            " id=\"{}\" name=\"{}\"",
+            sd_value(&stored.container_id),
+            sd_value(&stored.name)
+        ));
+    }
+    format!(
+        "<{}>1 {} {} docklord - {} [{}{}] {}",
+        priority,
+        time,
+        syslog_token(event.node_id(), SYSLOG_HOSTNAME_MAX),
+        syslog_token(event.action(), SYSLOG_MSGID_MAX),
+        SYSLOG_SD_ID,
+        params,
+        event.to_json()
+    )
+}
+
+/// Printable ASCII without spaces.
+fn syslog_token(value: &str, max: usize) -> String {
+    let token: String = value
+        .chars()
+        .filter(|c| c.is_ascii_graphic())
+        .take(max)
+        .collect();
+    if token.is_empty() {
+        "-".to_string()
+    } else {
+        token
+    }
+}
+
+fn sd_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace(']', "\\]")
+}
@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use http_body_util::Full;
+use hyper::body::Bytes;
+use hyper::{Request, Uri, header};
+use hyper_util::client::legacy::Client;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::rt::TokioExecutor;
+
+use crate::{Exporter, IntegrationEvent};
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// POSTs events as JSON to an HTTP endpoint, e.g. an alerting gateway, with
+/// the action in `X-Docklord-Event`. Delivery is best effort: failures are
+/// logged, not retried.
+pub struct WebhookExporter {
+    name: String,
+    url: Uri,
+    /// Only these actions are sent; every event when empty.
+    actions: Vec<String>,
+    client: Client<HttpConnector, Full<Bytes>>,
+}
+
+impl WebhookExporter {
+    pub fn new(url: &str) -> Result<Self, String> {
+        let parsed: Uri = url
+            .parse()
+            .map_err(|e| format!("Invalid event webhook URL: {}", e))?;
+        if parsed.scheme_str() != Some("http") {
+            return Err("Event webhook must be an http:// URL".to_string());
+        }
+        Ok(Self {
+            name: format!("webhook {}", url),
+            url: parsed,
+            actions: Vec::new(),
+            client: Client::builder(TokioExecutor::new()).build_http(),
+        })
+    }
+
+    /// Sends only events with one of these actions, e.g. `health_status` or
+    /// `disconnect`.
+    pub fn with_actions(mut self, actions: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.actions = actions.into_iter().map(Into::into).collect();
+        self
+    }
+}
+
+#[async_trait]
+impl Exporter for WebhookExporter {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn accepts(&self, event: &IntegrationEvent) -> bool {
+        self.actions.is_empty() || self.actions.iter().any(|action| action == event.action())
+    }
+
+    async fn export(&self, event: &IntegrationEvent) -> Result<(), String> {
+        let request = Request::post(self.url.clone())
+            .header(header::CONTENT_TYPE, "application/json")
+            .header("X-Docklord-Event", event.action())
+            .body(Full::new(Bytes::from(event.to_json().to_string())))
+            .map_err(|e| e.to_string())?;
+
+        let response = tokio::time::timeout(WEBHOOK_TIMEOUT, self.client.request(request))
+            .await
+            .map_err(|_| "timed out".to_string())?
+            .map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("status {}", response.status()));
+        }
+        Ok(())
+    }
+}
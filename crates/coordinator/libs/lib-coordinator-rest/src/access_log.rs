@@ -0,0 +1,273 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderName, HeaderValue, header},
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
+use serde_json::json;
+use tokio::io::{AsyncWrite, AsyncWriteExt, BufWriter};
+use tokio::sync::mpsc;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::bearer_tokens::bearer_token;
+use crate::usage::credential_label;
+
+// Lines waiting for the writer; beyond this they are dropped rather than slowing requests
+const ACCESS_LOG_QUEUE: usize = 4096;
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+pub(crate) static REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessLogFormat {
+    /// Common Log Format, followed by latency, request id and user agent.
+    Common,
+    /// One JSON object per line.
+    Json,
+}
+
+impl FromStr for AccessLogFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "common" => Ok(AccessLogFormat::Common),
+            "json" => Ok(AccessLogFormat::Json),
+            other => Err(format!(
+                "unknown access log format '{}' (common or json)",
+                other
+            )),
+        }
+    }
+}
+
+/// Where and how HTTP requests are logged.
+#[derive(Debug, Clone)]
+pub struct AccessLogConfig {
+    pub format: AccessLogFormat,
+    /// File the lines are appended to; stdout when unset.
+    pub path: Option<PathBuf>,
+    /// Share of successful requests logged, from 0 to 1. Error responses are
+    /// always logged.
+    pub sample_rate: f64,
+}
+
+/// HTTP access log, written by a task of its own so requests never wait on
+/// the output. Kept apart from the tracing output, so it can go to a file
+/// for traffic analysis.
+#[derive(Clone)]
+pub struct AccessLog {
+    format: AccessLogFormat,
+    sample_rate: f64,
+    seen: Arc<AtomicU64>,
+    lines: mpsc::Sender<String>,
+}
+
+impl AccessLog {
+    /// Opens the log file, if any, and starts the writer.
+    pub fn new(config: AccessLogConfig) -> std::io::Result<Self> {
+        let (lines, rx) = mpsc::channel(ACCESS_LOG_QUEUE);
+        match &config.path {
+            Some(path) => {
+                let file = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)?;
+                tokio::spawn(write_lines(rx, tokio::fs::File::from_std(file)));
+            }
+            None => {
+                tokio::spawn(write_lines(rx, tokio::io::stdout()));
+            }
+        }
+        Ok(Self {
+            format: config.format,
+            sample_rate: config.sample_rate.clamp(0.0, 1.0),
+            seen: Arc::default(),
+            lines,
+        })
+    }
+
+    // Spreads the sampled requests evenly instead of drawing them at random
+    fn sampled(&self) -> bool {
+        let n = self.seen.fetch_add(1, Ordering::Relaxed) as f64;
+        ((n + 1.0) * self.sample_rate).floor() > (n * self.sample_rate).floor()
+    }
+}
+
+async fn write_lines(mut rx: mpsc::Receiver<String>, output: impl AsyncWrite + Unpin) {
+    let mut output = BufWriter::new(output);
+    while let Some(line) = rx.recv().await {
+        let mut result = output.write_all(line.as_bytes()).await;
+        // Write whatever else is queued before flushing
+        while result.is_ok()
+            && let Ok(line) = rx.try_recv()
+        {
+            result = output.write_all(line.as_bytes()).await;
+        }
+        if let Err(e) = result.and(output.flush().await) {
+            warn!("Cannot write the access log: {}", e);
+        }
+    }
+}
+
+/// Middleware logging each request with its method, path, status, latency,
+/// size, credential and request id. The request id comes from `X-Request-Id`
+/// or is generated, and is returned in the response's `X-Request-Id`. Query
+/// strings are never logged, as they may carry passwords.
+pub async fn log_access(
+    State(log): State<AccessLog>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let started = Instant::now();
+    let request_id = assign_request_id(&mut request);
+
+    let mut entry = AccessEntry {
+        log: log.clone(),
+        time: Utc::now(),
+        client: request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip().to_string()),
+        credential: credential(&request),
+        method: request.method().to_string(),
+        path: request.uri().path().to_string(),
+        version: format!("{:?}", request.version()),
+        status: 0,
+        bytes: 0,
+        latency: Duration::ZERO,
+        request_id: request_id.to_str().unwrap_or("-").to_string(),
+        user_agent: request
+            .headers()
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string),
+    };
+
+    let mut response = next.run(request).await;
+    response
+        .headers_mut()
+        .insert(REQUEST_ID.clone(), request_id);
+
+    let status = response.status();
+    let is_error = status.is_client_error() || status.is_server_error();
+    if !log.sampled() && !is_error {
+        return response;
+    }
+    // Time to the response head; streamed bodies may go on for much longer
+    entry.latency = started.elapsed();
+    entry.status = status.as_u16();
+
+    // The entry is written once the body is sent or the client goes away
+    let (parts, body) = response.into_parts();
+    let body = Body::from_stream(body.into_data_stream().inspect(move |chunk| {
+        // The closure owns the whole entry, so it is dropped with the body
+        let entry = &mut entry;
+        if let Ok(bytes) = chunk {
+            entry.bytes += bytes.len() as u64;
+        }
+    }));
+    Response::from_parts(parts, body)
+}
+
+/// The request's `X-Request-Id`, or a new one set on it when it has none or
+/// an unusable one, so every later layer sees the same id.
+pub(crate) fn assign_request_id(request: &mut Request) -> HeaderValue {
+    let request_id = request
+        .headers()
+        .get(&REQUEST_ID)
+        .filter(|id| !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN)
+        .cloned()
+        .unwrap_or_else(|| {
+            HeaderValue::from_str(&Uuid::new_v4().to_string())
+                .expect("a UUID is a valid header value")
+        });
+    request
+        .headers_mut()
+        .insert(REQUEST_ID.clone(), request_id.clone());
+    request_id
+}
+
+struct AccessEntry {
+    log: AccessLog,
+    time: DateTime<Utc>,
+    client: Option<String>,
+    credential: String,
+    method: String,
+    path: String,
+    version: String,
+    status: u16,
+    bytes: u64,
+    latency: Duration,
+    request_id: String,
+    user_agent: Option<String>,
+}
+
+impl Drop for AccessEntry {
+    fn drop(&mut self) {
+        let latency_ms = self.latency.as_secs_f64() * 1000.0;
+        let line = match self.log.format {
+            AccessLogFormat::Common => format!(
+                "{} - {} [{}] \"{} {} {}\" {} {} {:.1}ms {} \"{}\"\n",
+                self.client.as_deref().unwrap_or("-"),
+                self.credential,
+                self.time.format("%d/%b/%Y:%H:%M:%S %z"),
+                self.method,
+                self.path,
+                self.version,
+                self.status,
+                self.bytes,
+                latency_ms,
+                self.request_id,
+                self.user_agent
+                    .as_deref()
+                    .unwrap_or("-")
+                    .replace('"', "\\\""),
+            ),
+            AccessLogFormat::Json => {
+                let entry = json!({
+                    "time": self.time.to_rfc3339(),
+                    "client": self.client,
+                    "credential": self.credential,
+                    "method": self.method,
+                    "path": self.path,
+                    "version": self.version,
+                    "status": self.status,
+                    "bytes": self.bytes,
+                    "latency_ms": (latency_ms * 10.0).round() / 10.0,
+                    "request_id": self.request_id,
+                    "user_agent": self.user_agent,
+                });
+                format!("{}\n", entry)
+            }
+        };
+        if self.log.lines.try_send(line).is_err() {
+            warn!("Access log queue full, dropping a line");
+        }
+    }
+}
+
+// Bearer tokens are resolved after this, so they are told apart by a hash of
+// their own; the token itself is never logged
+fn credential(request: &Request) -> String {
+    match bearer_token(request.headers()) {
+        Some(token) => {
+            let mut hasher = DefaultHasher::new();
+            token.hash(&mut hasher);
+            format!("token:{:016x}", hasher.finish())
+        }
+        None => credential_label(request),
+    }
+}
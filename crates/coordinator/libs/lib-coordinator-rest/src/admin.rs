@@ -0,0 +1,50 @@
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use subtle::ConstantTimeEq;
+use utoipa::IntoParams;
+
+use crate::{ApiError, ApiErrorDetail};
+
+/// Credential for cluster-wide routes. `None` keeps those routes disabled.
+#[derive(Clone, Default)]
+pub struct AdminToken(pub Option<Arc<str>>);
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AdminParams {
+    /// The coordinator's `--admin-token`.
+    pub admin_token: Option<String>,
+}
+
+impl AdminToken {
+    pub fn new(token: Option<String>) -> Self {
+        Self(token.filter(|t| !t.is_empty()).map(Arc::from))
+    }
+
+    /// Returns an error response unless admin routes are enabled and the token matches.
+    pub fn reject(&self, params: &AdminParams, request_id: &str) -> Option<Response> {
+        let (status, message) = match (&self.0, &params.admin_token) {
+            (Some(expected), Some(given))
+                if bool::from(given.as_bytes().ct_eq(expected.as_bytes())) =>
+            {
+                return None;
+            }
+            (None, _) => (StatusCode::FORBIDDEN, "Admin API disabled"),
+            _ => (StatusCode::UNAUTHORIZED, "Invalid admin token"),
+        };
+        let err = ApiError {
+            req_id: request_id.to_string(),
+            error: ApiErrorDetail {
+                message: message.to_string(),
+                detail: "Cluster-wide routes require the coordinator admin token".to_string(),
+            },
+        };
+        Some((status, Json(err)).into_response())
+    }
+}
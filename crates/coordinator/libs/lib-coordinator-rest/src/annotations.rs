@@ -0,0 +1,117 @@
+use axum::{
+    Extension, Json,
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use lib_coordinator_core::{AnnotationStore, Annotations, NodeChannels};
+use serde_json::json;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::validation::{ValidJson, Validator};
+use crate::{AuthParams, error_response, reject_unknown_node};
+
+const MAX_ANNOTATIONS: usize = 64;
+const MAX_ANNOTATION_KEY_LEN: usize = 128;
+const MAX_ANNOTATION_VALUE_LEN: usize = 4096;
+
+#[utoipa::path(
+    get,
+    path = "/api/containers/{container_id}/annotations",
+    tag = "containers",
+    params(("container_id" = String, Path, description = "Container id or name"), AuthParams),
+    responses((status = 200, description = "The container's annotations", body = Object))
+)]
+pub async fn get_container_annotations(
+    Path(container_id): Path<String>,
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(annotations): Extension<AnnotationStore>,
+    query: AuthParams,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = Validator::new()
+        .container_id("container_id", &container_id)
+        .reject(&request_id)
+    {
+        return response;
+    }
+    if let Some(response) = reject_unknown_node(&nodes, &query, &request_id).await {
+        return response;
+    }
+
+    let body = json!({
+        "req_id": request_id,
+        "container_id": container_id,
+        "annotations": annotations.get(&query.node_id, &container_id),
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+/// Replaces all annotations of a container with the JSON object in the body.
+#[utoipa::path(
+    put,
+    path = "/api/containers/{container_id}/annotations",
+    tag = "containers",
+    params(("container_id" = String, Path, description = "Container id or name"), AuthParams),
+    request_body = BTreeMap<String, String>,
+    responses((status = 200, description = "The stored annotations", body = Object))
+)]
+pub async fn put_container_annotations(
+    Path(container_id): Path<String>,
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(annotations): Extension<AnnotationStore>,
+    query: AuthParams,
+    ValidJson(body): ValidJson<Annotations>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    let mut validator = Validator::new();
+    validator.container_id("container_id", &container_id);
+    if let Err(detail) = validate_annotations(&body) {
+        validator.check(false, "annotations", detail);
+    }
+    if let Some(response) = validator.reject(&request_id) {
+        return response;
+    }
+    if let Some(response) = reject_unknown_node(&nodes, &query, &request_id).await {
+        return response;
+    }
+
+    if let Err(e) = annotations.set(&query.node_id, &container_id, body.clone()) {
+        error!("Failed to persist annotations: {}", e);
+        return error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            &request_id,
+            "Failed to store annotations",
+            e.to_string(),
+        );
+    }
+
+    let body = json!({
+        "req_id": request_id,
+        "container_id": container_id,
+        "annotations": body,
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+fn validate_annotations(annotations: &Annotations) -> Result<(), String> {
+    if annotations.len() > MAX_ANNOTATIONS {
+        return Err(format!("at most {} annotations allowed", MAX_ANNOTATIONS));
+    }
+    for (key, value) in annotations {
+        if key.is_empty() || key.len() > MAX_ANNOTATION_KEY_LEN {
+            return Err(format!(
+                "key \"{}\" must be 1-{} bytes",
+                key, MAX_ANNOTATION_KEY_LEN
+            ));
+        }
+        if value.len() > MAX_ANNOTATION_VALUE_LEN {
+            return Err(format!(
+                "value of \"{}\" exceeds {} bytes",
+                key, MAX_ANNOTATION_VALUE_LEN
+            ));
+        }
+    }
+    Ok(())
+}
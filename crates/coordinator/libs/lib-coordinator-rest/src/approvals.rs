@@ -0,0 +1,495 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use axum::{
+    Extension, Json,
+    body::{Body, to_bytes},
+    extract::{Path, Request, State},
+    http::{Extensions, HeaderName, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use lib_coordinator_core::{NodeChannels, verify_node};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tracing::{info, warn};
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::admin::{AdminParams, AdminToken};
+use crate::rate_limit::target_node_id;
+use crate::tenants::{CallerKey, CallerTenant};
+use crate::validation::ValidQuery;
+use crate::{AuthParams, VerifiedNode, error_response, reject_unknown_node, request_password};
+
+// Bodies of parked requests are kept in memory; destructive routes send little or none
+const MAX_PARKED_BODY: usize = 1024 * 1024;
+// Decided approvals stay listed this long, for whoever asked
+const DECIDED_RETENTION: Duration = Duration::from_secs(3600);
+
+static APPROVAL_ID: HeaderName = HeaderName::from_static("x-approval-id");
+
+/// Nodes whose destructive actions need a second credential's approval.
+#[derive(Debug, Clone)]
+pub struct ApprovalPolicy {
+    /// Protected node ids; `*` protects every node.
+    pub nodes: Vec<String>,
+    /// How long a request waits for approval before it is dropped.
+    pub ttl: Duration,
+}
+
+impl ApprovalPolicy {
+    pub fn protects(&self, node_id: &str) -> bool {
+        self.nodes.iter().any(|node| node == "*" || node == node_id)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ApprovalStatus {
+    Pending,
+    Approved { by: String, result: Option<u16> },
+    Rejected { by: String },
+}
+
+struct Approval {
+    node_id: String,
+    action: &'static str,
+    method: String,
+    path: String,
+    requested_by: String,
+    requested_at: i64,
+    expires_at: i64,
+    status: ApprovalStatus,
+    decided_at: Option<i64>,
+    // Taken out when approved
+    parked: Option<(Next, Request)>,
+}
+
+impl Approval {
+    fn to_json(&self, approval_id: &str) -> Value {
+        let (status, decided_by, result_status) = match &self.status {
+            ApprovalStatus::Pending => ("pending", None, None),
+            ApprovalStatus::Approved { by, result } => ("approved", Some(by), *result),
+            ApprovalStatus::Rejected { by } => ("rejected", Some(by), None),
+        };
+        json!({
+            "approval_id": approval_id,
+            "status": status,
+            "node_id": self.node_id,
+            "action": self.action,
+            "method": self.method,
+            "path": self.path,
+            "requested_by": self.requested_by,
+            "requested_at": self.requested_at,
+            "expires_at": self.expires_at,
+            "decided_by": decided_by,
+            "decided_at": self.decided_at,
+            "result_status": result_status,
+        })
+    }
+}
+
+/// Destructive requests to protected nodes waiting for a second credential.
+/// They are held in memory with their body and dispatched only once approved.
+#[derive(Clone)]
+pub struct ApprovalQueue {
+    policy: Option<Arc<ApprovalPolicy>>,
+    nodes: NodeChannels,
+    admin: AdminToken,
+    approvals: Arc<Mutex<HashMap<String, Approval>>>,
+}
+
+impl ApprovalQueue {
+    pub fn new(policy: Option<ApprovalPolicy>, nodes: NodeChannels, admin: AdminToken) -> Self {
+        Self {
+            policy: policy.map(Arc::new),
+            nodes,
+            admin,
+            approvals: Arc::default(),
+        }
+    }
+
+    // Drops pending approvals past their deadline and decided ones past retention
+    fn approvals(&self) -> MutexGuard<'_, HashMap<String, Approval>> {
+        let mut approvals = self.approvals.lock().unwrap_or_else(|e| e.into_inner());
+        let now = now_secs();
+        approvals.retain(|id, approval| match approval.decided_at {
+            None if approval.expires_at <= now => {
+                info!(
+                    "Approval {} of {} on {} expired",
+                    id, approval.action, approval.node_id
+                );
+                false
+            }
+            None => true,
+            Some(decided_at) => decided_at + (DECIDED_RETENTION.as_secs() as i64) > now,
+        });
+        approvals
+    }
+
+    /// Who is asking, for telling requester and approver apart. Only verified
+    /// credentials count: the admin token, else the tenant key the tenant
+    /// middleware accepted, else the node whose password was checked. Bearer
+    /// tokens count as their node's credential, since anyone with the password
+    /// can get one.
+    fn credential(
+        &self,
+        params: &HashMap<String, String>,
+        extensions: &Extensions,
+        node_id: &str,
+    ) -> String {
+        let admin_params = AdminParams {
+            admin_token: params.get("admin_token").cloned(),
+        };
+        if admin_params.admin_token.is_some() && self.admin.reject(&admin_params, "").is_none() {
+            return "admin".to_string();
+        }
+        match (
+            extensions.get::<CallerTenant>(),
+            extensions.get::<CallerKey>(),
+        ) {
+            (Some(CallerTenant(tenant)), Some(CallerKey(key_id))) => {
+                format!("tenant:{}/{}", tenant, key_id)
+            }
+            _ => format!("node:{}", node_id),
+        }
+    }
+}
+
+/// The approval-gated actions, named as for authorization policies.
+fn destructive_action(method: &str, path: &str) -> Option<&'static str> {
+    let segments: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+    match (method, segments.as_slice()) {
+        ("DELETE", ["api", "containers", _]) => Some("delete_container"),
+        ("POST", ["api", "containers", _, "stop"]) => Some("stop_container"),
+        ("POST", ["api", "containers", _, "restart"]) => Some("restart_container"),
+        ("DELETE", ["api", "images", _]) => Some("remove_image"),
+        ("POST", ["api", "images", "prune"]) => Some("prune_images"),
+        ("POST", ["api", "system", "prune"]) => Some("prune_system"),
+        ("DELETE", ["api", "networks", _]) => Some("remove_network"),
+        ("DELETE", ["api", "nodes", _, "secrets", _]) => Some("delete_secret"),
+        _ => None,
+    }
+}
+
+fn query_params(request: &Request) -> HashMap<String, String> {
+    request
+        .uri()
+        .query()
+        .and_then(|query| serde_urlencoded::from_str(query).ok())
+        .unwrap_or_default()
+}
+
+/// Middleware parking destructive requests to protected nodes: instead of
+/// running them it answers `202 Accepted` with an approval id, and runs them
+/// when a different credential approves at `/api/approvals/{id}/approve`.
+/// Requests with wrong credentials pass through and fail as usual.
+pub async fn require_approval(
+    State(queue): State<ApprovalQueue>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(policy) = &queue.policy else {
+        return next.run(request).await;
+    };
+    let Some(action) = destructive_action(request.method().as_str(), request.uri().path()) else {
+        return next.run(request).await;
+    };
+    let Some(node_id) = target_node_id(&request).filter(|node_id| policy.protects(node_id)) else {
+        return next.run(request).await;
+    };
+    let params = query_params(&request);
+    let password = request_password(&request, &queue.nodes).unwrap_or_default();
+    if !verify_node(&queue.nodes, &node_id, &password).await {
+        return next.run(request).await;
+    }
+
+    let request_id = Uuid::new_v4().to_string();
+    // The client's body is read now; the connection is gone by the time of approval
+    let (parts, body) = request.into_parts();
+    let body = match to_bytes(body, MAX_PARKED_BODY).await {
+        Ok(body) => body,
+        Err(e) => {
+            return error_response(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                &request_id,
+                "Request too large to hold for approval",
+                e.to_string(),
+            );
+        }
+    };
+    let request = Request::from_parts(parts, Body::from(body));
+    let requested_by = queue.credential(&params, request.extensions(), &node_id);
+
+    let approval_id = Uuid::new_v4().to_string();
+    let now = now_secs();
+    let approval = Approval {
+        node_id,
+        action,
+        method: request.method().to_string(),
+        path: request.uri().path().to_string(),
+        requested_by,
+        requested_at: now,
+        expires_at: now + policy.ttl.as_secs() as i64,
+        status: ApprovalStatus::Pending,
+        decided_at: None,
+        parked: Some((next, request)),
+    };
+    info!(
+        "{} on {} by {} waits for approval {}",
+        approval.action, approval.node_id, approval.requested_by, approval_id
+    );
+    let mut body = approval.to_json(&approval_id);
+    body["id"] = json!(request_id);
+    queue.approvals().insert(approval_id.clone(), approval);
+
+    let mut response = (StatusCode::ACCEPTED, Json(body)).into_response();
+    if let Ok(value) = HeaderValue::from_str(&approval_id) {
+        response.headers_mut().insert(APPROVAL_ID.clone(), value);
+    }
+    response
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ApprovalAuthQuery {
+    node_id: Option<String>,
+    password: Option<String>,
+    admin_token: Option<String>,
+}
+
+impl ApprovalAuthQuery {
+    /// Checks the admin token, or else the node credentials, which a bearer
+    /// token or session may have vouched for.
+    async fn reject(
+        &self,
+        admin: &AdminToken,
+        nodes: &NodeChannels,
+        extensions: &Extensions,
+        request_id: &str,
+    ) -> Option<Response> {
+        if self.admin_token.is_some() {
+            let admin_params = AdminParams {
+                admin_token: self.admin_token.clone(),
+            };
+            return admin.reject(&admin_params, request_id);
+        }
+        let auth = AuthParams {
+            node_id: self.node_id.clone().unwrap_or_default(),
+            password: match extensions.get::<VerifiedNode>() {
+                Some(verified) => verified.password(nodes),
+                None => self.password.clone().unwrap_or_default(),
+            },
+        };
+        reject_unknown_node(nodes, &auth, request_id).await
+    }
+
+    /// The node whose approvals the caller may see; every node's for the admin.
+    fn scope(&self) -> Option<&str> {
+        match self.admin_token {
+            Some(_) => None,
+            None => Some(self.node_id.as_deref().unwrap_or_default()),
+        }
+    }
+}
+
+/// Pending and recently decided approvals, oldest first. Node credentials see
+/// their node's, the admin token every node's.
+#[utoipa::path(
+    get,
+    path = "/api/approvals",
+    tag = "approvals",
+    params(ApprovalAuthQuery),
+    responses((status = 200, description = "Pending and recently decided approvals", body = Object))
+)]
+pub async fn list_approvals(
+    Extension(queue): Extension<ApprovalQueue>,
+    Extension(admin): Extension<AdminToken>,
+    extensions: Extensions,
+    ValidQuery(query): ValidQuery<ApprovalAuthQuery>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = query
+        .reject(&admin, &queue.nodes, &extensions, &request_id)
+        .await
+    {
+        return response;
+    }
+
+    let approvals = queue.approvals();
+    let mut visible: Vec<(&String, &Approval)> = approvals
+        .iter()
+        .filter(|(_, approval)| query.scope().is_none_or(|node| node == approval.node_id))
+        .collect();
+    visible.sort_by_key(|(_, approval)| approval.requested_at);
+    let visible: Vec<Value> = visible
+        .into_iter()
+        .map(|(id, approval)| approval.to_json(id))
+        .collect();
+    let body = json!({
+        "id": request_id,
+        "enabled": queue.policy.is_some(),
+        "count": visible.len(),
+        "approvals": visible,
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+/// Runs a parked request and answers with its response. The approver must use
+/// another credential than the requester.
+#[utoipa::path(
+    post,
+    path = "/api/approvals/{approval_id}/approve",
+    tag = "approvals",
+    params(("approval_id" = String, Path, description = "Approval id"), ApprovalAuthQuery),
+    responses((status = 200, description = "The held request's response, with its own status", body = Object), (status = 409, description = "Already decided", body = Object))
+)]
+pub async fn approve_request(
+    Path(approval_id): Path<String>,
+    Extension(queue): Extension<ApprovalQueue>,
+    Extension(admin): Extension<AdminToken>,
+    extensions: Extensions,
+    ValidQuery(query): ValidQuery<ApprovalAuthQuery>,
+    ValidQuery(params): ValidQuery<HashMap<String, String>>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = query
+        .reject(&admin, &queue.nodes, &extensions, &request_id)
+        .await
+    {
+        return response;
+    }
+    let approver = queue.credential(
+        &params,
+        &extensions,
+        query.node_id.as_deref().unwrap_or_default(),
+    );
+
+    let (next, request, action, node_id) = {
+        let mut approvals = queue.approvals();
+        let Some(approval) = decidable(&mut approvals, &approval_id, query.scope()) else {
+            return not_found(&request_id, &approval_id);
+        };
+        if approval.requested_by == approver {
+            return error_response(
+                StatusCode::FORBIDDEN,
+                &request_id,
+                "Second credential required",
+                format!(
+                    "{} requested this, another credential must approve it",
+                    approver
+                ),
+            );
+        }
+        let Some((next, request)) = approval.parked.take() else {
+            return decided_response(&request_id);
+        };
+        approval.status = ApprovalStatus::Approved {
+            by: approver.clone(),
+            result: None,
+        };
+        approval.decided_at = Some(now_secs());
+        (next, request, approval.action, approval.node_id.clone())
+    };
+
+    info!(
+        "{} approved {} on {} ({})",
+        approver, action, node_id, approval_id
+    );
+    let mut response = next.run(request).await;
+    if let Some(approval) = queue.approvals().get_mut(&approval_id) {
+        approval.status = ApprovalStatus::Approved {
+            by: approver,
+            result: Some(response.status().as_u16()),
+        };
+    }
+    if let Ok(value) = HeaderValue::from_str(&approval_id) {
+        response.headers_mut().insert(APPROVAL_ID.clone(), value);
+    }
+    response
+}
+
+/// Drops a parked request without running it. The requester may withdraw
+/// their own request this way.
+#[utoipa::path(
+    post,
+    path = "/api/approvals/{approval_id}/reject",
+    tag = "approvals",
+    params(("approval_id" = String, Path, description = "Approval id"), ApprovalAuthQuery),
+    responses((status = 200, description = "The request was dropped", body = Object), (status = 409, description = "Already decided", body = Object))
+)]
+pub async fn reject_request(
+    Path(approval_id): Path<String>,
+    Extension(queue): Extension<ApprovalQueue>,
+    Extension(admin): Extension<AdminToken>,
+    extensions: Extensions,
+    ValidQuery(query): ValidQuery<ApprovalAuthQuery>,
+    ValidQuery(params): ValidQuery<HashMap<String, String>>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = query
+        .reject(&admin, &queue.nodes, &extensions, &request_id)
+        .await
+    {
+        return response;
+    }
+    let by = queue.credential(
+        &params,
+        &extensions,
+        query.node_id.as_deref().unwrap_or_default(),
+    );
+
+    let mut approvals = queue.approvals();
+    let Some(approval) = decidable(&mut approvals, &approval_id, query.scope()) else {
+        return not_found(&request_id, &approval_id);
+    };
+    if approval.parked.take().is_none() {
+        return decided_response(&request_id);
+    }
+    warn!(
+        "{} rejected {} on {} ({})",
+        by, approval.action, approval.node_id, approval_id
+    );
+    approval.status = ApprovalStatus::Rejected { by };
+    approval.decided_at = Some(now_secs());
+    let mut body = approval.to_json(&approval_id);
+    body["id"] = json!(request_id);
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+// Approvals of other nodes are reported missing, like unknown ones
+fn decidable<'a>(
+    approvals: &'a mut HashMap<String, Approval>,
+    approval_id: &str,
+    scope: Option<&str>,
+) -> Option<&'a mut Approval> {
+    approvals
+        .get_mut(approval_id)
+        .filter(|approval| scope.is_none_or(|node| node == approval.node_id))
+}
+
+fn not_found(request_id: &str, approval_id: &str) -> Response {
+    error_response(
+        StatusCode::NOT_FOUND,
+        request_id,
+        "Approval not found",
+        format!("No approval {} (it may have expired)", approval_id),
+    )
+}
+
+fn decided_response(request_id: &str) -> Response {
+    error_response(
+        StatusCode::CONFLICT,
+        request_id,
+        "Already decided",
+        "The request was already approved or rejected".to_string(),
+    )
+}
+
+fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
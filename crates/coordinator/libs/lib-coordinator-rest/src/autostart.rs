@@ -0,0 +1,207 @@
+use std::collections::HashSet;
+
+use axum::{
+    Extension, Json,
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use lib_coordinator_core::{
+    AutostartEntry, AutostartRun, AutostartStore, CommandRouter, NodeChannels, PendingResponses,
+};
+use proto::generated::{Envelope, NodeCommand, RequestType, envelope::Payload, node_command};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::error;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::node_request::request_node;
+use crate::validation::{ValidJson, Validator};
+use crate::{NodePasswordParams, error_response, reject_unknown_node};
+
+const MAX_AUTOSTART_CONTAINERS: usize = 100;
+const MAX_AUTOSTART_DELAY_SECS: u32 = 600;
+
+#[derive(Deserialize, ToSchema)]
+pub struct AutostartBody {
+    /// Started in this order.
+    containers: Vec<AutostartEntry>,
+    #[serde(default)]
+    only_after_reboot: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/nodes/{node_id}/autostart",
+    tag = "nodes",
+    params(("node_id" = String, Path, description = "Node id"), NodePasswordParams),
+    responses((status = 200, description = "The node's autostart list", body = Object))
+)]
+pub async fn get_autostart(
+    Path(node_id): Path<String>,
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(autostart): Extension<AutostartStore>,
+    query: NodePasswordParams,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    let auth = query.for_node(node_id);
+    if let Some(response) = reject_unknown_node(&nodes, &auth, &request_id).await {
+        return response;
+    }
+
+    let plan = autostart.get(&auth.node_id).unwrap_or_default();
+    let body = json!({
+        "req_id": request_id,
+        "node_id": auth.node_id,
+        "containers": plan.containers,
+        "only_after_reboot": plan.only_after_reboot,
+        "last_run": plan.last_run,
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+/// Replaces the node's autostart list; an empty list removes it.
+#[utoipa::path(
+    put,
+    path = "/api/nodes/{node_id}/autostart",
+    tag = "nodes",
+    params(("node_id" = String, Path, description = "Node id"), NodePasswordParams),
+    request_body = AutostartBody,
+    responses((status = 200, description = "The stored autostart list", body = Object))
+)]
+pub async fn put_autostart(
+    Path(node_id): Path<String>,
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(autostart): Extension<AutostartStore>,
+    query: NodePasswordParams,
+    ValidJson(body): ValidJson<AutostartBody>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    let mut validator = Validator::new();
+    validator.check(
+        body.containers.len() <= MAX_AUTOSTART_CONTAINERS,
+        "containers",
+        format!("at most {} containers", MAX_AUTOSTART_CONTAINERS),
+    );
+    let mut seen = HashSet::new();
+    for (i, entry) in body.containers.iter().enumerate() {
+        let field = format!("containers[{}]", i);
+        validator
+            .container_id(&format!("{}.container_id", field), &entry.container_id)
+            .range(
+                &format!("{}.delay_secs", field),
+                Some(entry.delay_secs),
+                0,
+                MAX_AUTOSTART_DELAY_SECS,
+            )
+            .check(
+                seen.insert(entry.container_id.as_str()),
+                &field,
+                format!("{} is listed twice", entry.container_id),
+            );
+    }
+    if let Some(response) = validator.reject(&request_id) {
+        return response;
+    }
+    let auth = query.for_node(node_id);
+    if let Some(response) = reject_unknown_node(&nodes, &auth, &request_id).await {
+        return response;
+    }
+
+    match autostart.set(&auth.node_id, body.containers, body.only_after_reboot) {
+        Ok(()) => {
+            let plan = autostart.get(&auth.node_id).unwrap_or_default();
+            let body = json!({
+                "req_id": request_id,
+                "node_id": auth.node_id,
+                "containers": plan.containers,
+                "only_after_reboot": plan.only_after_reboot,
+            });
+            (StatusCode::OK, Json(body)).into_response()
+        }
+        Err(e) => {
+            error!("Failed to persist autostart list: {}", e);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                &request_id,
+                "Failed to store autostart list",
+                e.to_string(),
+            )
+        }
+    }
+}
+
+/// Runs the autostart list now, regardless of `only_after_reboot`, and waits for the result.
+#[utoipa::path(
+    post,
+    path = "/api/nodes/{node_id}/autostart/apply",
+    tag = "nodes",
+    params(("node_id" = String, Path, description = "Node id"), NodePasswordParams),
+    responses((status = 200, description = "What happened to each container", body = Object))
+)]
+pub async fn apply_autostart(
+    Path(node_id): Path<String>,
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    Extension(autostart): Extension<AutostartStore>,
+    query: NodePasswordParams,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    let auth = query.for_node(node_id);
+    let Some(plan) = autostart.get(&auth.node_id) else {
+        return error_response(
+            StatusCode::NOT_FOUND,
+            &request_id,
+            "No autostart list",
+            format!("Node {} has no autostart list", auth.node_id),
+        );
+    };
+
+    let envelope = Envelope {
+        payload: Some(Payload::NodeCommand(NodeCommand {
+            kind: Some(node_command::Kind::ApplyAutostart(
+                plan.command(&request_id, false),
+            )),
+        })),
+        ..Default::default()
+    };
+
+    match request_node(
+        &server_tx,
+        &pending,
+        &auth,
+        &request_id,
+        RequestType::ApplyAutostart as i32,
+        envelope,
+        plan.timeout(),
+    )
+    .await
+    {
+        Ok(response) => {
+            let run = extract_autostart_run_from_response(&response);
+            if let Some(run) = &run
+                && let Err(e) = autostart.record_run(&auth.node_id, run.clone())
+            {
+                error!("Failed to record autostart run: {}", e);
+            }
+            let body = json!({
+                "id": request_id,
+                "node_id": auth.node_id,
+                "run": run,
+            });
+            (StatusCode::OK, Json(body)).into_response()
+        }
+        Err(e) => e.into_response(&request_id),
+    }
+}
+
+fn extract_autostart_run_from_response(response: &Envelope) -> Option<AutostartRun> {
+    if let Some(proto::generated::envelope::Payload::NodeResponse(node_resp)) = &response.payload
+        && let Some(proto::generated::node_response::Kind::AutostartReport(report)) =
+            &node_resp.kind
+    {
+        return Some(report.into());
+    }
+    None
+}
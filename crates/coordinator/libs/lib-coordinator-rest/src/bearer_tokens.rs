@@ -0,0 +1,333 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::{
+    Extension, Json,
+    extract::{Request, State},
+    http::{HeaderMap, StatusCode, Uri, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use dashmap::DashMap;
+use lib_coordinator_core::NodeChannels;
+use serde::Deserialize;
+use serde_json::json;
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::validation::{ValidJson, Validator};
+use crate::{AuthParams, VerifiedNode, error_response, reject_unknown_node};
+
+const DEFAULT_TOKEN_TTL_SECS: u64 = 3600;
+const MIN_TOKEN_TTL_SECS: u64 = 60;
+const MAX_TOKEN_TTL_SECS: u64 = 24 * 3600;
+const TOKEN_PREFIX: &str = "dlt_";
+
+struct IssuedToken {
+    node_id: String,
+    expires_at: Instant,
+}
+
+/// Bearer tokens handed out for node credentials, kept in memory so they end
+/// with the coordinator. Only the node a token is for is kept, never its password.
+#[derive(Clone, Default)]
+pub struct TokenStore {
+    tokens: Arc<DashMap<String, IssuedToken>>,
+}
+
+impl TokenStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn issue(&self, node_id: String, ttl: Duration) -> String {
+        let now = Instant::now();
+        self.tokens.retain(|_, issued| issued.expires_at > now);
+        let token = format!(
+            "{}{}{}",
+            TOKEN_PREFIX,
+            Uuid::new_v4().simple(),
+            Uuid::new_v4().simple()
+        );
+        self.tokens.insert(
+            token.clone(),
+            IssuedToken {
+                node_id,
+                expires_at: now + ttl,
+            },
+        );
+        token
+    }
+
+    /// The node of a token that has not expired.
+    fn resolve(&self, token: &str) -> Option<String> {
+        let issued = self.tokens.get(token)?;
+        if issued.expires_at <= Instant::now() {
+            drop(issued);
+            self.tokens.remove(token);
+            return None;
+        }
+        Some(issued.node_id.clone())
+    }
+
+    fn revoke(&self, token: &str) -> bool {
+        self.tokens.remove(token).is_some()
+    }
+}
+
+/// State of the bearer token middleware.
+#[derive(Clone)]
+pub struct BearerAuth {
+    tokens: TokenStore,
+    query_auth: bool,
+}
+
+impl BearerAuth {
+    /// `query_auth` keeps accepting `node_id`/`password` query parameters.
+    pub fn new(tokens: TokenStore, query_auth: bool) -> Self {
+        Self { tokens, query_auth }
+    }
+}
+
+/// Resolves `Authorization: Bearer <token>` into the token's node: its id goes
+/// in the query, and a [`VerifiedNode`] marks the request as authenticated for
+/// it, so every route accepts tokens. Without query auth, requests carrying a
+/// `password` parameter are refused.
+pub async fn resolve_bearer_token(
+    State(auth): State<BearerAuth>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let Some(token) = bearer_token(request.headers()) else {
+        if !auth.query_auth && has_password_param(request.uri()) {
+            return error_response(
+                StatusCode::UNAUTHORIZED,
+                &Uuid::new_v4().to_string(),
+                "Query credentials disabled",
+                "Exchange the credentials at POST /api/auth/token and send Authorization: Bearer <token>"
+                    .to_string(),
+            );
+        }
+        return next.run(request).await;
+    };
+    let Some(node_id) = auth.tokens.resolve(token) else {
+        return error_response(
+            StatusCode::UNAUTHORIZED,
+            &Uuid::new_v4().to_string(),
+            "Invalid token",
+            "The bearer token is unknown or expired".to_string(),
+        );
+    };
+
+    let uri = match with_node_id(request.uri(), &node_id) {
+        Ok(uri) => uri,
+        Err(detail) => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                &Uuid::new_v4().to_string(),
+                "Invalid query",
+                detail,
+            );
+        }
+    };
+    *request.uri_mut() = uri;
+    request.extensions_mut().insert(VerifiedNode(node_id));
+    next.run(request).await
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct TokenRequest {
+    node_id: String,
+    password: String,
+    /// Token lifetime in seconds (default 3600, 60 to 86400).
+    ttl_secs: Option<u64>,
+}
+
+/// Exchanges node credentials for a bearer token. The node must be connected.
+#[utoipa::path(
+    post,
+    path = "/api/auth/token",
+    tag = "auth",
+    request_body = TokenRequest,
+    responses((status = 200, description = "The token and when it expires", body = Object))
+)]
+pub async fn issue_token(
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(tokens): Extension<TokenStore>,
+    ValidJson(body): ValidJson<TokenRequest>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = Validator::new()
+        .range(
+            "ttl_secs",
+            body.ttl_secs,
+            MIN_TOKEN_TTL_SECS,
+            MAX_TOKEN_TTL_SECS,
+        )
+        .reject(&request_id)
+    {
+        return response;
+    }
+    let auth = AuthParams {
+        node_id: body.node_id,
+        password: body.password,
+    };
+    if let Some(response) = reject_unknown_node(&nodes, &auth, &request_id).await {
+        return response;
+    }
+
+    let ttl = body.ttl_secs.unwrap_or(DEFAULT_TOKEN_TTL_SECS);
+    let node_id = auth.node_id;
+    let token = tokens.issue(node_id.clone(), Duration::from_secs(ttl));
+    info!("Issued a bearer token for node {} ({} s)", node_id, ttl);
+    let body = json!({
+        "req_id": request_id,
+        "access_token": token,
+        "token_type": "Bearer",
+        "expires_in": ttl,
+        "node_id": node_id,
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+/// Revokes the bearer token the request is sent with.
+#[utoipa::path(
+    delete,
+    path = "/api/auth/token",
+    tag = "auth",
+    responses((status = 200, description = "The token no longer works", body = Object))
+)]
+pub async fn revoke_token(
+    Extension(tokens): Extension<TokenStore>,
+    headers: HeaderMap,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    match bearer_token(&headers) {
+        Some(token) if tokens.revoke(token) => (
+            StatusCode::OK,
+            Json(json!({ "req_id": request_id, "revoked": true })),
+        )
+            .into_response(),
+        _ => error_response(
+            StatusCode::UNAUTHORIZED,
+            &request_id,
+            "Invalid token",
+            "Send the token to revoke as Authorization: Bearer <token>".to_string(),
+        ),
+    }
+}
+
+pub(crate) fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+    scheme
+        .eq_ignore_ascii_case("bearer")
+        .then(|| token.trim())
+        .filter(|token| !token.is_empty())
+}
+
+fn has_password_param(uri: &Uri) -> bool {
+    uri.query().is_some_and(|query| {
+        serde_urlencoded::from_str::<Vec<(String, String)>>(query)
+            .is_ok_and(|pairs| pairs.iter().any(|(key, _)| key == "password"))
+    })
+}
+
+// The token's node replaces any credentials given in the query
+pub(crate) fn with_node_id(uri: &Uri, node_id: &str) -> Result<Uri, String> {
+    let mut pairs: Vec<(String, String)> = match uri.query() {
+        Some(query) => serde_urlencoded::from_str(query).map_err(|e| e.to_string())?,
+        None => Vec::new(),
+    };
+    pairs.retain(|(key, _)| key != "node_id" && key != "password");
+    pairs.push(("node_id".to_string(), node_id.to_string()));
+    let query = serde_urlencoded::to_string(&pairs).map_err(|e| e.to_string())?;
+    format!("{}?{}", uri.path(), query)
+        .parse()
+        .map_err(|e: axum::http::uri::InvalidUri| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Router, body::Body, middleware, routing::get};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn app(tokens: TokenStore, query_auth: bool) -> Router {
+        let auth = BearerAuth::new(tokens, query_auth);
+        Router::new()
+            .route(
+                "/api/containers",
+                get(|request: Request| async move {
+                    let verified = request.extensions().get::<VerifiedNode>().cloned();
+                    let query = request.uri().query().unwrap_or_default().to_string();
+                    format!(
+                        "{} {}",
+                        verified.map(|node| node.0).unwrap_or_default(),
+                        query
+                    )
+                }),
+            )
+            .layer(middleware::from_fn_with_state(auth, resolve_bearer_token))
+    }
+
+    async fn send(app: &Router, uri: &str, token: Option<&str>) -> (StatusCode, String) {
+        let mut request = Request::builder().uri(uri);
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let response = app
+            .clone()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[test]
+    fn tokens_end_when_they_expire() {
+        let tokens = TokenStore::new();
+        let token = tokens.issue("web".to_string(), Duration::ZERO);
+        assert_eq!(tokens.resolve(&token), None);
+        // Expired tokens are removed as they are found
+        assert!(!tokens.revoke(&token));
+    }
+
+    #[tokio::test]
+    async fn stands_for_its_node_until_revoked() {
+        let tokens = TokenStore::new();
+        let token = tokens.issue("web".to_string(), Duration::from_secs(60));
+        let app = app(tokens.clone(), true);
+
+        // The token's node replaces whatever the query claimed
+        let (status, body) = send(
+            &app,
+            "/api/containers?node_id=db&password=x&all=true",
+            Some(&token),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "web all=true&node_id=web");
+
+        assert!(tokens.revoke(&token));
+        let (status, _) = send(&app, "/api/containers", Some(&token)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = send(&app, "/api/containers", Some("dlt_unknown")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn refuses_query_passwords_without_query_auth() {
+        let app = app(TokenStore::new(), false);
+        let (status, _) = send(&app, "/api/containers?node_id=web&password=x", None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, body) = send(&app, "/api/containers?all=true", None).await;
+        assert_eq!((status, body.as_str()), (StatusCode::OK, " all=true"));
+    }
+}
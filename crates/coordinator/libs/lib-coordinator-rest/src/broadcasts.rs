@@ -0,0 +1,375 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use axum::{
+    Extension, Json,
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use futures_util::future::join_all;
+use lib_coordinator_core::{CommandRouter, NodeChannels, NodeStateCache, PendingResponses};
+use proto::generated::{
+    BroadcastNotice, Envelope, RequestType, ServerCommand, envelope::Payload, node_response,
+    server_command,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::AuthParams;
+use crate::admin::{AdminParams, AdminToken};
+use crate::desired_state::node_auth;
+use crate::error_response;
+use crate::node_request::request_node;
+use crate::validation::{ValidJson, ValidQuery, Validator};
+
+const BROADCAST_ACK_TIMEOUT: Duration = Duration::from_secs(10);
+// Older broadcasts are forgotten
+const MAX_KEPT_BROADCASTS: usize = 100;
+const MAX_MESSAGE_LEN: usize = 1000;
+const MAX_TARGET_NODES: usize = 1000;
+const BROADCAST_ACTIONS: [&str; 2] = ["notice", "refresh"];
+
+/// Where a broadcast stands on one node.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    /// Sent, no acknowledgement yet.
+    Pending,
+    Acknowledged,
+    /// The node answered with an error or did not answer in time.
+    Failed,
+    /// The node was not connected, so nothing was sent.
+    Offline,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Delivery {
+    pub status: DeliveryStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// When the node acknowledged, in unix seconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub acknowledged_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BroadcastRecord {
+    pub id: String,
+    pub message: String,
+    pub action: String,
+    pub sent_at: i64,
+    pub nodes: BTreeMap<String, Delivery>,
+}
+
+/// Recent admin broadcasts with their per-node delivery, kept in memory since
+/// coordinator start.
+#[derive(Clone, Default)]
+pub struct BroadcastLog {
+    records: Arc<Mutex<VecDeque<BroadcastRecord>>>,
+}
+
+impl BroadcastLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn push(&self, record: BroadcastRecord) {
+        let mut records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        if records.len() == MAX_KEPT_BROADCASTS {
+            records.pop_front();
+        }
+        records.push_back(record);
+    }
+
+    fn update(&self, id: &str, node_id: &str, delivery: Delivery) {
+        let mut records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(record) = records.iter_mut().rev().find(|record| record.id == id) {
+            record.nodes.insert(node_id.to_string(), delivery);
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<BroadcastRecord> {
+        let records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        records.iter().find(|record| record.id == id).cloned()
+    }
+
+    /// Newest first.
+    pub fn list(&self) -> Vec<BroadcastRecord> {
+        let records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        records.iter().rev().cloned().collect()
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct BroadcastBody {
+    /// Shown in the node's log. Required for `notice`.
+    #[serde(default)]
+    message: String,
+    /// `notice` (default) or `refresh`, which makes nodes push their container states.
+    action: Option<String>,
+    /// Node ids to send to; every connected node when unset.
+    nodes: Option<Vec<String>>,
+}
+
+/// Sends a notice or command to all or selected nodes and waits for their
+/// acknowledgements. Nodes that do not answer within 10 seconds are marked failed.
+#[allow(clippy::too_many_arguments)] // Axum extractors, one per piece of shared state
+#[utoipa::path(
+    post,
+    path = "/api/broadcasts",
+    tag = "broadcasts",
+    params(AdminParams),
+    request_body = BroadcastBody,
+    responses((status = 200, description = "The broadcast with its delivery per node", body = Object))
+)]
+pub async fn send_broadcast(
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(node_states): Extension<NodeStateCache>,
+    Extension(broadcasts): Extension<BroadcastLog>,
+    Extension(admin): Extension<AdminToken>,
+    ValidQuery(params): ValidQuery<AdminParams>,
+    ValidJson(body): ValidJson<BroadcastBody>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = admin.reject(&params, &request_id) {
+        return response;
+    }
+    let action = body.action.as_deref().unwrap_or("notice");
+    let mut validator = Validator::new();
+    validator
+        .one_of("action", body.action.as_deref(), &BROADCAST_ACTIONS)
+        .check(
+            body.message.len() <= MAX_MESSAGE_LEN,
+            "message",
+            format!("must be at most {} bytes", MAX_MESSAGE_LEN),
+        )
+        .check(
+            action != "notice" || !body.message.trim().is_empty(),
+            "message",
+            "must not be empty for a notice",
+        );
+    if let Some(selected) = &body.nodes {
+        validator
+            .check(!selected.is_empty(), "nodes", "must not be empty")
+            .check(
+                selected.len() <= MAX_TARGET_NODES,
+                "nodes",
+                format!("must list at most {} nodes", MAX_TARGET_NODES),
+            )
+            .check(
+                selected.iter().all(|node_id| !node_id.is_empty()),
+                "nodes",
+                "must not contain empty node ids",
+            );
+    }
+    if let Some(response) = validator.reject(&request_id) {
+        return response;
+    }
+
+    let targets: Vec<String> = match body.nodes {
+        Some(mut selected) => {
+            selected.sort();
+            selected.dedup();
+            selected
+        }
+        None => {
+            let mut online: Vec<String> = node_states
+                .snapshot()
+                .into_iter()
+                .filter(|(_, state)| state.online)
+                .map(|(node_id, _)| node_id)
+                .collect();
+            online.sort();
+            online
+        }
+    };
+
+    let mut record = BroadcastRecord {
+        id: request_id.clone(),
+        message: body.message,
+        action: action.to_string(),
+        sent_at: now_secs(),
+        nodes: BTreeMap::new(),
+    };
+    let mut sends = Vec::new();
+    for node_id in targets {
+        let delivery = match node_auth(&nodes, &node_id) {
+            Some(auth) => {
+                sends.push((node_id.clone(), auth));
+                DeliveryStatus::Pending
+            }
+            None => DeliveryStatus::Offline,
+        };
+        record.nodes.insert(
+            node_id,
+            Delivery {
+                status: delivery,
+                error: None,
+                acknowledged_at: None,
+            },
+        );
+    }
+    info!(
+        "Broadcasting {} {} to {} nodes",
+        record.action,
+        request_id,
+        sends.len()
+    );
+    broadcasts.push(record.clone());
+
+    let deliveries = join_all(sends.into_iter().map(|(node_id, auth)| {
+        let notice = BroadcastNotice {
+            request_id: Uuid::new_v4().to_string(),
+            broadcast_id: request_id.clone(),
+            message: record.message.clone(),
+            action: record.action.clone(),
+        };
+        let server_tx = &server_tx;
+        let pending = &pending;
+        let broadcasts = &broadcasts;
+        let broadcast_id = &request_id;
+        async move {
+            let delivery = deliver(server_tx, pending, &auth, notice).await;
+            broadcasts.update(broadcast_id, &node_id, delivery.clone());
+            (node_id, delivery)
+        }
+    }))
+    .await;
+    record.nodes.extend(deliveries);
+
+    let acknowledged = record
+        .nodes
+        .values()
+        .filter(|delivery| delivery.status == DeliveryStatus::Acknowledged)
+        .count();
+    let body = json!({
+        "req_id": request_id,
+        "acknowledged": acknowledged,
+        "broadcast": record,
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+/// Recent broadcasts, newest first.
+#[utoipa::path(
+    get,
+    path = "/api/broadcasts",
+    tag = "broadcasts",
+    params(AdminParams),
+    responses((status = 200, description = "Recent broadcasts", body = Object))
+)]
+pub async fn list_broadcasts(
+    Extension(broadcasts): Extension<BroadcastLog>,
+    Extension(admin): Extension<AdminToken>,
+    ValidQuery(params): ValidQuery<AdminParams>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = admin.reject(&params, &request_id) {
+        return response;
+    }
+
+    let body = json!({
+        "req_id": request_id,
+        "broadcasts": broadcasts.list(),
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+/// One broadcast with the delivery state on each node.
+#[utoipa::path(
+    get,
+    path = "/api/broadcasts/{broadcast_id}",
+    tag = "broadcasts",
+    params(("broadcast_id" = String, Path, description = "Broadcast id"), AdminParams),
+    responses((status = 200, description = "The broadcast", body = Object))
+)]
+pub async fn get_broadcast(
+    Path(broadcast_id): Path<String>,
+    Extension(broadcasts): Extension<BroadcastLog>,
+    Extension(admin): Extension<AdminToken>,
+    ValidQuery(params): ValidQuery<AdminParams>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = admin.reject(&params, &request_id) {
+        return response;
+    }
+
+    match broadcasts.get(&broadcast_id) {
+        Some(record) => {
+            let body = json!({
+                "req_id": request_id,
+                "broadcast": record,
+            });
+            (StatusCode::OK, Json(body)).into_response()
+        }
+        None => error_response(
+            StatusCode::NOT_FOUND,
+            &request_id,
+            "Broadcast not found",
+            format!("No recent broadcast with id {}", broadcast_id),
+        ),
+    }
+}
+
+async fn deliver(
+    server_tx: &CommandRouter,
+    pending: &PendingResponses,
+    auth: &AuthParams,
+    notice: BroadcastNotice,
+) -> Delivery {
+    let request_id = notice.request_id.clone();
+    let envelope = Envelope {
+        payload: Some(Payload::ServerCommand(ServerCommand {
+            kind: Some(server_command::Kind::BroadcastNotice(notice)),
+        })),
+        ..Default::default()
+    };
+    let result = request_node(
+        server_tx,
+        pending,
+        auth,
+        &request_id,
+        RequestType::Broadcast as i32,
+        envelope,
+        BROADCAST_ACK_TIMEOUT,
+    )
+    .await;
+
+    let error = match result {
+        Ok(response) => match response.payload {
+            Some(Payload::NodeResponse(node_resp)) => match node_resp.kind {
+                Some(node_response::Kind::BroadcastAck(ack)) if ack.error.is_empty() => None,
+                Some(node_response::Kind::BroadcastAck(ack)) => Some(ack.error),
+                _ => Some("unexpected response".to_string()),
+            },
+            _ => Some("unexpected response".to_string()),
+        },
+        Err(e) => Some(e.to_string()),
+    };
+    match error {
+        None => Delivery {
+            status: DeliveryStatus::Acknowledged,
+            error: None,
+            acknowledged_at: Some(now_secs()),
+        },
+        Some(error) => Delivery {
+            status: DeliveryStatus::Failed,
+            error: Some(error),
+            acknowledged_at: None,
+        },
+    }
+}
+
+fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
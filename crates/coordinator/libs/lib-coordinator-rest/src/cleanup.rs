@@ -0,0 +1,272 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use axum::{
+    Extension, Json,
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use lib_coordinator_core::{
+    CleanupPolicy, CleanupStore, CommandRouter, NodeChannels, PendingResponses,
+};
+use proto::generated::{
+    CleanupStatus, Envelope, GetCleanup, NodeCommand, RequestType, envelope::Payload, node_command,
+};
+use serde_json::json;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::node_request::request_node;
+use crate::validation::{ValidJson, Validator};
+use crate::{AuthParams, NodePasswordParams, error_response, reject_unknown_node};
+
+const CLEANUP_TIMEOUT: Duration = Duration::from_secs(15);
+const MAX_CLEANUP_RULES: usize = 20;
+const MAX_RULE_CONTAINERS: usize = 100;
+const MIN_INTERVAL_SECS: u32 = 60;
+const MAX_INTERVAL_SECS: u32 = 86_400;
+
+/// The node's stored cleanup policy and its recent sweeps.
+#[utoipa::path(
+    get,
+    path = "/api/nodes/{node_id}/cleanup",
+    tag = "nodes",
+    params(("node_id" = String, Path, description = "Node id"), NodePasswordParams),
+    responses((status = 200, description = "Stored policy and what the node removed", body = Object))
+)]
+pub async fn get_cleanup(
+    Path(node_id): Path<String>,
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(cleanup): Extension<CleanupStore>,
+    query: NodePasswordParams,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    let auth = query.for_node(node_id);
+    if let Some(response) = reject_unknown_node(&nodes, &auth, &request_id).await {
+        return response;
+    }
+
+    let kind = node_command::Kind::GetCleanup(GetCleanup {
+        request_id: request_id.clone(),
+    });
+    match send(
+        &server_tx,
+        &pending,
+        &auth,
+        &request_id,
+        RequestType::GetCleanup,
+        kind,
+    )
+    .await
+    {
+        Ok(status) => {
+            let body = json!({
+                "id": request_id,
+                "node_id": auth.node_id,
+                "policy": cleanup.get(&auth.node_id),
+                "status": status,
+            });
+            (StatusCode::OK, Json(body)).into_response()
+        }
+        Err(response) => response,
+    }
+}
+
+/// Replaces the node's cleanup policy; the node sweeps right away. The policy
+/// is stored only once the node accepted it.
+#[utoipa::path(
+    put,
+    path = "/api/nodes/{node_id}/cleanup",
+    tag = "nodes",
+    params(("node_id" = String, Path, description = "Node id"), NodePasswordParams),
+    request_body = CleanupPolicy,
+    responses((status = 200, description = "The policy the node accepted", body = Object))
+)]
+pub async fn put_cleanup(
+    Path(node_id): Path<String>,
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(cleanup): Extension<CleanupStore>,
+    query: NodePasswordParams,
+    ValidJson(policy): ValidJson<CleanupPolicy>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = validate(&policy).reject(&request_id) {
+        return response;
+    }
+    let auth = query.for_node(node_id);
+    if let Some(response) = reject_unknown_node(&nodes, &auth, &request_id).await {
+        return response;
+    }
+
+    configure(
+        &server_tx,
+        &pending,
+        &cleanup,
+        &auth,
+        &request_id,
+        Some(policy),
+    )
+    .await
+}
+
+/// Stops the node's sweeps.
+#[utoipa::path(
+    delete,
+    path = "/api/nodes/{node_id}/cleanup",
+    tag = "nodes",
+    params(("node_id" = String, Path, description = "Node id"), NodePasswordParams),
+    responses((status = 200, description = "Cleanup stopped", body = Object))
+)]
+pub async fn delete_cleanup(
+    Path(node_id): Path<String>,
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(cleanup): Extension<CleanupStore>,
+    query: NodePasswordParams,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    let auth = query.for_node(node_id);
+    if let Some(response) = reject_unknown_node(&nodes, &auth, &request_id).await {
+        return response;
+    }
+
+    configure(&server_tx, &pending, &cleanup, &auth, &request_id, None).await
+}
+
+fn validate(policy: &CleanupPolicy) -> Validator {
+    let mut validator = Validator::new();
+    validator
+        .check(
+            !policy.rules.is_empty() && policy.rules.len() <= MAX_CLEANUP_RULES,
+            "rules",
+            format!(
+                "between 1 and {} rules; DELETE stops the cleanup",
+                MAX_CLEANUP_RULES
+            ),
+        )
+        .check(
+            (MIN_INTERVAL_SECS..=MAX_INTERVAL_SECS).contains(&policy.interval_secs),
+            "interval_secs",
+            format!("between {} and {}", MIN_INTERVAL_SECS, MAX_INTERVAL_SECS),
+        );
+    let mut names = HashSet::new();
+    for (i, rule) in policy.rules.iter().enumerate() {
+        let field = format!("rules[{}]", i);
+        validator
+            .secret_name(&format!("{}.name", field), &rule.name)
+            .check(
+                names.insert(rule.name.as_str()),
+                &format!("{}.name", field),
+                format!("{} is used twice", rule.name),
+            )
+            .check(
+                rule.containers.len() <= MAX_RULE_CONTAINERS,
+                &format!("{}.containers", field),
+                format!("at most {} container names", MAX_RULE_CONTAINERS),
+            )
+            // Neither limit would remove every exited container at once
+            .check(
+                rule.older_than_secs > 0 || rule.keep_last > 0,
+                &field,
+                "needs older_than_secs or keep_last",
+            );
+        for (j, pattern) in rule.containers.iter().enumerate() {
+            let field = format!("{}.containers[{}]", field, j);
+            match pattern.strip_suffix('*') {
+                Some("") => {}
+                Some(prefix) => {
+                    validator.container_id(&field, prefix);
+                }
+                None => {
+                    validator.container_id(&field, pattern);
+                }
+            }
+        }
+    }
+    validator
+}
+
+async fn configure(
+    server_tx: &CommandRouter,
+    pending: &PendingResponses,
+    cleanup: &CleanupStore,
+    auth: &AuthParams,
+    request_id: &str,
+    policy: Option<CleanupPolicy>,
+) -> Response {
+    let kind =
+        node_command::Kind::ConfigureCleanup(CleanupStore::command(request_id, policy.as_ref()));
+    let status = match send(
+        server_tx,
+        pending,
+        auth,
+        request_id,
+        RequestType::ConfigureCleanup,
+        kind,
+    )
+    .await
+    {
+        Ok(status) => status,
+        Err(response) => return response,
+    };
+
+    if let Err(e) = cleanup.set(&auth.node_id, policy.clone()) {
+        error!("Failed to persist cleanup policy: {}", e);
+        return error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            request_id,
+            "Failed to store cleanup policy",
+            e.to_string(),
+        );
+    }
+    let body = json!({
+        "id": request_id,
+        "node_id": auth.node_id,
+        "policy": policy,
+        "status": status,
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+async fn send(
+    server_tx: &CommandRouter,
+    pending: &PendingResponses,
+    auth: &AuthParams,
+    request_id: &str,
+    request_type: RequestType,
+    kind: node_command::Kind,
+) -> Result<Option<CleanupStatus>, Response> {
+    let envelope = Envelope {
+        payload: Some(Payload::NodeCommand(NodeCommand { kind: Some(kind) })),
+        ..Default::default()
+    };
+    match request_node(
+        server_tx,
+        pending,
+        auth,
+        request_id,
+        request_type as i32,
+        envelope,
+        CLEANUP_TIMEOUT,
+    )
+    .await
+    {
+        Ok(response) => Ok(extract_status_from_response(response)),
+        Err(e) => Err(e.into_response(request_id)),
+    }
+}
+
+fn extract_status_from_response(response: Envelope) -> Option<CleanupStatus> {
+    if let Some(Payload::NodeResponse(node_resp)) = response.payload
+        && let Some(proto::generated::node_response::Kind::CleanupStatus(status)) = node_resp.kind
+    {
+        return Some(status);
+    }
+    None
+}
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use axum::{
+    Extension, Json,
+    response::{IntoResponse, Response},
+};
+use lib_coordinator_core::{ContainerCounts, NodeState, NodeStateCache};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::admin::{AdminParams, AdminToken};
+use crate::pending_limit::PendingLimiter;
+use crate::validation::ValidQuery;
+use crate::ws_limit::WsLimiter;
+
+const RECENT_EVENTS_SHORT: Duration = Duration::from_secs(5 * 60);
+const RECENT_EVENTS_LONG: Duration = Duration::from_secs(60 * 60);
+
+/// Cluster-wide overview built from the coordinator's node cache, no node round-trips.
+#[utoipa::path(
+    get,
+    path = "/api/cluster/summary",
+    tag = "cluster",
+    params(AdminParams),
+    responses((status = 200, description = "Totals and per-node state", body = Object))
+)]
+pub async fn get_cluster_summary(
+    Extension(node_states): Extension<NodeStateCache>,
+    Extension(pending_limiter): Extension<PendingLimiter>,
+    Extension(ws_limiter): Extension<WsLimiter>,
+    Extension(admin): Extension<AdminToken>,
+    ValidQuery(params): ValidQuery<AdminParams>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = admin.reject(&params, &request_id) {
+        return response;
+    }
+
+    let nodes = node_states.snapshot();
+    let online = nodes.iter().filter(|(_, state)| state.online).count();
+
+    let mut by_state: HashMap<String, usize> = HashMap::new();
+    let mut totals = ContainerCounts::default();
+    let mut events_short = 0;
+    let mut events_long = 0;
+
+    let per_node: Vec<_> = nodes
+        .iter()
+        .map(|(node_id, state)| {
+            let counts = state.state_counts();
+            for (status, count) in &counts {
+                *by_state.entry(status.clone()).or_insert(0) += count;
+            }
+            totals += state.container_counts();
+
+            let node_events_short = state.events_within(RECENT_EVENTS_SHORT);
+            let node_events_long = state.events_within(RECENT_EVENTS_LONG);
+            events_short += node_events_short;
+            events_long += node_events_long;
+
+            node_summary_json(node_id, state, counts, node_events_short, node_events_long)
+        })
+        .collect();
+
+    let body = json!({
+        "id": request_id,
+        "nodes": {
+            "total": nodes.len(),
+            "online": online,
+            "offline": nodes.len() - online,
+        },
+        "containers": container_counts_json(totals, by_state),
+        "events": {
+            "last_5m": events_short,
+            "last_1h": events_long,
+        },
+        "requests": pending_limiter.gauge(),
+        "websockets": ws_limiter.gauge(),
+        "per_node": per_node,
+    });
+    (axum::http::StatusCode::OK, Json(body)).into_response()
+}
+
+fn node_summary_json(
+    node_id: &str,
+    state: &NodeState,
+    counts: HashMap<String, usize>,
+    events_short: usize,
+    events_long: usize,
+) -> serde_json::Value {
+    json!({
+        "node_id": node_id,
+        "online": state.online,
+        "connected_at": unix_secs(state.connected_at),
+        "last_seen": unix_secs(state.last_seen),
+        "disconnected_at": state.disconnected_at.map(unix_secs),
+        "containers": container_counts_json(state.container_counts(), counts),
+        "events": {
+            "last_5m": events_short,
+            "last_1h": events_long,
+        },
+    })
+}
+
+pub(crate) fn container_counts_json(
+    counts: ContainerCounts,
+    by_state: HashMap<String, usize>,
+) -> serde_json::Value {
+    json!({
+        "total": counts.total,
+        "running": counts.running,
+        "exited": counts.exited,
+        "paused": counts.paused,
+        "restarting": counts.restarting,
+        "other": counts.other,
+        "by_state": by_state,
+    })
+}
+
+fn unix_secs(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
@@ -0,0 +1,125 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use dashmap::DashMap;
+use dashmap::mapref::entry::Entry;
+use futures_util::FutureExt;
+use futures_util::future::{BoxFuture, WeakShared};
+use lib_coordinator_core::{CommandRouter, PendingResponses};
+use proto::generated::Envelope;
+use uuid::Uuid;
+
+use crate::AuthParams;
+use crate::node_request::{NodeRequestError, request_node};
+
+type ResponseFuture = BoxFuture<'static, Result<Envelope, NodeRequestError>>;
+
+/// Node id, password and a description of the query with all its parameters.
+type CoalesceKey = (String, String, String);
+
+/// The request id of the node request each query is waiting on.
+type InFlight = Arc<DashMap<CoalesceKey, (String, WeakShared<ResponseFuture>)>>;
+
+/// Joins concurrent identical read-only queries to a node into a single node
+/// request, built on `request_node` and `PendingResponses`. Every waiter gets the
+/// same response. The node request is cancelled only once all waiters are gone.
+#[derive(Clone)]
+pub struct RequestCoalescer {
+    server_tx: CommandRouter,
+    pending: PendingResponses,
+    in_flight: InFlight,
+}
+
+impl RequestCoalescer {
+    pub fn new(server_tx: CommandRouter, pending: PendingResponses) -> Self {
+        Self {
+            server_tx,
+            pending,
+            in_flight: InFlight::default(),
+        }
+    }
+
+    /// Waits for the response to `query`, sending the node command made by
+    /// `envelope` only if no identical query is already in flight. `query` must
+    /// identify the command and every parameter that changes the response.
+    pub async fn request(
+        &self,
+        auth: &AuthParams,
+        query: String,
+        request_type: i32,
+        envelope: impl FnOnce(&str) -> Envelope,
+        timeout: Duration,
+    ) -> Result<Envelope, NodeRequestError> {
+        let key = (auth.node_id.clone(), auth.password.clone(), query);
+
+        let start = |key: CoalesceKey| {
+            let request_id = Uuid::new_v4().to_string();
+            let envelope = envelope(&request_id);
+            let server_tx = self.server_tx.clone();
+            let pending = self.pending.clone();
+            let auth = auth.clone();
+            let deregister = Deregister {
+                in_flight: self.in_flight.clone(),
+                key,
+                request_id: request_id.clone(),
+            };
+            let entry_id = request_id.clone();
+
+            let response: ResponseFuture = async move {
+                // Held until the request completes or every waiter is gone
+                let _deregister = deregister;
+                request_node(
+                    &server_tx,
+                    &pending,
+                    &auth,
+                    &request_id,
+                    request_type,
+                    envelope,
+                    timeout,
+                )
+                .await
+            }
+            .boxed();
+            (entry_id, response.shared())
+        };
+
+        let shared = match self.in_flight.entry(key.clone()) {
+            Entry::Occupied(mut entry) => match entry.get().1.upgrade() {
+                Some(shared) => shared,
+                // Every waiter of the previous request went away
+                None => {
+                    let (request_id, shared) = start(key);
+                    if let Some(weak) = shared.downgrade() {
+                        entry.insert((request_id, weak));
+                    }
+                    shared
+                }
+            },
+            Entry::Vacant(entry) => {
+                let (request_id, shared) = start(key);
+                if let Some(weak) = shared.downgrade() {
+                    entry.insert((request_id, weak));
+                }
+                shared
+            }
+        };
+
+        shared.await
+    }
+}
+
+/// Removes the in-flight entry once its request is done, unless a newer
+/// request for the same query already took its place.
+struct Deregister {
+    in_flight: InFlight,
+    key: CoalesceKey,
+    request_id: String,
+}
+
+impl Drop for Deregister {
+    fn drop(&mut self) {
+        self.in_flight.remove_if(&self.key, |_, (request_id, _)| {
+            *request_id == self.request_id
+        });
+    }
+}
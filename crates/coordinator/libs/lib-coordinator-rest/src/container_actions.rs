@@ -1,321 +1,307 @@
 use axum::{
     Extension, Json,
-    extract::{Path, Query},
-    response::IntoResponse,
+    extract::Path,
+    response::{IntoResponse, Response},
 };
-use lib_coordinator_core::{PendingResponses, ServerRequestByUser};
+use lib_coordinator_core::{CommandRouter, PendingResponses};
 use proto::generated::{
-    DeleteContainer, Envelope, NodeCommand, RequestType, StartContainer, StopContainer,
-    envelope::Payload, node_command,
+    DeleteContainer, Envelope, NodeCommand, PauseContainer, RequestType, RestartContainer,
+    StartContainer, StopContainer, UnpauseContainer, envelope::Payload, node_command,
 };
+use serde::Deserialize;
 use serde_json::json;
-use tokio::sync::{broadcast, oneshot};
-use tracing::error;
+use utoipa::IntoParams;
 use uuid::Uuid;
 
-use crate::{ApiError, ApiErrorDetail, AuthParams};
+use crate::AuthParams;
+use crate::node_request::request_node;
+use crate::validation::{ValidQuery, Validator};
 
 const CONTAINER_ACTION_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+// Docker's stop grace period when the caller sets none
+const DEFAULT_RESTART_GRACE_SECS: u32 = 10;
+const MAX_RESTART_GRACE_SECS: u32 = 300;
+// Added to the grace period: the node's own budget for the restart plus the round trip
+const RESTART_OVERHEAD: std::time::Duration = std::time::Duration::from_secs(40);
 
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RestartQuery {
+    /// Seconds the container gets to stop before it is killed (0 to 300).
+    timeout: Option<u32>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/containers/{container_id}/start",
+    tag = "containers",
+    params(("container_id" = String, Path, description = "Container id or name"), AuthParams),
+    responses((status = 200, description = "The container started", body = Object))
+)]
 pub async fn start_container(
     Path(container_id): Path<String>,
-    Extension(server_tx): Extension<broadcast::Sender<ServerRequestByUser>>,
+    Extension(server_tx): Extension<CommandRouter>,
     Extension(pending): Extension<PendingResponses>,
-    Query(query): Query<AuthParams>,
-) -> impl IntoResponse {
-    let request_id = Uuid::new_v4().to_string();
-    let (response_tx, response_rx) = oneshot::channel();
-
-    // Register a pending response for this request
-    pending.insert(
-        (request_id.clone(), RequestType::StartContainer as i32),
-        response_tx,
-    );
-
-    // Build the command envelope to start the container
-    let envelope = Envelope {
-        payload: Some(Payload::NodeCommand(NodeCommand {
-            kind: Some(node_command::Kind::StartContainer(StartContainer {
-                request_id: request_id.clone(),
-                container_id: container_id.clone(),
-            })),
-        })),
-    };
-
-    // Send the request to the node via broadcast
-    let send_result = server_tx
-        .send(ServerRequestByUser {
-            id: query.node_id.clone(),
-            password: query.password.clone(),
-            envelope,
-        })
-        .map(|_| ());
-
-    if let Err(e) = send_result {
-        error!("Failed to send server request: {}", e);
-        pending.remove(&(request_id.clone(), RequestType::StartContainer as i32));
-        return (
-            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to send request to server",
-        )
-            .into_response();
-    }
-
-    // Wait for the response from the node with a timeout
-    match tokio::time::timeout(CONTAINER_ACTION_TIMEOUT, response_rx).await {
-        Ok(Ok(response)) => {
-            if let Some(err_msg) = extract_node_error_from_response(&response) {
-                pending.remove(&(request_id.clone(), RequestType::StartContainer as i32));
-                let err = ApiError {
-                    req_id: request_id.clone(),
-                    error: ApiErrorDetail {
-                        message: "Node error".to_string(),
-                        detail: err_msg,
-                    },
-                };
-                return (axum::http::StatusCode::BAD_REQUEST, Json(err)).into_response();
-            }
-
-            let action_result = extract_container_action_from_response(&response);
-            let body = json!({
-                "id": request_id,
-                "container_id": container_id,
-                "action": "start", // или stop/delete в соответствующих функциях
-                "result": action_result,
-            });
-            (axum::http::StatusCode::OK, Json(body)).into_response()
-        }
-        Ok(Err(_)) => {
-            pending.remove(&(request_id.clone(), RequestType::StartContainer as i32));
-            let err = ApiError {
-                req_id: request_id.clone(),
-                error: ApiErrorDetail {
-                    message: "Response channel closed".to_string(),
-                    detail: "Node dropped oneshot channel".to_string(),
-                },
-            };
-            (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(err)).into_response()
-        }
-        Err(_) => {
-            pending.remove(&(request_id.clone(), RequestType::StartContainer as i32));
-            let err = ApiError {
-                req_id: request_id.clone(),
-                error: ApiErrorDetail {
-                    message: "Timeout waiting for node response".to_string(),
-                    detail: "Timeout waiting for node response".to_string(),
-                },
-            };
-            (axum::http::StatusCode::REQUEST_TIMEOUT, Json(err)).into_response()
-        }
-    }
+    query: AuthParams,
+) -> Response {
+    run_container_action(
+        &server_tx,
+        &pending,
+        &query,
+        container_id,
+        RequestType::StartContainer,
+        "start",
+        |request_id, container_id| {
+            node_command::Kind::StartContainer(StartContainer {
+                request_id,
+                container_id,
+            })
+        },
+    )
+    .await
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/containers/{container_id}/stop",
+    tag = "containers",
+    params(("container_id" = String, Path, description = "Container id or name"), AuthParams),
+    responses((status = 200, description = "The container stopped", body = Object), (status = 202, description = "Held for approval", body = Object))
+)]
 pub async fn stop_container(
     Path(container_id): Path<String>,
-    Extension(server_tx): Extension<broadcast::Sender<ServerRequestByUser>>,
+    Extension(server_tx): Extension<CommandRouter>,
     Extension(pending): Extension<PendingResponses>,
-    Query(query): Query<AuthParams>,
-) -> impl IntoResponse {
-    let request_id = Uuid::new_v4().to_string();
-    let (response_tx, response_rx) = oneshot::channel();
+    query: AuthParams,
+) -> Response {
+    run_container_action(
+        &server_tx,
+        &pending,
+        &query,
+        container_id,
+        RequestType::StopContainer,
+        "stop",
+        |request_id, container_id| {
+            node_command::Kind::StopContainer(StopContainer {
+                request_id,
+                container_id,
+            })
+        },
+    )
+    .await
+}
 
-    // Register a pending response for this request
-    pending.insert(
-        (request_id.clone(), RequestType::StopContainer as i32),
-        response_tx,
-    );
+/// Stops and starts a container in a single Docker call, so a restart policy
+/// cannot start it again in between.
+#[utoipa::path(
+    post,
+    path = "/api/containers/{container_id}/restart",
+    tag = "containers",
+    params(("container_id" = String, Path, description = "Container id or name"), AuthParams, RestartQuery),
+    responses((status = 200, description = "The container restarted", body = Object), (status = 202, description = "Held for approval", body = Object))
+)]
+pub async fn restart_container(
+    Path(container_id): Path<String>,
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    query: AuthParams,
+    ValidQuery(restart): ValidQuery<RestartQuery>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = Validator::new()
+        .container_id("container_id", &container_id)
+        .range("timeout", restart.timeout, 0, MAX_RESTART_GRACE_SECS)
+        .reject(&request_id)
+    {
+        return response;
+    }
 
-    // Build the command envelope to stop the container
     let envelope = Envelope {
         payload: Some(Payload::NodeCommand(NodeCommand {
-            kind: Some(node_command::Kind::StopContainer(StopContainer {
+            kind: Some(node_command::Kind::RestartContainer(RestartContainer {
                 request_id: request_id.clone(),
                 container_id: container_id.clone(),
+                timeout_secs: restart.timeout,
             })),
         })),
+        ..Default::default()
     };
+    let grace = restart.timeout.unwrap_or(DEFAULT_RESTART_GRACE_SECS);
+    let result = request_node(
+        &server_tx,
+        &pending,
+        &query,
+        &request_id,
+        RequestType::RestartContainer as i32,
+        envelope,
+        RESTART_OVERHEAD + std::time::Duration::from_secs(grace.into()),
+    )
+    .await;
 
-    // Send the request to the node via broadcast
-    let send_result = server_tx
-        .send(ServerRequestByUser {
-            id: query.node_id.clone(),
-            password: query.password.clone(),
-            envelope,
-        })
-        .map(|_| ());
-
-    if let Err(e) = send_result {
-        error!("Failed to send server request: {}", e);
-        pending.remove(&(request_id.clone(), RequestType::StopContainer as i32));
-        return (
-            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to send request to server",
-        )
-            .into_response();
-    }
-
-    // Wait for the response from the node with a timeout
-    match tokio::time::timeout(CONTAINER_ACTION_TIMEOUT, response_rx).await {
-        Ok(Ok(response)) => {
-            if let Some(err_msg) = extract_node_error_from_response(&response) {
-                pending.remove(&(request_id.clone(), RequestType::StopContainer as i32));
-                let err = ApiError {
-                    req_id: request_id.clone(),
-                    error: ApiErrorDetail {
-                        message: "Node error".to_string(),
-                        detail: err_msg,
-                    },
-                };
-
-                return (axum::http::StatusCode::BAD_REQUEST, Json(err)).into_response();
-            }
-            let action_result = extract_container_action_from_response(&response);
+    match result {
+        Ok(response) => {
             let body = json!({
                 "id": request_id,
                 "container_id": container_id,
-                "action": "stop",
-                "result": action_result,
+                "action": "restart",
+                "result": extract_container_action_from_response(&response),
             });
             (axum::http::StatusCode::OK, Json(body)).into_response()
         }
-        Ok(Err(_)) => {
-            pending.remove(&(request_id.clone(), RequestType::StopContainer as i32));
-            let err = ApiError {
-                req_id: request_id.clone(),
-                error: ApiErrorDetail {
-                    message: "Response channel closed".to_string(),
-                    detail: "Node dropped oneshot channel".to_string(),
-                },
-            };
-            (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(err)).into_response()
-        }
-        Err(_) => {
-            pending.remove(&(request_id.clone(), RequestType::StopContainer as i32));
-            let err = ApiError {
-                req_id: request_id.clone(),
-                error: ApiErrorDetail {
-                    message: "Timeout waiting for node response".to_string(),
-                    detail: "Timeout waiting for node response".to_string(),
-                },
-            };
-            (axum::http::StatusCode::REQUEST_TIMEOUT, Json(err)).into_response()
-        }
+        Err(e) => e.into_response(&request_id),
     }
 }
 
-pub async fn delete_container(
+/// Freezes the container's processes, keeping their memory and state.
+#[utoipa::path(
+    post,
+    path = "/api/containers/{container_id}/pause",
+    tag = "containers",
+    params(("container_id" = String, Path, description = "Container id or name"), AuthParams),
+    responses((status = 200, description = "The container is paused", body = Object))
+)]
+pub async fn pause_container(
     Path(container_id): Path<String>,
-    Extension(server_tx): Extension<broadcast::Sender<ServerRequestByUser>>,
+    Extension(server_tx): Extension<CommandRouter>,
     Extension(pending): Extension<PendingResponses>,
-    Query(query): Query<AuthParams>,
-) -> impl IntoResponse {
-    let request_id = Uuid::new_v4().to_string();
-    let (response_tx, response_rx) = oneshot::channel();
+    query: AuthParams,
+) -> Response {
+    run_container_action(
+        &server_tx,
+        &pending,
+        &query,
+        container_id,
+        RequestType::PauseContainer,
+        "pause",
+        |request_id, container_id| {
+            node_command::Kind::PauseContainer(PauseContainer {
+                request_id,
+                container_id,
+            })
+        },
+    )
+    .await
+}
 
-    // Register a pending response for this request
-    pending.insert(
-        (request_id.clone(), RequestType::DeleteContainer as i32),
-        response_tx,
-    );
+/// Resumes a container frozen by [`pause_container`].
+#[utoipa::path(
+    post,
+    path = "/api/containers/{container_id}/unpause",
+    tag = "containers",
+    params(("container_id" = String, Path, description = "Container id or name"), AuthParams),
+    responses((status = 200, description = "The container runs again", body = Object))
+)]
+pub async fn unpause_container(
+    Path(container_id): Path<String>,
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    query: AuthParams,
+) -> Response {
+    run_container_action(
+        &server_tx,
+        &pending,
+        &query,
+        container_id,
+        RequestType::UnpauseContainer,
+        "unpause",
+        |request_id, container_id| {
+            node_command::Kind::UnpauseContainer(UnpauseContainer {
+                request_id,
+                container_id,
+            })
+        },
+    )
+    .await
+}
+
+// Sends the command built by `command` and answers with the node's ContainerAction
+async fn run_container_action(
+    server_tx: &CommandRouter,
+    pending: &PendingResponses,
+    query: &AuthParams,
+    container_id: String,
+    request_type: RequestType,
+    action: &str,
+    command: impl FnOnce(String, String) -> node_command::Kind,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = Validator::new()
+        .container_id("container_id", &container_id)
+        .reject(&request_id)
+    {
+        return response;
+    }
 
-    // Build the command envelope to delete the container
     let envelope = Envelope {
         payload: Some(Payload::NodeCommand(NodeCommand {
-            kind: Some(node_command::Kind::DeleteContainer(DeleteContainer {
-                request_id: request_id.clone(),
-                container_id: container_id.clone(),
-            })),
+            kind: Some(command(request_id.clone(), container_id.clone())),
         })),
+        ..Default::default()
     };
-
-    // Send the request to the node via broadcast
-    let send_result = server_tx
-        .send(ServerRequestByUser {
-            id: query.node_id.clone(),
-            password: query.password.clone(),
-            envelope,
-        })
-        .map(|_| ());
-
-    if let Err(e) = send_result {
-        error!("Failed to send server request: {}", e);
-        pending.remove(&(request_id.clone(), RequestType::DeleteContainer as i32));
-        return (
-            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to send request to server",
-        )
-            .into_response();
-    }
-
-    // Wait for the response from the node with a timeout
-    match tokio::time::timeout(CONTAINER_ACTION_TIMEOUT, response_rx).await {
-        Ok(Ok(response)) => {
-            if let Some(err_msg) = extract_node_error_from_response(&response) {
-                pending.remove(&(request_id.clone(), RequestType::DeleteContainer as i32));
-                let err = ApiError {
-                    req_id: request_id.clone(),
-                    error: ApiErrorDetail {
-                        message: "Node error".to_string(),
-                        detail: err_msg,
-                    },
-                };
-                return (axum::http::StatusCode::BAD_REQUEST, Json(err)).into_response();
-            }
-
-            let action_result = extract_container_action_from_response(&response);
+    match request_node(
+        server_tx,
+        pending,
+        query,
+        &request_id,
+        request_type as i32,
+        envelope,
+        CONTAINER_ACTION_TIMEOUT,
+    )
+    .await
+    {
+        Ok(response) => {
             let body = json!({
                 "id": request_id,
                 "container_id": container_id,
-                "action": "delete",
-                "result": action_result,
+                "action": action,
+                "result": extract_container_action_from_response(&response),
             });
             (axum::http::StatusCode::OK, Json(body)).into_response()
         }
-        Ok(Err(_)) => {
-            pending.remove(&(request_id.clone(), RequestType::DeleteContainer as i32));
-            let err = ApiError {
-                req_id: request_id.clone(),
-                error: ApiErrorDetail {
-                    message: "Response channel closed".to_string(),
-                    detail: "Node dropped oneshot channel".to_string(),
-                },
-            };
-            (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(err)).into_response()
-        }
-        Err(_) => {
-            pending.remove(&(request_id.clone(), RequestType::DeleteContainer as i32));
-            let err = ApiError {
-                req_id: request_id.clone(),
-                error: ApiErrorDetail {
-                    message: "Timeout waiting for node response".to_string(),
-                    detail: "Timeout waiting for node response".to_string(),
-                },
-            };
-            (axum::http::StatusCode::REQUEST_TIMEOUT, Json(err)).into_response()
-        }
+        Err(e) => e.into_response(&request_id),
     }
 }
 
-fn extract_container_action_from_response(response: &Envelope) -> Option<serde_json::Value> {
-    if let Some(proto::generated::envelope::Payload::NodeResponse(node_resp)) = &response.payload {
-        if let Some(proto::generated::node_response::Kind::ContainerAction(action)) =
-            &node_resp.kind
-        {
-            return Some(json!({
-                "container_id": action.container_id,
-                "action": action.action,
-                "message": action.message,
-            }));
-        }
-    }
-    None
+#[utoipa::path(
+    delete,
+    path = "/api/containers/{container_id}",
+    tag = "containers",
+    params(("container_id" = String, Path, description = "Container id or name"), AuthParams),
+    responses((status = 200, description = "The container is gone, or in the node's trash", body = Object), (status = 202, description = "Held for approval", body = Object))
+)]
+pub async fn delete_container(
+    Path(container_id): Path<String>,
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    query: AuthParams,
+) -> Response {
+    run_container_action(
+        &server_tx,
+        &pending,
+        &query,
+        container_id,
+        RequestType::DeleteContainer,
+        "delete",
+        |request_id, container_id| {
+            node_command::Kind::DeleteContainer(DeleteContainer {
+                request_id,
+                container_id,
+            })
+        },
+    )
+    .await
 }
 
-fn extract_node_error_from_response(response: &Envelope) -> Option<String> {
-    if let Some(proto::generated::envelope::Payload::NodeResponse(node_resp)) = &response.payload {
-        if let Some(proto::generated::node_response::Kind::Error(err)) = &node_resp.kind {
-            return Some(err.message.clone());
-        }
+fn extract_container_action_from_response(response: &Envelope) -> Option<serde_json::Value> {
+    if let Some(proto::generated::envelope::Payload::NodeResponse(node_resp)) = &response.payload
+        && let Some(proto::generated::node_response::Kind::ContainerAction(action)) =
+            &node_resp.kind
+    {
+        return Some(json!({
+            "container_id": action.container_id,
+            "action": action.action,
+            "message": action.message,
+        }));
     }
     None
 }
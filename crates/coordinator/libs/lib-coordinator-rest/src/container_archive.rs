@@ -0,0 +1,300 @@
+use std::time::Duration;
+
+use axum::{
+    Extension, Json,
+    body::{Body, Bytes},
+    extract::Path,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use futures_util::{StreamExt, stream};
+use lib_coordinator_core::{CommandRouter, PendingResponses, PendingStreams};
+use proto::generated::{
+    ContainerArchive, Envelope, ErrorCode, GetContainerArchive, NodeCommand, PutContainerArchive,
+    RequestType, envelope::Payload, node_command, node_response,
+};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::error;
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::node_request::{NodeRequestError, node_error, node_error_message, request_node};
+use crate::node_stream::open_node_stream;
+use crate::validation::{ValidQuery, Validator};
+use crate::{AuthParams, error_response};
+
+// Until Docker starts sending the archive, which is when a missing path shows
+const ARCHIVE_START_TIMEOUT: Duration = Duration::from_secs(30);
+// Per uploaded chunk; the last one also covers Docker extracting the archive
+const ARCHIVE_CHUNK_TIMEOUT: Duration = Duration::from_secs(60);
+// Uploads are relayed to the node in messages of this size
+const UPLOAD_CHUNK_SIZE: usize = 1024 * 1024;
+const MAX_UPLOAD_BYTES: u64 = 1024 * 1024 * 1024;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ArchiveQuery {
+    /// Absolute path in the container: what to download, or the directory an
+    /// uploaded archive is extracted into.
+    path: String,
+}
+
+/// Downloads a file or directory from a container as a tar archive, like
+/// `docker cp <container>:<path> -`. The archive is streamed as the node reads it.
+#[utoipa::path(
+    get,
+    path = "/api/containers/{container_id}/archive",
+    tag = "containers",
+    params(("container_id" = String, Path, description = "Container id or name"), AuthParams, ArchiveQuery),
+    responses((status = 200, description = "Tar archive of the path", content_type = "application/x-tar"))
+)]
+pub async fn get_container_archive(
+    Path(container_id): Path<String>,
+    ValidQuery(archive_query): ValidQuery<ArchiveQuery>,
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending_streams): Extension<PendingStreams>,
+    auth_query: AuthParams,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = validate(&request_id, &container_id, &archive_query.path) {
+        return response;
+    }
+
+    let envelope = Envelope {
+        payload: Some(Payload::NodeCommand(NodeCommand {
+            kind: Some(node_command::Kind::GetContainerArchive(
+                GetContainerArchive {
+                    request_id: request_id.clone(),
+                    container_id: container_id.clone(),
+                    path: archive_query.path.clone(),
+                },
+            )),
+        })),
+        ..Default::default()
+    };
+    let mut node_stream = match open_node_stream(
+        &server_tx,
+        &pending_streams,
+        &auth_query,
+        &request_id,
+        RequestType::GetContainerArchive as i32,
+        envelope,
+    ) {
+        Ok(node_stream) => node_stream,
+        Err(e) => {
+            error!("Failed to send server request: {}", e);
+            return NodeRequestError::Send.into_response(&request_id);
+        }
+    };
+
+    // The first chunk shows whether the path exists, before the status is sent
+    let first = match tokio::time::timeout(ARCHIVE_START_TIMEOUT, node_stream.recv()).await {
+        Ok(Some(envelope)) => envelope,
+        Ok(None) => return NodeRequestError::ChannelClosed.into_response(&request_id),
+        Err(_) => return NodeRequestError::Timeout.into_response(&request_id),
+    };
+    if let Some(e) = node_error(&first) {
+        return e.into_response(&request_id);
+    }
+    let first = match archive_chunk(first) {
+        Ok(chunk) => chunk,
+        Err(message) => {
+            return NodeRequestError::Node(ErrorCode::Unspecified, message)
+                .into_response(&request_id);
+        }
+    };
+
+    // A failure after the first chunk can only cut the body short, which
+    // leaves the chunked transfer unterminated so clients see it as an error
+    let chunks = stream::unfold(Some((Some(first), node_stream)), |state| async move {
+        let (first, mut node_stream) = state?;
+        let chunk = match first {
+            Some(chunk) => chunk,
+            None => match node_stream.recv().await.map(archive_chunk) {
+                Some(Ok(chunk)) => chunk,
+                Some(Err(message)) => return Some((Err(std::io::Error::other(message)), None)),
+                None => {
+                    return Some((Err(std::io::Error::other("node stream closed")), None));
+                }
+            },
+        };
+        let more = !chunk.done;
+        Some((
+            Ok(Bytes::from(chunk.data)),
+            more.then_some((None, node_stream)),
+        ))
+    });
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "application/x-tar".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"{}.tar\"",
+                    archive_name(&archive_query.path)
+                ),
+            ),
+        ],
+        Body::from_stream(chunks),
+    )
+        .into_response()
+}
+
+/// Extracts the tar archive in the request body into a directory of the
+/// container, like `docker cp - <container>:<path>`. The body is relayed to
+/// the node in chunks, each acknowledged before the next is read.
+#[utoipa::path(
+    put,
+    path = "/api/containers/{container_id}/archive",
+    tag = "containers",
+    params(("container_id" = String, Path, description = "Container id or name"), AuthParams, ArchiveQuery),
+    request_body(content = Vec<u8>, content_type = "application/x-tar"),
+    responses((status = 200, description = "The archive was extracted", body = Object))
+)]
+pub async fn put_container_archive(
+    Path(container_id): Path<String>,
+    ValidQuery(archive_query): ValidQuery<ArchiveQuery>,
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    auth_query: AuthParams,
+    body: Body,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = validate(&request_id, &container_id, &archive_query.path) {
+        return response;
+    }
+
+    // An upload abandoned halfway is dropped by the node once its chunks stop coming
+    let upload_id = Uuid::new_v4().to_string();
+    let mut body = body.into_data_stream();
+    let mut buffer = Vec::with_capacity(UPLOAD_CHUNK_SIZE);
+    let mut total: u64 = 0;
+    loop {
+        let done = match body.next().await {
+            Some(Ok(bytes)) => {
+                total += bytes.len() as u64;
+                if total > MAX_UPLOAD_BYTES {
+                    return error_response(
+                        StatusCode::PAYLOAD_TOO_LARGE,
+                        &request_id,
+                        "Archive too large",
+                        format!("Archives are limited to {} bytes", MAX_UPLOAD_BYTES),
+                    );
+                }
+                buffer.extend_from_slice(&bytes);
+                if buffer.len() < UPLOAD_CHUNK_SIZE {
+                    continue;
+                }
+                false
+            }
+            Some(Err(e)) => {
+                return error_response(
+                    StatusCode::BAD_REQUEST,
+                    &request_id,
+                    "Failed to read the archive",
+                    e.to_string(),
+                );
+            }
+            None => true,
+        };
+        if let Some(response) = Validator::new()
+            .check(!done || total > 0, "body", "must be a tar archive")
+            .reject(&request_id)
+        {
+            return response;
+        }
+
+        // Each chunk is its own request, so the node's acknowledgement paces the upload
+        let chunk_request_id = Uuid::new_v4().to_string();
+        let envelope = Envelope {
+            payload: Some(Payload::NodeCommand(NodeCommand {
+                kind: Some(node_command::Kind::PutContainerArchive(
+                    PutContainerArchive {
+                        request_id: chunk_request_id.clone(),
+                        upload_id: upload_id.clone(),
+                        container_id: container_id.clone(),
+                        path: archive_query.path.clone(),
+                        data: std::mem::take(&mut buffer),
+                        done,
+                    },
+                )),
+            })),
+            ..Default::default()
+        };
+        if let Err(e) = request_node(
+            &server_tx,
+            &pending,
+            &auth_query,
+            &chunk_request_id,
+            RequestType::PutContainerArchive as i32,
+            envelope,
+            ARCHIVE_CHUNK_TIMEOUT,
+        )
+        .await
+        {
+            return e.into_response(&request_id);
+        }
+        if done {
+            break;
+        }
+    }
+
+    let body = json!({
+        "id": request_id,
+        "container_id": container_id,
+        "action": "put_archive",
+        "path": archive_query.path,
+        "bytes": total,
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+fn validate(request_id: &str, container_id: &str, path: &str) -> Option<Response> {
+    Validator::new()
+        .container_id("container_id", container_id)
+        .check(
+            path.starts_with('/') && !path.contains('\0'),
+            "path",
+            "must be an absolute path in the container",
+        )
+        .reject(request_id)
+}
+
+// The node's chunk, or the error it answered with
+fn archive_chunk(envelope: Envelope) -> Result<ContainerArchive, String> {
+    if let Some(message) = node_error_message(&envelope) {
+        return Err(message);
+    }
+    match envelope.payload {
+        Some(Payload::NodeResponse(response)) => match response.kind {
+            Some(node_response::Kind::ContainerArchive(chunk)) => Ok(chunk),
+            _ => Err("unexpected response from node".to_string()),
+        },
+        _ => Err("unexpected response from node".to_string()),
+    }
+}
+
+// File name for the download: the last path segment, reduced to safe characters
+fn archive_name(path: &str) -> String {
+    let name: String = path
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.is_empty() || name.chars().all(|c| c == '.') {
+        "archive".to_string()
+    } else {
+        name
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::BTreeMap;
+
+use axum::{Extension, Json, extract::Path, response::IntoResponse};
+use lib_coordinator_core::{CommandRouter, PendingResponses};
+use proto::generated::{
+    ContainerGraph, Envelope, GetContainerGraph, NodeCommand, RequestType, envelope::Payload,
+    node_command,
+};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use crate::NodePasswordParams;
+use crate::node_request::request_node;
+
+const GET_CONTAINER_GRAPH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(15);
+
+/// The node's containers, networks, volumes and Compose projects as a
+/// node/edge structure for topology diagrams.
+#[utoipa::path(
+    get,
+    path = "/api/nodes/{node_id}/graph",
+    tag = "nodes",
+    params(("node_id" = String, Path, description = "Node id"), NodePasswordParams),
+    responses((status = 200, description = "Graph nodes and edges", body = Object))
+)]
+pub async fn get_container_graph(
+    Path(node_id): Path<String>,
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    query: NodePasswordParams,
+) -> impl IntoResponse {
+    let request_id = Uuid::new_v4().to_string();
+    let auth = query.for_node(node_id.clone());
+
+    let envelope = Envelope {
+        payload: Some(Payload::NodeCommand(NodeCommand {
+            kind: Some(node_command::Kind::GetContainerGraph(GetContainerGraph {
+                request_id: request_id.clone(),
+            })),
+        })),
+        ..Default::default()
+    };
+
+    match request_node(
+        &server_tx,
+        &pending,
+        &auth,
+        &request_id,
+        RequestType::GetContainerGraph as i32,
+        envelope,
+        GET_CONTAINER_GRAPH_TIMEOUT,
+    )
+    .await
+    {
+        Ok(response) => {
+            let graph = extract_container_graph_from_response(&response).map(build_graph);
+            let body = json!({
+                "id": request_id,
+                "node_id": node_id,
+                "graph": graph,
+            });
+            (axum::http::StatusCode::OK, Json(body)).into_response()
+        }
+        Err(e) => e.into_response(&request_id),
+    }
+}
+
+fn extract_container_graph_from_response(response: &Envelope) -> Option<&ContainerGraph> {
+    if let Some(proto::generated::envelope::Payload::NodeResponse(node_resp)) = &response.payload
+        && let Some(proto::generated::node_response::Kind::ContainerGraph(graph)) = &node_resp.kind
+    {
+        return Some(graph);
+    }
+    None
+}
+
+/// Graph vertices are keyed `kind:name` so ids of different kinds never clash.
+/// Edges point from a container to what it uses or depends on.
+fn build_graph(graph: &ContainerGraph) -> Value {
+    // BTreeMap keeps the output stable between calls
+    let mut vertices: BTreeMap<String, Value> = BTreeMap::new();
+    let mut edges = Vec::new();
+    let container_key = |name: &str| format!("container:{}", name);
+
+    for container in &graph.containers {
+        let from = container_key(&container.name);
+        vertices.insert(
+            from.clone(),
+            json!({
+                "id": from,
+                "kind": "container",
+                "label": container.name,
+                "container_id": container.id,
+                "image": container.image,
+                "state": container.state,
+                "compose_project": (!container.compose_project.is_empty())
+                    .then_some(&container.compose_project),
+                "compose_service": (!container.compose_service.is_empty())
+                    .then_some(&container.compose_service),
+            }),
+        );
+
+        for network in &container.networks {
+            let to = format!("network:{}", network);
+            vertices
+                .entry(to.clone())
+                .or_insert_with(|| json!({ "id": to, "kind": "network", "label": network }));
+            edges.push(json!({ "from": from, "to": to, "kind": "network" }));
+        }
+
+        // Sharing another container's network stack
+        if let Some(target) = container.network_mode.strip_prefix("container:")
+            && let Some(other) = graph
+                .containers
+                .iter()
+                .find(|c| c.name == target || (!target.is_empty() && c.id.starts_with(target)))
+        {
+            edges.push(json!({
+                "from": from,
+                "to": container_key(&other.name),
+                "kind": "network_mode",
+            }));
+        }
+
+        for mount in &container.mounts {
+            if mount.source.is_empty() {
+                continue;
+            }
+            let kind = if mount.r#type == "volume" {
+                "volume"
+            } else {
+                "bind"
+            };
+            let to = format!("{}:{}", kind, mount.source);
+            vertices
+                .entry(to.clone())
+                .or_insert_with(|| json!({ "id": to, "kind": kind, "label": mount.source }));
+            edges.push(json!({
+                "from": from,
+                "to": to,
+                "kind": "mount",
+                "destination": mount.destination,
+                "read_only": mount.read_only,
+            }));
+        }
+
+        if container.compose_project.is_empty() {
+            continue;
+        }
+        let project = format!("compose_project:{}", container.compose_project);
+        vertices.entry(project.clone()).or_insert_with(|| {
+            json!({
+                "id": project,
+                "kind": "compose_project",
+                "label": container.compose_project,
+            })
+        });
+        edges.push(json!({ "from": from, "to": project, "kind": "compose_project" }));
+
+        // A service may run as several containers; depend on each of them
+        for service in &container.depends_on {
+            for dependency in graph.containers.iter().filter(|c| {
+                c.compose_project == container.compose_project && &c.compose_service == service
+            }) {
+                edges.push(json!({
+                    "from": from,
+                    "to": container_key(&dependency.name),
+                    "kind": "depends_on",
+                    "service": service,
+                }));
+            }
+        }
+    }
+
+    json!({
+        "nodes": vertices.into_values().collect::<Vec<_>>(),
+        "edges": edges,
+    })
+}
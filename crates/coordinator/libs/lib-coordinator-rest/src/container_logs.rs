@@ -1,154 +1,404 @@
+use std::convert::Infallible;
+use std::time::Duration;
+
 use axum::{
     Extension, Json,
-    extract::{Path, Query},
-    response::IntoResponse,
+    body::Body,
+    extract::Path,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
 };
-use lib_coordinator_core::{PendingResponses, ServerRequestByUser};
+use futures_util::stream;
+use lib_coordinator_core::{CommandRouter, PendingResponses, PendingStreams};
 use proto::generated::{
-    Envelope, GetContainerLogs, NodeCommand, RequestType, envelope::Payload, node_command,
+    ContainerLogs, Envelope, FollowContainerLogs, GetContainerLogs, LogLine, LogPosition,
+    LogStreams, NodeCommand, RequestType, envelope::Payload, node_command, node_response,
 };
-use serde::Serialize;
-use serde_json::json;
-use tokio::sync::{broadcast, oneshot};
+use serde_json::{Value, json};
 use tracing::error;
+use utoipa::IntoParams;
 use uuid::Uuid;
 
 use crate::AuthParams;
+use crate::node_request::request_node;
+use crate::node_stream::{NodeStream, open_node_stream};
+use crate::timestamps::Timestamp;
+use crate::validation::{ValidQuery, Validator};
 
 const GET_CONTAINER_LOGS_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+const MAX_LOG_TAIL: i32 = 100_000;
+// Upper bound for the log lines of one response; larger logs are paged
+const MAX_LOG_RESPONSE_BYTES: usize = 4 * 1024 * 1024;
+const MIN_LOG_RESPONSE_BYTES: usize = 1024;
+// Written while a followed container is quiet, so proxies keep the connection
+// open and a client that went away is noticed
+const FOLLOW_HEARTBEAT: Duration = Duration::from_secs(15);
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct LogsQuery {
     tail: Option<i32>,
     follow: Option<bool>,
-    since: Option<String>,
+    /// Unix seconds or RFC 3339.
+    #[param(value_type = Option<String>)]
+    since: Option<Timestamp>,
+    /// `next_cursor` of the previous page.
+    cursor: Option<String>,
+    max_bytes: Option<usize>,
+    /// Read from Docker even if the node buffers the container's recent lines.
+    fresh: Option<bool>,
+    /// Return each line's Docker timestamp with it.
+    timestamps: Option<bool>,
+    /// `stdout`, `stderr` or `both` (default).
+    streams: Option<String>,
 }
 
-#[derive(Serialize)]
-struct ApiErrorDetail {
-    message: String,
-    detail: String,
+/// Position after the last line of a page: its timestamp and how many lines
+/// with that same timestamp were already returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct LogCursor {
+    time_nanos: i64,
+    line: usize,
 }
 
-#[derive(Serialize)]
-struct ApiError {
-    req_id: String,
-    error: ApiErrorDetail,
+impl LogCursor {
+    fn parse(value: &str) -> Option<Self> {
+        let (time_nanos, line) = value.split_once('/')?;
+        Some(Self {
+            time_nanos: time_nanos.parse().ok()?,
+            line: line.parse().ok()?,
+        })
+    }
+
+    fn to_query(self) -> String {
+        format!("{}/{}", self.time_nanos, self.line)
+    }
+
+    fn position(self) -> LogPosition {
+        LogPosition {
+            time_nanos: self.time_nanos,
+            line: self.line.try_into().unwrap_or(u32::MAX),
+        }
+    }
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/containers/{container_id}/logs",
+    tag = "containers",
+    params(("container_id" = String, Path, description = "Container id or name"), AuthParams, LogsQuery),
+    responses((status = 200, description = "A page of log lines, or NDJSON lines with `follow`", body = Object))
+)]
 pub async fn get_container_logs(
     Path(container_id): Path<String>,
-    Query(logs_query): Query<LogsQuery>,
-    Extension(server_tx): Extension<broadcast::Sender<ServerRequestByUser>>,
+    ValidQuery(logs_query): ValidQuery<LogsQuery>,
+    Extension(server_tx): Extension<CommandRouter>,
     Extension(pending): Extension<PendingResponses>,
-    Query(auth_query): Query<AuthParams>,
+    Extension(pending_streams): Extension<PendingStreams>,
+    auth_query: AuthParams,
 ) -> impl IntoResponse {
     let request_id = Uuid::new_v4().to_string();
-    let (response_tx, response_rx) = oneshot::channel();
-
-    // Register a pending response for this request
-    pending.insert(
-        (request_id.clone(), RequestType::GetContainerLogs as i32),
-        response_tx,
-    );
+    let cursor = logs_query.cursor.as_deref().map(LogCursor::parse);
+    let follow = logs_query.follow.unwrap_or(false);
+    let streams = logs_query.streams.as_deref();
+    if let Some(response) = Validator::new()
+        .container_id("container_id", &container_id)
+        .one_of("streams", streams, &["stdout", "stderr", "both"])
+        .range("tail", logs_query.tail, 0, MAX_LOG_TAIL)
+        .range(
+            "max_bytes",
+            logs_query.max_bytes,
+            MIN_LOG_RESPONSE_BYTES,
+            MAX_LOG_RESPONSE_BYTES,
+        )
+        .check(
+            !matches!(cursor, Some(None)),
+            "cursor",
+            "must be a next_cursor returned by a previous page",
+        )
+        .check(
+            cursor.is_none() || !follow,
+            "cursor",
+            "cannot be combined with follow",
+        )
+        .check(
+            cursor.is_none() || logs_query.since.is_none(),
+            "cursor",
+            "cannot be combined with since",
+        )
+        .check(
+            !follow || logs_query.since.is_none(),
+            "since",
+            "cannot be combined with follow",
+        )
+        .reject(&request_id)
+    {
+        return response;
+    }
+    let streams = match streams {
+        Some("stdout") => LogStreams::Stdout,
+        Some("stderr") => LogStreams::Stderr,
+        _ => LogStreams::Both,
+    };
+    if follow {
+        return follow_logs(
+            &server_tx,
+            &pending_streams,
+            &auth_query,
+            &request_id,
+            &container_id,
+            logs_query.tail.unwrap_or(100),
+            streams,
+        );
+    }
+    let cursor = cursor.flatten();
+    let max_bytes = logs_query.max_bytes.unwrap_or(MAX_LOG_RESPONSE_BYTES);
 
-    // Build the command envelope to get container logs
+    // A later page reads everything from the cursor's second on; the node skips
+    // what was already returned before counting `max_bytes`, so a second with
+    // more than a page of lines still advances. Timestamps are needed to place
+    // the next cursor.
+    let (tail, since) = match cursor {
+        Some(cursor) => (-1, cursor.time_nanos.div_euclid(1_000_000_000).to_string()),
+        None => (
+            logs_query.tail.unwrap_or(100),
+            logs_query
+                .since
+                .map(|since| since.0.to_string())
+                .unwrap_or_default(),
+        ),
+    };
     let envelope = Envelope {
         payload: Some(Payload::NodeCommand(NodeCommand {
             kind: Some(node_command::Kind::GetContainerLogs(GetContainerLogs {
                 request_id: request_id.clone(),
                 container_id: container_id.clone(),
-                tail: logs_query.tail.unwrap_or(100),
-                follow: logs_query.follow.unwrap_or(false),
-                since: logs_query.since.unwrap_or_default(),
+                tail,
+                follow: false,
+                since,
+                timestamps: true,
+                max_bytes: max_bytes as u64,
+                fresh: logs_query.fresh.unwrap_or(false),
+                streams: streams.into(),
+                after: cursor.map(LogCursor::position),
             })),
         })),
+        ..Default::default()
     };
 
-    // Send the request to the node via broadcast
-    let send_result = server_tx
-        .send(ServerRequestByUser {
-            id: auth_query.node_id.clone(),
-            password: auth_query.password.clone(),
-            envelope,
-        })
-        .map(|_| ());
-
-    if let Err(e) = send_result {
-        error!("Failed to send server request: {}", e);
-        pending.remove(&(request_id.clone(), RequestType::GetContainerLogs as i32));
-        return (
-            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to send request to server",
-        )
-            .into_response();
-    }
-
-    // Wait for the response from the node with a timeout
-    match tokio::time::timeout(GET_CONTAINER_LOGS_TIMEOUT, response_rx).await {
-        Ok(Ok(response)) => {
-            if let Some(err_msg) = extract_node_error_from_response(&response) {
-                pending.remove(&(request_id.clone(), RequestType::GetContainerLogs as i32));
-                let err = ApiError {
-                    req_id: request_id.clone(),
-                    error: ApiErrorDetail {
-                        message: "Node error".to_string(),
-                        detail: err_msg,
-                    },
-                };
-                return (axum::http::StatusCode::BAD_REQUEST, Json(err)).into_response();
-            }
-
-            let logs_result = extract_container_logs_from_response(&response);
+    match request_node(
+        &server_tx,
+        &pending,
+        &auth_query,
+        &request_id,
+        RequestType::GetContainerLogs as i32,
+        envelope,
+        GET_CONTAINER_LOGS_TIMEOUT,
+    )
+    .await
+    {
+        Ok(response) => {
+            let mut buffered = false;
+            let (logs_result, next_cursor) = match extract_container_logs_from_response(response) {
+                Some(logs) => {
+                    buffered = logs.buffered;
+                    let timestamps = logs_query.timestamps.unwrap_or(false);
+                    let page = paginate(&logs, cursor, max_bytes, timestamps);
+                    (
+                        Some(json!({
+                            "container_id": logs.container_id,
+                            "logs": page.lines,
+                        })),
+                        page.next_cursor,
+                    )
+                }
+                None => (None, None),
+            };
             let body = json!({
                 "id": request_id,
                 "container_id": container_id,
                 "logs": logs_result,
+                "truncated": next_cursor.is_some(),
+                "next_cursor": next_cursor.map(LogCursor::to_query),
+                "buffered": buffered,
             });
             (axum::http::StatusCode::OK, Json(body)).into_response()
         }
-        Ok(Err(_)) => {
-            pending.remove(&(request_id.clone(), RequestType::GetContainerLogs as i32));
-            let err = ApiError {
-                req_id: request_id.clone(),
-                error: ApiErrorDetail {
-                    message: "Response channel closed".to_string(),
-                    detail: "Node dropped oneshot channel".to_string(),
+        Err(e) => e.into_response(&request_id),
+    }
+}
+
+/// Streams the container's log lines as NDJSON until the container stops. The
+/// client disconnecting drops the node stream, which cancels the follow on the node.
+fn follow_logs(
+    server_tx: &CommandRouter,
+    pending_streams: &PendingStreams,
+    auth: &AuthParams,
+    request_id: &str,
+    container_id: &str,
+    tail: i32,
+    streams: LogStreams,
+) -> Response {
+    let envelope = Envelope {
+        payload: Some(Payload::NodeCommand(NodeCommand {
+            kind: Some(node_command::Kind::FollowContainerLogs(
+                FollowContainerLogs {
+                    request_id: request_id.to_string(),
+                    container_id: container_id.to_string(),
+                    tail,
+                    timestamps: true,
+                    streams: streams.into(),
                 },
-            };
-            (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(err)).into_response()
+            )),
+        })),
+        ..Default::default()
+    };
+    let node_stream = match open_node_stream(
+        server_tx,
+        pending_streams,
+        auth,
+        request_id,
+        RequestType::FollowContainerLogs as i32,
+        envelope,
+    ) {
+        Ok(node_stream) => node_stream,
+        Err(e) => {
+            error!("Failed to send server request: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to send request to server",
+            )
+                .into_response();
         }
-        Err(_) => {
-            pending.remove(&(request_id.clone(), RequestType::GetContainerLogs as i32));
-            let err = ApiError {
-                req_id: request_id.clone(),
-                error: ApiErrorDetail {
-                    message: "Timeout waiting for node response".to_string(),
-                    detail: "Timeout waiting for node response".to_string(),
-                },
-            };
-            (axum::http::StatusCode::REQUEST_TIMEOUT, Json(err)).into_response()
+    };
+
+    let records = stream::unfold(Some(node_stream), |state| async move {
+        let mut node_stream = state?;
+        let (chunk, more) = next_follow_chunk(&mut node_stream).await;
+        Some((Ok::<_, Infallible>(chunk), more.then_some(node_stream)))
+    });
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "application/x-ndjson"),
+            (header::CACHE_CONTROL, "no-cache"),
+            // Keeps nginx from holding lines back
+            (header::HeaderName::from_static("x-accel-buffering"), "no"),
+        ],
+        Body::from_stream(records),
+    )
+        .into_response()
+}
+
+/// NDJSON records for the next message of the follow, and whether more follow.
+async fn next_follow_chunk(node_stream: &mut NodeStream) -> (String, bool) {
+    let envelope = match tokio::time::timeout(FOLLOW_HEARTBEAT, node_stream.recv()).await {
+        Ok(Some(envelope)) => envelope,
+        Ok(None) => return (ndjson([error_record("node stream closed")]), false),
+        Err(_) => return (ndjson([json!({ "type": "heartbeat" })]), true),
+    };
+    let Some(Payload::NodeResponse(response)) = envelope.payload else {
+        return (String::new(), true);
+    };
+    match response.kind {
+        Some(node_response::Kind::ContainerLogs(logs)) => {
+            let mut records: Vec<Value> = logs.lines.iter().map(log_record).collect();
+            if logs.done {
+                records.push(json!({ "type": "done" }));
+            }
+            (ndjson(records), !logs.done)
         }
+        Some(node_response::Kind::Error(err)) => (ndjson([error_record(&err.message)]), false),
+        _ => (String::new(), true),
     }
 }
 
-fn extract_container_logs_from_response(response: &Envelope) -> Option<serde_json::Value> {
-    if let Some(proto::generated::envelope::Payload::NodeResponse(node_resp)) = &response.payload {
-        if let Some(proto::generated::node_response::Kind::ContainerLogs(logs)) = &node_resp.kind {
-            return Some(json!({
-                "container_id": logs.container_id,
-                "logs": logs.logs,
-            }));
-        }
+fn log_record(line: &LogLine) -> Value {
+    match line.timestamp.is_empty() {
+        true => json!({ "type": "log", "stream": line.stream, "line": line.text }),
+        false => json!({
+            "type": "log",
+            "stream": line.stream,
+            "timestamp": line.timestamp,
+            "line": line.text,
+        }),
     }
-    None
 }
 
-fn extract_node_error_from_response(response: &Envelope) -> Option<String> {
-    if let Some(proto::generated::envelope::Payload::NodeResponse(node_resp)) = &response.payload {
-        if let Some(proto::generated::node_response::Kind::Error(err)) = &node_resp.kind {
-            return Some(err.message.clone());
+fn error_record(message: &str) -> Value {
+    json!({ "type": "error", "message": message })
+}
+
+fn ndjson(records: impl IntoIterator<Item = Value>) -> String {
+    records
+        .into_iter()
+        .map(|record| record.to_string() + "\n")
+        .collect()
+}
+
+struct LogPage {
+    lines: Vec<Value>,
+    next_cursor: Option<LogCursor>,
+}
+
+/// Fills the page up to `max_bytes` with the lines the node returned after
+/// `cursor`, each with its stream and, with `timestamps`, its timestamp. A
+/// cursor is returned when lines were left out, by the node or here.
+fn paginate(
+    logs: &ContainerLogs,
+    cursor: Option<LogCursor>,
+    max_bytes: usize,
+    timestamps: bool,
+) -> LogPage {
+    let mut lines = Vec::new();
+    let mut bytes = 0;
+    let mut truncated = logs.truncated;
+    // Lines of the cursor's timestamp continue its count
+    let mut last: Option<LogCursor> = cursor;
+
+    for line in &logs.lines {
+        // Should not be missing with timestamps on; keep the line at the previous position
+        let time_nanos = timestamp_nanos(&line.timestamp)
+            .unwrap_or_else(|| last.map_or(0, |last| last.time_nanos));
+        let text = line.text.as_str();
+        let position = LogCursor {
+            time_nanos,
+            line: match last {
+                Some(last) if last.time_nanos == time_nanos => last.line + 1,
+                _ => 1,
+            },
+        };
+
+        // The first line is kept even if it is bigger, so paging always advances
+        if !lines.is_empty() && bytes + text.len() > max_bytes {
+            truncated = true;
+            break;
         }
+        bytes += text.len();
+        lines.push(match timestamps {
+            true => json!({ "stream": line.stream, "timestamp": line.timestamp, "line": text }),
+            false => json!({ "stream": line.stream, "line": text }),
+        });
+        last = Some(position);
+    }
+
+    LogPage {
+        lines,
+        next_cursor: if truncated { last } else { None },
+    }
+}
+
+/// Nanoseconds of a Docker timestamp such as `2024-05-01T10:00:00.123456789Z`.
+pub(crate) fn timestamp_nanos(timestamp: &str) -> Option<i64> {
+    let time = chrono::DateTime::parse_from_rfc3339(timestamp).ok()?;
+    time.timestamp_nanos_opt()
+}
+
+fn extract_container_logs_from_response(response: Envelope) -> Option<ContainerLogs> {
+    if let Some(proto::generated::envelope::Payload::NodeResponse(node_resp)) = response.payload
+        && let Some(proto::generated::node_response::Kind::ContainerLogs(logs)) = node_resp.kind
+    {
+        return Some(logs);
     }
     None
 }
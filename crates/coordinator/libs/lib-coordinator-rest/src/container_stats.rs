@@ -0,0 +1,269 @@
+use axum::{
+    Extension, Json,
+    extract::Path,
+    response::{
+        IntoResponse,
+        sse::{Event, KeepAlive, Sse},
+    },
+};
+use futures_util::stream;
+use lib_coordinator_core::{
+    CommandRouter, NodeCapability, NodeChannels, NodeStateCache, PendingResponses, PendingStreams,
+};
+use proto::generated::{
+    ContainerStats, Envelope, GetContainerStats, NodeCommand, RequestType, envelope::Payload,
+    node_command,
+};
+use serde_json::json;
+use tracing::error;
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::node_request::request_node;
+use crate::node_stream::open_node_stream;
+use crate::validation::{ValidQuery, Validator};
+use crate::{AuthParams, reject_unsupported};
+
+// The node needs two Docker samples (about a second apart) to compute rates
+const GET_CONTAINER_STATS_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+const MAX_STATS_STREAM_SECS: u32 = 3600;
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct StatsStreamQuery {
+    /// How long the node keeps sampling, in seconds (node default 60, max 3600)
+    duration: Option<u32>,
+}
+
+fn stats_command(request_id: &str, container_id: &str, stream: bool, duration: u32) -> Envelope {
+    Envelope {
+        payload: Some(Payload::NodeCommand(NodeCommand {
+            kind: Some(node_command::Kind::GetContainerStats(GetContainerStats {
+                request_id: request_id.to_string(),
+                container_id: container_id.to_string(),
+                stream,
+                duration_secs: duration,
+            })),
+        })),
+        ..Default::default()
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/containers/{container_id}/stats",
+    tag = "containers",
+    params(("container_id" = String, Path, description = "Container id or name"), AuthParams),
+    responses((status = 200, description = "One resource usage sample", body = Object))
+)]
+pub async fn get_container_stats(
+    Path(container_id): Path<String>,
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(node_states): Extension<NodeStateCache>,
+    query: AuthParams,
+) -> impl IntoResponse {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = Validator::new()
+        .container_id("container_id", &container_id)
+        .reject(&request_id)
+    {
+        return response;
+    }
+    if let Some(response) = reject_unsupported(
+        &nodes,
+        &node_states,
+        &query,
+        NodeCapability::Stats,
+        &request_id,
+    )
+    .await
+    {
+        return response;
+    }
+
+    match request_node(
+        &server_tx,
+        &pending,
+        &query,
+        &request_id,
+        RequestType::GetContainerStats as i32,
+        stats_command(&request_id, &container_id, false, 0),
+        GET_CONTAINER_STATS_TIMEOUT,
+    )
+    .await
+    {
+        Ok(response) => {
+            let stats = extract_container_stats(&response).map(stats_json);
+            let body = json!({
+                "id": request_id,
+                "container_id": container_id,
+                "stats": stats,
+            });
+            (axum::http::StatusCode::OK, Json(body)).into_response()
+        }
+        Err(e) => e.into_response(&request_id),
+    }
+}
+
+/// Server-sent `stats` events, one per second, until the requested duration elapses.
+#[utoipa::path(
+    get,
+    path = "/api/containers/{container_id}/stats/stream",
+    tag = "containers",
+    params(("container_id" = String, Path, description = "Container id or name"), AuthParams, StatsStreamQuery),
+    responses((status = 200, description = "Server-sent `stats` events", content_type = "text/event-stream"))
+)]
+pub async fn stream_container_stats(
+    Path(container_id): Path<String>,
+    ValidQuery(stream_query): ValidQuery<StatsStreamQuery>,
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending_streams): Extension<PendingStreams>,
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(node_states): Extension<NodeStateCache>,
+    query: AuthParams,
+) -> impl IntoResponse {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = Validator::new()
+        .container_id("container_id", &container_id)
+        .range("duration", stream_query.duration, 1, MAX_STATS_STREAM_SECS)
+        .reject(&request_id)
+    {
+        return response;
+    }
+    if let Some(response) = reject_unsupported(
+        &nodes,
+        &node_states,
+        &query,
+        NodeCapability::Stats,
+        &request_id,
+    )
+    .await
+    {
+        return response;
+    }
+
+    let node_stream = match open_node_stream(
+        &server_tx,
+        &pending_streams,
+        &query,
+        &request_id,
+        RequestType::GetContainerStats as i32,
+        stats_command(
+            &request_id,
+            &container_id,
+            true,
+            stream_query.duration.unwrap_or(0),
+        ),
+    ) {
+        Ok(node_stream) => node_stream,
+        Err(e) => {
+            error!("Failed to send server request: {}", e);
+            return (
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to send request to server",
+            )
+                .into_response();
+        }
+    };
+
+    let events = stream::unfold(Some(node_stream), move |state| {
+        let request_id = request_id.clone();
+        async move {
+            let mut node_stream = state?;
+            let event =
+                match tokio::time::timeout(GET_CONTAINER_STATS_TIMEOUT, node_stream.recv()).await {
+                    Ok(Some(response)) => response,
+                    Ok(None) => return None,
+                    Err(_) => {
+                        let event = Event::default().event("error").json_data(json!({
+                            "req_id": request_id,
+                            "message": "Timeout waiting for node response",
+                        }));
+                        return Some((event, None));
+                    }
+                };
+
+            match extract_container_stats(&event) {
+                Some(stats) if stats.done => Some((
+                    Event::default()
+                        .event("done")
+                        .json_data(json!({ "req_id": request_id })),
+                    None,
+                )),
+                Some(stats) => Some((
+                    Event::default().event("stats").json_data(stats_json(stats)),
+                    Some(node_stream),
+                )),
+                None => {
+                    let message = extract_node_error(&event)
+                        .unwrap_or_else(|| "Unexpected response from node".to_string());
+                    Some((
+                        Event::default().event("error").json_data(json!({
+                            "req_id": request_id,
+                            "message": message,
+                        })),
+                        None,
+                    ))
+                }
+            }
+        }
+    });
+
+    Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+fn stats_json(stats: &ContainerStats) -> serde_json::Value {
+    json!({
+        "cpu": {
+            "percent": stats.cpu_percent,
+            "online_cpus": stats.online_cpus,
+        },
+        "memory": {
+            "usage": stats.memory_usage,
+            "limit": stats.memory_limit,
+            "percent": if stats.memory_limit > 0 {
+                stats.memory_usage as f64 / stats.memory_limit as f64 * 100.0
+            } else {
+                0.0
+            },
+        },
+        "network": {
+            "rx_bytes": stats.rx_bytes,
+            "tx_bytes": stats.tx_bytes,
+            "rx_packets": stats.rx_packets,
+            "tx_packets": stats.tx_packets,
+            "rx_bytes_per_sec": stats.rx_bytes_per_sec,
+            "tx_bytes_per_sec": stats.tx_bytes_per_sec,
+            "rx_packets_per_sec": stats.rx_packets_per_sec,
+            "tx_packets_per_sec": stats.tx_packets_per_sec,
+        },
+        "block_io": {
+            "read_bytes": stats.block_read_bytes,
+            "write_bytes": stats.block_write_bytes,
+            "read_bytes_per_sec": stats.block_read_bytes_per_sec,
+            "write_bytes_per_sec": stats.block_write_bytes_per_sec,
+        },
+    })
+}
+
+fn extract_container_stats(response: &Envelope) -> Option<&ContainerStats> {
+    if let Some(proto::generated::envelope::Payload::NodeResponse(node_resp)) = &response.payload
+        && let Some(proto::generated::node_response::Kind::ContainerStats(stats)) = &node_resp.kind
+    {
+        return Some(stats);
+    }
+    None
+}
+
+fn extract_node_error(response: &Envelope) -> Option<String> {
+    if let Some(proto::generated::envelope::Payload::NodeResponse(node_resp)) = &response.payload
+        && let Some(proto::generated::node_response::Kind::Error(err)) = &node_resp.kind
+    {
+        return Some(err.message.clone());
+    }
+    None
+}
@@ -0,0 +1,298 @@
+use std::collections::BTreeMap;
+
+use axum::{
+    Extension, Json,
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use lib_coordinator_core::{
+    CommandRouter, ContainerSpec, NodeChannels, PendingResponses, SecretStore, parse_port,
+};
+use proto::generated::{
+    CloneContainer, Envelope, NodeCommand, RequestType, envelope::Payload, node_command,
+};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::node_request::request_node;
+use crate::templates::{
+    DEPLOY_TIMEOUT, MAX_TEMPLATES_ITEMS, default_start, extract_container_created_from_response,
+    problems_response,
+};
+use crate::validation::{ValidJson, ValidQuery, Validator};
+use crate::{AuthParams, reject_unknown_node};
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateContainerBody {
+    #[serde(flatten)]
+    spec: ContainerSpec,
+    /// Start the container after creating it.
+    #[serde(default = "default_start")]
+    start: bool,
+}
+
+/// Creates a container on `node_id`, pulling the image first if the node does
+/// not have it, and waits until the node reports the new container id.
+#[utoipa::path(
+    post,
+    path = "/api/containers",
+    tag = "containers",
+    params(AuthParams),
+    request_body = CreateContainerBody,
+    responses((status = 201, description = "The new container id", body = Object))
+)]
+pub async fn create_container(
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    Extension(secrets): Extension<SecretStore>,
+    Extension(nodes): Extension<NodeChannels>,
+    query: AuthParams,
+    ValidJson(body): ValidJson<CreateContainerBody>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = validate_create(&body.spec, &request_id) {
+        return response;
+    }
+    // Before secrets are resolved, whose errors name the node's secrets
+    if let Some(response) = reject_unknown_node(&nodes, &query, &request_id).await {
+        return response;
+    }
+
+    match create_on_node(
+        &server_tx,
+        &pending,
+        &secrets,
+        &query,
+        &request_id,
+        &body.spec,
+        body.start,
+    )
+    .await
+    {
+        Ok(created) => {
+            let body = json!({
+                "id": request_id,
+                "node_id": query.node_id,
+                "container": created,
+            });
+            (StatusCode::CREATED, Json(body)).into_response()
+        }
+        Err(response) => response,
+    }
+}
+
+/// Checks what can be checked before the target node is known.
+pub(crate) fn validate_create(spec: &ContainerSpec, request_id: &str) -> Option<Response> {
+    let mut validator = Validator::new();
+    validator
+        .image_ref("image", &spec.image)
+        .check(
+            spec.ports.len() <= MAX_TEMPLATES_ITEMS,
+            "ports",
+            format!("at most {} ports", MAX_TEMPLATES_ITEMS),
+        )
+        .check(
+            spec.volumes.len() <= MAX_TEMPLATES_ITEMS,
+            "volumes",
+            format!("at most {} volumes", MAX_TEMPLATES_ITEMS),
+        )
+        .check(
+            spec.env.len() <= MAX_TEMPLATES_ITEMS,
+            "env",
+            format!("at most {} env vars", MAX_TEMPLATES_ITEMS),
+        );
+    if let Some(name) = &spec.name {
+        validator.container_id("name", name);
+    }
+    for (i, secret) in spec.secrets.iter().enumerate() {
+        validator.secret_name(&format!("secrets[{}].name", i), &secret.name);
+    }
+    if let Some(response) = validator.reject(request_id) {
+        return Some(response);
+    }
+    spec.command(request_id)
+        .err()
+        .map(|problems| problems_response(problems, request_id))
+}
+
+/// Creates the container on the node `auth` names and returns what the node
+/// reported about it.
+pub(crate) async fn create_on_node(
+    server_tx: &CommandRouter,
+    pending: &PendingResponses,
+    secrets: &SecretStore,
+    auth: &AuthParams,
+    request_id: &str,
+    spec: &ContainerSpec,
+    start: bool,
+) -> Result<Option<Value>, Response> {
+    let mut command = spec
+        .command(request_id)
+        .map_err(|problems| problems_response(problems, request_id))?;
+    match secrets.resolve(&auth.node_id, &spec.secrets) {
+        Ok(resolved) => command.secrets = resolved,
+        Err(e) => {
+            if let Some(response) = Validator::new()
+                .check(false, "secrets", e.to_string())
+                .reject(request_id)
+            {
+                return Err(response);
+            }
+        }
+    }
+    command.start = start;
+
+    let envelope = Envelope {
+        payload: Some(Payload::NodeCommand(NodeCommand {
+            kind: Some(node_command::Kind::CreateContainer(command)),
+        })),
+        ..Default::default()
+    };
+    match request_node(
+        server_tx,
+        pending,
+        auth,
+        request_id,
+        RequestType::CreateContainer as i32,
+        envelope,
+        DEPLOY_TIMEOUT,
+    )
+    .await
+    {
+        Ok(response) => Ok(extract_container_created_from_response(&response)),
+        Err(e) => Err(e.into_response(request_id)),
+    }
+}
+
+// Inspect plus create, without a pull
+const CLONE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CloneQuery {
+    /// Name of the copy; Docker generates one when unset.
+    name: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CloneContainerBody {
+    /// Env vars set on the copy, replacing the source's values.
+    #[serde(default)]
+    env: BTreeMap<String, String>,
+    /// Ports published instead of the source's, as in `docker run -p`; `[]`
+    /// publishes none. Unset keeps the source's, which clash once both run.
+    ports: Option<Vec<String>>,
+}
+
+/// Creates a stopped copy of a container with the same image, command,
+/// mounts, networks and host config, e.g. to debug a production container
+/// without touching it. The body is optional.
+#[utoipa::path(
+    post,
+    path = "/api/containers/{container_id}/clone",
+    tag = "containers",
+    params(("container_id" = String, Path, description = "Container id or name to copy"), AuthParams, CloneQuery),
+    request_body(content = Option<CloneContainerBody>),
+    responses((status = 201, description = "The new container id", body = Object))
+)]
+pub async fn clone_container(
+    Path(container_id): Path<String>,
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    query: AuthParams,
+    ValidQuery(clone): ValidQuery<CloneQuery>,
+    body: Option<ValidJson<CloneContainerBody>>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    let body = body.map(|ValidJson(body)| body);
+    let (env, ports) = body.map_or((BTreeMap::new(), None), |body| (body.env, body.ports));
+
+    let mut validator = Validator::new();
+    validator
+        .container_id("container_id", &container_id)
+        .check(
+            env.len() <= MAX_TEMPLATES_ITEMS,
+            "env",
+            format!("at most {} env vars", MAX_TEMPLATES_ITEMS),
+        )
+        .check(
+            ports
+                .as_ref()
+                .is_none_or(|ports| ports.len() <= MAX_TEMPLATES_ITEMS),
+            "ports",
+            format!("at most {} ports", MAX_TEMPLATES_ITEMS),
+        );
+    if let Some(name) = &clone.name {
+        validator.container_id("name", name);
+    }
+    for key in env.keys() {
+        validator.check(
+            is_env_name(key),
+            &format!("env.{}", key),
+            "must be letters, digits and '_', not starting with a digit",
+        );
+    }
+    let mut mappings = Vec::new();
+    for (i, port) in ports.iter().flatten().enumerate() {
+        match parse_port(port) {
+            Ok(mapping) => mappings.push(mapping),
+            Err(message) => {
+                validator.check(false, &format!("ports[{}]", i), message);
+            }
+        }
+    }
+    if let Some(response) = validator.reject(&request_id) {
+        return response;
+    }
+
+    let envelope = Envelope {
+        payload: Some(Payload::NodeCommand(NodeCommand {
+            kind: Some(node_command::Kind::CloneContainer(CloneContainer {
+                request_id: request_id.clone(),
+                container_id: container_id.clone(),
+                name: clone.name.unwrap_or_default(),
+                env: env
+                    .iter()
+                    .map(|(key, value)| format!("{}={}", key, value))
+                    .collect(),
+                ports: mappings,
+                replace_ports: ports.is_some(),
+            })),
+        })),
+        ..Default::default()
+    };
+    match request_node(
+        &server_tx,
+        &pending,
+        &query,
+        &request_id,
+        RequestType::CloneContainer as i32,
+        envelope,
+        CLONE_TIMEOUT,
+    )
+    .await
+    {
+        Ok(response) => {
+            let created = extract_container_created_from_response(&response);
+            let body = json!({
+                "id": request_id,
+                "node_id": query.node_id,
+                "source": container_id,
+                "container": created,
+            });
+            (StatusCode::CREATED, Json(body)).into_response()
+        }
+        Err(e) => e.into_response(&request_id),
+    }
+}
+
+fn is_env_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
@@ -0,0 +1,534 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use axum::{
+    Extension, Json,
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use dashmap::DashMap;
+use lib_coordinator_core::{
+    ChangeAction, ChangeResult, CommandRouter, DESIRED_LABEL, DesiredContainer, DesiredStateStore,
+    EventLog, NodeChannels, NodeStateCache, PendingResponses, PlannedChange, SecretStore,
+    StoredEvent,
+    desired_state::{has_reported, plan_node},
+};
+use proto::generated::{
+    DeleteContainer, Envelope, NodeCommand, RequestType, StartContainer, StopContainer,
+    envelope::Payload, node_command,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::node_request::request_node;
+use crate::validation::{ValidJson, Validator};
+use crate::{AuthParams, NodePasswordParams, error_response, reject_unknown_node};
+
+// The node may have to pull the image first
+const CREATE_TIMEOUT: Duration = Duration::from_secs(660);
+const CONTAINER_ACTION_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_DESIRED_CONTAINERS: usize = 100;
+// Actor of the events the reconciler records, in place of a credential
+const RECONCILER_ACTOR: &str = "reconciler";
+
+/// Sends the commands that carry out planned changes, on the coordinator's
+/// behalf. Shared by the desired-state and GitOps reconcilers.
+#[derive(Clone)]
+pub(crate) struct ChangeApplier {
+    server_tx: CommandRouter,
+    pending: PendingResponses,
+    nodes: NodeChannels,
+    secrets: SecretStore,
+}
+
+impl ChangeApplier {
+    pub(crate) fn new(
+        server_tx: CommandRouter,
+        pending: PendingResponses,
+        nodes: NodeChannels,
+        secrets: SecretStore,
+    ) -> Self {
+        Self {
+            server_tx,
+            pending,
+            nodes,
+            secrets,
+        }
+    }
+
+    /// Applies the changes one after the other; a failed change does not stop the rest.
+    pub(crate) async fn apply(&self, changes: &[PlannedChange], label: &str) -> Vec<ChangeResult> {
+        let mut results = Vec::with_capacity(changes.len());
+        for change in changes {
+            let error = self.apply_change(change, label).await.err();
+            match &error {
+                Some(e) => warn!(
+                    "Failed to {} {} on {}: {}",
+                    change.action.as_str(),
+                    change.name,
+                    change.node_id,
+                    e
+                ),
+                None => info!(
+                    "Applied {} of {} on {}",
+                    change.action.as_str(),
+                    change.name,
+                    change.node_id
+                ),
+            }
+            results.push(ChangeResult {
+                node_id: change.node_id.clone(),
+                name: change.name.clone(),
+                action: change.action,
+                error,
+            });
+        }
+        results
+    }
+
+    async fn apply_change(&self, change: &PlannedChange, label: &str) -> Result<(), String> {
+        let auth = node_auth(&self.nodes, &change.node_id)
+            .ok_or_else(|| "node is not connected".to_string())?;
+        match change.action {
+            ChangeAction::Start => return self.start(&auth, &change.name).await,
+            ChangeAction::Stop => return self.stop(&auth, &change.name).await,
+            ChangeAction::Recreate | ChangeAction::Remove => {
+                // Deleting a running container fails, so stop it first
+                if let Err(e) = self.stop(&auth, &change.name).await {
+                    warn!("Stopping {} before deleting it failed: {}", change.name, e);
+                }
+                self.delete(&auth, &change.name).await?;
+            }
+            ChangeAction::Create => {}
+        }
+        let Some(desired) = change
+            .desired
+            .as_ref()
+            .filter(|_| change.action != ChangeAction::Remove)
+        else {
+            return Ok(());
+        };
+
+        let request_id = Uuid::new_v4().to_string();
+        let mut command = desired.command(&request_id, label).map_err(|problems| {
+            problems
+                .iter()
+                .map(|p| format!("{}: {}", p.field, p.message))
+                .collect::<Vec<_>>()
+                .join("; ")
+        })?;
+        command.secrets = self
+            .secrets
+            .resolve(&change.node_id, &desired.secrets)
+            .map_err(|e| e.to_string())?;
+        self.send(
+            &auth,
+            &request_id,
+            RequestType::CreateContainer,
+            node_command::Kind::CreateContainer(command),
+            CREATE_TIMEOUT,
+        )
+        .await
+    }
+
+    async fn start(&self, auth: &AuthParams, container_id: &str) -> Result<(), String> {
+        let request_id = Uuid::new_v4().to_string();
+        let kind = node_command::Kind::StartContainer(StartContainer {
+            request_id: request_id.clone(),
+            container_id: container_id.to_string(),
+        });
+        self.send(
+            auth,
+            &request_id,
+            RequestType::StartContainer,
+            kind,
+            CONTAINER_ACTION_TIMEOUT,
+        )
+        .await
+    }
+
+    async fn stop(&self, auth: &AuthParams, container_id: &str) -> Result<(), String> {
+        let request_id = Uuid::new_v4().to_string();
+        let kind = node_command::Kind::StopContainer(StopContainer {
+            request_id: request_id.clone(),
+            container_id: container_id.to_string(),
+        });
+        self.send(
+            auth,
+            &request_id,
+            RequestType::StopContainer,
+            kind,
+            CONTAINER_ACTION_TIMEOUT,
+        )
+        .await
+    }
+
+    async fn delete(&self, auth: &AuthParams, container_id: &str) -> Result<(), String> {
+        let request_id = Uuid::new_v4().to_string();
+        let kind = node_command::Kind::DeleteContainer(DeleteContainer {
+            request_id: request_id.clone(),
+            container_id: container_id.to_string(),
+        });
+        self.send(
+            auth,
+            &request_id,
+            RequestType::DeleteContainer,
+            kind,
+            CONTAINER_ACTION_TIMEOUT,
+        )
+        .await
+    }
+
+    async fn send(
+        &self,
+        auth: &AuthParams,
+        request_id: &str,
+        request_type: RequestType,
+        kind: node_command::Kind,
+        timeout: Duration,
+    ) -> Result<(), String> {
+        let envelope = Envelope {
+            payload: Some(Payload::NodeCommand(NodeCommand { kind: Some(kind) })),
+            ..Default::default()
+        };
+        request_node(
+            &self.server_tx,
+            &self.pending,
+            auth,
+            request_id,
+            request_type as i32,
+            envelope,
+            timeout,
+        )
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+    }
+}
+
+/// Credentials for a connected node that background tasks act with: its
+/// internal token, which stands in for the password the coordinator does not keep.
+pub(crate) fn node_auth(nodes: &NodeChannels, node_id: &str) -> Option<AuthParams> {
+    nodes.get(node_id).map(|node| AuthParams {
+        node_id: node_id.to_string(),
+        password: node.internal_token().to_string(),
+    })
+}
+
+/// Outcome of the last reconcile pass that found drift on a node.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReconcileRecord {
+    pub at: i64,
+    pub results: Vec<ChangeResult>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<String>,
+}
+
+/// Converges nodes to their desired containers: every interval it diffs each
+/// node's desired list against the containers the node last reported, records
+/// the drift as events and sends create/start/stop commands to fix it.
+#[derive(Clone)]
+pub struct DesiredStateReconciler {
+    applier: ChangeApplier,
+    store: DesiredStateStore,
+    node_states: NodeStateCache,
+    event_log: EventLog,
+    interval: Duration,
+    records: Arc<DashMap<String, ReconcileRecord>>,
+    /// When changes were last applied per node; its state is stale until the node reports again.
+    applied_at: Arc<DashMap<String, SystemTime>>,
+    busy: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl DesiredStateReconciler {
+    #[allow(clippy::too_many_arguments)] // One per piece of shared coordinator state
+    pub fn new(
+        store: DesiredStateStore,
+        server_tx: CommandRouter,
+        pending: PendingResponses,
+        nodes: NodeChannels,
+        node_states: NodeStateCache,
+        secrets: SecretStore,
+        event_log: EventLog,
+        interval: Duration,
+    ) -> Self {
+        Self {
+            applier: ChangeApplier::new(server_tx, pending, nodes, secrets),
+            store,
+            node_states,
+            event_log,
+            interval,
+            records: Arc::new(DashMap::new()),
+            applied_at: Arc::new(DashMap::new()),
+            busy: Arc::new(tokio::sync::Mutex::new(())),
+        }
+    }
+
+    pub fn spawn(&self) -> tokio::task::JoinHandle<()> {
+        let reconciler = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(reconciler.interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                for (node_id, _) in reconciler.store.all() {
+                    reconciler.reconcile_node(&node_id).await;
+                }
+            }
+        })
+    }
+
+    /// One pass over a node. Skipped while the node is offline or has not
+    /// reported since the previous pass changed something.
+    async fn reconcile_node(&self, node_id: &str) {
+        let _busy = self.busy.lock().await;
+        let Some(desired) = self.store.get(node_id) else {
+            return;
+        };
+        let Some(state) = self.node_states.get(node_id).filter(has_reported) else {
+            return;
+        };
+        if let Some(applied_at) = self.applied_at.get(node_id).map(|at| *at) {
+            let reported_since = state.containers_updated_at > Some(applied_at);
+            let settling = applied_at.elapsed().unwrap_or_default() < self.interval * 2;
+            if !reported_since && settling {
+                return;
+            }
+        }
+
+        let wanted: Vec<&DesiredContainer> = desired.containers.iter().collect();
+        let (changes, skipped) = plan_node(node_id, &wanted, &state.containers, DESIRED_LABEL);
+        if changes.is_empty() {
+            if !skipped.is_empty() {
+                self.records
+                    .insert(node_id.to_string(), record(Vec::new(), skipped));
+            }
+            return;
+        }
+
+        info!(
+            "Node {} drifted from its desired state: {} change(s)",
+            node_id,
+            changes.len()
+        );
+        for change in &changes {
+            self.record_event(change, &format!("drift_{}", change.action.as_str()));
+        }
+        let results = self.applier.apply(&changes, DESIRED_LABEL).await;
+        self.applied_at
+            .insert(node_id.to_string(), SystemTime::now());
+        for (change, result) in changes.iter().zip(&results) {
+            if result.error.is_some() {
+                self.record_event(change, "reconcile_failed");
+            }
+        }
+        self.records
+            .insert(node_id.to_string(), record(results, skipped));
+    }
+
+    /// Drift shows up in the container event history next to Docker's own events.
+    fn record_event(&self, change: &PlannedChange, action: &str) {
+        self.event_log.append(StoredEvent {
+            seq: 0, // assigned by the log
+            node_id: change.node_id.clone(),
+            kind: "container".to_string(),
+            container_id: change.name.clone(),
+            name: change.name.clone(),
+            action: action.to_string(),
+            time: now_secs(),
+            exit_code: 0,
+            image: change
+                .desired
+                .as_ref()
+                .map(|d| d.image.clone())
+                .unwrap_or_default(),
+            health: String::new(),
+            actor: RECONCILER_ACTOR.to_string(),
+            status: 0,
+        });
+    }
+}
+
+fn record(results: Vec<ChangeResult>, skipped: Vec<String>) -> ReconcileRecord {
+    ReconcileRecord {
+        at: now_secs(),
+        results,
+        skipped,
+    }
+}
+
+fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct DesiredStateBody {
+    containers: Vec<DesiredContainer>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/nodes/{node_id}/desired",
+    tag = "desired-state",
+    params(("node_id" = String, Path, description = "Node id"), NodePasswordParams),
+    responses((status = 200, description = "Desired containers and the last reconcile", body = Object))
+)]
+pub async fn get_desired_state(
+    Path(node_id): Path<String>,
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(reconciler): Extension<DesiredStateReconciler>,
+    query: NodePasswordParams,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    let auth = query.for_node(node_id);
+    if let Some(response) = reject_unknown_node(&nodes, &auth, &request_id).await {
+        return response;
+    }
+
+    let desired = reconciler.store.get(&auth.node_id);
+    let last_reconcile = reconciler
+        .records
+        .get(&auth.node_id)
+        .map(|record| record.clone());
+    let body = json!({
+        "req_id": request_id,
+        "node_id": auth.node_id,
+        "managed": desired.is_some(),
+        "containers": desired.as_ref().map(|d| d.containers.as_slice()).unwrap_or_default(),
+        "updated_at": desired.map(|d| d.updated_at),
+        "last_reconcile": last_reconcile,
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+/// Replaces the node's desired containers and reconciles it right away.
+#[utoipa::path(
+    put,
+    path = "/api/nodes/{node_id}/desired",
+    tag = "desired-state",
+    params(("node_id" = String, Path, description = "Node id"), NodePasswordParams),
+    request_body = DesiredStateBody,
+    responses((status = 200, description = "The stored list and the changes made", body = Object))
+)]
+pub async fn put_desired_state(
+    Path(node_id): Path<String>,
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(reconciler): Extension<DesiredStateReconciler>,
+    query: NodePasswordParams,
+    ValidJson(body): ValidJson<DesiredStateBody>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    let mut validator = Validator::new();
+    validator.check(
+        body.containers.len() <= MAX_DESIRED_CONTAINERS,
+        "containers",
+        format!("at most {} containers", MAX_DESIRED_CONTAINERS),
+    );
+    let mut seen = HashSet::new();
+    for (i, container) in body.containers.iter().enumerate() {
+        let field = format!("containers[{}]", i);
+        validator
+            .container_id(&format!("{}.name", field), &container.name)
+            .image_ref(&format!("{}.image", field), &container.image)
+            .check(
+                container.node_id.is_empty() || container.node_id == node_id,
+                &format!("{}.node_id", field),
+                "must be omitted or match the node in the path",
+            )
+            .check(
+                seen.insert(container.name.as_str()),
+                &field,
+                format!("{} is listed twice", container.name),
+            );
+        for problem in container.problems() {
+            validator.check(
+                false,
+                &format!("{}.{}", field, problem.field),
+                problem.message,
+            );
+        }
+        for (j, secret) in container.secrets.iter().enumerate() {
+            validator.secret_name(&format!("{}.secrets[{}].name", field, j), &secret.name);
+        }
+    }
+    if let Some(response) = validator.reject(&request_id) {
+        return response;
+    }
+    let auth = query.for_node(node_id);
+    if let Some(response) = reject_unknown_node(&nodes, &auth, &request_id).await {
+        return response;
+    }
+
+    if let Err(e) = reconciler.store.set(&auth.node_id, body.containers) {
+        error!("Failed to persist desired state: {}", e);
+        return error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            &request_id,
+            "Failed to store desired state",
+            e.to_string(),
+        );
+    }
+    reconciler.applied_at.remove(&auth.node_id);
+    let background = reconciler.clone();
+    let node_id = auth.node_id.clone();
+    tokio::spawn(async move { background.reconcile_node(&node_id).await });
+
+    let desired = reconciler.store.get(&auth.node_id).unwrap_or_default();
+    let body = json!({
+        "req_id": request_id,
+        "node_id": auth.node_id,
+        "containers": desired.containers,
+        "updated_at": desired.updated_at,
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+/// Stops reconciling the node. Its containers stay as they are.
+#[utoipa::path(
+    delete,
+    path = "/api/nodes/{node_id}/desired",
+    tag = "desired-state",
+    params(("node_id" = String, Path, description = "Node id"), NodePasswordParams),
+    responses((status = 200, description = "The node is no longer reconciled", body = Object))
+)]
+pub async fn delete_desired_state(
+    Path(node_id): Path<String>,
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(reconciler): Extension<DesiredStateReconciler>,
+    query: NodePasswordParams,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    let auth = query.for_node(node_id);
+    if let Some(response) = reject_unknown_node(&nodes, &auth, &request_id).await {
+        return response;
+    }
+
+    match reconciler.store.remove(&auth.node_id) {
+        Ok(removed) => {
+            reconciler.records.remove(&auth.node_id);
+            let body = json!({
+                "req_id": request_id,
+                "node_id": auth.node_id,
+                "removed": removed,
+            });
+            (StatusCode::OK, Json(body)).into_response()
+        }
+        Err(e) => {
+            error!("Failed to persist desired state: {}", e);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                &request_id,
+                "Failed to store desired state",
+                e.to_string(),
+            )
+        }
+    }
+}
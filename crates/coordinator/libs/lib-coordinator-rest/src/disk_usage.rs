@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+
+use axum::{Extension, Json, extract::Path, response::IntoResponse};
+use lib_coordinator_core::{CommandRouter, PendingResponses};
+use proto::generated::{
+    DiskUsage, Envelope, GetDiskUsage, NodeCommand, RequestType, envelope::Payload, node_command,
+};
+use serde_json::{Value, json};
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::NodePasswordParams;
+use crate::node_request::request_node;
+use crate::validation::{ValidQuery, Validator};
+
+// Sizing writable layers is slow on the node
+const GET_DISK_USAGE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(130);
+const DEFAULT_DISK_USAGE_LIMIT: u32 = 50;
+const MAX_DISK_USAGE_LIMIT: u32 = 1000;
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DiskUsageQuery {
+    sort: Option<String>,
+    limit: Option<u32>,
+}
+
+/// Disk space on the node attributed to containers, largest first: each
+/// container's writable layer plus an equal share of its image among all
+/// containers using it. Images no container uses are totalled separately.
+#[utoipa::path(
+    get,
+    path = "/api/nodes/{node_id}/disk-usage",
+    tag = "nodes",
+    params(("node_id" = String, Path, description = "Node id"), NodePasswordParams, DiskUsageQuery),
+    responses((status = 200, description = "Disk usage per container", body = Object))
+)]
+pub async fn get_disk_usage(
+    Path(node_id): Path<String>,
+    ValidQuery(usage_query): ValidQuery<DiskUsageQuery>,
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    query: NodePasswordParams,
+) -> impl IntoResponse {
+    let request_id = Uuid::new_v4().to_string();
+    let auth = query.for_node(node_id.clone());
+
+    if let Some(response) = Validator::new()
+        .one_of(
+            "sort",
+            usage_query.sort.as_deref(),
+            &["attributed", "writable"],
+        )
+        .range("limit", usage_query.limit, 1, MAX_DISK_USAGE_LIMIT)
+        .reject(&request_id)
+    {
+        return response;
+    }
+    let sort = usage_query.sort.unwrap_or_else(|| "attributed".to_string());
+    let limit = usage_query.limit.unwrap_or(DEFAULT_DISK_USAGE_LIMIT) as usize;
+
+    let envelope = Envelope {
+        payload: Some(Payload::NodeCommand(NodeCommand {
+            kind: Some(node_command::Kind::GetDiskUsage(GetDiskUsage {
+                request_id: request_id.clone(),
+            })),
+        })),
+        ..Default::default()
+    };
+
+    match request_node(
+        &server_tx,
+        &pending,
+        &auth,
+        &request_id,
+        RequestType::GetDiskUsage as i32,
+        envelope,
+        GET_DISK_USAGE_TIMEOUT,
+    )
+    .await
+    {
+        Ok(response) => {
+            let usage = extract_disk_usage_from_response(&response)
+                .map(|usage| attribute_disk_usage(usage, &sort, limit));
+            let body = json!({
+                "id": request_id,
+                "node_id": node_id,
+                "sort": sort,
+                "disk_usage": usage,
+            });
+            (axum::http::StatusCode::OK, Json(body)).into_response()
+        }
+        Err(e) => e.into_response(&request_id),
+    }
+}
+
+fn extract_disk_usage_from_response(response: &Envelope) -> Option<&DiskUsage> {
+    if let Some(proto::generated::envelope::Payload::NodeResponse(node_resp)) = &response.payload
+        && let Some(proto::generated::node_response::Kind::DiskUsage(usage)) = &node_resp.kind
+    {
+        return Some(usage);
+    }
+    None
+}
+
+fn attribute_disk_usage(usage: &DiskUsage, sort: &str, limit: usize) -> Value {
+    let mut users: HashMap<&str, i64> = HashMap::new();
+    for container in &usage.containers {
+        *users.entry(container.image_id.as_str()).or_default() += 1;
+    }
+    let image_sizes: HashMap<&str, i64> = usage
+        .images
+        .iter()
+        .map(|image| (image.id.as_str(), image.size))
+        .collect();
+
+    let mut containers: Vec<(i64, Value)> = usage
+        .containers
+        .iter()
+        .map(|container| {
+            let image_size = image_sizes
+                .get(container.image_id.as_str())
+                .copied()
+                .unwrap_or(0);
+            let shared_by = users.get(container.image_id.as_str()).copied().unwrap_or(1);
+            let attributed = container.writable_size + image_size / shared_by.max(1);
+            let key = if sort == "writable" {
+                container.writable_size
+            } else {
+                attributed
+            };
+            let value = json!({
+                "container_id": container.container_id,
+                "name": container.name,
+                "image": container.image,
+                "state": container.state,
+                "writable_size": container.writable_size,
+                "image_size": image_size,
+                "image_shared_by": shared_by,
+                "attributed_size": attributed,
+            });
+            (key, value)
+        })
+        .collect();
+    containers.sort_by_key(|(size, _)| std::cmp::Reverse(*size));
+    let container_count = containers.len();
+
+    let images: Vec<Value> = usage
+        .images
+        .iter()
+        .map(|image| {
+            json!({
+                "id": image.id,
+                "tags": image.tags,
+                "size": image.size,
+                "shared_size": image.shared_size,
+                "containers": users.get(image.id.as_str()).copied().unwrap_or(0),
+            })
+        })
+        .collect();
+    let unused_images_size: i64 = usage
+        .images
+        .iter()
+        .filter(|image| !users.contains_key(image.id.as_str()))
+        .map(|image| image.size)
+        .sum();
+
+    json!({
+        "container_count": container_count,
+        "containers": containers
+            .into_iter()
+            .take(limit)
+            .map(|(_, value)| value)
+            .collect::<Vec<_>>(),
+        "images": images,
+        "totals": {
+            "writable_size": usage.containers.iter().map(|c| c.writable_size).sum::<i64>(),
+            "images_size": usage.images.iter().map(|i| i.size).sum::<i64>(),
+            "unused_images_size": unused_images_size,
+        },
+    })
+}
@@ -0,0 +1,135 @@
+use axum::{
+    Extension, Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use lib_coordinator_core::{EventLog, EventQuery, NodeChannels};
+use serde::Deserialize;
+use serde_json::json;
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::admin::{AdminParams, AdminToken};
+use crate::timestamps::Timestamp;
+use crate::validation::{ValidQuery, Validator};
+use crate::{AuthParams, VerifiedNode, reject_unknown_node};
+
+const DEFAULT_EVENTS_LIMIT: usize = 100;
+const MAX_EVENTS_LIMIT: usize = 1000;
+const EVENT_KINDS: [&str; 5] = ["container", "image", "volume", "script", "action"];
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct EventHistoryQuery {
+    node_id: Option<String>,
+    password: Option<String>,
+    /// "container", "image", "volume", "script" or "action"; every type when unset.
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    container_id: Option<String>,
+    action: Option<String>,
+    /// Unix seconds or RFC 3339, inclusive.
+    #[param(value_type = Option<String>)]
+    from: Option<Timestamp>,
+    #[param(value_type = Option<String>)]
+    to: Option<Timestamp>,
+    limit: Option<usize>,
+    /// `next_cursor` of the previous page.
+    cursor: Option<u64>,
+}
+
+/// Container, image and volume lifecycle history, newest first. Node credentials see their own node,
+/// the admin token sees every node (optionally narrowed with `node_id`).
+#[utoipa::path(
+    get,
+    path = "/api/events/history",
+    tag = "events",
+    params(AdminParams, EventHistoryQuery),
+    responses((status = 200, description = "A page of events", body = Object))
+)]
+pub async fn get_event_history(
+    Extension(event_log): Extension<EventLog>,
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(admin): Extension<AdminToken>,
+    ValidQuery(admin_params): ValidQuery<AdminParams>,
+    verified: Option<Extension<VerifiedNode>>,
+    ValidQuery(query): ValidQuery<EventHistoryQuery>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = Validator::new()
+        .range("limit", query.limit, 1, MAX_EVENTS_LIMIT)
+        .one_of("type", query.kind.as_deref(), &EVENT_KINDS)
+        .check(
+            match (query.from, query.to) {
+                (Some(from), Some(to)) => from <= to,
+                _ => true,
+            },
+            "from",
+            "must not be after `to`",
+        )
+        .reject(&request_id)
+    {
+        return response;
+    }
+
+    if admin_params.admin_token.is_some() {
+        if let Some(response) = admin.reject(&admin_params, &request_id) {
+            return response;
+        }
+    } else {
+        let auth = AuthParams {
+            node_id: query.node_id.clone().unwrap_or_default(),
+            password: match &verified {
+                Some(Extension(verified)) => verified.password(&nodes),
+                None => query.password.clone().unwrap_or_default(),
+            },
+        };
+        if let Some(response) = reject_unknown_node(&nodes, &auth, &request_id).await {
+            return response;
+        }
+    }
+
+    let limit = query.limit.unwrap_or(DEFAULT_EVENTS_LIMIT);
+    let events = event_log.query(&EventQuery {
+        node_id: query.node_id.as_deref(),
+        kind: query.kind.as_deref(),
+        container_id: query.container_id.as_deref(),
+        action: query.action.as_deref(),
+        from: query.from.map(|from| from.0),
+        to: query.to.map(|to| to.0),
+        before: query.cursor,
+        limit,
+    });
+
+    // A full page means there may be more
+    let next_cursor = (events.len() == limit)
+        .then(|| events.last().map(|e| e.seq))
+        .flatten();
+
+    let events: Vec<_> = events
+        .iter()
+        .map(|e| {
+            json!({
+                "seq": e.seq,
+                "node_id": e.node_id,
+                "type": e.kind,
+                "container_id": e.container_id,
+                "name": e.name,
+                "action": e.action,
+                "time": e.time,
+                "exit_code": e.exit_code,
+                "image": e.image,
+                "health": (!e.health.is_empty()).then_some(&e.health),
+                "actor": (!e.actor.is_empty()).then_some(&e.actor),
+                "status": (e.status != 0).then_some(e.status),
+            })
+        })
+        .collect();
+
+    let body = json!({
+        "req_id": request_id,
+        "events": events,
+        "next_cursor": next_cursor,
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
@@ -0,0 +1,221 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use axum::{
+    Extension, Json,
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use lib_coordinator_core::{Favorite, FavoritesStore, NodeChannels, NodeStateCache, TenantStore};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::error;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::tenants::CallerTenant;
+use crate::validation::{ValidJson, ValidQuery, Validator};
+use crate::{AuthParams, error_response, reject_unknown_node};
+
+const MIN_API_KEY_LEN: usize = 16;
+const MAX_FAVORITES: usize = 200;
+
+/// Personal key the favorites list is stored under. Treat it like a password.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ApiKeyParams {
+    api_key: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct AddFavoriteBody {
+    container_id: String,
+}
+
+/// Resolves every favorite from the coordinator's node cache in one call, no node round-trips.
+#[utoipa::path(
+    get,
+    path = "/api/favorites",
+    tag = "favorites",
+    params(ApiKeyParams),
+    responses((status = 200, description = "Pinned containers with their current state", body = Object))
+)]
+pub async fn get_favorites(
+    Extension(favorites): Extension<FavoritesStore>,
+    Extension(node_states): Extension<NodeStateCache>,
+    Extension(tenants): Extension<TenantStore>,
+    caller: Option<Extension<CallerTenant>>,
+    ValidQuery(key): ValidQuery<ApiKeyParams>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = reject_short_key(&key, &request_id) {
+        return response;
+    }
+
+    let resolved: Vec<_> = favorites
+        .list(&key.api_key)
+        .into_iter()
+        // A tenant never sees nodes that were moved to another tenant since
+        .filter(|favorite| match &caller {
+            Some(Extension(CallerTenant(tenant))) => {
+                tenants.owner_of(&favorite.node_id).as_ref() == Some(tenant)
+            }
+            None => true,
+        })
+        .map(|favorite| {
+            let node = node_states.get(&favorite.node_id);
+            let container = node.as_ref().and_then(|state| {
+                state
+                    .containers
+                    .iter()
+                    .find(|c| c.container_id == favorite.container_id)
+            });
+            json!({
+                "node_id": favorite.node_id,
+                "container_id": favorite.container_id,
+                "node_online": node.as_ref().is_some_and(|state| state.online),
+                "last_seen": node.as_ref().map(|state| unix_secs(state.last_seen)),
+                // null when the node never reported the container (or it was removed)
+                "status": container.map(|c| c.status.clone()),
+                "created": container.map(|c| c.created),
+            })
+        })
+        .collect();
+
+    let body = json!({
+        "req_id": request_id,
+        "favorites": resolved,
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+/// Pins a container; the node credentials prove access to it.
+#[utoipa::path(
+    post,
+    path = "/api/favorites",
+    tag = "favorites",
+    params(ApiKeyParams, AuthParams),
+    request_body = AddFavoriteBody,
+    responses((status = 200, description = "The container is pinned", body = Object))
+)]
+pub async fn add_favorite(
+    Extension(favorites): Extension<FavoritesStore>,
+    Extension(nodes): Extension<NodeChannels>,
+    ValidQuery(key): ValidQuery<ApiKeyParams>,
+    auth: AuthParams,
+    ValidJson(body): ValidJson<AddFavoriteBody>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = Validator::new()
+        .container_id("container_id", &body.container_id)
+        .reject(&request_id)
+    {
+        return response;
+    }
+    if let Some(response) = reject_short_key(&key, &request_id) {
+        return response;
+    }
+    if let Some(response) = reject_unknown_node(&nodes, &auth, &request_id).await {
+        return response;
+    }
+
+    let favorite = Favorite {
+        node_id: auth.node_id,
+        container_id: body.container_id,
+    };
+    let current = favorites.list(&key.api_key);
+    if current.len() >= MAX_FAVORITES && !current.contains(&favorite) {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            &request_id,
+            "Too many favorites",
+            format!("at most {} favorites per key", MAX_FAVORITES),
+        );
+    }
+
+    match favorites.add(&key.api_key, favorite.clone()) {
+        Ok(count) => {
+            let body = json!({
+                "req_id": request_id,
+                "node_id": favorite.node_id,
+                "container_id": favorite.container_id,
+                "count": count,
+            });
+            (StatusCode::OK, Json(body)).into_response()
+        }
+        Err(e) => store_error(&request_id, e),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/favorites/{node_id}/{container_id}",
+    tag = "favorites",
+    params(("node_id" = String, Path, description = "Node id"), ("container_id" = String, Path, description = "Container id or name"), ApiKeyParams),
+    responses((status = 200, description = "The container is no longer pinned", body = Object))
+)]
+pub async fn remove_favorite(
+    Path((node_id, container_id)): Path<(String, String)>,
+    Extension(favorites): Extension<FavoritesStore>,
+    ValidQuery(key): ValidQuery<ApiKeyParams>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = Validator::new()
+        .container_id("container_id", &container_id)
+        .reject(&request_id)
+    {
+        return response;
+    }
+    if let Some(response) = reject_short_key(&key, &request_id) {
+        return response;
+    }
+
+    let favorite = Favorite {
+        node_id,
+        container_id,
+    };
+    match favorites.remove(&key.api_key, &favorite) {
+        Ok(true) => {
+            let body = json!({
+                "req_id": request_id,
+                "node_id": favorite.node_id,
+                "container_id": favorite.container_id,
+                "removed": true,
+            });
+            (StatusCode::OK, Json(body)).into_response()
+        }
+        Ok(false) => error_response(
+            StatusCode::NOT_FOUND,
+            &request_id,
+            "Favorite not found",
+            format!("{}/{}", favorite.node_id, favorite.container_id),
+        ),
+        Err(e) => store_error(&request_id, e),
+    }
+}
+
+fn reject_short_key(key: &ApiKeyParams, request_id: &str) -> Option<Response> {
+    Validator::new()
+        .check(
+            key.api_key.len() >= MIN_API_KEY_LEN,
+            "api_key",
+            format!("must be at least {} characters", MIN_API_KEY_LEN),
+        )
+        .reject(request_id)
+}
+
+fn store_error(request_id: &str, e: std::io::Error) -> Response {
+    error!("Failed to persist favorites: {}", e);
+    error_response(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        request_id,
+        "Failed to store favorites",
+        e.to_string(),
+    )
+}
+
+fn unix_secs(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
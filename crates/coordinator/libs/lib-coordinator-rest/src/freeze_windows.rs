@@ -0,0 +1,279 @@
+use axum::{
+    Extension, Json,
+    extract::{Path, Request, State},
+    http::{HeaderValue, Method, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{SecondsFormat, Utc};
+use lib_coordinator_core::{
+    CronSchedule, FreezeStore, FreezeWindow, MAX_FREEZE_MINUTES, TenantStore,
+};
+use serde_json::{Value, json};
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::admin::{AdminParams, AdminToken};
+use crate::error_response;
+use crate::rate_limit::target_node_id;
+use crate::validation::{ValidJson, ValidQuery, Validator};
+
+const MAX_WINDOW_NAME_LEN: usize = 64;
+const MAX_REASON_LEN: usize = 500;
+
+/// State of the freeze middleware: the windows, and tenants to resolve the
+/// windows that freeze whole tenants.
+#[derive(Clone)]
+pub struct FreezeCheck {
+    windows: FreezeStore,
+    tenants: TenantStore,
+}
+
+impl FreezeCheck {
+    pub fn new(windows: FreezeStore, tenants: TenantStore) -> Self {
+        Self { windows, tenants }
+    }
+}
+
+/// Middleware answering `423 Locked` to requests that change a node while one
+/// of its freeze windows is open. Reads, and requests not addressed to a node,
+/// pass; so do the approval routes, so held requests can still be rejected.
+pub async fn enforce_freeze_windows(
+    State(check): State<FreezeCheck>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    ) || request.uri().path().starts_with("/api/approvals")
+    {
+        return next.run(request).await;
+    }
+    let Some(node_id) = target_node_id(&request) else {
+        return next.run(request).await;
+    };
+
+    let now = Utc::now();
+    let tenant = check.tenants.owner_of(&node_id);
+    let Some(freeze) = check.windows.active(&node_id, tenant.as_deref(), now) else {
+        return next.run(request).await;
+    };
+    info!(
+        "Refused {} {} to node {}: freeze window {}",
+        request.method(),
+        request.uri().path(),
+        node_id,
+        freeze.name
+    );
+    let mut detail = format!(
+        "Node {} is frozen by window '{}' until {}",
+        node_id,
+        freeze.name,
+        freeze.until.to_rfc3339_opts(SecondsFormat::Secs, true)
+    );
+    if !freeze.reason.is_empty() {
+        detail.push_str(&format!(": {}", freeze.reason));
+    }
+    let mut response = error_response(
+        StatusCode::LOCKED,
+        &Uuid::new_v4().to_string(),
+        "Change freeze",
+        detail,
+    );
+    let secs = (freeze.until - now).num_seconds().max(1) as u64;
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from(secs));
+    response
+}
+
+/// Every freeze window, with whether it is open now and when it next opens.
+#[utoipa::path(
+    get,
+    path = "/api/freeze-windows",
+    tag = "freeze-windows",
+    params(AdminParams),
+    responses((status = 200, description = "Freeze windows", body = Object))
+)]
+pub async fn list_freeze_windows(
+    Extension(windows): Extension<FreezeStore>,
+    Extension(admin): Extension<AdminToken>,
+    ValidQuery(params): ValidQuery<AdminParams>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = admin.reject(&params, &request_id) {
+        return response;
+    }
+
+    let list: Vec<_> = windows
+        .list()
+        .iter()
+        .map(|(name, window)| window_json(name, window))
+        .collect();
+    let body = json!({
+        "req_id": request_id,
+        "freeze_windows": list,
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+/// Creates or replaces a freeze window.
+#[utoipa::path(
+    put,
+    path = "/api/freeze-windows/{name}",
+    tag = "freeze-windows",
+    params(("name" = String, Path, description = "Window name"), AdminParams),
+    request_body = FreezeWindow,
+    responses((status = 200, description = "The window was replaced", body = Object), (status = 201, description = "The window was created", body = Object))
+)]
+pub async fn put_freeze_window(
+    Path(name): Path<String>,
+    Extension(windows): Extension<FreezeStore>,
+    Extension(admin): Extension<AdminToken>,
+    ValidQuery(params): ValidQuery<AdminParams>,
+    ValidJson(window): ValidJson<FreezeWindow>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = admin.reject(&params, &request_id) {
+        return response;
+    }
+    let schedule = CronSchedule::parse(&window.schedule);
+    if let Some(response) = Validator::new()
+        .check(
+            !name.is_empty()
+                && name.len() <= MAX_WINDOW_NAME_LEN
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-')),
+            "name",
+            format!(
+                "must be 1 to {} letters, digits, '_' or '-'",
+                MAX_WINDOW_NAME_LEN
+            ),
+        )
+        .check(
+            schedule.is_ok(),
+            "schedule",
+            schedule.as_ref().err().cloned().unwrap_or_default(),
+        )
+        .check(
+            schedule
+                .as_ref()
+                .map_or(true, |schedule| schedule.next_after(Utc::now()).is_some()),
+            "schedule",
+            "never matches a date",
+        )
+        .range(
+            "duration_minutes",
+            Some(window.duration_minutes),
+            1,
+            MAX_FREEZE_MINUTES,
+        )
+        .check(
+            !window.nodes.is_empty() || !window.tenants.is_empty(),
+            "nodes",
+            "at least one node or tenant must be frozen",
+        )
+        .check(
+            window
+                .nodes
+                .iter()
+                .chain(&window.tenants)
+                .all(|id| !id.is_empty()),
+            "nodes",
+            "node ids and tenant names must not be empty",
+        )
+        .check(
+            window.reason.len() <= MAX_REASON_LEN,
+            "reason",
+            format!("must be at most {} bytes", MAX_REASON_LEN),
+        )
+        .reject(&request_id)
+    {
+        return response;
+    }
+
+    let json = window_json(&name, &window);
+    match windows.put(&name, window) {
+        Ok(created) => {
+            info!("Saved freeze window {}", name);
+            let status = if created {
+                StatusCode::CREATED
+            } else {
+                StatusCode::OK
+            };
+            let body = json!({
+                "req_id": request_id,
+                "freeze_window": json,
+                "created": created,
+            });
+            (status, Json(body)).into_response()
+        }
+        Err(e) => store_error(&request_id, e),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/freeze-windows/{name}",
+    tag = "freeze-windows",
+    params(("name" = String, Path, description = "Window name"), AdminParams),
+    responses((status = 200, description = "The window is gone", body = Object))
+)]
+pub async fn delete_freeze_window(
+    Path(name): Path<String>,
+    Extension(windows): Extension<FreezeStore>,
+    Extension(admin): Extension<AdminToken>,
+    ValidQuery(params): ValidQuery<AdminParams>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = admin.reject(&params, &request_id) {
+        return response;
+    }
+
+    match windows.delete(&name) {
+        Ok(true) => {
+            info!("Deleted freeze window {}", name);
+            let body = json!({
+                "req_id": request_id,
+                "name": name,
+                "deleted": true,
+            });
+            (StatusCode::OK, Json(body)).into_response()
+        }
+        Ok(false) => error_response(
+            StatusCode::NOT_FOUND,
+            &request_id,
+            "Freeze window not found",
+            name,
+        ),
+        Err(e) => store_error(&request_id, e),
+    }
+}
+
+fn window_json(name: &str, window: &FreezeWindow) -> Value {
+    let now = Utc::now();
+    let open_until = window.active_until(now);
+    json!({
+        "name": name,
+        "schedule": window.schedule,
+        "duration_minutes": window.duration_minutes,
+        "nodes": window.nodes,
+        "tenants": window.tenants,
+        "reason": window.reason,
+        "active": open_until.is_some(),
+        "ends_at": open_until.map(|time| time.timestamp()),
+        "next_start_at": window.next_start(now).map(|time| time.timestamp()),
+    })
+}
+
+fn store_error(request_id: &str, e: std::io::Error) -> Response {
+    error!("Failed to persist freeze windows: {}", e);
+    error_response(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        request_id,
+        "Failed to store freeze windows",
+        e.to_string(),
+    )
+}
@@ -1,80 +1,109 @@
-use axum::{Extension, Json, extract::Query, response::IntoResponse};
-use lib_coordinator_core::{PendingResponses, ServerRequestByUser};
+use std::collections::BTreeMap;
+
+use axum::{Extension, Json, response::IntoResponse};
+use lib_coordinator_core::{AnnotationStore, Annotations};
 use proto::generated::{
     Envelope, GetNodeContainersWithStatus, NodeCommand, RequestType, envelope::Payload,
     node_command,
 };
 use serde_json::json;
-use tokio::sync::{broadcast, oneshot};
-use tracing::error;
+use utoipa::IntoParams;
 use uuid::Uuid;
 
 use crate::AuthParams;
+use crate::node_request::NodeRequestError;
+use crate::response_cache::ResponseCache;
+use crate::validation::{ValidQuery, Validator};
 
 const GET_CONTAINERS_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
 
+const MAX_FILTER_LEN: usize = 256;
+
+/// Optional name filters, evaluated on the node.
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ContainerFilterQuery {
+    q: Option<String>,
+    name_regex: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/containers",
+    tag = "containers",
+    params(AuthParams, ContainerFilterQuery),
+    responses((status = 200, description = "The node's containers with their annotations", body = Object))
+)]
 pub async fn get_containers(
-    Extension(server_tx): Extension<broadcast::Sender<ServerRequestByUser>>,
-    Extension(pending): Extension<PendingResponses>,
-    Query(query): Query<AuthParams>,
+    Extension(cache): Extension<ResponseCache>,
+    Extension(annotations): Extension<AnnotationStore>,
+    query: AuthParams,
+    ValidQuery(filter): ValidQuery<ContainerFilterQuery>,
 ) -> impl IntoResponse {
     let request_id = Uuid::new_v4().to_string();
-    let (response_tx, response_rx) = oneshot::channel();
-
-    // Register a pending response for this request
-    pending.insert(
-        (
-            request_id.clone(),
-            RequestType::GetContainersWithStatus as i32,
-        ),
-        response_tx,
-    );
-
-    // Build the command envelope to ask the node for containers with status
-    let envelope = Envelope {
-        payload: Some(Payload::NodeCommand(NodeCommand {
-            kind: Some(node_command::Kind::GetNodeContainersWithStatus(
-                GetNodeContainersWithStatus {
-                    request_id: request_id.clone(),
-                },
-            )),
-        })),
-    };
-
-    // Send the request to the node via broadcast
-    let send_request = server_tx
-        .send(ServerRequestByUser {
-            id: query.node_id.clone(),
-            password: query.password.clone(),
-            envelope,
-        })
-        .map(|_| ());
+    if let Some(response) = Validator::new()
+        .check(
+            filter.q.as_ref().is_none_or(|q| q.len() <= MAX_FILTER_LEN),
+            "q",
+            format!("must be at most {} characters", MAX_FILTER_LEN),
+        )
+        .check(
+            filter
+                .name_regex
+                .as_ref()
+                .is_none_or(|regex| regex.len() <= MAX_FILTER_LEN),
+            "name_regex",
+            format!("must be at most {} characters", MAX_FILTER_LEN),
+        )
+        .reject(&request_id)
+    {
+        return response;
+    }
+    let name_query = filter.q.unwrap_or_default();
+    let name_regex = filter.name_regex.unwrap_or_default();
 
-    if let Err(e) = send_request {
-        error!("Failed to send server request: {}", e);
-        pending.remove(&(
-            request_id.clone(),
+    // Clients polling the same node share one node request, or a cached response
+    let result = cache
+        .request(
+            &query,
+            format!("containers_with_status {:?} {:?}", name_query, name_regex),
             RequestType::GetContainersWithStatus as i32,
-        ));
-        return (
-            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to send request to server",
+            |node_request_id| Envelope {
+                payload: Some(Payload::NodeCommand(NodeCommand {
+                    kind: Some(node_command::Kind::GetNodeContainersWithStatus(
+                        GetNodeContainersWithStatus {
+                            request_id: node_request_id.to_string(),
+                            name_query,
+                            name_regex,
+                        },
+                    )),
+                })),
+                ..Default::default()
+            },
+            GET_CONTAINERS_TIMEOUT,
         )
-            .into_response();
-    }
+        .await;
 
-    // Wait for the response from the node with a timeout
-    match tokio::time::timeout(GET_CONTAINERS_TIMEOUT, response_rx).await {
-        Ok(Ok(response)) => {
+    match result {
+        Ok(response) => {
             // Parse containers with status from response
-            let containers_with_status = extract_containers_with_status_from_response(&response);
+            let node_annotations = annotations.for_node(&query.node_id);
+            let containers_with_status =
+                extract_containers_with_status_from_response(&response.envelope, &node_annotations);
             let body = json!({
                 "id": request_id,
                 "containers": containers_with_status,
             });
-            (axum::http::StatusCode::OK, Json(body)).into_response()
+            (axum::http::StatusCode::OK, response.headers(), Json(body)).into_response()
         }
-        Ok(Err(_)) => {
+        // The node rejects an invalid name_regex
+        Err(e @ NodeRequestError::Node(..)) => e.into_response(&request_id),
+        Err(NodeRequestError::Send) => (
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to send request to server",
+        )
+            .into_response(),
+        Err(NodeRequestError::ChannelClosed) => {
             let body = json!({
                 "error": {
                     "message": "Response channel closed",
@@ -86,7 +115,7 @@ pub async fn get_containers(
             });
             (axum::http::StatusCode::INTERNAL_SERVER_ERROR, Json(body)).into_response()
         }
-        Err(_) => {
+        Err(NodeRequestError::Timeout) => {
             let body = json!({
                 "error": {
                     "message": "No response from node",
@@ -101,27 +130,37 @@ pub async fn get_containers(
     }
 }
 
-fn extract_containers_with_status_from_response(response: &Envelope) -> Vec<serde_json::Value> {
-    if let Some(proto::generated::envelope::Payload::NodeResponse(node_resp)) = &response.payload {
-        if let Some(proto::generated::node_response::Kind::NodeContainersWithStatus(
-            containers_msg,
-        )) = &node_resp.kind
-        {
-            return containers_msg
-                .containers
-                .iter()
-                .map(|container| {
-                    json!({
-                        "container_id": container.container_id,
-                        "status": container.status,
-                        "created": container.created,
-                        "started_at": container.started_at,
-                        "finished_at": container.finished_at,
-                        "exit_code": container.exit_code,
-                    })
+fn extract_containers_with_status_from_response(
+    response: &Envelope,
+    annotations: &BTreeMap<String, Annotations>,
+) -> Vec<serde_json::Value> {
+    if let Some(proto::generated::envelope::Payload::NodeResponse(node_resp)) = &response.payload
+        && let Some(proto::generated::node_response::Kind::NodeContainersWithStatus(containers_msg)) =
+            &node_resp.kind
+    {
+        return containers_msg
+            .containers
+            .iter()
+            .map(|container| {
+                json!({
+                    "container_id": container.container_id,
+                    "status": container.status,
+                    "created": container.created,
+                    "started_at": container.started_at,
+                    "finished_at": container.finished_at,
+                    "exit_code": container.exit_code,
+                    // Usage is only sampled for a single container's status
+                    "resources": {
+                        "memory_limit": container.memory_limit,
+                        "cpu_limit": container.cpu_limit,
+                    },
+                    "annotations": annotations
+                        .get(&container.container_id)
+                        .cloned()
+                        .unwrap_or_default(),
                 })
-                .collect();
-        }
+            })
+            .collect();
     }
     vec![]
 }
@@ -0,0 +1,233 @@
+use axum::{
+    Extension, Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use lib_coordinator_core::{
+    ChangeResult, CommandRouter, GITOPS_LABEL, GitOps, GitOpsMode, GitOpsPlan, NodeChannels,
+    NodeStateCache, PendingResponses, SecretStore, gitops::plan_changes,
+};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::admin::{AdminParams, AdminToken};
+use crate::desired_state::ChangeApplier;
+use crate::error_response;
+use crate::validation::{ValidJson, ValidQuery};
+
+/// Runs the GitOps loop and applies plans to the nodes on the coordinator's behalf.
+#[derive(Clone)]
+pub struct GitOpsReconciler {
+    gitops: GitOps,
+    applier: ChangeApplier,
+    node_states: NodeStateCache,
+}
+
+impl GitOpsReconciler {
+    pub fn new(
+        gitops: GitOps,
+        server_tx: CommandRouter,
+        pending: PendingResponses,
+        nodes: NodeChannels,
+        node_states: NodeStateCache,
+        secrets: SecretStore,
+    ) -> Self {
+        Self {
+            gitops,
+            applier: ChangeApplier::new(server_tx, pending, nodes, secrets),
+            node_states,
+        }
+    }
+
+    /// Syncs right away and then every interval.
+    pub fn spawn(&self) -> tokio::task::JoinHandle<()> {
+        let reconciler = self.clone();
+        let config = &reconciler.gitops.config;
+        info!(
+            "GitOps: syncing {} ({}) every {}s in {:?} mode",
+            config.repo,
+            config.branch,
+            config.interval.as_secs(),
+            config.mode
+        );
+        tokio::spawn(async move {
+            loop {
+                let _ = reconciler.reconcile().await;
+                tokio::time::sleep(reconciler.gitops.config.interval).await;
+            }
+        })
+    }
+
+    /// Fetches the repository and plans the changes; in auto mode applies them too.
+    async fn reconcile(&self) -> Result<(), String> {
+        let _busy = self.gitops.lock().await;
+        let (commit, desired) = match self.gitops.fetch_desired().await {
+            Ok(fetched) => fetched,
+            Err(e) => {
+                warn!("GitOps sync failed: {}", e);
+                self.gitops.record_sync_error(e.clone());
+                return Err(e);
+            }
+        };
+
+        let (changes, skipped) = plan_changes(&desired, &self.node_states);
+        for note in &skipped {
+            warn!("GitOps: {}", note);
+        }
+        let Some(plan) = self.gitops.record_plan(commit, changes, skipped) else {
+            return Ok(());
+        };
+        info!(
+            "GitOps plan {} for {} has {} change(s)",
+            plan.id,
+            plan.commit,
+            plan.changes.len()
+        );
+        if self.gitops.config.mode == GitOpsMode::Auto {
+            self.apply_locked(&plan).await;
+        }
+        Ok(())
+    }
+
+    async fn apply_locked(&self, plan: &GitOpsPlan) -> Vec<ChangeResult> {
+        let results = self.applier.apply(&plan.changes, GITOPS_LABEL).await;
+        self.gitops.record_apply(plan, results.clone());
+        results
+    }
+
+    fn status(&self, request_id: &str) -> serde_json::Value {
+        json!({
+            "req_id": request_id,
+            "config": &*self.gitops.config,
+            "state": self.gitops.state(),
+        })
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ApplyBody {
+    plan_id: String,
+}
+
+fn not_enabled(request_id: &str) -> Response {
+    error_response(
+        StatusCode::NOT_FOUND,
+        request_id,
+        "GitOps is not enabled",
+        "Start the coordinator with --gitops-repo".to_string(),
+    )
+}
+
+/// Configuration, last sync, pending plan and last apply.
+#[utoipa::path(
+    get,
+    path = "/api/gitops",
+    tag = "gitops",
+    params(AdminParams),
+    responses((status = 200, description = "Configuration, last sync, pending plan and last apply", body = Object))
+)]
+pub async fn get_gitops_status(
+    Extension(gitops): Extension<Option<GitOpsReconciler>>,
+    Extension(admin): Extension<AdminToken>,
+    ValidQuery(params): ValidQuery<AdminParams>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = admin.reject(&params, &request_id) {
+        return response;
+    }
+    let Some(reconciler) = &gitops else {
+        return not_enabled(&request_id);
+    };
+
+    (StatusCode::OK, Json(reconciler.status(&request_id))).into_response()
+}
+
+/// Syncs now instead of waiting for the next interval.
+#[utoipa::path(
+    post,
+    path = "/api/gitops/sync",
+    tag = "gitops",
+    params(AdminParams),
+    responses((status = 200, description = "The sync and its plan", body = Object))
+)]
+pub async fn sync_gitops(
+    Extension(gitops): Extension<Option<GitOpsReconciler>>,
+    Extension(admin): Extension<AdminToken>,
+    ValidQuery(params): ValidQuery<AdminParams>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = admin.reject(&params, &request_id) {
+        return response;
+    }
+    let Some(reconciler) = &gitops else {
+        return not_enabled(&request_id);
+    };
+
+    match reconciler.reconcile().await {
+        Ok(()) => (StatusCode::OK, Json(reconciler.status(&request_id))).into_response(),
+        Err(e) => error_response(
+            StatusCode::BAD_GATEWAY,
+            &request_id,
+            "GitOps sync failed",
+            e,
+        ),
+    }
+}
+
+/// Approves the pending plan in manual mode. The id guards against applying
+/// a plan that a newer sync has replaced in the meantime.
+#[utoipa::path(
+    post,
+    path = "/api/gitops/apply",
+    tag = "gitops",
+    params(AdminParams),
+    request_body = ApplyBody,
+    responses((status = 200, description = "The applied changes", body = Object), (status = 409, description = "The plan was replaced or already applied", body = Object))
+)]
+pub async fn apply_gitops(
+    Extension(gitops): Extension<Option<GitOpsReconciler>>,
+    Extension(admin): Extension<AdminToken>,
+    ValidQuery(params): ValidQuery<AdminParams>,
+    ValidJson(body): ValidJson<ApplyBody>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = admin.reject(&params, &request_id) {
+        return response;
+    }
+    let Some(reconciler) = &gitops else {
+        return not_enabled(&request_id);
+    };
+    if reconciler.gitops.config.mode == GitOpsMode::DryRun {
+        return error_response(
+            StatusCode::CONFLICT,
+            &request_id,
+            "GitOps is in dry-run mode",
+            "Plans are only computed, never applied".to_string(),
+        );
+    }
+
+    let _busy = reconciler.gitops.lock().await;
+    let Some(plan) = reconciler.gitops.plan(&body.plan_id) else {
+        return error_response(
+            StatusCode::CONFLICT,
+            &request_id,
+            "Plan is not current",
+            format!(
+                "Plan {} was applied or replaced by a newer sync",
+                body.plan_id
+            ),
+        );
+    };
+    let results = reconciler.apply_locked(&plan).await;
+
+    let body = json!({
+        "req_id": request_id,
+        "plan_id": plan.id,
+        "commit": plan.commit,
+        "results": results,
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
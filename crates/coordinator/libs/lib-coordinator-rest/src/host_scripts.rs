@@ -0,0 +1,153 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use axum::{
+    Extension, Json,
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use lib_coordinator_core::{
+    CommandRouter, EventLog, NodeChannels, PendingResponses, StoredEvent, TraceContext,
+};
+use proto::generated::{
+    Envelope, ErrorCode, NodeCommand, NodeResponse, RequestType, RunHostScript, envelope::Payload,
+    node_command, node_response,
+};
+use serde_json::json;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::node_request::{NodeRequestError, request_node};
+use crate::validation::Validator;
+use crate::{NodePasswordParams, reject_unknown_node};
+
+// Nodes kill a script after at most 10 minutes; this leaves room for the answer
+const RUN_HOST_SCRIPT_TIMEOUT: Duration = Duration::from_secs(630);
+
+/// Runs a script the node operator registered on the host, and waits for it to
+/// finish. Every attempt is recorded in the event history with type `script`.
+#[utoipa::path(
+    post,
+    path = "/api/nodes/{node_id}/scripts/{name}",
+    tag = "nodes",
+    params(("node_id" = String, Path, description = "Node id"), ("name" = String, Path, description = "Script name"), NodePasswordParams),
+    responses((status = 200, description = "The script's exit code and output", body = Object))
+)]
+pub async fn run_host_script(
+    Path((node_id, name)): Path<(String, String)>,
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(event_log): Extension<EventLog>,
+    query: NodePasswordParams,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    let auth = query.for_node(node_id.clone());
+    if let Some(response) = Validator::new()
+        .check(
+            !name.is_empty()
+                && name.len() <= 64
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-')),
+            "name",
+            "must be 1-64 letters, digits, '_' or '-'",
+        )
+        .reject(&request_id)
+    {
+        return response;
+    }
+    // Only connected nodes are asked, so a wrong password fails fast and is not audited
+    if let Some(response) = reject_unknown_node(&nodes, &auth, &request_id).await {
+        return response;
+    }
+
+    info!("Running host script {} on node {}", name, node_id);
+    let envelope = Envelope {
+        payload: Some(Payload::NodeCommand(NodeCommand {
+            kind: Some(node_command::Kind::RunHostScript(RunHostScript {
+                request_id: request_id.clone(),
+                name: name.clone(),
+            })),
+        })),
+        ..Default::default()
+    };
+    let result = request_node(
+        &server_tx,
+        &pending,
+        &auth,
+        &request_id,
+        RequestType::RunHostScript as i32,
+        envelope,
+        RUN_HOST_SCRIPT_TIMEOUT,
+    )
+    .await;
+
+    let outcome = match result {
+        Ok(response) => match response.payload {
+            Some(Payload::NodeResponse(NodeResponse {
+                kind: Some(node_response::Kind::HostScriptResult(script_result)),
+            })) => Ok(script_result),
+            _ => Err(NodeRequestError::Node(
+                ErrorCode::Unspecified,
+                "the node did not report a script result".to_string(),
+            )),
+        },
+        Err(e) => Err(e),
+    };
+    let (action, exit_code) = match &outcome {
+        Ok(script_result) => {
+            info!(
+                "Host script {} on node {} exited with {}",
+                name, node_id, script_result.exit_code
+            );
+            ("run", script_result.exit_code)
+        }
+        Err(e) => {
+            warn!("Host script {} on node {} failed: {}", name, node_id, e);
+            ("failed", 0)
+        }
+    };
+    // The credential the request came with, as `record_container_actions` records it
+    let actor = TraceContext::current()
+        .map(|trace| trace.actor)
+        .unwrap_or_else(|| format!("node:{}", node_id));
+    event_log.append(StoredEvent {
+        seq: 0, // assigned by the log
+        node_id: node_id.clone(),
+        kind: "script".to_string(),
+        container_id: name.clone(),
+        name,
+        action: action.to_string(),
+        time: now_secs(),
+        exit_code,
+        image: String::new(),
+        health: String::new(),
+        actor,
+        status: 0,
+    });
+
+    let script_result = match outcome {
+        Ok(script_result) => script_result,
+        Err(e) => return e.into_response(&request_id),
+    };
+    let body = json!({
+        "id": request_id,
+        "node_id": node_id,
+        "script": script_result.name,
+        "exit_code": script_result.exit_code,
+        "success": script_result.exit_code == 0,
+        "stdout": script_result.stdout,
+        "stderr": script_result.stderr,
+        "truncated": script_result.truncated,
+        "duration_ms": script_result.duration_ms,
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+
+use axum::{
+    Extension,
+    response::{
+        IntoResponse,
+        sse::{Event, KeepAlive, Sse},
+    },
+};
+use futures_util::stream;
+use lib_coordinator_core::{CommandRouter, PendingStreams};
+use proto::generated::{
+    BuildImage, Envelope, NodeCommand, RequestType, envelope::Payload, node_command,
+};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::error;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::AuthParams;
+use crate::node_stream::open_node_stream;
+use crate::validation::{ValidJson, Validator};
+
+// Builds can go quiet for a long time (e.g. large RUN steps), so this is per message
+const BUILD_IMAGE_IDLE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(300);
+
+#[derive(Deserialize, ToSchema)]
+pub struct BuildImageBody {
+    remote: String,
+    dockerfile: Option<String>,
+    tag: Option<String>,
+    #[serde(default)]
+    build_args: HashMap<String, String>,
+    #[serde(default)]
+    nocache: bool,
+    #[serde(default)]
+    pull: bool,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/images/build",
+    tag = "images",
+    params(AuthParams),
+    request_body = BuildImageBody,
+    responses((status = 200, description = "Server-sent build progress events", content_type = "text/event-stream"))
+)]
+pub async fn build_image(
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending_streams): Extension<PendingStreams>,
+    query: AuthParams,
+    ValidJson(body): ValidJson<BuildImageBody>,
+) -> impl IntoResponse {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = validate_build(&body).reject(&request_id) {
+        return response;
+    }
+
+    // Build the command envelope to build the image
+    let envelope = Envelope {
+        payload: Some(Payload::NodeCommand(NodeCommand {
+            kind: Some(node_command::Kind::BuildImage(BuildImage {
+                request_id: request_id.clone(),
+                remote: body.remote,
+                dockerfile: body.dockerfile.unwrap_or_default(),
+                tag: body.tag.unwrap_or_default(),
+                build_args: body.build_args,
+                nocache: body.nocache,
+                pull: body.pull,
+            })),
+        })),
+        ..Default::default()
+    };
+
+    let node_stream = match open_node_stream(
+        &server_tx,
+        &pending_streams,
+        &query,
+        &request_id,
+        RequestType::BuildImage as i32,
+        envelope,
+    ) {
+        Ok(node_stream) => node_stream,
+        Err(e) => {
+            error!("Failed to send server request: {}", e);
+            return (
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to send request to server",
+            )
+                .into_response();
+        }
+    };
+
+    // Relay build output as server-sent events until the node reports completion
+    let events = stream::unfold(Some(node_stream), move |state| {
+        let request_id = request_id.clone();
+        async move {
+            let mut node_stream = state?;
+            match tokio::time::timeout(BUILD_IMAGE_IDLE_TIMEOUT, node_stream.recv()).await {
+                Ok(Some(response)) => {
+                    let (event, finished) = build_output_event(&request_id, &response);
+                    Some((event, if finished { None } else { Some(node_stream) }))
+                }
+                Ok(None) => None,
+                Err(_) => Some((
+                    Event::default().event("error").json_data(json!({
+                        "req_id": request_id,
+                        "message": "Timeout waiting for node response",
+                    })),
+                    None,
+                )),
+            }
+        }
+    });
+
+    Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+/// Converts one node envelope into an SSE event; the flag is set for the final event.
+fn build_output_event(request_id: &str, response: &Envelope) -> (Result<Event, axum::Error>, bool) {
+    if let Some(Payload::NodeResponse(node_resp)) = &response.payload {
+        match &node_resp.kind {
+            Some(proto::generated::node_response::Kind::ImageBuildOutput(output)) => {
+                if !output.error.is_empty() {
+                    let event = Event::default().event("error").json_data(json!({
+                        "req_id": request_id,
+                        "message": output.error,
+                    }));
+                    return (event, true);
+                }
+                if output.done {
+                    let event = Event::default().event("done").json_data(json!({
+                        "req_id": request_id,
+                        "image_id": output.image_id,
+                    }));
+                    return (event, true);
+                }
+                let event = Event::default().event("output").json_data(json!({
+                    "stream": output.stream,
+                    "status": output.status,
+                    "progress": output.progress,
+                }));
+                return (event, false);
+            }
+            Some(proto::generated::node_response::Kind::Error(err)) => {
+                let event = Event::default().event("error").json_data(json!({
+                    "req_id": request_id,
+                    "message": err.message,
+                }));
+                return (event, true);
+            }
+            _ => {}
+        }
+    }
+
+    let event = Event::default().event("error").json_data(json!({
+        "req_id": request_id,
+        "message": "Unexpected response from node",
+    }));
+    (event, true)
+}
+
+fn validate_build(body: &BuildImageBody) -> Validator {
+    let mut validator = Validator::new();
+    validator.check(
+        ["http://", "https://", "git://", "git@"]
+            .iter()
+            .any(|scheme| body.remote.starts_with(scheme)),
+        "remote",
+        "must be an http(s) or git URL of the build context",
+    );
+    if let Some(tag) = body.tag.as_deref().filter(|tag| !tag.is_empty()) {
+        validator.image_ref("tag", tag);
+    }
+    if let Some(dockerfile) = &body.dockerfile {
+        validator.check(
+            !dockerfile.is_empty()
+                && !dockerfile.starts_with('/')
+                && !dockerfile.split('/').any(|part| part == ".."),
+            "dockerfile",
+            "must be a relative path inside the build context",
+        );
+    }
+    validator.check(
+        body.build_args.keys().all(|key| !key.is_empty()),
+        "build_args",
+        "argument names must not be empty",
+    );
+    validator
+}
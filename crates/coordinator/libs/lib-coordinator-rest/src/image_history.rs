@@ -0,0 +1,83 @@
+use axum::{Extension, Json, extract::Path, response::IntoResponse};
+use lib_coordinator_core::{CommandRouter, PendingResponses};
+use proto::generated::{
+    Envelope, GetImageHistory, NodeCommand, RequestType, envelope::Payload, node_command,
+};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::AuthParams;
+use crate::node_request::request_node;
+use crate::validation::Validator;
+
+const GET_IMAGE_HISTORY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(15);
+
+/// Layers of an image on the node, newest first. `image` is an image id or a
+/// reference; references containing `/` must be sent URL-encoded.
+#[utoipa::path(
+    get,
+    path = "/api/images/{image}/history",
+    tag = "images",
+    params(("image" = String, Path, description = "Image id or reference, URL-encoded"), AuthParams),
+    responses((status = 200, description = "The image's layers", body = Object))
+)]
+pub async fn get_image_history(
+    Path(image): Path<String>,
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    query: AuthParams,
+) -> impl IntoResponse {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = Validator::new()
+        .image_ref("image", &image)
+        .reject(&request_id)
+    {
+        return response;
+    }
+
+    let envelope = Envelope {
+        payload: Some(Payload::NodeCommand(NodeCommand {
+            kind: Some(node_command::Kind::GetImageHistory(GetImageHistory {
+                request_id: request_id.clone(),
+                image: image.clone(),
+            })),
+        })),
+        ..Default::default()
+    };
+
+    match request_node(
+        &server_tx,
+        &pending,
+        &query,
+        &request_id,
+        RequestType::GetImageHistory as i32,
+        envelope,
+        GET_IMAGE_HISTORY_TIMEOUT,
+    )
+    .await
+    {
+        Ok(response) => {
+            let history = extract_image_history_from_response(&response);
+            let body = json!({
+                "id": request_id,
+                "image": image,
+                "history": history,
+            });
+            (axum::http::StatusCode::OK, Json(body)).into_response()
+        }
+        Err(e) => e.into_response(&request_id),
+    }
+}
+
+fn extract_image_history_from_response(response: &Envelope) -> Option<serde_json::Value> {
+    if let Some(proto::generated::envelope::Payload::NodeResponse(node_resp)) = &response.payload
+        && let Some(proto::generated::node_response::Kind::ImageHistory(history)) = &node_resp.kind
+    {
+        return Some(json!({
+            "total_size": history.total_size,
+            "layer_count": history.layers.len(),
+            "layers": history.layers,
+        }));
+    }
+    None
+}
@@ -0,0 +1,101 @@
+use axum::{Extension, Json, extract::Path, response::IntoResponse};
+use lib_coordinator_core::{CommandRouter, PendingResponses};
+use proto::generated::{
+    Envelope, InspectImageManifest, NodeCommand, RequestType, envelope::Payload, node_command,
+};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::AuthParams;
+use crate::node_request::request_node;
+use crate::validation::Validator;
+
+// The node has to reach the registry, which is slower than local Docker calls
+const INSPECT_IMAGE_MANIFEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(20);
+
+/// `image_ref` may contain `/` (e.g. `ghcr.io/org/app:1.0`), which must be sent URL-encoded.
+#[utoipa::path(
+    get,
+    path = "/api/images/{image_ref}/manifest",
+    tag = "images",
+    params(("image_ref" = String, Path, description = "Image reference, URL-encoded"), AuthParams),
+    responses((status = 200, description = "The image's manifest from its registry", body = Object))
+)]
+pub async fn get_image_manifest(
+    Path(image_ref): Path<String>,
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    query: AuthParams,
+) -> impl IntoResponse {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = Validator::new()
+        .image_ref("image_ref", &image_ref)
+        .reject(&request_id)
+    {
+        return response;
+    }
+
+    // Build the command envelope to resolve the manifest through the node's registry access
+    let envelope = Envelope {
+        payload: Some(Payload::NodeCommand(NodeCommand {
+            kind: Some(node_command::Kind::InspectImageManifest(
+                InspectImageManifest {
+                    request_id: request_id.clone(),
+                    image: image_ref.clone(),
+                },
+            )),
+        })),
+        ..Default::default()
+    };
+
+    match request_node(
+        &server_tx,
+        &pending,
+        &query,
+        &request_id,
+        RequestType::InspectImageManifest as i32,
+        envelope,
+        INSPECT_IMAGE_MANIFEST_TIMEOUT,
+    )
+    .await
+    {
+        Ok(response) => {
+            let manifest = extract_image_manifest_from_response(&response);
+            let body = json!({
+                "id": request_id,
+                "image": image_ref,
+                "manifest": manifest,
+            });
+            (axum::http::StatusCode::OK, Json(body)).into_response()
+        }
+        Err(e) => e.into_response(&request_id),
+    }
+}
+
+fn extract_image_manifest_from_response(response: &Envelope) -> Option<serde_json::Value> {
+    if let Some(proto::generated::envelope::Payload::NodeResponse(node_resp)) = &response.payload
+        && let Some(proto::generated::node_response::Kind::ImageManifest(manifest)) =
+            &node_resp.kind
+    {
+        let platforms: Vec<serde_json::Value> = manifest
+            .platforms
+            .iter()
+            .map(|platform| {
+                json!({
+                    "os": platform.os,
+                    "architecture": platform.architecture,
+                    "variant": platform.variant,
+                    "os_version": platform.os_version,
+                })
+            })
+            .collect();
+
+        return Some(json!({
+            "digest": manifest.digest,
+            "media_type": manifest.media_type,
+            "size": manifest.size,
+            "platforms": platforms,
+        }));
+    }
+    None
+}
@@ -0,0 +1,368 @@
+use std::time::Duration;
+
+use axum::{
+    Extension, Json,
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use lib_coordinator_core::{CommandRouter, PendingResponses};
+use proto::generated::{
+    Envelope, ImagesRemoved, ListImages, NodeCommand, PruneImages, PullImage, RemoveImage,
+    RequestType, envelope::Payload, node_command, node_response,
+};
+use serde::Deserialize;
+use serde_json::json;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::AuthParams;
+use crate::node_request::request_node;
+use crate::timestamps::Timestamp;
+use crate::validation::{ValidJson, ValidQuery, Validator};
+
+const LIST_IMAGES_TIMEOUT: Duration = Duration::from_secs(20);
+// The node allows a pull 10 minutes; this leaves room for its answer
+const PULL_IMAGE_TIMEOUT: Duration = Duration::from_secs(630);
+const REMOVE_IMAGE_TIMEOUT: Duration = Duration::from_secs(40);
+const PRUNE_IMAGES_TIMEOUT: Duration = Duration::from_secs(320);
+const MAX_REFERENCE_FILTER_LEN: usize = 255;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListImagesQuery {
+    /// Include intermediate images.
+    #[serde(default)]
+    all: bool,
+    /// Docker reference filter such as `nginx` or `nginx:1.*`.
+    reference: Option<String>,
+}
+
+/// Images on the node, newest first.
+#[utoipa::path(
+    get,
+    path = "/api/images",
+    tag = "images",
+    params(AuthParams, ListImagesQuery),
+    responses((status = 200, description = "The node's images", body = Object))
+)]
+pub async fn list_images(
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    auth: AuthParams,
+    ValidQuery(query): ValidQuery<ListImagesQuery>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    let reference = query.reference.unwrap_or_default();
+    if let Some(response) = Validator::new()
+        .check(
+            reference.len() <= MAX_REFERENCE_FILTER_LEN
+                && reference.chars().all(|c| {
+                    c.is_ascii_alphanumeric()
+                        || matches!(c, '.' | '_' | '-' | '/' | ':' | '@' | '*')
+                }),
+            "reference",
+            "must be an image reference, optionally with '*' wildcards",
+        )
+        .reject(&request_id)
+    {
+        return response;
+    }
+
+    let kind = node_command::Kind::ListImages(ListImages {
+        request_id: request_id.clone(),
+        all: query.all,
+        reference,
+    });
+    let response = match send(
+        &server_tx,
+        &pending,
+        &auth,
+        &request_id,
+        RequestType::ListImages,
+        kind,
+        LIST_IMAGES_TIMEOUT,
+    )
+    .await
+    {
+        Ok(response) => response,
+        Err(response) => return response,
+    };
+
+    let images = match response {
+        Some(node_response::Kind::ImageList(list)) => list
+            .images
+            .iter()
+            .map(|image| {
+                json!({
+                    "id": image.id,
+                    "repo_tags": image.repo_tags,
+                    "repo_digests": image.repo_digests,
+                    "created": image.created,
+                    "size": image.size,
+                    "containers": image.containers,
+                    "dangling": image.repo_tags.is_empty(),
+                })
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    let body = json!({
+        "id": request_id,
+        "count": images.len(),
+        "images": images,
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct PullImageBody {
+    image: String,
+    /// `os/arch[/variant]`, e.g. `linux/arm64`; the node's own when unset.
+    platform: Option<String>,
+}
+
+/// Pulls an image onto the node, updating it if the tag moved. Waits for the pull to finish.
+#[utoipa::path(
+    post,
+    path = "/api/images/pull",
+    tag = "images",
+    params(AuthParams),
+    request_body = PullImageBody,
+    responses((status = 200, description = "The pulled image", body = Object))
+)]
+pub async fn pull_image(
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    auth: AuthParams,
+    ValidJson(body): ValidJson<PullImageBody>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    let platform = body.platform.unwrap_or_default();
+    let platform_parts: Vec<&str> = platform.split('/').collect();
+    if let Some(response) = Validator::new()
+        .image_ref("image", &body.image)
+        .check(
+            platform.is_empty()
+                || ((2..=3).contains(&platform_parts.len())
+                    && platform_parts.iter().all(|part| {
+                        !part.is_empty()
+                            && part
+                                .chars()
+                                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+                    })),
+            "platform",
+            "must be os/arch or os/arch/variant, e.g. linux/arm64",
+        )
+        .reject(&request_id)
+    {
+        return response;
+    }
+
+    let kind = node_command::Kind::PullImage(PullImage {
+        request_id: request_id.clone(),
+        image: body.image.clone(),
+        platform,
+    });
+    let response = match send(
+        &server_tx,
+        &pending,
+        &auth,
+        &request_id,
+        RequestType::PullImage,
+        kind,
+        PULL_IMAGE_TIMEOUT,
+    )
+    .await
+    {
+        Ok(response) => response,
+        Err(response) => return response,
+    };
+
+    let pulled = match response {
+        Some(node_response::Kind::ImagePulled(pulled)) => Some(pulled),
+        _ => None,
+    };
+    let body = json!({
+        "id": request_id,
+        "image": body.image,
+        "pulled": pulled,
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RemoveImageQuery {
+    /// Also remove the image when stopped containers use it.
+    #[serde(default)]
+    force: bool,
+    /// Keep untagged parent images.
+    #[serde(default)]
+    no_prune: bool,
+}
+
+/// Untags an image and deletes it once no tag is left. `image` is an image id
+/// or a reference; references containing `/` must be sent URL-encoded.
+#[utoipa::path(
+    delete,
+    path = "/api/images/{image}",
+    tag = "images",
+    params(("image" = String, Path, description = "Image id or reference, URL-encoded"), AuthParams, RemoveImageQuery),
+    responses((status = 200, description = "Untagged and deleted images", body = Object), (status = 202, description = "Held for approval", body = Object))
+)]
+pub async fn remove_image(
+    Path(image): Path<String>,
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    auth: AuthParams,
+    ValidQuery(query): ValidQuery<RemoveImageQuery>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = Validator::new()
+        .image_ref("image", &image)
+        .reject(&request_id)
+    {
+        return response;
+    }
+
+    let kind = node_command::Kind::RemoveImage(RemoveImage {
+        request_id: request_id.clone(),
+        image: image.clone(),
+        force: query.force,
+        no_prune: query.no_prune,
+    });
+    match send(
+        &server_tx,
+        &pending,
+        &auth,
+        &request_id,
+        RequestType::RemoveImage,
+        kind,
+        REMOVE_IMAGE_TIMEOUT,
+    )
+    .await
+    {
+        Ok(response) => {
+            let body = json!({
+                "id": request_id,
+                "image": image,
+                "result": removed(response),
+            });
+            (StatusCode::OK, Json(body)).into_response()
+        }
+        Err(response) => response,
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PruneImagesQuery {
+    /// Every image no container uses, not only dangling ones.
+    #[serde(default)]
+    all: bool,
+    /// Only images created before this: unix seconds, RFC 3339 or a duration
+    /// such as `24h`.
+    until: Option<String>,
+}
+
+/// Deletes unused images and reports the space reclaimed.
+#[utoipa::path(
+    post,
+    path = "/api/images/prune",
+    tag = "images",
+    params(AuthParams, PruneImagesQuery),
+    responses((status = 200, description = "Deleted images and space reclaimed", body = Object), (status = 202, description = "Held for approval", body = Object))
+)]
+pub async fn prune_images(
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    auth: AuthParams,
+    ValidQuery(query): ValidQuery<PruneImagesQuery>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    let mut until = query.until.unwrap_or_default();
+    // Nodes take unix seconds or durations
+    if let Some(time) = Timestamp::parse(&until) {
+        until = time.0.to_string();
+    }
+    let digits = until.trim_end_matches(['s', 'm', 'h']);
+    if let Some(response) = Validator::new()
+        .check(
+            until.is_empty()
+                || (!digits.is_empty()
+                    && digits.len() + 1 >= until.len()
+                    && digits.chars().all(|c| c.is_ascii_digit())),
+            "until",
+            "must be unix seconds, RFC 3339 or a duration such as 30m or 24h",
+        )
+        .reject(&request_id)
+    {
+        return response;
+    }
+
+    let kind = node_command::Kind::PruneImages(PruneImages {
+        request_id: request_id.clone(),
+        all: query.all,
+        until,
+    });
+    match send(
+        &server_tx,
+        &pending,
+        &auth,
+        &request_id,
+        RequestType::PruneImages,
+        kind,
+        PRUNE_IMAGES_TIMEOUT,
+    )
+    .await
+    {
+        Ok(response) => {
+            let body = json!({
+                "id": request_id,
+                "result": removed(response),
+            });
+            (StatusCode::OK, Json(body)).into_response()
+        }
+        Err(response) => response,
+    }
+}
+
+async fn send(
+    server_tx: &CommandRouter,
+    pending: &PendingResponses,
+    auth: &AuthParams,
+    request_id: &str,
+    request_type: RequestType,
+    kind: node_command::Kind,
+    timeout: Duration,
+) -> Result<Option<node_response::Kind>, Response> {
+    let envelope = Envelope {
+        payload: Some(Payload::NodeCommand(NodeCommand { kind: Some(kind) })),
+        ..Default::default()
+    };
+    match request_node(
+        server_tx,
+        pending,
+        auth,
+        request_id,
+        request_type as i32,
+        envelope,
+        timeout,
+    )
+    .await
+    {
+        Ok(response) => Ok(match response.payload {
+            Some(Payload::NodeResponse(node_resp)) => node_resp.kind,
+            _ => None,
+        }),
+        Err(e) => Err(e.into_response(request_id)),
+    }
+}
+
+fn removed(response: Option<node_response::Kind>) -> Option<ImagesRemoved> {
+    match response {
+        Some(node_response::Kind::ImagesRemoved(removed)) => Some(removed),
+        _ => None,
+    }
+}
@@ -1,27 +1,264 @@
+pub mod access_log;
+pub mod admin;
+pub mod annotations;
+pub mod approvals;
+pub mod autostart;
+pub mod bearer_tokens;
+pub mod broadcasts;
+pub mod cleanup;
+pub mod cluster_summary;
+pub mod coalescing;
 pub mod container_actions;
+pub mod container_archive;
+pub mod container_graph;
 pub mod container_logs;
+pub mod container_stats;
 pub mod container_status;
+pub mod create_container;
+pub mod desired_state;
+pub mod disk_usage;
+pub mod event_history;
+pub mod favorites;
+pub mod freeze_windows;
 pub mod get_containers;
+pub mod gitops;
+pub mod host_scripts;
+pub mod image_build;
+pub mod image_history;
+pub mod image_manifest;
+pub mod images;
+pub mod liveness;
+pub mod log_export;
+pub mod log_forwarding;
+pub mod migrate;
+pub mod networks;
+pub mod node_groups;
+pub mod node_metrics;
+pub mod node_request;
+pub mod node_stream;
+pub mod nodes;
+pub mod openapi;
+pub mod pending_limit;
+pub mod placements;
+pub mod rate_limit;
+pub mod recordings;
+pub mod reports;
+pub mod request_trace;
+pub mod response_cache;
 pub mod rest_server;
+pub mod secrets;
+pub mod sessions;
+pub mod system_disk;
+pub mod system_info;
+pub mod templates;
+pub mod tenant_quota;
+pub mod tenants;
+pub mod timeline;
+pub mod timestamps;
+pub mod top_containers;
+pub mod trash;
+pub mod usage;
+pub mod validation;
+pub mod ws_limit;
 
-pub use rest_server::build_rest_router;
+pub use access_log::{AccessLog, AccessLogConfig, AccessLogFormat, log_access};
+pub use admin::AdminToken;
+pub use approvals::ApprovalPolicy;
+pub use bearer_tokens::{BearerAuth, TokenStore, resolve_bearer_token};
+pub use desired_state::DesiredStateReconciler;
+pub use gitops::GitOpsReconciler;
+pub use openapi::{ApiDoc, openapi_routes};
+pub use pending_limit::PendingLimit;
+pub use rate_limit::RateLimit;
+pub use reports::ReportScheduler;
+pub use request_trace::trace_requests;
+pub use response_cache::ResponseCacheConfig;
+pub use rest_server::{RestContext, build_rest_router};
+pub use sessions::{SessionAuth, SessionStore, resolve_session_cookie, session_routes};
+pub use tenant_quota::{TenantUsage, TenantWsSlot};
+pub use tenants::{TenantAccess, enforce_tenant_access};
+pub use ws_limit::{WsLimit, WsLimiter, limit_ws_connections};
 
+use std::collections::HashMap;
+
+use axum::{
+    Json,
+    extract::{FromRequestParts, Query, Request},
+    http::{StatusCode, request::Parts},
+    response::{IntoResponse, Response},
+};
+use lib_coordinator_core::{NodeCapability, NodeChannels, NodeStateCache, verify_node};
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::validation::ValidQuery;
 
-#[derive(Deserialize)]
+#[derive(Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct AuthParams {
+    /// Filled in from the bearer token or session when one is sent.
+    #[param(required = false)]
     pub node_id: String,
+    /// Not needed with a bearer token or session.
+    #[param(required = false)]
     pub password: String,
 }
 
-#[derive(Deserialize, Serialize)]
+/// Auth for node-scoped routes (`/api/nodes/{node_id}/...`) where the node id is in the path.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct NodePasswordParams {
+    /// Not needed with a bearer token or session.
+    #[param(required = false)]
+    pub password: String,
+}
+
+impl NodePasswordParams {
+    pub fn for_node(self, node_id: String) -> AuthParams {
+        AuthParams {
+            node_id,
+            password: self.password,
+        }
+    }
+}
+
+/// The node a bearer token or session cookie was issued for, set on the
+/// request by those layers instead of a password in the query.
+#[derive(Debug, Clone)]
+pub struct VerifiedNode(pub String);
+
+impl VerifiedNode {
+    /// What stands in for the node's password, which the coordinator does not
+    /// keep: its internal token, or nothing once the node is gone.
+    pub fn password(&self, nodes: &NodeChannels) -> String {
+        nodes
+            .get(&self.0)
+            .map(|node| node.internal_token().to_string())
+            .unwrap_or_default()
+    }
+}
+
+// The node credentials vouched for by a token or session, else the query's
+impl<S: Send + Sync> FromRequestParts<S> for AuthParams {
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(verified) = parts.extensions.get::<VerifiedNode>() {
+            let password = parts
+                .extensions
+                .get::<NodeChannels>()
+                .map(|nodes| verified.password(nodes))
+                .unwrap_or_default();
+            return Ok(AuthParams {
+                node_id: verified.0.clone(),
+                password,
+            });
+        }
+        let ValidQuery(auth) = ValidQuery::<AuthParams>::from_request_parts(parts, state).await?;
+        Ok(auth)
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for NodePasswordParams {
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(verified) = parts.extensions.get::<VerifiedNode>() {
+            let password = parts
+                .extensions
+                .get::<NodeChannels>()
+                .map(|nodes| verified.password(nodes))
+                .unwrap_or_default();
+            return Ok(NodePasswordParams { password });
+        }
+        let ValidQuery(params) =
+            ValidQuery::<NodePasswordParams>::from_request_parts(parts, state).await?;
+        Ok(params)
+    }
+}
+
+/// The node password of a request, for the middlewares that run before the
+/// handlers: the stand-in when a token or session vouched for it, else the
+/// `password` parameter.
+pub(crate) fn request_password(request: &Request, nodes: &NodeChannels) -> Option<String> {
+    if let Some(verified) = request.extensions().get::<VerifiedNode>() {
+        return Some(verified.password(nodes));
+    }
+    Query::<HashMap<String, String>>::try_from_uri(request.uri())
+        .ok()
+        .and_then(|Query(mut params)| params.remove("password"))
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
 struct ApiErrorDetail {
     message: String,
     detail: String,
 }
 
-#[derive(Deserialize, Serialize)]
+/// The body of every error response.
+#[derive(Deserialize, Serialize, ToSchema)]
 struct ApiError {
     req_id: String,
     error: ApiErrorDetail,
 }
+
+/// Coordinator-only routes never reach the node, so check the credentials against connected nodes.
+pub(crate) async fn reject_unknown_node(
+    nodes: &NodeChannels,
+    auth: &AuthParams,
+    request_id: &str,
+) -> Option<Response> {
+    if verify_node(nodes, &auth.node_id, &auth.password).await {
+        return None;
+    }
+    Some(error_response(
+        StatusCode::UNAUTHORIZED,
+        request_id,
+        "Unknown node",
+        "Node is not connected or the password is wrong".to_string(),
+    ))
+}
+
+/// 501 when the node advertised that its platform cannot do `capability`, so
+/// the request fails right away instead of waiting for the node. Only answered
+/// for valid credentials, which keeps node platforms private.
+pub async fn reject_unsupported(
+    nodes: &NodeChannels,
+    node_states: &NodeStateCache,
+    auth: &AuthParams,
+    capability: NodeCapability,
+    request_id: &str,
+) -> Option<Response> {
+    if !verify_node(nodes, &auth.node_id, &auth.password).await {
+        return None;
+    }
+    let node_id = &auth.node_id;
+    let platform = node_states.lacks(node_id, capability)?;
+    Some(error_response(
+        StatusCode::NOT_IMPLEMENTED,
+        request_id,
+        "Not supported by node",
+        format!(
+            "Node {} ({}) does not support {}",
+            node_id,
+            platform,
+            capability.name()
+        ),
+    ))
+}
+
+pub(crate) fn error_response(
+    status: StatusCode,
+    request_id: &str,
+    message: &str,
+    detail: String,
+) -> Response {
+    let err = ApiError {
+        req_id: request_id.to_string(),
+        error: ApiErrorDetail {
+            message: message.to_string(),
+            detail,
+        },
+    };
+    (status, Json(err)).into_response()
+}
@@ -0,0 +1,68 @@
+use axum::{
+    extract::{Request, State},
+    http::{HeaderValue, StatusCode, header},
+    middleware::Next,
+    response::Response,
+};
+use lib_coordinator_core::{NodeChannels, NodeStateCache};
+use uuid::Uuid;
+
+use crate::rate_limit::target_node_id;
+use crate::{AuthParams, error_response, reject_unknown_node, request_password};
+
+// About one heartbeat, after which the node has either spoken up or not
+const RETRY_AFTER_SECS: u64 = 5;
+
+/// Connected nodes and their heartbeats, to tell whether a node can answer.
+#[derive(Clone)]
+pub struct NodeLiveness {
+    nodes: NodeChannels,
+    node_states: NodeStateCache,
+}
+
+impl NodeLiveness {
+    pub fn new(nodes: NodeChannels, node_states: NodeStateCache) -> Self {
+        Self { nodes, node_states }
+    }
+}
+
+/// Middleware answering requests for a node that cannot answer them right
+/// away: `401` when no node with the credentials is connected, as the routes
+/// that never reach the node do, and `503` with `Retry-After` when the node
+/// is connected but missed its heartbeats. Requests without a node password
+/// pass.
+pub async fn require_live_node(
+    State(liveness): State<NodeLiveness>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(node_id) = target_node_id(&request) else {
+        return next.run(request).await;
+    };
+    let Some(password) = request_password(&request, &liveness.nodes) else {
+        return next.run(request).await;
+    };
+
+    let request_id = Uuid::new_v4().to_string();
+    let auth = AuthParams { node_id, password };
+    if let Some(response) = reject_unknown_node(&liveness.nodes, &auth, &request_id).await {
+        return response;
+    }
+    let Some(silent) = liveness.node_states.unresponsive_for(&auth.node_id) else {
+        return next.run(request).await;
+    };
+    let mut response = error_response(
+        StatusCode::SERVICE_UNAVAILABLE,
+        &request_id,
+        "Node unresponsive",
+        format!(
+            "No heartbeat from node {} for {} s",
+            auth.node_id,
+            silent.as_secs()
+        ),
+    );
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from(RETRY_AFTER_SECS));
+    response
+}
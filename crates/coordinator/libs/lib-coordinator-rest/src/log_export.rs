@@ -0,0 +1,365 @@
+use std::time::{Duration, UNIX_EPOCH};
+
+use axum::{
+    Extension, Json,
+    extract::Path,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use futures_util::{StreamExt, stream};
+use lib_coordinator_core::{
+    CommandRouter, ExportCursor, LogExportCursors, NodeChannels, NodeStateCache, PendingResponses,
+};
+use proto::generated::{
+    Envelope, GetContainerLogs, LogPosition, LogStreams, NodeCommand, RequestType,
+    envelope::Payload, node_command,
+};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::warn;
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::admin::{AdminParams, AdminToken};
+use crate::container_logs::timestamp_nanos;
+use crate::desired_state::node_auth;
+use crate::error_response;
+use crate::node_request::request_node;
+use crate::validation::{ValidQuery, Validator};
+
+const EXPORT_LOGS_TIMEOUT: Duration = Duration::from_secs(10);
+// Containers read at the same time during one export
+const EXPORT_CONCURRENCY: usize = 8;
+const MAX_EXPORT_TAIL: i32 = 10_000;
+const MAX_EXPORT_BYTES: usize = 4 * 1024 * 1024;
+const MIN_EXPORT_BYTES: usize = 1024;
+const DEFAULT_EXPORT_BYTES: usize = 1024 * 1024;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LogExportQuery {
+    /// Name of the reading agent; each consumer has its own cursors.
+    consumer: Option<String>,
+    /// Comma-separated node ids; every connected node when unset.
+    nodes: Option<String>,
+    /// Comma-separated container names, a trailing `*` matches a prefix.
+    containers: Option<String>,
+    /// Lines returned for a container the consumer has not read before.
+    tail: Option<i32>,
+    /// Log bytes read per container in one export.
+    max_bytes: Option<usize>,
+}
+
+struct Target {
+    node_id: String,
+    container: String,
+    cursor: Option<ExportCursor>,
+}
+
+/// Log lines written since the consumer's previous export, as newline-delimited
+/// JSON records labelled with their node and container. Meant to be polled by
+/// Vector's `http_client` source or Fluent Bit's `exec` input.
+#[allow(clippy::too_many_arguments)] // Axum extractors, one per piece of shared state
+#[utoipa::path(
+    get,
+    path = "/api/logs/export",
+    tag = "logs",
+    params(AdminParams, LogExportQuery),
+    responses((status = 200, description = "Newline-delimited JSON log records", content_type = "application/x-ndjson"))
+)]
+pub async fn export_logs(
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(node_states): Extension<NodeStateCache>,
+    Extension(cursors): Extension<LogExportCursors>,
+    Extension(admin): Extension<AdminToken>,
+    ValidQuery(params): ValidQuery<AdminParams>,
+    ValidQuery(query): ValidQuery<LogExportQuery>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = admin.reject(&params, &request_id) {
+        return response;
+    }
+    let consumer = query.consumer.clone().unwrap_or_default();
+    let patterns = split_list(query.containers.as_deref().unwrap_or("*"));
+    let node_filter = query.nodes.as_deref().map(split_list);
+    let mut validator = Validator::new();
+    validator
+        .secret_name("consumer", &consumer)
+        .range("tail", query.tail, 0, MAX_EXPORT_TAIL)
+        .range(
+            "max_bytes",
+            query.max_bytes,
+            MIN_EXPORT_BYTES,
+            MAX_EXPORT_BYTES,
+        )
+        .check(!patterns.is_empty(), "containers", "must not be empty");
+    for pattern in &patterns {
+        match pattern.strip_suffix('*') {
+            Some("") => {}
+            Some(prefix) => {
+                validator.container_id("containers", prefix);
+            }
+            None => {
+                validator.container_id("containers", pattern);
+            }
+        }
+    }
+    if let Some(response) = validator.reject(&request_id) {
+        return response;
+    }
+    let tail = query.tail.unwrap_or(100);
+    let max_bytes = query.max_bytes.unwrap_or(DEFAULT_EXPORT_BYTES);
+
+    let mut targets = Vec::new();
+    for (node_id, state) in node_states.snapshot() {
+        if !state.online
+            || node_filter
+                .as_ref()
+                .is_some_and(|filter| !filter.contains(&node_id.as_str()))
+        {
+            continue;
+        }
+        for container in &state.containers {
+            let name = &container.container_id;
+            if !patterns.iter().any(|pattern| matches(pattern, name)) {
+                continue;
+            }
+            let cursor = cursors.get(&consumer, &node_id, name);
+            // Stopped containers only have new lines if they ran since the last export
+            let active = container.status == "running"
+                || cursor.is_some_and(|cursor| {
+                    container.finished_at.saturating_mul(1_000_000_000) > cursor.time_nanos
+                });
+            if active {
+                targets.push(Target {
+                    node_id: node_id.clone(),
+                    container: name.clone(),
+                    cursor,
+                });
+            }
+        }
+    }
+
+    let exports: Vec<_> = stream::iter(targets)
+        .map(|target| read_new_lines(&server_tx, &pending, &nodes, target, tail, max_bytes))
+        .buffer_unordered(EXPORT_CONCURRENCY)
+        .collect()
+        .await;
+
+    let mut body = String::new();
+    let mut records = 0;
+    let mut advanced = Vec::new();
+    for (target, lines, cursor) in exports.into_iter().flatten() {
+        for (time_nanos, line) in &lines {
+            let record = json!({
+                "timestamp": chrono::DateTime::from_timestamp_nanos(*time_nanos).to_rfc3339(),
+                "message": line,
+                "node_id": target.node_id,
+                "container": target.container,
+                "source": "docklord",
+            });
+            body.push_str(&record.to_string());
+            body.push('\n');
+            records += 1;
+        }
+        if let Some(cursor) = cursor {
+            advanced.push((target.node_id, target.container, cursor));
+        }
+    }
+    if let Err(e) = cursors.advance(&consumer, advanced) {
+        warn!("Failed to store log export cursors for {}: {}", consumer, e);
+    }
+
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "application/x-ndjson".to_string()),
+            (
+                header::HeaderName::from_static("x-docklord-records"),
+                records.to_string(),
+            ),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+/// Consumers with how many containers each has read from.
+#[utoipa::path(
+    get,
+    path = "/api/logs/consumers",
+    tag = "logs",
+    params(AdminParams),
+    responses((status = 200, description = "Consumers and their cursors", body = Object))
+)]
+pub async fn list_log_consumers(
+    Extension(cursors): Extension<LogExportCursors>,
+    Extension(admin): Extension<AdminToken>,
+    ValidQuery(params): ValidQuery<AdminParams>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = admin.reject(&params, &request_id) {
+        return response;
+    }
+
+    let consumers: Vec<_> = cursors
+        .consumers()
+        .into_iter()
+        .map(|(consumer, containers)| json!({ "consumer": consumer, "containers": containers }))
+        .collect();
+    let body = json!({
+        "req_id": request_id,
+        "consumers": consumers,
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+/// Forgets a consumer's cursors so its next export starts from the tail again.
+#[utoipa::path(
+    delete,
+    path = "/api/logs/consumers/{consumer}",
+    tag = "logs",
+    params(("consumer" = String, Path, description = "Consumer name"), AdminParams),
+    responses((status = 200, description = "The consumer's cursors are gone", body = Object))
+)]
+pub async fn reset_log_consumer(
+    Path(consumer): Path<String>,
+    Extension(cursors): Extension<LogExportCursors>,
+    Extension(admin): Extension<AdminToken>,
+    ValidQuery(params): ValidQuery<AdminParams>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = admin.reject(&params, &request_id) {
+        return response;
+    }
+
+    match cursors.reset(&consumer) {
+        Ok(true) => {
+            let body = json!({
+                "req_id": request_id,
+                "consumer": consumer,
+            });
+            (StatusCode::OK, Json(body)).into_response()
+        }
+        Ok(false) => error_response(
+            StatusCode::NOT_FOUND,
+            &request_id,
+            "Consumer not found",
+            format!("{} has not exported any logs", consumer),
+        ),
+        Err(e) => error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            &request_id,
+            "Failed to reset consumer",
+            e.to_string(),
+        ),
+    }
+}
+
+/// Reads the container's lines after its cursor. Returns them with the cursor
+/// after the last one, or `None` when the node could not be asked.
+async fn read_new_lines(
+    server_tx: &CommandRouter,
+    pending: &PendingResponses,
+    nodes: &NodeChannels,
+    target: Target,
+    tail: i32,
+    max_bytes: usize,
+) -> Option<(Target, Vec<(i64, String)>, Option<ExportCursor>)> {
+    let auth = node_auth(nodes, &target.node_id)?;
+    let request_id = Uuid::new_v4().to_string();
+    // Re-read from the cursor's second; the node skips what was already exported
+    let (tail, since) = match target.cursor {
+        Some(cursor) => (-1, cursor.time_nanos.div_euclid(1_000_000_000).to_string()),
+        None => (tail, String::new()),
+    };
+    let envelope = Envelope {
+        payload: Some(Payload::NodeCommand(NodeCommand {
+            kind: Some(node_command::Kind::GetContainerLogs(GetContainerLogs {
+                request_id: request_id.clone(),
+                container_id: target.container.clone(),
+                tail,
+                follow: false,
+                since,
+                timestamps: true,
+                max_bytes: max_bytes as u64,
+                fresh: false,
+                streams: LogStreams::Both.into(),
+                after: target.cursor.map(|cursor| LogPosition {
+                    time_nanos: cursor.time_nanos,
+                    line: cursor.line.try_into().unwrap_or(u32::MAX),
+                }),
+            })),
+        })),
+        ..Default::default()
+    };
+    let response = match request_node(
+        server_tx,
+        pending,
+        &auth,
+        &request_id,
+        RequestType::GetContainerLogs as i32,
+        envelope,
+        EXPORT_LOGS_TIMEOUT,
+    )
+    .await
+    {
+        Ok(response) => response,
+        Err(e) => {
+            warn!(
+                "Failed to read logs of {} on node {}: {}",
+                target.container, target.node_id, e
+            );
+            return None;
+        }
+    };
+    let Some(Payload::NodeResponse(node_resp)) = response.payload else {
+        return None;
+    };
+    let Some(proto::generated::node_response::Kind::ContainerLogs(logs)) = node_resp.kind else {
+        return None;
+    };
+
+    let mut lines = Vec::new();
+    // Lines of the cursor's timestamp continue its count
+    let mut last = target.cursor;
+    for line in logs.lines {
+        let time_nanos = timestamp_nanos(&line.timestamp)
+            .unwrap_or_else(|| last.map_or(0, |last| last.time_nanos));
+        let position = ExportCursor {
+            time_nanos,
+            line: match last {
+                Some(last) if last.time_nanos == time_nanos => last.line + 1,
+                _ => 1,
+            },
+        };
+        lines.push((time_nanos, line.text));
+        last = Some(position);
+    }
+    // A container without lines yet starts from now, so its first lines are not
+    // mistaken for an old tail
+    let cursor = last.or_else(|| {
+        target.cursor.is_none().then(|| ExportCursor {
+            time_nanos: UNIX_EPOCH.elapsed().map_or(0, |now| now.as_nanos() as i64),
+            line: 0,
+        })
+    });
+    Some((target, lines, cursor))
+}
+
+fn split_list(value: &str) -> Vec<&str> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+fn matches(pattern: &str, name: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => name == pattern,
+    }
+}
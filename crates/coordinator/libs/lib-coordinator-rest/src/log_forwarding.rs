@@ -0,0 +1,329 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use axum::{
+    Extension, Json,
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use lib_coordinator_core::{
+    CommandRouter, LogForwardConfig, LogForwardingStore, LogSinkKind, NodeChannels,
+    PendingResponses,
+};
+use proto::generated::{
+    Envelope, GetLogForwarding, LogForwardingStatus, NodeCommand, RequestType, envelope::Payload,
+    node_command,
+};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tracing::error;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::node_request::request_node;
+use crate::validation::{ValidJson, Validator};
+use crate::{AuthParams, NodePasswordParams, error_response, reject_unknown_node};
+
+const LOG_FORWARDING_TIMEOUT: Duration = Duration::from_secs(15);
+const MAX_LOG_FORWARDS: usize = 20;
+const MAX_FORWARD_CONTAINERS: usize = 100;
+const MAX_FORWARD_LABELS: usize = 15;
+
+#[derive(Deserialize, ToSchema)]
+pub struct LogForwardingBody {
+    forwards: Vec<LogForwardConfig>,
+}
+
+/// The node's stored forwards and what the node is doing with them.
+#[utoipa::path(
+    get,
+    path = "/api/nodes/{node_id}/log-forwarding",
+    tag = "logs",
+    params(("node_id" = String, Path, description = "Node id"), NodePasswordParams),
+    responses((status = 200, description = "Stored forwards and their status on the node", body = Object))
+)]
+pub async fn get_log_forwarding(
+    Path(node_id): Path<String>,
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(log_forwarding): Extension<LogForwardingStore>,
+    query: NodePasswordParams,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    let auth = query.for_node(node_id);
+    if let Some(response) = reject_unknown_node(&nodes, &auth, &request_id).await {
+        return response;
+    }
+
+    let kind = node_command::Kind::GetLogForwarding(GetLogForwarding {
+        request_id: request_id.clone(),
+    });
+    match send(
+        &server_tx,
+        &pending,
+        &auth,
+        &request_id,
+        RequestType::GetLogForwarding,
+        kind,
+    )
+    .await
+    {
+        Ok(status) => {
+            let body = json!({
+                "id": request_id,
+                "node_id": auth.node_id,
+                "forwards": log_forwarding.get(&auth.node_id),
+                "status": status,
+            });
+            (StatusCode::OK, Json(body)).into_response()
+        }
+        Err(response) => response,
+    }
+}
+
+/// Replaces the node's forwards. They are stored only once the node accepted them.
+#[utoipa::path(
+    put,
+    path = "/api/nodes/{node_id}/log-forwarding",
+    tag = "logs",
+    params(("node_id" = String, Path, description = "Node id"), NodePasswordParams),
+    request_body = LogForwardingBody,
+    responses((status = 200, description = "The forwards the node accepted", body = Object))
+)]
+pub async fn put_log_forwarding(
+    Path(node_id): Path<String>,
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(log_forwarding): Extension<LogForwardingStore>,
+    query: NodePasswordParams,
+    ValidJson(body): ValidJson<LogForwardingBody>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = validate(&body.forwards).reject(&request_id) {
+        return response;
+    }
+    let auth = query.for_node(node_id);
+    if let Some(response) = reject_unknown_node(&nodes, &auth, &request_id).await {
+        return response;
+    }
+
+    configure(
+        &server_tx,
+        &pending,
+        &log_forwarding,
+        &auth,
+        &request_id,
+        body.forwards,
+    )
+    .await
+}
+
+/// Stops all forwarding on the node.
+#[utoipa::path(
+    delete,
+    path = "/api/nodes/{node_id}/log-forwarding",
+    tag = "logs",
+    params(("node_id" = String, Path, description = "Node id"), NodePasswordParams),
+    responses((status = 200, description = "Forwarding stopped", body = Object))
+)]
+pub async fn delete_log_forwarding(
+    Path(node_id): Path<String>,
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(log_forwarding): Extension<LogForwardingStore>,
+    query: NodePasswordParams,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    let auth = query.for_node(node_id);
+    if let Some(response) = reject_unknown_node(&nodes, &auth, &request_id).await {
+        return response;
+    }
+
+    configure(
+        &server_tx,
+        &pending,
+        &log_forwarding,
+        &auth,
+        &request_id,
+        Vec::new(),
+    )
+    .await
+}
+
+fn validate(forwards: &[LogForwardConfig]) -> Validator {
+    let mut validator = Validator::new();
+    validator.check(
+        forwards.len() <= MAX_LOG_FORWARDS,
+        "forwards",
+        format!("at most {} forwards", MAX_LOG_FORWARDS),
+    );
+    let mut names = HashSet::new();
+    for (i, forward) in forwards.iter().enumerate() {
+        let field = format!("forwards[{}]", i);
+        validator
+            .secret_name(&format!("{}.name", field), &forward.name)
+            .check(
+                names.insert(forward.name.as_str()),
+                &format!("{}.name", field),
+                format!("{} is used twice", forward.name),
+            )
+            .check(
+                !forward.containers.is_empty()
+                    && forward.containers.len() <= MAX_FORWARD_CONTAINERS,
+                &format!("{}.containers", field),
+                format!("between 1 and {} container names", MAX_FORWARD_CONTAINERS),
+            )
+            .check(
+                forward.labels.len() <= MAX_FORWARD_LABELS,
+                &format!("{}.labels", field),
+                format!("at most {} labels", MAX_FORWARD_LABELS),
+            );
+        for (j, pattern) in forward.containers.iter().enumerate() {
+            let field = format!("{}.containers[{}]", field, j);
+            match pattern.strip_suffix('*') {
+                // A lone '*' forwards every container
+                Some("") => {}
+                Some(prefix) => {
+                    validator.container_id(&field, prefix);
+                }
+                None => {
+                    validator.container_id(&field, pattern);
+                }
+            }
+        }
+        for key in forward.labels.keys() {
+            // Loki's label name syntax, also safe as syslog SD names and JSON keys
+            let ok = key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                && key.len() <= 32;
+            validator.check(
+                ok,
+                &format!("{}.labels.{}", field, key),
+                "label names are 1-32 letters, digits or '_' and do not start with a digit",
+            );
+        }
+        let target_ok = match forward.sink {
+            LogSinkKind::Loki => forward.target.starts_with("http://"),
+            LogSinkKind::Syslog => ["udp://", "tcp://"].iter().any(|scheme| {
+                forward.target.len() > scheme.len() && forward.target.starts_with(scheme)
+            }),
+            LogSinkKind::File => forward.target.starts_with('/'),
+        };
+        validator.check(
+            target_ok,
+            &format!("{}.target", field),
+            match forward.sink {
+                LogSinkKind::Loki => "must be an http:// push URL",
+                LogSinkKind::Syslog => "must be udp://host:port or tcp://host:port",
+                LogSinkKind::File => "must be an absolute path on the node's host",
+            },
+        );
+    }
+    validator
+}
+
+async fn configure(
+    server_tx: &CommandRouter,
+    pending: &PendingResponses,
+    log_forwarding: &LogForwardingStore,
+    auth: &AuthParams,
+    request_id: &str,
+    forwards: Vec<LogForwardConfig>,
+) -> Response {
+    let kind = node_command::Kind::ConfigureLogForwarding(LogForwardingStore::command(
+        &auth.node_id,
+        request_id,
+        &forwards,
+    ));
+    let status = match send(
+        server_tx,
+        pending,
+        auth,
+        request_id,
+        RequestType::ConfigureLogForwarding,
+        kind,
+    )
+    .await
+    {
+        Ok(status) => status,
+        Err(response) => return response,
+    };
+
+    if let Err(e) = log_forwarding.set(&auth.node_id, forwards.clone()) {
+        error!("Failed to persist log forwarding: {}", e);
+        return error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            request_id,
+            "Failed to store log forwarding",
+            e.to_string(),
+        );
+    }
+    let body = json!({
+        "id": request_id,
+        "node_id": auth.node_id,
+        "forwards": forwards,
+        "status": status,
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+async fn send(
+    server_tx: &CommandRouter,
+    pending: &PendingResponses,
+    auth: &AuthParams,
+    request_id: &str,
+    request_type: RequestType,
+    kind: node_command::Kind,
+) -> Result<Value, Response> {
+    let envelope = Envelope {
+        payload: Some(Payload::NodeCommand(NodeCommand { kind: Some(kind) })),
+        ..Default::default()
+    };
+    match request_node(
+        server_tx,
+        pending,
+        auth,
+        request_id,
+        request_type as i32,
+        envelope,
+        LOG_FORWARDING_TIMEOUT,
+    )
+    .await
+    {
+        Ok(response) => Ok(extract_status_from_response(&response)
+            .map(status_json)
+            .unwrap_or(Value::Null)),
+        Err(e) => Err(e.into_response(request_id)),
+    }
+}
+
+fn extract_status_from_response(response: &Envelope) -> Option<&LogForwardingStatus> {
+    if let Some(proto::generated::envelope::Payload::NodeResponse(node_resp)) = &response.payload
+        && let Some(proto::generated::node_response::Kind::LogForwardingStatus(status)) =
+            &node_resp.kind
+    {
+        return Some(status);
+    }
+    None
+}
+
+fn status_json(status: &LogForwardingStatus) -> Value {
+    status
+        .forwards
+        .iter()
+        .map(|forward| {
+            json!({
+                "name": forward.name,
+                "containers": forward.containers,
+                "lines_forwarded": forward.lines_forwarded,
+                "lines_dropped": forward.lines_dropped,
+                "last_error": (!forward.last_error.is_empty()).then_some(&forward.last_error),
+                "last_error_at": (forward.last_error_at > 0).then_some(forward.last_error_at),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,418 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use axum::{
+    Extension, Json,
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use lib_coordinator_core::{
+    CommandRouter, FreezeStore, NodeChannels, PendingResponses, PendingStreams, TenantStore,
+};
+use proto::generated::{
+    DeleteContainer, Envelope, ExportContainer, ImportContainer, MigrationChunk, NodeCommand,
+    RequestType, StartContainer, StopContainer, envelope::Payload, node_command, node_response,
+};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::{error, info, warn};
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::node_request::{NodeRequestError, node_error_message, request_node};
+use crate::node_stream::open_node_stream;
+use crate::templates::extract_container_created_from_response;
+use crate::tenants::CallerTenant;
+use crate::validation::{ValidQuery, Validator};
+use crate::{AuthParams, error_response, reject_unknown_node};
+
+// Until the source committed the container and sends its config
+const EXPORT_START_TIMEOUT: Duration = Duration::from_secs(660);
+// Between two chunks from the source
+const EXPORT_CHUNK_TIMEOUT: Duration = Duration::from_secs(120);
+// Per relayed chunk; a section's first chunk after the image also covers
+// the target loading it and creating the container
+const IMPORT_CHUNK_TIMEOUT: Duration = Duration::from_secs(600);
+// Stopping the source and starting the target
+const SWITCH_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MigrateQuery {
+    /// Node to move the container to; it must be connected.
+    target_node: String,
+    /// Password of the target node, when it differs from the source's.
+    target_password: Option<String>,
+    /// Also copy the contents of the container's named volumes.
+    #[serde(default)]
+    include_volumes: bool,
+    /// Remove the source container once the target runs.
+    #[serde(default)]
+    remove_source: bool,
+}
+
+/// Moves a container to another node. The source commits the container to
+/// an image, which the coordinator relays chunk by chunk to the target along
+/// with the config and, if asked, the named volumes' contents. Only once the
+/// target container is created is the source stopped and the target started,
+/// so the container is down for about as long as a restart. A target that
+/// fails to start is removed and the source started again.
+#[allow(clippy::too_many_arguments)] // Axum extractors, one per piece of shared state
+#[utoipa::path(
+    post,
+    path = "/api/containers/{container_id}/migrate",
+    tag = "containers",
+    params(("container_id" = String, Path, description = "Container id or name"), AuthParams, MigrateQuery),
+    responses(
+        (status = 201, description = "The container on the target node", body = Object),
+        (status = 423, description = "The target node is frozen", body = Object)
+    )
+)]
+pub async fn migrate_container(
+    Path(container_id): Path<String>,
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    Extension(pending_streams): Extension<PendingStreams>,
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(freeze_windows): Extension<FreezeStore>,
+    Extension(tenants): Extension<TenantStore>,
+    caller: Option<Extension<CallerTenant>>,
+    source: AuthParams,
+    ValidQuery(migrate): ValidQuery<MigrateQuery>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = Validator::new()
+        .container_id("container_id", &container_id)
+        .check(
+            !migrate.target_node.is_empty(),
+            "target_node",
+            "must not be empty",
+        )
+        .check(
+            migrate.target_node != source.node_id,
+            "target_node",
+            "must differ from the container's node",
+        )
+        .reject(&request_id)
+    {
+        return response;
+    }
+    let target = AuthParams {
+        node_id: migrate.target_node.clone(),
+        password: migrate
+            .target_password
+            .clone()
+            .unwrap_or_else(|| source.password.clone()),
+    };
+    if let Some(response) = reject_unknown_node(&nodes, &source, &request_id).await {
+        return response;
+    }
+    if let Some(response) = reject_unknown_node(&nodes, &target, &request_id).await {
+        return response;
+    }
+    // The tenant and freeze middlewares only see the source node
+    let tenant = tenants.owner_of(&target.node_id);
+    if let Some(Extension(CallerTenant(caller))) = &caller
+        && tenant.as_ref() != Some(caller)
+    {
+        info!("Tenant {} denied access to node {}", caller, target.node_id);
+        return error_response(
+            StatusCode::FORBIDDEN,
+            &request_id,
+            "Node not in tenant",
+            format!(
+                "Node {} is not assigned to tenant {}",
+                target.node_id, caller
+            ),
+        );
+    }
+    if let Some(freeze) = freeze_windows.active(&target.node_id, tenant.as_deref(), Utc::now()) {
+        return error_response(
+            StatusCode::LOCKED,
+            &request_id,
+            "Change freeze",
+            format!(
+                "Node {} is frozen by window '{}'",
+                target.node_id, freeze.name
+            ),
+        );
+    }
+
+    info!(
+        "Migrating container {} from node {} to node {}",
+        container_id, source.node_id, target.node_id
+    );
+    let envelope = Envelope {
+        payload: Some(Payload::NodeCommand(NodeCommand {
+            kind: Some(node_command::Kind::ExportContainer(ExportContainer {
+                request_id: request_id.clone(),
+                container_id: container_id.clone(),
+                include_volumes: migrate.include_volumes,
+            })),
+        })),
+        ..Default::default()
+    };
+    let mut export = match open_node_stream(
+        &server_tx,
+        &pending_streams,
+        &source,
+        &request_id,
+        RequestType::ExportContainer as i32,
+        envelope,
+    ) {
+        Ok(export) => export,
+        Err(e) => {
+            error!("Failed to send server request: {}", e);
+            return NodeRequestError::Send.into_response(&request_id);
+        }
+    };
+
+    // Each chunk is its own request, so the target's acknowledgement paces the export
+    let upload_id = Uuid::new_v4().to_string();
+    let mut name = String::new();
+    let mut running = false;
+    let mut bytes: BTreeMap<String, u64> = BTreeMap::new();
+    let mut timeout = EXPORT_START_TIMEOUT;
+    let created = loop {
+        let chunk = match tokio::time::timeout(timeout, export.recv()).await {
+            Ok(Some(envelope)) => migration_chunk(envelope),
+            Ok(None) => return NodeRequestError::ChannelClosed.into_response(&request_id),
+            Err(_) => return NodeRequestError::Timeout.into_response(&request_id),
+        };
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(message) => {
+                return migration_failed(&request_id, &source.node_id, message);
+            }
+        };
+        timeout = EXPORT_CHUNK_TIMEOUT;
+        if !chunk.name.is_empty() {
+            name.clone_from(&chunk.name);
+            running = chunk.running;
+        }
+        if !chunk.section.is_empty() {
+            *bytes.entry(chunk.section.clone()).or_default() += chunk.data.len() as u64;
+        }
+
+        let chunk_request_id = Uuid::new_v4().to_string();
+        let envelope = Envelope {
+            payload: Some(Payload::NodeCommand(NodeCommand {
+                kind: Some(node_command::Kind::ImportContainer(ImportContainer {
+                    request_id: chunk_request_id.clone(),
+                    upload_id: upload_id.clone(),
+                    name: name.clone(),
+                    section: chunk.section,
+                    path: chunk.path,
+                    data: chunk.data,
+                    done: chunk.done,
+                })),
+            })),
+            ..Default::default()
+        };
+        let response = match request_node(
+            &server_tx,
+            &pending,
+            &target,
+            &chunk_request_id,
+            RequestType::ImportContainer as i32,
+            envelope,
+            IMPORT_CHUNK_TIMEOUT,
+        )
+        .await
+        {
+            Ok(response) => response,
+            Err(NodeRequestError::Node(_, message)) => {
+                return migration_failed(&request_id, &target.node_id, message);
+            }
+            Err(e) => return e.into_response(&request_id),
+        };
+        if chunk.done {
+            break extract_container_created_from_response(&response);
+        }
+    };
+    let target_id = created
+        .as_ref()
+        .and_then(|created| created["container_id"].as_str())
+        .unwrap_or_default()
+        .to_string();
+
+    let mut downtime_ms = None;
+    if running {
+        let stopped_at = Instant::now();
+        if let Err(e) = container_command(
+            &server_tx,
+            &pending,
+            &source,
+            RequestType::StopContainer,
+            |request_id| {
+                node_command::Kind::StopContainer(StopContainer {
+                    request_id,
+                    container_id: container_id.clone(),
+                })
+            },
+        )
+        .await
+        {
+            remove_target(&server_tx, &pending, &target, &target_id).await;
+            return e.into_response(&request_id);
+        }
+        if let Err(e) = container_command(
+            &server_tx,
+            &pending,
+            &target,
+            RequestType::StartContainer,
+            |request_id| {
+                node_command::Kind::StartContainer(StartContainer {
+                    request_id,
+                    container_id: target_id.clone(),
+                })
+            },
+        )
+        .await
+        {
+            warn!(
+                "Migrated container {} did not start on node {}, restarting the source",
+                target_id, target.node_id
+            );
+            let _ = container_command(
+                &server_tx,
+                &pending,
+                &source,
+                RequestType::StartContainer,
+                |request_id| {
+                    node_command::Kind::StartContainer(StartContainer {
+                        request_id,
+                        container_id: container_id.clone(),
+                    })
+                },
+            )
+            .await;
+            remove_target(&server_tx, &pending, &target, &target_id).await;
+            return e.into_response(&request_id);
+        }
+        downtime_ms = Some(stopped_at.elapsed().as_millis() as u64);
+    }
+
+    let source_removed = migrate.remove_source
+        && container_command(
+            &server_tx,
+            &pending,
+            &source,
+            RequestType::DeleteContainer,
+            |request_id| {
+                node_command::Kind::DeleteContainer(DeleteContainer {
+                    request_id,
+                    container_id: container_id.clone(),
+                })
+            },
+        )
+        .await
+        .inspect_err(|e| {
+            warn!(
+                "Could not remove migrated container {}: {}",
+                container_id, e
+            )
+        })
+        .is_ok();
+    info!(
+        "Migrated container {} from node {} to {} on node {}",
+        container_id, source.node_id, target_id, target.node_id
+    );
+
+    let body = json!({
+        "id": request_id,
+        "source": {
+            "node_id": source.node_id,
+            "container_id": container_id,
+            "removed": source_removed,
+        },
+        "target": {
+            "node_id": target.node_id,
+            "container": created,
+            "started": running,
+        },
+        "bytes": bytes,
+        "downtime_ms": downtime_ms,
+    });
+    (StatusCode::CREATED, Json(body)).into_response()
+}
+
+// Sends a command answered with a ContainerAction
+async fn container_command(
+    server_tx: &CommandRouter,
+    pending: &PendingResponses,
+    auth: &AuthParams,
+    request_type: RequestType,
+    command: impl FnOnce(String) -> node_command::Kind,
+) -> Result<Envelope, NodeRequestError> {
+    let request_id = Uuid::new_v4().to_string();
+    let envelope = Envelope {
+        payload: Some(Payload::NodeCommand(NodeCommand {
+            kind: Some(command(request_id.clone())),
+        })),
+        ..Default::default()
+    };
+    request_node(
+        server_tx,
+        pending,
+        auth,
+        &request_id,
+        request_type as i32,
+        envelope,
+        SWITCH_TIMEOUT,
+    )
+    .await
+}
+
+// Rolls back the target container after a failed switch
+async fn remove_target(
+    server_tx: &CommandRouter,
+    pending: &PendingResponses,
+    target: &AuthParams,
+    container_id: &str,
+) {
+    let removed = container_command(
+        server_tx,
+        pending,
+        target,
+        RequestType::DeleteContainer,
+        |request_id| {
+            node_command::Kind::DeleteContainer(DeleteContainer {
+                request_id,
+                container_id: container_id.to_string(),
+            })
+        },
+    )
+    .await;
+    if let Err(e) = removed {
+        warn!(
+            "Could not remove container {} from node {}: {}",
+            container_id, target.node_id, e
+        );
+    }
+}
+
+fn migration_failed(request_id: &str, node_id: &str, message: String) -> Response {
+    error_response(
+        StatusCode::BAD_GATEWAY,
+        request_id,
+        "Migration failed",
+        format!("Node {}: {}", node_id, message),
+    )
+}
+
+// The source's chunk, or the error it answered with
+fn migration_chunk(envelope: Envelope) -> Result<MigrationChunk, String> {
+    if let Some(message) = node_error_message(&envelope) {
+        return Err(message);
+    }
+    match envelope.payload {
+        Some(Payload::NodeResponse(response)) => match response.kind {
+            Some(node_response::Kind::MigrationChunk(chunk)) => Ok(chunk),
+            _ => Err("unexpected response from node".to_string()),
+        },
+        _ => Err("unexpected response from node".to_string()),
+    }
+}
@@ -0,0 +1,382 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::Duration;
+
+use axum::{
+    Extension, Json,
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use lib_coordinator_core::{CommandRouter, PendingResponses};
+use proto::generated::{
+    CreateNetwork, Envelope, InspectNetwork, ListNetworks, NetworkInfo, NodeCommand, RemoveNetwork,
+    RequestType, envelope::Payload, node_command, node_response,
+};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::AuthParams;
+use crate::node_request::request_node;
+use crate::validation::{ValidJson, ValidQuery, Validator};
+
+const LIST_NETWORKS_TIMEOUT: Duration = Duration::from_secs(20);
+const INSPECT_NETWORK_TIMEOUT: Duration = Duration::from_secs(15);
+const CHANGE_NETWORK_TIMEOUT: Duration = Duration::from_secs(40);
+const MAX_NAME_FILTER_LEN: usize = 255;
+const MAX_DRIVER_LEN: usize = 64;
+const MAX_NETWORK_LABELS: usize = 64;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListNetworksQuery {
+    /// Only networks whose name contains this.
+    name: Option<String>,
+}
+
+/// Networks on the node with the containers attached to each, by name.
+#[utoipa::path(
+    get,
+    path = "/api/networks",
+    tag = "networks",
+    params(AuthParams, ListNetworksQuery),
+    responses((status = 200, description = "The node's networks", body = Object))
+)]
+pub async fn list_networks(
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    auth: AuthParams,
+    ValidQuery(query): ValidQuery<ListNetworksQuery>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    let name = query.name.unwrap_or_default();
+    if let Some(response) = Validator::new()
+        .check(
+            name.len() <= MAX_NAME_FILTER_LEN,
+            "name",
+            format!("must be at most {} characters", MAX_NAME_FILTER_LEN),
+        )
+        .reject(&request_id)
+    {
+        return response;
+    }
+
+    let kind = node_command::Kind::ListNetworks(ListNetworks {
+        request_id: request_id.clone(),
+        name,
+    });
+    let response = match send(
+        &server_tx,
+        &pending,
+        &auth,
+        &request_id,
+        RequestType::ListNetworks,
+        kind,
+        LIST_NETWORKS_TIMEOUT,
+    )
+    .await
+    {
+        Ok(response) => response,
+        Err(response) => return response,
+    };
+
+    let networks: Vec<Value> = match response {
+        Some(node_response::Kind::NetworkList(list)) => {
+            list.networks.iter().map(network_json).collect()
+        }
+        _ => Vec::new(),
+    };
+    let body = json!({
+        "id": request_id,
+        "count": networks.len(),
+        "networks": networks,
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+/// One network, by id or name, with its attached containers.
+#[utoipa::path(
+    get,
+    path = "/api/networks/{network}",
+    tag = "networks",
+    params(("network" = String, Path, description = "Network id or name"), AuthParams),
+    responses((status = 200, description = "The network", body = Object))
+)]
+pub async fn inspect_network(
+    Path(network): Path<String>,
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    auth: AuthParams,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = Validator::new()
+        .network("network", &network)
+        .reject(&request_id)
+    {
+        return response;
+    }
+
+    let kind = node_command::Kind::InspectNetwork(InspectNetwork {
+        request_id: request_id.clone(),
+        network,
+    });
+    let response = match send(
+        &server_tx,
+        &pending,
+        &auth,
+        &request_id,
+        RequestType::InspectNetwork,
+        kind,
+        INSPECT_NETWORK_TIMEOUT,
+    )
+    .await
+    {
+        Ok(response) => response,
+        Err(response) => return response,
+    };
+
+    let network = match response {
+        Some(node_response::Kind::NetworkList(list)) => list.networks.first().map(network_json),
+        _ => None,
+    };
+    let body = json!({
+        "id": request_id,
+        "network": network,
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateNetworkBody {
+    name: String,
+    /// `bridge` when unset; `overlay`, `macvlan`, `ipvlan` or a plugin otherwise.
+    driver: Option<String>,
+    /// No route to the outside.
+    #[serde(default)]
+    internal: bool,
+    /// Standalone containers may join an overlay network.
+    #[serde(default)]
+    attachable: bool,
+    #[serde(default)]
+    enable_ipv6: bool,
+    /// CIDR such as `172.28.0.0/16`; Docker picks one when unset.
+    subnet: Option<String>,
+    /// Needs `subnet`; its first address when unset.
+    gateway: Option<String>,
+    #[serde(default)]
+    labels: HashMap<String, String>,
+    /// Driver options, e.g. `com.docker.network.bridge.name`.
+    #[serde(default)]
+    options: HashMap<String, String>,
+}
+
+/// Creates a network on the node.
+#[utoipa::path(
+    post,
+    path = "/api/networks",
+    tag = "networks",
+    params(AuthParams),
+    request_body = CreateNetworkBody,
+    responses((status = 201, description = "The new network id", body = Object))
+)]
+pub async fn create_network(
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    auth: AuthParams,
+    ValidJson(body): ValidJson<CreateNetworkBody>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    let driver = body.driver.unwrap_or_default();
+    let subnet = body.subnet.unwrap_or_default();
+    let gateway = body.gateway.unwrap_or_default();
+    if let Some(response) = Validator::new()
+        .network("name", &body.name)
+        .check(
+            driver.len() <= MAX_DRIVER_LEN
+                && driver
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-' | '/' | ':')),
+            "driver",
+            "must be a network driver such as bridge, overlay or macvlan",
+        )
+        .check(
+            subnet.is_empty() || is_cidr(&subnet),
+            "subnet",
+            "must be a CIDR such as 172.28.0.0/16",
+        )
+        .check(
+            gateway.is_empty() || (!subnet.is_empty() && gateway.parse::<IpAddr>().is_ok()),
+            "gateway",
+            "must be an IP address, and needs a subnet",
+        )
+        .check(
+            body.labels.len() + body.options.len() <= MAX_NETWORK_LABELS
+                && body
+                    .labels
+                    .keys()
+                    .chain(body.options.keys())
+                    .all(|key| !key.is_empty()),
+            "labels",
+            format!(
+                "must be at most {} labels and options with non-empty keys",
+                MAX_NETWORK_LABELS
+            ),
+        )
+        .reject(&request_id)
+    {
+        return response;
+    }
+
+    let kind = node_command::Kind::CreateNetwork(CreateNetwork {
+        request_id: request_id.clone(),
+        name: body.name.clone(),
+        driver,
+        internal: body.internal,
+        attachable: body.attachable,
+        enable_ipv6: body.enable_ipv6,
+        subnet,
+        gateway,
+        labels: body.labels,
+        options: body.options,
+    });
+    let response = match send(
+        &server_tx,
+        &pending,
+        &auth,
+        &request_id,
+        RequestType::CreateNetwork,
+        kind,
+        CHANGE_NETWORK_TIMEOUT,
+    )
+    .await
+    {
+        Ok(response) => response,
+        Err(response) => return response,
+    };
+
+    let (network_id, warning) = match response {
+        Some(node_response::Kind::NetworkCreated(created)) => (created.id, created.warning),
+        _ => (String::new(), String::new()),
+    };
+    let body = json!({
+        "id": request_id,
+        "name": body.name,
+        "network_id": network_id,
+        "warning": (!warning.is_empty()).then_some(warning),
+    });
+    (StatusCode::CREATED, Json(body)).into_response()
+}
+
+/// Removes a network from the node. Docker refuses while containers are attached.
+#[utoipa::path(
+    delete,
+    path = "/api/networks/{network}",
+    tag = "networks",
+    params(("network" = String, Path, description = "Network id or name"), AuthParams),
+    responses((status = 200, description = "The network is gone", body = Object), (status = 202, description = "Held for approval", body = Object))
+)]
+pub async fn remove_network(
+    Path(network): Path<String>,
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    auth: AuthParams,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = Validator::new()
+        .network("network", &network)
+        .reject(&request_id)
+    {
+        return response;
+    }
+
+    let kind = node_command::Kind::RemoveNetwork(RemoveNetwork {
+        request_id: request_id.clone(),
+        network: network.clone(),
+    });
+    match send(
+        &server_tx,
+        &pending,
+        &auth,
+        &request_id,
+        RequestType::RemoveNetwork,
+        kind,
+        CHANGE_NETWORK_TIMEOUT,
+    )
+    .await
+    {
+        Ok(_) => {
+            let body = json!({
+                "id": request_id,
+                "network": network,
+                "action": "remove",
+            });
+            (StatusCode::OK, Json(body)).into_response()
+        }
+        Err(response) => response,
+    }
+}
+
+async fn send(
+    server_tx: &CommandRouter,
+    pending: &PendingResponses,
+    auth: &AuthParams,
+    request_id: &str,
+    request_type: RequestType,
+    kind: node_command::Kind,
+    timeout: Duration,
+) -> Result<Option<node_response::Kind>, Response> {
+    let envelope = Envelope {
+        payload: Some(Payload::NodeCommand(NodeCommand { kind: Some(kind) })),
+        ..Default::default()
+    };
+    match request_node(
+        server_tx,
+        pending,
+        auth,
+        request_id,
+        request_type as i32,
+        envelope,
+        timeout,
+    )
+    .await
+    {
+        Ok(response) => Ok(match response.payload {
+            Some(Payload::NodeResponse(node_resp)) => node_resp.kind,
+            _ => None,
+        }),
+        Err(e) => Err(e.into_response(request_id)),
+    }
+}
+
+fn network_json(network: &NetworkInfo) -> Value {
+    json!({
+        "network_id": network.id,
+        "name": network.name,
+        "driver": network.driver,
+        "scope": network.scope,
+        "internal": network.internal,
+        "attachable": network.attachable,
+        "enable_ipv6": network.enable_ipv6,
+        "created": network.created,
+        "subnets": network.subnets,
+        "labels": network.labels,
+        "options": network.options,
+        "containers": network.containers,
+    })
+}
+
+fn is_cidr(value: &str) -> bool {
+    let Some((address, prefix)) = value.split_once('/') else {
+        return false;
+    };
+    let max_prefix = match address.parse::<IpAddr>() {
+        Ok(IpAddr::V4(_)) => 32,
+        Ok(IpAddr::V6(_)) => 128,
+        Err(_) => return false,
+    };
+    prefix
+        .parse::<u8>()
+        .is_ok_and(|prefix| prefix <= max_prefix)
+}
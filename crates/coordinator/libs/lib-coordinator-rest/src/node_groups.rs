@@ -0,0 +1,170 @@
+use axum::{
+    Extension, Json,
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use lib_coordinator_core::NodeGroupStore;
+use serde::Deserialize;
+use serde_json::json;
+use tracing::{error, info};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::admin::{AdminParams, AdminToken};
+use crate::error_response;
+use crate::validation::{ValidJson, ValidQuery, Validator};
+
+const MAX_GROUP_NAME_LEN: usize = 64;
+const MAX_GROUP_NODES: usize = 1000;
+
+#[derive(Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct NodeGroupBody {
+    /// Node ids in the group; they need not be connected.
+    nodes: Vec<String>,
+}
+
+/// Every node group with its nodes.
+#[utoipa::path(
+    get,
+    path = "/api/node-groups",
+    tag = "nodes",
+    params(AdminParams),
+    responses((status = 200, description = "Node groups", body = Object))
+)]
+pub async fn list_node_groups(
+    Extension(groups): Extension<NodeGroupStore>,
+    Extension(admin): Extension<AdminToken>,
+    ValidQuery(params): ValidQuery<AdminParams>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = admin.reject(&params, &request_id) {
+        return response;
+    }
+
+    let body = json!({
+        "req_id": request_id,
+        "node_groups": groups.list(),
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+/// Creates or replaces a node group.
+#[utoipa::path(
+    put,
+    path = "/api/node-groups/{name}",
+    tag = "nodes",
+    params(("name" = String, Path, description = "Group name"), AdminParams),
+    request_body = NodeGroupBody,
+    responses((status = 200, description = "The group was replaced", body = Object), (status = 201, description = "The group was created", body = Object))
+)]
+pub async fn put_node_group(
+    Path(name): Path<String>,
+    Extension(groups): Extension<NodeGroupStore>,
+    Extension(admin): Extension<AdminToken>,
+    ValidQuery(params): ValidQuery<AdminParams>,
+    ValidJson(body): ValidJson<NodeGroupBody>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = admin.reject(&params, &request_id) {
+        return response;
+    }
+    if let Some(response) = Validator::new()
+        .check(
+            !name.is_empty()
+                && name.len() <= MAX_GROUP_NAME_LEN
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-')),
+            "name",
+            format!(
+                "must be 1 to {} letters, digits, '_' or '-'",
+                MAX_GROUP_NAME_LEN
+            ),
+        )
+        .check(
+            !body.nodes.is_empty() && body.nodes.len() <= MAX_GROUP_NODES,
+            "nodes",
+            format!("between 1 and {} node ids", MAX_GROUP_NODES),
+        )
+        .check(
+            body.nodes.iter().all(|node_id| !node_id.is_empty()),
+            "nodes",
+            "must not contain empty node ids",
+        )
+        .reject(&request_id)
+    {
+        return response;
+    }
+
+    let mut nodes = body.nodes;
+    nodes.sort();
+    nodes.dedup();
+    match groups.put(&name, nodes.clone()) {
+        Ok(created) => {
+            info!("Saved node group {} with {} nodes", name, nodes.len());
+            let status = if created {
+                StatusCode::CREATED
+            } else {
+                StatusCode::OK
+            };
+            let body = json!({
+                "req_id": request_id,
+                "name": name,
+                "nodes": nodes,
+                "created": created,
+            });
+            (status, Json(body)).into_response()
+        }
+        Err(e) => store_error(&request_id, e),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/node-groups/{name}",
+    tag = "nodes",
+    params(("name" = String, Path, description = "Group name"), AdminParams),
+    responses((status = 200, description = "The group is gone", body = Object))
+)]
+pub async fn delete_node_group(
+    Path(name): Path<String>,
+    Extension(groups): Extension<NodeGroupStore>,
+    Extension(admin): Extension<AdminToken>,
+    ValidQuery(params): ValidQuery<AdminParams>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = admin.reject(&params, &request_id) {
+        return response;
+    }
+
+    match groups.delete(&name) {
+        Ok(true) => {
+            info!("Deleted node group {}", name);
+            let body = json!({
+                "req_id": request_id,
+                "name": name,
+                "deleted": true,
+            });
+            (StatusCode::OK, Json(body)).into_response()
+        }
+        Ok(false) => error_response(
+            StatusCode::NOT_FOUND,
+            &request_id,
+            "Node group not found",
+            name,
+        ),
+        Err(e) => store_error(&request_id, e),
+    }
+}
+
+fn store_error(request_id: &str, e: std::io::Error) -> Response {
+    error!("Failed to persist node groups: {}", e);
+    error_response(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        request_id,
+        "Failed to store node groups",
+        e.to_string(),
+    )
+}
@@ -0,0 +1,106 @@
+use axum::{Extension, Json, extract::Path, response::IntoResponse};
+use lib_coordinator_core::{CommandRouter, NodeStateCache, PendingResponses};
+use proto::generated::{
+    Envelope, GetNodeMetrics, NodeCommand, NodeMetrics, RequestType, envelope::Payload,
+    node_command,
+};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::node_request::{NodeRequestError, request_node};
+use crate::{AuthParams, NodePasswordParams};
+
+const GET_NODE_METRICS_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+#[utoipa::path(
+    get,
+    path = "/api/nodes/{node_id}/metrics",
+    tag = "nodes",
+    params(("node_id" = String, Path, description = "Node id"), NodePasswordParams),
+    responses((status = 200, description = "The node host's CPU, memory and disk", body = Object))
+)]
+pub async fn get_node_metrics(
+    Path(node_id): Path<String>,
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    Extension(node_states): Extension<NodeStateCache>,
+    query: NodePasswordParams,
+) -> impl IntoResponse {
+    let request_id = Uuid::new_v4().to_string();
+    let auth = query.for_node(node_id.clone());
+
+    match fetch_node_metrics(&server_tx, &pending, &node_states, &auth, &request_id).await {
+        Ok(metrics) => {
+            let body = json!({
+                "id": request_id,
+                "node_id": node_id,
+                "metrics": metrics.map(|metrics| json!({
+                    "psi_available": metrics.psi_available,
+                    "pressure": {
+                        "cpu": metrics.cpu_pressure,
+                        "memory": metrics.memory_pressure,
+                        "io": metrics.io_pressure,
+                    },
+                    "memory": {
+                        "total": metrics.memory_total,
+                        "available": metrics.memory_available,
+                        "reserved": metrics.memory_reserved,
+                    },
+                })),
+            });
+            (axum::http::StatusCode::OK, Json(body)).into_response()
+        }
+        Err(e) => e.into_response(&request_id),
+    }
+}
+
+/// Asks the node for host metrics and keeps its memory for placements.
+pub(crate) async fn fetch_node_metrics(
+    server_tx: &CommandRouter,
+    pending: &PendingResponses,
+    node_states: &NodeStateCache,
+    auth: &AuthParams,
+    request_id: &str,
+) -> Result<Option<NodeMetrics>, NodeRequestError> {
+    // Build the command envelope to ask the node for host metrics
+    let envelope = Envelope {
+        payload: Some(Payload::NodeCommand(NodeCommand {
+            kind: Some(node_command::Kind::GetNodeMetrics(GetNodeMetrics {
+                request_id: request_id.to_string(),
+            })),
+        })),
+        ..Default::default()
+    };
+
+    let response = request_node(
+        server_tx,
+        pending,
+        auth,
+        request_id,
+        RequestType::GetNodeMetrics as i32,
+        envelope,
+        GET_NODE_METRICS_TIMEOUT,
+    )
+    .await?;
+    let metrics = extract_node_metrics_from_response(response);
+    if let Some(metrics) = &metrics
+        && metrics.memory_total > 0
+    {
+        node_states.record_memory(
+            &auth.node_id,
+            metrics.memory_total,
+            metrics.memory_available,
+            metrics.memory_reserved,
+        );
+    }
+    Ok(metrics)
+}
+
+fn extract_node_metrics_from_response(response: Envelope) -> Option<NodeMetrics> {
+    if let Some(Payload::NodeResponse(node_resp)) = response.payload
+        && let Some(proto::generated::node_response::Kind::NodeMetrics(metrics)) = node_resp.kind
+    {
+        return Some(metrics);
+    }
+    None
+}
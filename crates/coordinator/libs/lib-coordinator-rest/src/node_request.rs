@@ -0,0 +1,223 @@
+use std::time::Duration;
+
+use axum::{
+    Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use lib_coordinator_core::{CommandRouter, PendingRequest, PendingResponses, ServerRequestByUser};
+use proto::generated::{
+    CancelRequest, Envelope, ErrorCode, NodeCommand, envelope::Payload, node_command, node_response,
+};
+use tokio::sync::oneshot;
+use tracing::error;
+
+use crate::{ApiError, ApiErrorDetail, AuthParams};
+
+/// Why a request/response round trip to the node did not produce a result.
+#[derive(Debug, Clone)]
+pub enum NodeRequestError {
+    Send,
+    ChannelClosed,
+    Timeout,
+    /// The node answered with a `NodeError`.
+    Node(ErrorCode, String),
+}
+
+impl std::fmt::Display for NodeRequestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NodeRequestError::Send => write!(f, "failed to send request to node"),
+            NodeRequestError::ChannelClosed => write!(f, "response channel closed"),
+            NodeRequestError::Timeout => write!(f, "timeout waiting for node response"),
+            NodeRequestError::Node(_, message) => write!(f, "node error: {}", message),
+        }
+    }
+}
+
+impl NodeRequestError {
+    /// Maps the error to the same responses the container endpoints return.
+    pub fn into_response(self, request_id: &str) -> Response {
+        let (status, message, detail) = match self {
+            NodeRequestError::Send => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Failed to send request to server",
+                )
+                    .into_response();
+            }
+            NodeRequestError::ChannelClosed => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Response channel closed",
+                "Node dropped oneshot channel".to_string(),
+            ),
+            NodeRequestError::Timeout => (
+                StatusCode::REQUEST_TIMEOUT,
+                "Timeout waiting for node response",
+                "Timeout waiting for node response".to_string(),
+            ),
+            NodeRequestError::Node(code, detail) => (node_error_status(code), "Node error", detail),
+        };
+
+        let err = ApiError {
+            req_id: request_id.to_string(),
+            error: ApiErrorDetail {
+                message: message.to_string(),
+                detail,
+            },
+        };
+        (status, Json(err)).into_response()
+    }
+}
+
+/// Sends `envelope` to the node and waits for the single response correlated
+/// by `(request_id, request_type)`. Node errors are returned as `NodeRequestError::Node`.
+pub async fn request_node(
+    server_tx: &CommandRouter,
+    pending: &PendingResponses,
+    auth: &AuthParams,
+    request_id: &str,
+    request_type: i32,
+    envelope: Envelope,
+    timeout: Duration,
+) -> Result<Envelope, NodeRequestError> {
+    let key = (request_id.to_string(), request_type);
+    let (response_tx, response_rx) = oneshot::channel();
+
+    let ttl = timeout + Duration::from_secs(60);
+    // Register a pending response for this request, kept past our own timeout
+    // so the sweeper never races it
+    pending.insert(
+        key.clone(),
+        PendingRequest::new(&auth.node_id, response_tx).with_ttl(Some(ttl)),
+    );
+    let cancel = CancelGuard::new(server_tx, pending, auth, request_id, request_type);
+
+    // Route the request to the node's stream
+    let send_result = server_tx.send(ServerRequestByUser::new(
+        auth.node_id.clone(),
+        auth.password.clone(),
+        envelope,
+    ));
+
+    if let Err(e) = send_result {
+        error!("Failed to send server request: {}", e);
+        pending.remove(&key);
+        return Err(NodeRequestError::Send);
+    }
+
+    // Wait for the response from the node with a timeout
+    let response = match tokio::time::timeout(timeout, response_rx).await {
+        Ok(Ok(response)) => {
+            cancel.disarm();
+            response
+        }
+        Ok(Err(_)) => {
+            pending.remove(&key);
+            return Err(NodeRequestError::ChannelClosed);
+        }
+        Err(_) => {
+            pending.remove(&key);
+            return Err(NodeRequestError::Timeout);
+        }
+    };
+
+    if let Some(e) = node_error(&response) {
+        return Err(e);
+    }
+
+    Ok(response)
+}
+
+/// Returns the error if the node answered with a `NodeError`.
+pub fn node_error(response: &Envelope) -> Option<NodeRequestError> {
+    if let Some(Payload::NodeResponse(node_resp)) = &response.payload
+        && let Some(node_response::Kind::Error(err)) = &node_resp.kind
+    {
+        return Some(NodeRequestError::Node(err.code(), err.message.clone()));
+    }
+    None
+}
+
+/// The HTTP status for a node error of kind `code`. Errors without a code
+/// stay `400 Bad Request`.
+pub fn node_error_status(code: ErrorCode) -> StatusCode {
+    match code {
+        ErrorCode::NotFound => StatusCode::NOT_FOUND,
+        ErrorCode::Conflict => StatusCode::CONFLICT,
+        ErrorCode::DaemonUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+        ErrorCode::PermissionDenied => StatusCode::FORBIDDEN,
+        ErrorCode::Timeout => StatusCode::GATEWAY_TIMEOUT,
+        ErrorCode::Unspecified => StatusCode::BAD_REQUEST,
+    }
+}
+
+/// Returns the message if the node answered with a `NodeError`.
+pub fn node_error_message(response: &Envelope) -> Option<String> {
+    if let Some(Payload::NodeResponse(node_resp)) = &response.payload
+        && let Some(node_response::Kind::Error(err)) = &node_resp.kind
+    {
+        return Some(err.message.clone());
+    }
+    None
+}
+
+/// Tells the node to abort `request_id`, e.g. a log fetch nobody waits for anymore.
+pub fn cancel_node_request(server_tx: &CommandRouter, auth: &AuthParams, request_id: &str) {
+    // Fails only when the node is not connected, and then there is nothing to cancel
+    let _ = server_tx.send(ServerRequestByUser::new(
+        auth.node_id.clone(),
+        auth.password.clone(),
+        Envelope {
+            payload: Some(Payload::NodeCommand(NodeCommand {
+                kind: Some(node_command::Kind::CancelRequest(CancelRequest {
+                    request_id: request_id.to_string(),
+                })),
+            })),
+            ..Default::default()
+        },
+    ));
+}
+
+/// Held while waiting for a node response. Unless disarmed once the response
+/// arrived, dropping it (timeout, error, or the HTTP client disconnecting and axum
+/// dropping the handler) removes the pending entry and cancels the request on the node.
+pub struct CancelGuard {
+    server_tx: CommandRouter,
+    pending: PendingResponses,
+    auth: AuthParams,
+    key: (String, i32),
+    armed: bool,
+}
+
+impl CancelGuard {
+    pub fn new(
+        server_tx: &CommandRouter,
+        pending: &PendingResponses,
+        auth: &AuthParams,
+        request_id: &str,
+        request_type: i32,
+    ) -> Self {
+        Self {
+            server_tx: server_tx.clone(),
+            pending: pending.clone(),
+            auth: auth.clone(),
+            key: (request_id.to_string(), request_type),
+            armed: true,
+        }
+    }
+
+    /// The node answered, there is nothing left to cancel.
+    pub fn disarm(mut self) {
+        self.armed = false;
+    }
+}
+
+impl Drop for CancelGuard {
+    fn drop(&mut self) {
+        if self.armed {
+            self.pending.remove(&self.key);
+            cancel_node_request(&self.server_tx, &self.auth, &self.key.0);
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use lib_coordinator_core::{CommandRouter, PendingRequest, PendingStreams, ServerRequestByUser};
+use proto::generated::Envelope;
+use tokio::sync::mpsc;
+
+use crate::AuthParams;
+use crate::node_request::cancel_node_request;
+
+const NODE_STREAM_CAPACITY: usize = 64;
+
+/// Receiving side of a request the node answers with a sequence of envelopes.
+/// The pending stream entry is removed and the node told to stop when this is
+/// dropped, so a client disconnect leaves neither the entry nor the node's work behind.
+pub struct NodeStream {
+    rx: mpsc::Receiver<Envelope>,
+    key: (String, i32),
+    pending_streams: PendingStreams,
+    server_tx: CommandRouter,
+    auth: AuthParams,
+}
+
+impl NodeStream {
+    pub async fn recv(&mut self) -> Option<Envelope> {
+        self.rx.recv().await
+    }
+}
+
+impl Drop for NodeStream {
+    fn drop(&mut self) {
+        self.pending_streams.remove(&self.key);
+        // A no-op on the node when the stream already finished
+        cancel_node_request(&self.server_tx, &self.auth, &self.key.0);
+    }
+}
+
+/// Registers a pending stream for `request_id` and sends the command to the node.
+pub fn open_node_stream(
+    server_tx: &CommandRouter,
+    pending_streams: &PendingStreams,
+    auth: &AuthParams,
+    request_id: &str,
+    request_type: i32,
+    envelope: Envelope,
+) -> Result<NodeStream, String> {
+    let (tx, rx) = mpsc::channel(NODE_STREAM_CAPACITY);
+    let key = (request_id.to_string(), request_type);
+    // Lives as long as the client; the sweeper drops it once `rx` is gone
+    pending_streams.insert(
+        key.clone(),
+        PendingRequest::new(&auth.node_id, tx).with_ttl(None),
+    );
+
+    let stream = NodeStream {
+        rx,
+        key,
+        pending_streams: pending_streams.clone(),
+        server_tx: server_tx.clone(),
+        auth: auth.clone(),
+    };
+
+    // On failure the stream is dropped and the pending entry goes with it
+    server_tx
+        .send(ServerRequestByUser::new(
+            auth.node_id.clone(),
+            auth.password.clone(),
+            envelope,
+        ))
+        .map_err(|e| e.to_string())?;
+
+    Ok(stream)
+}
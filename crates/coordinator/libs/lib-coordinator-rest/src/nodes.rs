@@ -0,0 +1,84 @@
+use std::collections::BTreeSet;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use axum::{
+    Extension, Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use lib_coordinator_core::{NodeChannels, NodeStateCache};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::admin::{AdminParams, AdminToken};
+use crate::cluster_summary::container_counts_json;
+use crate::validation::ValidQuery;
+
+/// Nodes connected right now, with when they connected and were last heard from.
+#[utoipa::path(
+    get,
+    path = "/api/nodes",
+    tag = "nodes",
+    params(AdminParams),
+    responses((status = 200, description = "Connected nodes", body = Object))
+)]
+pub async fn list_nodes(
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(node_states): Extension<NodeStateCache>,
+    Extension(admin): Extension<AdminToken>,
+    ValidQuery(params): ValidQuery<AdminParams>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = admin.reject(&params, &request_id) {
+        return response;
+    }
+
+    let now = SystemTime::now();
+    let connected: BTreeSet<String> = nodes.iter().map(|entry| entry.key().clone()).collect();
+    let list: Vec<_> = connected
+        .iter()
+        .map(|node_id| match node_states.get(node_id) {
+            Some(state) => json!({
+                "node_id": node_id,
+                "connected_at": unix_secs(state.connected_at),
+                "last_seen": unix_secs(state.last_seen),
+                "idle_secs": now
+                    .duration_since(state.last_seen)
+                    .map(|idle| idle.as_secs())
+                    .unwrap_or(0),
+                "heartbeat_secs": state.heartbeat_interval.map(|interval| interval.as_secs()),
+                "responsive": state.unresponsive_for().is_none(),
+                "remote_addr": state.remote_addr.map(|addr| addr.to_string()),
+                "connections": state.connections,
+                "containers": container_counts_json(state.container_counts(), state.state_counts()),
+                "capabilities": state.capabilities.as_ref().map(|capabilities| json!({
+                    "os": capabilities.os,
+                    "arch": capabilities.arch,
+                    "exec_tty": capabilities.exec_tty,
+                    "stats": capabilities.stats,
+                    "checkpoint": capabilities.checkpoint,
+                })),
+                "coordinator": state.link.as_ref().map(|link| json!({
+                    "address": link.address,
+                    "priority": link.priority,
+                    "standby": link.priority > 0,
+                    "coordinators": link.coordinators,
+                    "failovers": link.failovers,
+                })),
+            }),
+            None => json!({ "node_id": node_id }),
+        })
+        .collect();
+    let body = json!({
+        "req_id": request_id,
+        "count": list.len(),
+        "nodes": list,
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+fn unix_secs(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
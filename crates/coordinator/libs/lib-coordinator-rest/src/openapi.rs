@@ -0,0 +1,223 @@
+use axum::Router;
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::openapi::{RefOr, ResponseBuilder, content::ContentBuilder};
+use utoipa::{Modify, OpenApi};
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::ApiError;
+use crate::sessions::SESSION_COOKIE;
+use crate::tenants::TENANT_KEY_HEADER;
+
+pub const OPENAPI_PATH: &str = "/api/openapi.json";
+pub const SWAGGER_UI_PATH: &str = "/api/docs";
+
+/// The REST API as an OpenAPI 3.1 document, generated from the handlers'
+/// `#[utoipa::path]` annotations. Response bodies are described, not typed.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Docklord Coordinator API",
+        description = "Manage Docker containers on connected nodes through the coordinator. \
+            Node routes take `node_id` and `password` (or a bearer token from `/api/auth/token`, \
+            or a browser session from `/api/auth/session`), cluster-wide routes the coordinator's \
+            `admin_token`. With a session, changes need the CSRF token in `X-CSRF-Token`."
+    ),
+    paths(
+        crate::bearer_tokens::issue_token,
+        crate::bearer_tokens::revoke_token,
+        crate::sessions::open_session,
+        crate::sessions::get_session,
+        crate::sessions::close_session,
+        crate::get_containers::get_containers,
+        crate::create_container::create_container,
+        crate::create_container::clone_container,
+        crate::migrate::migrate_container,
+        crate::container_status::get_container_status,
+        crate::container_actions::start_container,
+        crate::container_actions::stop_container,
+        crate::container_actions::restart_container,
+        crate::container_actions::pause_container,
+        crate::container_actions::unpause_container,
+        crate::container_actions::delete_container,
+        crate::trash::restore_container,
+        crate::container_archive::get_container_archive,
+        crate::container_archive::put_container_archive,
+        crate::container_logs::get_container_logs,
+        crate::container_stats::get_container_stats,
+        crate::container_stats::stream_container_stats,
+        crate::annotations::get_container_annotations,
+        crate::annotations::put_container_annotations,
+        crate::images::list_images,
+        crate::image_build::build_image,
+        crate::images::pull_image,
+        crate::images::prune_images,
+        crate::images::remove_image,
+        crate::image_manifest::get_image_manifest,
+        crate::image_history::get_image_history,
+        crate::networks::list_networks,
+        crate::networks::create_network,
+        crate::networks::inspect_network,
+        crate::networks::remove_network,
+        crate::system_info::get_system_info,
+        crate::system_disk::get_system_disk_usage,
+        crate::system_disk::prune_system,
+        crate::nodes::list_nodes,
+        crate::node_metrics::get_node_metrics,
+        crate::container_graph::get_container_graph,
+        crate::disk_usage::get_disk_usage,
+        crate::top_containers::get_top_containers,
+        crate::autostart::get_autostart,
+        crate::autostart::put_autostart,
+        crate::autostart::apply_autostart,
+        crate::secrets::list_secrets,
+        crate::host_scripts::run_host_script,
+        crate::secrets::put_secret,
+        crate::secrets::delete_secret,
+        crate::desired_state::get_desired_state,
+        crate::desired_state::put_desired_state,
+        crate::desired_state::delete_desired_state,
+        crate::log_forwarding::get_log_forwarding,
+        crate::log_forwarding::put_log_forwarding,
+        crate::log_forwarding::delete_log_forwarding,
+        crate::cleanup::get_cleanup,
+        crate::cleanup::put_cleanup,
+        crate::cleanup::delete_cleanup,
+        crate::trash::get_trash,
+        crate::trash::put_trash,
+        crate::trash::delete_trash,
+        crate::templates::list_templates,
+        crate::templates::create_template,
+        crate::templates::get_template,
+        crate::templates::replace_template,
+        crate::templates::delete_template,
+        crate::templates::deploy_template,
+        crate::gitops::get_gitops_status,
+        crate::gitops::sync_gitops,
+        crate::gitops::apply_gitops,
+        crate::reports::list_reports,
+        crate::reports::generate_report,
+        crate::reports::get_report,
+        crate::favorites::get_favorites,
+        crate::favorites::add_favorite,
+        crate::favorites::remove_favorite,
+        crate::log_export::export_logs,
+        crate::log_export::list_log_consumers,
+        crate::log_export::reset_log_consumer,
+        crate::event_history::get_event_history,
+        crate::timeline::get_container_timeline,
+        crate::approvals::list_approvals,
+        crate::approvals::approve_request,
+        crate::approvals::reject_request,
+        crate::cluster_summary::get_cluster_summary,
+        crate::broadcasts::list_broadcasts,
+        crate::broadcasts::send_broadcast,
+        crate::broadcasts::get_broadcast,
+        crate::usage::get_usage,
+        crate::recordings::list_recordings,
+        crate::recordings::get_recording_cast,
+        crate::freeze_windows::list_freeze_windows,
+        crate::freeze_windows::put_freeze_window,
+        crate::freeze_windows::delete_freeze_window,
+        crate::node_groups::list_node_groups,
+        crate::node_groups::put_node_group,
+        crate::node_groups::delete_node_group,
+        crate::placements::place_container,
+        crate::tenants::get_own_tenant,
+        crate::tenants::list_tenants,
+        crate::tenants::create_tenant,
+        crate::tenants::delete_tenant,
+        crate::tenants::create_tenant_key,
+        crate::tenants::revoke_tenant_key,
+        crate::tenants::set_tenant_quota,
+        crate::tenants::assign_tenant_node,
+        crate::tenants::release_tenant_node,
+    ),
+    components(schemas(ApiError)),
+    modifiers(&SecurityAndErrors),
+    security((), ("bearer" = []), ("session" = []), ("tenant_key" = [])),
+    tags(
+        (name = "auth", description = "Bearer tokens and browser sessions for node credentials"),
+        (name = "containers", description = "Containers on a node"),
+        (name = "images", description = "Images on a node"),
+        (name = "networks", description = "Networks on a node"),
+        (name = "system", description = "The node's Docker engine"),
+        (name = "nodes", description = "Node-scoped routes, with the node id in the path"),
+        (name = "secrets", description = "Secrets injected into containers"),
+        (name = "desired-state", description = "Declared containers the coordinator converges nodes to"),
+        (name = "logs", description = "Log export and forwarding"),
+        (name = "templates", description = "Parameterized container specs"),
+        (name = "gitops", description = "Desired state from a Git repository"),
+        (name = "reports", description = "Daily resource reports"),
+        (name = "favorites", description = "Pinned containers per API key"),
+        (name = "events", description = "Container, image and volume event history"),
+        (name = "approvals", description = "Destructive actions held for a second credential"),
+        (name = "cluster", description = "Cluster-wide overview"),
+        (name = "broadcasts", description = "Notices and commands sent to many nodes"),
+        (name = "usage", description = "REST usage per credential"),
+        (name = "recordings", description = "Recorded interactive sessions"),
+        (name = "freeze-windows", description = "Schedules during which nodes refuse changes"),
+        (name = "tenants", description = "Multi-tenant accounts")
+    )
+)]
+pub struct ApiDoc;
+
+/// Adds the header credentials and the error body every route may answer with.
+struct SecurityAndErrors;
+
+impl Modify for SecurityAndErrors {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "bearer",
+                SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+            );
+            components.add_security_scheme(
+                "session",
+                SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new(SESSION_COOKIE))),
+            );
+            components.add_security_scheme(
+                "tenant_key",
+                SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new(TENANT_KEY_HEADER))),
+            );
+        }
+
+        let error = ResponseBuilder::new()
+            .description(
+                "Error with `req_id`, `error.message` and `error.detail`; validation \
+                 failures (422) also list `fields`",
+            )
+            .content(
+                "application/json",
+                ContentBuilder::new()
+                    .schema(Some(RefOr::Ref(utoipa::openapi::Ref::from_schema_name(
+                        "ApiError",
+                    ))))
+                    .build(),
+            )
+            .build();
+        for item in openapi.paths.paths.values_mut() {
+            for operation in [
+                &mut item.get,
+                &mut item.put,
+                &mut item.post,
+                &mut item.delete,
+            ]
+            .into_iter()
+            .flatten()
+            {
+                operation
+                    .responses
+                    .responses
+                    .insert("default".to_string(), RefOr::T(error.clone()));
+            }
+        }
+    }
+}
+
+/// `GET /api/openapi.json` and a Swagger UI at `/api/docs`. Served without
+/// credentials, like the documentation they are.
+pub fn openapi_routes() -> Router {
+    SwaggerUi::new(SWAGGER_UI_PATH)
+        .url(OPENAPI_PATH, ApiDoc::openapi())
+        .into()
+}
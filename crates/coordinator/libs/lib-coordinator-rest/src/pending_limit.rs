@@ -0,0 +1,121 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use axum::{
+    extract::{Request, State},
+    http::{HeaderValue, StatusCode, header},
+    middleware::Next,
+    response::Response,
+};
+use lib_coordinator_core::PendingResponses;
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use crate::error_response;
+use crate::rate_limit::target_node_id;
+
+// Pending requests are short-lived, so clients may retry almost at once
+const RETRY_AFTER_SECS: u64 = 1;
+
+/// Most requests waiting for a node reply at once, overall and per node.
+/// `0` leaves that cap off.
+#[derive(Debug, Clone, Copy)]
+pub struct PendingLimit {
+    pub total: usize,
+    pub per_node: usize,
+}
+
+impl Default for PendingLimit {
+    fn default() -> Self {
+        Self {
+            total: 10_000,
+            per_node: 1_000,
+        }
+    }
+}
+
+/// Caps the entries in `PendingResponses`, so clients retrying requests that
+/// nodes are slow to answer cannot grow it without bound.
+#[derive(Clone)]
+pub struct PendingLimiter {
+    limit: PendingLimit,
+    pending: PendingResponses,
+    rejected: Arc<AtomicU64>,
+}
+
+impl PendingLimiter {
+    pub fn new(limit: PendingLimit, pending: PendingResponses) -> Self {
+        Self {
+            limit,
+            pending,
+            rejected: Arc::default(),
+        }
+    }
+
+    /// Why a new request for `node_id` cannot wait for a reply right now.
+    fn check(&self, node_id: &str) -> Result<(), String> {
+        let total = self.pending.len();
+        if self.limit.total > 0 && total >= self.limit.total {
+            return Err(format!(
+                "{} requests are already waiting for nodes",
+                self.limit.total
+            ));
+        }
+        // Counting per node walks the map, which is only worth it near the cap
+        if self.limit.per_node > 0
+            && total >= self.limit.per_node
+            && self
+                .pending
+                .iter()
+                .filter(|entry| entry.node_id == node_id)
+                .count()
+                >= self.limit.per_node
+        {
+            return Err(format!(
+                "{} requests are already waiting for node {}",
+                self.limit.per_node, node_id
+            ));
+        }
+        Ok(())
+    }
+
+    /// Current pending requests next to the caps, for the cluster summary.
+    pub fn gauge(&self) -> Value {
+        json!({
+            "pending": self.pending.len(),
+            "max": self.limit.total,
+            "max_per_node": self.limit.per_node,
+            "rejected": self.rejected.load(Ordering::Relaxed),
+        })
+    }
+}
+
+/// Middleware answering `429 Too Many Requests` while the pending requests
+/// are at a cap. The check is made when a request arrives, so concurrent
+/// requests may go slightly over it. Requests not addressed to a node pass.
+pub async fn limit_pending_requests(
+    State(limiter): State<PendingLimiter>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(node_id) = target_node_id(&request) else {
+        return next.run(request).await;
+    };
+
+    match limiter.check(&node_id) {
+        Ok(()) => next.run(request).await,
+        Err(detail) => {
+            limiter.rejected.fetch_add(1, Ordering::Relaxed);
+            let mut response = error_response(
+                StatusCode::TOO_MANY_REQUESTS,
+                &Uuid::new_v4().to_string(),
+                "Too many pending requests",
+                detail,
+            );
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(RETRY_AFTER_SECS));
+            response
+        }
+    }
+}
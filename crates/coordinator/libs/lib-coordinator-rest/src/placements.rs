@@ -0,0 +1,253 @@
+use std::time::{Duration, SystemTime};
+
+use axum::{
+    Extension, Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use futures_util::future::join_all;
+use lib_coordinator_core::{
+    CommandRouter, ContainerSpec, FreezeStore, NodeChannels, NodeGroupStore, NodeStateCache,
+    PendingResponses, SecretStore, TenantStore,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::admin::{AdminParams, AdminToken};
+use crate::create_container::{create_on_node, validate_create};
+use crate::desired_state::node_auth;
+use crate::error_response;
+use crate::node_metrics::fetch_node_metrics;
+use crate::templates::default_start;
+use crate::validation::{ValidJson, ValidQuery, Validator};
+
+// Older memory samples are fetched again before placing
+const MAX_SAMPLE_AGE: Duration = Duration::from_secs(30);
+
+#[derive(Deserialize, ToSchema)]
+pub struct PlacementBody {
+    /// Node group to place the container in.
+    group: String,
+    /// Bytes the chosen node must have free, counting the memory limits of its
+    /// running containers as used.
+    #[serde(default)]
+    min_free_memory: u64,
+    #[serde(flatten)]
+    spec: ContainerSpec,
+    /// Start the container after creating it.
+    #[serde(default = "default_start")]
+    start: bool,
+}
+
+/// How one node of the group was judged.
+#[derive(Debug, Serialize)]
+struct Candidate {
+    node_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    free_memory: Option<u64>,
+    /// Why the node was not eligible.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    skipped: Option<String>,
+}
+
+/// Creates a container on the node of a group with the most free memory, at
+/// least `min_free_memory`, and tells which node was chosen. Memory samples
+/// older than 30 seconds are fetched again first.
+#[allow(clippy::too_many_arguments)] // Axum extractors, one per piece of shared state
+#[utoipa::path(
+    post,
+    path = "/api/placements",
+    tag = "containers",
+    params(AdminParams),
+    request_body = PlacementBody,
+    responses(
+        (status = 201, description = "The chosen node and the new container id", body = Object),
+        (status = 503, description = "No node of the group fits", body = Object)
+    )
+)]
+pub async fn place_container(
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(node_states): Extension<NodeStateCache>,
+    Extension(groups): Extension<NodeGroupStore>,
+    Extension(secrets): Extension<SecretStore>,
+    Extension(freeze_windows): Extension<FreezeStore>,
+    Extension(tenants): Extension<TenantStore>,
+    Extension(admin): Extension<AdminToken>,
+    ValidQuery(params): ValidQuery<AdminParams>,
+    ValidJson(body): ValidJson<PlacementBody>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = admin.reject(&params, &request_id) {
+        return response;
+    }
+    if let Some(response) = Validator::new()
+        .check(!body.group.is_empty(), "group", "must not be empty")
+        .reject(&request_id)
+    {
+        return response;
+    }
+    if let Some(response) = validate_create(&body.spec, &request_id) {
+        return response;
+    }
+    let Some(members) = groups.get(&body.group) else {
+        return error_response(
+            StatusCode::NOT_FOUND,
+            &request_id,
+            "Node group not found",
+            body.group,
+        );
+    };
+
+    let candidates = join_all(members.into_iter().map(|node_id| {
+        // The freeze middleware only sees nodes named in the request
+        let frozen = freeze_windows
+            .active(&node_id, tenants.owner_of(&node_id).as_deref(), Utc::now())
+            .map(|freeze| freeze.name);
+        judge(
+            &server_tx,
+            &pending,
+            &nodes,
+            &node_states,
+            node_id,
+            frozen,
+            body.min_free_memory,
+        )
+    }))
+    .await;
+    let Some(chosen) = candidates
+        .iter()
+        .filter(|candidate| candidate.skipped.is_none())
+        .max_by_key(|candidate| candidate.free_memory)
+    else {
+        let reasons: Vec<String> = candidates
+            .iter()
+            .map(|candidate| {
+                format!(
+                    "{}: {}",
+                    candidate.node_id,
+                    candidate.skipped.as_deref().unwrap_or_default()
+                )
+            })
+            .collect();
+        return error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            &request_id,
+            "No node fits",
+            format!(
+                "No node in group {} has {} bytes free ({})",
+                body.group,
+                body.min_free_memory,
+                reasons.join(", ")
+            ),
+        );
+    };
+    let Some(auth) = node_auth(&nodes, &chosen.node_id) else {
+        return error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            &request_id,
+            "No node fits",
+            format!("Node {} disconnected while placing", chosen.node_id),
+        );
+    };
+    info!(
+        "Placing {} on node {} of group {} ({} bytes free)",
+        body.spec.image,
+        chosen.node_id,
+        body.group,
+        chosen.free_memory.unwrap_or_default()
+    );
+    // Concurrent placements see this until the node's next sample
+    node_states.reserve_memory(&chosen.node_id, body.min_free_memory);
+
+    match create_on_node(
+        &server_tx,
+        &pending,
+        &secrets,
+        &auth,
+        &request_id,
+        &body.spec,
+        body.start,
+    )
+    .await
+    {
+        Ok(created) => {
+            let response = json!({
+                "id": request_id,
+                "node_id": chosen.node_id,
+                "container": created,
+                "placement": {
+                    "group": body.group,
+                    "min_free_memory": body.min_free_memory,
+                    "candidates": candidates,
+                },
+            });
+            (StatusCode::CREATED, Json(response)).into_response()
+        }
+        Err(response) => response,
+    }
+}
+
+/// Free memory of one group member, fetching a fresh sample when the cached
+/// one is too old, or why it cannot take the container, e.g. being `frozen`
+/// by the named window.
+async fn judge(
+    server_tx: &CommandRouter,
+    pending: &PendingResponses,
+    nodes: &NodeChannels,
+    node_states: &NodeStateCache,
+    node_id: String,
+    frozen: Option<String>,
+    min_free_memory: u64,
+) -> Candidate {
+    let skip = |node_id: String, reason: String| Candidate {
+        node_id,
+        free_memory: None,
+        skipped: Some(reason),
+    };
+    if let Some(window) = frozen {
+        return skip(node_id, format!("frozen by window {}", window));
+    }
+    let Some(auth) = node_auth(nodes, &node_id) else {
+        return skip(node_id, "offline".to_string());
+    };
+    if node_states.unresponsive_for(&node_id).is_some() {
+        return skip(node_id, "unresponsive".to_string());
+    }
+
+    let fresh = node_states
+        .get(&node_id)
+        .and_then(|state| state.memory)
+        .filter(|memory| {
+            SystemTime::now()
+                .duration_since(memory.sampled_at)
+                .is_ok_and(|age| age <= MAX_SAMPLE_AGE)
+        });
+    let memory = match fresh {
+        Some(memory) => memory,
+        None => {
+            let request_id = Uuid::new_v4().to_string();
+            if let Err(e) =
+                fetch_node_metrics(server_tx, pending, node_states, &auth, &request_id).await
+            {
+                return skip(node_id, format!("no metrics: {}", e));
+            }
+            match node_states.get(&node_id).and_then(|state| state.memory) {
+                Some(memory) => memory,
+                None => return skip(node_id, "does not report memory".to_string()),
+            }
+        }
+    };
+
+    let free = memory.free();
+    Candidate {
+        node_id,
+        free_memory: Some(free),
+        skipped: (free < min_free_memory).then(|| format!("only {} bytes free", free)),
+    }
+}
@@ -0,0 +1,257 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{ConnectInfo, Query, Request, State},
+    http::{HeaderMap, HeaderName, HeaderValue, StatusCode, header},
+    middleware::Next,
+    response::Response,
+};
+use dashmap::DashMap;
+use lib_coordinator_core::{NodeChannels, verify_node};
+use uuid::Uuid;
+
+use crate::{error_response, request_password};
+
+// Buckets of keys that have been quiet long enough to be full again are
+// dropped past this many, so made-up node ids cannot grow the map forever
+const MAX_TRACKED_KEYS: usize = 10_000;
+
+const RATELIMIT_LIMIT: HeaderName = HeaderName::from_static("ratelimit-limit");
+const RATELIMIT_REMAINING: HeaderName = HeaderName::from_static("ratelimit-remaining");
+const RATELIMIT_RESET: HeaderName = HeaderName::from_static("ratelimit-reset");
+
+/// Sustained requests per second and burst size allowed per node or client address.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    pub per_second: f64,
+    pub burst: f64,
+}
+
+impl RateLimit {
+    /// Default per node.
+    pub const NODE: Self = Self {
+        per_second: 50.0,
+        burst: 100.0,
+    };
+    /// Default per client address, above the node default so a single client
+    /// working with a few nodes is held back by the node limits first.
+    pub const CLIENT: Self = Self {
+        per_second: 100.0,
+        burst: 200.0,
+    };
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// What a request was allowed, for the `RateLimit-*` response headers.
+struct Quota {
+    limit: u64,
+    remaining: u64,
+    /// Seconds until the bucket is full again.
+    reset: u64,
+}
+
+/// A token bucket per key, a node id or client address. Every request first
+/// takes a token from its key's bucket, so one busy node or client gets `429`s
+/// while requests from the others keep reaching their nodes.
+#[derive(Clone)]
+pub struct RateLimiter {
+    limit: Option<RateLimit>,
+    buckets: Arc<DashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    /// `None` disables the limit.
+    pub fn new(limit: Option<RateLimit>) -> Self {
+        Self {
+            limit,
+            buckets: Arc::default(),
+        }
+    }
+
+    /// Takes a token for `key`, or tells how long until the next one.
+    /// `None` when the limit is disabled.
+    fn acquire(&self, key: &str) -> Option<Result<Quota, Duration>> {
+        let limit = self.limit?;
+        let now = Instant::now();
+
+        if self.buckets.len() > MAX_TRACKED_KEYS {
+            self.buckets
+                .retain(|_, bucket| refilled(bucket, &limit, now) < limit.burst);
+        }
+
+        let mut bucket = self
+            .buckets
+            .entry(key.to_string())
+            .or_insert_with(|| Bucket {
+                tokens: limit.burst,
+                updated: now,
+            });
+        bucket.tokens = refilled(&bucket, &limit, now);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Some(Ok(Quota {
+                limit: limit.burst as u64,
+                remaining: bucket.tokens as u64,
+                reset: ((limit.burst - bucket.tokens) / limit.per_second).ceil() as u64,
+            }))
+        } else {
+            Some(Err(Duration::from_secs_f64(
+                (1.0 - bucket.tokens) / limit.per_second,
+            )))
+        }
+    }
+}
+
+/// The per-node limiter with the connected nodes, whose credentials decide
+/// which bucket a request is charged to.
+#[derive(Clone)]
+pub struct NodeRateLimiter {
+    limiter: RateLimiter,
+    nodes: NodeChannels,
+}
+
+impl NodeRateLimiter {
+    pub fn new(limit: Option<RateLimit>, nodes: NodeChannels) -> Self {
+        Self {
+            limiter: RateLimiter::new(limit),
+            nodes,
+        }
+    }
+}
+
+fn refilled(bucket: &Bucket, limit: &RateLimit, now: Instant) -> f64 {
+    let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+    (bucket.tokens + elapsed * limit.per_second).min(limit.burst)
+}
+
+/// Middleware answering `429 Too Many Requests` with `Retry-After` once the
+/// target node's bucket is empty. Only requests with the node's credentials
+/// are charged to it, so made-up requests naming a node cannot use up its
+/// clients' budget; the others are charged to their client address. Requests
+/// not addressed to a node pass. Each node's commands then wait in the node's
+/// own queue, so a busy node does not hold up the others.
+pub async fn limit_node_requests(
+    State(limiter): State<NodeRateLimiter>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(node_id) = target_node_id(&request) else {
+        return next.run(request).await;
+    };
+    if limiter.limiter.limit.is_none() {
+        return next.run(request).await;
+    }
+    let password = request_password(&request, &limiter.nodes).unwrap_or_default();
+    if verify_node(&limiter.nodes, &node_id, &password).await {
+        let detail = |secs| {
+            format!(
+                "Rate limit for node {} exceeded, retry in {} s",
+                node_id, secs
+            )
+        };
+        return limit(&limiter.limiter, &node_id, detail, request, next).await;
+    }
+    let Some(ip) = client_ip(&request) else {
+        return next.run(request).await;
+    };
+    let detail = |secs| format!("Rate limit for client {} exceeded, retry in {} s", ip, secs);
+    // Apart from node ids, which are not IP addresses
+    let key = format!("client:{}", ip);
+    limit(&limiter.limiter, &key, detail, request, next).await
+}
+
+/// Middleware answering `429 Too Many Requests` with `Retry-After` once the
+/// client address's bucket is empty, so one client cannot flood the nodes'
+/// command queues whichever nodes it targets. Behind a reverse proxy every client
+/// shares the proxy's address.
+pub async fn limit_client_requests(
+    State(limiter): State<RateLimiter>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(ip) = client_ip(&request) else {
+        return next.run(request).await;
+    };
+    let detail = |secs| format!("Rate limit for client {} exceeded, retry in {} s", ip, secs);
+    limit(&limiter, &ip.to_string(), detail, request, next).await
+}
+
+async fn limit(
+    limiter: &RateLimiter,
+    key: &str,
+    detail: impl FnOnce(u64) -> String,
+    request: Request,
+    next: Next,
+) -> Response {
+    match limiter.acquire(key) {
+        None => next.run(request).await,
+        Some(Ok(quota)) => {
+            let mut response = next.run(request).await;
+            set_quota_headers(response.headers_mut(), &quota);
+            response
+        }
+        Some(Err(retry_after)) => {
+            let secs = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+            let mut response = error_response(
+                StatusCode::TOO_MANY_REQUESTS,
+                &Uuid::new_v4().to_string(),
+                "Too many requests",
+                detail(secs),
+            );
+            let headers = response.headers_mut();
+            headers.insert(header::RETRY_AFTER, HeaderValue::from(secs));
+            headers.insert(RATELIMIT_REMAINING, HeaderValue::from(0));
+            headers.insert(RATELIMIT_RESET, HeaderValue::from(secs));
+            response
+        }
+    }
+}
+
+/// Both limits apply to most requests; the headers describe whichever has
+/// fewer requests left, as that one answers `429` first.
+fn set_quota_headers(headers: &mut HeaderMap, quota: &Quota) {
+    let tighter = headers
+        .get(&RATELIMIT_REMAINING)
+        .and_then(|value| value.to_str().ok()?.parse::<u64>().ok())
+        .is_some_and(|remaining| remaining <= quota.remaining);
+    if tighter {
+        return;
+    }
+    headers.insert(RATELIMIT_LIMIT, HeaderValue::from(quota.limit));
+    headers.insert(RATELIMIT_REMAINING, HeaderValue::from(quota.remaining));
+    headers.insert(RATELIMIT_RESET, HeaderValue::from(quota.reset));
+}
+
+fn client_ip(request: &Request) -> Option<IpAddr> {
+    request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip())
+}
+
+/// Node a request is addressed to: `node_id` in the query or `/api/nodes/{node_id}/...`.
+pub(crate) fn target_node_id(request: &Request) -> Option<String> {
+    let params = Query::<HashMap<String, String>>::try_from_uri(request.uri())
+        .map(|q| q.0)
+        .unwrap_or_default();
+
+    if let Some(node_id) = params.get("node_id") {
+        return Some(node_id.clone());
+    }
+    request
+        .uri()
+        .path()
+        .strip_prefix("/api/nodes/")
+        .and_then(|rest| rest.split('/').next())
+        .filter(|id| !id.is_empty())
+        .map(str::to_string)
+}
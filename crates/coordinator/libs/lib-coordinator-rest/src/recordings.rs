@@ -0,0 +1,111 @@
+use axum::{
+    Extension, Json,
+    extract::Path,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use lib_coordinator_core::SessionRecorder;
+use serde::Deserialize;
+use serde_json::json;
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::admin::{AdminParams, AdminToken};
+use crate::error_response;
+use crate::validation::ValidQuery;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RecordingsQuery {
+    node_id: Option<String>,
+    container_id: Option<String>,
+}
+
+/// Recorded interactive sessions with their audit metadata, newest first.
+#[utoipa::path(
+    get,
+    path = "/api/recordings",
+    tag = "recordings",
+    params(AdminParams, RecordingsQuery),
+    responses((status = 200, description = "Recordings", body = Object))
+)]
+pub async fn list_recordings(
+    Extension(recorder): Extension<SessionRecorder>,
+    Extension(admin): Extension<AdminToken>,
+    ValidQuery(params): ValidQuery<AdminParams>,
+    ValidQuery(query): ValidQuery<RecordingsQuery>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = admin.reject(&params, &request_id) {
+        return response;
+    }
+
+    let recordings: Vec<_> = recorder
+        .list()
+        .into_iter()
+        .filter(|r| query.node_id.as_ref().is_none_or(|id| &r.node_id == id))
+        .filter(|r| {
+            query
+                .container_id
+                .as_ref()
+                .is_none_or(|id| &r.container_id == id)
+        })
+        .collect();
+
+    let body = json!({
+        "req_id": request_id,
+        "recording_enabled": recorder.is_enabled(),
+        "recordings": recordings,
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+/// The asciicast v2 file of a recording, for `asciinema play` or a web player.
+#[utoipa::path(
+    get,
+    path = "/api/recordings/{recording_id}/cast",
+    tag = "recordings",
+    params(("recording_id" = String, Path, description = "Recording id"), AdminParams),
+    responses((status = 200, description = "The asciicast v2 file", content_type = "application/x-asciicast"))
+)]
+pub async fn get_recording_cast(
+    Path(recording_id): Path<String>,
+    Extension(recorder): Extension<SessionRecorder>,
+    Extension(admin): Extension<AdminToken>,
+    ValidQuery(params): ValidQuery<AdminParams>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = admin.reject(&params, &request_id) {
+        return response;
+    }
+
+    let Some(path) = recorder.cast_path(&recording_id) else {
+        return error_response(
+            StatusCode::NOT_FOUND,
+            &request_id,
+            "Recording not found",
+            recording_id,
+        );
+    };
+
+    match tokio::fs::read(&path).await {
+        Ok(bytes) => (
+            StatusCode::OK,
+            [
+                (header::CONTENT_TYPE, "application/x-asciicast".to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{}.cast\"", recording_id),
+                ),
+            ],
+            bytes,
+        )
+            .into_response(),
+        Err(e) => error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            &request_id,
+            "Failed to read recording",
+            e.to_string(),
+        ),
+    }
+}
@@ -0,0 +1,366 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use axum::{
+    Extension, Json,
+    extract::Path,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use chrono::{NaiveDate, Utc};
+use lib_coordinator_core::{
+    CommandRouter, DailyReport, EventLog, NodeChannels, NodeStateCache, PendingResponses,
+    ReportConfig, ReportDelivery, ReportStore, ResourcePeaks,
+    reports::{build_daily_report, today},
+};
+use proto::generated::{
+    Envelope, GetTopContainers, NodeCommand, RequestType, envelope::Payload, node_command,
+};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::{info, warn};
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::admin::{AdminParams, AdminToken};
+use crate::desired_state::node_auth;
+use crate::error_response;
+use crate::node_request::request_node;
+use crate::validation::{ValidQuery, Validator};
+
+const SAMPLE_TIMEOUT: Duration = Duration::from_secs(15);
+// Enough to cover every container on a node in one sample
+const SAMPLE_LIMIT: u32 = 1000;
+
+/// Samples container resource usage for the peaks and generates, stores and
+/// delivers the previous day's report at the configured time.
+#[derive(Clone)]
+pub struct ReportScheduler {
+    config: ReportConfig,
+    store: ReportStore,
+    peaks: ResourcePeaks,
+    delivery: ReportDelivery,
+    event_log: EventLog,
+    node_states: NodeStateCache,
+    server_tx: CommandRouter,
+    pending: PendingResponses,
+    nodes: NodeChannels,
+}
+
+impl ReportScheduler {
+    #[allow(clippy::too_many_arguments)] // One per piece of shared coordinator state
+    pub fn new(
+        config: ReportConfig,
+        store: ReportStore,
+        peaks: ResourcePeaks,
+        event_log: EventLog,
+        node_states: NodeStateCache,
+        server_tx: CommandRouter,
+        pending: PendingResponses,
+        nodes: NodeChannels,
+    ) -> Result<Self, String> {
+        Ok(Self {
+            delivery: ReportDelivery::new(&config)?,
+            config,
+            store,
+            peaks,
+            event_log,
+            node_states,
+            server_tx,
+            pending,
+            nodes,
+        })
+    }
+
+    pub fn spawn(&self) {
+        info!(
+            "Reports: generating daily at {} UTC, sampling usage every {}s",
+            self.config.at.format("%H:%M"),
+            self.config.sample_interval.as_secs()
+        );
+
+        let sampler = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(sampler.config.sample_interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                sampler.sample().await;
+            }
+        });
+
+        let scheduler = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(scheduler.until_next_run()).await;
+                let yesterday = today().pred_opt().unwrap_or_else(today);
+                let (report, errors) = scheduler.generate(yesterday, true).await;
+                for e in errors {
+                    warn!("Failed to deliver the report for {}: {}", report.date, e);
+                }
+                // Past the scheduled minute, so the next run is tomorrow's
+                tokio::time::sleep(Duration::from_secs(61)).await;
+            }
+        });
+    }
+
+    fn until_next_run(&self) -> Duration {
+        let now = Utc::now().naive_utc();
+        let mut next = now.date().and_time(self.config.at);
+        if next <= now {
+            next += chrono::TimeDelta::days(1);
+        }
+        (next - now).to_std().unwrap_or_default()
+    }
+
+    /// Builds and stores the report; when `deliver` is set also sends it out.
+    /// Returns the report with the delivery errors.
+    async fn generate(&self, date: NaiveDate, deliver: bool) -> (DailyReport, Vec<String>) {
+        let report = build_daily_report(date, &self.event_log, &self.node_states, &self.peaks);
+        if let Err(e) = self.store.insert(report.clone()) {
+            warn!("Failed to store the report for {}: {}", report.date, e);
+        }
+        info!(
+            "Generated the report for {} ({} containers)",
+            report.date,
+            report.containers.len()
+        );
+        if !deliver {
+            return (report, Vec::new());
+        }
+
+        let errors = self.delivery.deliver(&report).await;
+        (report, errors)
+    }
+
+    /// Records the current CPU and memory use of every container on every connected node.
+    async fn sample(&self) {
+        let node_ids: HashSet<String> = self.nodes.iter().map(|e| e.key().clone()).collect();
+        for node_id in node_ids {
+            let Some(auth) = node_auth(&self.nodes, &node_id) else {
+                continue;
+            };
+            let request_id = Uuid::new_v4().to_string();
+            let envelope = Envelope {
+                payload: Some(Payload::NodeCommand(NodeCommand {
+                    kind: Some(node_command::Kind::GetTopContainers(GetTopContainers {
+                        request_id: request_id.clone(),
+                        by: "memory".to_string(),
+                        limit: SAMPLE_LIMIT,
+                    })),
+                })),
+                ..Default::default()
+            };
+            let response = match request_node(
+                &self.server_tx,
+                &self.pending,
+                &auth,
+                &request_id,
+                RequestType::GetTopContainers as i32,
+                envelope,
+                SAMPLE_TIMEOUT,
+            )
+            .await
+            {
+                Ok(response) => response,
+                Err(e) => {
+                    warn!("Failed to sample usage on node {}: {}", node_id, e);
+                    continue;
+                }
+            };
+            let Some(Payload::NodeResponse(node_resp)) = &response.payload else {
+                continue;
+            };
+            let Some(proto::generated::node_response::Kind::TopContainers(top)) = &node_resp.kind
+            else {
+                continue;
+            };
+
+            let samples: Vec<(String, f64, u64)> = top
+                .containers
+                .iter()
+                .map(|c| (c.name.clone(), c.cpu_usage, c.memory_usage))
+                .collect();
+            if let Err(e) = self.peaks.record(&node_id, &samples) {
+                warn!("Failed to store resource peaks: {}", e);
+            }
+        }
+    }
+}
+
+fn not_enabled(request_id: &str) -> Response {
+    error_response(
+        StatusCode::NOT_FOUND,
+        request_id,
+        "Reports are not enabled",
+        "Start the coordinator with --reports".to_string(),
+    )
+}
+
+/// Stored reports, newest first, with the schedule.
+#[utoipa::path(
+    get,
+    path = "/api/reports",
+    tag = "reports",
+    params(AdminParams),
+    responses((status = 200, description = "Stored reports and the schedule", body = Object))
+)]
+pub async fn list_reports(
+    Extension(reports): Extension<Option<ReportScheduler>>,
+    Extension(admin): Extension<AdminToken>,
+    ValidQuery(params): ValidQuery<AdminParams>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = admin.reject(&params, &request_id) {
+        return response;
+    }
+    let Some(scheduler) = &reports else {
+        return not_enabled(&request_id);
+    };
+
+    let config = &scheduler.config;
+    let body = json!({
+        "req_id": request_id,
+        "schedule": {
+            "at": config.at.format("%H:%M").to_string(),
+            "sample_interval": config.sample_interval.as_secs(),
+            "webhook": config.webhook.is_some(),
+            "email": config.email.as_ref().map(|email| &email.to),
+        },
+        "reports": scheduler.store.list(),
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ReportFormatQuery {
+    format: Option<String>,
+}
+
+/// One stored report as JSON, or CSV with `?format=csv`.
+#[utoipa::path(
+    get,
+    path = "/api/reports/{date}",
+    tag = "reports",
+    params(("date" = String, Path, description = "Day of the report, `YYYY-MM-DD`"), AdminParams, ReportFormatQuery),
+    responses((status = 200, description = "The report as JSON or CSV", body = Object))
+)]
+pub async fn get_report(
+    Path(date): Path<String>,
+    Extension(reports): Extension<Option<ReportScheduler>>,
+    Extension(admin): Extension<AdminToken>,
+    ValidQuery(params): ValidQuery<AdminParams>,
+    ValidQuery(query): ValidQuery<ReportFormatQuery>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = admin.reject(&params, &request_id) {
+        return response;
+    }
+    let Some(scheduler) = &reports else {
+        return not_enabled(&request_id);
+    };
+    if let Some(response) = Validator::new()
+        .one_of("format", query.format.as_deref(), &["json", "csv"])
+        .reject(&request_id)
+    {
+        return response;
+    }
+    let Some(report) = scheduler.store.get(&date) else {
+        return error_response(
+            StatusCode::NOT_FOUND,
+            &request_id,
+            "Report not found",
+            format!("No report for {}; generate it first", date),
+        );
+    };
+
+    report_response(&request_id, report, query.format.as_deref())
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct GenerateQuery {
+    /// `YYYY-MM-DD`; today (so far) when unset.
+    date: Option<String>,
+    /// Also send the report to the webhook and mailbox.
+    #[serde(default)]
+    deliver: bool,
+    format: Option<String>,
+}
+
+/// Generates (or regenerates) the report for a day now.
+#[utoipa::path(
+    post,
+    path = "/api/reports/generate",
+    tag = "reports",
+    params(AdminParams, GenerateQuery),
+    responses((status = 200, description = "The report", body = Object))
+)]
+pub async fn generate_report(
+    Extension(reports): Extension<Option<ReportScheduler>>,
+    Extension(admin): Extension<AdminToken>,
+    ValidQuery(params): ValidQuery<AdminParams>,
+    ValidQuery(query): ValidQuery<GenerateQuery>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = admin.reject(&params, &request_id) {
+        return response;
+    }
+    let Some(scheduler) = &reports else {
+        return not_enabled(&request_id);
+    };
+    let date = query
+        .date
+        .as_deref()
+        .map(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d"));
+    if let Some(response) = Validator::new()
+        .check(
+            date.as_ref().is_none_or(|date| date.is_ok()),
+            "date",
+            "must be a date as YYYY-MM-DD",
+        )
+        .check(
+            date.as_ref()
+                .is_none_or(|date| date.as_ref().is_ok_and(|date| *date <= today())),
+            "date",
+            "must not be in the future",
+        )
+        .one_of("format", query.format.as_deref(), &["json", "csv"])
+        .reject(&request_id)
+    {
+        return response;
+    }
+    let date = date.and_then(Result::ok).unwrap_or_else(today);
+
+    let (report, errors) = scheduler.generate(date, query.deliver).await;
+    if !errors.is_empty() {
+        return error_response(
+            StatusCode::BAD_GATEWAY,
+            &request_id,
+            "Report generated but not delivered",
+            errors.join("; "),
+        );
+    }
+    report_response(&request_id, report, query.format.as_deref())
+}
+
+fn report_response(request_id: &str, report: DailyReport, format: Option<&str>) -> Response {
+    if format == Some("csv") {
+        let disposition = format!("attachment; filename=\"report-{}.csv\"", report.date);
+        return (
+            StatusCode::OK,
+            [
+                (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+                (header::CONTENT_DISPOSITION, disposition),
+            ],
+            report.to_csv(),
+        )
+            .into_response();
+    }
+    let body = json!({
+        "req_id": request_id,
+        "report": report,
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
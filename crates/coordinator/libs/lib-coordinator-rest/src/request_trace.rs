@@ -0,0 +1,27 @@
+use axum::{extract::Request, middleware::Next, response::Response};
+use lib_coordinator_core::TraceContext;
+use tracing::Instrument;
+
+use crate::access_log::{REQUEST_ID, assign_request_id};
+use crate::usage::credential_label;
+
+/// Middleware tracing each request by its `X-Request-Id`, taken from the
+/// request or generated and returned on the response. Commands it sends to
+/// nodes carry the id and the credential it was made with, which the node
+/// logs and echoes, and coordinator logs written meanwhile are in a span with
+/// both. Runs inside the credential layers, so bearer tokens and browser
+/// sessions show up as the node they stand for.
+pub async fn trace_requests(mut request: Request, next: Next) -> Response {
+    let request_id = assign_request_id(&mut request);
+    let trace = TraceContext {
+        trace_id: request_id.to_str().unwrap_or_default().to_string(),
+        actor: credential_label(&request),
+    };
+    let span = tracing::info_span!("request", trace_id = %trace.trace_id, actor = %trace.actor);
+
+    let mut response = trace.scope(next.run(request)).instrument(span).await;
+    response
+        .headers_mut()
+        .insert(REQUEST_ID.clone(), request_id);
+    response
+}
@@ -0,0 +1,224 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{Request, State},
+    http::{HeaderName, Method, header},
+    middleware::Next,
+    response::Response,
+};
+use dashmap::DashMap;
+use proto::generated::Envelope;
+use tracing::debug;
+
+use crate::AuthParams;
+use crate::coalescing::RequestCoalescer;
+use crate::node_request::NodeRequestError;
+use crate::rate_limit::target_node_id;
+
+// Expired entries are swept once the cache holds this many
+const MAX_CACHED_RESPONSES: usize = 10_000;
+
+/// How long node responses to status queries are reused. Within `ttl` a
+/// response is fresh; for `stale` after that it is still served while a
+/// refresh runs in the background. A zero `ttl` disables the cache.
+#[derive(Debug, Clone, Copy)]
+pub struct ResponseCacheConfig {
+    pub ttl: Duration,
+    pub stale: Duration,
+}
+
+impl Default for ResponseCacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl: Duration::from_secs(1),
+            stale: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Node id, password and the query, as for the coalescer.
+type CacheKey = (String, String, String);
+
+struct CachedEnvelope {
+    envelope: Envelope,
+    fetched_at: Instant,
+    refreshing: bool,
+}
+
+/// Where a response came from, as told in `X-Cache`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheState {
+    Hit,
+    Stale,
+    Miss,
+}
+
+/// A node response with its age, for the freshness headers.
+pub struct CachedResponse {
+    pub envelope: Envelope,
+    age: Duration,
+    state: CacheState,
+    config: ResponseCacheConfig,
+}
+
+impl CachedResponse {
+    /// `Cache-Control`, `Age` and `X-Cache` describing the response's freshness.
+    pub fn headers(&self) -> [(HeaderName, String); 3] {
+        let cache_control = if self.config.ttl.is_zero() {
+            "no-cache".to_string()
+        } else {
+            format!(
+                "private, max-age={}, stale-while-revalidate={}",
+                self.config.ttl.saturating_sub(self.age).as_secs(),
+                self.config.stale.as_secs()
+            )
+        };
+        let state = match self.state {
+            CacheState::Hit => "HIT",
+            CacheState::Stale => "STALE",
+            CacheState::Miss => "MISS",
+        };
+        [
+            (header::CACHE_CONTROL, cache_control),
+            (header::AGE, self.age.as_secs().to_string()),
+            (HeaderName::from_static("x-cache"), state.to_string()),
+        ]
+    }
+}
+
+/// Reuses recent node responses to read-only status queries, so dashboards
+/// polling in bursts cost the node one request per `ttl`. Misses and refreshes
+/// go through the [`RequestCoalescer`]; errors are never cached.
+#[derive(Clone)]
+pub struct ResponseCache {
+    coalescer: RequestCoalescer,
+    config: ResponseCacheConfig,
+    entries: Arc<DashMap<CacheKey, CachedEnvelope>>,
+}
+
+impl ResponseCache {
+    pub fn new(coalescer: RequestCoalescer, config: ResponseCacheConfig) -> Self {
+        Self {
+            coalescer,
+            config,
+            entries: Arc::default(),
+        }
+    }
+
+    /// The response to `query`, from the cache when it is fresh or stale, from
+    /// the node otherwise. A stale response starts a background refresh. `query`
+    /// must identify the command and every parameter that changes the response.
+    pub async fn request(
+        &self,
+        auth: &AuthParams,
+        query: String,
+        request_type: i32,
+        envelope: impl FnOnce(&str) -> Envelope + Send + 'static,
+        timeout: Duration,
+    ) -> Result<CachedResponse, NodeRequestError> {
+        let key = (auth.node_id.clone(), auth.password.clone(), query.clone());
+        if !self.config.ttl.is_zero()
+            && let Some(mut entry) = self.entries.get_mut(&key)
+        {
+            let age = entry.fetched_at.elapsed();
+            if age < self.config.ttl {
+                return Ok(self.response(entry.envelope.clone(), age, CacheState::Hit));
+            }
+            if age < self.config.ttl + self.config.stale {
+                let response = self.response(entry.envelope.clone(), age, CacheState::Stale);
+                if !entry.refreshing {
+                    entry.refreshing = true;
+                    drop(entry);
+                    self.refresh(auth, key, request_type, envelope, timeout);
+                }
+                return Ok(response);
+            }
+        }
+
+        let envelope = self
+            .coalescer
+            .request(auth, query, request_type, envelope, timeout)
+            .await?;
+        if !self.config.ttl.is_zero() {
+            self.store(key, envelope.clone());
+        }
+        Ok(self.response(envelope, Duration::ZERO, CacheState::Miss))
+    }
+
+    /// Drops every cached response of `node_id`, e.g. after a container changed.
+    pub fn invalidate(&self, node_id: &str) {
+        self.entries.retain(|(node, _, _), _| node != node_id);
+    }
+
+    fn refresh(
+        &self,
+        auth: &AuthParams,
+        key: CacheKey,
+        request_type: i32,
+        envelope: impl FnOnce(&str) -> Envelope + Send + 'static,
+        timeout: Duration,
+    ) {
+        let cache = self.clone();
+        let auth = auth.clone();
+        tokio::spawn(async move {
+            let result = cache
+                .coalescer
+                .request(&auth, key.2.clone(), request_type, envelope, timeout)
+                .await;
+            match result {
+                Ok(envelope) => cache.store(key, envelope),
+                Err(e) => {
+                    debug!("Refreshing a cached response failed: {}", e);
+                    // The next request past the stale window asks the node itself
+                    if let Some(mut entry) = cache.entries.get_mut(&key) {
+                        entry.refreshing = false;
+                    }
+                }
+            }
+        });
+    }
+
+    fn store(&self, key: CacheKey, envelope: Envelope) {
+        if self.entries.len() >= MAX_CACHED_RESPONSES {
+            let max_age = self.config.ttl + self.config.stale;
+            self.entries
+                .retain(|_, entry| entry.fetched_at.elapsed() < max_age);
+        }
+        self.entries.insert(
+            key,
+            CachedEnvelope {
+                envelope,
+                fetched_at: Instant::now(),
+                refreshing: false,
+            },
+        );
+    }
+
+    fn response(&self, envelope: Envelope, age: Duration, state: CacheState) -> CachedResponse {
+        CachedResponse {
+            envelope,
+            age,
+            state,
+            config: self.config,
+        }
+    }
+}
+
+/// Middleware dropping a node's cached responses after any request that may
+/// change its containers, so a status read right after a stop is not stale.
+pub async fn invalidate_cached_responses(
+    State(cache): State<ResponseCache>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let changed_node = match *request.method() {
+        Method::GET | Method::HEAD | Method::OPTIONS => None,
+        _ => target_node_id(&request),
+    };
+    let response = next.run(request).await;
+    if let Some(node_id) = changed_node {
+        cache.invalidate(&node_id);
+    }
+    response
+}
@@ -1,21 +1,154 @@
 use axum::{
-    Extension, Router,
-    routing::{delete, get, post},
+    Extension, Router, middleware,
+    routing::{delete, get, post, put},
+};
+use lib_coordinator_core::{
+    AnnotationStore, AutostartStore, CleanupStore, CommandRouter, EventLog, FavoritesStore,
+    FreezeStore, LogExportCursors, LogForwardingStore, NodeChannels, NodeGroupStore,
+    NodeStateCache, PendingResponses, PendingStreams, SecretStore, SessionRecorder, TemplateStore,
+    TenantStore, TrashStore,
 };
-use lib_coordinator_core::{PendingResponses, ServerRequestByUser};
-use tokio::sync::broadcast;
 
-use crate::container_actions::{delete_container, start_container, stop_container};
+use crate::admin::AdminToken;
+use crate::annotations::{get_container_annotations, put_container_annotations};
+use crate::approvals::{
+    ApprovalPolicy, ApprovalQueue, approve_request, list_approvals, reject_request,
+    require_approval,
+};
+use crate::autostart::{apply_autostart, get_autostart, put_autostart};
+use crate::bearer_tokens::{TokenStore, issue_token, revoke_token};
+use crate::broadcasts::{BroadcastLog, get_broadcast, list_broadcasts, send_broadcast};
+use crate::cleanup::{delete_cleanup, get_cleanup, put_cleanup};
+use crate::cluster_summary::get_cluster_summary;
+use crate::coalescing::RequestCoalescer;
+use crate::container_actions::{
+    delete_container, pause_container, restart_container, start_container, stop_container,
+    unpause_container,
+};
+use crate::container_archive::{get_container_archive, put_container_archive};
+use crate::container_graph::get_container_graph;
 use crate::container_logs::get_container_logs;
+use crate::container_stats::{get_container_stats, stream_container_stats};
 use crate::container_status::get_container_status;
+use crate::create_container::{clone_container, create_container};
+use crate::desired_state::{
+    DesiredStateReconciler, delete_desired_state, get_desired_state, put_desired_state,
+};
+use crate::disk_usage::get_disk_usage;
+use crate::event_history::get_event_history;
+use crate::favorites::{add_favorite, get_favorites, remove_favorite};
+use crate::freeze_windows::{
+    FreezeCheck, delete_freeze_window, enforce_freeze_windows, list_freeze_windows,
+    put_freeze_window,
+};
 use crate::get_containers::get_containers;
+use crate::gitops::{GitOpsReconciler, apply_gitops, get_gitops_status, sync_gitops};
+use crate::host_scripts::run_host_script;
+use crate::image_build::build_image;
+use crate::image_history::get_image_history;
+use crate::image_manifest::get_image_manifest;
+use crate::images::{list_images, prune_images, pull_image, remove_image};
+use crate::liveness::{NodeLiveness, require_live_node};
+use crate::log_export::{export_logs, list_log_consumers, reset_log_consumer};
+use crate::log_forwarding::{delete_log_forwarding, get_log_forwarding, put_log_forwarding};
+use crate::migrate::migrate_container;
+use crate::networks::{create_network, inspect_network, list_networks, remove_network};
+use crate::node_groups::{delete_node_group, list_node_groups, put_node_group};
+use crate::node_metrics::get_node_metrics;
+use crate::nodes::list_nodes;
+use crate::pending_limit::{PendingLimit, PendingLimiter, limit_pending_requests};
+use crate::placements::place_container;
+use crate::rate_limit::{
+    NodeRateLimiter, RateLimit, RateLimiter, limit_client_requests, limit_node_requests,
+};
+use crate::recordings::{get_recording_cast, list_recordings};
+use crate::reports::{ReportScheduler, generate_report, get_report, list_reports};
+use crate::response_cache::{ResponseCache, ResponseCacheConfig, invalidate_cached_responses};
+use crate::secrets::{delete_secret, list_secrets, put_secret};
+use crate::system_disk::{get_system_disk_usage, prune_system};
+use crate::system_info::get_system_info;
+use crate::templates::{
+    create_template, delete_template, deploy_template, get_template, list_templates,
+    replace_template,
+};
+use crate::tenant_quota::TenantUsage;
+use crate::tenants::{
+    assign_tenant_node, create_tenant, create_tenant_key, delete_tenant, get_own_tenant,
+    list_tenants, release_tenant_node, revoke_tenant_key, set_tenant_quota,
+};
+use crate::timeline::{get_container_timeline, record_container_actions};
+use crate::timestamps::annotate_timestamps;
+use crate::top_containers::get_top_containers;
+use crate::trash::{delete_trash, get_trash, put_trash, restore_container};
+use crate::usage::{UsageTracker, get_usage, track_usage};
+use crate::ws_limit::WsLimiter;
+
+/// Coordinator state shared with the REST handlers (as axum extensions).
+pub struct RestContext {
+    pub server_cmd_tx: CommandRouter,
+    pub pending: PendingResponses,
+    pub pending_streams: PendingStreams,
+    pub node_states: NodeStateCache,
+    pub admin_token: AdminToken,
+    pub nodes: NodeChannels,
+    pub annotations: AnnotationStore,
+    pub autostart: AutostartStore,
+    pub secrets: SecretStore,
+    pub templates: TemplateStore,
+    pub desired_state: DesiredStateReconciler,
+    pub favorites: FavoritesStore,
+    pub log_forwarding: LogForwardingStore,
+    pub cleanup: CleanupStore,
+    pub trash: TrashStore,
+    pub log_export: LogExportCursors,
+    pub event_log: EventLog,
+    pub recorder: SessionRecorder,
+    pub tenants: TenantStore,
+    pub tenant_usage: TenantUsage,
+    /// Schedules during which nodes refuse changes.
+    pub freeze_windows: FreezeStore,
+    /// Named node sets for placements.
+    pub node_groups: NodeGroupStore,
+    /// Bearer tokens issued at `/api/auth/token`.
+    pub tokens: TokenStore,
+    /// Git-driven desired state; disabled when `None`.
+    pub gitops: Option<GitOpsReconciler>,
+    /// Scheduled daily reports; disabled when `None`.
+    pub reports: Option<ReportScheduler>,
+    /// Per-node request rate; unlimited when `None`.
+    pub node_rate_limit: Option<RateLimit>,
+    /// Per-client-address request rate; unlimited when `None`.
+    pub client_rate_limit: Option<RateLimit>,
+    /// Caps on requests waiting for node replies.
+    pub pending_limit: PendingLimit,
+    /// How long status responses are served from the cache.
+    pub response_cache: ResponseCacheConfig,
+    /// Caps on open WebSocket connections, for the cluster summary.
+    pub ws_limiter: WsLimiter,
+    /// Nodes whose destructive actions need a second credential; off when `None`.
+    pub approval_policy: Option<ApprovalPolicy>,
+}
+
+pub fn build_rest_router(ctx: RestContext) -> Router {
+    let usage = UsageTracker::new(ctx.nodes.clone(), ctx.admin_token.clone());
+    let pending_limiter = PendingLimiter::new(ctx.pending_limit, ctx.pending.clone());
+    let coalescer = RequestCoalescer::new(ctx.server_cmd_tx.clone(), ctx.pending.clone());
+    let response_cache = ResponseCache::new(coalescer.clone(), ctx.response_cache);
+    let approvals = ApprovalQueue::new(
+        ctx.approval_policy,
+        ctx.nodes.clone(),
+        ctx.admin_token.clone(),
+    );
+    let freeze_check = FreezeCheck::new(ctx.freeze_windows.clone(), ctx.tenants.clone());
+    let liveness = NodeLiveness::new(ctx.nodes.clone(), ctx.node_states.clone());
+    let node_rate_limiter = NodeRateLimiter::new(ctx.node_rate_limit, ctx.nodes.clone());
 
-pub fn build_rest_router(
-    server_cmd_tx: broadcast::Sender<ServerRequestByUser>,
-    pending: PendingResponses,
-) -> Router {
     Router::new()
-        .route("/api/containers", get(get_containers))
+        .route("/api/auth/token", post(issue_token).delete(revoke_token))
+        .route(
+            "/api/containers",
+            get(get_containers).post(create_container),
+        )
         .route(
             "/api/containers/{container_id}/status",
             get(get_container_status),
@@ -25,11 +158,237 @@ pub fn build_rest_router(
             post(start_container),
         )
         .route("/api/containers/{container_id}/stop", post(stop_container))
+        .route(
+            "/api/containers/{container_id}/restart",
+            post(restart_container),
+        )
+        .route(
+            "/api/containers/{container_id}/pause",
+            post(pause_container),
+        )
+        .route(
+            "/api/containers/{container_id}/unpause",
+            post(unpause_container),
+        )
         .route("/api/containers/{container_id}", delete(delete_container))
+        .route("/api/trash/{container_id}/restore", post(restore_container))
+        .route(
+            "/api/containers/{container_id}/clone",
+            post(clone_container),
+        )
+        .route(
+            "/api/containers/{container_id}/migrate",
+            post(migrate_container),
+        )
+        .route(
+            "/api/containers/{container_id}/archive",
+            get(get_container_archive).put(put_container_archive),
+        )
         .route(
             "/api/containers/{container_id}/logs",
             get(get_container_logs),
         )
-        .layer(Extension(server_cmd_tx))
-        .layer(Extension(pending))
+        .route(
+            "/api/containers/{container_id}/stats",
+            get(get_container_stats),
+        )
+        .route(
+            "/api/containers/{container_id}/stats/stream",
+            get(stream_container_stats),
+        )
+        .route(
+            "/api/containers/{container_id}/annotations",
+            get(get_container_annotations).put(put_container_annotations),
+        )
+        .route("/api/images", get(list_images))
+        .route("/api/images/build", post(build_image))
+        .route("/api/images/pull", post(pull_image))
+        .route("/api/images/prune", post(prune_images))
+        .route("/api/images/{image}", delete(remove_image))
+        .route("/api/images/{image_ref}/manifest", get(get_image_manifest))
+        .route("/api/images/{image}/history", get(get_image_history))
+        .route("/api/networks", get(list_networks).post(create_network))
+        .route(
+            "/api/networks/{network}",
+            get(inspect_network).delete(remove_network),
+        )
+        .route("/api/system/info", get(get_system_info))
+        .route("/api/system/df", get(get_system_disk_usage))
+        .route("/api/system/prune", post(prune_system))
+        .route("/api/nodes", get(list_nodes))
+        .route("/api/nodes/{node_id}/metrics", get(get_node_metrics))
+        .route("/api/nodes/{node_id}/graph", get(get_container_graph))
+        .route("/api/nodes/{node_id}/disk-usage", get(get_disk_usage))
+        .route(
+            "/api/nodes/{node_id}/top-containers",
+            get(get_top_containers),
+        )
+        .route(
+            "/api/nodes/{node_id}/autostart",
+            get(get_autostart).put(put_autostart),
+        )
+        .route(
+            "/api/nodes/{node_id}/autostart/apply",
+            post(apply_autostart),
+        )
+        .route("/api/nodes/{node_id}/secrets", get(list_secrets))
+        .route("/api/nodes/{node_id}/scripts/{name}", post(run_host_script))
+        .route(
+            "/api/nodes/{node_id}/secrets/{name}",
+            put(put_secret).delete(delete_secret),
+        )
+        .route(
+            "/api/nodes/{node_id}/desired",
+            get(get_desired_state)
+                .put(put_desired_state)
+                .delete(delete_desired_state),
+        )
+        .route(
+            "/api/nodes/{node_id}/log-forwarding",
+            get(get_log_forwarding)
+                .put(put_log_forwarding)
+                .delete(delete_log_forwarding),
+        )
+        .route(
+            "/api/nodes/{node_id}/cleanup",
+            get(get_cleanup).put(put_cleanup).delete(delete_cleanup),
+        )
+        .route(
+            "/api/nodes/{node_id}/trash",
+            get(get_trash).put(put_trash).delete(delete_trash),
+        )
+        .route("/api/templates", get(list_templates).post(create_template))
+        .route(
+            "/api/templates/{template_id}",
+            get(get_template)
+                .put(replace_template)
+                .delete(delete_template),
+        )
+        .route("/api/templates/{template_id}/deploy", post(deploy_template))
+        .route("/api/gitops", get(get_gitops_status))
+        .route("/api/gitops/sync", post(sync_gitops))
+        .route("/api/gitops/apply", post(apply_gitops))
+        .route("/api/reports", get(list_reports))
+        .route("/api/reports/generate", post(generate_report))
+        .route("/api/reports/{date}", get(get_report))
+        .route("/api/favorites", get(get_favorites).post(add_favorite))
+        .route(
+            "/api/favorites/{node_id}/{container_id}",
+            delete(remove_favorite),
+        )
+        .route("/api/logs/export", get(export_logs))
+        .route("/api/logs/consumers", get(list_log_consumers))
+        .route("/api/logs/consumers/{consumer}", delete(reset_log_consumer))
+        .route("/api/events/history", get(get_event_history))
+        .route(
+            "/api/containers/{container_id}/timeline",
+            get(get_container_timeline),
+        )
+        .route("/api/approvals", get(list_approvals))
+        .route(
+            "/api/approvals/{approval_id}/approve",
+            post(approve_request),
+        )
+        .route("/api/approvals/{approval_id}/reject", post(reject_request))
+        .route("/api/cluster/summary", get(get_cluster_summary))
+        .route("/api/broadcasts", get(list_broadcasts).post(send_broadcast))
+        .route("/api/broadcasts/{broadcast_id}", get(get_broadcast))
+        .route("/api/usage", get(get_usage))
+        .route("/api/recordings", get(list_recordings))
+        .route(
+            "/api/recordings/{recording_id}/cast",
+            get(get_recording_cast),
+        )
+        .route("/api/freeze-windows", get(list_freeze_windows))
+        .route(
+            "/api/freeze-windows/{name}",
+            put(put_freeze_window).delete(delete_freeze_window),
+        )
+        .route("/api/node-groups", get(list_node_groups))
+        .route(
+            "/api/node-groups/{name}",
+            put(put_node_group).delete(delete_node_group),
+        )
+        .route("/api/placements", post(place_container))
+        .route("/api/tenant", get(get_own_tenant))
+        .route("/api/tenants", get(list_tenants))
+        .route(
+            "/api/tenants/{tenant}",
+            put(create_tenant).delete(delete_tenant),
+        )
+        .route("/api/tenants/{tenant}/keys", post(create_tenant_key))
+        .route(
+            "/api/tenants/{tenant}/keys/{key_id}",
+            delete(revoke_tenant_key),
+        )
+        .route("/api/tenants/{tenant}/quota", put(set_tenant_quota))
+        .route(
+            "/api/tenants/{tenant}/nodes/{node_id}",
+            put(assign_tenant_node).delete(release_tenant_node),
+        )
+        // Innermost, so it sees the handlers' bodies before they are streamed
+        .layer(middleware::from_fn(annotate_timestamps))
+        .layer(middleware::from_fn_with_state(
+            ctx.event_log.clone(),
+            record_container_actions,
+        ))
+        .layer(Extension(ctx.server_cmd_tx))
+        .layer(Extension(ctx.pending))
+        .layer(Extension(ctx.pending_streams))
+        .layer(Extension(ctx.node_states))
+        .layer(Extension(ctx.admin_token))
+        .layer(Extension(ctx.nodes))
+        .layer(Extension(ctx.annotations))
+        .layer(Extension(ctx.autostart))
+        .layer(Extension(ctx.secrets))
+        .layer(Extension(ctx.templates))
+        .layer(Extension(ctx.desired_state))
+        .layer(Extension(ctx.favorites))
+        .layer(Extension(ctx.log_forwarding))
+        .layer(Extension(ctx.cleanup))
+        .layer(Extension(ctx.trash))
+        .layer(Extension(ctx.log_export))
+        .layer(Extension(ctx.event_log))
+        .layer(Extension(ctx.recorder))
+        .layer(Extension(ctx.tenants))
+        .layer(Extension(ctx.tenant_usage))
+        .layer(Extension(ctx.freeze_windows))
+        .layer(Extension(ctx.node_groups))
+        .layer(Extension(ctx.tokens))
+        .layer(Extension(ctx.gitops))
+        .layer(Extension(ctx.reports))
+        .layer(Extension(coalescer))
+        .layer(Extension(response_cache.clone()))
+        .layer(Extension(BroadcastLog::new()))
+        .layer(Extension(usage.clone()))
+        .layer(Extension(pending_limiter.clone()))
+        .layer(Extension(approvals.clone()))
+        .layer(Extension(ctx.ws_limiter))
+        .layer(middleware::from_fn_with_state(
+            response_cache,
+            invalidate_cached_responses,
+        ))
+        // Checked when the request runs, which for an approved one is later
+        .layer(middleware::from_fn_with_state(liveness, require_live_node))
+        .layer(middleware::from_fn_with_state(
+            pending_limiter,
+            limit_pending_requests,
+        ))
+        .layer(middleware::from_fn_with_state(
+            node_rate_limiter,
+            limit_node_requests,
+        ))
+        // Inside the approval queue, so an approved request is checked when it runs
+        .layer(middleware::from_fn_with_state(
+            freeze_check,
+            enforce_freeze_windows,
+        ))
+        // Outside the limits, so an approved request is counted when it runs
+        .layer(middleware::from_fn_with_state(approvals, require_approval))
+        .layer(middleware::from_fn_with_state(usage, track_usage))
+        // Outermost, so a flood is turned away before anything else runs
+        .layer(middleware::from_fn_with_state(
+            RateLimiter::new(ctx.client_rate_limit),
+            limit_client_requests,
+        ))
 }
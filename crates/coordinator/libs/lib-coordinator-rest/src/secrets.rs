@@ -0,0 +1,155 @@
+use axum::{
+    Extension, Json,
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use lib_coordinator_core::{NodeChannels, SecretStore};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::error;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::validation::{ValidJson, Validator};
+use crate::{NodePasswordParams, error_response, reject_unknown_node};
+
+const MAX_SECRET_VALUE_LEN: usize = 64 * 1024;
+
+#[derive(Deserialize, ToSchema)]
+pub struct SecretBody {
+    value: String,
+}
+
+/// Names of the node's secrets; values are never returned.
+#[utoipa::path(
+    get,
+    path = "/api/nodes/{node_id}/secrets",
+    tag = "secrets",
+    params(("node_id" = String, Path, description = "Node id"), NodePasswordParams),
+    responses((status = 200, description = "Secret names", body = Object))
+)]
+pub async fn list_secrets(
+    Path(node_id): Path<String>,
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(secrets): Extension<SecretStore>,
+    query: NodePasswordParams,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    let auth = query.for_node(node_id);
+    if let Some(response) = reject_unknown_node(&nodes, &auth, &request_id).await {
+        return response;
+    }
+
+    let body = json!({
+        "req_id": request_id,
+        "node_id": auth.node_id,
+        "secrets": secrets.list(&auth.node_id),
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/nodes/{node_id}/secrets/{name}",
+    tag = "secrets",
+    params(("node_id" = String, Path, description = "Node id"), ("name" = String, Path, description = "Secret name"), NodePasswordParams),
+    request_body = SecretBody,
+    responses((status = 200, description = "The secret is stored", body = Object))
+)]
+pub async fn put_secret(
+    Path((node_id, name)): Path<(String, String)>,
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(secrets): Extension<SecretStore>,
+    query: NodePasswordParams,
+    ValidJson(body): ValidJson<SecretBody>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = Validator::new()
+        .secret_name("name", &name)
+        .check(
+            body.value.len() <= MAX_SECRET_VALUE_LEN,
+            "value",
+            format!("must be at most {} bytes", MAX_SECRET_VALUE_LEN),
+        )
+        .reject(&request_id)
+    {
+        return response;
+    }
+    let auth = query.for_node(node_id);
+    if let Some(response) = reject_unknown_node(&nodes, &auth, &request_id).await {
+        return response;
+    }
+
+    match secrets.set(&auth.node_id, &name, body.value.as_bytes()) {
+        Ok(info) => {
+            let body = json!({
+                "req_id": request_id,
+                "node_id": auth.node_id,
+                "secret": info,
+            });
+            (StatusCode::OK, Json(body)).into_response()
+        }
+        Err(e) => {
+            error!("Failed to persist secret: {}", e);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                &request_id,
+                "Failed to store secret",
+                e.to_string(),
+            )
+        }
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/nodes/{node_id}/secrets/{name}",
+    tag = "secrets",
+    params(("node_id" = String, Path, description = "Node id"), ("name" = String, Path, description = "Secret name"), NodePasswordParams),
+    responses((status = 200, description = "The secret is gone", body = Object), (status = 202, description = "Held for approval", body = Object))
+)]
+pub async fn delete_secret(
+    Path((node_id, name)): Path<(String, String)>,
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(secrets): Extension<SecretStore>,
+    query: NodePasswordParams,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = Validator::new()
+        .secret_name("name", &name)
+        .reject(&request_id)
+    {
+        return response;
+    }
+    let auth = query.for_node(node_id);
+    if let Some(response) = reject_unknown_node(&nodes, &auth, &request_id).await {
+        return response;
+    }
+
+    match secrets.delete(&auth.node_id, &name) {
+        Ok(true) => {
+            let body = json!({
+                "req_id": request_id,
+                "node_id": auth.node_id,
+                "deleted": name,
+            });
+            (StatusCode::OK, Json(body)).into_response()
+        }
+        Ok(false) => error_response(
+            StatusCode::NOT_FOUND,
+            &request_id,
+            "Secret not found",
+            format!("Node {} has no secret '{}'", auth.node_id, name),
+        ),
+        Err(e) => {
+            error!("Failed to persist secret removal: {}", e);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                &request_id,
+                "Failed to delete secret",
+                e.to_string(),
+            )
+        }
+    }
+}
@@ -0,0 +1,544 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::{
+    Form, Json, Router,
+    extract::{Query, Request, State},
+    http::{HeaderMap, HeaderValue, Method, StatusCode, header},
+    middleware::Next,
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::get,
+};
+use dashmap::DashMap;
+use lib_coordinator_core::{NodeChannels, verify_node};
+use serde::Deserialize;
+use serde_json::json;
+use subtle::ConstantTimeEq;
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::bearer_tokens::with_node_id;
+use crate::validation::{ValidJson, Validator};
+use crate::{AuthParams, VerifiedNode, error_response, reject_unknown_node};
+
+pub const SESSION_COOKIE: &str = "docklord_session";
+pub const CSRF_COOKIE: &str = "docklord_csrf";
+pub const CSRF_HEADER: &str = "x-csrf-token";
+pub const LOGIN_PATH: &str = "/login";
+const DEFAULT_SESSION_TTL_SECS: u64 = 8 * 3600;
+const MIN_SESSION_TTL_SECS: u64 = 60;
+const MAX_SESSION_TTL_SECS: u64 = 7 * 24 * 3600;
+const DEFAULT_LANDING_PATH: &str = "/api/docs";
+
+struct Session {
+    node_id: String,
+    csrf_token: String,
+    expires_at: Instant,
+}
+
+/// Browser sessions for node credentials, kept in memory like bearer tokens
+/// so they end with the coordinator. Only the signed-in node is kept, never its
+/// password.
+#[derive(Clone, Default)]
+pub struct SessionStore {
+    sessions: Arc<DashMap<String, Session>>,
+}
+
+impl SessionStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A new session id and its CSRF token.
+    fn open(&self, node_id: String, ttl: Duration) -> (String, String) {
+        let now = Instant::now();
+        self.sessions.retain(|_, session| session.expires_at > now);
+        let id = random_token();
+        let csrf_token = random_token();
+        self.sessions.insert(
+            id.clone(),
+            Session {
+                node_id,
+                csrf_token: csrf_token.clone(),
+                expires_at: now + ttl,
+            },
+        );
+        (id, csrf_token)
+    }
+
+    /// The node and CSRF token of a session that has not expired.
+    fn resolve(&self, id: &str) -> Option<(String, String, Duration)> {
+        let session = self.sessions.get(id)?;
+        let now = Instant::now();
+        if session.expires_at <= now {
+            drop(session);
+            self.sessions.remove(id);
+            return None;
+        }
+        Some((
+            session.node_id.clone(),
+            session.csrf_token.clone(),
+            session.expires_at - now,
+        ))
+    }
+
+    fn close(&self, id: &str) -> bool {
+        self.sessions.remove(id).is_some()
+    }
+}
+
+/// State of the session cookie middleware and the login routes.
+#[derive(Clone)]
+pub struct SessionAuth {
+    sessions: SessionStore,
+    nodes: NodeChannels,
+    secure_cookies: bool,
+}
+
+impl SessionAuth {
+    /// `secure_cookies` marks the cookies `Secure`, so browsers only send
+    /// them over HTTPS (and to `localhost`).
+    pub fn new(sessions: SessionStore, nodes: NodeChannels, secure_cookies: bool) -> Self {
+        Self {
+            sessions,
+            nodes,
+            secure_cookies,
+        }
+    }
+
+    fn cookie(&self, name: &str, value: &str, http_only: bool, max_age: u64) -> HeaderValue {
+        let mut cookie = format!("{name}={value}; Path=/; Max-Age={max_age}; SameSite=Strict");
+        if http_only {
+            cookie.push_str("; HttpOnly");
+        }
+        if self.secure_cookies {
+            cookie.push_str("; Secure");
+        }
+        HeaderValue::from_str(&cookie).expect("cookie values are url-safe")
+    }
+
+    fn set_cookies(&self, headers: &mut HeaderMap, id: &str, csrf_token: &str, ttl: u64) {
+        headers.append(
+            header::SET_COOKIE,
+            self.cookie(SESSION_COOKIE, id, true, ttl),
+        );
+        // Readable by the page, which echoes it in the CSRF header
+        headers.append(
+            header::SET_COOKIE,
+            self.cookie(CSRF_COOKIE, csrf_token, false, ttl),
+        );
+    }
+
+    /// Opens a session for connected node credentials: its id and CSRF token.
+    async fn login(&self, auth: AuthParams, ttl: u64) -> Option<(String, String)> {
+        if !verify_node(&self.nodes, &auth.node_id, &auth.password).await {
+            return None;
+        }
+        info!(
+            "Opened a browser session for node {} ({} s)",
+            auth.node_id, ttl
+        );
+        Some(self.sessions.open(auth.node_id, Duration::from_secs(ttl)))
+    }
+}
+
+/// Resolves the session cookie into the session's node, like a bearer token. Requests that change anything must
+/// also echo the session's CSRF token in `X-CSRF-Token`. Requests that already
+/// carry credentials (a bearer token or query parameters) are left alone.
+pub async fn resolve_session_cookie(
+    State(auth): State<SessionAuth>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let Some(id) = cookie(request.headers(), SESSION_COOKIE).map(str::to_string) else {
+        return next.run(request).await;
+    };
+    if has_credentials(&request) {
+        return next.run(request).await;
+    }
+    let Some((node_id, csrf_token, _)) = auth.sessions.resolve(&id) else {
+        return error_response(
+            StatusCode::UNAUTHORIZED,
+            &Uuid::new_v4().to_string(),
+            "Session expired",
+            format!("Sign in again at {}", LOGIN_PATH),
+        );
+    };
+    if !is_safe(request.method()) && !csrf_matches(request.headers(), &csrf_token) {
+        return error_response(
+            StatusCode::FORBIDDEN,
+            &Uuid::new_v4().to_string(),
+            "CSRF token missing or wrong",
+            format!(
+                "Send the value of the {} cookie in the X-CSRF-Token header",
+                CSRF_COOKIE
+            ),
+        );
+    }
+
+    let uri = match with_node_id(request.uri(), &node_id) {
+        Ok(uri) => uri,
+        Err(detail) => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                &Uuid::new_v4().to_string(),
+                "Invalid query",
+                detail,
+            );
+        }
+    };
+    *request.uri_mut() = uri;
+    request.extensions_mut().insert(VerifiedNode(node_id));
+    next.run(request).await
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct SessionRequest {
+    node_id: String,
+    password: String,
+    /// Session lifetime in seconds (default 28800, 60 to 604800).
+    ttl_secs: Option<u64>,
+}
+
+/// Signs in with node credentials and sets the session and CSRF cookies. The
+/// node must be connected.
+#[utoipa::path(
+    post,
+    path = "/api/auth/session",
+    tag = "auth",
+    request_body = SessionRequest,
+    responses((status = 200, description = "The session's node, lifetime and CSRF token; the cookies are set", body = Object))
+)]
+pub async fn open_session(
+    State(auth): State<SessionAuth>,
+    ValidJson(body): ValidJson<SessionRequest>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = Validator::new()
+        .range(
+            "ttl_secs",
+            body.ttl_secs,
+            MIN_SESSION_TTL_SECS,
+            MAX_SESSION_TTL_SECS,
+        )
+        .reject(&request_id)
+    {
+        return response;
+    }
+    let ttl = body.ttl_secs.unwrap_or(DEFAULT_SESSION_TTL_SECS);
+    let node_id = body.node_id.clone();
+    let credentials = AuthParams {
+        node_id: body.node_id,
+        password: body.password,
+    };
+    if let Some(response) = reject_unknown_node(&auth.nodes, &credentials, &request_id).await {
+        return response;
+    }
+    let Some((id, csrf_token)) = auth.login(credentials, ttl).await else {
+        return not_signed_in(&request_id);
+    };
+    let body = json!({
+        "req_id": request_id,
+        "node_id": node_id,
+        "expires_in": ttl,
+        "csrf_token": csrf_token,
+    });
+    let mut response = (StatusCode::OK, Json(body)).into_response();
+    auth.set_cookies(response.headers_mut(), &id, &csrf_token, ttl);
+    response
+}
+
+/// The signed-in node and the CSRF token to send with changes.
+#[utoipa::path(
+    get,
+    path = "/api/auth/session",
+    tag = "auth",
+    responses((status = 200, description = "The session's node, remaining lifetime and CSRF token", body = Object))
+)]
+pub async fn get_session(State(auth): State<SessionAuth>, headers: HeaderMap) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    let Some((node_id, csrf_token, remaining)) =
+        cookie(&headers, SESSION_COOKIE).and_then(|id| auth.sessions.resolve(id))
+    else {
+        return not_signed_in(&request_id);
+    };
+    let body = json!({
+        "req_id": request_id,
+        "node_id": node_id,
+        "expires_in": remaining.as_secs(),
+        "csrf_token": csrf_token,
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+/// Signs out: ends the session and clears its cookies. Needs the CSRF token.
+#[utoipa::path(
+    delete,
+    path = "/api/auth/session",
+    tag = "auth",
+    responses((status = 200, description = "The session no longer works", body = Object))
+)]
+pub async fn close_session(State(auth): State<SessionAuth>, headers: HeaderMap) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    let Some(id) = cookie(&headers, SESSION_COOKIE) else {
+        return not_signed_in(&request_id);
+    };
+    if let Some((_, csrf_token, _)) = auth.sessions.resolve(id) {
+        if !csrf_matches(&headers, &csrf_token) {
+            return error_response(
+                StatusCode::FORBIDDEN,
+                &request_id,
+                "CSRF token missing or wrong",
+                format!(
+                    "Send the value of the {} cookie in the X-CSRF-Token header",
+                    CSRF_COOKIE
+                ),
+            );
+        }
+        auth.sessions.close(id);
+    }
+    let mut response = (
+        StatusCode::OK,
+        Json(json!({ "req_id": request_id, "signed_out": true })),
+    )
+        .into_response();
+    auth.set_cookies(response.headers_mut(), "", "", 0);
+    response
+}
+
+#[derive(Deserialize)]
+pub struct LoginPageParams {
+    /// Where to go after signing in; a path on this coordinator.
+    next: Option<String>,
+    /// Set after a failed attempt.
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct LoginForm {
+    node_id: String,
+    password: String,
+    next: Option<String>,
+}
+
+/// The sign-in form.
+async fn login_page(Query(params): Query<LoginPageParams>) -> Html<String> {
+    let next = landing_path(params.next.as_deref());
+    let error = if params.error.is_some() {
+        r#"<p class="error">Unknown node or wrong password.</p>"#
+    } else {
+        ""
+    };
+    Html(format!(
+        r#"<!doctype html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Sign in - Docklord</title>
+<style>
+body {{ font-family: system-ui, sans-serif; display: flex; justify-content: center; margin-top: 10vh; }}
+form {{ display: flex; flex-direction: column; gap: 0.75rem; width: 18rem; }}
+input, button {{ font: inherit; padding: 0.4rem; }}
+.error {{ color: #b00020; }}
+</style>
+</head>
+<body>
+<form method="post" action="{LOGIN_PATH}">
+<h1>Docklord</h1>
+{error}
+<label>Node ID <input name="node_id" autocomplete="username" required autofocus></label>
+<label>Password <input name="password" type="password" autocomplete="current-password" required></label>
+<input type="hidden" name="next" value="{next}">
+<button type="submit">Sign in</button>
+</form>
+</body>
+</html>
+"#,
+        next = escape_html(&next),
+    ))
+}
+
+/// Form sign-in: sets the cookies and redirects to `next`, or back to the
+/// form on failure.
+async fn login_form(State(auth): State<SessionAuth>, Form(form): Form<LoginForm>) -> Response {
+    let next = landing_path(form.next.as_deref());
+    let credentials = AuthParams {
+        node_id: form.node_id,
+        password: form.password,
+    };
+    let Some((id, csrf_token)) = auth.login(credentials, DEFAULT_SESSION_TTL_SECS).await else {
+        let query = serde_urlencoded::to_string([("error", "1"), ("next", next.as_str())])
+            .unwrap_or_default();
+        return Redirect::to(&format!("{}?{}", LOGIN_PATH, query)).into_response();
+    };
+    let mut response = Redirect::to(&next).into_response();
+    auth.set_cookies(
+        response.headers_mut(),
+        &id,
+        &csrf_token,
+        DEFAULT_SESSION_TTL_SECS,
+    );
+    response
+}
+
+/// The sign-in form and the session routes. Served outside the credential
+/// layers, since they are how a browser gets credentials.
+pub fn session_routes(auth: SessionAuth) -> Router {
+    Router::new()
+        .route(LOGIN_PATH, get(login_page).post(login_form))
+        .route(
+            "/api/auth/session",
+            get(get_session).post(open_session).delete(close_session),
+        )
+        .with_state(auth)
+}
+
+fn not_signed_in(request_id: &str) -> Response {
+    error_response(
+        StatusCode::UNAUTHORIZED,
+        request_id,
+        "Not signed in",
+        format!("Sign in at {} or POST /api/auth/session", LOGIN_PATH),
+    )
+}
+
+fn random_token() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+fn cookie<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, value)| *key == name && !value.is_empty())
+        .map(|(_, value)| value)
+}
+
+fn has_credentials(request: &Request) -> bool {
+    request.headers().contains_key(header::AUTHORIZATION)
+        || request.uri().query().is_some_and(|query| {
+            serde_urlencoded::from_str::<Vec<(String, String)>>(query)
+                .is_ok_and(|pairs| pairs.iter().any(|(key, _)| key == "password"))
+        })
+}
+
+fn is_safe(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+fn csrf_matches(headers: &HeaderMap, expected: &str) -> bool {
+    headers
+        .get(CSRF_HEADER)
+        .and_then(|value| value.to_str().ok())
+        // In constant time, so the token cannot be guessed byte by byte
+        .is_some_and(|given| bool::from(given.as_bytes().ct_eq(expected.as_bytes())))
+}
+
+// Only paths on this host, so the form cannot redirect elsewhere
+fn landing_path(next: Option<&str>) -> String {
+    match next {
+        Some(path) if path.starts_with('/') && !path.starts_with("//") && !path.contains('\\') => {
+            path.to_string()
+        }
+        _ => DEFAULT_LANDING_PATH.to_string(),
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, middleware};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn app(sessions: SessionStore) -> Router {
+        let auth = SessionAuth::new(sessions, NodeChannels::default(), true);
+        Router::new()
+            .route(
+                "/api/containers/web/stop",
+                get(|| async { "ok" }).post(|| async { "ok" }),
+            )
+            .layer(middleware::from_fn_with_state(auth, resolve_session_cookie))
+    }
+
+    async fn status(
+        app: &Router,
+        method: Method,
+        uri: &str,
+        headers: &[(&str, &str)],
+    ) -> StatusCode {
+        let mut request = Request::builder().method(method).uri(uri);
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let request = request.body(Body::empty()).unwrap();
+        app.clone().oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn changes_need_the_csrf_token() {
+        let sessions = SessionStore::new();
+        let (id, csrf_token) = sessions.open("web".to_string(), Duration::from_secs(60));
+        let app = app(sessions);
+        let cookie = format!("{}={}", SESSION_COOKIE, id);
+        let uri = "/api/containers/web/stop";
+
+        let read = status(&app, Method::GET, uri, &[("cookie", &cookie)]).await;
+        assert_eq!(read, StatusCode::OK);
+        let missing = status(&app, Method::POST, uri, &[("cookie", &cookie)]).await;
+        assert_eq!(missing, StatusCode::FORBIDDEN);
+        let wrong = [("cookie", cookie.as_str()), (CSRF_HEADER, "guess")];
+        assert_eq!(
+            status(&app, Method::POST, uri, &wrong).await,
+            StatusCode::FORBIDDEN
+        );
+        let echoed = [
+            ("cookie", cookie.as_str()),
+            (CSRF_HEADER, csrf_token.as_str()),
+        ];
+        assert_eq!(
+            status(&app, Method::POST, uri, &echoed).await,
+            StatusCode::OK
+        );
+    }
+
+    #[tokio::test]
+    async fn ended_sessions_are_refused() {
+        let sessions = SessionStore::new();
+        let (expired, _) = sessions.open("web".to_string(), Duration::ZERO);
+        let (closed, _) = sessions.open("web".to_string(), Duration::from_secs(60));
+        assert!(sessions.close(&closed));
+        let app = app(sessions);
+
+        for id in [expired, closed] {
+            let cookie = format!("{}={}", SESSION_COOKIE, id);
+            let uri = "/api/containers/web/stop";
+            assert_eq!(
+                status(&app, Method::GET, uri, &[("cookie", &cookie)]).await,
+                StatusCode::UNAUTHORIZED
+            );
+        }
+    }
+
+    #[test]
+    fn landing_path_stays_on_this_host() {
+        assert_eq!(landing_path(Some("/api/containers")), "/api/containers");
+        for next in ["https://evil.example", "//evil.example", "/\\evil.example"] {
+            assert_eq!(landing_path(Some(next)), DEFAULT_LANDING_PATH);
+        }
+        assert_eq!(landing_path(None), DEFAULT_LANDING_PATH);
+    }
+}
@@ -0,0 +1,238 @@
+use std::time::Duration;
+
+use axum::{
+    Extension, Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use lib_coordinator_core::{CommandRouter, PendingResponses};
+use proto::generated::{
+    Envelope, GetSystemDiskUsage, NodeCommand, PruneSystem, RequestType, SystemDiskUsage,
+    SystemPruned, envelope::Payload, node_command, node_response,
+};
+use serde::Deserialize;
+use serde_json::json;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::AuthParams;
+use crate::node_request::request_node;
+use crate::timestamps::Timestamp;
+use crate::validation::{ValidJson, Validator};
+
+// The node allows `system df` 2 minutes and each prune step 5 minutes
+const DISK_USAGE_TIMEOUT: Duration = Duration::from_secs(130);
+const PRUNE_SYSTEM_TIMEOUT: Duration = Duration::from_secs(1520);
+const MAX_PRUNE_LABELS: usize = 32;
+const MAX_LABEL_FILTER_LEN: usize = 255;
+
+/// Objects, bytes and reclaimable bytes for images, containers, volumes and
+/// the build cache, like `docker system df` on the host.
+#[utoipa::path(
+    get,
+    path = "/api/system/df",
+    tag = "system",
+    params(AuthParams),
+    responses((status = 200, description = "Disk usage per kind", body = Object))
+)]
+pub async fn get_system_disk_usage(
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    auth: AuthParams,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    let kind = node_command::Kind::GetSystemDiskUsage(GetSystemDiskUsage {
+        request_id: request_id.clone(),
+    });
+    match send(
+        &server_tx,
+        &pending,
+        &auth,
+        &request_id,
+        RequestType::GetSystemDiskUsage,
+        kind,
+        DISK_USAGE_TIMEOUT,
+    )
+    .await
+    {
+        Ok(response) => {
+            let body = json!({
+                "id": request_id,
+                "node_id": auth.node_id,
+                "usage": usage(response),
+            });
+            (StatusCode::OK, Json(body)).into_response()
+        }
+        Err(response) => response,
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct PruneSystemBody {
+    /// Stopped containers. Default true.
+    containers: Option<bool>,
+    /// Dangling images, or every unused image with `all_images`. Default true.
+    images: Option<bool>,
+    /// Networks no container uses. Default true.
+    networks: Option<bool>,
+    /// Anonymous volumes no container uses, or named ones too with
+    /// `all_volumes`. Default false, as with `docker system prune`.
+    volumes: Option<bool>,
+    /// Build cache. Default true.
+    build_cache: Option<bool>,
+    #[serde(default)]
+    all_images: bool,
+    #[serde(default)]
+    all_volumes: bool,
+    /// Only objects created before this: unix seconds, RFC 3339 or a duration
+    /// such as `24h`. Not applied to volumes.
+    until: Option<String>,
+    /// `key` or `key=value` to keep only matching objects; `!` in front to
+    /// skip them instead.
+    #[serde(default)]
+    labels: Vec<String>,
+}
+
+/// Deletes unused containers, images, networks, volumes and build cache on
+/// the node, like `docker system prune`, and reports what went.
+#[utoipa::path(
+    post,
+    path = "/api/system/prune",
+    tag = "system",
+    params(AuthParams),
+    request_body = PruneSystemBody,
+    responses((status = 200, description = "What was deleted and the space reclaimed", body = Object), (status = 202, description = "Held for approval", body = Object))
+)]
+pub async fn prune_system(
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    auth: AuthParams,
+    ValidJson(body): ValidJson<PruneSystemBody>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    let mut until = body.until.unwrap_or_default();
+    // Nodes take unix seconds or durations
+    if let Some(time) = Timestamp::parse(&until) {
+        until = time.0.to_string();
+    }
+    let digits = until.trim_end_matches(['s', 'm', 'h']);
+    let containers = body.containers.unwrap_or(true);
+    let images = body.images.unwrap_or(true);
+    let networks = body.networks.unwrap_or(true);
+    let volumes = body.volumes.unwrap_or(false);
+    let build_cache = body.build_cache.unwrap_or(true);
+    if let Some(response) = Validator::new()
+        .check(
+            until.is_empty()
+                || (!digits.is_empty()
+                    && digits.len() + 1 >= until.len()
+                    && digits.chars().all(|c| c.is_ascii_digit())),
+            "until",
+            "must be unix seconds, RFC 3339 or a duration such as 30m or 24h",
+        )
+        .check(
+            body.labels.len() <= MAX_PRUNE_LABELS,
+            "labels",
+            "must have at most 32 entries",
+        )
+        .check(
+            body.labels.iter().all(|label| {
+                let key = label.strip_prefix('!').unwrap_or(label);
+                let key = key.split_once('=').map_or(key, |(key, _)| key);
+                !key.is_empty()
+                    && label.len() <= MAX_LABEL_FILTER_LEN
+                    && !label.chars().any(char::is_control)
+            }),
+            "labels",
+            "must each be key or key=value, optionally prefixed with '!'",
+        )
+        .check(
+            containers || images || networks || volumes || build_cache,
+            "containers",
+            "at least one of containers, images, networks, volumes or build_cache must be true",
+        )
+        .reject(&request_id)
+    {
+        return response;
+    }
+
+    let kind = node_command::Kind::PruneSystem(PruneSystem {
+        request_id: request_id.clone(),
+        containers,
+        images,
+        networks,
+        volumes,
+        build_cache,
+        all_images: body.all_images,
+        all_volumes: body.all_volumes,
+        until,
+        labels: body.labels,
+    });
+    match send(
+        &server_tx,
+        &pending,
+        &auth,
+        &request_id,
+        RequestType::PruneSystem,
+        kind,
+        PRUNE_SYSTEM_TIMEOUT,
+    )
+    .await
+    {
+        Ok(response) => {
+            let body = json!({
+                "id": request_id,
+                "node_id": auth.node_id,
+                "result": pruned(response),
+            });
+            (StatusCode::OK, Json(body)).into_response()
+        }
+        Err(response) => response,
+    }
+}
+
+async fn send(
+    server_tx: &CommandRouter,
+    pending: &PendingResponses,
+    auth: &AuthParams,
+    request_id: &str,
+    request_type: RequestType,
+    kind: node_command::Kind,
+    timeout: Duration,
+) -> Result<Option<node_response::Kind>, Response> {
+    let envelope = Envelope {
+        payload: Some(Payload::NodeCommand(NodeCommand { kind: Some(kind) })),
+        ..Default::default()
+    };
+    match request_node(
+        server_tx,
+        pending,
+        auth,
+        request_id,
+        request_type as i32,
+        envelope,
+        timeout,
+    )
+    .await
+    {
+        Ok(response) => Ok(match response.payload {
+            Some(Payload::NodeResponse(node_resp)) => node_resp.kind,
+            _ => None,
+        }),
+        Err(e) => Err(e.into_response(request_id)),
+    }
+}
+
+fn usage(response: Option<node_response::Kind>) -> Option<SystemDiskUsage> {
+    match response {
+        Some(node_response::Kind::SystemDiskUsage(usage)) => Some(usage),
+        _ => None,
+    }
+}
+
+fn pruned(response: Option<node_response::Kind>) -> Option<SystemPruned> {
+    match response {
+        Some(node_response::Kind::SystemPruned(pruned)) => Some(pruned),
+        _ => None,
+    }
+}
@@ -0,0 +1,106 @@
+use axum::{Extension, Json, response::IntoResponse};
+use lib_coordinator_core::{CommandRouter, PendingResponses};
+use proto::generated::{
+    Envelope, GetSystemInfo, NodeCommand, RequestType, envelope::Payload, node_command,
+    node_response,
+};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::AuthParams;
+use crate::node_request::request_node;
+
+const GET_SYSTEM_INFO_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(15);
+
+/// The node's Docker engine: version, host OS, storage driver and totals, like
+/// `docker info` and `docker version` on the host.
+#[utoipa::path(
+    get,
+    path = "/api/system/info",
+    tag = "system",
+    params(AuthParams),
+    responses((status = 200, description = "Engine and host details", body = Object))
+)]
+pub async fn get_system_info(
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    query: AuthParams,
+) -> impl IntoResponse {
+    let request_id = Uuid::new_v4().to_string();
+
+    let envelope = Envelope {
+        payload: Some(Payload::NodeCommand(NodeCommand {
+            kind: Some(node_command::Kind::GetSystemInfo(GetSystemInfo {
+                request_id: request_id.clone(),
+            })),
+        })),
+        ..Default::default()
+    };
+
+    match request_node(
+        &server_tx,
+        &pending,
+        &query,
+        &request_id,
+        RequestType::GetSystemInfo as i32,
+        envelope,
+        GET_SYSTEM_INFO_TIMEOUT,
+    )
+    .await
+    {
+        Ok(response) => {
+            let info = extract_system_info_from_response(&response);
+            let body = json!({
+                "id": request_id,
+                "node_id": query.node_id,
+                "info": info,
+            });
+            (axum::http::StatusCode::OK, Json(body)).into_response()
+        }
+        Err(e) => e.into_response(&request_id),
+    }
+}
+
+fn extract_system_info_from_response(response: &Envelope) -> Option<serde_json::Value> {
+    if let Some(Payload::NodeResponse(node_resp)) = &response.payload
+        && let Some(node_response::Kind::SystemInfo(info)) = &node_resp.kind
+    {
+        return Some(json!({
+            "docker": {
+                "version": info.docker_version,
+                "api_version": info.api_version,
+                "min_api_version": info.min_api_version,
+                "git_commit": info.git_commit,
+                "go_version": info.go_version,
+                "storage_driver": info.storage_driver,
+                "logging_driver": info.logging_driver,
+                "cgroup_driver": info.cgroup_driver,
+                "cgroup_version": info.cgroup_version,
+                "root_dir": info.docker_root_dir,
+                "default_runtime": info.default_runtime,
+                "experimental": info.experimental,
+                "live_restore": info.live_restore,
+                "security_options": info.security_options,
+            },
+            "host": {
+                "hostname": info.hostname,
+                "os": info.os,
+                "os_type": info.os_type,
+                "os_version": info.os_version,
+                "kernel_version": info.kernel_version,
+                "architecture": info.architecture,
+                "ncpu": info.ncpu,
+                "mem_total": info.mem_total,
+            },
+            "containers": {
+                "total": info.containers,
+                "running": info.containers_running,
+                "paused": info.containers_paused,
+                "stopped": info.containers_stopped,
+            },
+            "images": info.images,
+            "warnings": info.warnings,
+        }));
+    }
+    None
+}
@@ -0,0 +1,380 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use axum::{
+    Extension, Json,
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use lib_coordinator_core::{
+    CommandRouter, NodeChannels, PendingResponses, SecretStore, TEMPLATE_LABEL, Template,
+    TemplateProblem, TemplateStore,
+};
+use proto::generated::{Envelope, NodeCommand, RequestType, envelope::Payload, node_command};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::error;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::node_request::request_node;
+use crate::tenants::CallerTenant;
+use crate::validation::{ValidJson, Validator};
+use crate::{AuthParams, error_response, reject_unknown_node};
+
+// The node may have to pull the image first
+pub(crate) const DEPLOY_TIMEOUT: Duration = Duration::from_secs(660);
+pub(crate) const MAX_TEMPLATES_ITEMS: usize = 100;
+
+#[derive(Deserialize, ToSchema)]
+pub struct DeployBody {
+    #[serde(default)]
+    variables: BTreeMap<String, String>,
+    /// Start the container after creating it.
+    #[serde(default = "default_start")]
+    start: bool,
+}
+
+pub(crate) fn default_start() -> bool {
+    true
+}
+
+fn caller_tenant(caller: &Option<Extension<CallerTenant>>) -> Option<&str> {
+    caller
+        .as_ref()
+        .map(|Extension(CallerTenant(tenant))| tenant.as_str())
+}
+
+fn validate_template(template: &Template, validator: &mut Validator) {
+    for problem in template.problems() {
+        validator.check(false, &problem.field, problem.message);
+    }
+    validator
+        .check(
+            template.ports.len() <= MAX_TEMPLATES_ITEMS,
+            "ports",
+            format!("at most {} ports", MAX_TEMPLATES_ITEMS),
+        )
+        .check(
+            template.volumes.len() <= MAX_TEMPLATES_ITEMS,
+            "volumes",
+            format!("at most {} volumes", MAX_TEMPLATES_ITEMS),
+        )
+        .check(
+            template.env.len() <= MAX_TEMPLATES_ITEMS,
+            "env",
+            format!("at most {} env vars", MAX_TEMPLATES_ITEMS),
+        );
+    // Placeholders are checked once the values are known
+    if !template.image.contains('$') {
+        validator.image_ref("image", &template.image);
+    }
+    for (i, secret) in template.secrets.iter().enumerate() {
+        validator.secret_name(&format!("secrets[{}].name", i), &secret.name);
+    }
+}
+
+/// The 422 response listing everything wrong with a deploy request.
+pub(crate) fn problems_response(problems: Vec<TemplateProblem>, request_id: &str) -> Response {
+    let mut validator = Validator::new();
+    for problem in problems {
+        validator.check(false, &problem.field, problem.message);
+    }
+    validator.reject(request_id).unwrap_or_else(|| {
+        error_response(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            request_id,
+            "Validation failed",
+            "template could not be rendered".to_string(),
+        )
+    })
+}
+
+fn template_not_found(request_id: &str, template_id: &str) -> Response {
+    error_response(
+        StatusCode::NOT_FOUND,
+        request_id,
+        "Template not found",
+        format!("No template with id {}", template_id),
+    )
+}
+
+fn store_failed(request_id: &str, e: std::io::Error) -> Response {
+    error!("Failed to persist templates: {}", e);
+    error_response(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        request_id,
+        "Failed to store template",
+        e.to_string(),
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/templates",
+    tag = "templates",
+    params(AuthParams),
+    responses((status = 200, description = "Stored templates", body = Object))
+)]
+pub async fn list_templates(
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(templates): Extension<TemplateStore>,
+    caller: Option<Extension<CallerTenant>>,
+    query: AuthParams,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = reject_unknown_node(&nodes, &query, &request_id).await {
+        return response;
+    }
+
+    let body = json!({
+        "req_id": request_id,
+        "templates": templates.list(caller_tenant(&caller)),
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/templates/{template_id}",
+    tag = "templates",
+    params(("template_id" = String, Path, description = "Template id"), AuthParams),
+    responses((status = 200, description = "The template", body = Object))
+)]
+pub async fn get_template(
+    Path(template_id): Path<String>,
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(templates): Extension<TemplateStore>,
+    caller: Option<Extension<CallerTenant>>,
+    query: AuthParams,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = reject_unknown_node(&nodes, &query, &request_id).await {
+        return response;
+    }
+
+    match templates.get(&template_id, caller_tenant(&caller)) {
+        Some(template) => {
+            let body = json!({
+                "req_id": request_id,
+                "template": template,
+            });
+            (StatusCode::OK, Json(body)).into_response()
+        }
+        None => template_not_found(&request_id, &template_id),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/templates",
+    tag = "templates",
+    params(AuthParams),
+    request_body = Template,
+    responses((status = 201, description = "The stored template", body = Object))
+)]
+pub async fn create_template(
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(templates): Extension<TemplateStore>,
+    caller: Option<Extension<CallerTenant>>,
+    query: AuthParams,
+    ValidJson(template): ValidJson<Template>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    let mut validator = Validator::new();
+    validate_template(&template, &mut validator);
+    if let Some(response) = validator.reject(&request_id) {
+        return response;
+    }
+    if let Some(response) = reject_unknown_node(&nodes, &query, &request_id).await {
+        return response;
+    }
+
+    let template_id = Uuid::new_v4().to_string();
+    match templates.create(&template_id, caller_tenant(&caller), template) {
+        Ok(template) => {
+            let body = json!({
+                "req_id": request_id,
+                "template": template,
+            });
+            (StatusCode::CREATED, Json(body)).into_response()
+        }
+        Err(e) => store_failed(&request_id, e),
+    }
+}
+
+/// Replaces a template's spec; containers deployed from it are left as they are.
+#[utoipa::path(
+    put,
+    path = "/api/templates/{template_id}",
+    tag = "templates",
+    params(("template_id" = String, Path, description = "Template id"), AuthParams),
+    request_body = Template,
+    responses((status = 200, description = "The stored template", body = Object))
+)]
+pub async fn replace_template(
+    Path(template_id): Path<String>,
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(templates): Extension<TemplateStore>,
+    caller: Option<Extension<CallerTenant>>,
+    query: AuthParams,
+    ValidJson(template): ValidJson<Template>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    let mut validator = Validator::new();
+    validate_template(&template, &mut validator);
+    if let Some(response) = validator.reject(&request_id) {
+        return response;
+    }
+    if let Some(response) = reject_unknown_node(&nodes, &query, &request_id).await {
+        return response;
+    }
+
+    match templates.replace(&template_id, caller_tenant(&caller), template) {
+        Ok(Some(template)) => {
+            let body = json!({
+                "req_id": request_id,
+                "template": template,
+            });
+            (StatusCode::OK, Json(body)).into_response()
+        }
+        Ok(None) => template_not_found(&request_id, &template_id),
+        Err(e) => store_failed(&request_id, e),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/templates/{template_id}",
+    tag = "templates",
+    params(("template_id" = String, Path, description = "Template id"), AuthParams),
+    responses((status = 200, description = "The template is gone", body = Object))
+)]
+pub async fn delete_template(
+    Path(template_id): Path<String>,
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(templates): Extension<TemplateStore>,
+    caller: Option<Extension<CallerTenant>>,
+    query: AuthParams,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = reject_unknown_node(&nodes, &query, &request_id).await {
+        return response;
+    }
+
+    match templates.delete(&template_id, caller_tenant(&caller)) {
+        Ok(true) => {
+            let body = json!({
+                "req_id": request_id,
+                "deleted": template_id,
+            });
+            (StatusCode::OK, Json(body)).into_response()
+        }
+        Ok(false) => template_not_found(&request_id, &template_id),
+        Err(e) => store_failed(&request_id, e),
+    }
+}
+
+/// Creates a container from the template on `node_id`, filling in the
+/// variables and the node's secrets, and waits until the node reports it.
+#[allow(clippy::too_many_arguments)] // Axum extractors, one per piece of shared state
+#[utoipa::path(
+    post,
+    path = "/api/templates/{template_id}/deploy",
+    tag = "templates",
+    params(("template_id" = String, Path, description = "Template id"), AuthParams),
+    request_body = DeployBody,
+    responses((status = 201, description = "The new container id", body = Object))
+)]
+pub async fn deploy_template(
+    Path(template_id): Path<String>,
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    Extension(templates): Extension<TemplateStore>,
+    Extension(secrets): Extension<SecretStore>,
+    Extension(nodes): Extension<NodeChannels>,
+    caller: Option<Extension<CallerTenant>>,
+    query: AuthParams,
+    ValidJson(body): ValidJson<DeployBody>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = reject_unknown_node(&nodes, &query, &request_id).await {
+        return response;
+    }
+    let Some(stored) = templates.get(&template_id, caller_tenant(&caller)) else {
+        return template_not_found(&request_id, &template_id);
+    };
+
+    let mut command = match stored.template.render(&request_id, &body.variables) {
+        Ok(command) => command,
+        Err(problems) => return problems_response(problems, &request_id),
+    };
+    let mut validator = Validator::new();
+    validator.image_ref("image", &command.image);
+    if !command.name.is_empty() {
+        validator.container_id("container_name", &command.name);
+    }
+    match secrets.resolve(&query.node_id, &stored.template.secrets) {
+        Ok(resolved) => command.secrets = resolved,
+        Err(e) => {
+            validator.check(false, "secrets", e.to_string());
+        }
+    }
+    if let Some(response) = validator.reject(&request_id) {
+        return response;
+    }
+    command.start = body.start;
+    command
+        .labels
+        .insert(TEMPLATE_LABEL.to_string(), template_id.clone());
+
+    let envelope = Envelope {
+        payload: Some(Payload::NodeCommand(NodeCommand {
+            kind: Some(node_command::Kind::CreateContainer(command)),
+        })),
+        ..Default::default()
+    };
+
+    match request_node(
+        &server_tx,
+        &pending,
+        &query,
+        &request_id,
+        RequestType::CreateContainer as i32,
+        envelope,
+        DEPLOY_TIMEOUT,
+    )
+    .await
+    {
+        Ok(response) => {
+            let created = extract_container_created_from_response(&response);
+            let body = json!({
+                "id": request_id,
+                "template_id": template_id,
+                "node_id": query.node_id,
+                "container": created,
+            });
+            (StatusCode::CREATED, Json(body)).into_response()
+        }
+        Err(e) => e.into_response(&request_id),
+    }
+}
+
+pub(crate) fn extract_container_created_from_response(
+    response: &Envelope,
+) -> Option<serde_json::Value> {
+    if let Some(proto::generated::envelope::Payload::NodeResponse(node_resp)) = &response.payload
+        && let Some(proto::generated::node_response::Kind::ContainerCreated(created)) =
+            &node_resp.kind
+    {
+        return Some(json!({
+            "container_id": created.container_id,
+            "name": created.name,
+            "started": created.started,
+            "pulled": created.pulled,
+            "warnings": created.warnings,
+        }));
+    }
+    None
+}
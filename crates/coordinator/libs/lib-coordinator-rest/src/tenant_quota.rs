@@ -0,0 +1,128 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+
+const REQUEST_WINDOW: Duration = Duration::from_secs(60);
+
+struct RequestWindow {
+    started: Instant,
+    count: u32,
+}
+
+/// Live consumption of the per-tenant quotas: requests in the current minute
+/// and open WebSocket connections. Node counts come from the tenant store.
+#[derive(Clone, Default)]
+pub struct TenantUsage {
+    requests: Arc<DashMap<String, RequestWindow>>,
+    ws_connections: Arc<DashMap<String, usize>>,
+}
+
+impl TenantUsage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts a request in the tenant's current minute, or tells how long until
+    /// the next minute starts when `limit` is reached.
+    pub(crate) fn count_request(&self, tenant: &str, limit: Option<u32>) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut window = self
+            .requests
+            .entry(tenant.to_string())
+            .or_insert_with(|| RequestWindow {
+                started: now,
+                count: 0,
+            });
+        if now.duration_since(window.started) >= REQUEST_WINDOW {
+            window.started = now;
+            window.count = 0;
+        }
+        if limit.is_some_and(|limit| window.count >= limit) {
+            return Err(REQUEST_WINDOW.saturating_sub(now.duration_since(window.started)));
+        }
+        window.count += 1;
+        Ok(())
+    }
+
+    /// Takes a WebSocket connection slot unless `limit` are already open.
+    pub(crate) fn open_ws(&self, tenant: &str, limit: Option<usize>) -> Option<TenantWsSlot> {
+        let mut open = self.ws_connections.entry(tenant.to_string()).or_insert(0);
+        if limit.is_some_and(|limit| *open >= limit) {
+            return None;
+        }
+        *open += 1;
+        Some(TenantWsSlot {
+            _slot: Arc::new(WsSlot {
+                usage: self.clone(),
+                tenant: tenant.to_string(),
+            }),
+        })
+    }
+
+    pub fn requests_this_minute(&self, tenant: &str) -> u32 {
+        self.requests
+            .get(tenant)
+            .filter(|window| window.started.elapsed() < REQUEST_WINDOW)
+            .map_or(0, |window| window.count)
+    }
+
+    pub fn ws_connections(&self, tenant: &str) -> usize {
+        self.ws_connections.get(tenant).map_or(0, |open| *open)
+    }
+}
+
+/// A tenant's open WebSocket connection. WebSocket handlers keep it for the
+/// lifetime of the socket; the slot is freed when the last clone is dropped.
+#[derive(Clone)]
+pub struct TenantWsSlot {
+    _slot: Arc<WsSlot>,
+}
+
+struct WsSlot {
+    usage: TenantUsage,
+    tenant: String,
+}
+
+impl Drop for WsSlot {
+    fn drop(&mut self) {
+        if let Some(mut open) = self.usage.ws_connections.get_mut(&self.tenant) {
+            *open = open.saturating_sub(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_requests_up_to_the_limit() {
+        let usage = TenantUsage::new();
+        assert!(usage.count_request("red", Some(2)).is_ok());
+        assert!(usage.count_request("red", Some(2)).is_ok());
+        let retry_after = usage.count_request("red", Some(2)).unwrap_err();
+        assert!(retry_after <= REQUEST_WINDOW);
+        assert_eq!(usage.requests_this_minute("red"), 2);
+        assert!(usage.count_request("blue", Some(2)).is_ok());
+        assert!(usage.count_request("red", None).is_ok());
+    }
+
+    #[test]
+    fn frees_websocket_slots_when_dropped() {
+        let usage = TenantUsage::new();
+        let first = usage.open_ws("red", Some(2)).unwrap();
+        let second = usage.open_ws("red", Some(2)).unwrap();
+        assert!(usage.open_ws("red", Some(2)).is_none());
+        assert_eq!(usage.ws_connections("red"), 2);
+
+        // Clones share the slot, which is freed with the last one
+        let clone = first.clone();
+        drop(first);
+        assert_eq!(usage.ws_connections("red"), 2);
+        drop(clone);
+        drop(second);
+        assert_eq!(usage.ws_connections("red"), 0);
+        assert!(usage.open_ws("red", Some(2)).is_some());
+    }
+}
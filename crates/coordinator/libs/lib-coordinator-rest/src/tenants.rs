@@ -0,0 +1,672 @@
+use std::collections::HashMap;
+
+use axum::{
+    Extension, Json,
+    extract::{Path, Query, Request, State},
+    http::{HeaderValue, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use lib_coordinator_core::{NodeAssignment, NodeStateCache, Tenant, TenantQuota, TenantStore};
+use serde_json::json;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::admin::{AdminParams, AdminToken};
+use crate::error_response;
+use crate::rate_limit::target_node_id;
+use crate::tenant_quota::TenantUsage;
+use crate::validation::{ValidJson, ValidQuery, Validator};
+use crate::ws_limit::is_websocket;
+
+pub const TENANT_KEY_HEADER: &str = "x-tenant-key";
+const TENANT_KEY_PARAM: &str = "tenant_key";
+const MAX_TENANT_NAME_LEN: usize = 64;
+
+/// The tenant whose API key authenticated the request, for handlers that list
+/// more than one node.
+#[derive(Debug, Clone)]
+pub struct CallerTenant(pub String);
+
+/// Id of the tenant API key that authenticated the request.
+#[derive(Debug, Clone)]
+pub struct CallerKey(pub String);
+
+/// State of the tenant isolation middleware.
+#[derive(Clone)]
+pub struct TenantAccess {
+    tenants: TenantStore,
+    admin: AdminToken,
+    usage: TenantUsage,
+}
+
+impl TenantAccess {
+    pub fn new(tenants: TenantStore, admin: AdminToken, usage: TenantUsage) -> Self {
+        Self {
+            tenants,
+            admin,
+            usage,
+        }
+    }
+}
+
+/// With multi-tenancy enabled, every REST and WebSocket request needs a tenant
+/// API key (`X-Tenant-Key` header or `tenant_key` parameter) and may only
+/// address nodes of that tenant, within the tenant's request and WebSocket
+/// quotas. The admin token bypasses the checks, and the tenant management
+/// routes check the admin token themselves.
+pub async fn enforce_tenant_access(
+    State(access): State<TenantAccess>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if !access.tenants.is_enabled() || request.uri().path().starts_with("/api/tenants") {
+        return next.run(request).await;
+    }
+
+    let params = Query::<HashMap<String, String>>::try_from_uri(request.uri())
+        .map(|q| q.0)
+        .unwrap_or_default();
+    let admin_params = AdminParams {
+        admin_token: params.get("admin_token").cloned(),
+    };
+    if admin_params.admin_token.is_some() && access.admin.reject(&admin_params, "").is_none() {
+        return next.run(request).await;
+    }
+
+    let request_id = Uuid::new_v4().to_string();
+    let key = request
+        .headers()
+        .get(TENANT_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .or(params.get(TENANT_KEY_PARAM).map(String::as_str));
+    let Some((tenant, key_id)) = key.and_then(|key| access.tenants.tenant_for_key(key)) else {
+        return error_response(
+            StatusCode::UNAUTHORIZED,
+            &request_id,
+            "Invalid tenant key",
+            format!(
+                "Multi-tenant coordinator: pass a tenant API key in the {} header or the {} parameter",
+                TENANT_KEY_HEADER, TENANT_KEY_PARAM
+            ),
+        );
+    };
+
+    if let Some(node_id) = target_node_id(&request)
+        && access.tenants.owner_of(&node_id).as_deref() != Some(tenant.as_str())
+    {
+        info!("Tenant {} denied access to node {}", tenant, node_id);
+        return error_response(
+            StatusCode::FORBIDDEN,
+            &request_id,
+            "Node not in tenant",
+            format!("Node {} is not assigned to tenant {}", node_id, tenant),
+        );
+    }
+
+    let quota = access.tenants.quota(&tenant);
+    if let Err(retry_after) = access
+        .usage
+        .count_request(&tenant, quota.max_requests_per_minute)
+    {
+        let secs = retry_after.as_secs().max(1);
+        let mut response = error_response(
+            StatusCode::TOO_MANY_REQUESTS,
+            &request_id,
+            "Tenant quota exceeded",
+            format!(
+                "Tenant {} may send {} requests per minute, retry in {} s",
+                tenant,
+                quota.max_requests_per_minute.unwrap_or_default(),
+                secs
+            ),
+        );
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, HeaderValue::from(secs));
+        return response;
+    }
+
+    if is_websocket(&request) {
+        let Some(slot) = access.usage.open_ws(&tenant, quota.max_ws_connections) else {
+            return error_response(
+                StatusCode::TOO_MANY_REQUESTS,
+                &request_id,
+                "Tenant quota exceeded",
+                format!(
+                    "Tenant {} may keep {} WebSocket connections open",
+                    tenant,
+                    quota.max_ws_connections.unwrap_or_default()
+                ),
+            );
+        };
+        request.extensions_mut().insert(slot);
+    }
+
+    request.extensions_mut().insert(CallerTenant(tenant));
+    request.extensions_mut().insert(CallerKey(key_id));
+    next.run(request).await
+}
+
+/// The caller's own tenant: its nodes with their connection state.
+#[utoipa::path(
+    get,
+    path = "/api/tenant",
+    tag = "tenants",
+    responses((status = 200, description = "The caller's tenant", body = Object))
+)]
+pub async fn get_own_tenant(
+    Extension(tenants): Extension<TenantStore>,
+    Extension(usage): Extension<TenantUsage>,
+    Extension(node_states): Extension<NodeStateCache>,
+    caller: Option<Extension<CallerTenant>>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    let Some(Extension(CallerTenant(name))) = caller else {
+        return error_response(
+            StatusCode::NOT_FOUND,
+            &request_id,
+            "Not a tenant",
+            "Multi-tenancy is disabled or the request used the admin token".to_string(),
+        );
+    };
+    let tenant = tenants.get(&name).unwrap_or_default();
+
+    let nodes: Vec<_> = tenant
+        .nodes
+        .iter()
+        .map(|node_id| {
+            json!({
+                "node_id": node_id,
+                "online": node_states.get(node_id).is_some_and(|state| state.online),
+            })
+        })
+        .collect();
+    let body = json!({
+        "req_id": request_id,
+        "tenant": name,
+        "nodes": nodes,
+        "api_keys": tenant.api_keys.keys().collect::<Vec<_>>(),
+        "quota": quota_json(&name, &tenant, &usage),
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/tenants",
+    tag = "tenants",
+    params(AdminParams),
+    responses((status = 200, description = "Tenants", body = Object))
+)]
+pub async fn list_tenants(
+    Extension(tenants): Extension<TenantStore>,
+    Extension(usage): Extension<TenantUsage>,
+    Extension(admin): Extension<AdminToken>,
+    ValidQuery(params): ValidQuery<AdminParams>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = admin.reject(&params, &request_id) {
+        return response;
+    }
+
+    let list: Vec<_> = tenants
+        .list()
+        .iter()
+        .map(|(name, tenant)| tenant_json(name, tenant, &usage))
+        .collect();
+    let body = json!({
+        "req_id": request_id,
+        "enabled": tenants.is_enabled(),
+        "tenants": list,
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/tenants/{tenant}",
+    tag = "tenants",
+    params(("tenant" = String, Path, description = "Tenant name"), AdminParams),
+    responses((status = 200, description = "The tenant already existed", body = Object), (status = 201, description = "The tenant was created", body = Object))
+)]
+pub async fn create_tenant(
+    Path(name): Path<String>,
+    Extension(tenants): Extension<TenantStore>,
+    Extension(admin): Extension<AdminToken>,
+    ValidQuery(params): ValidQuery<AdminParams>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = admin.reject(&params, &request_id) {
+        return response;
+    }
+    if let Some(response) = reject_bad_name(&name, &request_id) {
+        return response;
+    }
+
+    match tenants.create(&name) {
+        Ok(created) => {
+            if created {
+                info!("Created tenant {}", name);
+            }
+            let status = if created {
+                StatusCode::CREATED
+            } else {
+                StatusCode::OK
+            };
+            let body = json!({
+                "req_id": request_id,
+                "tenant": name,
+                "created": created,
+            });
+            (status, Json(body)).into_response()
+        }
+        Err(e) => store_error(&request_id, e),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/tenants/{tenant}",
+    tag = "tenants",
+    params(("tenant" = String, Path, description = "Tenant name"), AdminParams),
+    responses((status = 200, description = "The tenant is gone", body = Object))
+)]
+pub async fn delete_tenant(
+    Path(name): Path<String>,
+    Extension(tenants): Extension<TenantStore>,
+    Extension(admin): Extension<AdminToken>,
+    ValidQuery(params): ValidQuery<AdminParams>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = admin.reject(&params, &request_id) {
+        return response;
+    }
+
+    match tenants.delete(&name) {
+        Ok(true) => {
+            info!("Deleted tenant {}", name);
+            let body = json!({
+                "req_id": request_id,
+                "tenant": name,
+                "deleted": true,
+            });
+            (StatusCode::OK, Json(body)).into_response()
+        }
+        Ok(false) => tenant_not_found(&request_id, &name),
+        Err(e) => store_error(&request_id, e),
+    }
+}
+
+/// Issues a new API key. The key is only ever shown in this response.
+#[utoipa::path(
+    post,
+    path = "/api/tenants/{tenant}/keys",
+    tag = "tenants",
+    params(("tenant" = String, Path, description = "Tenant name"), AdminParams),
+    responses((status = 201, description = "The new key, shown only here", body = Object))
+)]
+pub async fn create_tenant_key(
+    Path(name): Path<String>,
+    Extension(tenants): Extension<TenantStore>,
+    Extension(admin): Extension<AdminToken>,
+    ValidQuery(params): ValidQuery<AdminParams>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = admin.reject(&params, &request_id) {
+        return response;
+    }
+
+    let key_id = Uuid::new_v4().simple().to_string()[..12].to_string();
+    let api_key = format!("dlt_{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+    match tenants.add_key(&name, &key_id, &api_key) {
+        Ok(true) => {
+            info!("Issued API key {} for tenant {}", key_id, name);
+            let body = json!({
+                "req_id": request_id,
+                "tenant": name,
+                "key_id": key_id,
+                "api_key": api_key,
+            });
+            (StatusCode::CREATED, Json(body)).into_response()
+        }
+        Ok(false) => tenant_not_found(&request_id, &name),
+        Err(e) => store_error(&request_id, e),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/tenants/{tenant}/keys/{key_id}",
+    tag = "tenants",
+    params(("tenant" = String, Path, description = "Tenant name"), ("key_id" = String, Path, description = "Key id"), AdminParams),
+    responses((status = 200, description = "The key no longer works", body = Object))
+)]
+pub async fn revoke_tenant_key(
+    Path((name, key_id)): Path<(String, String)>,
+    Extension(tenants): Extension<TenantStore>,
+    Extension(admin): Extension<AdminToken>,
+    ValidQuery(params): ValidQuery<AdminParams>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = admin.reject(&params, &request_id) {
+        return response;
+    }
+
+    match tenants.revoke_key(&name, &key_id) {
+        Ok(true) => {
+            info!("Revoked API key {} of tenant {}", key_id, name);
+            let body = json!({
+                "req_id": request_id,
+                "tenant": name,
+                "key_id": key_id,
+                "revoked": true,
+            });
+            (StatusCode::OK, Json(body)).into_response()
+        }
+        Ok(false) => error_response(
+            StatusCode::NOT_FOUND,
+            &request_id,
+            "API key not found",
+            format!("Tenant {} has no key {}", name, key_id),
+        ),
+        Err(e) => store_error(&request_id, e),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/tenants/{tenant}/nodes/{node_id}",
+    tag = "tenants",
+    params(("tenant" = String, Path, description = "Tenant name"), ("node_id" = String, Path, description = "Node id"), AdminParams),
+    responses((status = 200, description = "The node belongs to the tenant", body = Object), (status = 409, description = "Another tenant has the node", body = Object))
+)]
+pub async fn assign_tenant_node(
+    Path((name, node_id)): Path<(String, String)>,
+    Extension(tenants): Extension<TenantStore>,
+    Extension(admin): Extension<AdminToken>,
+    ValidQuery(params): ValidQuery<AdminParams>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = admin.reject(&params, &request_id) {
+        return response;
+    }
+
+    match tenants.assign_node(&name, &node_id) {
+        Ok(NodeAssignment::Assigned) => {
+            info!("Assigned node {} to tenant {}", node_id, name);
+            let body = json!({
+                "req_id": request_id,
+                "tenant": name,
+                "node_id": node_id,
+                "assigned": true,
+            });
+            (StatusCode::OK, Json(body)).into_response()
+        }
+        Ok(NodeAssignment::UnknownTenant) => tenant_not_found(&request_id, &name),
+        Ok(NodeAssignment::OwnedBy(owner)) => error_response(
+            StatusCode::CONFLICT,
+            &request_id,
+            "Node already assigned",
+            format!("Node {} belongs to tenant {}", node_id, owner),
+        ),
+        Ok(NodeAssignment::QuotaExceeded(max_nodes)) => error_response(
+            StatusCode::FORBIDDEN,
+            &request_id,
+            "Tenant quota exceeded",
+            format!("Tenant {} may own at most {} nodes", name, max_nodes),
+        ),
+        Err(e) => store_error(&request_id, e),
+    }
+}
+
+/// Replaces the tenant's quotas with the JSON body; omitted limits become unlimited.
+#[utoipa::path(
+    put,
+    path = "/api/tenants/{tenant}/quota",
+    tag = "tenants",
+    params(("tenant" = String, Path, description = "Tenant name"), AdminParams),
+    request_body = TenantQuota,
+    responses((status = 200, description = "The stored quotas", body = Object))
+)]
+pub async fn set_tenant_quota(
+    Path(name): Path<String>,
+    Extension(tenants): Extension<TenantStore>,
+    Extension(admin): Extension<AdminToken>,
+    ValidQuery(params): ValidQuery<AdminParams>,
+    ValidJson(quota): ValidJson<TenantQuota>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = admin.reject(&params, &request_id) {
+        return response;
+    }
+
+    match tenants.set_quota(&name, quota) {
+        Ok(true) => {
+            info!("Set quota of tenant {}: {:?}", name, quota);
+            let body = json!({
+                "req_id": request_id,
+                "tenant": name,
+                "quota": quota,
+            });
+            (StatusCode::OK, Json(body)).into_response()
+        }
+        Ok(false) => tenant_not_found(&request_id, &name),
+        Err(e) => store_error(&request_id, e),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/tenants/{tenant}/nodes/{node_id}",
+    tag = "tenants",
+    params(("tenant" = String, Path, description = "Tenant name"), ("node_id" = String, Path, description = "Node id"), AdminParams),
+    responses((status = 200, description = "The node no longer belongs to the tenant", body = Object))
+)]
+pub async fn release_tenant_node(
+    Path((name, node_id)): Path<(String, String)>,
+    Extension(tenants): Extension<TenantStore>,
+    Extension(admin): Extension<AdminToken>,
+    ValidQuery(params): ValidQuery<AdminParams>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = admin.reject(&params, &request_id) {
+        return response;
+    }
+
+    match tenants.release_node(&name, &node_id) {
+        Ok(true) => {
+            info!("Released node {} from tenant {}", node_id, name);
+            let body = json!({
+                "req_id": request_id,
+                "tenant": name,
+                "node_id": node_id,
+                "released": true,
+            });
+            (StatusCode::OK, Json(body)).into_response()
+        }
+        Ok(false) => error_response(
+            StatusCode::NOT_FOUND,
+            &request_id,
+            "Node not in tenant",
+            format!("Tenant {} has no node {}", name, node_id),
+        ),
+        Err(e) => store_error(&request_id, e),
+    }
+}
+
+fn tenant_json(name: &str, tenant: &Tenant, usage: &TenantUsage) -> serde_json::Value {
+    json!({
+        "tenant": name,
+        "nodes": tenant.nodes,
+        "api_keys": tenant.api_keys.keys().collect::<Vec<_>>(),
+        "quota": quota_json(name, tenant, usage),
+    })
+}
+
+/// Each quota with its current consumption; a `null` limit is unlimited.
+fn quota_json(name: &str, tenant: &Tenant, usage: &TenantUsage) -> serde_json::Value {
+    json!({
+        "nodes": {
+            "used": tenant.nodes.len(),
+            "limit": tenant.quota.max_nodes,
+        },
+        "requests_per_minute": {
+            "used": usage.requests_this_minute(name),
+            "limit": tenant.quota.max_requests_per_minute,
+        },
+        "ws_connections": {
+            "used": usage.ws_connections(name),
+            "limit": tenant.quota.max_ws_connections,
+        },
+    })
+}
+
+fn reject_bad_name(name: &str, request_id: &str) -> Option<Response> {
+    Validator::new()
+        .check(
+            !name.is_empty()
+                && name.len() <= MAX_TENANT_NAME_LEN
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-')),
+            "tenant",
+            format!(
+                "must be 1 to {} letters, digits, '_' or '-'",
+                MAX_TENANT_NAME_LEN
+            ),
+        )
+        .reject(request_id)
+}
+
+fn tenant_not_found(request_id: &str, name: &str) -> Response {
+    error_response(
+        StatusCode::NOT_FOUND,
+        request_id,
+        "Tenant not found",
+        name.to_string(),
+    )
+}
+
+fn store_error(request_id: &str, e: std::io::Error) -> Response {
+    error!("Failed to persist tenants: {}", e);
+    error_response(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        request_id,
+        "Failed to store tenants",
+        e.to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Router, body::Body, middleware, routing::get};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    // Tenant `red` owns node `web` and may send 3 requests a minute; `blue` owns `db`
+    fn app() -> Router {
+        let tenants = TenantStore::open(None, true);
+        for (tenant, key, node) in [("red", "red-key", "web"), ("blue", "blue-key", "db")] {
+            tenants.create(tenant).unwrap();
+            tenants.add_key(tenant, "k1", key).unwrap();
+            tenants.assign_node(tenant, node).unwrap();
+        }
+        let quota = TenantQuota {
+            max_requests_per_minute: Some(3),
+            ..Default::default()
+        };
+        tenants.set_quota("red", quota).unwrap();
+        let access = TenantAccess::new(
+            tenants,
+            AdminToken::new(Some("admin-secret".to_string())),
+            TenantUsage::new(),
+        );
+        Router::new()
+            .route("/api/containers", get(|| async { "ok" }))
+            .route("/api/nodes/{node_id}/metrics", get(|| async { "ok" }))
+            .layer(middleware::from_fn_with_state(
+                access,
+                enforce_tenant_access,
+            ))
+    }
+
+    async fn status(app: &Router, uri: &str, key: Option<&str>) -> StatusCode {
+        let mut request = Request::builder().uri(uri);
+        if let Some(key) = key {
+            request = request.header(TENANT_KEY_HEADER, key);
+        }
+        let request = request.body(Body::empty()).unwrap();
+        app.clone().oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn refuses_nodes_of_other_tenants() {
+        let app = app();
+        let own = "/api/containers?node_id=web";
+        assert_eq!(status(&app, own, Some("red-key")).await, StatusCode::OK);
+        let other = "/api/containers?node_id=db";
+        assert_eq!(
+            status(&app, other, Some("red-key")).await,
+            StatusCode::FORBIDDEN
+        );
+        let other_path = "/api/nodes/db/metrics";
+        assert_eq!(
+            status(&app, other_path, Some("red-key")).await,
+            StatusCode::FORBIDDEN
+        );
+        let unassigned = "/api/containers?node_id=cache";
+        assert_eq!(
+            status(&app, unassigned, Some("blue-key")).await,
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    #[tokio::test]
+    async fn needs_a_valid_tenant_key() {
+        let app = app();
+        let uri = "/api/containers?node_id=web";
+        assert_eq!(status(&app, uri, None).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            status(&app, uri, Some("wrong")).await,
+            StatusCode::UNAUTHORIZED
+        );
+        let in_query = "/api/containers?node_id=web&tenant_key=red-key";
+        assert_eq!(status(&app, in_query, None).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn admin_token_bypasses_tenant_checks() {
+        let app = app();
+        let admin = "/api/containers?node_id=db&admin_token=admin-secret";
+        assert_eq!(status(&app, admin, None).await, StatusCode::OK);
+        // A wrong admin token falls back to the checks of the tenant key
+        let wrong_admin = "/api/containers?node_id=db&admin_token=guess";
+        assert_eq!(
+            status(&app, wrong_admin, Some("red-key")).await,
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    #[tokio::test]
+    async fn stops_a_tenant_at_its_request_quota() {
+        let app = app();
+        let uri = "/api/containers?node_id=web";
+        for _ in 0..3 {
+            assert_eq!(status(&app, uri, Some("red-key")).await, StatusCode::OK);
+        }
+        let request = Request::builder()
+            .uri(uri)
+            .header(TENANT_KEY_HEADER, "red-key")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers().contains_key(header::RETRY_AFTER));
+
+        // Other tenants have their own quota
+        let blue = "/api/containers?node_id=db";
+        assert_eq!(status(&app, blue, Some("blue-key")).await, StatusCode::OK);
+    }
+}
@@ -0,0 +1,238 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use axum::{
+    Extension, Json,
+    extract::{Path, Request, State},
+    http::{Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use lib_coordinator_core::{EventLog, EventQuery, NodeChannels, SessionRecorder, StoredEvent};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::rate_limit::target_node_id;
+use crate::timestamps::Timestamp;
+use crate::usage::credential_label;
+use crate::validation::{ValidQuery, Validator};
+use crate::{AuthParams, reject_unknown_node};
+
+const DEFAULT_TIMELINE_LIMIT: usize = 200;
+const MAX_TIMELINE_LIMIT: usize = 1000;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TimelineQuery {
+    /// Unix seconds or RFC 3339, inclusive.
+    #[param(value_type = Option<String>)]
+    from: Option<Timestamp>,
+    #[param(value_type = Option<String>)]
+    to: Option<Timestamp>,
+    /// Most recent entries returned (default: 200).
+    limit: Option<usize>,
+}
+
+/// Middleware recording every request that changes a container in the event
+/// history as an `action`, with the credential that sent it and the status it
+/// got. Requests turned away for their credentials are not recorded.
+pub async fn record_container_actions(
+    State(event_log): State<EventLog>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let mutating = matches!(
+        *request.method(),
+        Method::POST | Method::PUT | Method::PATCH | Method::DELETE
+    );
+    let target = mutating
+        .then(|| container_action(request.method(), request.uri().path()))
+        .flatten()
+        .zip(target_node_id(&request));
+    let Some(((container_id, action), node_id)) = target else {
+        return next.run(request).await;
+    };
+    let actor = credential_label(&request);
+
+    let response = next.run(request).await;
+    let status = response.status();
+    if status != StatusCode::UNAUTHORIZED && status != StatusCode::FORBIDDEN {
+        event_log.append(StoredEvent {
+            seq: 0, // assigned by the log
+            node_id,
+            kind: "action".to_string(),
+            container_id: container_id.clone(),
+            name: container_id,
+            action,
+            time: now_secs(),
+            exit_code: 0,
+            image: String::new(),
+            health: String::new(),
+            actor,
+            status: status.as_u16(),
+        });
+    }
+    response
+}
+
+// The container a request path acts on and what it does: the path below the
+// container, e.g. `restart` or `annotations`, else the method. Restores from
+// the trash count too.
+fn container_action(method: &Method, path: &str) -> Option<(String, String)> {
+    let rest = path
+        .strip_prefix("/api/containers/")
+        .or_else(|| path.strip_prefix("/api/trash/"))?;
+    let (container_id, action) = match rest.split_once('/') {
+        Some((container_id, action)) => (container_id, action.to_string()),
+        None => (rest, method.as_str().to_ascii_lowercase()),
+    };
+    (!container_id.is_empty()).then(|| (container_id.to_string(), action))
+}
+
+/// What happened to a container, oldest first: requests acting on it with who
+/// sent them and the status they got (`action`), Docker's lifecycle events
+/// (`event`) and recorded exec and attach sessions (`session`). Entries of the
+/// same second list requests before the events they caused.
+#[utoipa::path(
+    get,
+    path = "/api/containers/{container_id}/timeline",
+    tag = "events",
+    params(("container_id" = String, Path, description = "Container id or name"), AuthParams, TimelineQuery),
+    responses((status = 200, description = "The container's timeline", body = Object))
+)]
+pub async fn get_container_timeline(
+    Path(container_id): Path<String>,
+    Extension(event_log): Extension<EventLog>,
+    Extension(recorder): Extension<SessionRecorder>,
+    Extension(nodes): Extension<NodeChannels>,
+    auth: AuthParams,
+    ValidQuery(query): ValidQuery<TimelineQuery>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = Validator::new()
+        .container_id("container_id", &container_id)
+        .range("limit", query.limit, 1, MAX_TIMELINE_LIMIT)
+        .check(
+            match (query.from, query.to) {
+                (Some(from), Some(to)) => from <= to,
+                _ => true,
+            },
+            "from",
+            "must not be after `to`",
+        )
+        .reject(&request_id)
+    {
+        return response;
+    }
+    if let Some(response) = reject_unknown_node(&nodes, &auth, &request_id).await {
+        return response;
+    }
+
+    let from = query.from.map(|from| from.0);
+    let to = query.to.map(|to| to.0);
+    let events = event_log.query(&EventQuery {
+        node_id: Some(&auth.node_id),
+        kind: Some("container"),
+        container_id: Some(&container_id),
+        from,
+        to,
+        limit: usize::MAX,
+        ..Default::default()
+    });
+    // Requests may have named the container by name, id or id prefix
+    let mut names = vec![container_id.clone()];
+    let mut ids = Vec::new();
+    for event in &events {
+        if !names.contains(&event.name) {
+            names.push(event.name.clone());
+        }
+        if !ids.contains(&event.container_id) {
+            ids.push(event.container_id.clone());
+        }
+    }
+    let is_container = |id: &str| {
+        names.iter().any(|name| name == id)
+            || (!id.is_empty() && ids.iter().any(|full| full.starts_with(id)))
+    };
+
+    let actions = event_log
+        .query(&EventQuery {
+            node_id: Some(&auth.node_id),
+            kind: Some("action"),
+            from,
+            to,
+            limit: usize::MAX,
+            ..Default::default()
+        })
+        .into_iter()
+        .filter(|action| is_container(&action.container_id));
+    let sessions = recorder.list().into_iter().filter(|session| {
+        session.node_id == auth.node_id
+            && is_container(&session.container_id)
+            && from.is_none_or(|from| session.started_at >= from)
+            && to.is_none_or(|to| session.started_at <= to)
+    });
+
+    // (time, requests before reactions, sequence, entry)
+    let mut entries: Vec<(i64, u8, u64, Value)> = Vec::new();
+    for action in actions {
+        let entry = json!({
+            "time": action.time,
+            "source": "action",
+            "action": action.action,
+            "actor": action.actor,
+            "status": action.status,
+        });
+        entries.push((action.time, 0, action.seq, entry));
+    }
+    for session in sessions {
+        let entry = json!({
+            "time": session.started_at,
+            "source": "session",
+            "action": session.kind,
+            "actor": session.opened_by,
+            "command": session.command,
+            "ended_at": session.ended_at,
+            "recording_id": session.id,
+        });
+        entries.push((session.started_at, 0, 0, entry));
+    }
+    for event in events {
+        let entry = json!({
+            "time": event.time,
+            "source": "event",
+            "action": event.action,
+            "container_id": event.container_id,
+            "name": event.name,
+            "image": event.image,
+            "exit_code": (event.action == "die").then_some(event.exit_code),
+            "health": (!event.health.is_empty()).then_some(&event.health),
+        });
+        entries.push((event.time, 1, event.seq, entry));
+    }
+    entries.sort_by_key(|(time, rank, seq, _)| (*time, *rank, *seq));
+
+    let limit = query.limit.unwrap_or(DEFAULT_TIMELINE_LIMIT);
+    let truncated = entries.len() > limit;
+    let entries: Vec<Value> = entries
+        .split_off(entries.len().saturating_sub(limit))
+        .into_iter()
+        .map(|(_, _, _, entry)| entry)
+        .collect();
+
+    let body = json!({
+        "req_id": request_id,
+        "container_id": container_id,
+        "entries": entries,
+        "truncated": truncated,
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
@@ -0,0 +1,144 @@
+use std::fmt;
+
+use axum::{
+    body::{Body, HttpBody, to_bytes},
+    extract::Request,
+    http::header,
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde_json::Value;
+
+// Beyond this a number is milliseconds or nanoseconds, not seconds (year 5138)
+const MAX_EPOCH_SECS: i64 = 100_000_000_000;
+// Larger or streamed JSON bodies are passed through untouched
+const MAX_ANNOTATED_BODY: usize = 16 * 1024 * 1024;
+const RFC3339_SUFFIX: &str = "_rfc3339";
+
+/// A point in time given as unix seconds or an RFC 3339 string with any
+/// offset, e.g. `1717000000` or `2024-05-29T16:26:40+02:00`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Timestamp(pub i64);
+
+impl Timestamp {
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if let Ok(secs) = value.parse::<i64>() {
+            return Some(Self(secs));
+        }
+        DateTime::parse_from_rfc3339(value)
+            .ok()
+            .map(|time| Self(time.timestamp()))
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct TimestampVisitor;
+
+        impl Visitor<'_> for TimestampVisitor {
+            type Value = Timestamp;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("unix seconds or an RFC 3339 time such as 2024-05-29T14:26:40Z")
+            }
+
+            fn visit_i64<E: de::Error>(self, secs: i64) -> Result<Timestamp, E> {
+                Ok(Timestamp(secs))
+            }
+
+            fn visit_u64<E: de::Error>(self, secs: u64) -> Result<Timestamp, E> {
+                i64::try_from(secs)
+                    .map(Timestamp)
+                    .map_err(|_| E::custom("timestamp out of range"))
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Timestamp, E> {
+                Timestamp::parse(value)
+                    .ok_or_else(|| E::invalid_value(de::Unexpected::Str(value), &self))
+            }
+        }
+
+        deserializer.deserialize_any(TimestampVisitor)
+    }
+}
+
+/// RFC 3339 UTC form of unix seconds; `None` for 0 and earlier, which
+/// responses use for "not set".
+pub fn rfc3339(secs: i64) -> Option<String> {
+    if secs <= 0 {
+        return None;
+    }
+    DateTime::<Utc>::from_timestamp(secs, 0)
+        .map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
+/// Adds a `<field>_rfc3339` string next to every unix-seconds field: `time`,
+/// `created`, `last_seen`, `as_of`, and names ending in `_at` or `_time`.
+pub fn add_rfc3339_fields(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            let stamped: Vec<(String, Option<String>)> = map
+                .iter()
+                .filter(|(key, _)| is_timestamp_field(key))
+                .filter_map(|(key, value)| {
+                    let secs = value.as_i64()?;
+                    (secs < MAX_EPOCH_SECS)
+                        .then(|| (format!("{}{}", key, RFC3339_SUFFIX), rfc3339(secs)))
+                })
+                .collect();
+            for value in map.values_mut() {
+                add_rfc3339_fields(value);
+            }
+            for (key, time) in stamped {
+                map.entry(key)
+                    .or_insert(time.map_or(Value::Null, Value::String));
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(add_rfc3339_fields),
+        _ => {}
+    }
+}
+
+fn is_timestamp_field(key: &str) -> bool {
+    matches!(key, "time" | "created" | "last_seen" | "as_of")
+        || key.ends_with("_at")
+        || key.ends_with("_time")
+}
+
+/// Applies [`add_rfc3339_fields`] to JSON responses, so every timestamp comes
+/// both as unix seconds and as an RFC 3339 UTC string.
+pub async fn annotate_timestamps(request: Request, next: Next) -> Response {
+    let response = next.run(request).await;
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+
+    let fits = response
+        .body()
+        .size_hint()
+        .upper()
+        .is_some_and(|size| size <= MAX_ANNOTATED_BODY as u64);
+    if !is_json || !fits {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = to_bytes(body, MAX_ANNOTATED_BODY).await else {
+        parts.headers.remove(header::CONTENT_LENGTH);
+        return Response::from_parts(parts, Body::empty());
+    };
+    let Ok(mut value) = serde_json::from_slice::<Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+    add_rfc3339_fields(&mut value);
+    let Ok(annotated) = serde_json::to_vec(&value) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(annotated))
+}
@@ -0,0 +1,124 @@
+use axum::{Extension, Json, extract::Path, response::IntoResponse};
+use lib_coordinator_core::{
+    CommandRouter, NodeCapability, NodeChannels, NodeStateCache, PendingResponses,
+};
+use proto::generated::{
+    Envelope, GetTopContainers, NodeCommand, RequestType, envelope::Payload, node_command,
+};
+use serde_json::json;
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::node_request::request_node;
+use crate::validation::{ValidQuery, Validator};
+use crate::{NodePasswordParams, reject_unsupported};
+
+// The node samples every running container once, concurrently
+const GET_TOP_CONTAINERS_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(15);
+const DEFAULT_TOP_LIMIT: u32 = 10;
+const MAX_TOP_LIMIT: u32 = 100;
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TopContainersQuery {
+    by: Option<String>,
+    limit: Option<u32>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/nodes/{node_id}/top-containers",
+    tag = "nodes",
+    params(("node_id" = String, Path, description = "Node id"), NodePasswordParams, TopContainersQuery),
+    responses((status = 200, description = "Containers by resource usage", body = Object))
+)]
+pub async fn get_top_containers(
+    Path(node_id): Path<String>,
+    ValidQuery(top_query): ValidQuery<TopContainersQuery>,
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(node_states): Extension<NodeStateCache>,
+    query: NodePasswordParams,
+) -> impl IntoResponse {
+    let request_id = Uuid::new_v4().to_string();
+    let auth = query.for_node(node_id.clone());
+
+    if let Some(response) = Validator::new()
+        .one_of("by", top_query.by.as_deref(), &["cpu", "memory"])
+        .range("limit", top_query.limit, 1, MAX_TOP_LIMIT)
+        .reject(&request_id)
+    {
+        return response;
+    }
+    if let Some(response) = reject_unsupported(
+        &nodes,
+        &node_states,
+        &auth,
+        NodeCapability::Stats,
+        &request_id,
+    )
+    .await
+    {
+        return response;
+    }
+    let by = top_query.by.unwrap_or_else(|| "cpu".to_string());
+    let limit = top_query.limit.unwrap_or(DEFAULT_TOP_LIMIT);
+
+    // Build the command envelope to ask the node for its heaviest containers
+    let envelope = Envelope {
+        payload: Some(Payload::NodeCommand(NodeCommand {
+            kind: Some(node_command::Kind::GetTopContainers(GetTopContainers {
+                request_id: request_id.clone(),
+                by: by.clone(),
+                limit,
+            })),
+        })),
+        ..Default::default()
+    };
+
+    match request_node(
+        &server_tx,
+        &pending,
+        &auth,
+        &request_id,
+        RequestType::GetTopContainers as i32,
+        envelope,
+        GET_TOP_CONTAINERS_TIMEOUT,
+    )
+    .await
+    {
+        Ok(response) => {
+            let containers = extract_top_containers_from_response(&response);
+            let body = json!({
+                "id": request_id,
+                "node_id": node_id,
+                "by": by,
+                "containers": containers,
+            });
+            (axum::http::StatusCode::OK, Json(body)).into_response()
+        }
+        Err(e) => e.into_response(&request_id),
+    }
+}
+
+fn extract_top_containers_from_response(response: &Envelope) -> Vec<serde_json::Value> {
+    if let Some(proto::generated::envelope::Payload::NodeResponse(node_resp)) = &response.payload
+        && let Some(proto::generated::node_response::Kind::TopContainers(top)) = &node_resp.kind
+    {
+        return top
+            .containers
+            .iter()
+            .map(|container| {
+                json!({
+                    "container_id": container.container_id,
+                    "name": container.name,
+                    "cpu_usage": container.cpu_usage,
+                    "memory_usage": container.memory_usage,
+                    "memory_limit": container.memory_limit,
+                })
+            })
+            .collect();
+    }
+    vec![]
+}
@@ -0,0 +1,297 @@
+use std::time::Duration;
+
+use axum::{
+    Extension, Json,
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use lib_coordinator_core::{
+    CommandRouter, NodeChannels, PendingResponses, TrashPolicy, TrashStore,
+};
+use proto::generated::{
+    Envelope, GetTrash, NodeCommand, RequestType, RestoreContainer, TrashStatus, envelope::Payload,
+    node_command, node_response,
+};
+use serde_json::json;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::node_request::request_node;
+use crate::validation::{ValidJson, Validator};
+use crate::{AuthParams, NodePasswordParams, error_response, reject_unknown_node};
+
+const TRASH_TIMEOUT: Duration = Duration::from_secs(15);
+// Renaming and updating the restart policy, two quick Docker calls
+const RESTORE_TIMEOUT: Duration = Duration::from_secs(60);
+const MIN_RETENTION_SECS: u64 = 60;
+const MAX_RETENTION_SECS: u64 = 30 * 86_400;
+
+/// The node's trash retention and the containers in its trash.
+#[utoipa::path(
+    get,
+    path = "/api/nodes/{node_id}/trash",
+    tag = "nodes",
+    params(("node_id" = String, Path, description = "Node id"), NodePasswordParams),
+    responses((status = 200, description = "Stored retention and the trashed containers", body = Object))
+)]
+pub async fn get_trash(
+    Path(node_id): Path<String>,
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(trash): Extension<TrashStore>,
+    query: NodePasswordParams,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    let auth = query.for_node(node_id);
+    if let Some(response) = reject_unknown_node(&nodes, &auth, &request_id).await {
+        return response;
+    }
+
+    let kind = node_command::Kind::GetTrash(GetTrash {
+        request_id: request_id.clone(),
+    });
+    match send(
+        &server_tx,
+        &pending,
+        &auth,
+        &request_id,
+        RequestType::GetTrash,
+        kind,
+    )
+    .await
+    {
+        Ok(status) => {
+            let body = json!({
+                "id": request_id,
+                "node_id": auth.node_id,
+                "policy": trash.get(&auth.node_id),
+                "status": status,
+            });
+            (StatusCode::OK, Json(body)).into_response()
+        }
+        Err(response) => response,
+    }
+}
+
+/// Turns the node's trash on or changes its retention. The retention is
+/// stored only once the node accepted it.
+#[utoipa::path(
+    put,
+    path = "/api/nodes/{node_id}/trash",
+    tag = "nodes",
+    params(("node_id" = String, Path, description = "Node id"), NodePasswordParams),
+    request_body = TrashPolicy,
+    responses((status = 200, description = "The retention the node accepted", body = Object))
+)]
+pub async fn put_trash(
+    Path(node_id): Path<String>,
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(trash): Extension<TrashStore>,
+    query: NodePasswordParams,
+    ValidJson(policy): ValidJson<TrashPolicy>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = Validator::new()
+        .range(
+            "retention_secs",
+            Some(policy.retention_secs),
+            MIN_RETENTION_SECS,
+            MAX_RETENTION_SECS,
+        )
+        .reject(&request_id)
+    {
+        return response;
+    }
+    let auth = query.for_node(node_id);
+    if let Some(response) = reject_unknown_node(&nodes, &auth, &request_id).await {
+        return response;
+    }
+
+    configure(
+        &server_tx,
+        &pending,
+        &trash,
+        &auth,
+        &request_id,
+        Some(policy),
+    )
+    .await
+}
+
+/// Turns the node's trash off, so deletes remove containers right away. What
+/// is in the trash stays until restored or deleted.
+#[utoipa::path(
+    delete,
+    path = "/api/nodes/{node_id}/trash",
+    tag = "nodes",
+    params(("node_id" = String, Path, description = "Node id"), NodePasswordParams),
+    responses((status = 200, description = "Trash turned off", body = Object))
+)]
+pub async fn delete_trash(
+    Path(node_id): Path<String>,
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(trash): Extension<TrashStore>,
+    query: NodePasswordParams,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    let auth = query.for_node(node_id);
+    if let Some(response) = reject_unknown_node(&nodes, &auth, &request_id).await {
+        return response;
+    }
+
+    configure(&server_tx, &pending, &trash, &auth, &request_id, None).await
+}
+
+/// Takes a deleted container back out of the node's trash under its old
+/// name. It comes back stopped, with its restart policy.
+#[utoipa::path(
+    post,
+    path = "/api/trash/{container_id}/restore",
+    tag = "containers",
+    params(("container_id" = String, Path, description = "Id or trash name of the container, or the name it had"), AuthParams),
+    responses(
+        (status = 200, description = "The container is back", body = Object),
+        (status = 409, description = "Another container has the name now", body = Object)
+    )
+)]
+pub async fn restore_container(
+    Path(container_id): Path<String>,
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(pending): Extension<PendingResponses>,
+    Extension(nodes): Extension<NodeChannels>,
+    auth: AuthParams,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = Validator::new()
+        .container_id("container_id", &container_id)
+        .reject(&request_id)
+    {
+        return response;
+    }
+    if let Some(response) = reject_unknown_node(&nodes, &auth, &request_id).await {
+        return response;
+    }
+
+    let envelope = Envelope {
+        payload: Some(Payload::NodeCommand(NodeCommand {
+            kind: Some(node_command::Kind::RestoreContainer(RestoreContainer {
+                request_id: request_id.clone(),
+                container_id: container_id.clone(),
+            })),
+        })),
+        ..Default::default()
+    };
+    match request_node(
+        &server_tx,
+        &pending,
+        &auth,
+        &request_id,
+        RequestType::RestoreContainer as i32,
+        envelope,
+        RESTORE_TIMEOUT,
+    )
+    .await
+    {
+        Ok(response) => {
+            let action = match response.payload {
+                Some(Payload::NodeResponse(node_resp)) => match node_resp.kind {
+                    Some(node_response::Kind::ContainerAction(action)) => Some(action),
+                    _ => None,
+                },
+                _ => None,
+            };
+            let body = json!({
+                "id": request_id,
+                "container_id": container_id,
+                "action": "restore",
+                "result": action,
+            });
+            (StatusCode::OK, Json(body)).into_response()
+        }
+        Err(e) => e.into_response(&request_id),
+    }
+}
+
+async fn configure(
+    server_tx: &CommandRouter,
+    pending: &PendingResponses,
+    trash: &TrashStore,
+    auth: &AuthParams,
+    request_id: &str,
+    policy: Option<TrashPolicy>,
+) -> Response {
+    let kind = node_command::Kind::ConfigureTrash(TrashStore::command(request_id, policy.as_ref()));
+    let status = match send(
+        server_tx,
+        pending,
+        auth,
+        request_id,
+        RequestType::ConfigureTrash,
+        kind,
+    )
+    .await
+    {
+        Ok(status) => status,
+        Err(response) => return response,
+    };
+
+    if let Err(e) = trash.set(&auth.node_id, policy.clone()) {
+        error!("Failed to persist trash retention: {}", e);
+        return error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            request_id,
+            "Failed to store trash retention",
+            e.to_string(),
+        );
+    }
+    let body = json!({
+        "id": request_id,
+        "node_id": auth.node_id,
+        "policy": policy,
+        "status": status,
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+async fn send(
+    server_tx: &CommandRouter,
+    pending: &PendingResponses,
+    auth: &AuthParams,
+    request_id: &str,
+    request_type: RequestType,
+    kind: node_command::Kind,
+) -> Result<Option<TrashStatus>, Response> {
+    let envelope = Envelope {
+        payload: Some(Payload::NodeCommand(NodeCommand { kind: Some(kind) })),
+        ..Default::default()
+    };
+    match request_node(
+        server_tx,
+        pending,
+        auth,
+        request_id,
+        request_type as i32,
+        envelope,
+        TRASH_TIMEOUT,
+    )
+    .await
+    {
+        Ok(response) => Ok(extract_status_from_response(response)),
+        Err(e) => Err(e.into_response(request_id)),
+    }
+}
+
+fn extract_status_from_response(response: Envelope) -> Option<TrashStatus> {
+    if let Some(Payload::NodeResponse(node_resp)) = response.payload
+        && let Some(node_response::Kind::TrashStatus(status)) = node_resp.kind
+    {
+        return Some(status);
+    }
+    None
+}
@@ -0,0 +1,318 @@
+use std::collections::HashMap;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, LazyLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use axum::{
+    Extension, Json,
+    body::Body,
+    extract::{Query, Request, State},
+    http::{Method, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use dashmap::DashMap;
+use futures_util::StreamExt;
+use hmac::{Hmac, Mac};
+use lib_coordinator_core::{NodeChannels, verify_node};
+use serde::Deserialize;
+use serde_json::json;
+use sha2::Sha256;
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::admin::{AdminParams, AdminToken};
+use crate::rate_limit::target_node_id;
+use crate::request_password;
+
+// Past this many credentials the one seen longest ago is dropped for a new one
+const MAX_TRACKED_CREDENTIALS: usize = 10_000;
+
+// Keys the api key labels, so a label cannot be matched against guessed keys.
+// Drawn at start, so labels of the same key differ between coordinator runs
+static LABEL_KEY: LazyLock<String> =
+    LazyLock::new(|| format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple()));
+
+/// Per-credential REST usage, kept in memory since coordinator start. Only
+/// requests whose credentials check out are counted.
+#[derive(Clone)]
+pub struct UsageTracker {
+    credentials: Arc<DashMap<String, Arc<UsageCounters>>>,
+    nodes: NodeChannels,
+    admin: AdminToken,
+}
+
+#[derive(Default)]
+struct UsageCounters {
+    requests: AtomicU64,
+    actions: AtomicU64,
+    errors: AtomicU64,
+    bytes_in: AtomicU64,
+    bytes_out: AtomicU64,
+    last_seen: AtomicU64,
+}
+
+struct UsageRow {
+    credential: String,
+    requests: u64,
+    actions: u64,
+    errors: u64,
+    bytes_in: u64,
+    bytes_out: u64,
+    last_seen: u64,
+}
+
+impl UsageTracker {
+    pub fn new(nodes: NodeChannels, admin: AdminToken) -> Self {
+        Self {
+            credentials: Arc::default(),
+            nodes,
+            admin,
+        }
+    }
+
+    fn counters(&self, credential: String) -> Arc<UsageCounters> {
+        if let Some(counters) = self.credentials.get(&credential) {
+            return counters.clone();
+        }
+        if self.credentials.len() >= MAX_TRACKED_CREDENTIALS {
+            let oldest = self
+                .credentials
+                .iter()
+                .min_by_key(|entry| entry.value().last_seen.load(Ordering::Relaxed))
+                .map(|entry| entry.key().clone());
+            if let Some(oldest) = oldest {
+                self.credentials.remove(&oldest);
+            }
+        }
+        self.credentials.entry(credential).or_default().clone()
+    }
+
+    /// Whether the node password or admin token a request carries checks out,
+    /// `params` being its query. Node passwords verified by the handler are
+    /// cached, so checking them again here is cheap.
+    async fn verifies(
+        &self,
+        node_id: Option<&str>,
+        password: Option<&str>,
+        params: &HashMap<String, String>,
+    ) -> bool {
+        if let Some(node_id) = node_id {
+            return verify_node(&self.nodes, node_id, password.unwrap_or("")).await;
+        }
+        if params.contains_key("admin_token") {
+            let admin_params = AdminParams {
+                admin_token: params.get("admin_token").cloned(),
+            };
+            return self.admin.reject(&admin_params, "").is_none();
+        }
+        true
+    }
+
+    fn rows(&self) -> Vec<UsageRow> {
+        let mut rows: Vec<_> = self
+            .credentials
+            .iter()
+            .map(|entry| {
+                let c = entry.value();
+                UsageRow {
+                    credential: entry.key().clone(),
+                    requests: c.requests.load(Ordering::Relaxed),
+                    actions: c.actions.load(Ordering::Relaxed),
+                    errors: c.errors.load(Ordering::Relaxed),
+                    bytes_in: c.bytes_in.load(Ordering::Relaxed),
+                    bytes_out: c.bytes_out.load(Ordering::Relaxed),
+                    last_seen: c.last_seen.load(Ordering::Relaxed),
+                }
+            })
+            .collect();
+        rows.sort_by_key(|row| std::cmp::Reverse(row.requests));
+        rows
+    }
+}
+
+/// Middleware counting requests, mutating actions and body bytes per credential.
+/// Response bodies are counted as they stream, so SSE and log follows are included.
+/// Requests turned away for their credentials are not counted, so made-up node
+/// ids and tokens cannot fill the report.
+pub async fn track_usage(
+    State(tracker): State<UsageTracker>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let is_action = matches!(
+        *request.method(),
+        Method::POST | Method::PUT | Method::PATCH | Method::DELETE
+    );
+
+    let label = credential_label(&request);
+    let node_id = target_node_id(&request);
+    let password = request_password(&request, &tracker.nodes);
+    let params = Query::<HashMap<String, String>>::try_from_uri(request.uri())
+        .map(|q| q.0)
+        .unwrap_or_default();
+
+    let (parts, body) = request.into_parts();
+    // Billed once the credentials are known to be good
+    let bytes_in = Arc::new(AtomicU64::new(0));
+    let in_counter = bytes_in.clone();
+    let body = Body::from_stream(body.into_data_stream().inspect(move |chunk| {
+        if let Ok(bytes) = chunk {
+            in_counter.fetch_add(bytes.len() as u64, Ordering::Relaxed);
+        }
+    }));
+
+    let response = next.run(Request::from_parts(parts, body)).await;
+    if matches!(
+        response.status(),
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN
+    ) {
+        return response;
+    }
+    if !tracker
+        .verifies(node_id.as_deref(), password.as_deref(), &params)
+        .await
+    {
+        return response;
+    }
+
+    let counters = tracker.counters(label);
+    counters.requests.fetch_add(1, Ordering::Relaxed);
+    counters.last_seen.store(unix_secs(), Ordering::Relaxed);
+    if is_action {
+        counters.actions.fetch_add(1, Ordering::Relaxed);
+    }
+    counters
+        .bytes_in
+        .fetch_add(bytes_in.load(Ordering::Relaxed), Ordering::Relaxed);
+    if response.status().is_client_error() || response.status().is_server_error() {
+        counters.errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = Body::from_stream(body.into_data_stream().inspect(move |chunk| {
+        if let Ok(bytes) = chunk {
+            counters
+                .bytes_out
+                .fetch_add(bytes.len() as u64, Ordering::Relaxed);
+        }
+    }));
+    Response::from_parts(parts, body)
+}
+
+/// Which credential a request is billed to: node id, keyed hash of the api key,
+/// admin or anonymous.
+pub(crate) fn credential_label(request: &Request) -> String {
+    if let Some(node_id) = target_node_id(request) {
+        return format!("node:{}", node_id);
+    }
+
+    let params = Query::<HashMap<String, String>>::try_from_uri(request.uri())
+        .map(|q| q.0)
+        .unwrap_or_default();
+    if let Some(api_key) = params.get("api_key") {
+        // Never expose the key itself in usage reports
+        let mut mac = Hmac::<Sha256>::new_from_slice(LABEL_KEY.as_bytes())
+            .expect("HMAC takes keys of any length");
+        mac.update(api_key.as_bytes());
+        let tag = mac.finalize().into_bytes();
+        let hex: String = tag[..8].iter().map(|b| format!("{:02x}", b)).collect();
+        return format!("api_key:{}", hex);
+    }
+    if params.contains_key("admin_token") {
+        return "admin".to_string();
+    }
+    "anonymous".to_string()
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UsageQuery {
+    format: Option<String>,
+}
+
+/// Usage per credential as JSON, or CSV with `?format=csv`.
+#[utoipa::path(
+    get,
+    path = "/api/usage",
+    tag = "usage",
+    params(AdminParams, UsageQuery),
+    responses((status = 200, description = "Usage per credential as JSON or CSV", body = Object))
+)]
+pub async fn get_usage(
+    Extension(tracker): Extension<UsageTracker>,
+    Extension(admin): Extension<AdminToken>,
+    Query(params): Query<AdminParams>,
+    Query(query): Query<UsageQuery>,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    if let Some(response) = admin.reject(&params, &request_id) {
+        return response;
+    }
+
+    let rows = tracker.rows();
+    if query.format.as_deref() == Some("csv") {
+        let mut csv =
+            String::from("credential,requests,actions,errors,bytes_in,bytes_out,last_seen\n");
+        for row in &rows {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{}\n",
+                csv_field(&row.credential),
+                row.requests,
+                row.actions,
+                row.errors,
+                row.bytes_in,
+                row.bytes_out,
+                row.last_seen
+            ));
+        }
+        return (
+            StatusCode::OK,
+            [
+                (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+                (
+                    header::CONTENT_DISPOSITION,
+                    "attachment; filename=\"usage.csv\"",
+                ),
+            ],
+            csv,
+        )
+            .into_response();
+    }
+
+    let usage: Vec<_> = rows
+        .iter()
+        .map(|row| {
+            json!({
+                "credential": row.credential,
+                "requests": row.requests,
+                "actions": row.actions,
+                "errors": row.errors,
+                "bytes_in": row.bytes_in,
+                "bytes_out": row.bytes_out,
+                "last_seen": row.last_seen,
+            })
+        })
+        .collect();
+    let body = json!({
+        "req_id": request_id,
+        "usage": usage,
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn unix_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,336 @@
+use std::fmt::Display;
+
+use axum::{
+    Json,
+    extract::{FromRequest, FromRequestParts, Query, Request, rejection::JsonRejection},
+    http::{StatusCode, header, request::Parts},
+    response::{IntoResponse, Response},
+};
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::json;
+use uuid::Uuid;
+
+const MAX_CONTAINER_ID_LEN: usize = 255;
+const MAX_IMAGE_REF_LEN: usize = 255;
+const MAX_SECRET_NAME_LEN: usize = 64;
+const MAX_NETWORK_NAME_LEN: usize = 255;
+
+#[derive(Debug, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+/// Collects field-level problems with a request; answered with 422 and all of them at once.
+#[derive(Debug, Default)]
+pub struct Validator {
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn check(&mut self, ok: bool, field: &str, message: impl Into<String>) -> &mut Self {
+        if !ok {
+            self.errors.push(FieldError {
+                field: field.to_string(),
+                message: message.into(),
+            });
+        }
+        self
+    }
+
+    /// Docker container id (or prefix) or name.
+    pub fn container_id(&mut self, field: &str, value: &str) -> &mut Self {
+        let message = if value.is_empty() {
+            Some("must not be empty".to_string())
+        } else if value.len() > MAX_CONTAINER_ID_LEN {
+            Some(format!(
+                "must be at most {} characters",
+                MAX_CONTAINER_ID_LEN
+            ))
+        } else if !is_container_name(value) {
+            Some("must be a container id or a name of letters, digits, '_', '.' or '-'".to_string())
+        } else {
+            None
+        };
+        if let Some(message) = message {
+            self.check(false, field, message);
+        }
+        self
+    }
+
+    /// Docker network id or name, which follow the same rules as container names.
+    pub fn network(&mut self, field: &str, value: &str) -> &mut Self {
+        let ok = value.len() <= MAX_NETWORK_NAME_LEN
+            && !value.starts_with('/')
+            && is_container_name(value);
+        self.check(
+            ok,
+            field,
+            format!(
+                "must be 1-{} letters, digits, '_', '.' or '-', starting with a letter or digit",
+                MAX_NETWORK_NAME_LEN
+            ),
+        )
+    }
+
+    /// Image reference such as `nginx:1.27`, `ghcr.io/org/app:1.0` or `app@sha256:...`.
+    pub fn image_ref(&mut self, field: &str, value: &str) -> &mut Self {
+        let message = if value.is_empty() {
+            Some("must not be empty".to_string())
+        } else if value.len() > MAX_IMAGE_REF_LEN {
+            Some(format!("must be at most {} characters", MAX_IMAGE_REF_LEN))
+        } else {
+            image_ref_problem(value)
+        };
+        if let Some(message) = message {
+            self.check(false, field, message);
+        }
+        self
+    }
+
+    /// Name of a coordinator-stored secret: letters, digits, '_', '.' or '-'.
+    pub fn secret_name(&mut self, field: &str, value: &str) -> &mut Self {
+        let ok = !value.is_empty()
+            && value.len() <= MAX_SECRET_NAME_LEN
+            && value
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'));
+        self.check(
+            ok,
+            field,
+            format!(
+                "must be 1-{} letters, digits, '_', '.' or '-'",
+                MAX_SECRET_NAME_LEN
+            ),
+        )
+    }
+
+    pub fn range<T: PartialOrd + Display>(
+        &mut self,
+        field: &str,
+        value: Option<T>,
+        min: T,
+        max: T,
+    ) -> &mut Self {
+        if let Some(value) = value
+            && (value < min || value > max)
+        {
+            self.check(
+                false,
+                field,
+                format!("must be between {} and {}, got {}", min, max, value),
+            );
+        }
+        self
+    }
+
+    pub fn one_of(&mut self, field: &str, value: Option<&str>, allowed: &[&str]) -> &mut Self {
+        if let Some(value) = value
+            && !allowed.contains(&value)
+        {
+            self.check(
+                false,
+                field,
+                format!("must be one of {}, got \"{}\"", allowed.join(", "), value),
+            );
+        }
+        self
+    }
+
+    /// The 422 response if anything failed.
+    pub fn reject(&self, request_id: &str) -> Option<Response> {
+        if self.errors.is_empty() {
+            return None;
+        }
+        let detail = self
+            .errors
+            .iter()
+            .map(|e| format!("{}: {}", e.field, e.message))
+            .collect::<Vec<_>>()
+            .join("; ");
+        Some(validation_response(request_id, detail, &self.errors))
+    }
+}
+
+fn validation_response(request_id: &str, detail: String, fields: &[FieldError]) -> Response {
+    let body = json!({
+        "req_id": request_id,
+        "error": {
+            "message": "Validation failed",
+            "detail": detail,
+        },
+        "fields": fields,
+    });
+    (StatusCode::UNPROCESSABLE_ENTITY, Json(body)).into_response()
+}
+
+/// `Query` that answers malformed parameters (missing, wrong type) with a JSON 422.
+pub struct ValidQuery<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for ValidQuery<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        match Query::<T>::from_request_parts(parts, state).await {
+            Ok(Query(value)) => Ok(Self(value)),
+            Err(rejection) => {
+                let field = FieldError {
+                    field: "query".to_string(),
+                    message: rejection.body_text(),
+                };
+                Err(validation_response(
+                    &Uuid::new_v4().to_string(),
+                    field.message.clone(),
+                    &[field],
+                ))
+            }
+        }
+    }
+}
+
+/// `Json` that answers an unparsable body with a JSON 422.
+pub struct ValidJson<T>(pub T);
+
+impl<T, S> FromRequest<S> for ValidJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        match Json::<T>::from_request(request, state).await {
+            Ok(Json(value)) => Ok(Self(value)),
+            Err(rejection) => {
+                let status = rejection.status();
+                let field = FieldError {
+                    field: "body".to_string(),
+                    message: rejection.body_text(),
+                };
+                // Wrong content type or an oversized body are not validation problems
+                if matches!(rejection, JsonRejection::MissingJsonContentType(_))
+                    || status == StatusCode::PAYLOAD_TOO_LARGE
+                {
+                    return Err(rejection.into_response());
+                }
+                Err(validation_response(
+                    &Uuid::new_v4().to_string(),
+                    field.message.clone(),
+                    &[field],
+                ))
+            }
+        }
+    }
+}
+
+/// A request without a body (no `Content-Type`) is `None`; one with a body is
+/// parsed as by [`ValidJson`].
+impl<T, S> axum::extract::OptionalFromRequest<S> for ValidJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(request: Request, state: &S) -> Result<Option<Self>, Self::Rejection> {
+        if !request.headers().contains_key(header::CONTENT_TYPE) {
+            return Ok(None);
+        }
+        <Self as FromRequest<S>>::from_request(request, state)
+            .await
+            .map(Some)
+    }
+}
+
+fn is_container_name(value: &str) -> bool {
+    // Docker reports names with a leading slash
+    let name = value.strip_prefix('/').unwrap_or(value);
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphanumeric())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+}
+
+fn image_ref_problem(value: &str) -> Option<String> {
+    let (name, digest) = match value.split_once('@') {
+        Some((name, digest)) => (name, Some(digest)),
+        None => (value, None),
+    };
+
+    if let Some(digest) = digest {
+        let valid = digest.split_once(':').is_some_and(|(algorithm, hex)| {
+            !algorithm.is_empty()
+                && algorithm
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+                && hex.len() >= 32
+                && hex.chars().all(|c| c.is_ascii_hexdigit())
+        });
+        if !valid {
+            return Some(format!("invalid digest \"{}\"", digest));
+        }
+    }
+
+    // A tag follows the last ':' unless that ':' is part of a registry host:port
+    let (repository, tag) = match name.rsplit_once(':') {
+        Some((repository, tag)) if !tag.contains('/') => (repository, Some(tag)),
+        _ => (name, None),
+    };
+
+    if let Some(tag) = tag {
+        let mut chars = tag.chars();
+        let valid = tag.len() <= 128
+            && chars
+                .next()
+                .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'));
+        if !valid {
+            return Some(format!("invalid tag \"{}\"", tag));
+        }
+    }
+
+    let mut components = repository.split('/').peekable();
+    let mut first = true;
+    while let Some(component) = components.next() {
+        let is_registry = first
+            && components.peek().is_some()
+            && (component.contains(['.', ':']) || component == "localhost");
+        first = false;
+        if is_registry {
+            let valid = component
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | ':'));
+            if !valid {
+                return Some(format!("invalid registry \"{}\"", component));
+            }
+            continue;
+        }
+
+        let valid = component
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '.' | '_' | '-'))
+            && component
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_alphanumeric())
+            && component
+                .chars()
+                .last()
+                .is_some_and(|c| c.is_ascii_alphanumeric());
+        if !valid {
+            return Some(format!(
+                "invalid repository name \"{}\" (lowercase letters, digits and separators only)",
+                component
+            ));
+        }
+    }
+
+    None
+}
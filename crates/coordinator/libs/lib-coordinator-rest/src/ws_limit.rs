@@ -0,0 +1,134 @@
+use axum::{
+    extract::{
+        FromRequestParts, Request, State,
+        ws::{CloseFrame, Message, WebSocketUpgrade, close_code},
+    },
+    http::header,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use lib_coordinator_core::{WsCap, WsConnections};
+use serde_json::{Value, json};
+use tracing::info;
+
+use crate::usage::credential_label;
+
+/// Most WebSocket connections open at once, overall and per credential.
+/// `0` leaves that cap off.
+#[derive(Debug, Clone, Copy)]
+pub struct WsLimit {
+    pub total: usize,
+    pub per_credential: usize,
+}
+
+impl Default for WsLimit {
+    fn default() -> Self {
+        Self {
+            total: 2_000,
+            per_credential: 50,
+        }
+    }
+}
+
+/// Caps open WebSocket connections, so a storm of browser tabs cannot
+/// exhaust the coordinator's memory with sockets and their subscriptions.
+#[derive(Clone)]
+pub struct WsLimiter {
+    limit: WsLimit,
+    connections: WsConnections,
+}
+
+impl WsLimiter {
+    pub fn new(limit: WsLimit, connections: WsConnections) -> Self {
+        Self { limit, connections }
+    }
+
+    /// Open connections next to the caps, for the cluster summary.
+    pub fn gauge(&self) -> Value {
+        let by_credential: Vec<_> = self
+            .connections
+            .snapshot()
+            .into_iter()
+            .map(|(credential, open, rejected)| {
+                json!({
+                    "credential": credential,
+                    "open": open,
+                    "rejected": rejected,
+                })
+            })
+            .collect();
+        json!({
+            "open": self.connections.open_total(),
+            "max": self.limit.total,
+            "max_per_credential": self.limit.per_credential,
+            "by_credential": by_credential,
+        })
+    }
+}
+
+/// Whether the request asks to be upgraded to a WebSocket.
+pub(crate) fn is_websocket(request: &Request) -> bool {
+    request
+        .headers()
+        .get(header::UPGRADE)
+        .is_some_and(|value| value.as_bytes().eq_ignore_ascii_case(b"websocket"))
+}
+
+/// Middleware counting WebSocket connections per credential. Over a cap the
+/// socket is still accepted, then closed with code 1013 (try again later):
+/// browsers do not expose the status of a refused handshake, but they do
+/// expose close codes. Other requests pass.
+pub async fn limit_ws_connections(
+    State(limiter): State<WsLimiter>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !is_websocket(&request) {
+        return next.run(request).await;
+    }
+
+    let credential = credential_label(&request);
+    let cap = match limiter.connections.open(
+        &credential,
+        limiter.limit.total,
+        limiter.limit.per_credential,
+    ) {
+        Ok(connection) => {
+            let mut request = request;
+            request.extensions_mut().insert(connection);
+            return next.run(request).await;
+        }
+        Err(cap) => cap,
+    };
+
+    let reason = match cap {
+        WsCap::Total => {
+            info!(
+                "Refused WebSocket of {}: {} connections are open",
+                credential, limiter.limit.total
+            );
+            "Too many WebSocket connections to the coordinator"
+        }
+        WsCap::PerCredential => {
+            info!(
+                "Refused WebSocket of {}: it has {} connections open",
+                credential, limiter.limit.per_credential
+            );
+            "Too many WebSocket connections for this credential"
+        }
+    };
+    // Not a valid upgrade after all; the handler answers it
+    let (mut parts, body) = request.into_parts();
+    let Ok(ws) = WebSocketUpgrade::from_request_parts(&mut parts, &()).await else {
+        return next.run(Request::from_parts(parts, body)).await;
+    };
+    ws.on_upgrade(move |mut socket| async move {
+        let _ = socket
+            .send(Message::Close(Some(CloseFrame {
+                code: close_code::AGAIN,
+                reason: reason.into(),
+            })))
+            .await;
+    })
+    .into_response()
+}
@@ -1,4 +1,7 @@
+pub mod ws_attach;
+pub mod ws_exec;
 pub mod ws_observe_containers;
+pub mod ws_observe_logs;
 pub mod ws_server;
 
 pub use ws_server::build_ws_router;
@@ -0,0 +1,398 @@
+use axum::{
+    extract::{
+        Extension, Path, Query,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    response::IntoResponse,
+};
+use futures_util::{SinkExt, StreamExt};
+use lib_coordinator_core::{
+    CommandRouter, NodeChannels, PendingStreams, RecordingInfo, ServerRequestByUser,
+    SessionRecorder, TraceContext, WsConnection, verify_node,
+};
+use lib_coordinator_rest::node_stream::open_node_stream;
+use lib_coordinator_rest::{AuthParams, TenantWsSlot};
+use proto::generated::{
+    AttachContainer, CloseSession, Envelope, NodeCommand, RequestType, ResizeSession, SessionInput,
+    envelope::Payload, node_command, node_response::Kind,
+};
+use serde::Deserialize;
+use serde_json::json;
+use tokio::time::{Duration, interval};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::ws_server::going_away;
+
+// Initial size used for recordings when the client does not send one
+const DEFAULT_COLS: u16 = 80;
+const DEFAULT_ROWS: u16 = 24;
+// How long to wait for the node to confirm a detach after the client is gone
+const DETACH_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Deserialize)]
+pub struct AttachParams {
+    cols: Option<u16>,
+    rows: Option<u16>,
+}
+
+/// Control messages a client may send as text frames. Binary frames are raw stdin.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AttachClientMessage {
+    /// Text typed by the user, for clients that cannot send binary frames.
+    Input { data: String },
+    /// Terminal size changed, e.g. the browser window was resized.
+    Resize { cols: u16, rows: u16 },
+    /// Ends the session and leaves the container running.
+    #[serde(alias = "close")]
+    Detach,
+}
+
+/// The node command opening an interactive session, and what to record about it.
+pub(crate) struct SessionRequest {
+    /// "attach" or "exec".
+    pub kind: &'static str,
+    pub request_type: RequestType,
+    pub command: node_command::Kind,
+    /// The exec'd command; empty for attach.
+    pub args: Vec<String>,
+    pub cols: u16,
+    pub rows: u16,
+}
+
+// Axum extractors, one per piece of shared state
+#[allow(clippy::too_many_arguments)]
+pub async fn handle_attach_connection(
+    Path(container_id): Path<String>,
+    auth: AuthParams,
+    Query(params): Query<AttachParams>,
+    ws: WebSocketUpgrade,
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(pending_streams): Extension<PendingStreams>,
+    Extension(recorder): Extension<SessionRecorder>,
+    Extension(shutdown): Extension<CancellationToken>,
+    tenant_slot: Option<Extension<TenantWsSlot>>,
+    ws_connection: Option<Extension<WsConnection>>,
+) -> impl IntoResponse {
+    let trace = TraceContext::current();
+    ws.on_upgrade(move |socket| {
+        TraceContext::resume(trace, async move {
+            // Counts against the tenant's and the coordinator's connection caps until the socket closes
+            let _tenant_slot = tenant_slot;
+            let _ws_connection = ws_connection;
+            handle_socket(
+                socket,
+                container_id,
+                auth,
+                params,
+                server_tx,
+                nodes,
+                pending_streams,
+                recorder,
+                shutdown,
+            )
+            .await
+        })
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn handle_socket(
+    socket: WebSocket,
+    container_id: String,
+    auth: AuthParams,
+    params: AttachParams,
+    server_tx: CommandRouter,
+    nodes: NodeChannels,
+    pending_streams: PendingStreams,
+    recorder: SessionRecorder,
+    shutdown: CancellationToken,
+) {
+    info!(
+        "🔌 Attach to container {} on node {}",
+        container_id, auth.node_id
+    );
+
+    let session_id = Uuid::new_v4().to_string();
+    let cols = params.cols.unwrap_or(0);
+    let rows = params.rows.unwrap_or(0);
+    let request = SessionRequest {
+        kind: "attach",
+        request_type: RequestType::AttachContainer,
+        command: node_command::Kind::AttachContainer(AttachContainer {
+            request_id: session_id.clone(),
+            container_id: container_id.clone(),
+            cols: cols.into(),
+            rows: rows.into(),
+        }),
+        args: Vec::new(),
+        cols,
+        rows,
+    };
+    relay_session(
+        socket,
+        container_id,
+        auth,
+        session_id,
+        request,
+        server_tx,
+        nodes,
+        pending_streams,
+        recorder,
+        shutdown,
+    )
+    .await;
+}
+
+/// Opens the session on the node and relays it to the socket until either side ends it.
+#[allow(clippy::too_many_arguments)] // One per piece of shared coordinator state
+pub(crate) async fn relay_session(
+    socket: WebSocket,
+    container_id: String,
+    auth: AuthParams,
+    session_id: String,
+    request: SessionRequest,
+    server_tx: CommandRouter,
+    nodes: NodeChannels,
+    pending_streams: PendingStreams,
+    recorder: SessionRecorder,
+    shutdown: CancellationToken,
+) {
+    let (mut ws_sender, mut ws_receiver) = socket.split();
+    if !verify_node(&nodes, &auth.node_id, &auth.password).await {
+        error!("Node {} not registered", auth.node_id);
+        let _ = ws_sender.send(Message::Close(None)).await;
+        return;
+    }
+
+    let SessionRequest {
+        kind,
+        request_type,
+        command,
+        args,
+        cols,
+        rows,
+    } = request;
+    let envelope = Envelope {
+        payload: Some(Payload::NodeCommand(NodeCommand {
+            kind: Some(command),
+        })),
+        ..Default::default()
+    };
+    let mut node_stream = match open_node_stream(
+        &server_tx,
+        &pending_streams,
+        &auth,
+        &session_id,
+        request_type as i32,
+        envelope,
+    ) {
+        Ok(node_stream) => node_stream,
+        Err(e) => {
+            error!("Failed to send {} request: {}", kind, e);
+            let _ = ws_sender.send(Message::Close(None)).await;
+            return;
+        }
+    };
+
+    let recording = recorder.start(
+        RecordingInfo {
+            id: session_id.clone(),
+            kind: kind.to_string(),
+            node_id: auth.node_id.clone(),
+            container_id: container_id.clone(),
+            command: args,
+            opened_by: format!("node:{}", auth.node_id),
+            started_at: 0,
+            ended_at: None,
+            bytes_in: 0,
+            bytes_out: 0,
+        },
+        if cols > 0 { cols } else { DEFAULT_COLS },
+        if rows > 0 { rows } else { DEFAULT_ROWS },
+    );
+
+    let mut ping_interval = interval(Duration::from_secs(20));
+    // Set once the client goes away; the loop then only waits for the node to confirm
+    let mut client_gone = false;
+
+    loop {
+        tokio::select! {
+            msg = ws_receiver.next(), if !client_gone => {
+                let input = match msg {
+                    Some(Ok(Message::Binary(data))) => Some(data.to_vec()),
+                    Some(Ok(Message::Text(text))) => {
+                        match serde_json::from_str::<AttachClientMessage>(&text) {
+                            Ok(AttachClientMessage::Input { data }) => Some(data.into_bytes()),
+                            Ok(AttachClientMessage::Resize { cols, rows }) => {
+                                if let Some(recording) = &recording {
+                                    recording.resize(cols, rows);
+                                }
+                                send_resize(&server_tx, &auth, &session_id, cols, rows);
+                                None
+                            }
+                            Ok(AttachClientMessage::Detach) => {
+                                info!("Closing {} session in container {}", kind, container_id);
+                                send_close(&server_tx, &auth, &session_id);
+                                None
+                            }
+                            Err(e) => {
+                                info!("Ignoring unknown {} message: {}", kind, e);
+                                None
+                            }
+                        }
+                    }
+                    Some(Ok(Message::Ping(payload))) => {
+                        let _ = ws_sender.send(Message::Pong(payload)).await;
+                        None
+                    }
+                    Some(Ok(Message::Close(_))) | None => {
+                        // Closing the socket detaches too; the container keeps running
+                        send_close(&server_tx, &auth, &session_id);
+                        client_gone = true;
+                        None
+                    }
+                    Some(Ok(_)) => None,
+                    Some(Err(e)) => {
+                        error!("WebSocket error: {:?}", e);
+                        None
+                    }
+                };
+
+                if let Some(data) = input {
+                    if let Some(recording) = &recording {
+                        recording.input(&data);
+                    }
+                    send_input(&server_tx, &auth, &session_id, data);
+                }
+            }
+
+            envelope = node_stream.recv() => {
+                let Some(envelope) = envelope else { break };
+                let Some(Payload::NodeResponse(resp)) = envelope.payload else { continue };
+                let output = match resp.kind {
+                    Some(Kind::SessionOutput(output)) => output,
+                    // Refused before the session was opened, e.g. by policy
+                    Some(Kind::Error(err)) => {
+                        send_closed(&mut ws_sender, err.message, None).await;
+                        break;
+                    }
+                    _ => continue,
+                };
+
+                if !output.data.is_empty() {
+                    if let Some(recording) = &recording {
+                        recording.output(&output.data);
+                    }
+                    if !client_gone
+                        && ws_sender.send(Message::Binary(output.data.into())).await.is_err()
+                    {
+                        send_close(&server_tx, &auth, &session_id);
+                        client_gone = true;
+                    }
+                }
+
+                if output.closed {
+                    if !client_gone {
+                        send_closed(&mut ws_sender, output.error, output.exit_code).await;
+                    }
+                    break;
+                }
+            }
+
+            _ = ping_interval.tick(), if !client_gone => {
+                if ws_sender.send(Message::Ping(axum::body::Bytes::new())).await.is_err() {
+                    send_close(&server_tx, &auth, &session_id);
+                    client_gone = true;
+                }
+            }
+
+            () = shutdown.cancelled(), if !client_gone => {
+                // Detached like a closed socket, so the process keeps running
+                info!("Closing {} session {}: coordinator shutting down", kind, session_id);
+                send_close(&server_tx, &auth, &session_id);
+                let _ = ws_sender.send(going_away()).await;
+                client_gone = true;
+            }
+
+            _ = tokio::time::sleep(DETACH_TIMEOUT), if client_gone => {
+                info!("Node did not confirm detach of session {}", session_id);
+                break;
+            }
+        }
+    }
+
+    info!("🔚 {} session {} ended", kind, session_id);
+}
+
+async fn send_closed(
+    ws_sender: &mut futures_util::stream::SplitSink<WebSocket, Message>,
+    error: String,
+    exit_code: Option<i64>,
+) {
+    let mut body = json!({
+        "type": "closed",
+        "error": (!error.is_empty()).then_some(error),
+    });
+    if let Some(exit_code) = exit_code {
+        body["exit_code"] = exit_code.into();
+    }
+    let _ = ws_sender.send(Message::Text(body.to_string().into())).await;
+    let _ = ws_sender.send(Message::Close(None)).await;
+}
+
+fn send_input(server_tx: &CommandRouter, auth: &AuthParams, session_id: &str, data: Vec<u8>) {
+    send_session_command(
+        server_tx,
+        auth,
+        node_command::Kind::SessionInput(SessionInput {
+            request_id: session_id.to_string(),
+            data,
+        }),
+    );
+}
+
+fn send_resize(
+    server_tx: &CommandRouter,
+    auth: &AuthParams,
+    session_id: &str,
+    cols: u16,
+    rows: u16,
+) {
+    send_session_command(
+        server_tx,
+        auth,
+        node_command::Kind::ResizeSession(ResizeSession {
+            request_id: session_id.to_string(),
+            cols: cols.into(),
+            rows: rows.into(),
+        }),
+    );
+}
+
+fn send_close(server_tx: &CommandRouter, auth: &AuthParams, session_id: &str) {
+    send_session_command(
+        server_tx,
+        auth,
+        node_command::Kind::CloseSession(CloseSession {
+            request_id: session_id.to_string(),
+        }),
+    );
+}
+
+fn send_session_command(server_tx: &CommandRouter, auth: &AuthParams, kind: node_command::Kind) {
+    let request = ServerRequestByUser::new(
+        auth.node_id.clone(),
+        auth.password.clone(),
+        Envelope {
+            payload: Some(Payload::NodeCommand(NodeCommand { kind: Some(kind) })),
+            ..Default::default()
+        },
+    );
+    if let Err(e) = server_tx.send(request) {
+        error!("Failed to send session command: {}", e);
+    }
+}
@@ -0,0 +1,164 @@
+use axum::{
+    extract::{
+        Extension, Path, Query,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    response::Response,
+};
+use lib_coordinator_core::{
+    CommandRouter, NodeCapability, NodeChannels, NodeStateCache, PendingStreams, SessionRecorder,
+    TraceContext, WsConnection,
+};
+use lib_coordinator_rest::{AuthParams, TenantWsSlot, reject_unsupported};
+use proto::generated::{ExecContainer, RequestType, node_command};
+use serde::Deserialize;
+use serde_json::json;
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::ws_attach::{SessionRequest, relay_session};
+
+const DEFAULT_COMMAND: &str = "/bin/sh";
+const MAX_COMMAND_ARGS: usize = 64;
+
+#[derive(Deserialize)]
+pub struct ExecParams {
+    /// A JSON array of arguments, or a command line split on whitespace.
+    cmd: Option<String>,
+    tty: Option<bool>,
+    cols: Option<u16>,
+    rows: Option<u16>,
+    user: Option<String>,
+    workdir: Option<String>,
+}
+
+// Axum extractors, one per piece of shared state
+#[allow(clippy::too_many_arguments)]
+pub async fn handle_exec_connection(
+    Path(container_id): Path<String>,
+    auth: AuthParams,
+    Query(params): Query<ExecParams>,
+    ws: WebSocketUpgrade,
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(pending_streams): Extension<PendingStreams>,
+    Extension(recorder): Extension<SessionRecorder>,
+    Extension(node_states): Extension<NodeStateCache>,
+    Extension(shutdown): Extension<CancellationToken>,
+    tenant_slot: Option<Extension<TenantWsSlot>>,
+    ws_connection: Option<Extension<WsConnection>>,
+) -> Response {
+    // Refused before the upgrade, so the client gets a plain 501
+    if params.tty.unwrap_or(true) {
+        let request_id = Uuid::new_v4().to_string();
+        if let Some(response) = reject_unsupported(
+            &nodes,
+            &node_states,
+            &auth,
+            NodeCapability::ExecTty,
+            &request_id,
+        )
+        .await
+        {
+            return response;
+        }
+    }
+    let trace = TraceContext::current();
+    ws.on_upgrade(move |socket| {
+        TraceContext::resume(trace, async move {
+            // Counts against the tenant's and the coordinator's connection caps until the socket closes
+            let _tenant_slot = tenant_slot;
+            let _ws_connection = ws_connection;
+            handle_socket(
+                socket,
+                container_id,
+                auth,
+                params,
+                server_tx,
+                nodes,
+                pending_streams,
+                recorder,
+                shutdown,
+            )
+            .await
+        })
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn handle_socket(
+    mut socket: WebSocket,
+    container_id: String,
+    auth: AuthParams,
+    params: ExecParams,
+    server_tx: CommandRouter,
+    nodes: NodeChannels,
+    pending_streams: PendingStreams,
+    recorder: SessionRecorder,
+    shutdown: CancellationToken,
+) {
+    let command = match parse_command(params.cmd.as_deref().unwrap_or(DEFAULT_COMMAND)) {
+        Ok(command) => command,
+        Err(error) => {
+            let body = json!({ "type": "closed", "error": error });
+            let _ = socket.send(Message::Text(body.to_string().into())).await;
+            let _ = socket.send(Message::Close(None)).await;
+            return;
+        }
+    };
+    info!(
+        "🔌 Exec {:?} in container {} on node {}",
+        command, container_id, auth.node_id
+    );
+
+    let session_id = Uuid::new_v4().to_string();
+    let cols = params.cols.unwrap_or(0);
+    let rows = params.rows.unwrap_or(0);
+    let request = SessionRequest {
+        kind: "exec",
+        request_type: RequestType::ExecContainer,
+        command: node_command::Kind::ExecContainer(ExecContainer {
+            request_id: session_id.clone(),
+            container_id: container_id.clone(),
+            command: command.clone(),
+            tty: params.tty.unwrap_or(true),
+            cols: cols.into(),
+            rows: rows.into(),
+            user: params.user.unwrap_or_default(),
+            working_dir: params.workdir.unwrap_or_default(),
+            env: Vec::new(),
+        }),
+        args: command,
+        cols,
+        rows,
+    };
+    relay_session(
+        socket,
+        container_id,
+        auth,
+        session_id,
+        request,
+        server_tx,
+        nodes,
+        pending_streams,
+        recorder,
+        shutdown,
+    )
+    .await;
+}
+
+fn parse_command(cmd: &str) -> Result<Vec<String>, String> {
+    let command: Vec<String> = if cmd.trim_start().starts_with('[') {
+        serde_json::from_str(cmd).map_err(|e| format!("cmd is not a JSON array: {}", e))?
+    } else {
+        cmd.split_whitespace().map(String::from).collect()
+    };
+    if command.is_empty() || command[0].is_empty() {
+        return Err("cmd must not be empty".to_string());
+    }
+    if command.len() > MAX_COMMAND_ARGS {
+        return Err(format!("cmd has more than {} arguments", MAX_COMMAND_ARGS));
+    }
+    Ok(command)
+}
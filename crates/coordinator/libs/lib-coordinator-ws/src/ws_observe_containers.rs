@@ -1,130 +1,361 @@
 use axum::{
     extract::{
-        Extension, Query,
+        Extension,
         ws::{Message, WebSocket, WebSocketUpgrade},
     },
     response::IntoResponse,
 };
-use dashmap::DashMap;
 use futures_util::{SinkExt, StreamExt};
-use lib_coordinator_core::ServerRequestByUser;
-use lib_coordinator_rest::AuthParams;
+use lib_coordinator_core::{
+    CommandRouter, NodeChannels, NodeConnectionEvent, NodeStateCache, PendingResponses, RouteError,
+    ServerRequestByUser, TraceContext, WsConnection,
+};
+use lib_coordinator_rest::node_request::request_node;
+use lib_coordinator_rest::timestamps::add_rfc3339_fields;
+use lib_coordinator_rest::{AuthParams, TenantWsSlot, VerifiedNode};
 use proto::generated::{
-    Envelope, GetNodeContainers, NodeCommand, RequestType, envelope::Payload, node_command,
+    Envelope, GetNodeContainers, NodeCommand, Ping, RequestType, envelope::Payload, node_command,
     node_response::Kind,
 };
+use serde::Deserialize;
 use serde_json::json;
-use std::sync::Arc;
-use tokio::sync::broadcast::{
-    self,
-    error::{self, RecvError},
-};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::mpsc;
 use tokio::time::{Duration, interval};
+use tokio_util::sync::CancellationToken;
 use tracing::{error, info};
 use uuid::Uuid;
 
+use crate::ws_server::going_away;
+
+const NODE_PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Messages a WebSocket client may send.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    /// Application-level ping; answered with a `pong` carrying server (and node) timestamps.
+    Ping {
+        client_ts: Option<i64>,
+        #[serde(default)]
+        through_node: bool,
+    },
+}
+
+/// What text handlers need to answer a client, possibly after a node round trip.
+struct ClientContext {
+    server_tx: CommandRouter,
+    pending: PendingResponses,
+    auth: AuthParams,
+    reply_tx: mpsc::Sender<String>,
+}
+
+// Axum extractors, one per piece of shared state
+#[allow(clippy::too_many_arguments)]
 pub async fn handle_ws_connection(
-    Query(auth_params): Query<AuthParams>,
+    auth_params: AuthParams,
+    verified: Option<Extension<VerifiedNode>>,
     ws: WebSocketUpgrade,
-    Extension(server_tx): Extension<broadcast::Sender<ServerRequestByUser>>,
-    Extension(nodes): Extension<Arc<DashMap<(String, String), broadcast::Sender<Envelope>>>>,
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(pending): Extension<PendingResponses>,
+    Extension(node_states): Extension<NodeStateCache>,
+    Extension(shutdown): Extension<CancellationToken>,
+    tenant_slot: Option<Extension<TenantWsSlot>>,
+    ws_connection: Option<Extension<WsConnection>>,
 ) -> impl IntoResponse {
+    let trace = TraceContext::current();
     ws.on_upgrade(move |socket| {
-        handle_socket(
-            socket,
-            auth_params.node_id,
-            auth_params.password,
-            server_tx,
-            nodes,
-        )
+        TraceContext::resume(trace, async move {
+            // Counts against the tenant's and the coordinator's connection caps until the socket closes
+            let _tenant_slot = tenant_slot;
+            let _ws_connection = ws_connection;
+            handle_socket(
+                socket,
+                auth_params.node_id,
+                auth_params.password,
+                verified.map(|Extension(verified)| verified),
+                server_tx,
+                nodes,
+                pending,
+                node_states,
+                shutdown,
+            )
+            .await
+        })
     })
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn handle_socket(
     socket: WebSocket,
     node_id: String,
     password: String,
-    server_tx: broadcast::Sender<ServerRequestByUser>,
-    nodes: Arc<DashMap<(String, String), broadcast::Sender<Envelope>>>,
+    verified: Option<VerifiedNode>,
+    server_tx: CommandRouter,
+    nodes: NodeChannels,
+    pending: PendingResponses,
+    node_states: NodeStateCache,
+    shutdown: CancellationToken,
 ) {
     let (mut ws_sender, mut ws_receiver) = socket.split();
     info!("🔌 New WebSocket connection for node: {}", node_id);
 
-    // Check if the node is registered
-    let node_key = (node_id.clone(), password.clone());
-    let Some(node_tx) = nodes.get(&node_key).map(|g| g.value().clone()) else {
+    // Connection changes are watched from before the node lookup, so a
+    // reconnect right after it is not missed
+    let mut connections_rx = node_states.subscribe_connections();
+
+    // Check if the node is registered. Subscribe to container updates for this
+    // node before taking the snapshot, so nothing that happens in between is
+    // lost. No sender is kept, so the channel closes when the node goes away.
+    let mut node_key = (node_id.clone(), password.clone());
+    let Some(mut broadcast_rx) = subscribe(&nodes, &node_key).await.map(Some) else {
         error!("Node {} not registered", node_id);
         let _ = ws_sender.send(Message::Close(None)).await;
         return;
     };
+    info!("📡 Containers observing for node: {}", node_id);
 
-    // Immediately send a request to get the current containers list
-    if let Err(e) = send_get_containers(&server_tx, &node_id, &password).await {
-        error!("Failed to send containers request: {}", e);
+    // Backfill the current state from the coordinator cache. If the node has not
+    // reported yet, ask it; its reply is forwarded as the snapshot instead.
+    match cached_snapshot(&node_states, &node_id) {
+        Some(snapshot) => {
+            if ws_sender
+                .send(Message::Text(snapshot.to_string().into()))
+                .await
+                .is_err()
+            {
+                error!("Failed to send snapshot to node {}", node_id);
+                return;
+            }
+        }
+        None => {
+            if let Err(e) = send_get_containers(&server_tx, &node_id, &password).await {
+                error!("Failed to send containers request: {}", e);
+            }
+        }
     }
 
-    // Subscribe to container updates for this node
-    let mut broadcast_rx = node_tx.subscribe();
-    info!("📡 Containers observing for node: {}", node_id);
-
     let mut ping_interval = interval(Duration::from_secs(20));
 
+    // Replies produced outside the loop (e.g. after a node round trip)
+    let (reply_tx, mut reply_rx) = mpsc::channel::<String>(16);
+    let mut client_ctx = ClientContext {
+        server_tx: server_tx.clone(),
+        pending,
+        auth: AuthParams {
+            node_id: node_id.clone(),
+            password: password.clone(),
+        },
+        reply_tx,
+    };
+
     // Main loop: handle both node and server messages
     loop {
         tokio::select! {
             // Handle incoming messages from the WebSocket node
             msg = ws_receiver.next() => {
-                if !handle_node_message(msg, &mut ws_sender).await {
+                if !handle_node_message(msg, &mut ws_sender, &client_ctx).await {
                     let _ = ws_sender.send(Message::Close(None)).await;
                     break;
                 }
             }
 
             // Handle messages from the server (container updates)
-            msg = broadcast_rx.recv() => {
+            msg = recv_node_update(&mut broadcast_rx) => {
+                // The node is gone; its disconnect event tells the client
+                if let Err(RecvError::Closed) = msg {
+                    broadcast_rx = None;
+                    continue;
+                }
                 if !handle_server_message(msg, &mut ws_sender, &node_id).await {
                     let _ = ws_sender.send(Message::Close(None)).await;
                     break;
                 }
             }
 
+            event = connections_rx.recv() => {
+                let event = match event {
+                    Ok(event) if event.node_id == node_id => event,
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                };
+                // A token or session stands in with the node's internal token,
+                // which is new for each connection
+                if event.connected && let Some(verified) = &verified {
+                    node_key.1 = verified.password(&nodes);
+                    client_ctx.auth.password = node_key.1.clone();
+                }
+                if !handle_connection_event(
+                    &event,
+                    &mut broadcast_rx,
+                    &mut ws_sender,
+                    &nodes,
+                    &node_key,
+                    &server_tx,
+                )
+                .await
+                {
+                    break;
+                }
+            }
+
+            Some(reply) = reply_rx.recv() => {
+                if ws_sender.send(Message::Text(reply.into())).await.is_err() {
+                    error!("Failed to send reply to node {}", node_id);
+                    break;
+                }
+            }
+
             _ = ping_interval.tick() => {
                 if ws_sender.send(Message::Ping(axum::body::Bytes::new())).await.is_err() {
                     error!("Failed to send Ping to node {}", node_id);
                     break;
                 }
             }
+
+            () = shutdown.cancelled() => {
+                let _ = ws_sender.send(going_away()).await;
+                break;
+            }
         }
     }
 
     info!("🔚 WebSocket session ended for {}", node_id);
 }
 
+// Next container update, or never while the node is disconnected
+async fn recv_node_update(
+    broadcast_rx: &mut Option<broadcast::Receiver<Envelope>>,
+) -> Result<Envelope, RecvError> {
+    match broadcast_rx {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+// Subscribes to the node's pushed updates if it is connected with these credentials
+async fn subscribe(
+    nodes: &NodeChannels,
+    (node_id, password): &(String, String),
+) -> Option<broadcast::Receiver<Envelope>> {
+    let node = nodes.get(node_id).map(|node| node.clone())?;
+    node.verify(password)
+        .await
+        .then(|| node.updates.subscribe())
+}
+
+// Tell the client the node went away or came back. On a reconnect the new
+// channel is subscribed and a fresh snapshot requested, since containers may
+// have changed while the node was gone.
+async fn handle_connection_event(
+    event: &NodeConnectionEvent,
+    broadcast_rx: &mut Option<broadcast::Receiver<Envelope>>,
+    ws_sender: &mut futures_util::stream::SplitSink<WebSocket, Message>,
+    nodes: &NodeChannels,
+    node_key: &(String, String),
+    server_tx: &CommandRouter,
+) -> bool {
+    let (node_id, password) = node_key;
+    if event.connected {
+        // A node with the same id but another password is not this client's
+        let Some(rx) = subscribe(nodes, node_key).await else {
+            return true;
+        };
+        *broadcast_rx = Some(rx);
+        info!("📡 Node {} reconnected, observing again", node_id);
+    } else {
+        *broadcast_rx = None;
+        info!("Node {} disconnected, waiting for it to reconnect", node_id);
+    }
+
+    let mut body = json!({
+        "type": "node_status",
+        "node_id": node_id,
+        "status": if event.connected { "connected" } else { "disconnected" },
+        "time": std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+    });
+    add_rfc3339_fields(&mut body);
+    if ws_sender
+        .send(Message::Text(body.to_string().into()))
+        .await
+        .is_err()
+    {
+        error!("Failed to send node status to {}", node_id);
+        return false;
+    }
+
+    if event.connected
+        && let Err(e) = send_get_containers(server_tx, node_id, password).await
+    {
+        error!("Failed to send containers request: {}", e);
+    }
+    true
+}
+
+// Full container state from the node cache, tagged as a snapshot
+fn cached_snapshot(node_states: &NodeStateCache, node_id: &str) -> Option<serde_json::Value> {
+    let state = node_states.get(node_id)?;
+    let as_of = state.containers_updated_at?;
+
+    let containers: Vec<_> = state
+        .containers
+        .iter()
+        .map(|c| c.container_id.clone())
+        .collect();
+    let states: Vec<_> = state
+        .containers
+        .iter()
+        .map(|c| {
+            json!({
+                "container_id": c.container_id,
+                "status": c.status,
+                "created": c.created,
+            })
+        })
+        .collect();
+
+    let mut snapshot = json!({
+        "type": "snapshot",
+        "as_of": as_of
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        "containers": containers,
+        "states": states,
+    });
+    add_rfc3339_fields(&mut snapshot);
+    Some(snapshot)
+}
+
 // Helper to send a GetNodeContainers command to the node
 async fn send_get_containers(
-    server_tx: &broadcast::Sender<ServerRequestByUser>,
+    server_tx: &CommandRouter,
     node_id: &str,
     password: &str,
-) -> Result<(), error::SendError<ServerRequestByUser>> {
-    server_tx
-        .send(ServerRequestByUser {
-            id: node_id.to_string(),
-            password: password.to_string(),
-            envelope: Envelope {
-                payload: Some(Payload::NodeCommand(NodeCommand {
-                    kind: Some(node_command::Kind::GetNodeContainers(GetNodeContainers {
-                        request_id: Uuid::new_v4().to_string(),
-                    })),
+) -> Result<(), RouteError> {
+    server_tx.send(ServerRequestByUser::new(
+        node_id.to_string(),
+        password.to_string(),
+        Envelope {
+            payload: Some(Payload::NodeCommand(NodeCommand {
+                kind: Some(node_command::Kind::GetNodeContainers(GetNodeContainers {
+                    request_id: Uuid::new_v4().to_string(),
                 })),
-            },
-        })
-        .map(|_| ())
+            })),
+            ..Default::default()
+        },
+    ))
 }
 
 // Handle messages from the WebSocket node (pings, closes, etc.)
 async fn handle_node_message(
     msg: Option<Result<Message, axum::Error>>,
     ws_sender: &mut futures_util::stream::SplitSink<WebSocket, Message>,
+    client_ctx: &ClientContext,
 ) -> bool {
     match msg {
         Some(Ok(Message::Ping(payload))) => {
@@ -139,6 +370,16 @@ async fn handle_node_message(
             info!("Node disconnected: {:?}", frame);
             false
         }
+        Some(Ok(Message::Text(text))) => {
+            match serde_json::from_str::<ClientMessage>(&text) {
+                Ok(ClientMessage::Ping {
+                    client_ts,
+                    through_node,
+                }) => handle_client_ping(client_ctx, client_ts, through_node),
+                Err(e) => info!("Ignoring unknown client message: {}", e),
+            }
+            true
+        }
         Some(Ok(_)) => {
             // Ignore other message types
             true
@@ -154,6 +395,70 @@ async fn handle_node_message(
     }
 }
 
+// Answer an application-level ping. With `through_node` the reply waits for a node round trip.
+fn handle_client_ping(client_ctx: &ClientContext, client_ts: Option<i64>, through_node: bool) {
+    let server_tx = client_ctx.server_tx.clone();
+    let pending = client_ctx.pending.clone();
+    let auth = AuthParams {
+        node_id: client_ctx.auth.node_id.clone(),
+        password: client_ctx.auth.password.clone(),
+    };
+    let reply_tx = client_ctx.reply_tx.clone();
+
+    tokio::spawn(async move {
+        let server_ts = unix_millis();
+        let mut reply = json!({
+            "type": "pong",
+            "client_ts": client_ts,
+            "server_ts": server_ts,
+        });
+
+        if through_node {
+            let request_id = Uuid::new_v4().to_string();
+            let envelope = Envelope {
+                payload: Some(Payload::NodeCommand(NodeCommand {
+                    kind: Some(node_command::Kind::Ping(Ping {
+                        request_id: request_id.clone(),
+                        sent_at_ms: server_ts,
+                    })),
+                })),
+                ..Default::default()
+            };
+            let result = request_node(
+                &server_tx,
+                &pending,
+                &auth,
+                &request_id,
+                RequestType::Ping as i32,
+                envelope,
+                NODE_PING_TIMEOUT,
+            )
+            .await;
+
+            match result {
+                Ok(response) => {
+                    if let Some(Payload::NodeResponse(resp)) = response.payload
+                        && let Some(Kind::Pong(pong)) = resp.kind
+                    {
+                        reply["node_ts"] = json!(pong.node_time_ms);
+                        reply["node_rtt_ms"] = json!(unix_millis() - pong.sent_at_ms);
+                    }
+                }
+                Err(e) => reply["node_error"] = json!(e.to_string()),
+            }
+        }
+
+        let _ = reply_tx.send(reply.to_string()).await;
+    });
+}
+
+fn unix_millis() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
 // Handle messages from the server (container updates) and send to WebSocket node
 async fn handle_server_message(
     msg: Result<Envelope, RecvError>,
@@ -169,9 +474,18 @@ async fn handle_server_message(
                             if rk.request_type == RequestType::GetContainers as i32
                                 || rk.request_type == RequestType::UpdateContainerInfo as i32
                             {
-                                let body = json!({
+                                // A reply to GetContainers is the full list requested on subscribe
+                                let message_type =
+                                    if rk.request_type == RequestType::GetContainers as i32 {
+                                        "snapshot"
+                                    } else {
+                                        "update"
+                                    };
+                                let mut body = json!({
+                                    "type": message_type,
                                     "containers": containers_msg.containers,
                                 });
+                                add_rfc3339_fields(&mut body);
 
                                 if ws_sender
                                     .send(Message::Text(body.to_string().into()))
@@ -181,18 +495,50 @@ async fn handle_server_message(
                                     error!("Failed to send to node {}", node_id);
                                     return false;
                                 }
-                                return true;
+                                true
                             } else {
-                                return true;
+                                true
                             }
                         } else {
-                            return true;
+                            true
                         }
                     }
-                    _ => return true,
+                    Some(Kind::ContainerEvent(ref event))
+                        if event.action == "health_status"
+                            || event.r#type == "image"
+                            || event.r#type == "volume" =>
+                    {
+                        let mut body = if event.action == "health_status" {
+                            json!({
+                                "type": "health",
+                                "container": event.name,
+                                "health": event.health,
+                                "time": event.time,
+                            })
+                        } else {
+                            json!({
+                                "type": event.r#type,
+                                "action": event.action,
+                                "id": event.container_id,
+                                "name": event.name,
+                                "time": event.time,
+                            })
+                        };
+                        add_rfc3339_fields(&mut body);
+                        if ws_sender
+                            .send(Message::Text(body.to_string().into()))
+                            .await
+                            .is_err()
+                        {
+                            error!("Failed to send to node {}", node_id);
+                            return false;
+                        }
+                        true
+                    }
+                    _ => true,
                 }
             } else {
-                return true;
+                true
             }
         }
         Err(_e) => {
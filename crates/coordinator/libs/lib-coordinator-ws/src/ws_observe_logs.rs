@@ -0,0 +1,416 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{
+        Extension, Path, Query,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    response::IntoResponse,
+};
+use dashmap::{DashMap, mapref::entry::Entry};
+use futures_util::{SinkExt, StreamExt};
+use lib_coordinator_core::{
+    CommandRouter, NodeChannels, PendingResponses, PendingStreams, TraceContext, WsConnection,
+    verify_node,
+};
+use lib_coordinator_rest::node_request::request_node;
+use lib_coordinator_rest::node_stream::{NodeStream, open_node_stream};
+use lib_coordinator_rest::{AuthParams, TenantWsSlot};
+use proto::generated::{
+    Envelope, FollowContainerLogs, GetContainerLogs, LogLine, LogStreams, NodeCommand, RequestType,
+    envelope::Payload, node_command, node_response::Kind,
+};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio::sync::broadcast;
+use tokio::time::{Duration, interval};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::ws_server::going_away;
+
+const DEFAULT_TAIL: i32 = 100;
+const MAX_TAIL: i32 = 10_000;
+const HISTORY_TIMEOUT: Duration = Duration::from_secs(10);
+// Batches a follow keeps for clients that read slower than the container logs
+const FOLLOW_CAPACITY: usize = 256;
+// How often an idle follow checks whether anyone still watches it
+const FOLLOW_IDLE_CHECK: Duration = Duration::from_secs(5);
+
+#[derive(Deserialize)]
+pub struct ObserveLogsParams {
+    /// Earlier lines sent before the live ones.
+    tail: Option<i32>,
+    #[serde(default)]
+    timestamps: bool,
+    /// `stdout`, `stderr` or `both` (default).
+    streams: Option<String>,
+}
+
+#[derive(Clone)]
+enum FollowEvent {
+    Lines(Arc<[LogLine]>),
+    /// The follow ended: the container stopped, or with the node's error.
+    End(Option<String>),
+}
+
+// Node, container, whether lines carry timestamps, and the streams followed
+type FollowKey = (String, String, bool, LogStreams);
+
+/// Log follows running on nodes. Every client watching the same container
+/// shares one follow, which is cancelled once the last client has left.
+#[derive(Clone, Default)]
+pub struct LogFollows {
+    follows: Arc<DashMap<FollowKey, broadcast::Sender<FollowEvent>>>,
+}
+
+impl LogFollows {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Joins the container's follow, asking the node to start one if none runs.
+    fn subscribe(
+        &self,
+        key: FollowKey,
+        server_tx: &CommandRouter,
+        pending_streams: &PendingStreams,
+        auth: &AuthParams,
+    ) -> Result<broadcast::Receiver<FollowEvent>, String> {
+        match self.follows.entry(key.clone()) {
+            Entry::Occupied(follow) => Ok(follow.get().subscribe()),
+            Entry::Vacant(slot) => {
+                let request_id = Uuid::new_v4().to_string();
+                let envelope = Envelope {
+                    payload: Some(Payload::NodeCommand(NodeCommand {
+                        kind: Some(node_command::Kind::FollowContainerLogs(
+                            FollowContainerLogs {
+                                request_id: request_id.clone(),
+                                container_id: key.1.clone(),
+                                // Each client reads its own tail first
+                                tail: 0,
+                                timestamps: key.2,
+                                streams: key.3.into(),
+                            },
+                        )),
+                    })),
+                    ..Default::default()
+                };
+                let stream = open_node_stream(
+                    server_tx,
+                    pending_streams,
+                    auth,
+                    &request_id,
+                    RequestType::FollowContainerLogs as i32,
+                    envelope,
+                )?;
+                let (tx, rx) = broadcast::channel(FOLLOW_CAPACITY);
+                slot.insert(tx.clone());
+                info!("Following logs of {} on node {}", key.1, key.0);
+                tokio::spawn(self.clone().relay(key, stream, tx));
+                Ok(rx)
+            }
+        }
+    }
+
+    /// Passes the node's log batches to every subscriber until the follow ends
+    /// or nobody watches it. Dropping the stream cancels the follow on the node.
+    async fn relay(
+        self,
+        key: FollowKey,
+        mut stream: NodeStream,
+        tx: broadcast::Sender<FollowEvent>,
+    ) {
+        let mut idle_check = interval(FOLLOW_IDLE_CHECK);
+        let end = loop {
+            tokio::select! {
+                envelope = stream.recv() => {
+                    let Some(envelope) = envelope else {
+                        break Some("node stream closed".to_string());
+                    };
+                    let Some(Payload::NodeResponse(resp)) = envelope.payload else { continue };
+                    match resp.kind {
+                        Some(Kind::ContainerLogs(logs)) => {
+                            if !logs.lines.is_empty() {
+                                let _ = tx.send(FollowEvent::Lines(logs.lines.into()));
+                            }
+                            if logs.done {
+                                break None;
+                            }
+                        }
+                        Some(Kind::Error(err)) => break Some(err.message),
+                        _ => {}
+                    }
+                }
+
+                _ = idle_check.tick() => {
+                    // Holding the entry keeps new clients from joining while it goes
+                    if self
+                        .follows
+                        .remove_if(&key, |_, follow| {
+                            follow.same_channel(&tx) && follow.receiver_count() == 0
+                        })
+                        .is_some()
+                    {
+                        info!("Stopped following logs of {} on node {}", key.1, key.0);
+                        return;
+                    }
+                }
+            }
+        };
+
+        self.follows
+            .remove_if(&key, |_, follow| follow.same_channel(&tx));
+        let _ = tx.send(FollowEvent::End(end));
+    }
+}
+
+// Axum extractors, one per piece of shared state
+#[allow(clippy::too_many_arguments)]
+pub async fn handle_observe_logs_connection(
+    Path(container_id): Path<String>,
+    auth: AuthParams,
+    Query(params): Query<ObserveLogsParams>,
+    ws: WebSocketUpgrade,
+    Extension(server_tx): Extension<CommandRouter>,
+    Extension(nodes): Extension<NodeChannels>,
+    Extension(pending): Extension<PendingResponses>,
+    Extension(pending_streams): Extension<PendingStreams>,
+    Extension(follows): Extension<LogFollows>,
+    Extension(shutdown): Extension<CancellationToken>,
+    tenant_slot: Option<Extension<TenantWsSlot>>,
+    ws_connection: Option<Extension<WsConnection>>,
+) -> impl IntoResponse {
+    let trace = TraceContext::current();
+    ws.on_upgrade(move |socket| {
+        TraceContext::resume(trace, async move {
+            // Counts against the tenant's and the coordinator's connection caps until the socket closes
+            let _tenant_slot = tenant_slot;
+            let _ws_connection = ws_connection;
+            handle_socket(
+                socket,
+                container_id,
+                auth,
+                params,
+                server_tx,
+                nodes,
+                pending,
+                pending_streams,
+                follows,
+                shutdown,
+            )
+            .await
+        })
+    })
+}
+
+// One per piece of shared coordinator state
+#[allow(clippy::too_many_arguments)]
+async fn handle_socket(
+    socket: WebSocket,
+    container_id: String,
+    auth: AuthParams,
+    params: ObserveLogsParams,
+    server_tx: CommandRouter,
+    nodes: NodeChannels,
+    pending: PendingResponses,
+    pending_streams: PendingStreams,
+    follows: LogFollows,
+    shutdown: CancellationToken,
+) {
+    let (mut ws_sender, mut ws_receiver) = socket.split();
+    let tail = params.tail.unwrap_or(DEFAULT_TAIL);
+    if !(0..=MAX_TAIL).contains(&tail) {
+        let error = format!("tail must be between 0 and {}", MAX_TAIL);
+        send_closed(&mut ws_sender, Some(error)).await;
+        return;
+    }
+    let streams = match params.streams.as_deref() {
+        None | Some("both") => LogStreams::Both,
+        Some("stdout") => LogStreams::Stdout,
+        Some("stderr") => LogStreams::Stderr,
+        Some(_) => {
+            let error = "streams must be stdout, stderr or both".to_string();
+            send_closed(&mut ws_sender, Some(error)).await;
+            return;
+        }
+    };
+    if !verify_node(&nodes, &auth.node_id, &auth.password).await {
+        error!("Node {} not registered", auth.node_id);
+        let _ = ws_sender.send(Message::Close(None)).await;
+        return;
+    }
+    info!(
+        "🔌 Observing logs of {} on node {}",
+        container_id, auth.node_id
+    );
+
+    // Joined before the tail is read, so no line falls between the two; one
+    // written meanwhile may arrive twice instead
+    let key = (
+        auth.node_id.clone(),
+        container_id.clone(),
+        params.timestamps,
+        streams,
+    );
+    let mut follow = match follows.subscribe(key, &server_tx, &pending_streams, &auth) {
+        Ok(follow) => follow,
+        Err(e) => {
+            error!("Failed to send follow logs request: {}", e);
+            let _ = ws_sender.send(Message::Close(None)).await;
+            return;
+        }
+    };
+    if tail > 0 {
+        match read_tail(
+            &server_tx,
+            &pending,
+            &auth,
+            &container_id,
+            tail,
+            params.timestamps,
+            streams,
+        )
+        .await
+        {
+            Ok(lines) if lines.is_empty() => {}
+            Ok(lines) => {
+                if send_lines(&mut ws_sender, &lines).await.is_err() {
+                    return;
+                }
+            }
+            Err(e) => {
+                send_closed(&mut ws_sender, Some(e)).await;
+                return;
+            }
+        }
+    }
+
+    let mut ping_interval = interval(Duration::from_secs(20));
+    loop {
+        tokio::select! {
+            msg = ws_receiver.next() => match msg {
+                Some(Ok(Message::Ping(payload))) => {
+                    let _ = ws_sender.send(Message::Pong(payload)).await;
+                }
+                Some(Ok(Message::Close(_))) | None => break,
+                Some(Ok(_)) => {}
+                Some(Err(e)) => {
+                    error!("WebSocket error: {:?}", e);
+                    break;
+                }
+            },
+
+            event = follow.recv() => match event {
+                Ok(FollowEvent::Lines(lines)) => {
+                    if send_lines(&mut ws_sender, &lines).await.is_err() {
+                        break;
+                    }
+                }
+                Ok(FollowEvent::End(error)) => {
+                    send_closed(&mut ws_sender, error).await;
+                    break;
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    let body = json!({ "type": "lagged", "skipped": skipped });
+                    if ws_sender.send(Message::Text(body.to_string().into())).await.is_err() {
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Closed) => {
+                    send_closed(&mut ws_sender, None).await;
+                    break;
+                }
+            },
+
+            _ = ping_interval.tick() => {
+                if ws_sender.send(Message::Ping(axum::body::Bytes::new())).await.is_err() {
+                    break;
+                }
+            }
+
+            () = shutdown.cancelled() => {
+                let _ = ws_sender.send(going_away()).await;
+                break;
+            }
+        }
+    }
+
+    info!("🔚 Stopped observing logs of {}", container_id);
+}
+
+/// The last `tail` lines, read separately so each client gets its own.
+async fn read_tail(
+    server_tx: &CommandRouter,
+    pending: &PendingResponses,
+    auth: &AuthParams,
+    container_id: &str,
+    tail: i32,
+    timestamps: bool,
+    streams: LogStreams,
+) -> Result<Vec<LogLine>, String> {
+    let request_id = Uuid::new_v4().to_string();
+    let envelope = Envelope {
+        payload: Some(Payload::NodeCommand(NodeCommand {
+            kind: Some(node_command::Kind::GetContainerLogs(GetContainerLogs {
+                request_id: request_id.clone(),
+                container_id: container_id.to_string(),
+                tail,
+                follow: false,
+                since: String::new(),
+                timestamps,
+                max_bytes: 0,
+                fresh: false,
+                streams: streams.into(),
+                after: None,
+            })),
+        })),
+        ..Default::default()
+    };
+    let response = request_node(
+        server_tx,
+        pending,
+        auth,
+        &request_id,
+        RequestType::GetContainerLogs as i32,
+        envelope,
+        HISTORY_TIMEOUT,
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+    match response.payload {
+        Some(Payload::NodeResponse(resp)) => match resp.kind {
+            Some(Kind::ContainerLogs(logs)) => Ok(logs.lines),
+            _ => Ok(Vec::new()),
+        },
+        _ => Ok(Vec::new()),
+    }
+}
+
+async fn send_lines(
+    ws_sender: &mut futures_util::stream::SplitSink<WebSocket, Message>,
+    lines: &[LogLine],
+) -> Result<(), axum::Error> {
+    let lines: Vec<Value> = lines.iter().map(line_record).collect();
+    let body = json!({ "type": "logs", "lines": lines });
+    ws_sender.send(Message::Text(body.to_string().into())).await
+}
+
+/// A line as the REST logs endpoint returns it: its stream, and its timestamp
+/// when the node read one.
+fn line_record(line: &LogLine) -> Value {
+    match line.timestamp.is_empty() {
+        true => json!({ "stream": line.stream, "line": line.text }),
+        false => json!({ "stream": line.stream, "timestamp": line.timestamp, "line": line.text }),
+    }
+}
+
+async fn send_closed(
+    ws_sender: &mut futures_util::stream::SplitSink<WebSocket, Message>,
+    error: Option<String>,
+) {
+    let body = json!({ "type": "closed", "error": error });
+    let _ = ws_sender.send(Message::Text(body.to_string().into())).await;
+    let _ = ws_sender.send(Message::Close(None)).await;
+}
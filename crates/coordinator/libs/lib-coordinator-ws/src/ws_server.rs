@@ -1,23 +1,53 @@
+use axum::extract::ws::{CloseFrame, Message, close_code};
 use axum::{Extension, Router, routing::get};
-use dashmap::DashMap;
-use lib_coordinator_core::PendingResponses;
-use proto::generated::Envelope;
-use std::sync::Arc;
-use tokio::sync::broadcast;
+use lib_coordinator_core::{
+    NodeChannels, NodeStateCache, PendingResponses, PendingStreams, SessionRecorder,
+};
+use tokio_util::sync::CancellationToken;
 
+use crate::ws_attach;
+use crate::ws_exec;
 use crate::ws_observe_containers::{self};
+use crate::ws_observe_logs::{self, LogFollows};
 
 pub fn build_ws_router(
-    server_cmd_tx: broadcast::Sender<lib_coordinator_core::ServerRequestByUser>,
-    clients: Arc<DashMap<(String, String), broadcast::Sender<Envelope>>>,
+    server_cmd_tx: lib_coordinator_core::CommandRouter,
+    clients: NodeChannels,
     pending: PendingResponses,
+    pending_streams: PendingStreams,
+    node_states: NodeStateCache,
+    recorder: SessionRecorder,
+    shutdown: CancellationToken,
 ) -> Router {
     Router::new()
         .route(
             "/observe-containers",
             get(ws_observe_containers::handle_ws_connection),
         )
+        .route(
+            "/attach/{container_id}",
+            get(ws_attach::handle_attach_connection),
+        )
+        .route("/exec/{container_id}", get(ws_exec::handle_exec_connection))
+        .route(
+            "/observe-logs/{container_id}",
+            get(ws_observe_logs::handle_observe_logs_connection),
+        )
         .layer(Extension(server_cmd_tx.clone()))
         .layer(Extension(clients.clone()))
         .layer(Extension(pending.clone()))
+        .layer(Extension(pending_streams))
+        .layer(Extension(node_states))
+        .layer(Extension(recorder))
+        .layer(Extension(LogFollows::new()))
+        .layer(Extension(shutdown))
+}
+
+/// Sent to every open socket when the coordinator shuts down, so clients can
+/// tell it from a dropped connection and reconnect.
+pub(crate) fn going_away() -> Message {
+    Message::Close(Some(CloseFrame {
+        code: close_code::AWAY,
+        reason: "Coordinator shutting down".into(),
+    }))
 }
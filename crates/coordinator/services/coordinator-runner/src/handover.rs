@@ -0,0 +1,72 @@
+use std::net::SocketAddr;
+
+use tokio::net::{TcpListener, TcpSocket};
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+const LISTEN_BACKLOG: u32 = 1024;
+
+/// Binds a listener. With `reuse_port` another coordinator may bind the same
+/// address while this one runs, and the kernel spreads new connections over
+/// both until this one stops accepting.
+pub(crate) fn bind(addr: SocketAddr, reuse_port: bool) -> std::io::Result<TcpListener> {
+    let socket = if addr.is_ipv4() {
+        TcpSocket::new_v4()?
+    } else {
+        TcpSocket::new_v6()?
+    };
+    socket.set_reuseaddr(true)?;
+    if reuse_port {
+        #[cfg(unix)]
+        socket.set_reuseport(true)?;
+        #[cfg(not(unix))]
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "--reuse-port needs a Unix system",
+        ));
+    }
+    socket.bind(addr)?;
+    socket.listen(LISTEN_BACKLOG)
+}
+
+/// Flips to `true` once a handover is requested with `SIGUSR2`, after which
+/// the listeners close and the coordinator drains and exits.
+pub(crate) fn watch_handover() -> watch::Receiver<bool> {
+    let (tx, rx) = watch::channel(false);
+    tokio::spawn(async move {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{SignalKind, signal};
+
+            match signal(SignalKind::user_defined2()) {
+                Ok(mut usr2) => {
+                    usr2.recv().await;
+                    info!("SIGUSR2 received: handing over, no longer accepting connections");
+                    let _ = tx.send(true);
+                }
+                Err(e) => warn!("Cannot listen for SIGUSR2, handover disabled: {}", e),
+            }
+        }
+        // Holding the sender keeps receivers from taking a closed channel for a handover
+        let _sender = tx;
+        std::future::pending::<()>().await;
+    });
+    rx
+}
+
+/// Completes once a handover is requested.
+pub(crate) async fn handed_over(mut handover: watch::Receiver<bool>) {
+    if handover.wait_for(|requested| *requested).await.is_err() {
+        std::future::pending::<()>().await;
+    }
+}
+
+/// Completes once a handover is requested or `shutdown` is cancelled; either
+/// way the listeners close and in-flight requests drain.
+pub(crate) async fn stopping(handover: watch::Receiver<bool>, shutdown: CancellationToken) {
+    tokio::select! {
+        () = handed_over(handover) => {}
+        () = shutdown.cancelled() => {}
+    }
+}
@@ -1,24 +1,126 @@
-use axum::Router;
+mod handover;
+
+use axum::{Router, middleware};
 use dashmap::DashMap;
-use lib_coordinator_core::PendingResponses;
+use lib_coordinator_core::{
+    AllowAll, AnnotationStore, AutostartStore, CleanupStore, CommandRouter, DesiredStateStore,
+    EventLog, FavoritesStore, FreezeStore, GitOps, LogExportCursors, LogForwardingStore,
+    NodeChannels, NodeGroupStore, NodeStateCache, PendingResponses, PendingStreams, ReportStore,
+    ResourcePeaks, SecretStore, ServerRequestByUser, SessionRecorder, SharedAuthorizer,
+    TemplateStore, TenantStore, TrashStore, WebhookAuthorizer, spawn_pending_sweeper,
+};
 use lib_coordinator_grpc::{grpc_server_service::CoordinatorServiceImpl, run_grpc_server};
-use lib_coordinator_rest::build_rest_router;
+use lib_coordinator_rest::{
+    AccessLog, AdminToken, BearerAuth, DesiredStateReconciler, GitOpsReconciler, ReportScheduler,
+    RestContext, SessionAuth, SessionStore, TenantAccess, TenantUsage, TokenStore, WsLimiter,
+    build_rest_router, enforce_tenant_access, limit_ws_connections, log_access, openapi_routes,
+    resolve_bearer_token, resolve_session_cookie, session_routes, trace_requests,
+};
 use lib_coordinator_ws::build_ws_router;
-use proto::generated::Envelope;
-use std::{net::SocketAddr, sync::Arc};
-use tokio::sync::broadcast;
-use tracing::info;
+use proto::generated::{
+    CoordinatorShutdown, Envelope, ServerCommand, envelope::Payload, server_command,
+};
+use std::{
+    net::SocketAddr,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+const DEFAULT_DRAIN_TIMEOUT_SECS: u64 = 30;
+// How long nodes get to end their streams after the shutdown notice
+const NODE_CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub use lib_coordinator_core::{
+    EmailConfig, GitOpsConfig, GitOpsMode, ReportConfig, reports::parse_report_time,
+};
+pub use lib_coordinator_grpc::GrpcServerTls;
+pub use lib_coordinator_integrations::{
+    Exporter, ExporterRegistry, IntegrationEvent, PrometheusExporter, SyslogExporter,
+    WebhookExporter,
+};
+pub use lib_coordinator_rest::{
+    AccessLogConfig, AccessLogFormat, ApprovalPolicy, PendingLimit, RateLimit, ResponseCacheConfig,
+    WsLimit,
+};
+
+/// Coordinator settings beyond the listen addresses.
+#[derive(Debug, Clone, Default)]
+pub struct CoordinatorOptions {
+    /// Enables cluster-wide admin routes (`/api/cluster/...`) when set.
+    pub admin_token: Option<String>,
+    /// Directory for persisted coordinator state (annotations, favorites, event history, ...); in-memory only when unset.
+    pub state_dir: Option<PathBuf>,
+    /// Record interactive sessions (exec, attach) into the state directory.
+    pub record_sessions: bool,
+    /// HTTP policy endpoint consulted before each node command (e.g. OPA); everything is allowed when unset.
+    pub authz_webhook: Option<String>,
+    /// Allow commands when the policy endpoint is unreachable instead of denying them.
+    pub authz_fail_open: bool,
+    /// HTTP endpoint receiving container health transitions; off when unset.
+    pub health_webhook: Option<String>,
+    /// Integrations fed with container events and node connections, e.g.
+    /// webhooks, Prometheus metrics or syslog; register custom ones here.
+    pub exporters: ExporterRegistry,
+    /// Largest gRPC message exchanged with nodes, in bytes; `proto::DEFAULT_MAX_MESSAGE_SIZE` when unset.
+    pub max_message_size: Option<usize>,
+    /// Certificate and key for serving the node protocol over TLS; plaintext when unset.
+    pub grpc_tls: Option<GrpcServerTls>,
+    /// Requests per second and burst allowed per node on the REST API; unlimited when unset.
+    pub node_rate_limit: Option<RateLimit>,
+    /// Requests per second and burst allowed per client address on the REST API; unlimited when unset.
+    pub client_rate_limit: Option<RateLimit>,
+    /// Requests allowed to wait for node replies at once, overall and per node.
+    pub pending_limit: PendingLimit,
+    /// WebSocket connections allowed open at once, overall and per credential.
+    pub ws_limit: WsLimit,
+    /// How long container status responses are cached and then served stale.
+    pub response_cache: ResponseCacheConfig,
+    /// Require a tenant API key on every request and confine it to the tenant's nodes.
+    pub multi_tenant: bool,
+    /// Base64 32-byte key encrypting stored secrets.
+    pub secrets_key: Option<String>,
+    /// File holding the secrets key when `secrets_key` is unset; outside the state directory.
+    pub secrets_key_file: Option<PathBuf>,
+    /// Git repository holding the desired containers; GitOps is off when unset.
+    pub gitops: Option<GitOpsConfig>,
+    /// Seconds between desired-state reconcile passes; 30 when unset.
+    pub reconcile_interval: Option<u64>,
+    /// Daily container reports with their delivery targets; off when unset.
+    pub reports: Option<ReportConfig>,
+    /// Refuse `node_id`/`password` query parameters, so clients must use bearer tokens.
+    pub disable_query_auth: bool,
+    /// Leave `Secure` off the browser session cookies, for plain-HTTP deployments.
+    pub insecure_session_cookies: bool,
+    /// Bind the listeners with `SO_REUSEPORT`, so a new coordinator can start on
+    /// the same ports and take over after `SIGUSR2`.
+    pub reuse_port: bool,
+    /// Seconds a handed-over coordinator waits for in-flight requests; 30 when unset.
+    pub drain_timeout: Option<u64>,
+    /// HTTP access log, apart from the application logs; off when unset.
+    pub access_log: Option<AccessLogConfig>,
+    /// Nodes whose destructive actions wait for a second credential's approval; off when unset.
+    pub approval_policy: Option<ApprovalPolicy>,
+    /// Cancel to shut down: the listeners close, WebSocket clients get a close
+    /// frame, in-flight requests drain for up to `drain_timeout` and nodes are
+    /// told before their streams close. Never cancelled by default.
+    pub shutdown: CancellationToken,
+}
 
 pub async fn run(
     grpc_coordinator_addr: &str,
     api_addr: &str,
+    options: CoordinatorOptions,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    run_with_ready_callback(grpc_coordinator_addr, api_addr, || {}).await
+    run_with_ready_callback(grpc_coordinator_addr, api_addr, options, || {}).await
 }
 
 pub async fn run_with_ready_callback<F>(
     grpc_coordinator_addr: &str,
     api_addr: &str,
+    options: CoordinatorOptions,
     ready_callback: F,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
 where
@@ -27,15 +129,72 @@ where
     let grpc_coordinator_addr = grpc_coordinator_addr.parse()?;
     let api_addr: SocketAddr = api_addr.parse()?;
 
-    let (server_cmd_tx, _) = broadcast::channel(2048);
-
-    let clients: Arc<DashMap<(String, String), broadcast::Sender<Envelope>>> =
-        Arc::new(DashMap::new());
+    let clients: NodeChannels = Arc::new(DashMap::new());
+    let server_cmd_tx = CommandRouter::new(clients.clone());
 
     let pending: PendingResponses = Arc::new(DashMap::new());
+    let pending_streams: PendingStreams = Arc::new(DashMap::new());
+    let node_states = NodeStateCache::new();
+    spawn_pending_sweeper(
+        pending.clone(),
+        pending_streams.clone(),
+        node_states.expired_requests().clone(),
+    );
 
-    let coordinator_service =
-        CoordinatorServiceImpl::new(clients.clone(), server_cmd_tx.clone(), pending.clone());
+    if let Some(state_dir) = &options.state_dir {
+        std::fs::create_dir_all(state_dir)?;
+        info!("Persisting coordinator state in {:?}", state_dir);
+    }
+    let annotations = AnnotationStore::open(options.state_dir.as_deref());
+    let autostart = AutostartStore::open(options.state_dir.as_deref());
+    let secrets = SecretStore::open(
+        options.state_dir.as_deref(),
+        options.secrets_key.as_deref(),
+        options.secrets_key_file.as_deref(),
+    )?;
+    let templates = TemplateStore::open(options.state_dir.as_deref());
+    let desired_state = DesiredStateStore::open(options.state_dir.as_deref());
+    let favorites = FavoritesStore::open(options.state_dir.as_deref());
+    let log_forwarding = LogForwardingStore::open(options.state_dir.as_deref());
+    let cleanup = CleanupStore::open(options.state_dir.as_deref());
+    let trash = TrashStore::open(options.state_dir.as_deref());
+    let log_export = LogExportCursors::open(options.state_dir.as_deref());
+    let event_log = EventLog::open(options.state_dir.as_deref());
+    let recorder = SessionRecorder::open(options.state_dir.as_deref(), options.record_sessions);
+    let tenants = TenantStore::open(options.state_dir.as_deref(), options.multi_tenant);
+    let freeze_windows = FreezeStore::open(options.state_dir.as_deref());
+    let node_groups = NodeGroupStore::open(options.state_dir.as_deref());
+    if options.multi_tenant {
+        info!("Multi-tenant mode: requests need a tenant API key");
+    }
+    let admin_token = AdminToken::new(options.admin_token);
+    let tenant_usage = TenantUsage::new();
+    let authorizer: SharedAuthorizer = match &options.authz_webhook {
+        Some(url) => {
+            info!("Authorizing node commands with {}", url);
+            Arc::new(WebhookAuthorizer::new(url, options.authz_fail_open)?)
+        }
+        None => Arc::new(AllowAll),
+    };
+
+    let mut exporters = options.exporters;
+    if let Some(url) = &options.health_webhook {
+        exporters.register(WebhookExporter::new(url)?.with_actions(["health_status"]));
+    }
+    exporters.spawn(&event_log, &node_states);
+
+    let coordinator_service = CoordinatorServiceImpl::new(
+        clients.clone(),
+        pending.clone(),
+        pending_streams.clone(),
+        node_states.clone(),
+        event_log.clone(),
+        authorizer,
+    )
+    .with_autostart(autostart.clone())
+    .with_log_forwarding(log_forwarding.clone())
+    .with_cleanup(cleanup.clone())
+    .with_trash(trash.clone());
 
     info!(
         "gRPC Conversation server listening on {}",
@@ -43,24 +202,245 @@ where
     );
     info!("HTTP (WS+REST) server listening on {}", api_addr);
 
-    let ws_router = build_ws_router(server_cmd_tx.clone(), clients.clone(), pending.clone());
-    let rest_router = build_rest_router(server_cmd_tx.clone(), pending.clone());
-    let app = Router::new().merge(ws_router).merge(rest_router);
+    let desired_state = DesiredStateReconciler::new(
+        desired_state,
+        server_cmd_tx.clone(),
+        pending.clone(),
+        clients.clone(),
+        node_states.clone(),
+        secrets.clone(),
+        event_log.clone(),
+        std::time::Duration::from_secs(options.reconcile_interval.unwrap_or(30).max(1)),
+    );
+    desired_state.spawn();
+
+    let gitops = options.gitops.map(|config| {
+        let reconciler = GitOpsReconciler::new(
+            GitOps::new(config, options.state_dir.as_deref()),
+            server_cmd_tx.clone(),
+            pending.clone(),
+            clients.clone(),
+            node_states.clone(),
+            secrets.clone(),
+        );
+        reconciler.spawn();
+        reconciler
+    });
 
-    let http_handle = tokio::spawn(async move {
-        let listener = tokio::net::TcpListener::bind(api_addr).await?;
-        ready_callback();
+    let reports = match options.reports {
+        Some(config) => {
+            let scheduler = ReportScheduler::new(
+                config,
+                ReportStore::open(options.state_dir.as_deref()),
+                ResourcePeaks::open(options.state_dir.as_deref()),
+                event_log.clone(),
+                node_states.clone(),
+                server_cmd_tx.clone(),
+                pending.clone(),
+                clients.clone(),
+            )?;
+            scheduler.spawn();
+            Some(scheduler)
+        }
+        None => None,
+    };
 
-        axum::serve(listener, app.into_make_service()).await?;
+    let ws_router = build_ws_router(
+        server_cmd_tx.clone(),
+        clients.clone(),
+        pending.clone(),
+        pending_streams.clone(),
+        node_states.clone(),
+        recorder.clone(),
+        options.shutdown.clone(),
+    );
+    let ws_limiter = WsLimiter::new(options.ws_limit, node_states.ws_connections().clone());
+    let tokens = TokenStore::new();
+    if options.disable_query_auth {
+        info!("Query credentials disabled: requests need a bearer token");
+    }
+    let sessions = SessionAuth::new(
+        SessionStore::new(),
+        clients.clone(),
+        !options.insecure_session_cookies,
+    );
+    let rest_router = build_rest_router(RestContext {
+        server_cmd_tx: server_cmd_tx.clone(),
+        pending: pending.clone(),
+        pending_streams: pending_streams.clone(),
+        node_states: node_states.clone(),
+        admin_token: admin_token.clone(),
+        nodes: clients.clone(),
+        annotations,
+        autostart,
+        secrets,
+        templates,
+        desired_state,
+        favorites,
+        log_forwarding,
+        cleanup,
+        trash,
+        log_export,
+        event_log,
+        recorder,
+        tenants: tenants.clone(),
+        tenant_usage: tenant_usage.clone(),
+        freeze_windows,
+        node_groups,
+        tokens: tokens.clone(),
+        gitops,
+        reports,
+        node_rate_limit: options.node_rate_limit,
+        client_rate_limit: options.client_rate_limit,
+        pending_limit: options.pending_limit,
+        ws_limiter: ws_limiter.clone(),
+        response_cache: options.response_cache,
+        approval_policy: options.approval_policy,
+    });
+    let mut app = Router::new()
+        .merge(ws_router)
+        .merge(rest_router)
+        // Inside the tenant checks, so sockets they refuse take no slot
+        .layer(middleware::from_fn_with_state(
+            ws_limiter,
+            limit_ws_connections,
+        ))
+        // Inside the credential layers, so the actor is the resolved credential
+        .layer(middleware::from_fn(trace_requests))
+        .layer(middleware::from_fn_with_state(
+            TenantAccess::new(tenants, admin_token, tenant_usage),
+            enforce_tenant_access,
+        ))
+        // Before the tenant checks, so they see the session's node
+        .layer(middleware::from_fn_with_state(
+            sessions.clone(),
+            resolve_session_cookie,
+        ))
+        // Before the session cookie, so an explicit token wins
+        .layer(middleware::from_fn_with_state(
+            BearerAuth::new(tokens, !options.disable_query_auth),
+            resolve_bearer_token,
+        ))
+        // Scrape endpoints, sign-in and the like, outside the API's credentials
+        .merge(exporters.routes())
+        .merge(session_routes(sessions))
+        .merge(openapi_routes());
+    if let Some(config) = options.access_log {
+        // Outermost, so refused requests are logged too
+        app = app.layer(middleware::from_fn_with_state(
+            AccessLog::new(config)?,
+            log_access,
+        ));
+    }
+
+    let api_listener = handover::bind(api_addr, options.reuse_port)?;
+    let grpc_listener = handover::bind(grpc_coordinator_addr, options.reuse_port)?;
+    if options.reuse_port {
+        info!("Listening with SO_REUSEPORT: send SIGUSR2 to hand over to a new coordinator");
+    }
+    let handover = handover::watch_handover();
+    ready_callback();
+
+    // After a handover or on shutdown the listeners close at once, so a new
+    // coordinator gets every new connection, and requests in flight here may finish
+    let shutdown = options.shutdown;
+    let stop_accepting = handover::stopping(handover.clone(), shutdown.clone());
+    let mut http_handle = tokio::spawn(async move {
+        axum::serve(
+            api_listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(stop_accepting)
+        .await?;
         Ok(()) as Result<(), Box<dyn std::error::Error + Send + Sync>>
     });
 
-    let grpc_handle =
-        tokio::spawn(
-            async move { run_grpc_server(coordinator_service, grpc_coordinator_addr).await },
-        );
+    let max_message_size = options
+        .max_message_size
+        .unwrap_or(proto::DEFAULT_MAX_MESSAGE_SIZE);
+    let grpc_tls = options.grpc_tls;
+    let stop_accepting = handover::stopping(handover.clone(), shutdown.clone());
+    let mut grpc_handle = tokio::spawn(async move {
+        run_grpc_server(
+            coordinator_service,
+            grpc_listener,
+            max_message_size,
+            grpc_tls,
+            stop_accepting,
+        )
+        .await
+    });
 
-    let _ = tokio::try_join!(grpc_handle, http_handle)?;
+    // Node streams never end on their own: the nodes are told to close them
+    // once requests have drained, and reconnect to the next coordinator
+    let drain_timeout =
+        Duration::from_secs(options.drain_timeout.unwrap_or(DEFAULT_DRAIN_TIMEOUT_SECS));
+    let drain_deadline = async {
+        handover::stopping(handover, shutdown.clone()).await;
+        tokio::time::sleep(drain_timeout).await;
+    };
+    tokio::select! {
+        result = &mut http_handle => {
+            result??;
+            info!("Requests drained, closing node streams");
+        }
+        result = &mut grpc_handle => result??,
+        () = drain_deadline => {
+            warn!(
+                "Drain timed out after {} s, closing remaining connections",
+                drain_timeout.as_secs()
+            );
+        }
+    }
+    let unanswered = pending.len() + pending_streams.len();
+    if unanswered > 0 {
+        warn!("Dropping {} requests still waiting for nodes", unanswered);
+    }
+    let reason = if shutdown.is_cancelled() {
+        "shutdown"
+    } else {
+        "handover"
+    };
+    close_node_streams(&server_cmd_tx, &clients, reason).await;
 
     Ok(())
 }
+
+/// Tells every connected node the coordinator is going away and waits a
+/// little for them to end their streams, so none takes it for a network
+/// failure.
+async fn close_node_streams(server_cmd_tx: &CommandRouter, nodes: &NodeChannels, reason: &str) {
+    let connected: Vec<(String, String)> = nodes
+        .iter()
+        .map(|node| (node.key().clone(), node.internal_token().to_string()))
+        .collect();
+    if connected.is_empty() {
+        return;
+    }
+    info!(
+        "Asking {} nodes to disconnect ({})",
+        connected.len(),
+        reason
+    );
+    for (id, password) in connected {
+        let envelope = Envelope {
+            payload: Some(Payload::ServerCommand(ServerCommand {
+                kind: Some(server_command::Kind::CoordinatorShutdown(
+                    CoordinatorShutdown {
+                        reason: reason.to_string(),
+                    },
+                )),
+            })),
+            ..Default::default()
+        };
+        let _ = server_cmd_tx.send(ServerRequestByUser::new(id, password, envelope));
+    }
+
+    let deadline = Instant::now() + NODE_CLOSE_TIMEOUT;
+    while !nodes.is_empty() && Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    if !nodes.is_empty() {
+        warn!("{} nodes did not disconnect in time", nodes.len());
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::error::Error;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use bollard::query_parameters::{
+    DownloadFromContainerOptionsBuilder, UploadToContainerOptionsBuilder,
+};
+use bytes::Bytes;
+use futures_util::stream::{self, Stream, TryStreamExt};
+use proto::generated::{ContainerAction, GetContainerArchive, PutContainerArchive};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::info;
+
+use crate::docker_client::docker;
+
+// Chunks waiting for Docker per upload; the coordinator sends one at a time anyway
+const UPLOAD_CHANNEL_CAPACITY: usize = 4;
+// An upload whose next chunk does not arrive in time is abandoned
+const UPLOAD_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+struct Upload {
+    chunks: mpsc::Sender<Bytes>,
+    task: JoinHandle<Result<(), bollard::errors::Error>>,
+    last_chunk: Instant,
+}
+
+// Uploads in progress by upload id. Chunks arrive as separate requests, each
+// handled in its own task, so the upload outlives them.
+static UPLOADS: LazyLock<Mutex<HashMap<String, Upload>>> = LazyLock::new(Default::default);
+
+/// Reads `path` (a file or directory) out of a container as a tar archive,
+/// sending it on in the chunks Docker produces. Used for GET /api/containers/:id/archive
+pub async fn get_container_archive(
+    request: &GetContainerArchive,
+    output_tx: mpsc::Sender<Bytes>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let docker = docker()?;
+    let options = DownloadFromContainerOptionsBuilder::default()
+        .path(&request.path)
+        .build();
+
+    // No overall timeout: large directories take as long as they take
+    let mut stream = docker.download_from_container(&request.container_id, Some(options));
+    while let Some(chunk) = stream.try_next().await? {
+        if output_tx.send(chunk).await.is_err() {
+            // Nobody is listening anymore, stop reading the archive
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Hands one chunk of a tar archive to the upload it belongs to, starting the
+/// upload with its first chunk. Returns once Docker took the chunk, or for the
+/// last one once Docker extracted the whole archive. Used for PUT /api/containers/:id/archive
+pub async fn put_container_archive(
+    request: PutContainerArchive,
+) -> Result<ContainerAction, Box<dyn Error + Send + Sync>> {
+    let chunks = {
+        let mut uploads = UPLOADS.lock().unwrap_or_else(|e| e.into_inner());
+        if !uploads.contains_key(&request.upload_id) {
+            // Uploads the coordinator gave up on have long stopped in Docker
+            uploads.retain(|_, upload| upload.last_chunk.elapsed() < UPLOAD_IDLE_TIMEOUT);
+        }
+        let upload = match uploads.entry(request.upload_id.clone()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                info!(
+                    "Uploading an archive to {}:{}",
+                    request.container_id, request.path
+                );
+                entry.insert(start_upload(&request.container_id, &request.path)?)
+            }
+        };
+        upload.last_chunk = Instant::now();
+        upload.chunks.clone()
+    };
+
+    // A failed send means Docker already stopped reading; its error is below
+    let sent = request.data.is_empty() || chunks.send(Bytes::from(request.data)).await.is_ok();
+    drop(chunks);
+    if sent && !request.done {
+        return Ok(ContainerAction {
+            request_key: None, // set by the handler
+            container_id: request.container_id,
+            action: "put_archive".to_string(),
+            message: "Chunk received".to_string(),
+        });
+    }
+
+    let upload = UPLOADS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(&request.upload_id)
+        .ok_or_else(|| format!("Upload {} is gone", request.upload_id))?;
+    // Closing the channel ends the archive for Docker
+    drop(upload.chunks);
+    upload.task.await??;
+    if !sent {
+        return Err("Docker stopped reading the archive".into());
+    }
+    Ok(ContainerAction {
+        request_key: None, // set by the handler
+        container_id: request.container_id,
+        action: "put_archive".to_string(),
+        message: "Archive extracted successfully".to_string(),
+    })
+}
+
+// Streams the chunks sent to the returned upload into the container
+fn start_upload(container_id: &str, path: &str) -> Result<Upload, Box<dyn Error + Send + Sync>> {
+    let docker = docker()?;
+    let options = UploadToContainerOptionsBuilder::default()
+        .path(path)
+        .build();
+    let (chunks, rx) = mpsc::channel::<Bytes>(UPLOAD_CHANNEL_CAPACITY);
+    let body = received_chunks(rx, UPLOAD_IDLE_TIMEOUT);
+
+    let container_id = container_id.to_string();
+    let task = tokio::spawn(async move {
+        docker
+            .upload_to_container(&container_id, Some(options), bollard::body_try_stream(body))
+            .await
+    });
+    Ok(Upload {
+        chunks,
+        task,
+        last_chunk: Instant::now(),
+    })
+}
+
+/// Request body made of the chunks sent to `rx`, ending when the sender is
+/// dropped and failing when the next chunk takes longer than `idle_timeout`.
+pub(crate) fn received_chunks(
+    rx: mpsc::Receiver<Bytes>,
+    idle_timeout: Duration,
+) -> impl Stream<Item = Result<Bytes, std::io::Error>> + Send + 'static {
+    stream::unfold(Some(rx), move |rx| async move {
+        let mut rx = rx?;
+        match tokio::time::timeout(idle_timeout, rx.recv()).await {
+            Ok(Some(chunk)) => Some((Ok(chunk), Some(rx))),
+            Ok(None) => None,
+            Err(_) => Some((
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    "no data received in time",
+                )),
+                None,
+            )),
+        }
+    })
+}
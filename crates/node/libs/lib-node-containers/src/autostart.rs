@@ -0,0 +1,90 @@
+use std::error::Error;
+use std::time::Duration;
+
+use bollard::Docker;
+use bollard::query_parameters::InspectContainerOptionsBuilder;
+use proto::generated::{ApplyAutostart, AutostartReport, AutostartResult};
+use tracing::{info, warn};
+
+use crate::docker_client::docker;
+use crate::docker_limits::{INSPECT_TIMEOUT, limited};
+use crate::start_container;
+
+/// Starts the containers of the autostart list in order, waiting each entry's
+/// delay first. Containers already running are left alone. With
+/// `only_after_reboot` nothing happens unless the host booted since the last run.
+pub async fn apply_autostart(
+    request: &ApplyAutostart,
+) -> Result<AutostartReport, Box<dyn Error + Send + Sync>> {
+    let boot_time = host_boot_time().await.unwrap_or(0);
+    if request.only_after_reboot && boot_time != 0 && boot_time == request.last_boot_time {
+        info!("Host did not reboot since the last autostart run, skipping");
+        return Ok(AutostartReport {
+            request_key: None, // will be set by the handler
+            boot_time,
+            skipped: true,
+            results: Vec::new(),
+        });
+    }
+
+    let docker = docker()?;
+    let mut results = Vec::with_capacity(request.containers.len());
+    for entry in &request.containers {
+        if entry.delay_secs > 0 {
+            tokio::time::sleep(Duration::from_secs(entry.delay_secs.into())).await;
+        }
+        let result = start_if_stopped(&docker, &entry.container_id).await;
+        match &result {
+            Ok(outcome) => info!("Autostart {}: {}", entry.container_id, outcome),
+            Err(e) => warn!("Autostart {} failed: {}", entry.container_id, e),
+        }
+        results.push(match result {
+            Ok(outcome) => AutostartResult {
+                container_id: entry.container_id.clone(),
+                outcome: outcome.to_string(),
+                error: String::new(),
+            },
+            Err(e) => AutostartResult {
+                container_id: entry.container_id.clone(),
+                outcome: "failed".to_string(),
+                error: e.to_string(),
+            },
+        });
+    }
+
+    Ok(AutostartReport {
+        request_key: None, // will be set by the handler
+        boot_time,
+        skipped: false,
+        results,
+    })
+}
+
+async fn start_if_stopped(
+    docker: &Docker,
+    container_id: &str,
+) -> Result<&'static str, Box<dyn Error + Send + Sync>> {
+    let info = limited(
+        "inspect container",
+        INSPECT_TIMEOUT,
+        docker.inspect_container(
+            container_id,
+            Some(InspectContainerOptionsBuilder::default().build()),
+        ),
+    )
+    .await?;
+    if info.state.and_then(|state| state.running).unwrap_or(false) {
+        return Ok("already_running");
+    }
+    start_container(container_id).await?;
+    Ok("started")
+}
+
+/// Host boot time in unix seconds, from the `btime` line of `/proc/stat`
+/// (not namespaced, so also right inside a container).
+async fn host_boot_time() -> Option<i64> {
+    let stat = tokio::fs::read_to_string("/proc/stat").await.ok()?;
+    stat.lines()
+        .find_map(|line| line.strip_prefix("btime "))
+        .and_then(|value| value.trim().parse().ok())
+}
@@ -0,0 +1,58 @@
+use bollard::secret::SystemInfoCgroupDriverEnum;
+use proto::generated::NodeCapabilities;
+use tracing::{info, warn};
+
+use crate::docker_client::docker;
+use crate::docker_limits::{INSPECT_TIMEOUT, limited};
+
+/// What this node can do, from the Docker engine's info. Sent to the
+/// coordinator on connect so it can refuse what the node does not support.
+/// When Docker does not answer, the node's own platform is assumed.
+pub async fn detect_capabilities() -> NodeCapabilities {
+    let info = match docker() {
+        Ok(docker) => limited("docker info", INSPECT_TIMEOUT, docker.info()).await,
+        Err(e) => Err(e.into()),
+    };
+    let capabilities = match info {
+        Ok(info) => {
+            let os = info
+                .os_type
+                .filter(|os| !os.is_empty())
+                .unwrap_or_else(|| std::env::consts::OS.to_string());
+            NodeCapabilities {
+                arch: info
+                    .architecture
+                    .filter(|arch| !arch.is_empty())
+                    .unwrap_or_else(|| std::env::consts::ARCH.to_string()),
+                // Windows engines do not give exec sessions a terminal docklord can drive
+                exec_tty: os != "windows",
+                // Rootless engines on cgroup v1 run without cgroups and report no stats
+                stats: info.cgroup_driver != Some(SystemInfoCgroupDriverEnum::NONE),
+                checkpoint: os == "linux" && info.experimental_build.unwrap_or(false),
+                os,
+            }
+        }
+        Err(e) => {
+            warn!(
+                "Could not read Docker info, assuming default capabilities: {}",
+                e
+            );
+            NodeCapabilities {
+                os: std::env::consts::OS.to_string(),
+                arch: std::env::consts::ARCH.to_string(),
+                exec_tty: std::env::consts::OS != "windows",
+                stats: true,
+                checkpoint: false,
+            }
+        }
+    };
+    info!(
+        "Node platform {}/{}: exec tty {}, stats {}, checkpoint {}",
+        capabilities.os,
+        capabilities.arch,
+        capabilities.exec_tty,
+        capabilities.stats,
+        capabilities.checkpoint
+    );
+    capabilities
+}
@@ -0,0 +1,264 @@
+use std::cmp::Reverse;
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bollard::query_parameters::{InspectContainerOptionsBuilder, ListContainersOptionsBuilder};
+use proto::generated::{CleanupRule, CleanupStatus, CleanupSweep, RemovedContainer};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::docker_client::docker;
+use crate::docker_limits::{INSPECT_TIMEOUT, LIST_TIMEOUT, limited};
+use crate::trash::is_trashed;
+use crate::{delete_container, epoch_secs};
+
+const MIN_INTERVAL: Duration = Duration::from_secs(60);
+// Sweeps that removed something or failed, kept for the status
+const MAX_SWEEPS: usize = 20;
+const COMPOSE_PROJECT_LABEL: &str = "com.docker.compose.project";
+const COMPOSE_SERVICE_LABEL: &str = "com.docker.compose.service";
+
+static CLEANUP: OnceLock<Mutex<Cleanup>> = OnceLock::new();
+
+#[derive(Default)]
+struct Cleanup {
+    rules: Vec<CleanupRule>,
+    interval: Duration,
+    task: Option<JoinHandle<()>>,
+    last_sweep_at: i64,
+    sweeps: VecDeque<CleanupSweep>,
+}
+
+fn cleanup() -> MutexGuard<'static, Cleanup> {
+    CLEANUP
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// An exited container with what the rules look at.
+struct Exited {
+    id: String,
+    name: String,
+    /// Compose service, or image outside compose; `keep_last` counts per group.
+    group: String,
+    exited_at: i64,
+}
+
+/// Replaces the rules and restarts the sweeps, the first one right away. The
+/// same rules again keep the running sweeps, so a reconnect does not sweep.
+/// Used for the coordinator's /api/nodes/:id/cleanup
+pub fn configure_cleanup(
+    rules: Vec<CleanupRule>,
+    interval_secs: u32,
+) -> Result<CleanupStatus, Box<dyn Error + Send + Sync>> {
+    let interval = Duration::from_secs(interval_secs.into());
+    if !rules.is_empty() && interval < MIN_INTERVAL {
+        return Err(format!(
+            "cleanup interval must be at least {} s",
+            MIN_INTERVAL.as_secs()
+        )
+        .into());
+    }
+
+    let mut state = cleanup();
+    if state.rules == rules && state.interval == interval {
+        return Ok(status(&state));
+    }
+    if let Some(task) = state.task.take() {
+        task.abort();
+    }
+    if rules.is_empty() {
+        info!("Stopped container cleanup");
+    } else {
+        info!(
+            "Sweeping exited containers every {} s by {} rules",
+            interval.as_secs(),
+            rules.len()
+        );
+        state.task = Some(tokio::spawn(run_sweeps(rules.clone(), interval)));
+    }
+    state.rules = rules;
+    state.interval = interval;
+    Ok(status(&state))
+}
+
+/// The rules in force and recent sweeps.
+pub fn cleanup_status() -> CleanupStatus {
+    status(&cleanup())
+}
+
+fn status(state: &Cleanup) -> CleanupStatus {
+    CleanupStatus {
+        request_key: None, // will be set by the handler
+        rules: state.rules.iter().map(|rule| rule.name.clone()).collect(),
+        last_sweep_at: state.last_sweep_at,
+        sweeps: state.sweeps.iter().cloned().collect(),
+    }
+}
+
+async fn run_sweeps(rules: Vec<CleanupRule>, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        ticker.tick().await;
+        let sweep = sweep(&rules).await;
+
+        let mut state = cleanup();
+        state.last_sweep_at = sweep.swept_at;
+        if !sweep.removed.is_empty() || !sweep.errors.is_empty() {
+            state.sweeps.push_front(sweep);
+            state.sweeps.truncate(MAX_SWEEPS);
+        }
+    }
+}
+
+/// Removes the exited containers any rule selects.
+async fn sweep(rules: &[CleanupRule]) -> CleanupSweep {
+    let swept_at = now_secs();
+    let mut sweep = CleanupSweep {
+        swept_at,
+        ..Default::default()
+    };
+    let exited = match exited_containers(rules).await {
+        Ok(exited) => exited,
+        Err(e) => {
+            warn!("Container cleanup could not list containers: {}", e);
+            sweep.errors.push(format!("listing containers: {}", e));
+            return sweep;
+        }
+    };
+
+    let mut doomed: Vec<(&Exited, &str)> = Vec::new();
+    for rule in rules {
+        let min_age = i64::try_from(rule.older_than_secs).unwrap_or(i64::MAX);
+        let mut groups: HashMap<&str, Vec<&Exited>> = HashMap::new();
+        for container in exited.iter().filter(|c| matches(rule, &c.name)) {
+            groups
+                .entry(container.group.as_str())
+                .or_default()
+                .push(container);
+        }
+        for mut group in groups.into_values() {
+            group.sort_by_key(|container| Reverse(container.exited_at));
+            for container in group.into_iter().skip(rule.keep_last as usize) {
+                if swept_at - container.exited_at >= min_age
+                    && !doomed.iter().any(|(other, _)| other.id == container.id)
+                {
+                    doomed.push((container, &rule.name));
+                }
+            }
+        }
+    }
+
+    for (container, rule) in doomed {
+        match delete_container(&container.id).await {
+            Ok(_) => {
+                info!("Cleanup rule {} removed container {}", rule, container.name);
+                sweep.removed.push(RemovedContainer {
+                    container_id: container.id.clone(),
+                    name: container.name.clone(),
+                    rule: rule.to_string(),
+                    exited_at: container.exited_at,
+                });
+            }
+            Err(e) => {
+                warn!(
+                    "Cleanup rule {} could not remove container {}: {}",
+                    rule, container.name, e
+                );
+                sweep
+                    .errors
+                    .push(format!("removing {}: {}", container.name, e));
+            }
+        }
+    }
+    sweep
+}
+
+/// Exited and dead containers some rule matches by name. Containers whose exit
+/// time Docker does not know are left alone.
+async fn exited_containers(
+    rules: &[CleanupRule],
+) -> Result<Vec<Exited>, Box<dyn Error + Send + Sync>> {
+    let docker = docker()?;
+    let filters = HashMap::from([("status", vec!["exited", "dead"])]);
+    let containers = limited(
+        "list containers",
+        LIST_TIMEOUT,
+        docker.list_containers(Some(
+            ListContainersOptionsBuilder::default()
+                .all(true)
+                .filters(&filters)
+                .build(),
+        )),
+    )
+    .await?;
+
+    let mut exited = Vec::new();
+    for container in containers {
+        let (Some(id), Some(name)) = (
+            container.id,
+            container
+                .names
+                .and_then(|names| names.into_iter().next())
+                .map(|name| name.trim_start_matches('/').to_string()),
+        ) else {
+            continue;
+        };
+        // The trash removes its containers on its own schedule
+        if is_trashed(&name) || !rules.iter().any(|rule| matches(rule, &name)) {
+            continue;
+        }
+        let labels = container.labels.unwrap_or_default();
+        let group = match (
+            labels.get(COMPOSE_PROJECT_LABEL),
+            labels.get(COMPOSE_SERVICE_LABEL),
+        ) {
+            (Some(project), Some(service)) => format!("compose:{}/{}", project, service),
+            _ => format!("image:{}", container.image.unwrap_or_default()),
+        };
+
+        // The list has no exit time; a container removed meanwhile is skipped
+        let Ok(info) = limited(
+            "inspect container",
+            INSPECT_TIMEOUT,
+            docker.inspect_container(&id, Some(InspectContainerOptionsBuilder::default().build())),
+        )
+        .await
+        else {
+            continue;
+        };
+        let exited_at = epoch_secs(info.state.and_then(|state| state.finished_at).as_deref());
+        if exited_at == 0 {
+            continue;
+        }
+        exited.push(Exited {
+            id,
+            name,
+            group,
+            exited_at,
+        });
+    }
+    Ok(exited)
+}
+
+fn matches(rule: &CleanupRule, name: &str) -> bool {
+    rule.containers.is_empty()
+        || rule
+            .containers
+            .iter()
+            .any(|pattern| match pattern.strip_suffix('*') {
+                Some(prefix) => name.starts_with(prefix),
+                None => name == pattern,
+            })
+}
+
+fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
@@ -0,0 +1,241 @@
+use std::error::Error;
+use std::time::Duration;
+
+use bollard::query_parameters::{ListContainersOptionsBuilder, StatsOptionsBuilder};
+use bollard::secret::{ContainerCpuStats, ContainerStatsResponse};
+use futures_util::stream::TryStreamExt;
+use proto::generated::{ContainerStats, ContainerUsage};
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+use crate::docker_client::docker;
+use crate::docker_limits::{LIST_TIMEOUT, STATS_SAMPLE_TIMEOUT, limited};
+
+/// Raw cumulative counters from one Docker stats sample.
+#[derive(Default, Clone, Copy)]
+struct Counters {
+    rx_bytes: u64,
+    tx_bytes: u64,
+    rx_packets: u64,
+    tx_packets: u64,
+    block_read_bytes: u64,
+    block_write_bytes: u64,
+}
+
+impl Counters {
+    fn from_stats(stats: &ContainerStatsResponse) -> Self {
+        let mut counters = Counters::default();
+
+        for network in stats.networks.iter().flat_map(|n| n.values()) {
+            counters.rx_bytes += network.rx_bytes.unwrap_or(0);
+            counters.tx_bytes += network.tx_bytes.unwrap_or(0);
+            counters.rx_packets += network.rx_packets.unwrap_or(0);
+            counters.tx_packets += network.tx_packets.unwrap_or(0);
+        }
+
+        let entries = stats
+            .blkio_stats
+            .as_ref()
+            .and_then(|b| b.io_service_bytes_recursive.as_ref());
+        for entry in entries.into_iter().flatten() {
+            // cgroup v1 reports "Read"/"Write", v2 reports "read"/"write"
+            match entry.op.as_deref().map(str::to_ascii_lowercase).as_deref() {
+                Some("read") => counters.block_read_bytes += entry.value.unwrap_or(0),
+                Some("write") => counters.block_write_bytes += entry.value.unwrap_or(0),
+                _ => {}
+            }
+        }
+
+        counters
+    }
+}
+
+/// Memory in use, matching `docker stats`: reclaimable page cache is not counted.
+pub(crate) fn memory_usage(stats: &ContainerStatsResponse) -> u64 {
+    let Some(memory) = stats.memory_stats.as_ref() else {
+        return 0;
+    };
+    let inactive_file = memory
+        .stats
+        .as_ref()
+        .and_then(|s| {
+            s.get("inactive_file")
+                .or_else(|| s.get("total_inactive_file"))
+        })
+        .copied()
+        .unwrap_or(0);
+    memory.usage.unwrap_or(0).saturating_sub(inactive_file)
+}
+
+/// CPU usage in cores between `precpu_stats` and `cpu_stats`.
+/// Only meaningful for non one-shot samples, where Docker fills `precpu_stats`.
+pub(crate) fn cpu_cores(stats: &ContainerStatsResponse) -> f64 {
+    let (Some(cpu), Some(precpu)) = (stats.cpu_stats.as_ref(), stats.precpu_stats.as_ref()) else {
+        return 0.0;
+    };
+    let total = |c: &ContainerCpuStats| {
+        c.cpu_usage
+            .as_ref()
+            .and_then(|u| u.total_usage)
+            .unwrap_or(0)
+    };
+    let cpu_delta = total(cpu).saturating_sub(total(precpu)) as f64;
+    let system_delta = cpu
+        .system_cpu_usage
+        .unwrap_or(0)
+        .saturating_sub(precpu.system_cpu_usage.unwrap_or(0)) as f64;
+    let online_cpus = cpu.online_cpus.unwrap_or(1) as f64;
+    if system_delta > 0.0 {
+        cpu_delta / system_delta * online_cpus
+    } else {
+        0.0
+    }
+}
+
+fn per_sec(current: u64, previous: u64, elapsed: f64) -> f64 {
+    // Counters reset when the container restarts
+    current.saturating_sub(previous) as f64 / elapsed
+}
+
+/// Samples CPU, memory, network and block I/O and sends them with per-second rates
+/// computed against the previous sample. Stops after `max_samples` samples, when `duration` has
+/// elapsed, or when the receiver goes away. Used for /api/containers/:container_id/stats
+pub async fn stream_container_stats(
+    container_id: &str,
+    max_samples: Option<usize>,
+    duration: Duration,
+    output_tx: mpsc::Sender<ContainerStats>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let docker = docker()?;
+    let mut stream = docker.stats(
+        container_id,
+        Some(StatsOptionsBuilder::default().stream(true).build()),
+    );
+
+    let deadline = Instant::now() + duration;
+    let mut previous: Option<(Counters, Instant)> = None;
+    let mut sent = 0;
+
+    loop {
+        let stats = match tokio::time::timeout_at(deadline, stream.try_next()).await {
+            Ok(Ok(Some(stats))) => stats,
+            Ok(Ok(None)) | Err(_) => return Ok(()),
+            Ok(Err(e)) => return Err(e.into()),
+        };
+        let now = Instant::now();
+        let counters = Counters::from_stats(&stats);
+
+        // The first sample only serves as the baseline for rates
+        if let Some((prev, prev_at)) = previous {
+            let elapsed = now.duration_since(prev_at).as_secs_f64().max(0.001);
+            let sample = ContainerStats {
+                request_key: None, // will be set by the handler
+                container_id: container_id.to_string(),
+                rx_bytes: counters.rx_bytes,
+                tx_bytes: counters.tx_bytes,
+                rx_packets: counters.rx_packets,
+                tx_packets: counters.tx_packets,
+                block_read_bytes: counters.block_read_bytes,
+                block_write_bytes: counters.block_write_bytes,
+                rx_bytes_per_sec: per_sec(counters.rx_bytes, prev.rx_bytes, elapsed),
+                tx_bytes_per_sec: per_sec(counters.tx_bytes, prev.tx_bytes, elapsed),
+                rx_packets_per_sec: per_sec(counters.rx_packets, prev.rx_packets, elapsed),
+                tx_packets_per_sec: per_sec(counters.tx_packets, prev.tx_packets, elapsed),
+                block_read_bytes_per_sec: per_sec(
+                    counters.block_read_bytes,
+                    prev.block_read_bytes,
+                    elapsed,
+                ),
+                block_write_bytes_per_sec: per_sec(
+                    counters.block_write_bytes,
+                    prev.block_write_bytes,
+                    elapsed,
+                ),
+                done: false,
+                cpu_percent: cpu_cores(&stats) * 100.0,
+                online_cpus: stats
+                    .cpu_stats
+                    .as_ref()
+                    .and_then(|cpu| cpu.online_cpus)
+                    .unwrap_or(0),
+                memory_usage: memory_usage(&stats),
+                memory_limit: stats
+                    .memory_stats
+                    .as_ref()
+                    .and_then(|memory| memory.limit)
+                    .unwrap_or(0),
+            };
+
+            if output_tx.send(sample).await.is_err() {
+                return Ok(());
+            }
+            sent += 1;
+            if max_samples.is_some_and(|max| sent >= max) {
+                return Ok(());
+            }
+        }
+
+        previous = Some((counters, now));
+    }
+}
+
+/// Takes one stats sample of every running container (concurrently) and returns the
+/// `limit` heaviest by `by` ("cpu" or "memory"). Used for /api/nodes/:node_id/top-containers
+pub async fn get_top_containers(
+    by: &str,
+    limit: usize,
+) -> Result<Vec<ContainerUsage>, Box<dyn Error + Send + Sync>> {
+    let docker = docker()?;
+    let containers = limited(
+        "list containers",
+        LIST_TIMEOUT,
+        docker.list_containers(Some(ListContainersOptionsBuilder::default().build())),
+    )
+    .await?;
+
+    let samples = containers.into_iter().filter_map(|container| {
+        let id = container.id?;
+        let name = container
+            .names
+            .and_then(|names| names.first().map(|n| n.trim_start_matches('/').to_string()))
+            .unwrap_or_default();
+        let docker = docker.clone();
+        Some(async move {
+            // Not one-shot: Docker waits for a second sample so the CPU delta is filled
+            let mut stream = docker.stats(
+                &id,
+                Some(StatsOptionsBuilder::default().stream(false).build()),
+            );
+            // Each sample takes a slot, so large hosts are sampled a few at a time
+            let stats = limited("stats", STATS_SAMPLE_TIMEOUT, stream.try_next())
+                .await
+                .ok()
+                .flatten()?;
+            Some(ContainerUsage {
+                container_id: id.clone(),
+                name,
+                cpu_usage: cpu_cores(&stats),
+                memory_usage: memory_usage(&stats),
+                memory_limit: stats
+                    .memory_stats
+                    .as_ref()
+                    .and_then(|m| m.limit)
+                    .unwrap_or(0),
+            })
+        })
+    });
+
+    let mut usages: Vec<ContainerUsage> = futures_util::future::join_all(samples)
+        .await
+        .into_iter()
+        .flatten()
+        .collect();
+
+    match by {
+        "memory" => usages.sort_by_key(|u| std::cmp::Reverse(u.memory_usage)),
+        _ => usages.sort_by(|a, b| b.cpu_usage.total_cmp(&a.cpu_usage)),
+    }
+    usages.truncate(limit);
+
+    Ok(usages)
+}
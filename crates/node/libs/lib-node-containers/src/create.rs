@@ -0,0 +1,315 @@
+use std::collections::HashMap;
+use std::error::Error;
+
+use bollard::Docker;
+use bollard::models::{
+    ContainerCreateBody, ContainerInspectResponse, EndpointSettings, HostConfig, NetworkingConfig,
+    PortBinding, RestartPolicy, RestartPolicyNameEnum,
+};
+use bollard::query_parameters::{CreateContainerOptionsBuilder, InspectContainerOptionsBuilder};
+use proto::generated::{CloneContainer, ContainerCreated, CreateContainer, PortMapping};
+use tracing::{info, warn};
+
+use crate::docker_client::docker;
+use crate::docker_limits::{INSPECT_TIMEOUT, LIFECYCLE_TIMEOUT, limited};
+use crate::images;
+use crate::secrets::{self, SECRETS_LABEL};
+use crate::start_container;
+
+// Labels docker compose finds a project's containers by
+const COMPOSE_LABEL_PREFIX: &str = "com.docker.compose.";
+
+/// Creates a container from the request, pulling the image first when the
+/// node does not have it, and starts it if asked to. Secrets are materialized
+/// here and only here; their files are removed again if creation fails.
+pub async fn create_container(
+    request: &CreateContainer,
+) -> Result<ContainerCreated, Box<dyn Error + Send + Sync>> {
+    let docker = docker()?;
+    let pulled = pull_if_missing(&docker, &request.image).await?;
+
+    let prepared = secrets::prepare_secrets(&request.secrets).await?;
+    let options = (!request.name.is_empty()).then(|| {
+        CreateContainerOptionsBuilder::default()
+            .name(&request.name)
+            .build()
+    });
+    let created = async {
+        let config = container_config(request, &prepared)?;
+        limited(
+            "create container",
+            LIFECYCLE_TIMEOUT,
+            docker.create_container(options, config),
+        )
+        .await
+    };
+
+    let created = match created.await {
+        Ok(created) => created,
+        Err(e) => {
+            if let Some(label) = &prepared.label {
+                secrets::remove_secret_files(label).await;
+            }
+            return Err(e);
+        }
+    };
+    info!("Created container {} from {}", created.id, request.image);
+
+    let mut warnings = created.warnings;
+    let started = if request.start {
+        match start_container(&created.id).await {
+            Ok(_) => true,
+            Err(e) => {
+                warn!("Created container {} did not start: {}", created.id, e);
+                warnings.push(format!("start failed: {}", e));
+                false
+            }
+        }
+    } else {
+        false
+    };
+
+    Ok(ContainerCreated {
+        request_key: None, // will be set by the handler
+        container_id: created.id,
+        name: request.name.clone(),
+        started,
+        pulled,
+        warnings,
+    })
+}
+
+/// Creates a stopped container with the config, host config and networks of
+/// an existing one, so it can be poked at without touching the original. The
+/// copy runs the source's exact image id and leaves out the labels that tie the
+/// source to its compose project and secret files.
+pub async fn clone_container(
+    request: &CloneContainer,
+) -> Result<ContainerCreated, Box<dyn Error + Send + Sync>> {
+    let docker = docker()?;
+    let source = limited(
+        "inspect container",
+        INSPECT_TIMEOUT,
+        docker.inspect_container(
+            &request.container_id,
+            Some(InspectContainerOptionsBuilder::default().build()),
+        ),
+    )
+    .await?;
+    let config = clone_config(request, source)?;
+
+    let options = (!request.name.is_empty()).then(|| {
+        CreateContainerOptionsBuilder::default()
+            .name(&request.name)
+            .build()
+    });
+    let created = limited(
+        "create container",
+        LIFECYCLE_TIMEOUT,
+        docker.create_container(options, config),
+    )
+    .await?;
+    info!(
+        "Cloned container {} into {}",
+        request.container_id, created.id
+    );
+
+    Ok(ContainerCreated {
+        request_key: None, // will be set by the handler
+        container_id: created.id,
+        name: request.name.clone(),
+        started: false,
+        pulled: false,
+        warnings: created.warnings,
+    })
+}
+
+pub(crate) fn clone_config(
+    request: &CloneContainer,
+    source: ContainerInspectResponse,
+) -> Result<ContainerCreateBody, String> {
+    let source_id = source.id.unwrap_or_default();
+    let short_id = &source_id[..source_id.len().min(12)];
+    let config = source
+        .config
+        .ok_or_else(|| format!("container {} has no config", request.container_id))?;
+    let mut host_config = source.host_config.unwrap_or_default();
+
+    let mut env = config.env.unwrap_or_default();
+    for entry in &request.env {
+        let key = entry.split_once('=').map_or(entry.as_str(), |(key, _)| key);
+        match env
+            .iter_mut()
+            .find(|existing| existing.split_once('=').is_some_and(|(k, _)| k == key))
+        {
+            Some(existing) => existing.clone_from(entry),
+            None => env.push(entry.clone()),
+        }
+    }
+
+    let mut exposed_ports = config.exposed_ports.unwrap_or_default();
+    if request.replace_ports {
+        let (exposed, bindings) = published_ports(&request.ports);
+        exposed_ports.extend(exposed);
+        host_config.port_bindings = Some(bindings);
+    }
+
+    let labels: HashMap<String, String> = config
+        .labels
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(key, _)| key != SECRETS_LABEL && !key.starts_with(COMPOSE_LABEL_PREFIX))
+        .collect();
+
+    // Endpoints keep their aliases but not the source's addresses
+    let endpoints: HashMap<String, EndpointSettings> = source
+        .network_settings
+        .and_then(|settings| settings.networks)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(network, endpoint)| {
+            let aliases = endpoint.aliases.map(|aliases| {
+                aliases
+                    .into_iter()
+                    .filter(|alias| alias != short_id)
+                    .collect()
+            });
+            let settings = EndpointSettings {
+                aliases,
+                links: endpoint.links,
+                driver_opts: endpoint.driver_opts,
+                ..Default::default()
+            };
+            (network, settings)
+        })
+        .collect();
+
+    Ok(ContainerCreateBody {
+        // Docker sets the hostname to the short id unless one was given
+        hostname: config.hostname.filter(|hostname| hostname != short_id),
+        domainname: config.domainname,
+        user: config.user,
+        attach_stdin: config.attach_stdin,
+        attach_stdout: config.attach_stdout,
+        attach_stderr: config.attach_stderr,
+        exposed_ports: (!exposed_ports.is_empty()).then_some(exposed_ports),
+        tty: config.tty,
+        open_stdin: config.open_stdin,
+        stdin_once: config.stdin_once,
+        env: (!env.is_empty()).then_some(env),
+        cmd: config.cmd,
+        healthcheck: config.healthcheck,
+        args_escaped: config.args_escaped,
+        image: source.image.or(config.image),
+        volumes: config.volumes,
+        working_dir: config.working_dir,
+        entrypoint: config.entrypoint,
+        network_disabled: config.network_disabled,
+        on_build: config.on_build,
+        labels: (!labels.is_empty()).then_some(labels),
+        stop_signal: config.stop_signal,
+        stop_timeout: config.stop_timeout,
+        shell: config.shell,
+        host_config: Some(host_config),
+        networking_config: (!endpoints.is_empty()).then_some(NetworkingConfig {
+            endpoints_config: Some(endpoints),
+        }),
+        ..Default::default()
+    })
+}
+
+/// Pulls `image` unless it is already on the node. Returns whether it pulled.
+async fn pull_if_missing(
+    docker: &Docker,
+    image: &str,
+) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    if limited(
+        "inspect image",
+        INSPECT_TIMEOUT,
+        docker.inspect_image(image),
+    )
+    .await
+    .is_ok()
+    {
+        return Ok(false);
+    }
+
+    images::pull(docker, image, "").await?;
+    Ok(true)
+}
+
+fn container_config(
+    request: &CreateContainer,
+    secrets: &secrets::ContainerSecrets,
+) -> Result<ContainerCreateBody, String> {
+    let (exposed_ports, port_bindings) = published_ports(&request.ports);
+
+    let restart_policy = match request.restart_policy.as_str() {
+        "" => None,
+        "no" => Some(RestartPolicyNameEnum::NO),
+        "always" => Some(RestartPolicyNameEnum::ALWAYS),
+        "unless-stopped" => Some(RestartPolicyNameEnum::UNLESS_STOPPED),
+        "on-failure" => Some(RestartPolicyNameEnum::ON_FAILURE),
+        other => return Err(format!("unknown restart policy '{}'", other)),
+    };
+
+    let mut labels: HashMap<String, String> = request
+        .labels
+        .iter()
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    if let Some(label) = &secrets.label {
+        labels.insert(SECRETS_LABEL.to_string(), label.clone());
+    }
+
+    let env: Vec<String> = request.env.iter().chain(&secrets.env).cloned().collect();
+    let binds: Vec<String> = request
+        .volumes
+        .iter()
+        .chain(&secrets.binds)
+        .cloned()
+        .collect();
+
+    Ok(ContainerCreateBody {
+        image: Some(request.image.clone()),
+        env: (!env.is_empty()).then_some(env),
+        labels: (!labels.is_empty()).then_some(labels),
+        exposed_ports: (!exposed_ports.is_empty()).then_some(exposed_ports),
+        host_config: Some(HostConfig {
+            port_bindings: (!port_bindings.is_empty()).then_some(port_bindings),
+            binds: (!binds.is_empty()).then_some(binds),
+            restart_policy: restart_policy.map(|name| RestartPolicy {
+                name: Some(name),
+                maximum_retry_count: None,
+            }),
+            ..Default::default()
+        }),
+        ..Default::default()
+    })
+}
+
+type PortBindings = HashMap<String, Option<Vec<PortBinding>>>;
+
+/// Exposed ports and host bindings for `ports`, keyed `port/protocol`.
+fn published_ports(ports: &[PortMapping]) -> (HashMap<String, HashMap<(), ()>>, PortBindings) {
+    let mut exposed_ports = HashMap::new();
+    let mut port_bindings: PortBindings = HashMap::new();
+    for port in ports {
+        let protocol = if port.protocol.is_empty() {
+            "tcp"
+        } else {
+            port.protocol.as_str()
+        };
+        let key = format!("{}/{}", port.container_port, protocol);
+        exposed_ports.insert(key.clone(), HashMap::new());
+        port_bindings
+            .entry(key)
+            .or_default()
+            .get_or_insert_with(Vec::new)
+            .push(PortBinding {
+                host_ip: (!port.host_ip.is_empty()).then(|| port.host_ip.clone()),
+                host_port: (port.host_port != 0).then(|| port.host_port.to_string()),
+            });
+    }
+    (exposed_ports, port_bindings)
+}
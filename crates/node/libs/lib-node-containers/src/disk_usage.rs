@@ -0,0 +1,74 @@
+use std::error::Error;
+use std::time::Duration;
+
+use bollard::query_parameters::{ListContainersOptionsBuilder, ListImagesOptionsBuilder};
+use proto::generated::{ContainerDiskUsage, DiskUsage, ImageDiskUsage};
+
+use crate::docker_client::docker;
+use crate::docker_limits::{LIST_TIMEOUT, limited};
+
+// Docker walks every writable layer to size it, which takes a while on busy hosts
+const SIZED_LIST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Writable layer sizes of all containers and the sizes of the images they
+/// share. Used for /api/nodes/:id/disk-usage
+pub async fn get_disk_usage() -> Result<DiskUsage, Box<dyn Error + Send + Sync>> {
+    let docker = docker()?;
+    let containers = limited(
+        "list containers with sizes",
+        SIZED_LIST_TIMEOUT,
+        docker.list_containers(Some(
+            ListContainersOptionsBuilder::default()
+                .all(true)
+                .size(true)
+                .build(),
+        )),
+    )
+    .await?;
+    let images = limited(
+        "list images",
+        LIST_TIMEOUT,
+        docker.list_images(Some(
+            ListImagesOptionsBuilder::default()
+                .all(false)
+                .shared_size(true)
+                .build(),
+        )),
+    )
+    .await?;
+
+    let containers = containers
+        .into_iter()
+        .map(|container| ContainerDiskUsage {
+            container_id: container.id.unwrap_or_default(),
+            name: container
+                .names
+                .and_then(|names| names.into_iter().next())
+                .map(|name| name.trim_start_matches('/').to_string())
+                .unwrap_or_default(),
+            image: container.image.unwrap_or_default(),
+            image_id: container.image_id.unwrap_or_default(),
+            state: container
+                .state
+                .map(|state| state.to_string())
+                .unwrap_or_else(|| "unknown".to_string()),
+            writable_size: container.size_rw.unwrap_or(0),
+            root_fs_size: container.size_root_fs.unwrap_or(0),
+        })
+        .collect();
+    let images = images
+        .into_iter()
+        .map(|image| ImageDiskUsage {
+            id: image.id,
+            tags: image.repo_tags,
+            size: image.size,
+            shared_size: image.shared_size,
+        })
+        .collect();
+
+    Ok(DiskUsage {
+        request_key: None, // will be set by the handler
+        containers,
+        images,
+    })
+}
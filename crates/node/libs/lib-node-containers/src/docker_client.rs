@@ -0,0 +1,164 @@
+use std::error::Error;
+use std::io;
+use std::sync::{OnceLock, RwLock};
+use std::time::Duration;
+
+use bollard::Docker;
+use bollard::errors::Error as DockerError;
+use proto::generated::ErrorCode;
+use tracing::{info, warn};
+
+use crate::docker_endpoint::docker_endpoint;
+use crate::docker_limits::TimedOut;
+
+pub const DEFAULT_DOCKER_HEALTH_INTERVAL: Duration = Duration::from_secs(30);
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The node's one Docker client, shared by every handler. It connects on first
+/// use, and is replaced when Docker stops answering so the next call reconnects.
+pub struct DockerManager {
+    client: RwLock<Option<Docker>>,
+}
+
+static MANAGER: OnceLock<DockerManager> = OnceLock::new();
+
+impl DockerManager {
+    pub fn global() -> &'static DockerManager {
+        MANAGER.get_or_init(|| DockerManager {
+            client: RwLock::new(None),
+        })
+    }
+
+    /// The shared client, connecting it if there is none. Cloning a client is
+    /// cheap and reuses its connections.
+    pub fn client(&self) -> Result<Docker, DockerError> {
+        if let Some(docker) = &*self.client.read().unwrap_or_else(|e| e.into_inner()) {
+            return Ok(docker.clone());
+        }
+        let mut client = self.client.write().unwrap_or_else(|e| e.into_inner());
+        // Another caller may have connected while this one waited for the lock
+        if let Some(docker) = &*client {
+            return Ok(docker.clone());
+        }
+        let docker = docker_endpoint().connect()?;
+        *client = Some(docker.clone());
+        Ok(docker)
+    }
+
+    /// Drops the client so the next call connects afresh.
+    pub fn disconnect(&self) {
+        self.client
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+    }
+
+    /// Pings Docker, dropping the client when it does not answer.
+    pub async fn health_check(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let docker = self.client()?;
+        let result = match tokio::time::timeout(PING_TIMEOUT, docker.ping()).await {
+            Ok(Ok(_)) => return Ok(()),
+            Ok(Err(e)) => Err(e.into()),
+            Err(_) => Err(format!("no answer within {} s", PING_TIMEOUT.as_secs()).into()),
+        };
+        self.disconnect();
+        result
+    }
+}
+
+/// The shared Docker client.
+pub(crate) fn docker() -> Result<Docker, DockerError> {
+    DockerManager::global().client()
+}
+
+/// Drops the shared client when `error` means the connection to Docker broke,
+/// rather than Docker refusing the request.
+pub(crate) fn note_docker_error(error: &(dyn Error + Send + Sync + 'static)) {
+    if let Some(
+        DockerError::HyperLegacyError { .. }
+        | DockerError::IOError { .. }
+        | DockerError::SocketNotFoundError(_)
+        | DockerError::RequestTimeoutError,
+    ) = error.downcast_ref::<DockerError>()
+    {
+        DockerManager::global().disconnect();
+    }
+}
+
+/// What kind of failure `error` is, from the Docker error or timeout behind
+/// it. Errors the node raised itself, or that only kept Docker's message,
+/// are `Unspecified`.
+pub fn error_code(error: &(dyn Error + 'static)) -> ErrorCode {
+    let mut cause = Some(error);
+    while let Some(error) = cause {
+        if error.is::<TimedOut>() {
+            return ErrorCode::Timeout;
+        }
+        if let Some(error) = error.downcast_ref::<DockerError>() {
+            return match error {
+                DockerError::DockerResponseServerError { status_code, .. } => match status_code {
+                    404 => ErrorCode::NotFound,
+                    // 304 is Docker's answer to starting a running container and the like
+                    304 | 409 => ErrorCode::Conflict,
+                    401 | 403 => ErrorCode::PermissionDenied,
+                    503 => ErrorCode::DaemonUnavailable,
+                    _ => ErrorCode::Unspecified,
+                },
+                DockerError::RequestTimeoutError => ErrorCode::Timeout,
+                DockerError::HyperLegacyError { .. }
+                | DockerError::HyperResponseError { .. }
+                | DockerError::IOError { .. }
+                | DockerError::SocketNotFoundError(_) => {
+                    // The node may not be allowed on the Docker socket
+                    if io_error_kind(error) == Some(io::ErrorKind::PermissionDenied) {
+                        ErrorCode::PermissionDenied
+                    } else {
+                        ErrorCode::DaemonUnavailable
+                    }
+                }
+                _ => ErrorCode::Unspecified,
+            };
+        }
+        cause = error.source();
+    }
+    ErrorCode::Unspecified
+}
+
+fn io_error_kind(error: &(dyn Error + 'static)) -> Option<io::ErrorKind> {
+    let mut cause = Some(error);
+    while let Some(error) = cause {
+        if let Some(error) = error.downcast_ref::<io::Error>() {
+            return Some(error.kind());
+        }
+        if let Some(DockerError::IOError { err }) = error.downcast_ref::<DockerError>() {
+            return Some(err.kind());
+        }
+        cause = error.source();
+    }
+    None
+}
+
+/// Pings Docker every `interval` in the background, logging when it becomes
+/// unreachable and when it is back.
+pub fn spawn_docker_health_check(interval: Duration) {
+    tokio::spawn(async move {
+        let mut healthy = true;
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            match DockerManager::global().health_check().await {
+                Ok(()) if !healthy => {
+                    info!("Docker is reachable again");
+                    healthy = true;
+                }
+                Ok(()) => {}
+                Err(e) if healthy => {
+                    warn!("Docker is unreachable, reconnecting: {}", e);
+                    healthy = false;
+                }
+                Err(_) => {}
+            }
+        }
+    });
+}
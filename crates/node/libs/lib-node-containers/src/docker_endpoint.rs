@@ -0,0 +1,307 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, OnceLock};
+
+use bollard::errors::Error as DockerError;
+use bollard::{API_DEFAULT_VERSION, BollardRequest, Docker};
+use hyper::Response;
+use hyper::body::Incoming;
+use hyper::http::{HeaderValue, Uri, header};
+use hyper_util::rt::TokioIo;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+use tokio::process::Command;
+use tokio_rustls::TlsConnector;
+use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName};
+use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+use tracing::{debug, info, warn};
+
+/// Seconds a Docker request may take, as bollard's own defaults allow.
+const REQUEST_TIMEOUT_SECS: u64 = 120;
+const DEFAULT_TCP_PORT: u16 = 2375;
+const DEFAULT_TLS_PORT: u16 = 2376;
+
+static ENDPOINT: OnceLock<DockerEndpoint> = OnceLock::new();
+
+/// Sets how the node reaches Docker. Only the first call counts; without it
+/// Docker's local defaults are used.
+pub fn configure_docker_endpoint(endpoint: DockerEndpoint) {
+    info!("Docker endpoint: {}", endpoint);
+    if ENDPOINT.set(endpoint).is_err() {
+        warn!("Docker endpoint already configured, keeping the first one");
+    }
+}
+
+pub(crate) fn docker_endpoint() -> &'static DockerEndpoint {
+    ENDPOINT.get_or_init(DockerEndpoint::default)
+}
+
+/// Where the Docker API is served.
+#[derive(Debug, Clone, Default)]
+pub enum DockerEndpoint {
+    /// `DOCKER_HOST` when it is a `unix://` socket, `/var/run/docker.sock` otherwise.
+    #[default]
+    Local,
+    /// A Unix socket at this path, e.g. rootless Docker's.
+    Socket(PathBuf),
+    /// Plain HTTP to `host:port`.
+    Tcp(String),
+    /// HTTPS to `host:port`, verified against the given CA.
+    Tls { address: String, tls: DockerTls },
+    /// The remote Docker of `[user@]host`, through `ssh ... docker system dial-stdio`.
+    Ssh {
+        destination: String,
+        port: Option<u16>,
+    },
+}
+
+/// Certificates for a TLS-protected Docker daemon (`dockerd --tlsverify`).
+#[derive(Debug, Clone)]
+pub struct DockerTls {
+    ca: PathBuf,
+    /// Client certificate and key, for daemons that verify clients.
+    identity: Option<(PathBuf, PathBuf)>,
+}
+
+impl DockerTls {
+    pub fn new(ca: PathBuf, cert: Option<PathBuf>, key: Option<PathBuf>) -> Result<Self, String> {
+        let identity = match (cert, key) {
+            (Some(cert), Some(key)) => Some((cert, key)),
+            (None, None) => None,
+            _ => {
+                return Err(
+                    "a Docker TLS client certificate needs its key, and the other way round"
+                        .to_string(),
+                );
+            }
+        };
+        Ok(Self { ca, identity })
+    }
+
+    fn client_config(&self) -> io::Result<ClientConfig> {
+        let mut roots = RootCertStore::empty();
+        for cert in read_certs(&self.ca)? {
+            roots.add(cert).map_err(io::Error::other)?;
+        }
+        let builder = ClientConfig::builder().with_root_certificates(roots);
+        let Some((cert, key)) = &self.identity else {
+            return Ok(builder.with_no_client_auth());
+        };
+        let private_key = rustls_pemfile::private_key(&mut BufReader::new(File::open(key)?))?
+            .ok_or_else(|| io::Error::other(format!("no private key in {}", key.display())))?;
+        builder
+            .with_client_auth_cert(read_certs(cert)?, private_key)
+            .map_err(io::Error::other)
+    }
+}
+
+fn read_certs(path: &Path) -> io::Result<Vec<CertificateDer<'static>>> {
+    let certs = rustls_pemfile::certs(&mut BufReader::new(File::open(path)?))
+        .collect::<Result<Vec<_>, _>>()?;
+    if certs.is_empty() {
+        return Err(io::Error::other(format!(
+            "no certificate in {}",
+            path.display()
+        )));
+    }
+    Ok(certs)
+}
+
+impl DockerEndpoint {
+    /// Parses a `DOCKER_HOST`-style address: `unix:///path` (or just the path),
+    /// `tcp://host[:port]`, `https://host[:port]` or `ssh://[user@]host[:port]`.
+    /// With `tls` a `tcp://` address is served over HTTPS as well.
+    pub fn parse(host: &str, tls: Option<DockerTls>) -> Result<Self, String> {
+        let host = host.trim().trim_end_matches('/');
+        if let Some(path) = host
+            .strip_prefix("unix://")
+            .or_else(|| host.starts_with('/').then_some(host))
+        {
+            if tls.is_some() {
+                return Err("Docker TLS options do not apply to a Unix socket".to_string());
+            }
+            return Ok(Self::Socket(PathBuf::from(path)));
+        }
+        if let Some(destination) = host.strip_prefix("ssh://") {
+            if tls.is_some() {
+                return Err(
+                    "Docker TLS options do not apply to ssh://, the SSH connection is encrypted"
+                        .to_string(),
+                );
+            }
+            let (destination, port) = split_port(destination)?;
+            if destination.is_empty() {
+                return Err(format!("Docker host '{}' has no host name", host));
+            }
+            return Ok(Self::Ssh {
+                destination: destination.to_string(),
+                port,
+            });
+        }
+        if let Some(address) = host.strip_prefix("https://") {
+            let tls =
+                tls.ok_or_else(|| "an https:// Docker host needs --docker-tls-ca".to_string())?;
+            return Ok(Self::Tls {
+                address: with_port(address, DEFAULT_TLS_PORT)?,
+                tls,
+            });
+        }
+        let Some(address) = host
+            .strip_prefix("tcp://")
+            .or_else(|| host.strip_prefix("http://"))
+        else {
+            return Err(format!(
+                "Docker host '{}' must start with unix://, tcp://, https:// or ssh://",
+                host
+            ));
+        };
+        Ok(match tls {
+            Some(tls) => Self::Tls {
+                address: with_port(address, DEFAULT_TLS_PORT)?,
+                tls,
+            },
+            None => Self::Tcp(with_port(address, DEFAULT_TCP_PORT)?),
+        })
+    }
+
+    /// A client for this endpoint. TCP and SSH clients open a connection per
+    /// request, like bollard's own HTTP client does.
+    pub(crate) fn connect(&self) -> Result<Docker, DockerError> {
+        match self {
+            Self::Local => Docker::connect_with_local_defaults(),
+            Self::Socket(path) => Docker::connect_with_socket(
+                &path.to_string_lossy(),
+                REQUEST_TIMEOUT_SECS,
+                API_DEFAULT_VERSION,
+            ),
+            Self::Tcp(address) => Docker::connect_with_http(
+                &format!("tcp://{}", address),
+                REQUEST_TIMEOUT_SECS,
+                API_DEFAULT_VERSION,
+            ),
+            Self::Tls { address, tls } => connect_tls(address, tls),
+            Self::Ssh { destination, port } => connect_ssh(destination, *port),
+        }
+    }
+}
+
+impl fmt::Display for DockerEndpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Local => write!(f, "local default"),
+            Self::Socket(path) => write!(f, "unix://{}", path.display()),
+            Self::Tcp(address) => write!(f, "tcp://{}", address),
+            Self::Tls { address, .. } => write!(f, "https://{}", address),
+            Self::Ssh {
+                destination,
+                port: Some(port),
+            } => write!(f, "ssh://{}:{}", destination, port),
+            Self::Ssh { destination, .. } => write!(f, "ssh://{}", destination),
+        }
+    }
+}
+
+/// Splits a trailing `:port` off, leaving IPv6 literals like `[::1]` whole.
+fn split_port(address: &str) -> Result<(&str, Option<u16>), String> {
+    match address.rsplit_once(':') {
+        Some((host, port)) if !port.contains(']') => {
+            port.parse().map(|port| (host, Some(port))).map_err(|_| {
+                format!(
+                    "'{}' is not a valid port in Docker host '{}'",
+                    port, address
+                )
+            })
+        }
+        _ => Ok((address, None)),
+    }
+}
+
+fn with_port(address: &str, default_port: u16) -> Result<String, String> {
+    let (host, port) = split_port(address)?;
+    if host.is_empty() {
+        return Err(format!("Docker host '{}' has no host name", address));
+    }
+    Ok(format!("{}:{}", host, port.unwrap_or(default_port)))
+}
+
+fn connect_tls(address: &str, tls: &DockerTls) -> Result<Docker, DockerError> {
+    let connector = TlsConnector::from(Arc::new(tls.client_config()?));
+    let (host, _) = split_port(address).map_err(io::Error::other)?;
+    let server_name = ServerName::try_from(host.trim_matches(['[', ']']).to_string())
+        .map_err(io::Error::other)?;
+    let target = address.to_string();
+    Docker::connect_with_custom_transport(
+        move |request: BollardRequest| {
+            let connector = connector.clone();
+            let server_name = server_name.clone();
+            let target = target.clone();
+            async move {
+                let stream = TcpStream::connect(&target).await?;
+                let stream = connector.connect(server_name, stream).await?;
+                send(stream, request).await
+            }
+        },
+        Some(format!("https://{}", address)),
+        REQUEST_TIMEOUT_SECS,
+        API_DEFAULT_VERSION,
+    )
+}
+
+fn connect_ssh(destination: &str, port: Option<u16>) -> Result<Docker, DockerError> {
+    let destination = destination.to_string();
+    Docker::connect_with_custom_transport(
+        move |request: BollardRequest| {
+            let mut ssh = Command::new("ssh");
+            // No prompts: keys come from the agent or ~/.ssh, hosts from known_hosts
+            ssh.args(["-o", "BatchMode=yes"]);
+            if let Some(port) = port {
+                ssh.arg("-p").arg(port.to_string());
+            }
+            ssh.args(["--", &destination, "docker", "system", "dial-stdio"])
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped());
+            async move {
+                let mut child = ssh.spawn()?;
+                let (Some(stdout), Some(stdin)) = (child.stdout.take(), child.stdin.take()) else {
+                    return Err(io::Error::other("ssh has no stdio").into());
+                };
+                // ssh exits once the connection is dropped and its stdin closes
+                tokio::spawn(async move {
+                    let _ = child.wait().await;
+                });
+                send(tokio::io::join(stdout, stdin), request).await
+            }
+        },
+        // The remote daemon ignores the host, the docker CLI sends this one too
+        Some("http://docker"),
+        REQUEST_TIMEOUT_SECS,
+        API_DEFAULT_VERSION,
+    )
+}
+
+/// Sends one request over its own HTTP/1.1 connection. The connection stays
+/// up for streamed bodies and hijacked attach/exec streams.
+async fn send<S>(stream: S, mut request: BollardRequest) -> Result<Response<Incoming>, DockerError>
+where
+    S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    if let Some(authority) = request.uri().authority()
+        && let Ok(host) = HeaderValue::from_str(authority.as_str())
+    {
+        request.headers_mut().entry(header::HOST).or_insert(host);
+    }
+    if let Some(path) = request.uri().path_and_query().cloned() {
+        *request.uri_mut() = Uri::from(path);
+    }
+    let (mut sender, connection) =
+        hyper::client::conn::http1::handshake(TokioIo::new(stream)).await?;
+    tokio::spawn(async move {
+        if let Err(e) = connection.with_upgrades().await {
+            debug!("Docker connection closed: {}", e);
+        }
+    });
+    Ok(sender.send_request(request).await?)
+}
@@ -0,0 +1,115 @@
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use tokio::sync::Semaphore;
+
+use crate::docker_client::note_docker_error;
+
+pub const DEFAULT_MAX_CONCURRENT_DOCKER_CALLS: usize = 8;
+pub const DEFAULT_DOCKER_QUEUE_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Per-command limits for how long a call may run once it has a slot
+pub(crate) const LIST_TIMEOUT: Duration = Duration::from_secs(15);
+pub(crate) const INSPECT_TIMEOUT: Duration = Duration::from_secs(15);
+pub(crate) const STATS_SAMPLE_TIMEOUT: Duration = Duration::from_secs(15);
+// Docker waits up to 10 s for a graceful stop before killing
+pub(crate) const LIFECYCLE_TIMEOUT: Duration = Duration::from_secs(30);
+pub(crate) const LOGS_TIMEOUT: Duration = Duration::from_secs(60);
+pub(crate) const REGISTRY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How many Docker API calls the node runs at once, and how long a call waits
+/// in line for a free slot before it is refused.
+#[derive(Debug, Clone, Copy)]
+pub struct DockerLimits {
+    pub max_concurrent: usize,
+    pub queue_timeout: Duration,
+}
+
+impl Default for DockerLimits {
+    fn default() -> Self {
+        Self {
+            max_concurrent: DEFAULT_MAX_CONCURRENT_DOCKER_CALLS,
+            queue_timeout: DEFAULT_DOCKER_QUEUE_TIMEOUT,
+        }
+    }
+}
+
+struct Limiter {
+    // Tokio's semaphore is fair, so waiting calls are served in arrival order
+    slots: Semaphore,
+    queue_timeout: Duration,
+}
+
+impl Limiter {
+    fn new(limits: DockerLimits) -> Self {
+        Self {
+            slots: Semaphore::new(limits.max_concurrent.max(1)),
+            queue_timeout: limits.queue_timeout,
+        }
+    }
+}
+
+static LIMITER: OnceLock<Limiter> = OnceLock::new();
+
+/// Sets the node-wide limits. Only the first call counts, so call it before
+/// serving requests; without it the defaults apply.
+pub fn configure_docker_limits(limits: DockerLimits) {
+    if LIMITER.set(Limiter::new(limits)).is_err() {
+        tracing::warn!("Docker call limits already configured, keeping the first ones");
+    }
+}
+
+/// A Docker call that ran out of time, waiting for a slot or to finish.
+#[derive(Debug)]
+pub struct TimedOut(String);
+
+impl fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for TimedOut {}
+
+fn limiter() -> &'static Limiter {
+    LIMITER.get_or_init(|| Limiter::new(DockerLimits::default()))
+}
+
+/// Runs one Docker call within the node-wide concurrency limit: waits in line
+/// for a slot, then gives the call `timeout` to finish.
+pub(crate) async fn limited<T, E>(
+    command: &str,
+    timeout: Duration,
+    call: impl Future<Output = Result<T, E>>,
+) -> Result<T, Box<dyn Error + Send + Sync>>
+where
+    E: Into<Box<dyn Error + Send + Sync>>,
+{
+    let limiter = limiter();
+    let _slot = tokio::time::timeout(limiter.queue_timeout, limiter.slots.acquire())
+        .await
+        .map_err(|_| {
+            TimedOut(format!(
+                "Node is busy: {} waited {} s for one of its Docker call slots",
+                command,
+                limiter.queue_timeout.as_secs()
+            ))
+        })??;
+
+    match tokio::time::timeout(timeout, call).await {
+        Ok(result) => result.map_err(|e| {
+            let e = e.into();
+            note_docker_error(&*e);
+            e
+        }),
+        Err(_) => Err(TimedOut(format!(
+            "{} timed out after {} s",
+            command,
+            timeout.as_secs()
+        ))
+        .into()),
+    }
+}
@@ -0,0 +1,59 @@
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use tracing::warn;
+
+pub const DEFAULT_WATCH_ACTIONS: [&str; 5] = ["start", "stop", "die", "destroy", "create"];
+pub const DEFAULT_EVENT_DEBOUNCE: Duration = Duration::from_millis(500);
+pub const DEFAULT_MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+// A steady stream of events still refreshes the lists this often
+pub(crate) const MAX_REFRESH_DELAY: Duration = Duration::from_secs(5);
+// Full container states are pushed this often so the coordinator resyncs
+// even if it missed a delta
+pub(crate) const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Which Docker container actions make the node push fresh container lists,
+/// and how long it waits for the events to settle before doing so.
+#[derive(Debug, Clone)]
+pub struct EventWatchConfig {
+    /// Actions such as `start` or `health_status`; details after a `:` are ignored.
+    pub actions: Vec<String>,
+    /// Quiet time after the last event before the lists are pushed. Events in
+    /// between are coalesced into one push.
+    pub debounce: Duration,
+    /// Shortest time between two event-triggered list refreshes, however
+    /// many events arrive. Zero leaves refreshes to the debounce alone.
+    pub min_refresh_interval: Duration,
+}
+
+impl Default for EventWatchConfig {
+    fn default() -> Self {
+        Self {
+            actions: DEFAULT_WATCH_ACTIONS.map(String::from).to_vec(),
+            debounce: DEFAULT_EVENT_DEBOUNCE,
+            min_refresh_interval: DEFAULT_MIN_REFRESH_INTERVAL,
+        }
+    }
+}
+
+impl EventWatchConfig {
+    /// Whether `action` (as Docker reports it, e.g. `health_status: healthy`) is watched.
+    pub fn watches(&self, action: &str) -> bool {
+        let action = action.split(':').next().unwrap_or(action).trim();
+        self.actions.iter().any(|watched| watched == action)
+    }
+}
+
+static EVENT_WATCH: OnceLock<EventWatchConfig> = OnceLock::new();
+
+/// Sets the watched actions and debounce. Only the first call counts, so call
+/// it before the node connects; without it the defaults apply.
+pub fn configure_event_watch(config: EventWatchConfig) {
+    if EVENT_WATCH.set(config).is_err() {
+        warn!("Event watch already configured, keeping the first settings");
+    }
+}
+
+pub(crate) fn event_watch() -> &'static EventWatchConfig {
+    EVENT_WATCH.get_or_init(EventWatchConfig::default)
+}
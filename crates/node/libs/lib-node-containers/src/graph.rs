@@ -0,0 +1,90 @@
+use std::error::Error;
+
+use bollard::query_parameters::ListContainersOptionsBuilder;
+use proto::generated::{ContainerGraph, GraphContainer, GraphMount};
+
+use crate::docker_client::docker;
+use crate::docker_limits::{LIST_TIMEOUT, limited};
+
+const COMPOSE_PROJECT_LABEL: &str = "com.docker.compose.project";
+const COMPOSE_SERVICE_LABEL: &str = "com.docker.compose.service";
+// "db:service_started:false,cache:service_healthy:true" with Compose v2
+const COMPOSE_DEPENDS_ON_LABEL: &str = "com.docker.compose.depends_on";
+
+/// Every container with the networks it joined, its mounts and its Compose
+/// project, service and dependencies. Used for /api/nodes/:id/graph
+pub async fn get_container_graph() -> Result<ContainerGraph, Box<dyn Error + Send + Sync>> {
+    let docker = docker()?;
+    let containers = limited(
+        "list containers",
+        LIST_TIMEOUT,
+        docker.list_containers(Some(
+            ListContainersOptionsBuilder::default().all(true).build(),
+        )),
+    )
+    .await?;
+
+    let containers = containers
+        .into_iter()
+        .map(|container| {
+            let labels = container.labels.unwrap_or_default();
+            let label = |key: &str| labels.get(key).cloned().unwrap_or_default();
+            let mut networks: Vec<String> = container
+                .network_settings
+                .and_then(|settings| settings.networks)
+                .map(|networks| networks.into_keys().collect())
+                .unwrap_or_default();
+            networks.sort();
+            let depends_on = labels
+                .get(COMPOSE_DEPENDS_ON_LABEL)
+                .map(|value| {
+                    value
+                        .split(',')
+                        .filter_map(|dependency| dependency.split(':').next())
+                        .filter(|service| !service.is_empty())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            GraphContainer {
+                id: container.id.unwrap_or_default(),
+                name: container
+                    .names
+                    .and_then(|names| names.into_iter().next())
+                    .map(|name| name.trim_start_matches('/').to_string())
+                    .unwrap_or_default(),
+                image: container.image.unwrap_or_default(),
+                state: container
+                    .state
+                    .map(|state| state.to_string())
+                    .unwrap_or_else(|| "unknown".to_string()),
+                networks,
+                network_mode: container
+                    .host_config
+                    .and_then(|config| config.network_mode)
+                    .unwrap_or_default(),
+                mounts: container
+                    .mounts
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|mount| GraphMount {
+                        r#type: mount.typ.map(|typ| typ.to_string()).unwrap_or_default(),
+                        // Volumes are identified by name, binds by their host path
+                        source: mount.name.or(mount.source).unwrap_or_default(),
+                        destination: mount.destination.unwrap_or_default(),
+                        read_only: mount.rw == Some(false),
+                    })
+                    .collect(),
+                compose_project: label(COMPOSE_PROJECT_LABEL),
+                compose_service: label(COMPOSE_SERVICE_LABEL),
+                depends_on,
+            }
+        })
+        .collect();
+
+    Ok(ContainerGraph {
+        request_key: None, // will be set by the handler
+        containers,
+    })
+}
@@ -0,0 +1,196 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::error::Error;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use proto::generated::{HostScriptResult, RunHostScript};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::Command;
+use tracing::{info, warn};
+
+pub const DEFAULT_HOST_SCRIPT_TIMEOUT: Duration = Duration::from_secs(300);
+// The coordinator waits a little longer than this for the result
+pub const MAX_HOST_SCRIPT_TIMEOUT: Duration = Duration::from_secs(600);
+// Each of stdout and stderr is cut to its last this many bytes
+const MAX_SCRIPT_OUTPUT: usize = 64 * 1024;
+const OUTPUT_READ_SIZE: usize = 8 * 1024;
+const MAX_SCRIPT_NAME_LEN: usize = 64;
+
+/// Host-side scripts the coordinator may run by name. Nothing else can be run:
+/// the node refuses unknown names and never passes arguments. Empty unless the
+/// operator registers scripts.
+#[derive(Debug, Clone)]
+pub struct HostScripts {
+    pub scripts: BTreeMap<String, PathBuf>,
+    /// How long a script may run before it is killed.
+    pub timeout: Duration,
+}
+
+impl Default for HostScripts {
+    fn default() -> Self {
+        Self {
+            scripts: BTreeMap::new(),
+            timeout: DEFAULT_HOST_SCRIPT_TIMEOUT,
+        }
+    }
+}
+
+impl HostScripts {
+    /// Registers a script from `name=/absolute/path`.
+    pub fn add(&mut self, spec: &str) -> Result<(), String> {
+        let (name, path) = spec
+            .split_once('=')
+            .ok_or_else(|| format!("host script '{}' must be NAME=PATH", spec))?;
+        let name = name.trim();
+        if name.is_empty()
+            || name.len() > MAX_SCRIPT_NAME_LEN
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-'))
+        {
+            return Err(format!(
+                "host script name '{}' must be 1-{} letters, digits, '_' or '-'",
+                name, MAX_SCRIPT_NAME_LEN
+            ));
+        }
+        let path = PathBuf::from(path.trim());
+        if !path.is_absolute() {
+            return Err(format!(
+                "host script '{}' needs an absolute path, got {}",
+                name,
+                path.display()
+            ));
+        }
+        if self.scripts.insert(name.to_string(), path).is_some() {
+            return Err(format!("host script '{}' is registered twice", name));
+        }
+        Ok(())
+    }
+}
+
+static HOST_SCRIPTS: OnceLock<HostScripts> = OnceLock::new();
+
+/// Sets the scripts the coordinator may run. Only the first call counts;
+/// without it no script can be run.
+pub fn configure_host_scripts(scripts: HostScripts) {
+    for (name, path) in &scripts.scripts {
+        info!("Host script {} registered: {}", name, path.display());
+    }
+    if HOST_SCRIPTS.set(scripts).is_err() {
+        warn!("Host scripts already configured, keeping the first ones");
+    }
+}
+
+fn host_scripts() -> &'static HostScripts {
+    HOST_SCRIPTS.get_or_init(HostScripts::default)
+}
+
+/// Runs the registered script and reports its exit code and output. The script
+/// is killed when it outlives the configured timeout or the request is
+/// cancelled. Used for POST /api/nodes/:id/scripts/:name
+pub async fn run_host_script(
+    request: &RunHostScript,
+) -> Result<HostScriptResult, Box<dyn Error + Send + Sync>> {
+    let config = host_scripts();
+    let Some(path) = config.scripts.get(&request.name) else {
+        warn!("Refused to run unregistered host script {}", request.name);
+        return Err(if config.scripts.is_empty() {
+            "no host scripts are registered on this node".into()
+        } else {
+            format!(
+                "host script '{}' is not registered (registered: {})",
+                request.name,
+                config
+                    .scripts
+                    .keys()
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+            .into()
+        });
+    };
+
+    info!("Running host script {} ({})", request.name, path.display());
+    let started = Instant::now();
+    let mut child = Command::new(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("failed to start {}: {}", path.display(), e))?;
+    let (stdout, stderr) = (child.stdout.take(), child.stderr.take());
+    // Both pipes are drained while the script runs, so a chatty script neither
+    // blocks on a full pipe nor grows the node's memory
+    let run = async {
+        let (stdout, stderr, status) =
+            tokio::join!(read_tail(stdout), read_tail(stderr), child.wait());
+        Ok::<_, std::io::Error>((stdout?, stderr?, status?))
+    };
+    let timeout = config.timeout.min(MAX_HOST_SCRIPT_TIMEOUT);
+    let (stdout, stderr, status) = tokio::time::timeout(timeout, run)
+        .await
+        .map_err(|_| format!("host script timed out after {} s", timeout.as_secs()))?
+        .map_err(|e| format!("failed to run {}: {}", path.display(), e))?;
+    let duration_ms = started.elapsed().as_millis() as u64;
+    let exit_code = status.code().unwrap_or(-1);
+    info!(
+        "Host script {} exited with {} after {} ms",
+        request.name, exit_code, duration_ms
+    );
+
+    let (stdout, stdout_cut) = stdout.into_text();
+    let (stderr, stderr_cut) = stderr.into_text();
+    Ok(HostScriptResult {
+        request_key: None, // will be set by the handler
+        name: request.name.clone(),
+        exit_code,
+        stdout,
+        stderr,
+        truncated: stdout_cut || stderr_cut,
+        duration_ms,
+    })
+}
+
+/// The last `MAX_SCRIPT_OUTPUT` bytes a script wrote to one of its pipes.
+#[derive(Default)]
+struct OutputTail {
+    bytes: VecDeque<u8>,
+    cut: bool,
+}
+
+impl OutputTail {
+    fn push(&mut self, data: &[u8]) {
+        self.bytes.extend(data);
+        let excess = self.bytes.len().saturating_sub(MAX_SCRIPT_OUTPUT);
+        if excess > 0 {
+            self.bytes.drain(..excess);
+            self.cut = true;
+        }
+    }
+
+    /// The kept bytes as text, and whether earlier ones were dropped.
+    fn into_text(self) -> (String, bool) {
+        let bytes = Vec::from(self.bytes);
+        (String::from_utf8_lossy(&bytes).into_owned(), self.cut)
+    }
+}
+
+/// Reads `pipe` to its end, keeping only its tail.
+async fn read_tail(pipe: Option<impl AsyncRead + Unpin>) -> std::io::Result<OutputTail> {
+    let mut tail = OutputTail::default();
+    let Some(mut pipe) = pipe else {
+        return Ok(tail);
+    };
+    let mut buf = [0; OUTPUT_READ_SIZE];
+    loop {
+        let read = pipe.read(&mut buf).await?;
+        if read == 0 {
+            return Ok(tail);
+        }
+        tail.push(&buf[..read]);
+    }
+}
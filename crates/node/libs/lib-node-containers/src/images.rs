@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::time::Duration;
+
+use bollard::Docker;
+use bollard::query_parameters::{
+    CreateImageOptionsBuilder, ListContainersOptionsBuilder, ListImagesOptionsBuilder,
+    PruneImagesOptionsBuilder, RemoveImageOptionsBuilder,
+};
+use bollard::secret::ImageDeleteResponseItem;
+use futures_util::stream::TryStreamExt;
+use proto::generated::{
+    ImageInfo, ImageList, ImagePulled, ImagesRemoved, ListImages, PruneImages, PullImage,
+    RemoveImage,
+};
+use tracing::info;
+
+use crate::docker_client::docker;
+use crate::docker_limits::{INSPECT_TIMEOUT, LIFECYCLE_TIMEOUT, LIST_TIMEOUT, limited};
+
+// Pulling a large image over a slow link takes a while
+const PULL_TIMEOUT: Duration = Duration::from_secs(600);
+// Docker deletes every unused layer before answering
+const PRUNE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Images on the node with how many containers use each. Used for GET /api/images
+pub async fn list_images(request: &ListImages) -> Result<ImageList, Box<dyn Error + Send + Sync>> {
+    let docker = docker()?;
+    let mut options = ListImagesOptionsBuilder::default().all(request.all);
+    if !request.reference.is_empty() {
+        options = options.filters(&HashMap::from([(
+            "reference",
+            vec![request.reference.as_str()],
+        )]));
+    }
+    let images = limited(
+        "list images",
+        LIST_TIMEOUT,
+        docker.list_images(Some(options.build())),
+    )
+    .await?;
+    // Docker reports -1 containers per image outside `system df`, so count them here
+    let containers = limited(
+        "list containers",
+        LIST_TIMEOUT,
+        docker.list_containers(Some(
+            ListContainersOptionsBuilder::default().all(true).build(),
+        )),
+    )
+    .await?;
+    let mut users: HashMap<String, i64> = HashMap::new();
+    for container in containers {
+        if let Some(image_id) = container.image_id {
+            *users.entry(image_id).or_default() += 1;
+        }
+    }
+
+    let mut images: Vec<ImageInfo> = images
+        .into_iter()
+        .map(|image| ImageInfo {
+            containers: users.get(&image.id).copied().unwrap_or(0),
+            id: image.id,
+            repo_tags: image
+                .repo_tags
+                .into_iter()
+                .filter(|tag| tag != "<none>:<none>")
+                .collect(),
+            repo_digests: image
+                .repo_digests
+                .into_iter()
+                .filter(|digest| digest != "<none>@<none>")
+                .collect(),
+            created: image.created,
+            size: image.size,
+        })
+        .collect();
+    images.sort_by_key(|image| std::cmp::Reverse(image.created));
+
+    Ok(ImageList {
+        request_key: None, // will be set by the handler
+        images,
+    })
+}
+
+/// Pulls the image (again, if the node has it) and reports whether the
+/// reference moved to a different image. Used for POST /api/images/pull
+pub async fn pull_image(request: &PullImage) -> Result<ImagePulled, Box<dyn Error + Send + Sync>> {
+    let docker = docker()?;
+    let before = limited(
+        "inspect image",
+        INSPECT_TIMEOUT,
+        docker.inspect_image(&request.image),
+    )
+    .await
+    .ok()
+    .and_then(|image| image.id);
+
+    pull(&docker, &request.image, &request.platform).await?;
+    let image = limited(
+        "inspect image",
+        INSPECT_TIMEOUT,
+        docker.inspect_image(&request.image),
+    )
+    .await?;
+    let id = image.id.unwrap_or_default();
+    info!("Pulled image {} ({})", request.image, id);
+
+    Ok(ImagePulled {
+        request_key: None, // will be set by the handler
+        image: request.image.clone(),
+        updated: before.as_deref() != Some(id.as_str()),
+        id,
+        repo_digests: image.repo_digests.unwrap_or_default(),
+    })
+}
+
+/// Pulls `image` for `platform` (the node's own when empty).
+pub(crate) async fn pull(
+    docker: &Docker,
+    image: &str,
+    platform: &str,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    // Without a tag Docker would pull every tag of the repository
+    let (name, tag) = match image.rsplit_once(':') {
+        _ if image.contains('@') => (image, None),
+        Some((name, tag)) if !tag.contains('/') => (name, Some(tag)),
+        _ => (image, Some("latest")),
+    };
+    let mut options = CreateImageOptionsBuilder::default().from_image(name);
+    if let Some(tag) = tag {
+        options = options.tag(tag);
+    }
+    if !platform.is_empty() {
+        options = options.platform(platform);
+    }
+    info!("Pulling image {}", image);
+    limited(
+        "pull image",
+        PULL_TIMEOUT,
+        docker
+            .create_image(Some(options.build()), None, None)
+            .try_collect::<Vec<_>>(),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Untags the image and deletes it once no tag is left. Used for DELETE /api/images/:id
+pub async fn remove_image(
+    request: &RemoveImage,
+) -> Result<ImagesRemoved, Box<dyn Error + Send + Sync>> {
+    let docker = docker()?;
+    let items = limited(
+        "remove image",
+        LIFECYCLE_TIMEOUT,
+        docker.remove_image(
+            &request.image,
+            Some(
+                RemoveImageOptionsBuilder::default()
+                    .force(request.force)
+                    .noprune(request.no_prune)
+                    .build(),
+            ),
+            None,
+        ),
+    )
+    .await?;
+    info!("Removed image {}", request.image);
+
+    Ok(removed(items, 0))
+}
+
+/// Deletes dangling images, or with `all` every image no container uses.
+/// Used for POST /api/images/prune
+pub async fn prune_images(
+    request: &PruneImages,
+) -> Result<ImagesRemoved, Box<dyn Error + Send + Sync>> {
+    let docker = docker()?;
+    let mut filters =
+        HashMap::from([("dangling", vec![if request.all { "false" } else { "true" }])]);
+    if !request.until.is_empty() {
+        filters.insert("until", vec![request.until.as_str()]);
+    }
+    let pruned = limited(
+        "prune images",
+        PRUNE_TIMEOUT,
+        docker.prune_images(Some(
+            PruneImagesOptionsBuilder::default()
+                .filters(&filters)
+                .build(),
+        )),
+    )
+    .await?;
+    let space_reclaimed = pruned.space_reclaimed.unwrap_or(0);
+    info!("Pruned images, reclaimed {} bytes", space_reclaimed);
+
+    Ok(removed(
+        pruned.images_deleted.unwrap_or_default(),
+        space_reclaimed,
+    ))
+}
+
+fn removed(items: Vec<ImageDeleteResponseItem>, space_reclaimed: i64) -> ImagesRemoved {
+    let mut result = ImagesRemoved {
+        request_key: None, // will be set by the handler
+        space_reclaimed,
+        ..Default::default()
+    };
+    for item in items {
+        result.untagged.extend(item.untagged);
+        result.deleted.extend(item.deleted);
+    }
+    result
+}
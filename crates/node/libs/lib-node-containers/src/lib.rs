@@ -2,68 +2,433 @@
 // The following code was written by an AI assistant (GPT-4) at the user's request.
 // It implements REST/gRPC handlers for container status, start/stop/delete, and logs with detailed options.
 
+pub mod archive;
+pub mod autostart;
+pub mod capabilities;
+pub mod cleanup;
+pub mod container_stats;
+pub mod create;
+pub mod disk_usage;
+pub mod docker_client;
+pub mod docker_endpoint;
+pub mod docker_limits;
+pub mod event_watch;
+pub mod graph;
+pub mod host_scripts;
+pub mod images;
+pub mod log_buffer;
+pub mod log_forwarding;
+mod log_page;
+pub mod migrate;
+pub mod name_filter;
+pub mod networks;
+pub mod node_metrics;
+pub mod secrets;
+pub mod session;
+pub mod system_disk;
+pub mod system_info;
+pub mod trash;
+
+pub use archive::{get_container_archive, put_container_archive};
+pub use autostart::apply_autostart;
+pub use capabilities::detect_capabilities;
+pub use cleanup::{cleanup_status, configure_cleanup};
+pub use container_stats::{get_top_containers, stream_container_stats};
+pub use create::{clone_container, create_container};
+pub use disk_usage::get_disk_usage;
+pub use docker_client::{
+    DEFAULT_DOCKER_HEALTH_INTERVAL, DockerManager, error_code, spawn_docker_health_check,
+};
+pub use docker_endpoint::{DockerEndpoint, DockerTls, configure_docker_endpoint};
+pub use docker_limits::{DockerLimits, configure_docker_limits};
+pub use event_watch::{EventWatchConfig, configure_event_watch};
+pub use graph::get_container_graph;
+pub use host_scripts::{HostScripts, configure_host_scripts, run_host_script};
+pub use images::{list_images, prune_images, pull_image, remove_image};
+pub use log_buffer::{LogBufferConfig, configure_log_buffer, spawn_log_buffers};
+pub use log_forwarding::{configure_log_forwarding, log_forwarding_status};
+pub use migrate::{ImportReply, export_container, import_container};
+pub use name_filter::NameFilter;
+pub use networks::{create_network, inspect_network, list_networks, remove_network};
+pub use node_metrics::get_node_metrics;
+pub use secrets::{ContainerSecrets, configure_secrets_dir, prepare_secrets};
+pub use session::{SessionControl, attach_container, exec_container};
+pub use system_disk::{get_system_disk_usage, prune_system};
+pub use system_info::get_system_info;
+pub use trash::{configure_trash, delete_or_trash_container, restore_container, trash_status};
+
 use bollard::query_parameters::{
-    EventsOptionsBuilder, ListContainersOptionsBuilder, LogsOptionsBuilder,
-    RemoveContainerOptionsBuilder, StartContainerOptionsBuilder, StopContainerOptionsBuilder,
+    BuildImageOptionsBuilder, EventsOptionsBuilder, ListContainersOptionsBuilder,
+    LogsOptionsBuilder, RemoveContainerOptionsBuilder, RestartContainerOptionsBuilder,
+    StartContainerOptionsBuilder, StatsOptionsBuilder, StopContainerOptionsBuilder,
 };
-use bollard::{Docker, secret::EventMessageTypeEnum};
-use chrono;
+use bollard::{
+    Docker,
+    container::LogOutput,
+    secret::{EventMessage, EventMessageTypeEnum},
+};
+use docker_client::docker;
+use docker_limits::{
+    INSPECT_TIMEOUT, LIFECYCLE_TIMEOUT, LIST_TIMEOUT, LOGS_TIMEOUT, REGISTRY_TIMEOUT,
+    STATS_SAMPLE_TIMEOUT, limited,
+};
+use event_watch::{MAX_REFRESH_DELAY, SNAPSHOT_INTERVAL, event_watch};
 use futures_util::stream::TryStreamExt;
+use log_page::LogPage;
 use proto::generated::request_key::RequestId;
+use proto::generated::{
+    BuildImage, ContainerDelta, ContainerEvent, FollowContainerLogs, GetContainerLogs,
+    ImageBuildOutput, LogLine, LogStreams, NodeContainersWithStatus, NodeResponse, RequestKey,
+    RequestType, node_response,
+};
 use proto::generated::{Envelope, envelope::Payload};
-use proto::generated::{NodeContainers, NodeResponse, RequestKey, RequestType, node_response};
+use std::collections::HashMap;
 use std::error::Error;
+use std::time::Duration;
 use tokio::sync::mpsc;
-use tracing::{error, info};
+use tokio::time::Instant;
+use tracing::{debug, error, info, warn};
+
+// Labels docklord sets on containers it manages; only these are reported in state pushes
+const DOCKLORD_LABEL_PREFIX: &str = "docklord.";
+
+// Wait before resubscribing to Docker events after the stream broke
+const EVENTS_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+// Lifecycle actions recorded in the coordinator's event history
+const HISTORY_ACTIONS: [&str; 11] = [
+    "create", "start", "restart", "stop", "die", "kill", "oom", "pause", "unpause", "rename",
+    "destroy",
+];
+
+// Image and volume actions recorded in the event history, by object type
+const OBJECT_ACTIONS: [(&str, &str); 4] = [
+    ("image", "pull"),
+    ("image", "delete"),
+    ("volume", "create"),
+    ("volume", "destroy"),
+];
 
 /// Watches for Docker container events and notifies the system about changes.
+/// When the event stream breaks it reconnects and resubscribes, until `tx` closes.
 pub async fn watch_container_changes(tx: mpsc::Sender<Envelope>) -> Result<(), Box<dyn Error>> {
-    let docker = Docker::connect_with_local_defaults()?;
+    // The first connection error is returned; later ones are retried
+    docker()?;
+    while !tx.is_closed() {
+        match watch_events(&tx).await {
+            Ok(()) => warn!(
+                "Docker event stream ended, resubscribing in {} s",
+                EVENTS_RETRY_DELAY.as_secs()
+            ),
+            Err(e) => {
+                docker_client::note_docker_error(&*e);
+                warn!(
+                    "Docker event stream broke, resubscribing in {} s: {}",
+                    EVENTS_RETRY_DELAY.as_secs(),
+                    e
+                );
+            }
+        }
+        tokio::time::sleep(EVENTS_RETRY_DELAY).await;
+    }
+    Ok(())
+}
+
+/// Follows Docker's event stream until it ends or fails.
+async fn watch_events(tx: &mpsc::Sender<Envelope>) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let docker = docker()?;
+
+    // Seed the coordinator's view of container states before the first event arrives
+    // (again after a reconnect, as events may have been missed in between)
+    let mut reported = send_container_states(tx).await;
+    let mut last_refresh = Instant::now();
+    let mut resync =
+        tokio::time::interval_at(Instant::now() + SNAPSHOT_INTERVAL, SNAPSHOT_INTERVAL);
+    resync.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    let config = event_watch();
+    // When the pending list refresh is due: after `debounce` without watched
+    // events, but no later than MAX_REFRESH_DELAY after the first one
+    let mut refresh_at: Option<(Instant, Instant)> = None;
+    let mut coalesced = 0usize;
+
     let mut events_stream = docker.events(Some(EventsOptionsBuilder::default().build()));
-    while let Ok(Some(event)) = events_stream.try_next().await {
-        if let Some(event_type) = event.typ {
-            if event_type == EventMessageTypeEnum::CONTAINER {
-                if let Some(action) = event.action {
-                    if ["start", "stop", "die", "destroy", "create"].contains(&action.as_str()) {
-                        info!(
-                            "Container state changed: {} -> {}",
-                            event.actor.unwrap_or_default().id.unwrap_or_default(),
-                            action
-                        );
-
-                        let containers = get_docker_containers().await.unwrap_or_default();
-
-                        let envelope = Envelope {
-                            payload: Some(Payload::NodeResponse(NodeResponse {
-                                kind: Some(node_response::Kind::NodeContainers(NodeContainers {
-                                    containers,
-                                    request_key: Some(RequestKey {
-                                        request_type: RequestType::UpdateContainerInfo as i32,
-                                        request_id: Some(RequestId::Unspecific(true)),
-                                    }),
-                                })),
-                            })),
-                        };
-                        if tx.send(envelope).await.is_err() {
-                            error!("Failed to send container change message");
-                        }
+    loop {
+        // Bursts of events (e.g. `docker compose up`) list the containers no
+        // more often than every `min_refresh_interval`
+        let due = refresh_at.map(|(quiet, first)| {
+            quiet
+                .min(first + MAX_REFRESH_DELAY.max(config.debounce))
+                .max(last_refresh + config.min_refresh_interval)
+        });
+        tokio::select! {
+            event = events_stream.try_next() => {
+                let Some(event) = event? else {
+                    break;
+                };
+                let object = match event.typ {
+                    Some(EventMessageTypeEnum::CONTAINER) => "container",
+                    Some(EventMessageTypeEnum::IMAGE) => "image",
+                    Some(EventMessageTypeEnum::VOLUME) => "volume",
+                    _ => continue,
+                };
+                let Some(action) = event.action.clone() else {
+                    continue;
+                };
+                if object != "container" {
+                    if OBJECT_ACTIONS.contains(&(object, action.as_str())) {
+                        send_container_event(tx, &event, object, &action).await;
                     }
+                    continue;
+                }
+
+                // Docker reports health only when it changes, so each event is a transition
+                if HISTORY_ACTIONS.contains(&action.as_str()) || action.starts_with("health_status:") {
+                    send_container_event(tx, &event, "container", &action).await;
+                }
+
+                if config.watches(&action) {
+                    debug!(
+                        "Container state changed: {} -> {}",
+                        event
+                            .actor
+                            .as_ref()
+                            .and_then(|actor| actor.id.clone())
+                            .unwrap_or_default(),
+                        action
+                    );
+                    let now = Instant::now();
+                    let first = refresh_at.map_or(now, |(_, first)| first);
+                    refresh_at = Some((now + config.debounce, first));
+                    coalesced += 1;
                 }
             }
+            _ = tokio::time::sleep_until(due.unwrap_or_else(Instant::now)), if due.is_some() => {
+                info!("Container state changed ({} events), pushing changed containers", coalesced);
+                refresh_at = None;
+                coalesced = 0;
+                reported = send_container_delta(tx, reported).await;
+                last_refresh = Instant::now();
+            }
+            _ = resync.tick() => {
+                reported = send_container_states(tx).await;
+                last_refresh = Instant::now();
+            }
         }
     }
+    // Events that arrived just before the stream ended still count
+    if refresh_at.is_some() {
+        send_container_delta(tx, reported).await;
+    }
     Ok(())
 }
 
+/// Pushes the containers added, removed or changed since `reported` was sent,
+/// or every state when nothing was reported yet. Returns what the coordinator
+/// now knows.
+async fn send_container_delta(
+    tx: &mpsc::Sender<Envelope>,
+    reported: Option<HashMap<String, proto::generated::ContainerStatus>>,
+) -> Option<HashMap<String, proto::generated::ContainerStatus>> {
+    let Some(mut reported) = reported else {
+        return send_container_states(tx).await;
+    };
+    let containers = match get_container_states().await {
+        Ok(containers) => containers,
+        Err(e) => {
+            error!("Failed to list container states: {}", e);
+            return Some(reported);
+        }
+    };
+
+    let mut delta = ContainerDelta {
+        request_key: Some(RequestKey {
+            request_type: RequestType::UpdateContainerInfo as i32,
+            request_id: Some(RequestId::Unspecific(true)),
+        }),
+        ..Default::default()
+    };
+    let mut current = HashMap::with_capacity(containers.len());
+    for container in containers {
+        match reported.remove(&container.container_id) {
+            None => delta.added.push(container.clone()),
+            Some(previous) if previous != container => delta.changed.push(container.clone()),
+            Some(_) => {}
+        }
+        current.insert(container.container_id.clone(), container);
+    }
+    delta.removed = reported.into_keys().collect();
+    delta.removed.sort();
+    if delta.added.is_empty() && delta.changed.is_empty() && delta.removed.is_empty() {
+        return Some(current);
+    }
+
+    let envelope = Envelope {
+        payload: Some(Payload::NodeResponse(NodeResponse {
+            kind: Some(node_response::Kind::ContainerDelta(delta)),
+        })),
+        ..Default::default()
+    };
+    if tx.send(envelope).await.is_err() {
+        error!("Failed to send container delta message");
+        return None;
+    }
+    Some(current)
+}
+
+/// Pushes a single lifecycle event so the coordinator can keep an event history.
+/// `object` is the Docker object type: "container", "image" or "volume".
+async fn send_container_event(
+    tx: &mpsc::Sender<Envelope>,
+    event: &EventMessage,
+    object: &str,
+    action: &str,
+) {
+    let actor = event.actor.clone().unwrap_or_default();
+    let attributes = actor.attributes.unwrap_or_default();
+
+    let (action, detail) = match action.split_once(':') {
+        Some((action, detail)) => (action, detail.trim()),
+        None => (action, ""),
+    };
+    let container_event = ContainerEvent {
+        request_key: Some(RequestKey {
+            request_type: RequestType::UpdateContainerInfo as i32,
+            request_id: Some(RequestId::Unspecific(true)),
+        }),
+        // Volumes are known by name only, which Docker reports as the id
+        name: attributes
+            .get("name")
+            .or(actor.id.as_ref().filter(|_| object == "volume"))
+            .cloned()
+            .unwrap_or_default(),
+        container_id: actor.id.unwrap_or_default(),
+        // Docker appends details to some actions ("exec_start: sh", "health_status: healthy")
+        action: action.to_string(),
+        time: event.time.unwrap_or(0),
+        exit_code: attributes
+            .get("exitCode")
+            .and_then(|code| code.parse().ok())
+            .unwrap_or(0),
+        image: attributes.get("image").cloned().unwrap_or_default(),
+        health: if action == "health_status" {
+            detail.to_string()
+        } else {
+            String::new()
+        },
+        r#type: object.to_string(),
+    };
+
+    let envelope = Envelope {
+        payload: Some(Payload::NodeResponse(NodeResponse {
+            kind: Some(node_response::Kind::ContainerEvent(container_event)),
+        })),
+        ..Default::default()
+    };
+    if tx.send(envelope).await.is_err() {
+        error!("Failed to send container event message");
+    }
+}
+
+/// Pushes the state of every container as an unsolicited update.
+/// The coordinator keeps the latest one per node for cluster-wide summaries.
+/// Returns the states sent, keyed by name, or `None` if nothing was sent.
+async fn send_container_states(
+    tx: &mpsc::Sender<Envelope>,
+) -> Option<HashMap<String, proto::generated::ContainerStatus>> {
+    let containers = match get_container_states().await {
+        Ok(containers) => containers,
+        Err(e) => {
+            error!("Failed to list container states: {}", e);
+            return None;
+        }
+    };
+    let sent = containers
+        .iter()
+        .map(|container| (container.container_id.clone(), container.clone()))
+        .collect();
+
+    let envelope = Envelope {
+        payload: Some(Payload::NodeResponse(NodeResponse {
+            kind: Some(node_response::Kind::NodeContainersWithStatus(
+                NodeContainersWithStatus {
+                    containers,
+                    request_key: Some(RequestKey {
+                        request_type: RequestType::UpdateContainerInfo as i32,
+                        request_id: Some(RequestId::Unspecific(true)),
+                    }),
+                },
+            )),
+        })),
+        ..Default::default()
+    };
+    if tx.send(envelope).await.is_err() {
+        error!("Failed to send container states message");
+        return None;
+    }
+    Some(sent)
+}
+
+/// Pushes the state of every container right away, outside the event watch.
+/// Used when an administrator asks nodes to refresh. Returns whether it was sent.
+pub async fn refresh_container_states(tx: &mpsc::Sender<Envelope>) -> bool {
+    send_container_states(tx).await.is_some()
+}
+
+/// Returns name, state and creation time of every container from a single list call.
+/// Unlike `get_container_status` it does not inspect each container.
+pub async fn get_container_states()
+-> Result<Vec<proto::generated::ContainerStatus>, Box<dyn Error + Send + Sync>> {
+    let docker = docker()?;
+    let containers = limited(
+        "list containers",
+        LIST_TIMEOUT,
+        docker.list_containers(Some(
+            ListContainersOptionsBuilder::default().all(true).build(),
+        )),
+    )
+    .await?;
+
+    let states = containers
+        .into_iter()
+        .filter_map(|container| {
+            let name = container
+                .names
+                .as_ref()
+                .and_then(|names| names.first())
+                .map(|name| name.trim_start_matches('/').to_string())?;
+            Some(proto::generated::ContainerStatus {
+                container_id: name,
+                status: container
+                    .state
+                    .map(|s| s.to_string())
+                    .unwrap_or_else(|| "unknown".to_string()),
+                created: container.created.unwrap_or(0),
+                labels: container
+                    .labels
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|(key, _)| key.starts_with(DOCKLORD_LABEL_PREFIX))
+                    .collect(),
+                ..Default::default()
+            })
+        })
+        .collect();
+    Ok(states)
+}
+
 /// Returns a list of all Docker containers (by name).
 /// Used for the REST endpoint /api/containers
 pub async fn get_docker_containers() -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
-    let docker = Docker::connect_with_local_defaults()?;
-    let containers = docker
-        .list_containers(Some(
+    let docker = docker()?;
+    let containers = limited(
+        "list containers",
+        LIST_TIMEOUT,
+        docker.list_containers(Some(
             ListContainersOptionsBuilder::default().all(true).build(),
-        ))
-        .await?;
+        )),
+    )
+    .await?;
     let container_names: Vec<String> = containers
         .into_iter()
         .filter_map(|container| {
@@ -82,13 +447,16 @@ pub async fn get_docker_containers() -> Result<Vec<String>, Box<dyn Error + Send
 pub async fn get_container_status(
     container_id: &str,
 ) -> Result<proto::generated::ContainerStatus, Box<dyn Error + Send + Sync>> {
-    let docker = Docker::connect_with_local_defaults()?;
-    let container_info = docker
-        .inspect_container(
+    let docker = docker()?;
+    let container_info = limited(
+        "inspect container",
+        INSPECT_TIMEOUT,
+        docker.inspect_container(
             container_id,
             Some(bollard::query_parameters::InspectContainerOptionsBuilder::default().build()),
-        )
-        .await?;
+        ),
+    )
+    .await?;
 
     let state = container_info.state.unwrap_or_default();
     let status = state
@@ -96,27 +464,25 @@ pub async fn get_container_status(
         .map(|s| s.to_string())
         .unwrap_or_else(|| "unknown".to_string());
 
-    // Parse timestamps properly
-    let created = container_info
-        .created
-        .and_then(|s| chrono::DateTime::parse_from_rfc3339(&s).ok())
-        .map(|dt| dt.timestamp())
-        .unwrap_or(0);
-
-    let started_at = state
-        .started_at
-        .and_then(|s| chrono::DateTime::parse_from_rfc3339(&s).ok())
-        .map(|dt| dt.timestamp())
-        .unwrap_or(0);
-
-    let finished_at = state
-        .finished_at
-        .and_then(|s| chrono::DateTime::parse_from_rfc3339(&s).ok())
-        .map(|dt| dt.timestamp())
-        .unwrap_or(0);
+    let created = epoch_secs(container_info.created.as_deref());
+    let started_at = epoch_secs(state.started_at.as_deref());
+    let finished_at = epoch_secs(state.finished_at.as_deref());
 
     let exit_code = state.exit_code.unwrap_or(0).try_into().unwrap_or(0);
 
+    // Configured cgroup limits
+    let host_config = container_info.host_config.unwrap_or_default();
+    let memory_limit = host_config.memory.unwrap_or(0);
+    let cpu_limit = match (
+        host_config.nano_cpus.unwrap_or(0),
+        host_config.cpu_quota.unwrap_or(0),
+        host_config.cpu_period.unwrap_or(0),
+    ) {
+        (nano_cpus, _, _) if nano_cpus > 0 => nano_cpus as f64 / 1e9,
+        (_, quota, period) if quota > 0 && period > 0 => quota as f64 / period as f64,
+        _ => 0.0,
+    };
+
     Ok(proto::generated::ContainerStatus {
         request_key: None, // will be set by the handler
         container_id: container_id.to_string(),
@@ -125,21 +491,53 @@ pub async fn get_container_status(
         started_at,
         finished_at,
         exit_code,
+        memory_limit,
+        cpu_limit,
+        memory_usage: 0,
+        cpu_usage: 0.0,
+        labels: Default::default(),
     })
 }
 
+/// Fills current memory and CPU usage from a single stats sample.
+/// Takes about a second because Docker samples CPU twice to compute the delta.
+pub async fn fill_container_usage(
+    status: &mut proto::generated::ContainerStatus,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let docker = docker()?;
+    let mut stream = docker.stats(
+        &status.container_id,
+        Some(StatsOptionsBuilder::default().stream(false).build()),
+    );
+
+    let sample = limited("stats", STATS_SAMPLE_TIMEOUT, stream.try_next()).await?;
+    let Some(stats) = sample else {
+        return Ok(());
+    };
+
+    status.memory_usage = container_stats::memory_usage(&stats)
+        .try_into()
+        .unwrap_or(i64::MAX);
+    status.cpu_usage = container_stats::cpu_cores(&stats);
+
+    Ok(())
+}
+
 /// Starts a container by id. Used for /api/containers/:container_id/start
 pub async fn start_container(
     container_id: &str,
 ) -> Result<proto::generated::ContainerAction, Box<dyn Error + Send + Sync>> {
-    let docker = Docker::connect_with_local_defaults()?;
+    let docker = docker()?;
 
-    match docker
-        .start_container(
+    match limited(
+        "start container",
+        LIFECYCLE_TIMEOUT,
+        docker.start_container(
             container_id,
             Some(StartContainerOptionsBuilder::default().build()),
-        )
-        .await
+        ),
+    )
+    .await
     {
         Ok(_) => Ok(proto::generated::ContainerAction {
             request_key: None, // будет установлено в обработчике
@@ -147,7 +545,7 @@ pub async fn start_container(
             action: "start".to_string(),
             message: "Container started successfully".to_string(),
         }),
-        Err(e) => Err(e.into()),
+        Err(e) => Err(e),
     }
 }
 
@@ -155,14 +553,17 @@ pub async fn start_container(
 pub async fn stop_container(
     container_id: &str,
 ) -> Result<proto::generated::ContainerAction, Box<dyn Error + Send + Sync>> {
-    let docker = Docker::connect_with_local_defaults()?;
+    let docker = docker()?;
 
-    match docker
-        .stop_container(
+    match limited(
+        "stop container",
+        LIFECYCLE_TIMEOUT,
+        docker.stop_container(
             container_id,
             Some(StopContainerOptionsBuilder::default().build()),
-        )
-        .await
+        ),
+    )
+    .await
     {
         Ok(_) => Ok(proto::generated::ContainerAction {
             request_key: None, // будет установлено в обработчике
@@ -170,85 +571,385 @@ pub async fn stop_container(
             action: "stop".to_string(),
             message: "Container stopped successfully".to_string(),
         }),
-        Err(e) => Err(e.into()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Restarts a container by id, giving it `timeout_secs` (Docker's default when
+/// `None`) to stop before it is killed. Used for /api/containers/:container_id/restart
+pub async fn restart_container(
+    container_id: &str,
+    timeout_secs: Option<u32>,
+) -> Result<proto::generated::ContainerAction, Box<dyn Error + Send + Sync>> {
+    let docker = docker()?;
+    let mut options = RestartContainerOptionsBuilder::default();
+    if let Some(timeout) = timeout_secs {
+        options = options.t(timeout.min(i32::MAX as u32) as i32);
+    }
+    // The grace period comes on top of the usual lifecycle budget
+    let budget =
+        LIFECYCLE_TIMEOUT + std::time::Duration::from_secs(timeout_secs.unwrap_or(10).into());
+
+    match limited(
+        "restart container",
+        budget,
+        docker.restart_container(container_id, Some(options.build())),
+    )
+    .await
+    {
+        Ok(_) => Ok(proto::generated::ContainerAction {
+            request_key: None, // set by the handler
+            container_id: container_id.to_string(),
+            action: "restart".to_string(),
+            message: "Container restarted successfully".to_string(),
+        }),
+        Err(e) => Err(e),
     }
 }
 
+/// Freezes the processes of a container by id. Used for /api/containers/:container_id/pause
+pub async fn pause_container(
+    container_id: &str,
+) -> Result<proto::generated::ContainerAction, Box<dyn Error + Send + Sync>> {
+    let docker = docker()?;
+    limited(
+        "pause container",
+        LIFECYCLE_TIMEOUT,
+        docker.pause_container(container_id),
+    )
+    .await?;
+    Ok(proto::generated::ContainerAction {
+        request_key: None, // set by the handler
+        container_id: container_id.to_string(),
+        action: "pause".to_string(),
+        message: "Container paused successfully".to_string(),
+    })
+}
+
+/// Resumes a paused container by id. Used for /api/containers/:container_id/unpause
+pub async fn unpause_container(
+    container_id: &str,
+) -> Result<proto::generated::ContainerAction, Box<dyn Error + Send + Sync>> {
+    let docker = docker()?;
+    limited(
+        "unpause container",
+        LIFECYCLE_TIMEOUT,
+        docker.unpause_container(container_id),
+    )
+    .await?;
+    Ok(proto::generated::ContainerAction {
+        request_key: None, // set by the handler
+        container_id: container_id.to_string(),
+        action: "unpause".to_string(),
+        message: "Container unpaused successfully".to_string(),
+    })
+}
+
 /// Deletes a container by id. Used for DELETE /api/containers/:container_id
 pub async fn delete_container(
     container_id: &str,
 ) -> Result<proto::generated::ContainerAction, Box<dyn Error + Send + Sync>> {
-    let docker = Docker::connect_with_local_defaults()?;
+    let docker = docker()?;
+    let secrets_label = secrets_label_of(&docker, container_id).await;
 
-    match docker
-        .remove_container(
+    match limited(
+        "remove container",
+        LIFECYCLE_TIMEOUT,
+        docker.remove_container(
             container_id,
             Some(RemoveContainerOptionsBuilder::default().build()),
-        )
-        .await
+        ),
+    )
+    .await
     {
-        Ok(_) => Ok(proto::generated::ContainerAction {
-            request_key: None, // будет установлено в обработчике
-            container_id: container_id.to_string(),
-            action: "delete".to_string(),
-            message: "Container deleted successfully".to_string(),
-        }),
-        Err(e) => Err(e.into()),
+        Ok(_) => {
+            if let Some(label) = secrets_label {
+                secrets::remove_secret_files(&label).await;
+            }
+            Ok(proto::generated::ContainerAction {
+                request_key: None, // будет установлено в обработчике
+                container_id: container_id.to_string(),
+                action: "delete".to_string(),
+                message: "Container deleted successfully".to_string(),
+            })
+        }
+        Err(e) => Err(e),
     }
 }
 
-/// Returns logs for a container. Supports tail, follow, since options.
+/// Unix seconds of a Docker RFC 3339 time, 0 when missing or unset. Docker
+/// reports unset times as `0001-01-01T00:00:00Z`, far before the epoch.
+pub(crate) fn epoch_secs(time: Option<&str>) -> i64 {
+    time.and_then(|time| chrono::DateTime::parse_from_rfc3339(time).ok())
+        .map(|time| time.timestamp().max(0))
+        .unwrap_or(0)
+}
+
+/// The container's secret files label, if it was created with file secrets.
+async fn secrets_label_of(docker: &Docker, container_id: &str) -> Option<String> {
+    let info = limited(
+        "inspect container",
+        INSPECT_TIMEOUT,
+        docker.inspect_container(
+            container_id,
+            Some(bollard::query_parameters::InspectContainerOptionsBuilder::default().build()),
+        ),
+    )
+    .await
+    .ok()?;
+    info.config?.labels?.remove(secrets::SECRETS_LABEL)
+}
+
+/// Returns logs for a container, each line tagged with its stream. Supports tail,
+/// follow, since, timestamps and streams options; reading stops once `max_bytes`
+/// of lines were collected, oldest first.
 /// Used for /api/containers/:container_id/logs
 pub async fn get_container_logs(
-    container_id: &str,
-    tail: Option<i32>,
-    follow: bool,
-    since: Option<String>,
+    request: &GetContainerLogs,
 ) -> Result<proto::generated::ContainerLogs, Box<dyn Error + Send + Sync>> {
-    let docker = Docker::connect_with_local_defaults()?;
+    if let Some(logs) = log_buffer::buffered_logs(request) {
+        return Ok(logs);
+    }
+    let docker = docker()?;
 
     let mut logs_builder = LogsOptionsBuilder::default();
-    logs_builder = logs_builder.stdout(true);
-    logs_builder = logs_builder.stderr(true);
-    if let Some(t) = tail {
-        logs_builder = logs_builder.tail(&t.to_string());
-    }
-    logs_builder = logs_builder.follow(follow);
-    if let Some(s) = since {
-        if let Ok(timestamp) = s.parse::<i64>() {
-            logs_builder = logs_builder.since(timestamp.try_into().unwrap());
-        }
+    logs_builder = logs_builder.stdout(reads_stream(request.streams(), "stdout"));
+    logs_builder = logs_builder.stderr(reads_stream(request.streams(), "stderr"));
+    if request.tail >= 0 {
+        logs_builder = logs_builder.tail(&request.tail.to_string());
+    }
+    logs_builder = logs_builder.follow(request.follow);
+    logs_builder = logs_builder.timestamps(request.timestamps);
+    if let Ok(timestamp) = request.since.parse::<i64>() {
+        logs_builder = logs_builder.since(timestamp.try_into().unwrap_or(i32::MAX));
     }
 
     let options = logs_builder.build();
-    let mut stream = docker.logs(container_id, Some(options));
+    let mut stream = docker.logs(&request.container_id, Some(options));
 
-    let mut logs = Vec::new();
-
-    // Если follow = false, читаем все доступные логи
-    if !follow {
-        while let Ok(Some(log)) = stream.try_next().await {
-            if let Ok(log_line) = String::from_utf8(log.into_bytes().to_vec()) {
-                logs.push(log_line);
-            }
-        }
-    } else {
+    let read_logs = async {
+        let mut page = LogPage::new(request.after.as_ref(), request.max_bytes);
         // Для follow = true читаем только последние логи
-        let mut count = 0;
+        let max_lines = match request.follow {
+            true if request.tail >= 0 => request.tail as usize,
+            true => 100,
+            false => usize::MAX,
+        };
+
         while let Ok(Some(log)) = stream.try_next().await {
-            if let Ok(log_line) = String::from_utf8(log.into_bytes().to_vec()) {
-                logs.push(log_line);
-                count += 1;
-                if count >= tail.unwrap_or(100) {
-                    break;
-                }
+            let Some(line) = log_line(log, request.timestamps) else {
+                continue;
+            };
+            if !page.push(line) || page.lines.len() >= max_lines {
+                break;
             }
         }
-    }
+        Ok::<_, Box<dyn Error + Send + Sync>>(page)
+    };
+    let LogPage {
+        lines, truncated, ..
+    } = limited("logs", LOGS_TIMEOUT, read_logs).await?;
 
     Ok(proto::generated::ContainerLogs {
         request_key: None, // будет установлено в обработчике
-        container_id: container_id.to_string(),
-        logs,
+        container_id: request.container_id.clone(),
+        lines,
+        truncated,
+        ..Default::default()
+    })
+}
+
+/// Whether `streams` includes the output stream named `stream`.
+pub(crate) fn reads_stream(streams: LogStreams, stream: &str) -> bool {
+    match streams {
+        LogStreams::Both => true,
+        LogStreams::Stdout => stream == "stdout",
+        LogStreams::Stderr => stream == "stderr",
+    }
+}
+
+/// A piece of Docker's log output as a line tagged with its stream. With
+/// `timestamps`, Docker's time prefix is moved into the line's timestamp.
+pub(crate) fn log_line(output: LogOutput, timestamps: bool) -> Option<LogLine> {
+    let (stream, message) = match output {
+        LogOutput::StdErr { message } => ("stderr", message),
+        LogOutput::StdOut { message } | LogOutput::Console { message } => ("stdout", message),
+        LogOutput::StdIn { .. } => return None,
+    };
+    let text = String::from_utf8(message.to_vec()).ok()?;
+    if timestamps && let Some((timestamp, rest)) = text.split_once(' ') {
+        return Some(LogLine {
+            text: rest.to_string(),
+            stream: stream.to_string(),
+            timestamp: timestamp.to_string(),
+        });
+    }
+    Some(LogLine {
+        text,
+        stream: stream.to_string(),
+        timestamp: String::new(),
+    })
+}
+
+/// Sends each log line, tagged with its stream, to `output_tx` as Docker writes
+/// it, starting with the last `tail` lines. Returns once the container stops or
+/// the receiver is gone. Used for the /observe-logs WebSocket
+pub async fn follow_container_logs(
+    request: &FollowContainerLogs,
+    output_tx: mpsc::Sender<LogLine>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let docker = docker()?;
+    let mut logs_builder = LogsOptionsBuilder::default()
+        .stdout(reads_stream(request.streams(), "stdout"))
+        .stderr(reads_stream(request.streams(), "stderr"))
+        .follow(true)
+        .timestamps(request.timestamps);
+    if request.tail >= 0 {
+        logs_builder = logs_builder.tail(&request.tail.to_string());
+    }
+
+    // No overall timeout: the stream lasts as long as the container runs
+    let mut stream = docker.logs(&request.container_id, Some(logs_builder.build()));
+    while let Some(log) = stream.try_next().await? {
+        let Some(line) = log_line(log, request.timestamps) else {
+            continue;
+        };
+        if output_tx.send(line).await.is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Builds an image from a remote context, sending each piece of build output as it arrives.
+/// The final message has `done` set. Used for POST /api/images/build
+pub async fn build_image(
+    request: BuildImage,
+    output_tx: mpsc::Sender<ImageBuildOutput>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let docker = docker()?;
+
+    let mut options_builder = BuildImageOptionsBuilder::default()
+        .remote(&request.remote)
+        .nocache(request.nocache)
+        .rm(true);
+    if !request.dockerfile.is_empty() {
+        options_builder = options_builder.dockerfile(&request.dockerfile);
+    }
+    if !request.tag.is_empty() {
+        options_builder = options_builder.t(&request.tag);
+    }
+    if !request.build_args.is_empty() {
+        options_builder = options_builder.buildargs(&request.build_args);
+    }
+    if request.pull {
+        options_builder = options_builder.pull("true");
+    }
+
+    // The context is fetched by the daemon from `remote`, so the body stays empty
+    let mut stream = docker.build_image(
+        options_builder.build(),
+        None,
+        Some(bollard::body_full(bytes::Bytes::new())),
+    );
+
+    let mut image_id = String::new();
+    while let Some(info) = stream.try_next().await? {
+        if let Some(id) = info.aux.and_then(|aux| aux.id) {
+            image_id = id;
+        }
+
+        let output = ImageBuildOutput {
+            request_key: None, // will be set by the handler
+            stream: info.stream.unwrap_or_default(),
+            status: info.status.unwrap_or_default(),
+            progress: info.progress.unwrap_or_default(),
+            ..Default::default()
+        };
+        if output.stream.is_empty() && output.status.is_empty() && output.progress.is_empty() {
+            continue;
+        }
+        if output_tx.send(output).await.is_err() {
+            // Nobody is listening anymore, stop reading the build
+            return Ok(());
+        }
+    }
+
+    let _ = output_tx
+        .send(ImageBuildOutput {
+            request_key: None, // will be set by the handler
+            image_id,
+            done: true,
+            ..Default::default()
+        })
+        .await;
+
+    Ok(())
+}
+
+/// Resolves an image reference against its registry, including the platforms
+/// covered by a multi-arch manifest list. Used for /api/images/:ref/manifest
+pub async fn inspect_image_manifest(
+    image: &str,
+) -> Result<proto::generated::ImageManifest, Box<dyn Error + Send + Sync>> {
+    let docker = docker()?;
+    let distribution = limited(
+        "inspect image manifest",
+        REGISTRY_TIMEOUT,
+        docker.inspect_registry_image(image, None),
+    )
+    .await?;
+
+    let platforms = distribution
+        .platforms
+        .into_iter()
+        .map(|platform| proto::generated::ImagePlatform {
+            os: platform.os.unwrap_or_default(),
+            architecture: platform.architecture.unwrap_or_default(),
+            variant: platform.variant.unwrap_or_default(),
+            os_version: platform.os_version.unwrap_or_default(),
+        })
+        .collect();
+
+    Ok(proto::generated::ImageManifest {
+        request_key: None, // will be set by the handler
+        image: image.to_string(),
+        digest: distribution.descriptor.digest.unwrap_or_default(),
+        media_type: distribution.descriptor.media_type.unwrap_or_default(),
+        size: distribution.descriptor.size.unwrap_or(0),
+        platforms,
+    })
+}
+
+/// Layers of a local image with the command that created each, newest first.
+/// Used for /api/images/:id/history
+pub async fn get_image_history(
+    image: &str,
+) -> Result<proto::generated::ImageHistory, Box<dyn Error + Send + Sync>> {
+    let docker = docker()?;
+    let history = limited(
+        "image history",
+        INSPECT_TIMEOUT,
+        docker.image_history(image),
+    )
+    .await?;
+
+    let layers: Vec<_> = history
+        .into_iter()
+        .map(|layer| proto::generated::ImageLayer {
+            id: layer.id,
+            created: layer.created,
+            created_by: layer.created_by,
+            size: layer.size,
+            tags: layer.tags,
+            comment: layer.comment,
+        })
+        .collect();
+
+    Ok(proto::generated::ImageHistory {
+        request_key: None, // will be set by the handler
+        image: image.to_string(),
+        total_size: layers.iter().map(|layer| layer.size).sum(),
+        layers,
     })
 }
@@ -0,0 +1,174 @@
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::sync::{LazyLock, Mutex, OnceLock};
+use std::time::Duration;
+
+use bollard::query_parameters::LogsOptionsBuilder;
+use futures_util::stream::TryStreamExt;
+use proto::generated::{ContainerLogs, GetContainerLogs, LogLine};
+use tracing::{debug, info, warn};
+
+use crate::docker_client::docker;
+use crate::log_page::LogPage;
+use crate::{log_line, reads_stream};
+
+pub const DEFAULT_LOG_BUFFER_LINES: usize = 1000;
+// Docker sends the seeding tail at once; the buffer is used once it had time to arrive
+const SEED_GRACE: Duration = Duration::from_secs(1);
+// Wait before following a container again after its log stream ended
+const FOLLOW_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Containers whose recent log lines the node keeps in memory, so tail
+/// requests are answered without asking Docker.
+#[derive(Debug, Clone)]
+pub struct LogBufferConfig {
+    /// Container names; none disables the buffer.
+    pub containers: Vec<String>,
+    /// Lines kept per container. Larger tails still go to Docker.
+    pub lines: usize,
+}
+
+impl Default for LogBufferConfig {
+    fn default() -> Self {
+        Self {
+            containers: Vec::new(),
+            lines: DEFAULT_LOG_BUFFER_LINES,
+        }
+    }
+}
+
+#[derive(Default)]
+struct LogBuffer {
+    // With their Docker timestamps, oldest first
+    lines: VecDeque<LogLine>,
+    // Following the container's logs, so nothing is missing from the end
+    live: bool,
+}
+
+static LOG_BUFFER: OnceLock<LogBufferConfig> = OnceLock::new();
+static BUFFERS: LazyLock<Mutex<HashMap<String, LogBuffer>>> = LazyLock::new(Default::default);
+
+/// Sets the buffered containers. Only the first call counts, so call it before
+/// the node connects; without it nothing is buffered.
+pub fn configure_log_buffer(config: LogBufferConfig) {
+    if LOG_BUFFER.set(config).is_err() {
+        warn!("Log buffer already configured, keeping the first settings");
+    }
+}
+
+/// Starts following the logs of each buffered container for as long as the
+/// node runs. Containers that are stopped or missing are retried.
+pub fn spawn_log_buffers() {
+    let Some(config) = LOG_BUFFER.get() else {
+        return;
+    };
+    for name in &config.containers {
+        info!("Buffering the last {} log lines of {}", config.lines, name);
+        tokio::spawn(follow_into_buffer(name.clone(), config.lines));
+    }
+}
+
+async fn follow_into_buffer(name: String, capacity: usize) {
+    loop {
+        match fill_buffer(&name, capacity).await {
+            Ok(()) => debug!("Log stream of {} ended", name),
+            Err(e) => debug!("Cannot follow the logs of {}: {}", name, e),
+        }
+        if let Some(buffer) = buffers().get_mut(&name) {
+            buffer.live = false;
+        }
+        tokio::time::sleep(FOLLOW_RETRY_DELAY).await;
+    }
+}
+
+// Seeds the buffer with the last `capacity` lines, then appends new lines
+// until the container stops
+async fn fill_buffer(name: &str, capacity: usize) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let docker = docker()?;
+    let options = LogsOptionsBuilder::default()
+        .stdout(true)
+        .stderr(true)
+        .follow(true)
+        .timestamps(true)
+        .tail(&capacity.to_string())
+        .build();
+    let mut stream = docker.logs(name, Some(options));
+    buffers().insert(name.to_string(), LogBuffer::default());
+
+    let seeded = tokio::time::sleep(SEED_GRACE);
+    tokio::pin!(seeded);
+    let mut seeding = true;
+    loop {
+        tokio::select! {
+            log = stream.try_next() => {
+                let Some(log) = log? else {
+                    return Ok(());
+                };
+                let Some(line) = log_line(log, true) else {
+                    continue;
+                };
+                if let Some(buffer) = buffers().get_mut(name) {
+                    if buffer.lines.len() >= capacity {
+                        buffer.lines.pop_front();
+                    }
+                    buffer.lines.push_back(line);
+                }
+            }
+            _ = &mut seeded, if seeding => {
+                seeding = false;
+                if let Some(buffer) = buffers().get_mut(name) {
+                    buffer.live = true;
+                }
+            }
+        }
+    }
+}
+
+fn buffers() -> std::sync::MutexGuard<'static, HashMap<String, LogBuffer>> {
+    BUFFERS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Answers a plain tail request from the buffer: no `since`, no `follow`, a
+/// `tail` the buffer covers, and a container it is currently following.
+/// Lines are limited to `max_bytes` the same way Docker reads are.
+pub(crate) fn buffered_logs(request: &GetContainerLogs) -> Option<ContainerLogs> {
+    let config = LOG_BUFFER.get()?;
+    if request.fresh
+        || request.follow
+        || !request.since.is_empty()
+        || request.after.is_some()
+        || request.tail < 0
+        || request.tail as usize > config.lines
+    {
+        return None;
+    }
+
+    let buffers = buffers();
+    let buffer = buffers.get(&request.container_id).filter(|b| b.live)?;
+    // The tail counts lines of the streams asked for
+    let selected: Vec<&LogLine> = buffer
+        .lines
+        .iter()
+        .filter(|line| reads_stream(request.streams(), &line.stream))
+        .collect();
+    let skip = selected.len().saturating_sub(request.tail as usize);
+    let mut page = LogPage::new(None, request.max_bytes);
+    for line in selected.into_iter().skip(skip) {
+        let mut line = line.clone();
+        if !request.timestamps {
+            line.timestamp.clear();
+        }
+        if !page.push(line) {
+            break;
+        }
+    }
+
+    Some(ContainerLogs {
+        request_key: None, // set by the handler
+        container_id: request.container_id.clone(),
+        lines: page.lines,
+        truncated: page.truncated,
+        buffered: true,
+        ..Default::default()
+    })
+}
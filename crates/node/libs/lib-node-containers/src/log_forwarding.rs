@@ -0,0 +1,488 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bollard::container::LogOutput;
+use bollard::query_parameters::{ListContainersOptionsBuilder, LogsOptionsBuilder};
+use futures_util::StreamExt;
+use http_body_util::Full;
+use hyper::body::Bytes;
+use hyper::{Request, Uri, header};
+use hyper_util::client::legacy::Client;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::rt::TokioExecutor;
+use proto::generated::{LogForward, LogForwardStatus, LogForwardingStatus};
+use serde_json::json;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::sync::mpsc;
+use tokio::task::{JoinHandle, JoinSet};
+use tracing::{info, warn};
+
+use crate::docker_client::docker;
+use crate::docker_limits::{LIST_TIMEOUT, limited};
+
+// How often the running containers are checked for new ones to tail
+const RESCAN_INTERVAL: Duration = Duration::from_secs(10);
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+const MAX_BATCH_LINES: usize = 500;
+// Lines waiting for the sink; tails pause while it is full
+const LINE_BUFFER: usize = 10_000;
+const SINK_TIMEOUT: Duration = Duration::from_secs(10);
+const SHIP_ATTEMPTS: u32 = 3;
+// RFC 5424 limits APP-NAME to 48 characters
+const SYSLOG_APP_NAME_MAX: usize = 48;
+// Private enterprise number for the structured data id, from the documentation range
+const SYSLOG_SD_ID: &str = "docklord@32473";
+
+static FORWARDS: OnceLock<Mutex<Vec<ActiveForward>>> = OnceLock::new();
+
+fn forwards() -> &'static Mutex<Vec<ActiveForward>> {
+    FORWARDS.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+struct ActiveForward {
+    config: LogForward,
+    stats: Arc<ForwardStats>,
+    task: JoinHandle<()>,
+}
+
+impl Drop for ActiveForward {
+    fn drop(&mut self) {
+        // Dropping the task's JoinSet aborts its tails too
+        self.task.abort();
+    }
+}
+
+#[derive(Default)]
+struct ForwardStats {
+    tailing: Mutex<Vec<String>>,
+    forwarded: AtomicU64,
+    dropped: AtomicU64,
+    last_error: Mutex<Option<(String, i64)>>,
+}
+
+impl ForwardStats {
+    fn error(&self, name: &str, message: String) {
+        warn!("Log forward {}: {}", name, message);
+        *self.last_error.lock().unwrap_or_else(|e| e.into_inner()) = Some((message, now_secs()));
+    }
+
+    fn status(&self, name: &str) -> LogForwardStatus {
+        let (last_error, last_error_at) = self
+            .last_error
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+            .unwrap_or_default();
+        LogForwardStatus {
+            name: name.to_string(),
+            containers: self
+                .tailing
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .clone(),
+            lines_forwarded: self.forwarded.load(Ordering::Relaxed),
+            lines_dropped: self.dropped.load(Ordering::Relaxed),
+            last_error,
+            last_error_at,
+        }
+    }
+}
+
+/// Replaces the node's log forwards. Forwards whose configuration did not
+/// change keep running, so a reconnect does not restart their tails.
+/// Used for the coordinator's /api/nodes/:id/log-forwarding
+pub fn configure_log_forwarding(
+    configs: Vec<LogForward>,
+) -> Result<LogForwardingStatus, Box<dyn Error + Send + Sync>> {
+    // Check every sink first so a bad one leaves the current forwards untouched
+    let sinks = configs
+        .iter()
+        .map(|config| Sink::new(config).map_err(|e| format!("forward '{}': {}", config.name, e)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut active = forwards().lock().unwrap_or_else(|e| e.into_inner());
+    let mut previous: Vec<ActiveForward> = active.drain(..).collect();
+    for (config, sink) in configs.into_iter().zip(sinks) {
+        if let Some(index) = previous.iter().position(|f| f.config == config) {
+            active.push(previous.swap_remove(index));
+            continue;
+        }
+        info!(
+            "Forwarding logs of {:?} to {} {}",
+            config.containers, config.sink, config.target
+        );
+        let stats = Arc::new(ForwardStats::default());
+        let task = tokio::spawn(run_forward(config.clone(), sink, stats.clone()));
+        active.push(ActiveForward {
+            config,
+            stats,
+            task,
+        });
+    }
+    for stopped in &previous {
+        info!("Stopped log forward {}", stopped.config.name);
+    }
+    drop(previous);
+
+    Ok(status(&active))
+}
+
+/// Containers being tailed and line counts per forward.
+pub fn log_forwarding_status() -> LogForwardingStatus {
+    status(&forwards().lock().unwrap_or_else(|e| e.into_inner()))
+}
+
+fn status(active: &[ActiveForward]) -> LogForwardingStatus {
+    LogForwardingStatus {
+        request_key: None, // will be set by the handler
+        forwards: active
+            .iter()
+            .map(|forward| forward.stats.status(&forward.config.name))
+            .collect(),
+    }
+}
+
+struct LogLine {
+    container: String,
+    stream: &'static str,
+    /// Unix nanoseconds, as Docker stamped the line.
+    time_ns: i64,
+    line: String,
+}
+
+/// Tails matching containers as they start and ships their lines in batches.
+async fn run_forward(config: LogForward, sink: Sink, stats: Arc<ForwardStats>) {
+    let (line_tx, mut line_rx) = mpsc::channel(LINE_BUFFER);
+    let mut tails = JoinSet::new();
+    let mut tailing: HashSet<String> = HashSet::new();
+    // Where to resume a container's logs after it restarts, unix seconds
+    let mut resume_at: HashMap<String, i64> = HashMap::new();
+    let mut rescan = tokio::time::interval(RESCAN_INTERVAL);
+    let mut flush = tokio::time::interval(FLUSH_INTERVAL);
+    let mut batch = Vec::new();
+
+    loop {
+        tokio::select! {
+            _ = rescan.tick() => {
+                match running_containers().await {
+                    Ok(names) => {
+                        for name in names.into_iter().filter(|name| matches(&config, name)) {
+                            if tailing.insert(name.clone()) {
+                                let since = resume_at.get(&name).copied().unwrap_or_else(now_secs);
+                                tails.spawn(tail(name, since, line_tx.clone()));
+                            }
+                        }
+                        set_tailing(&stats, &tailing);
+                    }
+                    Err(e) => stats.error(&config.name, format!("listing containers failed: {}", e)),
+                }
+            }
+            Some(Ok((name, last_secs))) = tails.join_next() => {
+                tailing.remove(&name);
+                resume_at.insert(name, last_secs + 1);
+                set_tailing(&stats, &tailing);
+            }
+            Some(line) = line_rx.recv() => {
+                batch.push(line);
+                if batch.len() >= MAX_BATCH_LINES {
+                    ship(&config, &sink, &stats, std::mem::take(&mut batch)).await;
+                }
+            }
+            _ = flush.tick() => {
+                if !batch.is_empty() {
+                    ship(&config, &sink, &stats, std::mem::take(&mut batch)).await;
+                }
+            }
+        }
+    }
+}
+
+fn matches(config: &LogForward, name: &str) -> bool {
+    config
+        .containers
+        .iter()
+        .any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => name == pattern,
+        })
+}
+
+fn set_tailing(stats: &ForwardStats, tailing: &HashSet<String>) {
+    let mut names: Vec<String> = tailing.iter().cloned().collect();
+    names.sort();
+    *stats.tailing.lock().unwrap_or_else(|e| e.into_inner()) = names;
+}
+
+async fn running_containers() -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+    let docker = docker()?;
+    let containers = limited(
+        "list containers",
+        LIST_TIMEOUT,
+        docker.list_containers(Some(ListContainersOptionsBuilder::default().build())),
+    )
+    .await?;
+    Ok(containers
+        .into_iter()
+        .filter_map(|container| container.names?.into_iter().next())
+        .map(|name| name.trim_start_matches('/').to_string())
+        .collect())
+}
+
+/// Follows one container's logs from `since` until it stops. Returns the
+/// container name and the time of the last line, in unix seconds.
+async fn tail(name: String, since: i64, tx: mpsc::Sender<LogLine>) -> (String, i64) {
+    let mut last_secs = since;
+    let docker = match docker() {
+        Ok(docker) => docker,
+        Err(e) => {
+            warn!("Cannot tail {}: {}", name, e);
+            return (name, last_secs);
+        }
+    };
+    // Not limited: a follow runs as long as the container does
+    let options = LogsOptionsBuilder::default()
+        .follow(true)
+        .stdout(true)
+        .stderr(true)
+        .timestamps(true)
+        .since(since.try_into().unwrap_or(i32::MAX))
+        .build();
+    let mut stream = docker.logs(&name, Some(options));
+
+    while let Some(Ok(output)) = stream.next().await {
+        let (stream_name, message) = match output {
+            LogOutput::StdErr { message } => ("stderr", message),
+            LogOutput::StdOut { message } | LogOutput::Console { message } => ("stdout", message),
+            LogOutput::StdIn { .. } => continue,
+        };
+        for raw in String::from_utf8_lossy(&message).lines() {
+            // With timestamps each line starts with an RFC 3339 time and a space
+            let (time_ns, line) = raw
+                .split_once(' ')
+                .and_then(|(time, line)| {
+                    let time = chrono::DateTime::parse_from_rfc3339(time).ok()?;
+                    Some((time.timestamp_nanos_opt()?, line))
+                })
+                .unwrap_or_else(|| (now_secs() * 1_000_000_000, raw));
+            last_secs = time_ns / 1_000_000_000;
+            let line = LogLine {
+                container: name.clone(),
+                stream: stream_name,
+                time_ns,
+                line: line.to_string(),
+            };
+            if tx.send(line).await.is_err() {
+                return (name, last_secs);
+            }
+        }
+    }
+    (name, last_secs)
+}
+
+/// Sends a batch, retrying a few times before the lines are dropped.
+async fn ship(config: &LogForward, sink: &Sink, stats: &ForwardStats, batch: Vec<LogLine>) {
+    let mut delay = Duration::from_secs(1);
+    for attempt in 1..=SHIP_ATTEMPTS {
+        match tokio::time::timeout(SINK_TIMEOUT, sink.send(&config.labels, &batch)).await {
+            Ok(Ok(())) => {
+                stats
+                    .forwarded
+                    .fetch_add(batch.len() as u64, Ordering::Relaxed);
+                return;
+            }
+            Ok(Err(e)) => stats.error(&config.name, format!("{} sink failed: {}", config.sink, e)),
+            Err(_) => stats.error(&config.name, format!("{} sink timed out", config.sink)),
+        }
+        if attempt < SHIP_ATTEMPTS {
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+    }
+    stats
+        .dropped
+        .fetch_add(batch.len() as u64, Ordering::Relaxed);
+}
+
+enum Sink {
+    Loki {
+        url: Uri,
+        client: Client<HttpConnector, Full<Bytes>>,
+    },
+    SyslogUdp(String),
+    SyslogTcp(String),
+    File(PathBuf),
+}
+
+impl Sink {
+    fn new(config: &LogForward) -> Result<Self, String> {
+        match config.sink.as_str() {
+            "loki" => {
+                let url: Uri = config
+                    .target
+                    .parse()
+                    .map_err(|e| format!("invalid Loki URL: {}", e))?;
+                if url.scheme_str() != Some("http") {
+                    return Err("Loki URL must be an http:// URL".to_string());
+                }
+                Ok(Sink::Loki {
+                    url,
+                    client: Client::builder(TokioExecutor::new()).build_http(),
+                })
+            }
+            "syslog" => match config.target.split_once("://") {
+                Some(("udp", address)) if !address.is_empty() => {
+                    Ok(Sink::SyslogUdp(address.to_string()))
+                }
+                Some(("tcp", address)) if !address.is_empty() => {
+                    Ok(Sink::SyslogTcp(address.to_string()))
+                }
+                _ => Err("syslog target must be udp://host:port or tcp://host:port".to_string()),
+            },
+            "file" if config.target.starts_with('/') => {
+                Ok(Sink::File(PathBuf::from(&config.target)))
+            }
+            "file" => Err("file target must be an absolute path".to_string()),
+            other => Err(format!("unknown sink '{}'", other)),
+        }
+    }
+
+    async fn send(
+        &self,
+        labels: &HashMap<String, String>,
+        batch: &[LogLine],
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        match self {
+            Sink::Loki { url, client } => {
+                let request = Request::post(url.clone())
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Full::new(Bytes::from(loki_payload(labels, batch))))?;
+                let response = client.request(request).await?;
+                if !response.status().is_success() {
+                    return Err(format!("status {}", response.status()).into());
+                }
+            }
+            Sink::SyslogUdp(address) => {
+                let socket = UdpSocket::bind("0.0.0.0:0").await?;
+                socket.connect(address).await?;
+                for line in batch {
+                    socket.send(syslog_message(labels, line).as_bytes()).await?;
+                }
+            }
+            Sink::SyslogTcp(address) => {
+                // Octet-counting framing (RFC 6587), so lines may contain newlines
+                let mut frames = String::new();
+                for line in batch {
+                    let message = syslog_message(labels, line);
+                    frames.push_str(&format!("{} {}", message.len(), message));
+                }
+                let mut stream = TcpStream::connect(address).await?;
+                stream.write_all(frames.as_bytes()).await?;
+                stream.flush().await?;
+            }
+            Sink::File(path) => {
+                let mut lines = String::new();
+                for line in batch {
+                    let mut entry = json!({
+                        "time": chrono::DateTime::from_timestamp_nanos(line.time_ns).to_rfc3339(),
+                        "container": line.container,
+                        "stream": line.stream,
+                        "line": line.line,
+                    });
+                    for (key, value) in labels {
+                        entry[key] = json!(value);
+                    }
+                    lines.push_str(&entry.to_string());
+                    lines.push('\n');
+                }
+                let mut file = tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await?;
+                file.write_all(lines.as_bytes()).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Loki push API body: one stream per container and output stream.
+fn loki_payload(labels: &HashMap<String, String>, batch: &[LogLine]) -> Vec<u8> {
+    let mut streams: BTreeMap<(&str, &str), Vec<[String; 2]>> = BTreeMap::new();
+    for line in batch {
+        streams
+            .entry((&line.container, line.stream))
+            .or_default()
+            .push([line.time_ns.to_string(), line.line.clone()]);
+    }
+    let streams: Vec<_> = streams
+        .into_iter()
+        .map(|((container, stream), values)| {
+            let mut stream_labels = json!(labels);
+            stream_labels["container"] = json!(container);
+            stream_labels["stream"] = json!(stream);
+            json!({ "stream": stream_labels, "values": values })
+        })
+        .collect();
+    json!({ "streams": streams }).to_string().into_bytes()
+}
+
+/// RFC 5424 message; the labels go into structured data.
+fn syslog_message(labels: &HashMap<String, String>, line: &LogLine) -> String {
+    // Facility user (1); severity error for stderr, informational for stdout
+    let priority = if line.stream == "stderr" { 11 } else { 14 };
+    let time = chrono::DateTime::from_timestamp_nanos(line.time_ns)
+        .to_rfc3339_opts(chrono::SecondsFormat::Micros, true);
+    let hostname = labels
+        .get("node_id")
+        .map(|id| syslog_token(id, 255))
+        .unwrap_or_else(|| "-".to_string());
+    let app_name = syslog_token(&line.container, SYSLOG_APP_NAME_MAX);
+    let structured_data = if labels.is_empty() {
+        "-".to_string()
+    } else {
+        let mut params: Vec<_> = labels.iter().collect();
+        params.sort();
+        let params: String = params
+            .into_iter()
+            .map(|(key, value)| {
+                let value = value
+                    .replace('\\', "\\\\")
+                    .replace('"', "\\\"")
+                    .replace(']', "\\]");
+                format!(" {}=\"{}\"", syslog_token(key, 32), value)
+            })
+            .collect();
+        format!("[{}{}]", SYSLOG_SD_ID, params)
+    };
+    format!(
+        "<{}>1 {} {} {} - {} {} {}",
+        priority, time, hostname, app_name, line.stream, structured_data, line.line
+    )
+}
+
+/// Printable ASCII without spaces (and without `=`, `]` and `"`, which SD names forbid).
+fn syslog_token(value: &str, max: usize) -> String {
+    let token: String = value
+        .chars()
+        .filter(|c| c.is_ascii_graphic() && !matches!(c, '=' | ']' | '"'))
+        .take(max)
+        .collect();
+    if token.is_empty() {
+        "-".to_string()
+    } else {
+        token
+    }
+}
+
+fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
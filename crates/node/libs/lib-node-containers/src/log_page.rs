@@ -0,0 +1,121 @@
+use proto::generated::{LogLine, LogPosition};
+
+/// Collects log lines for one answer: lines up to the `after` cursor are
+/// skipped without counting, the rest fill up to `max_bytes`.
+pub(crate) struct LogPage {
+    after: Option<(i64, u32)>,
+    last: Option<(i64, u32)>,
+    max_bytes: usize,
+    bytes: usize,
+    pub(crate) lines: Vec<LogLine>,
+    pub(crate) truncated: bool,
+}
+
+impl LogPage {
+    pub(crate) fn new(after: Option<&LogPosition>, max_bytes: u64) -> Self {
+        Self {
+            after: after.map(|after| (after.time_nanos, after.line)),
+            last: None,
+            max_bytes: match max_bytes {
+                0 => usize::MAX,
+                max_bytes => max_bytes.try_into().unwrap_or(usize::MAX),
+            },
+            bytes: 0,
+            lines: Vec::new(),
+            truncated: false,
+        }
+    }
+
+    /// Adds a line, unless it was returned before. Returns `false` once the
+    /// page is full.
+    pub(crate) fn push(&mut self, line: LogLine) -> bool {
+        if self.skips(&line.timestamp) {
+            return true;
+        }
+        let size = line.text.len() + line.timestamp.len();
+        // The first line is kept even if it is bigger, so a reader can always make progress
+        if !self.lines.is_empty() && self.bytes + size > self.max_bytes {
+            self.truncated = true;
+            return false;
+        }
+        self.bytes += size;
+        self.lines.push(line);
+        true
+    }
+
+    // Places the line the way the coordinator places its cursors; a line
+    // without a timestamp keeps the previous one's
+    fn skips(&mut self, timestamp: &str) -> bool {
+        let Some(after) = self.after else {
+            return false;
+        };
+        let time_nanos = chrono::DateTime::parse_from_rfc3339(timestamp)
+            .ok()
+            .and_then(|time| time.timestamp_nanos_opt())
+            .unwrap_or_else(|| self.last.map_or(0, |(time_nanos, _)| time_nanos));
+        let line = match self.last {
+            Some((last, line)) if last == time_nanos => line + 1,
+            _ => 1,
+        };
+        self.last = Some((time_nanos, line));
+        if (time_nanos, line) <= after {
+            return true;
+        }
+        self.after = None;
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(timestamp: &str, n: usize) -> LogLine {
+        LogLine {
+            text: format!("line {n} {}\n", "x".repeat(100)),
+            stream: "stdout".to_string(),
+            timestamp: timestamp.to_string(),
+        }
+    }
+
+    // Pages through 100 lines of one second with room for 10 lines a page
+    #[test]
+    fn pages_through_lines_of_the_same_second() {
+        let timestamp = "2024-05-01T10:00:00.000000000Z";
+        let time_nanos = 1_714_557_600_000_000_000;
+        let mut after = None;
+        let mut seen = 0;
+        for _ in 0..10 {
+            let mut page = LogPage::new(after.as_ref(), 1450);
+            for n in 0..100 {
+                if !page.push(line(timestamp, n)) {
+                    break;
+                }
+            }
+            assert_eq!(page.lines.len(), 10);
+            assert!(page.lines[0].text.starts_with(&format!("line {seen} ")));
+            seen += page.lines.len();
+            assert_eq!(page.truncated, seen < 100);
+            after = Some(LogPosition {
+                time_nanos,
+                line: seen as u32,
+            });
+        }
+        assert_eq!(seen, 100);
+    }
+
+    #[test]
+    fn keeps_lines_after_the_cursor_second() {
+        let after = LogPosition {
+            time_nanos: 1_714_557_600_000_000_000,
+            line: 2,
+        };
+        let mut page = LogPage::new(Some(&after), 0);
+        page.push(line("2024-05-01T10:00:00.000000000Z", 0));
+        page.push(line("2024-05-01T10:00:00.000000000Z", 1));
+        page.push(line("2024-05-01T10:00:00.000000000Z", 2));
+        page.push(line("2024-05-01T10:00:01.000000000Z", 3));
+        let texts: Vec<_> = page.lines.iter().map(|line| &line.text[..6]).collect();
+        assert_eq!(texts, ["line 2", "line 3"]);
+    }
+}
@@ -0,0 +1,390 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use bollard::models::{ContainerConfig, ContainerCreateBody, MountPointTypeEnum};
+use bollard::query_parameters::{
+    CommitContainerOptionsBuilder, CreateContainerOptionsBuilder,
+    DownloadFromContainerOptionsBuilder, ImportImageOptionsBuilder, InspectContainerOptionsBuilder,
+    RemoveImageOptions, UploadToContainerOptionsBuilder,
+};
+use bytes::Bytes;
+use futures_util::stream::{Stream, TryStreamExt};
+use proto::generated::{
+    CloneContainer, ContainerAction, ContainerCreated, ExportContainer, ImportContainer,
+    MigrationChunk,
+};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::archive::received_chunks;
+use crate::create::clone_config;
+use crate::delete_container;
+use crate::docker_client::docker;
+use crate::docker_limits::{INSPECT_TIMEOUT, LIFECYCLE_TIMEOUT, limited};
+
+const SECTION_CONFIG: &str = "config";
+const SECTION_IMAGE: &str = "image";
+const SECTION_VOLUME: &str = "volume";
+// Exported containers are committed to this repository, tagged with the request id
+const MIGRATION_REPOSITORY: &str = "docklord-migrate";
+// Docker's small reads are merged into chunks of up to this size
+const CHUNK_SIZE: usize = 1024 * 1024;
+// Committing copies the writable layer, which can be large
+const COMMIT_TIMEOUT: Duration = Duration::from_secs(600);
+// Chunks waiting for Docker per import; the coordinator sends one at a time anyway
+const IMPORT_CHANNEL_CAPACITY: usize = 4;
+// An import whose next chunk does not arrive in time is abandoned
+const IMPORT_IDLE_TIMEOUT: Duration = Duration::from_secs(120);
+const MAX_CONFIG_BYTES: usize = 1024 * 1024;
+
+/// What the target answers a chunk with.
+pub enum ImportReply {
+    Received(ContainerAction),
+    Created(ContainerCreated),
+}
+
+struct Import {
+    name: String,
+    config: Vec<u8>,
+    /// Set once the image is loaded and the container created from it.
+    container_id: Option<String>,
+    warnings: Vec<String>,
+    sink: Option<Sink>,
+    last_chunk: Instant,
+}
+
+// The Docker call the chunks of the current section stream into
+struct Sink {
+    section: String,
+    path: String,
+    chunks: mpsc::Sender<Bytes>,
+    task: JoinHandle<Result<(), Box<dyn Error + Send + Sync>>>,
+}
+
+// Imports between chunks by upload id. Each chunk is handled in its own task,
+// which takes the import out while it works on it.
+static IMPORTS: LazyLock<Mutex<HashMap<String, Import>>> = LazyLock::new(Default::default);
+
+/// Commits the container to an image and sends what recreating it on another
+/// node takes: its config, the image and, if asked, the contents of its named
+/// volumes. The container keeps running; Docker only pauses it for the commit.
+/// Used for POST /api/containers/:id/migrate
+pub async fn export_container(
+    request: &ExportContainer,
+    output_tx: mpsc::Sender<MigrationChunk>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let docker = docker()?;
+    let source = limited(
+        "inspect container",
+        INSPECT_TIMEOUT,
+        docker.inspect_container(
+            &request.container_id,
+            Some(InspectContainerOptionsBuilder::default().build()),
+        ),
+    )
+    .await?;
+    let container_id = source.id.clone().unwrap_or_default();
+    let name = source
+        .name
+        .as_deref()
+        .unwrap_or_default()
+        .trim_start_matches('/')
+        .to_string();
+    let running = source
+        .state
+        .as_ref()
+        .and_then(|state| state.running)
+        .unwrap_or(false);
+    let volumes: Vec<String> = source
+        .mounts
+        .iter()
+        .flatten()
+        .filter(|mount| request.include_volumes && mount.typ == Some(MountPointTypeEnum::VOLUME))
+        .filter_map(|mount| mount.destination.clone())
+        .collect();
+    let source_ref = CloneContainer {
+        container_id: request.container_id.clone(),
+        ..Default::default()
+    };
+    let mut config = clone_config(&source_ref, source)?;
+
+    let image = format!("{}:{}", MIGRATION_REPOSITORY, request.request_id);
+    let options = CommitContainerOptionsBuilder::default()
+        .container(&container_id)
+        .repo(MIGRATION_REPOSITORY)
+        .tag(&request.request_id)
+        .pause(true)
+        .build();
+    limited(
+        "commit container",
+        COMMIT_TIMEOUT,
+        docker.commit_container(options, ContainerConfig::default()),
+    )
+    .await?;
+    info!("Committed container {} to {} to migrate it", name, image);
+    config.image = Some(image.clone());
+
+    let sent = async {
+        let chunk = MigrationChunk {
+            section: SECTION_CONFIG.to_string(),
+            data: serde_json::to_vec(&config)?,
+            name,
+            running,
+            ..Default::default()
+        };
+        if output_tx.send(chunk).await.is_err() {
+            return Ok(());
+        }
+        if !send_section(&output_tx, SECTION_IMAGE, "", docker.export_image(&image)).await? {
+            return Ok(());
+        }
+        for path in volumes {
+            let options = DownloadFromContainerOptionsBuilder::default()
+                .path(&path)
+                .build();
+            let archive = docker.download_from_container(&container_id, Some(options));
+            if !send_section(&output_tx, SECTION_VOLUME, &path, archive).await? {
+                return Ok(());
+            }
+        }
+        Ok::<_, Box<dyn Error + Send + Sync>>(())
+    }
+    .await;
+
+    // The image was read in full or the migration is over; either way the tag goes
+    if let Err(e) = limited(
+        "remove image",
+        LIFECYCLE_TIMEOUT,
+        docker.remove_image(&image, None::<RemoveImageOptions>, None),
+    )
+    .await
+    {
+        warn!("Could not remove migration image {}: {}", image, e);
+    }
+    sent
+}
+
+// Sends a Docker stream as one section. Returns false once nobody listens anymore.
+async fn send_section(
+    output_tx: &mpsc::Sender<MigrationChunk>,
+    section: &str,
+    path: &str,
+    stream: impl Stream<Item = Result<Bytes, bollard::errors::Error>>,
+) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    let send = |data: Vec<u8>| {
+        output_tx.send(MigrationChunk {
+            section: section.to_string(),
+            path: path.to_string(),
+            data,
+            ..Default::default()
+        })
+    };
+    let mut stream = std::pin::pin!(stream);
+    let mut buffer = Vec::with_capacity(CHUNK_SIZE);
+    while let Some(bytes) = stream.try_next().await? {
+        buffer.extend_from_slice(&bytes);
+        if buffer.len() >= CHUNK_SIZE && send(std::mem::take(&mut buffer)).await.is_err() {
+            return Ok(false);
+        }
+    }
+    Ok(buffer.is_empty() || send(buffer).await.is_ok())
+}
+
+/// Hands one chunk of an exported container to the import it belongs to. The
+/// image is loaded as it arrives, the container is created from the config
+/// once the image is in, and volume contents are extracted into it. The last
+/// chunk is answered with the stopped container; a failure removes it again.
+/// Used for POST /api/containers/:id/migrate
+pub async fn import_container(
+    request: ImportContainer,
+) -> Result<ImportReply, Box<dyn Error + Send + Sync>> {
+    let mut import = {
+        let mut imports = IMPORTS.lock().unwrap_or_else(|e| e.into_inner());
+        match imports.remove(&request.upload_id) {
+            Some(import) => import,
+            None => {
+                // Imports the coordinator gave up on have long stopped in Docker
+                imports.retain(|_, import| import.last_chunk.elapsed() < IMPORT_IDLE_TIMEOUT);
+                info!("Importing migrated container {}", request.name);
+                Import {
+                    name: request.name.clone(),
+                    config: Vec::new(),
+                    container_id: None,
+                    warnings: Vec::new(),
+                    sink: None,
+                    last_chunk: Instant::now(),
+                }
+            }
+        }
+    };
+
+    let upload_id = request.upload_id.clone();
+    let done = request.done;
+    if let Err(e) = import.take(request).await {
+        if let Some(sink) = import.sink.take() {
+            sink.task.abort();
+        }
+        if let Some(container_id) = &import.container_id
+            && let Err(e) = delete_container(container_id).await
+        {
+            warn!(
+                "Could not remove half-migrated container {}: {}",
+                container_id, e
+            );
+        }
+        return Err(e);
+    }
+
+    let container_id = import.container_id.clone().unwrap_or_default();
+    if !done {
+        import.last_chunk = Instant::now();
+        IMPORTS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(upload_id, import);
+        return Ok(ImportReply::Received(ContainerAction {
+            request_key: None, // set by the handler
+            container_id,
+            action: "import".to_string(),
+            message: "Chunk received".to_string(),
+        }));
+    }
+    info!("Imported migrated container {}", container_id);
+    Ok(ImportReply::Created(ContainerCreated {
+        request_key: None, // set by the handler
+        container_id,
+        name: import.name,
+        started: false,
+        pulled: false,
+        warnings: import.warnings,
+    }))
+}
+
+impl Import {
+    async fn take(&mut self, chunk: ImportContainer) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if self
+            .sink
+            .as_ref()
+            .is_some_and(|sink| sink.section != chunk.section || sink.path != chunk.path)
+        {
+            self.finish_section().await?;
+        }
+
+        if chunk.section == SECTION_CONFIG {
+            if self.config.len() + chunk.data.len() > MAX_CONFIG_BYTES {
+                return Err("container config too large".into());
+            }
+            self.config.extend_from_slice(&chunk.data);
+        } else if !chunk.data.is_empty() {
+            if self.sink.is_none() {
+                self.sink = Some(self.start_section(&chunk.section, &chunk.path)?);
+            }
+            let sent = match &self.sink {
+                Some(sink) => sink.chunks.send(Bytes::from(chunk.data)).await.is_ok(),
+                None => false,
+            };
+            if !sent {
+                // Docker stopped reading; its error tells why
+                self.finish_section().await?;
+                return Err("Docker stopped reading the migration".into());
+            }
+        }
+
+        if chunk.done {
+            self.finish_section().await?;
+            if self.container_id.is_none() {
+                return Err("the export carried no image".into());
+            }
+        }
+        Ok(())
+    }
+
+    fn start_section(
+        &self,
+        section: &str,
+        path: &str,
+    ) -> Result<Sink, Box<dyn Error + Send + Sync>> {
+        let docker = docker()?;
+        let (chunks, rx) = mpsc::channel::<Bytes>(IMPORT_CHANNEL_CAPACITY);
+        let body = bollard::body_try_stream(received_chunks(rx, IMPORT_IDLE_TIMEOUT));
+
+        let task = match section {
+            SECTION_IMAGE if self.container_id.is_none() => tokio::spawn(async move {
+                let options = ImportImageOptionsBuilder::default().quiet(true).build();
+                let mut progress = docker.import_image(options, body, None);
+                while let Some(info) = progress.try_next().await? {
+                    if let Some(error) = info.error {
+                        return Err(error.into());
+                    }
+                }
+                Ok(())
+            }),
+            SECTION_VOLUME => {
+                let container_id = self
+                    .container_id
+                    .clone()
+                    .ok_or("volume contents arrived before the image")?;
+                // The archive holds the mount point's directory itself
+                let parent = match path.trim_end_matches('/').rsplit_once('/') {
+                    Some(("", _)) => "/",
+                    Some((parent, _)) => parent,
+                    None => return Err(format!("volume path {} is not absolute", path).into()),
+                };
+                let options = UploadToContainerOptionsBuilder::default()
+                    .path(parent)
+                    .build();
+                tokio::spawn(async move {
+                    docker
+                        .upload_to_container(&container_id, Some(options), body)
+                        .await?;
+                    Ok(())
+                })
+            }
+            _ => return Err(format!("unexpected migration section {:?}", section).into()),
+        };
+        Ok(Sink {
+            section: section.to_string(),
+            path: path.to_string(),
+            chunks,
+            task,
+        })
+    }
+
+    // Waits for Docker to take in the current section; the container is
+    // created once its image is loaded
+    async fn finish_section(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let Some(sink) = self.sink.take() else {
+            return Ok(());
+        };
+        // Closing the channel ends the body for Docker
+        drop(sink.chunks);
+        sink.task.await??;
+        if sink.section != SECTION_IMAGE {
+            return Ok(());
+        }
+
+        if self.config.is_empty() {
+            return Err("the export carried no container config".into());
+        }
+        let config: ContainerCreateBody = serde_json::from_slice(&self.config)?;
+        let docker = docker()?;
+        let options = (!self.name.is_empty()).then(|| {
+            CreateContainerOptionsBuilder::default()
+                .name(&self.name)
+                .build()
+        });
+        let created = limited(
+            "create container",
+            LIFECYCLE_TIMEOUT,
+            docker.create_container(options, config),
+        )
+        .await?;
+        info!("Created migrated container {}", created.id);
+        self.container_id = Some(created.id);
+        self.warnings = created.warnings;
+        Ok(())
+    }
+}
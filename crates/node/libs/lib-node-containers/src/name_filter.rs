@@ -0,0 +1,37 @@
+use regex::{Regex, RegexBuilder};
+
+// Keeps user-supplied patterns from compiling into huge automata
+const NAME_REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+/// Container name filter for list requests: a substring and/or a regex, both optional.
+#[derive(Debug, Default)]
+pub struct NameFilter {
+    query: Option<String>,
+    regex: Option<Regex>,
+}
+
+impl NameFilter {
+    /// Empty strings mean "no filter". Fails if `name_regex` is not a valid pattern.
+    pub fn new(name_query: &str, name_regex: &str) -> Result<Self, regex::Error> {
+        let query = (!name_query.is_empty()).then(|| name_query.to_lowercase());
+        let regex = if name_regex.is_empty() {
+            None
+        } else {
+            Some(
+                RegexBuilder::new(name_regex)
+                    .size_limit(NAME_REGEX_SIZE_LIMIT)
+                    .build()?,
+            )
+        };
+        Ok(Self { query, regex })
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        let query_matches = self
+            .query
+            .as_ref()
+            .is_none_or(|query| name.to_lowercase().contains(query));
+        let regex_matches = self.regex.as_ref().is_none_or(|regex| regex.is_match(name));
+        query_matches && regex_matches
+    }
+}
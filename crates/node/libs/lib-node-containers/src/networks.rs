@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+use std::error::Error;
+
+use bollard::models::{Ipam, IpamConfig, Network, NetworkCreateRequest};
+use bollard::query_parameters::{
+    InspectNetworkOptions, ListContainersOptionsBuilder, ListNetworksOptions,
+};
+use proto::generated::{
+    CreateNetwork, InspectNetwork, ListNetworks, NetworkCreated, NetworkEndpoint, NetworkInfo,
+    NetworkList, NetworkRemoved, NetworkSubnet, RemoveNetwork,
+};
+use tracing::info;
+
+use crate::docker_client::docker;
+use crate::docker_limits::{INSPECT_TIMEOUT, LIFECYCLE_TIMEOUT, LIST_TIMEOUT, limited};
+use crate::epoch_secs;
+
+/// Networks on the node with the containers attached to each, by name. Used
+/// for GET /api/networks
+pub async fn list_networks(
+    request: &ListNetworks,
+) -> Result<NetworkList, Box<dyn Error + Send + Sync>> {
+    let docker = docker()?;
+    let networks = limited(
+        "list networks",
+        LIST_TIMEOUT,
+        docker.list_networks(None::<ListNetworksOptions>),
+    )
+    .await?;
+    // Docker leaves the containers out of network lists, so take them from the containers
+    let containers = limited(
+        "list containers",
+        LIST_TIMEOUT,
+        docker.list_containers(Some(
+            ListContainersOptionsBuilder::default().all(true).build(),
+        )),
+    )
+    .await?;
+    let mut endpoints: HashMap<String, Vec<NetworkEndpoint>> = HashMap::new();
+    for container in containers {
+        let name = container
+            .names
+            .as_ref()
+            .and_then(|names| names.first())
+            .map(|name| name.trim_start_matches('/').to_string())
+            .unwrap_or_default();
+        let networks = container
+            .network_settings
+            .and_then(|settings| settings.networks)
+            .unwrap_or_default();
+        for settings in networks.into_values() {
+            let Some(network_id) = settings.network_id else {
+                continue;
+            };
+            let ipv4_address = match (settings.ip_address, settings.ip_prefix_len) {
+                (Some(ip), Some(len)) if !ip.is_empty() => format!("{}/{}", ip, len),
+                (ip, _) => ip.unwrap_or_default(),
+            };
+            let ipv6_address = match (
+                settings.global_ipv6_address,
+                settings.global_ipv6_prefix_len,
+            ) {
+                (Some(ip), Some(len)) if !ip.is_empty() => format!("{}/{}", ip, len),
+                (ip, _) => ip.unwrap_or_default(),
+            };
+            endpoints
+                .entry(network_id)
+                .or_default()
+                .push(NetworkEndpoint {
+                    container_id: container.id.clone().unwrap_or_default(),
+                    name: name.clone(),
+                    ipv4_address,
+                    ipv6_address,
+                    mac_address: settings.mac_address.unwrap_or_default(),
+                });
+        }
+    }
+
+    let mut networks: Vec<NetworkInfo> = networks
+        .into_iter()
+        .filter(|network| {
+            request.name.is_empty()
+                || network
+                    .name
+                    .as_deref()
+                    .is_some_and(|name| name.contains(&request.name))
+        })
+        .map(|network| {
+            let containers = network
+                .id
+                .as_ref()
+                .and_then(|id| endpoints.remove(id))
+                .unwrap_or_default();
+            network_info(network, containers)
+        })
+        .collect();
+    networks.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(NetworkList {
+        request_key: None, // will be set by the handler
+        networks,
+    })
+}
+
+/// One network with its attached containers. Used for GET /api/networks/:network
+pub async fn inspect_network(
+    request: &InspectNetwork,
+) -> Result<NetworkList, Box<dyn Error + Send + Sync>> {
+    let docker = docker()?;
+    let mut network = limited(
+        "inspect network",
+        INSPECT_TIMEOUT,
+        docker.inspect_network(&request.network, None::<InspectNetworkOptions>),
+    )
+    .await?;
+    let mut containers: Vec<NetworkEndpoint> = network
+        .containers
+        .take()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(container_id, endpoint)| NetworkEndpoint {
+            container_id,
+            name: endpoint.name.unwrap_or_default(),
+            ipv4_address: endpoint.ipv4_address.unwrap_or_default(),
+            ipv6_address: endpoint.ipv6_address.unwrap_or_default(),
+            mac_address: endpoint.mac_address.unwrap_or_default(),
+        })
+        .collect();
+    containers.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(NetworkList {
+        request_key: None, // will be set by the handler
+        networks: vec![network_info(network, containers)],
+    })
+}
+
+/// Creates a network. Used for POST /api/networks
+pub async fn create_network(
+    request: &CreateNetwork,
+) -> Result<NetworkCreated, Box<dyn Error + Send + Sync>> {
+    let docker = docker()?;
+    let ipam = (!request.subnet.is_empty()).then(|| Ipam {
+        config: Some(vec![IpamConfig {
+            subnet: Some(request.subnet.clone()),
+            gateway: (!request.gateway.is_empty()).then(|| request.gateway.clone()),
+            ..Default::default()
+        }]),
+        ..Default::default()
+    });
+    let config = NetworkCreateRequest {
+        name: request.name.clone(),
+        driver: (!request.driver.is_empty()).then(|| request.driver.clone()),
+        internal: Some(request.internal),
+        attachable: Some(request.attachable),
+        enable_ipv6: Some(request.enable_ipv6),
+        ipam,
+        labels: Some(request.labels.clone()),
+        options: Some(request.options.clone()),
+        ..Default::default()
+    };
+    let created = limited(
+        "create network",
+        LIFECYCLE_TIMEOUT,
+        docker.create_network(config),
+    )
+    .await?;
+    info!("Created network {} ({})", request.name, created.id);
+
+    Ok(NetworkCreated {
+        request_key: None, // will be set by the handler
+        id: created.id,
+        name: request.name.clone(),
+        warning: created.warning,
+    })
+}
+
+/// Removes a network; Docker refuses while containers are attached. Used for
+/// DELETE /api/networks/:network
+pub async fn remove_network(
+    request: &RemoveNetwork,
+) -> Result<NetworkRemoved, Box<dyn Error + Send + Sync>> {
+    let docker = docker()?;
+    limited(
+        "remove network",
+        LIFECYCLE_TIMEOUT,
+        docker.remove_network(&request.network),
+    )
+    .await?;
+    info!("Removed network {}", request.network);
+
+    Ok(NetworkRemoved {
+        request_key: None, // will be set by the handler
+        network: request.network.clone(),
+    })
+}
+
+fn network_info(network: Network, containers: Vec<NetworkEndpoint>) -> NetworkInfo {
+    let subnets = network
+        .ipam
+        .and_then(|ipam| ipam.config)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|config| NetworkSubnet {
+            subnet: config.subnet.unwrap_or_default(),
+            gateway: config.gateway.unwrap_or_default(),
+        })
+        .collect();
+    NetworkInfo {
+        id: network.id.unwrap_or_default(),
+        name: network.name.unwrap_or_default(),
+        driver: network.driver.unwrap_or_default(),
+        scope: network.scope.unwrap_or_default(),
+        internal: network.internal.unwrap_or(false),
+        attachable: network.attachable.unwrap_or(false),
+        enable_ipv6: network.enable_ipv6.unwrap_or(false),
+        created: epoch_secs(network.created.as_deref()),
+        subnets,
+        labels: network.labels.unwrap_or_default(),
+        options: network.options.unwrap_or_default(),
+        containers,
+    }
+}
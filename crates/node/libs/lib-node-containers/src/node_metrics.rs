@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::error::Error;
+
+use bollard::query_parameters::{InspectContainerOptionsBuilder, ListContainersOptionsBuilder};
+use futures_util::future::join_all;
+use proto::generated::{NodeMetrics, PressureLine, PressureStats};
+
+use crate::docker_client::docker;
+use crate::docker_limits::{INSPECT_TIMEOUT, LIST_TIMEOUT, limited};
+
+const PSI_DIR: &str = "/proc/pressure";
+const MEMINFO: &str = "/proc/meminfo";
+
+/// Collects host-level metrics. PSI is only present on cgroup v2 kernels built
+/// with CONFIG_PSI; elsewhere `psi_available` is false and the pressure fields stay empty.
+/// Used for /api/nodes/:node_id/metrics
+pub async fn get_node_metrics() -> Result<NodeMetrics, Box<dyn Error + Send + Sync>> {
+    let cpu = read_pressure("cpu").await;
+    let memory = read_pressure("memory").await;
+    let io = read_pressure("io").await;
+    let (memory_total, memory_available) = tokio::fs::read_to_string(MEMINFO)
+        .await
+        .map(|content| parse_meminfo(&content))
+        .unwrap_or_default();
+    let memory_reserved = reserved_memory().await?;
+
+    Ok(NodeMetrics {
+        request_key: None, // will be set by the handler
+        psi_available: cpu.is_some() || memory.is_some() || io.is_some(),
+        cpu_pressure: cpu,
+        memory_pressure: memory,
+        io_pressure: io,
+        memory_total,
+        memory_available,
+        memory_reserved,
+    })
+}
+
+/// Memory limits of the running containers, summed: what they may still grow
+/// into even when the host has it free now. Unlimited containers count 0.
+async fn reserved_memory() -> Result<u64, Box<dyn Error + Send + Sync>> {
+    let docker = docker()?;
+    let filters = HashMap::from([("status", vec!["running"])]);
+    let running = limited(
+        "list containers",
+        LIST_TIMEOUT,
+        docker.list_containers(Some(
+            ListContainersOptionsBuilder::default()
+                .filters(&filters)
+                .build(),
+        )),
+    )
+    .await?;
+
+    let limits = running
+        .into_iter()
+        .filter_map(|container| container.id)
+        .map(|id| {
+            let docker = docker.clone();
+            async move {
+                limited(
+                    "inspect container",
+                    INSPECT_TIMEOUT,
+                    docker.inspect_container(
+                        &id,
+                        Some(InspectContainerOptionsBuilder::default().build()),
+                    ),
+                )
+                .await
+                .ok()
+                .and_then(|info| info.host_config?.memory)
+                .map_or(0, |limit| u64::try_from(limit).unwrap_or(0))
+            }
+        });
+    Ok(join_all(limits).await.into_iter().sum())
+}
+
+/// Total and available bytes from `/proc/meminfo`, where lines look like
+/// `MemAvailable:    8030448 kB`.
+fn parse_meminfo(content: &str) -> (u64, u64) {
+    let field = |name: &str| {
+        content
+            .lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+            .and_then(|value| value.split_whitespace().next()?.parse::<u64>().ok())
+            .map_or(0, |kib| kib * 1024)
+    };
+    (field("MemTotal"), field("MemAvailable"))
+}
+
+async fn read_pressure(resource: &str) -> Option<PressureStats> {
+    let content = tokio::fs::read_to_string(format!("{}/{}", PSI_DIR, resource))
+        .await
+        .ok()?;
+    Some(parse_pressure(&content))
+}
+
+/// Parses the `/proc/pressure/<resource>` format:
+/// `some avg10=0.00 avg60=0.00 avg300=0.00 total=0`
+fn parse_pressure(content: &str) -> PressureStats {
+    let mut stats = PressureStats::default();
+    for line in content.lines() {
+        let mut parts = line.split_whitespace();
+        let kind = parts.next();
+        let mut pressure = PressureLine::default();
+        for field in parts {
+            match field.split_once('=') {
+                Some(("avg10", v)) => pressure.avg10 = v.parse().unwrap_or(0.0),
+                Some(("avg60", v)) => pressure.avg60 = v.parse().unwrap_or(0.0),
+                Some(("avg300", v)) => pressure.avg300 = v.parse().unwrap_or(0.0),
+                Some(("total", v)) => pressure.total = v.parse().unwrap_or(0),
+                _ => {}
+            }
+        }
+        match kind {
+            Some("some") => stats.some = Some(pressure),
+            Some("full") => stats.full = Some(pressure),
+            _ => {}
+        }
+    }
+    stats
+}
@@ -0,0 +1,126 @@
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use proto::generated::InjectedSecret;
+use tracing::{info, warn};
+
+/// Label naming a container's secret file directory, so it is removed with the container.
+pub const SECRETS_LABEL: &str = "docklord.secrets";
+
+const DEFAULT_SECRETS_DIR: &str = "/run/docklord/secrets";
+
+static SECRETS_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Sets where secret files are written before being bind-mounted into
+/// containers. It must be the same path on the Docker host, so a node running
+/// in a container needs it mounted at the identical path. Only the first call
+/// counts; without it `/run/docklord/secrets` is used.
+pub fn configure_secrets_dir(dir: PathBuf) {
+    if SECRETS_DIR.set(dir).is_err() {
+        warn!("Secrets directory already configured, keeping the first one");
+    }
+}
+
+fn secrets_dir() -> &'static Path {
+    SECRETS_DIR.get_or_init(|| PathBuf::from(DEFAULT_SECRETS_DIR))
+}
+
+/// How a new container receives its secrets.
+#[derive(Debug, Default)]
+pub struct ContainerSecrets {
+    /// `NAME=value` entries for the container environment.
+    pub env: Vec<String>,
+    /// Read-only `host:container` bind mounts of the secret files.
+    pub binds: Vec<String>,
+    /// Value for [`SECRETS_LABEL`] when files were written.
+    pub label: Option<String>,
+}
+
+/// Collects env secrets and writes file secrets into a fresh directory that
+/// only the node's user can enter; the files themselves stay readable for
+/// whatever user the container runs as. Values never leave the node again:
+/// inspect and list endpoints do not report container environments or mounts.
+pub async fn prepare_secrets(
+    secrets: &[InjectedSecret],
+) -> Result<ContainerSecrets, Box<dyn Error + Send + Sync>> {
+    let mut prepared = ContainerSecrets::default();
+    let mut dir_name = None;
+
+    for (index, secret) in secrets.iter().enumerate() {
+        if !secret.env.is_empty() {
+            let value = std::str::from_utf8(&secret.value)
+                .map_err(|_| format!("secret '{}' is not UTF-8 text", secret.name))?;
+            prepared.env.push(format!("{}={}", secret.env, value));
+        }
+        if secret.file_path.is_empty() {
+            continue;
+        }
+        let name = match &dir_name {
+            Some(name) => name,
+            None => {
+                let name = unique_dir_name();
+                create_private_dir(&secrets_dir().join(&name)).await?;
+                dir_name.insert(name)
+            }
+        };
+        let host_path = secrets_dir()
+            .join(name)
+            .join(format!("{}-{}", index, secret.name));
+        write_private_file(&host_path, &secret.value).await?;
+        prepared
+            .binds
+            .push(format!("{}:{}:ro", host_path.display(), secret.file_path));
+    }
+
+    prepared.label = dir_name;
+    Ok(prepared)
+}
+
+/// Removes the secret files of a container once it is gone.
+pub async fn remove_secret_files(label: &str) {
+    // The label comes from Docker; never follow it outside the secrets directory
+    if label.is_empty() || label.contains(['/', '\\']) || label.starts_with('.') {
+        warn!("Ignoring unexpected {} label {:?}", SECRETS_LABEL, label);
+        return;
+    }
+    let path = secrets_dir().join(label);
+    match tokio::fs::remove_dir_all(&path).await {
+        Ok(()) => info!("Removed secret files in {:?}", path),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => warn!("Failed to remove secret files in {:?}: {}", path, e),
+    }
+}
+
+fn unique_dir_name() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    format!(
+        "{:x}-{:x}-{:x}",
+        nanos,
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+async fn create_private_dir(path: &Path) -> std::io::Result<()> {
+    let mut builder = tokio::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    builder.mode(0o700);
+    builder.create(path).await
+}
+
+async fn write_private_file(path: &Path, value: &[u8]) -> std::io::Result<()> {
+    let mut options = tokio::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o444);
+    let mut file = options.open(path).await?;
+    tokio::io::AsyncWriteExt::write_all(&mut file, value).await
+}
@@ -0,0 +1,227 @@
+use std::error::Error;
+
+use bollard::Docker;
+use bollard::exec::{StartExecOptions, StartExecResults};
+use bollard::query_parameters::{
+    AttachContainerOptionsBuilder, InspectContainerOptionsBuilder,
+    ResizeContainerTTYOptionsBuilder, ResizeExecOptionsBuilder,
+};
+use bollard::secret::{ContainerStateStatusEnum, ExecConfig};
+use futures_util::StreamExt;
+use proto::generated::ExecContainer;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tracing::error;
+
+use crate::docker_client::docker;
+
+// Docker's default detach sequence (ctrl-p ctrl-q). Sending it ends the attach
+// cleanly, without closing stdin, so the container keeps running.
+const DETACH_KEYS: &str = "ctrl-p,ctrl-q";
+const DETACH_SEQUENCE: &[u8] = &[0x10, 0x11];
+
+/// Messages from the coordinator to an open interactive session.
+#[derive(Debug)]
+pub enum SessionControl {
+    Input(Vec<u8>),
+    /// New terminal size, so full-screen programs redraw to fit.
+    Resize {
+        cols: u32,
+        rows: u32,
+    },
+    /// Detach; the container is left running.
+    Close,
+}
+
+/// Attaches to stdin/stdout of PID 1 of a running container started with a TTY.
+/// Output is sent to `output_tx` until the process exits, the session is closed
+/// or `control_rx` is dropped.
+pub async fn attach_container(
+    container_id: &str,
+    cols: u32,
+    rows: u32,
+    mut control_rx: mpsc::Receiver<SessionControl>,
+    output_tx: mpsc::Sender<Vec<u8>>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let docker = docker()?;
+    let info = docker
+        .inspect_container(
+            container_id,
+            Some(InspectContainerOptionsBuilder::default().build()),
+        )
+        .await?;
+
+    let running = info
+        .state
+        .as_ref()
+        .and_then(|s| s.status)
+        .is_some_and(|s| s == ContainerStateStatusEnum::RUNNING);
+    if !running {
+        return Err("container is not running".into());
+    }
+    // Without a TTY the output is multiplexed and there is no terminal to drive
+    if !info.config.as_ref().and_then(|c| c.tty).unwrap_or(false) {
+        return Err("container was not started with a TTY".into());
+    }
+
+    let options = AttachContainerOptionsBuilder::default()
+        .stream(true)
+        .stdin(true)
+        .stdout(true)
+        .stderr(true)
+        .detach_keys(DETACH_KEYS)
+        .build();
+    let mut attached = docker.attach_container(container_id, Some(options)).await?;
+
+    resize_tty(&docker, container_id, cols, rows).await?;
+
+    loop {
+        tokio::select! {
+            output = attached.output.next() => match output {
+                Some(Ok(output)) => {
+                    if output_tx.send(output.into_bytes().to_vec()).await.is_err() {
+                        break;
+                    }
+                }
+                Some(Err(e)) => return Err(e.into()),
+                None => break,
+            },
+            control = control_rx.recv() => match control {
+                Some(SessionControl::Input(data)) => {
+                    attached.input.write_all(&data).await?;
+                    attached.input.flush().await?;
+                }
+                Some(SessionControl::Resize { cols, rows }) => {
+                    // A failed resize only affects rendering, keep the session
+                    if let Err(e) = resize_tty(&docker, container_id, cols, rows).await {
+                        error!("Failed to resize TTY of {}: {}", container_id, e);
+                    }
+                }
+                Some(SessionControl::Close) | None => {
+                    attached.input.write_all(DETACH_SEQUENCE).await?;
+                    attached.input.flush().await?;
+                    break;
+                }
+            },
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs a command in a running container. Output (stdout and stderr) is sent to
+/// `output_tx` until the process exits or the session is closed; closing drops
+/// the connection, which ends stdin of the process. Returns the exit code, or
+/// `None` when the session was closed before the process exited.
+pub async fn exec_container(
+    request: &ExecContainer,
+    mut control_rx: mpsc::Receiver<SessionControl>,
+    output_tx: mpsc::Sender<Vec<u8>>,
+) -> Result<Option<i64>, Box<dyn Error + Send + Sync>> {
+    if request.command.is_empty() {
+        return Err("no command to run".into());
+    }
+    let docker = docker()?;
+    let non_empty = |value: &str| (!value.is_empty()).then(|| value.to_string());
+    let exec = docker
+        .create_exec(
+            &request.container_id,
+            ExecConfig {
+                attach_stdin: Some(true),
+                attach_stdout: Some(true),
+                attach_stderr: Some(true),
+                tty: Some(request.tty),
+                cmd: Some(request.command.clone()),
+                env: (!request.env.is_empty()).then(|| request.env.clone()),
+                user: non_empty(&request.user),
+                working_dir: non_empty(&request.working_dir),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+    let started = docker
+        .start_exec(
+            &exec.id,
+            Some(StartExecOptions {
+                tty: request.tty,
+                ..Default::default()
+            }),
+        )
+        .await?;
+    let StartExecResults::Attached {
+        mut output,
+        mut input,
+    } = started
+    else {
+        return Err("exec did not attach".into());
+    };
+
+    if request.tty {
+        resize_exec(&docker, &exec.id, request.cols, request.rows).await?;
+    }
+
+    loop {
+        tokio::select! {
+            chunk = output.next() => match chunk {
+                Some(Ok(chunk)) => {
+                    if output_tx.send(chunk.into_bytes().to_vec()).await.is_err() {
+                        return Ok(None);
+                    }
+                }
+                Some(Err(e)) => return Err(e.into()),
+                None => break,
+            },
+            control = control_rx.recv() => match control {
+                Some(SessionControl::Input(data)) => {
+                    input.write_all(&data).await?;
+                    input.flush().await?;
+                }
+                Some(SessionControl::Resize { cols, rows }) => {
+                    // A failed resize only affects rendering, keep the session
+                    if let Err(e) = resize_exec(&docker, &exec.id, cols, rows).await {
+                        error!("Failed to resize exec TTY in {}: {}", request.container_id, e);
+                    }
+                }
+                Some(SessionControl::Close) | None => return Ok(None),
+            },
+        }
+    }
+
+    let inspected = docker.inspect_exec(&exec.id).await?;
+    Ok(inspected.exit_code)
+}
+
+async fn resize_exec(
+    docker: &Docker,
+    exec_id: &str,
+    cols: u32,
+    rows: u32,
+) -> Result<(), bollard::errors::Error> {
+    // A zero size keeps the current one
+    if cols == 0 || rows == 0 {
+        return Ok(());
+    }
+    let options = ResizeExecOptionsBuilder::default()
+        .w(cols.min(i32::MAX as u32) as i32)
+        .h(rows.min(i32::MAX as u32) as i32)
+        .build();
+    docker.resize_exec(exec_id, options).await
+}
+
+async fn resize_tty(
+    docker: &Docker,
+    container_id: &str,
+    cols: u32,
+    rows: u32,
+) -> Result<(), bollard::errors::Error> {
+    // A zero size keeps the current one
+    if cols == 0 || rows == 0 {
+        return Ok(());
+    }
+    let options = ResizeContainerTTYOptionsBuilder::default()
+        .w(cols.min(i32::MAX as u32) as i32)
+        .h(rows.min(i32::MAX as u32) as i32)
+        .build();
+    docker.resize_container_tty(container_id, options).await
+}
@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::time::Duration;
+
+use bollard::models::ContainerSummaryStateEnum;
+use bollard::query_parameters::{
+    DataUsageOptions, PruneBuildOptionsBuilder, PruneContainersOptionsBuilder,
+    PruneImagesOptionsBuilder, PruneNetworksOptionsBuilder, PruneVolumesOptionsBuilder,
+};
+use proto::generated::{DiskUsageSummary, PruneSystem, SystemDiskUsage, SystemPruned};
+use tracing::info;
+
+use crate::docker_client::docker;
+use crate::docker_limits::limited;
+
+// Docker sizes every layer and volume for `system df`, which takes a while on busy hosts
+const DF_TIMEOUT: Duration = Duration::from_secs(120);
+// Per kind; Docker deletes everything before answering
+const PRUNE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Objects, bytes and reclaimable bytes per kind, as `docker system df` shows
+/// them. Used for GET /api/system/df
+pub async fn get_system_disk_usage() -> Result<SystemDiskUsage, Box<dyn Error + Send + Sync>> {
+    let docker = docker()?;
+    let usage = limited("system df", DF_TIMEOUT, docker.df(None::<DataUsageOptions>)).await?;
+
+    let images = usage.images.unwrap_or_default();
+    // Layers shared with used images stay; this is how the Docker CLI counts it
+    let used_image_bytes: i64 = images
+        .iter()
+        .filter(|image| image.containers > 0 && image.size >= 0 && image.shared_size >= 0)
+        .map(|image| image.size - image.shared_size)
+        .sum();
+    let layers_size = usage.layers_size.unwrap_or(0);
+    let images = DiskUsageSummary {
+        total: images.len() as i64,
+        active: images.iter().filter(|image| image.containers > 0).count() as i64,
+        size: layers_size,
+        reclaimable: (layers_size - used_image_bytes).max(0),
+    };
+
+    let containers = usage.containers.unwrap_or_default();
+    let is_running = |state: Option<ContainerSummaryStateEnum>| {
+        matches!(
+            state,
+            Some(
+                ContainerSummaryStateEnum::RUNNING
+                    | ContainerSummaryStateEnum::PAUSED
+                    | ContainerSummaryStateEnum::RESTARTING
+            )
+        )
+    };
+    let containers = DiskUsageSummary {
+        total: containers.len() as i64,
+        active: containers
+            .iter()
+            .filter(|container| is_running(container.state))
+            .count() as i64,
+        size: containers
+            .iter()
+            .map(|container| container.size_rw.unwrap_or(0))
+            .sum(),
+        reclaimable: containers
+            .iter()
+            .filter(|container| !is_running(container.state))
+            .map(|container| container.size_rw.unwrap_or(0))
+            .sum(),
+    };
+
+    let volumes = usage.volumes.unwrap_or_default();
+    // -1 when Docker could not size a volume
+    let sized: Vec<(i64, i64)> = volumes
+        .iter()
+        .filter_map(|volume| volume.usage_data.as_ref())
+        .map(|data| (data.size.max(0), data.ref_count))
+        .collect();
+    let volumes = DiskUsageSummary {
+        total: volumes.len() as i64,
+        active: sized.iter().filter(|(_, refs)| *refs > 0).count() as i64,
+        size: sized.iter().map(|(size, _)| size).sum(),
+        reclaimable: sized
+            .iter()
+            .filter(|(_, refs)| *refs == 0)
+            .map(|(size, _)| size)
+            .sum(),
+    };
+
+    let build_cache = usage.build_cache.unwrap_or_default();
+    let build_cache = DiskUsageSummary {
+        total: build_cache.len() as i64,
+        active: build_cache
+            .iter()
+            .filter(|cache| cache.in_use == Some(true))
+            .count() as i64,
+        size: build_cache
+            .iter()
+            .map(|cache| cache.size.unwrap_or(0))
+            .sum(),
+        reclaimable: build_cache
+            .iter()
+            .filter(|cache| cache.in_use != Some(true) && cache.shared != Some(true))
+            .map(|cache| cache.size.unwrap_or(0))
+            .sum(),
+    };
+
+    Ok(SystemDiskUsage {
+        request_key: None, // will be set by the handler
+        images: Some(images),
+        containers: Some(containers),
+        volumes: Some(volumes),
+        build_cache: Some(build_cache),
+    })
+}
+
+/// Deletes the unused objects of each requested kind, like `docker system
+/// prune`. Used for POST /api/system/prune
+pub async fn prune_system(
+    request: &PruneSystem,
+) -> Result<SystemPruned, Box<dyn Error + Send + Sync>> {
+    let docker = docker()?;
+    let mut filters: HashMap<&str, Vec<&str>> = HashMap::new();
+    for label in &request.labels {
+        match label.strip_prefix('!') {
+            Some(label) => filters.entry("label!").or_default().push(label),
+            None => filters.entry("label").or_default().push(label),
+        }
+    }
+    // Volumes cannot be filtered by age
+    let volume_filters = filters.clone();
+    if !request.until.is_empty() {
+        filters.insert("until", vec![request.until.as_str()]);
+    }
+
+    let mut pruned = SystemPruned {
+        request_key: None, // will be set by the handler
+        ..Default::default()
+    };
+    // Containers first, so the images, networks and volumes they used become unused
+    if request.containers {
+        let result = limited(
+            "prune containers",
+            PRUNE_TIMEOUT,
+            docker.prune_containers(Some(
+                PruneContainersOptionsBuilder::default()
+                    .filters(&filters)
+                    .build(),
+            )),
+        )
+        .await?;
+        pruned.containers_deleted = result.containers_deleted.unwrap_or_default();
+        pruned.space_reclaimed += result.space_reclaimed.unwrap_or(0);
+    }
+    if request.images {
+        let mut image_filters = filters.clone();
+        image_filters.insert(
+            "dangling",
+            vec![if request.all_images { "false" } else { "true" }],
+        );
+        let result = limited(
+            "prune images",
+            PRUNE_TIMEOUT,
+            docker.prune_images(Some(
+                PruneImagesOptionsBuilder::default()
+                    .filters(&image_filters)
+                    .build(),
+            )),
+        )
+        .await?;
+        for item in result.images_deleted.unwrap_or_default() {
+            pruned.images_untagged.extend(item.untagged);
+            pruned.images_deleted.extend(item.deleted);
+        }
+        pruned.space_reclaimed += result.space_reclaimed.unwrap_or(0);
+    }
+    if request.networks {
+        let result = limited(
+            "prune networks",
+            PRUNE_TIMEOUT,
+            docker.prune_networks(Some(
+                PruneNetworksOptionsBuilder::default()
+                    .filters(&filters)
+                    .build(),
+            )),
+        )
+        .await?;
+        pruned.networks_deleted = result.networks_deleted.unwrap_or_default();
+    }
+    if request.volumes {
+        let mut volume_filters = volume_filters;
+        if request.all_volumes {
+            volume_filters.insert("all", vec!["true"]);
+        }
+        let result = limited(
+            "prune volumes",
+            PRUNE_TIMEOUT,
+            docker.prune_volumes(Some(
+                PruneVolumesOptionsBuilder::default()
+                    .filters(&volume_filters)
+                    .build(),
+            )),
+        )
+        .await?;
+        pruned.volumes_deleted = result.volumes_deleted.unwrap_or_default();
+        pruned.space_reclaimed += result.space_reclaimed.unwrap_or(0);
+    }
+    if request.build_cache {
+        let mut options = PruneBuildOptionsBuilder::default();
+        if !request.until.is_empty() {
+            options = options.filters(&HashMap::from([("until", vec![request.until.as_str()])]));
+        }
+        let result = limited(
+            "prune build cache",
+            PRUNE_TIMEOUT,
+            docker.prune_build(Some(options.build())),
+        )
+        .await?;
+        pruned.build_cache_deleted = result.caches_deleted.unwrap_or_default();
+        pruned.space_reclaimed += result.space_reclaimed.unwrap_or(0);
+    }
+    info!(
+        "Pruned {} containers, {} images, {} networks, {} volumes and {} build cache records, reclaimed {} bytes",
+        pruned.containers_deleted.len(),
+        pruned.images_deleted.len(),
+        pruned.networks_deleted.len(),
+        pruned.volumes_deleted.len(),
+        pruned.build_cache_deleted.len(),
+        pruned.space_reclaimed
+    );
+
+    Ok(pruned)
+}
@@ -0,0 +1,60 @@
+use std::error::Error;
+
+use proto::generated::SystemInfo;
+
+use crate::docker_client::docker;
+use crate::docker_limits::{INSPECT_TIMEOUT, limited};
+
+/// The engine's version, host details and totals from Docker's `info` and
+/// `version`. Used for GET /api/system/info
+pub async fn get_system_info() -> Result<SystemInfo, Box<dyn Error + Send + Sync>> {
+    let docker = docker()?;
+    let (info, version) = tokio::try_join!(
+        limited("docker info", INSPECT_TIMEOUT, docker.info()),
+        limited("docker version", INSPECT_TIMEOUT, docker.version()),
+    )?;
+
+    Ok(SystemInfo {
+        request_key: None, // will be set by the handler
+        docker_version: version.version.or(info.server_version).unwrap_or_default(),
+        api_version: version.api_version.unwrap_or_default(),
+        min_api_version: version.min_api_version.unwrap_or_default(),
+        git_commit: version.git_commit.unwrap_or_default(),
+        go_version: version.go_version.unwrap_or_default(),
+        os: info.operating_system.unwrap_or_default(),
+        os_type: info.os_type.or(version.os).unwrap_or_default(),
+        os_version: info.os_version.unwrap_or_default(),
+        kernel_version: info
+            .kernel_version
+            .or(version.kernel_version)
+            .unwrap_or_default(),
+        architecture: info.architecture.or(version.arch).unwrap_or_default(),
+        ncpu: info.ncpu.unwrap_or(0),
+        mem_total: info.mem_total.unwrap_or(0),
+        hostname: info.name.unwrap_or_default(),
+        storage_driver: info.driver.unwrap_or_default(),
+        logging_driver: info.logging_driver.unwrap_or_default(),
+        cgroup_driver: info
+            .cgroup_driver
+            .map(|driver| driver.to_string())
+            .unwrap_or_default(),
+        cgroup_version: info
+            .cgroup_version
+            .map(|version| version.to_string())
+            .unwrap_or_default(),
+        docker_root_dir: info.docker_root_dir.unwrap_or_default(),
+        default_runtime: info.default_runtime.unwrap_or_default(),
+        experimental: info
+            .experimental_build
+            .or(version.experimental)
+            .unwrap_or(false),
+        live_restore: info.live_restore_enabled.unwrap_or(false),
+        containers: info.containers.unwrap_or(0),
+        containers_running: info.containers_running.unwrap_or(0),
+        containers_paused: info.containers_paused.unwrap_or(0),
+        containers_stopped: info.containers_stopped.unwrap_or(0),
+        images: info.images.unwrap_or(0),
+        security_options: info.security_options.unwrap_or_default(),
+        warnings: info.warnings.unwrap_or_default(),
+    })
+}
@@ -0,0 +1,408 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bollard::Docker;
+use bollard::errors::Error as DockerError;
+use bollard::models::{ContainerUpdateBody, RestartPolicy, RestartPolicyNameEnum};
+use bollard::query_parameters::{
+    InspectContainerOptionsBuilder, ListContainersOptionsBuilder, RenameContainerOptionsBuilder,
+    StopContainerOptionsBuilder,
+};
+use proto::generated::{ContainerAction, TrashStatus, TrashedContainer};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::delete_container;
+use crate::docker_client::docker;
+use crate::docker_limits::{INSPECT_TIMEOUT, LIFECYCLE_TIMEOUT, LIST_TIMEOUT, limited};
+
+// Trashed containers are named `docklord-trash.<deleted at>.<restart policy>.<name>`,
+// so the trash needs no state of its own and survives node restarts
+const TRASH_PREFIX: &str = "docklord-trash.";
+// How often expired containers are removed, and so how long they may outstay
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+static TRASH: OnceLock<Mutex<Trash>> = OnceLock::new();
+
+#[derive(Default)]
+struct Trash {
+    retention_secs: u64,
+    task: Option<JoinHandle<()>>,
+}
+
+fn trash() -> MutexGuard<'static, Trash> {
+    TRASH
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// A container in the trash, as read from its name.
+struct Trashed {
+    id: String,
+    name: String,
+    trash_name: String,
+    deleted_at: i64,
+    restart_policy: RestartPolicy,
+}
+
+/// Sets how long deleted containers stay in the trash; 0 deletes right away.
+/// A new retention also applies to the containers already in the trash. With
+/// the trash off they stay until restored or deleted.
+/// Used for the coordinator's /api/nodes/:id/trash
+pub fn configure_trash(retention_secs: u64) -> TrashStatus {
+    let mut state = trash();
+    if state.retention_secs != retention_secs {
+        if retention_secs == 0 {
+            info!("Deleted containers are removed right away");
+        } else {
+            info!(
+                "Deleted containers stay in the trash for {} s",
+                retention_secs
+            );
+        }
+    }
+    state.retention_secs = retention_secs;
+    match (&state.task, retention_secs) {
+        (Some(_), 0) => {
+            if let Some(task) = state.task.take() {
+                task.abort();
+            }
+        }
+        (None, 1..) => state.task = Some(tokio::spawn(run_sweeps())),
+        _ => {}
+    }
+    TrashStatus {
+        request_key: None, // will be set by the handler
+        retention_secs,
+        containers: Vec::new(),
+    }
+}
+
+/// The retention and the containers in the trash, most recently deleted first.
+pub async fn trash_status() -> Result<TrashStatus, Box<dyn Error + Send + Sync>> {
+    let retention_secs = trash().retention_secs;
+    let docker = docker()?;
+    let containers = trashed_containers(&docker)
+        .await?
+        .into_iter()
+        .map(|trashed| TrashedContainer {
+            expires_at: if retention_secs == 0 {
+                0
+            } else {
+                trashed.deleted_at + retention_secs.min(i64::MAX as u64) as i64
+            },
+            container_id: trashed.id,
+            name: trashed.name,
+            trash_name: trashed.trash_name,
+            deleted_at: trashed.deleted_at,
+        })
+        .collect();
+    Ok(TrashStatus {
+        request_key: None, // will be set by the handler
+        retention_secs,
+        containers,
+    })
+}
+
+/// Deletes a container, or with the trash on stops it and renames it into
+/// the trash. Deleting a container that is in the trash removes it for good.
+/// Used for DELETE /api/containers/:container_id
+pub async fn delete_or_trash_container(
+    container_id: &str,
+) -> Result<ContainerAction, Box<dyn Error + Send + Sync>> {
+    let retention_secs = trash().retention_secs;
+    if retention_secs == 0 {
+        return delete_container(container_id).await;
+    }
+    let docker = docker()?;
+    let info = limited(
+        "inspect container",
+        INSPECT_TIMEOUT,
+        docker.inspect_container(
+            container_id,
+            Some(InspectContainerOptionsBuilder::default().build()),
+        ),
+    )
+    .await?;
+    let name = info
+        .name
+        .unwrap_or_default()
+        .trim_start_matches('/')
+        .to_string();
+    if is_trashed(&name) {
+        return delete_container(container_id).await;
+    }
+    let id = info.id.unwrap_or_else(|| container_id.to_string());
+    let restart_policy = info
+        .host_config
+        .and_then(|host_config| host_config.restart_policy)
+        .unwrap_or_default();
+
+    // Docker answers 304 when the container is already stopped
+    match limited(
+        "stop container",
+        LIFECYCLE_TIMEOUT,
+        docker.stop_container(&id, Some(StopContainerOptionsBuilder::default().build())),
+    )
+    .await
+    {
+        Ok(_) => {}
+        Err(e)
+            if matches!(
+                e.downcast_ref::<DockerError>(),
+                Some(DockerError::DockerResponseServerError {
+                    status_code: 304,
+                    ..
+                })
+            ) => {}
+        Err(e) => return Err(e),
+    }
+    let trash_name = format!(
+        "{}{}.{}.{}",
+        TRASH_PREFIX,
+        now_secs(),
+        policy_tag(&restart_policy),
+        name
+    );
+    rename(&docker, &id, &trash_name).await?;
+    // A daemon restart would otherwise start `always` containers again
+    if !is_no_restart(&restart_policy)
+        && let Err(e) = set_restart_policy(&docker, &id, no_restart()).await
+    {
+        warn!(
+            "Trashed container {} keeps its restart policy: {}",
+            trash_name, e
+        );
+    }
+    info!("Moved container {} to the trash as {}", name, trash_name);
+
+    Ok(ContainerAction {
+        request_key: None, // set by the handler
+        container_id: container_id.to_string(),
+        action: "trash".to_string(),
+        message: format!(
+            "Container moved to the trash as {}, removed in {} s unless restored",
+            trash_name, retention_secs
+        ),
+    })
+}
+
+/// Renames a trashed container back and gives it back its restart policy. It
+/// stays stopped. `container_id` is its id or trash name, or the name it had,
+/// for the latest container deleted under that name.
+/// Used for POST /api/trash/:container_id/restore
+pub async fn restore_container(
+    container_id: &str,
+) -> Result<ContainerAction, Box<dyn Error + Send + Sync>> {
+    let docker = docker()?;
+    let trashed = trashed_containers(&docker)
+        .await?
+        .into_iter()
+        .find(|trashed| {
+            trashed.id.starts_with(container_id)
+                || trashed.trash_name == container_id
+                || trashed.name == container_id
+        });
+    let Some(trashed) = trashed else {
+        // Docker's 404 for a container that does not exist at all
+        limited(
+            "inspect container",
+            INSPECT_TIMEOUT,
+            docker.inspect_container(
+                container_id,
+                Some(InspectContainerOptionsBuilder::default().build()),
+            ),
+        )
+        .await?;
+        return Err(format!("Container {} is not in the trash", container_id).into());
+    };
+
+    rename(&docker, &trashed.id, &trashed.name).await?;
+    if !is_no_restart(&trashed.restart_policy)
+        && let Err(e) = set_restart_policy(&docker, &trashed.id, trashed.restart_policy).await
+    {
+        warn!(
+            "Restored container {} did not get its restart policy back: {}",
+            trashed.name, e
+        );
+    }
+    info!("Restored container {} from the trash", trashed.name);
+
+    Ok(ContainerAction {
+        request_key: None, // set by the handler
+        container_id: trashed.id,
+        action: "restore".to_string(),
+        message: format!("Container restored as {}; it is stopped", trashed.name),
+    })
+}
+
+/// Whether `name` is that of a container in the trash.
+pub(crate) fn is_trashed(name: &str) -> bool {
+    name.starts_with(TRASH_PREFIX)
+}
+
+async fn run_sweeps() {
+    let mut ticker = tokio::time::interval(SWEEP_INTERVAL);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        ticker.tick().await;
+        let retention_secs = trash().retention_secs.min(i64::MAX as u64) as i64;
+        let trashed = match docker() {
+            Ok(docker) => trashed_containers(&docker).await,
+            Err(e) => Err(e.into()),
+        };
+        let trashed = match trashed {
+            Ok(trashed) => trashed,
+            Err(e) => {
+                warn!("Could not list the containers in the trash: {}", e);
+                continue;
+            }
+        };
+        let now = now_secs();
+        for trashed in trashed {
+            if now - trashed.deleted_at < retention_secs {
+                continue;
+            }
+            match delete_container(&trashed.id).await {
+                Ok(_) => info!(
+                    "Removed container {} from the trash for good",
+                    trashed.trash_name
+                ),
+                Err(e) => warn!(
+                    "Could not remove container {} from the trash: {}",
+                    trashed.trash_name, e
+                ),
+            }
+        }
+    }
+}
+
+/// The containers in the trash, most recently deleted first.
+async fn trashed_containers(docker: &Docker) -> Result<Vec<Trashed>, Box<dyn Error + Send + Sync>> {
+    let filters = HashMap::from([("name", vec![TRASH_PREFIX])]);
+    let containers = limited(
+        "list containers",
+        LIST_TIMEOUT,
+        docker.list_containers(Some(
+            ListContainersOptionsBuilder::default()
+                .all(true)
+                .filters(&filters)
+                .build(),
+        )),
+    )
+    .await?;
+
+    let mut trashed: Vec<Trashed> = containers
+        .into_iter()
+        .filter_map(|container| {
+            let id = container.id?;
+            let trash_name = container
+                .names?
+                .into_iter()
+                .map(|name| name.trim_start_matches('/').to_string())
+                .find(|name| is_trashed(name))?;
+            let mut parts = trash_name.strip_prefix(TRASH_PREFIX)?.splitn(3, '.');
+            let deleted_at = parts.next()?.parse().ok()?;
+            let restart_policy = parse_policy_tag(parts.next()?)?;
+            let name = parts.next()?.to_string();
+            Some(Trashed {
+                id,
+                name,
+                trash_name,
+                deleted_at,
+                restart_policy,
+            })
+        })
+        .collect();
+    trashed.sort_by_key(|trashed| std::cmp::Reverse(trashed.deleted_at));
+    Ok(trashed)
+}
+
+async fn rename(
+    docker: &Docker,
+    container_id: &str,
+    name: &str,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    limited(
+        "rename container",
+        LIFECYCLE_TIMEOUT,
+        docker.rename_container(
+            container_id,
+            RenameContainerOptionsBuilder::default().name(name).build(),
+        ),
+    )
+    .await
+}
+
+async fn set_restart_policy(
+    docker: &Docker,
+    container_id: &str,
+    restart_policy: RestartPolicy,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    limited(
+        "update container",
+        LIFECYCLE_TIMEOUT,
+        docker.update_container(
+            container_id,
+            ContainerUpdateBody {
+                restart_policy: Some(restart_policy),
+                ..Default::default()
+            },
+        ),
+    )
+    .await
+}
+
+fn no_restart() -> RestartPolicy {
+    RestartPolicy {
+        name: Some(RestartPolicyNameEnum::NO),
+        maximum_retry_count: None,
+    }
+}
+
+fn is_no_restart(restart_policy: &RestartPolicy) -> bool {
+    matches!(
+        restart_policy.name,
+        None | Some(RestartPolicyNameEnum::EMPTY | RestartPolicyNameEnum::NO)
+    )
+}
+
+// The restart policy as it goes into the trash name: `no`, `always`,
+// `unless-stopped` or `on-failure-<retries>`
+fn policy_tag(restart_policy: &RestartPolicy) -> String {
+    match restart_policy.name {
+        Some(RestartPolicyNameEnum::ALWAYS) => "always".to_string(),
+        Some(RestartPolicyNameEnum::UNLESS_STOPPED) => "unless-stopped".to_string(),
+        Some(RestartPolicyNameEnum::ON_FAILURE) => format!(
+            "on-failure-{}",
+            restart_policy.maximum_retry_count.unwrap_or(0)
+        ),
+        _ => "no".to_string(),
+    }
+}
+
+fn parse_policy_tag(tag: &str) -> Option<RestartPolicy> {
+    let (name, maximum_retry_count) = match tag {
+        "no" => (RestartPolicyNameEnum::NO, None),
+        "always" => (RestartPolicyNameEnum::ALWAYS, None),
+        "unless-stopped" => (RestartPolicyNameEnum::UNLESS_STOPPED, None),
+        tag => (
+            RestartPolicyNameEnum::ON_FAILURE,
+            Some(tag.strip_prefix("on-failure-")?.parse().ok()?),
+        ),
+    };
+    Some(RestartPolicy {
+        name: Some(name),
+        maximum_retry_count,
+    })
+}
+
+fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
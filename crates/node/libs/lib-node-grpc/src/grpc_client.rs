@@ -1,40 +1,284 @@
+use std::collections::HashMap;
 use std::error::Error;
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Duration;
 
 use futures_util::StreamExt;
 use lib_node_containers::{
-    delete_container, get_container_logs, get_container_status, get_docker_containers,
-    start_container, stop_container, watch_container_changes,
+    ImportReply, NameFilter, SessionControl, apply_autostart, attach_container, build_image,
+    cleanup_status, clone_container, configure_cleanup, configure_log_forwarding, configure_trash,
+    create_container, create_network, delete_or_trash_container, detect_capabilities, error_code,
+    exec_container, export_container, fill_container_usage, follow_container_logs,
+    get_container_archive, get_container_graph, get_container_logs, get_container_status,
+    get_disk_usage, get_docker_containers, get_image_history, get_node_metrics,
+    get_system_disk_usage, get_system_info, get_top_containers, import_container,
+    inspect_image_manifest, inspect_network, list_images, list_networks, log_forwarding_status,
+    pause_container, prune_images, prune_system, pull_image, put_container_archive,
+    refresh_container_states, remove_image, remove_network, restart_container, restore_container,
+    run_host_script, start_container, stop_container, stream_container_stats, trash_status,
+    unpause_container, watch_container_changes,
 };
+use prost::Message;
 use proto::generated::{
-    AuthRequest, Envelope, NodeContainers, NodeError, NodeResponse, RequestKey, RequestType,
-    ServerCommand, conversation_service_client::ConversationServiceClient, envelope::Payload,
-    node_command, node_response, request_key::RequestId, server_command, server_response,
+    ApplyAutostart, AttachContainer, AuthRequest, BroadcastAck, BroadcastNotice, BuildImage,
+    CloneContainer, Compression, ConfigureCleanup, ConfigureLogForwarding, ConfigureTrash,
+    ContainerArchive, ContainerLogs, ContainerLogsChunk, ContainerStats, CoordinatorLink,
+    CreateContainer, CreateNetwork, Envelope, ErrorCode, ExecContainer, ExportContainer,
+    FollowContainerLogs, GetContainerArchive, GetContainerLogs, GetContainerStats,
+    GetNodeContainersWithStatus, Heartbeat, ImageBuildOutput, ImportContainer, InspectNetwork,
+    ListImages, ListNetworks, LogLine, MigrationChunk, NodeContainers, NodeError, NodeResponse,
+    PruneImages, PruneSystem, PullImage, PutContainerArchive, RemoveImage, RemoveNetwork,
+    RequestKey, RequestType, RunHostScript, ServerCommand, SessionOutput,
+    conversation_service_client::ConversationServiceClient, envelope::Payload, node_command,
+    node_response, request_key::RequestId, server_command, server_response,
 };
+use proto::{METADATA_ACTOR, METADATA_TRACE_ID};
+use tokio::sync::oneshot::error::TryRecvError;
 use tokio::sync::{mpsc, oneshot};
+use tokio::task::{AbortHandle, JoinHandle};
 use tokio_stream;
-use tonic::transport::Channel;
-use tracing::{error, info};
+use tokio_util::sync::CancellationToken;
+use tonic::transport::{Certificate, ClientTlsConfig, Endpoint};
+use tracing::{Instrument, Span, error, info, info_span, warn};
 
 // Алиасы для упрощения
 use node_command::Kind as NodeCommandKind;
 use node_response::Kind as NodeResponseKind;
+use server_command::Kind as ServerCommandKind;
 use server_response::Kind as ServerResponseKind;
 
+const DEFAULT_STATS_STREAM_SECS: u32 = 60;
+const MAX_STATS_STREAM_SECS: u32 = 3600;
+const ONE_SHOT_STATS_TIMEOUT: Duration = Duration::from_secs(10);
+const SESSION_CHANNEL_CAPACITY: usize = 64;
+const FOLLOW_LOGS_CHANNEL_CAPACITY: usize = 256;
+const MAX_FOLLOW_LOGS_BATCH: usize = 100;
+// Log answers with more bytes of lines than this go out in several chunks,
+// well below any sensible gRPC message limit
+const LOG_CHUNK_SIZE: usize = 1024 * 1024;
+const ARCHIVE_CHANNEL_CAPACITY: usize = 16;
+// Docker's small reads are merged into messages of up to this size
+const ARCHIVE_CHUNK_SIZE: usize = 256 * 1024;
+// Exports merge Docker's reads themselves, into larger chunks
+const MIGRATION_CHANNEL_CAPACITY: usize = 4;
+const ECHO_CHANNEL_CAPACITY: usize = 16;
+// Wait before reconnecting to the coordinator, doubled after each failed attempt
+const RECONNECT_MIN_DELAY: Duration = Duration::from_secs(1);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+// On shutdown, requests in progress get this long to finish before they are
+// aborted, and the coordinator a little longer to close the stream
+const SHUTDOWN_DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+const STREAM_CLOSE_TIMEOUT: Duration = Duration::from_secs(2);
+// While on a standby coordinator, the primary is probed this often and the
+// node moves back once it accepts connections again
+const FAILBACK_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+const FAILBACK_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+// The coordinator takes the node for hung after a few missed heartbeats
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Open interactive sessions by session id, holding the control channel of each.
+pub type Sessions = Arc<Mutex<HashMap<String, mpsc::Sender<SessionControl>>>>;
+
+/// Requests being worked on by request id, so the coordinator can cancel them.
+pub type InFlight = Arc<Mutex<HashMap<String, AbortHandle>>>;
+
+// Per container id or name, the last lifecycle command queued on it; its
+// sender is dropped once that command is done
+static CONTAINER_QUEUES: LazyLock<Mutex<HashMap<String, oneshot::Receiver<()>>>> =
+    LazyLock::new(Default::default);
+
+/// How the node verifies the coordinator's TLS certificate. TLS is used for
+/// `https://` coordinator addresses, with the system roots unless a CA is set.
+#[derive(Debug, Clone, Default)]
+pub struct GrpcClientTls {
+    /// PEM CA certificate to trust instead of the system roots, e.g. for a
+    /// self-signed coordinator certificate.
+    pub ca_path: Option<PathBuf>,
+    /// Name the certificate must be valid for; the address host when unset.
+    pub domain: Option<String>,
+}
+
+impl GrpcClientTls {
+    async fn load(&self) -> Result<ClientTlsConfig, Box<dyn std::error::Error + Send + Sync>> {
+        let mut config = ClientTlsConfig::new();
+        if let Some(ca_path) = &self.ca_path {
+            let ca = tokio::fs::read(ca_path).await.map_err(|e| {
+                format!(
+                    "Failed to read TLS CA certificate {}: {}",
+                    ca_path.display(),
+                    e
+                )
+            })?;
+            config = config.ca_certificate(Certificate::from_pem(ca));
+        }
+        if let Some(domain) = &self.domain {
+            config = config.domain_name(domain.clone());
+        }
+        Ok(config)
+    }
+}
+
+/// Serves the coordinators at `addresses`, one at a time. The first is the
+/// primary; when a connection fails the node moves to the next one, and from
+/// a standby it moves back to the primary once that is reachable again.
 pub async fn run_grpc_client(
+    addresses: &[String],
+    node_id: &str,
+    password: &str,
+    max_message_size: usize,
+    compression: Compression,
+    tls: Option<&GrpcClientTls>,
+    shutdown: CancellationToken,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if addresses.is_empty() {
+        return Err("No coordinator address given".into());
+    }
+    let mut endpoints = Vec::with_capacity(addresses.len());
+    for address in addresses {
+        endpoints.push(coordinator_endpoint(address, tls).await?);
+    }
+    if addresses
+        .iter()
+        .any(|address| address.starts_with("https://"))
+    {
+        info!("Connecting to the coordinator over TLS");
+    }
+
+    // The coordinator going away (a restart, or a handover to a new process)
+    // is not fatal: the node reconnects until it is stopped
+    info!("Client started. Press Ctrl+C to exit.");
+    let mut delay = RECONNECT_MIN_DELAY;
+    let mut current = 0;
+    let mut failovers = 0;
+    // Bounds a session that is still connecting or draining when shut down
+    let shutdown_deadline = async {
+        shutdown.cancelled().await;
+        tokio::time::sleep(SHUTDOWN_DRAIN_TIMEOUT + STREAM_CLOSE_TIMEOUT).await;
+    };
+    tokio::pin!(shutdown_deadline);
+    loop {
+        let link = CoordinatorLink {
+            address: addresses[current].clone(),
+            priority: current as u32,
+            coordinators: addresses.len() as u32,
+            failovers,
+        };
+        // Stopping the session alone drains it like a shutdown, for failing back
+        let session_stop = shutdown.child_token();
+        let failback = (current > 0).then(|| {
+            tokio::spawn(watch_primary(
+                endpoints[0].clone(),
+                addresses[0].clone(),
+                session_stop.clone(),
+            ))
+        });
+        let mut failed = false;
+        tokio::select! {
+            result = run_session(&endpoints[current], node_id, password, max_message_size, compression, link, &session_stop) => {
+                match result {
+                    Ok(()) => {
+                        info!("Stream closed by server");
+                        delay = RECONNECT_MIN_DELAY;
+                    }
+                    Err(e) => {
+                        warn!("Connection to coordinator {} failed: {}", addresses[current], e);
+                        failed = true;
+                    }
+                }
+            }
+            () = &mut shutdown_deadline => {
+                warn!("Shutdown timed out, closing the connection");
+                break;
+            }
+        }
+        if let Some(failback) = failback {
+            failback.abort();
+        }
+        if shutdown.is_cancelled() {
+            break;
+        }
+        if session_stop.is_cancelled() {
+            info!("Failing back to primary coordinator {}", addresses[0]);
+            current = 0;
+            failovers += 1;
+            delay = RECONNECT_MIN_DELAY;
+            continue;
+        }
+        // A stream the coordinator closed is picked up again at the same
+        // address, where a handover brings up the new process
+        if failed && addresses.len() > 1 {
+            current = (current + 1) % addresses.len();
+            failovers += 1;
+            info!("Failing over to coordinator {}", addresses[current]);
+            // Only a full round of failed coordinators is waited out
+            if current != 0 {
+                continue;
+            }
+        }
+        info!("Reconnecting in {} s", delay.as_secs());
+        tokio::select! {
+            () = tokio::time::sleep(delay) => {}
+            () = shutdown.cancelled() => break,
+        }
+        delay = (delay * 2).min(RECONNECT_MAX_DELAY);
+    }
+
+    info!("Client stopped");
+    Ok(())
+}
+
+async fn coordinator_endpoint(
     address: &str,
+    tls: Option<&GrpcClientTls>,
+) -> Result<Endpoint, Box<dyn std::error::Error + Send + Sync>> {
+    let endpoint = Endpoint::from_shared(address.to_string())?;
+    let is_https = address.starts_with("https://");
+    // tonic would silently send plaintext to an http:// address
+    if tls.is_some() && !is_https {
+        return Err(format!("TLS needs an https:// coordinator address, got {}", address).into());
+    }
+    if !is_https {
+        return Ok(endpoint);
+    }
+    let tls = tls.cloned().unwrap_or_default();
+    Ok(endpoint.tls_config(tls.load().await?)?)
+}
+
+/// Stops the standby session once the primary coordinator accepts connections.
+async fn watch_primary(primary: Endpoint, address: String, session_stop: CancellationToken) {
+    loop {
+        tokio::time::sleep(FAILBACK_CHECK_INTERVAL).await;
+        if let Ok(Ok(_)) = tokio::time::timeout(FAILBACK_CONNECT_TIMEOUT, primary.connect()).await {
+            info!("Primary coordinator {} is reachable again", address);
+            session_stop.cancel();
+            return;
+        }
+    }
+}
+
+/// One connection to the coordinator: authenticates and serves its commands
+/// until the coordinator closes the stream, or until `shutdown`, after which
+/// the requests in progress finish and the node closes the stream itself.
+async fn run_session(
+    endpoint: &Endpoint,
     node_id: &str,
     password: &str,
+    max_message_size: usize,
+    compression: Compression,
+    link: CoordinatorLink,
+    shutdown: &CancellationToken,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let address_owned = address.to_string();
-    let channel = Channel::from_static(Box::leak(address_owned.into_boxed_str()))
-        .connect()
-        .await?;
-    let mut client = ConversationServiceClient::new(channel);
+    let channel = endpoint.connect().await?;
+    let mut client = ConversationServiceClient::new(channel)
+        .max_decoding_message_size(max_message_size)
+        .max_encoding_message_size(max_message_size);
 
     let (tx_out, rx_out) = mpsc::channel(100);
-    let (shutdown_tx, mut shutdown_rx) = oneshot::channel();
 
-    let request = tonic::Request::new(tokio_stream::wrappers::ReceiverStream::new(rx_out));
+    let outbound = tokio_stream::wrappers::ReceiverStream::new(rx_out)
+        .map(move |envelope| prepare_outbound(envelope, compression, max_message_size));
+    let request = tonic::Request::new(outbound);
     let mut stream = client.conversation(request).await?.into_inner();
 
     let auth_envelope = Envelope {
@@ -42,21 +286,25 @@ pub async fn run_grpc_client(
             kind: Some(server_command::Kind::AuthRequest(AuthRequest {
                 node_id: node_id.into(),
                 password: password.into(),
+                capabilities: Some(detect_capabilities().await),
+                link: Some(link),
             })),
         })),
+        ..Default::default()
     };
 
     let status_envelope = Envelope {
         payload: Some(Payload::ServerCommand(ServerCommand {
             kind: Some(server_command::Kind::GetServerStatus(Default::default())),
         })),
+        ..Default::default()
     };
 
     tx_out.send(auth_envelope).await?;
     tx_out.send(status_envelope).await?;
 
     let tx_clone_for_docker = tx_out.clone();
-    tokio::spawn(async move {
+    let watcher = tokio::spawn(async move {
         if let Err(e) = watch_container_changes(tx_clone_for_docker).await {
             let err_str = e.to_string();
             if err_str.contains("Socket not found: /var/run/docker.sock") {
@@ -67,42 +315,111 @@ pub async fn run_grpc_client(
         }
     });
 
-    let tx_clone = tx_out.clone();
-    tokio::spawn(async move {
-        loop {
-            tokio::select! {
-                maybe_msg = stream.next() => {
-                    match maybe_msg {
-                        Some(Ok(envelope)) => {
-                            if let Err(e) = process_incoming_message(envelope, &tx_clone).await {
-                                error!("Error processing message: {}", e);
-                            }
-                        }
-                        Some(Err(e)) => {
-                            error!("Stream error: {}", e);
-                        }
-                        None => {
-                            info!("Stream closed by server");
-                            break;
-                        }
-                    }
-                }
-                _ = &mut shutdown_rx => {
-                    info!("Shutdown signal received");
-                    break;
-                }
-            }
-        }
-    });
+    let heartbeat = tokio::spawn(send_heartbeats(tx_out.clone()));
 
-    info!("Client started. Press Ctrl+C to exit.");
-    tokio::signal::ctrl_c().await?;
-    let _ = shutdown_tx.send(());
+    let sessions = Sessions::default();
+    let in_flight = InFlight::default();
+    // Tasks of this connection end once their sends fail after it closes
+    loop {
+        let envelope = tokio::select! {
+            envelope = stream.next() => envelope,
+            () = shutdown.cancelled() => break,
+        };
+        let Some(envelope) = envelope else {
+            return Ok(());
+        };
+        let envelope = envelope?;
+        if let Some(Payload::ServerCommand(ServerCommand {
+            kind: Some(ServerCommandKind::CoordinatorShutdown(notice)),
+        })) = &envelope.payload
+        {
+            info!("Coordinator is going away ({})", notice.reason);
+            watcher.abort();
+            heartbeat.abort();
+            return Ok(());
+        }
+        if let Err(e) = process_incoming_message(envelope, &tx_out, &sessions, &in_flight).await {
+            error!("Error processing message: {}", e);
+        }
+    }
 
-    info!("Client stopped");
+    drain_requests(&sessions, &in_flight).await;
+    // The outbound stream ends once every sender is gone, which tells the
+    // coordinator the node is leaving
+    watcher.abort();
+    heartbeat.abort();
+    drop(tx_out);
+    let closed = tokio::time::timeout(STREAM_CLOSE_TIMEOUT, async {
+        while stream.next().await.is_some() {}
+    })
+    .await;
+    if closed.is_err() {
+        warn!("Coordinator did not close the stream in time");
+    }
     Ok(())
 }
 
+/// Tells the coordinator every `HEARTBEAT_INTERVAL` that the node is alive,
+/// until the connection closes.
+async fn send_heartbeats(tx: mpsc::Sender<Envelope>) {
+    let mut ticker = tokio::time::interval(HEARTBEAT_INTERVAL);
+    loop {
+        ticker.tick().await;
+        let sent_at_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or(0);
+        let envelope = Envelope {
+            payload: Some(Payload::ServerCommand(ServerCommand {
+                kind: Some(ServerCommandKind::Heartbeat(Heartbeat {
+                    interval_secs: HEARTBEAT_INTERVAL.as_secs() as u32,
+                    sent_at_ms,
+                })),
+            })),
+            ..Default::default()
+        };
+        if tx.send(envelope).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Detaches interactive sessions and gives the other requests in progress
+/// `SHUTDOWN_DRAIN_TIMEOUT` to finish, then aborts what is left.
+async fn drain_requests(sessions: &Sessions, in_flight: &InFlight) {
+    let controls: Vec<_> = sessions
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .values()
+        .cloned()
+        .collect();
+    for control in controls {
+        let _ = control.send(SessionControl::Close).await;
+    }
+
+    let count = || in_flight.lock().unwrap_or_else(|e| e.into_inner()).len();
+    if count() > 0 {
+        info!("Shutting down: waiting for {} requests to finish", count());
+    }
+    let drained = tokio::time::timeout(SHUTDOWN_DRAIN_TIMEOUT, async {
+        while count() > 0 {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await;
+    if drained.is_err() {
+        let tasks: Vec<_> = in_flight
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .drain()
+            .collect();
+        warn!("Aborting {} requests still running", tasks.len());
+        for (_, task) in tasks {
+            task.abort();
+        }
+    }
+}
+
 pub async fn handle_get_client_containers(
     tx: &mpsc::Sender<Envelope>,
     request_id: String,
@@ -118,6 +435,7 @@ pub async fn handle_get_client_containers(
                 containers,
             })),
         })),
+        ..Default::default()
     };
 
     tx.send(response)
@@ -129,13 +447,26 @@ pub async fn handle_get_client_containers(
 
 pub async fn handle_get_client_containers_with_status(
     tx: &mpsc::Sender<Envelope>,
-    request_id: String,
+    request: GetNodeContainersWithStatus,
 ) -> Result<(), String> {
+    let request_id = request.request_id;
+    let filter = match NameFilter::new(&request.name_query, &request.name_regex) {
+        Ok(filter) => filter,
+        Err(e) => {
+            let kind = node_error(
+                RequestType::GetContainersWithStatus,
+                request_id,
+                format!("Invalid name_regex: {}", e),
+            );
+            return send_node_response(tx, kind).await;
+        }
+    };
+
     let containers = get_docker_containers().await.unwrap_or_default();
     let mut containers_with_status = Vec::new();
 
-    // Get status for each container
-    for container_id in containers {
+    // Get status for each matching container
+    for container_id in containers.into_iter().filter(|name| filter.matches(name)) {
         if let Ok(status) = get_container_status(&container_id).await {
             containers_with_status.push(status);
         }
@@ -153,6 +484,7 @@ pub async fn handle_get_client_containers_with_status(
                 },
             )),
         })),
+        ..Default::default()
     };
 
     tx.send(response)
@@ -169,6 +501,12 @@ pub async fn handle_get_container_status(
 ) -> Result<(), String> {
     match get_container_status(&container_id).await {
         Ok(mut status) => {
+            if status.status == "running"
+                && let Err(e) = fill_container_usage(&mut status).await
+            {
+                error!("Failed to read container usage: {}", e);
+            }
+
             status.request_key = Some(RequestKey {
                 request_type: RequestType::GetContainerStatus as i32,
                 request_id: Some(RequestId::Value(request_id)),
@@ -178,6 +516,7 @@ pub async fn handle_get_container_status(
                 payload: Some(Payload::NodeResponse(NodeResponse {
                     kind: Some(NodeResponseKind::ContainerStatus(status)),
                 })),
+                ..Default::default()
             };
 
             tx.send(response)
@@ -195,8 +534,10 @@ pub async fn handle_get_container_status(
                             request_id: Some(RequestId::Value(request_id)),
                         }),
                         message: e.to_string(),
+                        code: error_code(&*e).into(),
                     })),
                 })),
+                ..Default::default()
             };
 
             tx.send(response)
@@ -224,6 +565,7 @@ pub async fn handle_start_container(
                 payload: Some(Payload::NodeResponse(NodeResponse {
                     kind: Some(NodeResponseKind::ContainerAction(action)),
                 })),
+                ..Default::default()
             };
 
             tx.send(response)
@@ -241,8 +583,10 @@ pub async fn handle_start_container(
                             request_id: Some(RequestId::Value(request_id)),
                         }),
                         message: e.to_string(),
+                        code: error_code(&*e).into(),
                     })),
                 })),
+                ..Default::default()
             };
 
             tx.send(response)
@@ -270,6 +614,7 @@ pub async fn handle_stop_container(
                 payload: Some(Payload::NodeResponse(NodeResponse {
                     kind: Some(NodeResponseKind::ContainerAction(action)),
                 })),
+                ..Default::default()
             };
 
             tx.send(response)
@@ -287,8 +632,10 @@ pub async fn handle_stop_container(
                             request_id: Some(RequestId::Value(request_id)),
                         }),
                         message: e.to_string(),
+                        code: error_code(&*e).into(),
                     })),
                 })),
+                ..Default::default()
             };
 
             tx.send(response)
@@ -300,12 +647,84 @@ pub async fn handle_stop_container(
     Ok(())
 }
 
+pub async fn handle_restart_container(
+    tx: &mpsc::Sender<Envelope>,
+    request_id: String,
+    container_id: String,
+    timeout_secs: Option<u32>,
+) -> Result<(), String> {
+    let result = restart_container(&container_id, timeout_secs).await;
+    send_container_action(tx, RequestType::RestartContainer, request_id, result).await
+}
+
+pub async fn handle_pause_container(
+    tx: &mpsc::Sender<Envelope>,
+    request_id: String,
+    container_id: String,
+) -> Result<(), String> {
+    let result = pause_container(&container_id).await;
+    send_container_action(tx, RequestType::PauseContainer, request_id, result).await
+}
+
+pub async fn handle_unpause_container(
+    tx: &mpsc::Sender<Envelope>,
+    request_id: String,
+    container_id: String,
+) -> Result<(), String> {
+    let result = unpause_container(&container_id).await;
+    send_container_action(tx, RequestType::UnpauseContainer, request_id, result).await
+}
+
+pub async fn handle_restore_container(
+    tx: &mpsc::Sender<Envelope>,
+    request_id: String,
+    container_id: String,
+) -> Result<(), String> {
+    let result = restore_container(&container_id).await;
+    send_container_action(tx, RequestType::RestoreContainer, request_id, result).await
+}
+
+// Answers a lifecycle command with its action or the error
+async fn send_container_action(
+    tx: &mpsc::Sender<Envelope>,
+    request_type: RequestType,
+    request_id: String,
+    result: Result<proto::generated::ContainerAction, Box<dyn Error + Send + Sync>>,
+) -> Result<(), String> {
+    let request_key = RequestKey {
+        request_type: request_type as i32,
+        request_id: Some(RequestId::Value(request_id)),
+    };
+    let kind = match result {
+        Ok(mut action) => {
+            action.request_key = Some(request_key);
+            NodeResponseKind::ContainerAction(action)
+        }
+        Err(e) => {
+            error!("Failed to run {:?}: {}", request_type, e);
+            NodeResponseKind::Error(NodeError {
+                request_key: Some(request_key),
+                message: e.to_string(),
+                code: error_code(&*e).into(),
+            })
+        }
+    };
+    let response = Envelope {
+        payload: Some(Payload::NodeResponse(NodeResponse { kind: Some(kind) })),
+        ..Default::default()
+    };
+
+    tx.send(response)
+        .await
+        .map_err(|_| String::from("Failed to send response"))
+}
+
 pub async fn handle_delete_container(
     tx: &mpsc::Sender<Envelope>,
     request_id: String,
     container_id: String,
 ) -> Result<(), String> {
-    match delete_container(&container_id).await {
+    match delete_or_trash_container(&container_id).await {
         Ok(mut action) => {
             action.request_key = Some(RequestKey {
                 request_type: RequestType::DeleteContainer as i32,
@@ -316,6 +735,7 @@ pub async fn handle_delete_container(
                 payload: Some(Payload::NodeResponse(NodeResponse {
                     kind: Some(NodeResponseKind::ContainerAction(action)),
                 })),
+                ..Default::default()
             };
 
             tx.send(response)
@@ -333,8 +753,10 @@ pub async fn handle_delete_container(
                             request_id: Some(RequestId::Value(request_id)),
                         }),
                         message: e.to_string(),
+                        code: error_code(&*e).into(),
                     })),
                 })),
+                ..Default::default()
             };
 
             tx.send(response)
@@ -348,23 +770,23 @@ pub async fn handle_delete_container(
 
 pub async fn handle_get_container_logs(
     tx: &mpsc::Sender<Envelope>,
-    request_id: String,
-    container_id: String,
-    tail: Option<i32>,
-    follow: bool,
-    since: Option<String>,
+    request: GetContainerLogs,
 ) -> Result<(), String> {
-    match get_container_logs(&container_id, tail, follow, since).await {
+    match get_container_logs(&request).await {
         Ok(mut logs) => {
             logs.request_key = Some(RequestKey {
                 request_type: RequestType::GetContainerLogs as i32,
-                request_id: Some(RequestId::Value(request_id)),
+                request_id: Some(RequestId::Value(request.request_id)),
             });
+            if logs.lines.iter().map(line_size).sum::<usize>() > LOG_CHUNK_SIZE {
+                return send_log_chunks(tx, logs).await;
+            }
 
             let response = Envelope {
                 payload: Some(Payload::NodeResponse(NodeResponse {
                     kind: Some(NodeResponseKind::ContainerLogs(logs)),
                 })),
+                ..Default::default()
             };
 
             tx.send(response)
@@ -373,79 +795,1553 @@ pub async fn handle_get_container_logs(
         }
         Err(e) => {
             error!("Failed to get container logs: {}", e);
-            return Err(e.to_string());
+            let kind = failed(RequestType::GetContainerLogs, request.request_id, &*e);
+            send_node_response(tx, kind).await?;
         }
     }
 
     Ok(())
 }
 
-pub async fn process_incoming_message(
-    envelope: Envelope,
+/// Sends logs too large for one message as numbered chunks of up to
+/// `LOG_CHUNK_SIZE` bytes of lines, the last one flagged.
+async fn send_log_chunks(tx: &mpsc::Sender<Envelope>, logs: ContainerLogs) -> Result<(), String> {
+    let mut lines = logs.lines.into_iter().peekable();
+    let mut sequence = 0;
+    loop {
+        let mut chunk = Vec::new();
+        let mut bytes = 0;
+        // A single line bigger than a chunk still goes out alone
+        while let Some(line) =
+            lines.next_if(|line| chunk.is_empty() || bytes + line_size(line) <= LOG_CHUNK_SIZE)
+        {
+            bytes += line_size(&line);
+            chunk.push(line);
+        }
+        let is_last = lines.peek().is_none();
+        let kind = NodeResponseKind::ContainerLogsChunk(ContainerLogsChunk {
+            request_key: logs.request_key.clone(),
+            container_id: logs.container_id.clone(),
+            sequence,
+            lines: chunk,
+            is_last,
+            truncated: is_last && logs.truncated,
+            buffered: logs.buffered,
+        });
+        send_node_response(tx, kind).await?;
+        if is_last {
+            return Ok(());
+        }
+        sequence += 1;
+    }
+}
+
+fn line_size(line: &LogLine) -> usize {
+    line.text.len() + line.stream.len() + line.timestamp.len()
+}
+
+pub async fn handle_follow_container_logs(
     tx: &mpsc::Sender<Envelope>,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-    match envelope.payload {
-        Some(Payload::NodeCommand(cmd)) => match cmd.kind {
-            Some(NodeCommandKind::GetNodeContainers(get_containers_request)) => {
-                handle_get_client_containers(tx, get_containers_request.request_id).await?;
-            }
-            Some(NodeCommandKind::GetNodeContainersWithStatus(
-                get_containers_with_status_request,
-            )) => {
-                handle_get_client_containers_with_status(
-                    tx,
-                    get_containers_with_status_request.request_id,
-                )
-                .await?;
-            }
-            Some(NodeCommandKind::GetContainerStatus(get_status_request)) => {
-                handle_get_container_status(
-                    tx,
-                    get_status_request.request_id,
-                    get_status_request.container_id,
-                )
-                .await?;
-            }
-            Some(NodeCommandKind::StartContainer(start_request)) => {
-                handle_start_container(tx, start_request.request_id, start_request.container_id)
-                    .await?;
-            }
-            Some(NodeCommandKind::StopContainer(stop_request)) => {
-                handle_stop_container(tx, stop_request.request_id, stop_request.container_id)
-                    .await?;
-            }
-            Some(NodeCommandKind::DeleteContainer(delete_request)) => {
-                handle_delete_container(tx, delete_request.request_id, delete_request.container_id)
-                    .await?;
-            }
-            Some(NodeCommandKind::GetContainerLogs(logs_request)) => {
-                handle_get_container_logs(
-                    tx,
-                    logs_request.request_id,
-                    logs_request.container_id,
-                    Some(logs_request.tail),
-                    logs_request.follow,
-                    Some(logs_request.since),
-                )
-                .await?;
+    request: FollowContainerLogs,
+) -> Result<(), String> {
+    let request_key = RequestKey {
+        request_type: RequestType::FollowContainerLogs as i32,
+        request_id: Some(RequestId::Value(request.request_id.clone())),
+    };
+    let (output_tx, mut output_rx) = mpsc::channel::<LogLine>(FOLLOW_LOGS_CHANNEL_CAPACITY);
+
+    let key = &request_key;
+    let container_id = &request.container_id;
+    // Lines that are already waiting go out together, so a burst is one message
+    let forward = async move {
+        while let Some(line) = output_rx.recv().await {
+            let mut lines = vec![line];
+            while lines.len() < MAX_FOLLOW_LOGS_BATCH
+                && let Ok(line) = output_rx.try_recv()
+            {
+                lines.push(line);
             }
-            _ => info!("Unknown client command"),
-        },
-        Some(Payload::ServerResponse(resp)) => {
-            if let Some(ServerResponseKind::ServerStatus(status)) = &resp.kind {
-                info!(
-                    "Server status: {}, uptime: {}",
-                    status.status, status.uptime
-                );
+            let batch = ContainerLogs {
+                request_key: Some(key.clone()),
+                container_id: container_id.clone(),
+                lines,
+                ..Default::default()
+            };
+            send_node_response(tx, NodeResponseKind::ContainerLogs(batch)).await?;
+        }
+        Ok::<_, String>(())
+    };
+    let (result, sent) = tokio::join!(follow_container_logs(&request, output_tx), forward);
+    sent?;
+
+    let kind = match result {
+        Ok(()) => NodeResponseKind::ContainerLogs(ContainerLogs {
+            request_key: Some(request_key),
+            container_id: request.container_id,
+            done: true,
+            ..Default::default()
+        }),
+        Err(e) => {
+            error!("Failed to follow container logs: {}", e);
+            failed(RequestType::FollowContainerLogs, request.request_id, &*e)
+        }
+    };
+    send_node_response(tx, kind).await
+}
+
+pub async fn handle_get_container_archive(
+    tx: &mpsc::Sender<Envelope>,
+    request: GetContainerArchive,
+) -> Result<(), String> {
+    let request_key = RequestKey {
+        request_type: RequestType::GetContainerArchive as i32,
+        request_id: Some(RequestId::Value(request.request_id.clone())),
+    };
+    let (output_tx, mut output_rx) = mpsc::channel::<prost::bytes::Bytes>(ARCHIVE_CHANNEL_CAPACITY);
+
+    // Forward in the same task, so a cancelled request drops the archive stream too
+    let key = &request_key;
+    let forward = async move {
+        while let Some(chunk) = output_rx.recv().await {
+            let mut data = chunk.to_vec();
+            while data.len() < ARCHIVE_CHUNK_SIZE
+                && let Ok(chunk) = output_rx.try_recv()
+            {
+                data.extend_from_slice(&chunk);
             }
-            if let Some(ServerResponseKind::AuthResponse(response)) = &resp.kind {
-                info!(
-                    "Auth result: {}, message: {}",
-                    response.success, response.message
-                );
+            let archive = ContainerArchive {
+                request_key: Some(key.clone()),
+                data,
+                done: false,
+            };
+            send_node_response(tx, NodeResponseKind::ContainerArchive(archive)).await?;
+        }
+        Ok::<_, String>(())
+    };
+    let (result, sent) = tokio::join!(get_container_archive(&request, output_tx), forward);
+    sent?;
+
+    let kind = match result {
+        Ok(()) => NodeResponseKind::ContainerArchive(ContainerArchive {
+            request_key: Some(request_key),
+            done: true,
+            ..Default::default()
+        }),
+        Err(e) => {
+            error!("Failed to read container archive: {}", e);
+            failed(RequestType::GetContainerArchive, request.request_id, &*e)
+        }
+    };
+    send_node_response(tx, kind).await
+}
+
+pub async fn handle_put_container_archive(
+    tx: &mpsc::Sender<Envelope>,
+    request: PutContainerArchive,
+) -> Result<(), String> {
+    let request_id = request.request_id.clone();
+    let result = put_container_archive(request).await;
+    send_container_action(tx, RequestType::PutContainerArchive, request_id, result).await
+}
+
+pub async fn handle_build_image(
+    tx: &mpsc::Sender<Envelope>,
+    request: BuildImage,
+) -> Result<(), String> {
+    let request_key = RequestKey {
+        request_type: RequestType::BuildImage as i32,
+        request_id: Some(RequestId::Value(request.request_id.clone())),
+    };
+    let (output_tx, mut output_rx) = mpsc::channel::<ImageBuildOutput>(64);
+
+    // Forward in the same task, so a cancelled request drops the build stream too.
+    // The receiver moves in, so a failed send also ends the build.
+    let key = &request_key;
+    let forward = async move {
+        while let Some(mut output) = output_rx.recv().await {
+            output.request_key = Some(key.clone());
+            if send_node_response(tx, NodeResponseKind::ImageBuildOutput(output))
+                .await
+                .is_err()
+            {
+                return Err(String::from("Failed to send build output"));
             }
         }
-        _ => info!("Received unknown message"),
-    }
-    Ok(())
+        Ok(())
+    };
+    let (result, forwarded) = tokio::join!(build_image(request, output_tx), forward);
+    forwarded?;
+
+    let Err(e) = result else {
+        return Ok(());
+    };
+    error!("Failed to build image: {}", e);
+
+    let output = ImageBuildOutput {
+        request_key: Some(request_key),
+        error: e.to_string(),
+        done: true,
+        ..Default::default()
+    };
+    send_node_response(tx, NodeResponseKind::ImageBuildOutput(output)).await
+}
+
+pub async fn handle_inspect_image_manifest(
+    tx: &mpsc::Sender<Envelope>,
+    request_id: String,
+    image: String,
+) -> Result<(), String> {
+    let kind = match inspect_image_manifest(&image).await {
+        Ok(mut manifest) => {
+            manifest.request_key = Some(RequestKey {
+                request_type: RequestType::InspectImageManifest as i32,
+                request_id: Some(RequestId::Value(request_id)),
+            });
+            NodeResponseKind::ImageManifest(manifest)
+        }
+        Err(e) => {
+            error!("Failed to inspect image manifest: {}", e);
+            failed(RequestType::InspectImageManifest, request_id, &*e)
+        }
+    };
+
+    send_node_response(tx, kind).await
+}
+
+pub async fn handle_get_container_graph(
+    tx: &mpsc::Sender<Envelope>,
+    request_id: String,
+) -> Result<(), String> {
+    let kind = match get_container_graph().await {
+        Ok(mut graph) => {
+            graph.request_key = Some(RequestKey {
+                request_type: RequestType::GetContainerGraph as i32,
+                request_id: Some(RequestId::Value(request_id)),
+            });
+            NodeResponseKind::ContainerGraph(graph)
+        }
+        Err(e) => {
+            error!("Failed to get container graph: {}", e);
+            failed(RequestType::GetContainerGraph, request_id, &*e)
+        }
+    };
+
+    send_node_response(tx, kind).await
+}
+
+pub async fn handle_get_disk_usage(
+    tx: &mpsc::Sender<Envelope>,
+    request_id: String,
+) -> Result<(), String> {
+    let kind = match get_disk_usage().await {
+        Ok(mut usage) => {
+            usage.request_key = Some(RequestKey {
+                request_type: RequestType::GetDiskUsage as i32,
+                request_id: Some(RequestId::Value(request_id)),
+            });
+            NodeResponseKind::DiskUsage(usage)
+        }
+        Err(e) => {
+            error!("Failed to get disk usage: {}", e);
+            failed(RequestType::GetDiskUsage, request_id, &*e)
+        }
+    };
+
+    send_node_response(tx, kind).await
+}
+
+pub async fn handle_configure_log_forwarding(
+    tx: &mpsc::Sender<Envelope>,
+    request: ConfigureLogForwarding,
+) -> Result<(), String> {
+    let kind = match configure_log_forwarding(request.forwards) {
+        Ok(mut status) => {
+            status.request_key = Some(RequestKey {
+                request_type: RequestType::ConfigureLogForwarding as i32,
+                request_id: Some(RequestId::Value(request.request_id)),
+            });
+            NodeResponseKind::LogForwardingStatus(status)
+        }
+        Err(e) => {
+            error!("Failed to configure log forwarding: {}", e);
+            failed(RequestType::ConfigureLogForwarding, request.request_id, &*e)
+        }
+    };
+
+    send_node_response(tx, kind).await
+}
+
+pub async fn handle_configure_cleanup(
+    tx: &mpsc::Sender<Envelope>,
+    request: ConfigureCleanup,
+) -> Result<(), String> {
+    let kind = match configure_cleanup(request.rules, request.interval_secs) {
+        Ok(mut status) => {
+            status.request_key = Some(RequestKey {
+                request_type: RequestType::ConfigureCleanup as i32,
+                request_id: Some(RequestId::Value(request.request_id)),
+            });
+            NodeResponseKind::CleanupStatus(status)
+        }
+        Err(e) => {
+            error!("Failed to configure container cleanup: {}", e);
+            failed(RequestType::ConfigureCleanup, request.request_id, &*e)
+        }
+    };
+
+    send_node_response(tx, kind).await
+}
+
+pub async fn handle_get_cleanup(
+    tx: &mpsc::Sender<Envelope>,
+    request_id: String,
+) -> Result<(), String> {
+    let mut status = cleanup_status();
+    status.request_key = Some(RequestKey {
+        request_type: RequestType::GetCleanup as i32,
+        request_id: Some(RequestId::Value(request_id)),
+    });
+
+    send_node_response(tx, NodeResponseKind::CleanupStatus(status)).await
+}
+
+pub async fn handle_configure_trash(
+    tx: &mpsc::Sender<Envelope>,
+    request: ConfigureTrash,
+) -> Result<(), String> {
+    let mut status = configure_trash(request.retention_secs);
+    status.request_key = Some(RequestKey {
+        request_type: RequestType::ConfigureTrash as i32,
+        request_id: Some(RequestId::Value(request.request_id)),
+    });
+
+    send_node_response(tx, NodeResponseKind::TrashStatus(status)).await
+}
+
+pub async fn handle_get_trash(
+    tx: &mpsc::Sender<Envelope>,
+    request_id: String,
+) -> Result<(), String> {
+    let kind = match trash_status().await {
+        Ok(mut status) => {
+            status.request_key = Some(RequestKey {
+                request_type: RequestType::GetTrash as i32,
+                request_id: Some(RequestId::Value(request_id)),
+            });
+            NodeResponseKind::TrashStatus(status)
+        }
+        Err(e) => {
+            error!("Failed to list the trash: {}", e);
+            failed(RequestType::GetTrash, request_id, &*e)
+        }
+    };
+
+    send_node_response(tx, kind).await
+}
+
+pub async fn handle_get_log_forwarding(
+    tx: &mpsc::Sender<Envelope>,
+    request_id: String,
+) -> Result<(), String> {
+    let mut status = log_forwarding_status();
+    status.request_key = Some(RequestKey {
+        request_type: RequestType::GetLogForwarding as i32,
+        request_id: Some(RequestId::Value(request_id)),
+    });
+
+    send_node_response(tx, NodeResponseKind::LogForwardingStatus(status)).await
+}
+
+pub async fn handle_get_image_history(
+    tx: &mpsc::Sender<Envelope>,
+    request_id: String,
+    image: String,
+) -> Result<(), String> {
+    let kind = match get_image_history(&image).await {
+        Ok(mut history) => {
+            history.request_key = Some(RequestKey {
+                request_type: RequestType::GetImageHistory as i32,
+                request_id: Some(RequestId::Value(request_id)),
+            });
+            NodeResponseKind::ImageHistory(history)
+        }
+        Err(e) => {
+            error!("Failed to get image history: {}", e);
+            failed(RequestType::GetImageHistory, request_id, &*e)
+        }
+    };
+
+    send_node_response(tx, kind).await
+}
+
+pub async fn handle_apply_autostart(
+    tx: &mpsc::Sender<Envelope>,
+    request: ApplyAutostart,
+) -> Result<(), String> {
+    let kind = match apply_autostart(&request).await {
+        Ok(mut report) => {
+            report.request_key = Some(RequestKey {
+                request_type: RequestType::ApplyAutostart as i32,
+                request_id: Some(RequestId::Value(request.request_id)),
+            });
+            NodeResponseKind::AutostartReport(report)
+        }
+        Err(e) => {
+            error!("Failed to apply autostart: {}", e);
+            failed(RequestType::ApplyAutostart, request.request_id, &*e)
+        }
+    };
+
+    send_node_response(tx, kind).await
+}
+
+pub async fn handle_list_images(
+    tx: &mpsc::Sender<Envelope>,
+    request: ListImages,
+) -> Result<(), String> {
+    let kind = match list_images(&request).await {
+        Ok(mut images) => {
+            images.request_key = Some(RequestKey {
+                request_type: RequestType::ListImages as i32,
+                request_id: Some(RequestId::Value(request.request_id)),
+            });
+            NodeResponseKind::ImageList(images)
+        }
+        Err(e) => {
+            error!("Failed to list images: {}", e);
+            failed(RequestType::ListImages, request.request_id, &*e)
+        }
+    };
+
+    send_node_response(tx, kind).await
+}
+
+pub async fn handle_run_host_script(
+    tx: &mpsc::Sender<Envelope>,
+    request: RunHostScript,
+) -> Result<(), String> {
+    let kind = match run_host_script(&request).await {
+        Ok(mut result) => {
+            result.request_key = Some(RequestKey {
+                request_type: RequestType::RunHostScript as i32,
+                request_id: Some(RequestId::Value(request.request_id)),
+            });
+            NodeResponseKind::HostScriptResult(result)
+        }
+        Err(e) => {
+            error!("Failed to run host script {}: {}", request.name, e);
+            failed(RequestType::RunHostScript, request.request_id, &*e)
+        }
+    };
+
+    send_node_response(tx, kind).await
+}
+
+pub async fn handle_pull_image(
+    tx: &mpsc::Sender<Envelope>,
+    request: PullImage,
+) -> Result<(), String> {
+    let kind = match pull_image(&request).await {
+        Ok(mut pulled) => {
+            pulled.request_key = Some(RequestKey {
+                request_type: RequestType::PullImage as i32,
+                request_id: Some(RequestId::Value(request.request_id)),
+            });
+            NodeResponseKind::ImagePulled(pulled)
+        }
+        Err(e) => {
+            error!("Failed to pull image {}: {}", request.image, e);
+            failed(RequestType::PullImage, request.request_id, &*e)
+        }
+    };
+
+    send_node_response(tx, kind).await
+}
+
+pub async fn handle_remove_image(
+    tx: &mpsc::Sender<Envelope>,
+    request: RemoveImage,
+) -> Result<(), String> {
+    let kind = match remove_image(&request).await {
+        Ok(mut removed) => {
+            removed.request_key = Some(RequestKey {
+                request_type: RequestType::RemoveImage as i32,
+                request_id: Some(RequestId::Value(request.request_id)),
+            });
+            NodeResponseKind::ImagesRemoved(removed)
+        }
+        Err(e) => {
+            error!("Failed to remove image {}: {}", request.image, e);
+            failed(RequestType::RemoveImage, request.request_id, &*e)
+        }
+    };
+
+    send_node_response(tx, kind).await
+}
+
+pub async fn handle_prune_images(
+    tx: &mpsc::Sender<Envelope>,
+    request: PruneImages,
+) -> Result<(), String> {
+    let kind = match prune_images(&request).await {
+        Ok(mut removed) => {
+            removed.request_key = Some(RequestKey {
+                request_type: RequestType::PruneImages as i32,
+                request_id: Some(RequestId::Value(request.request_id)),
+            });
+            NodeResponseKind::ImagesRemoved(removed)
+        }
+        Err(e) => {
+            error!("Failed to prune images: {}", e);
+            failed(RequestType::PruneImages, request.request_id, &*e)
+        }
+    };
+
+    send_node_response(tx, kind).await
+}
+
+pub async fn handle_list_networks(
+    tx: &mpsc::Sender<Envelope>,
+    request: ListNetworks,
+) -> Result<(), String> {
+    let kind = match list_networks(&request).await {
+        Ok(mut networks) => {
+            networks.request_key = Some(RequestKey {
+                request_type: RequestType::ListNetworks as i32,
+                request_id: Some(RequestId::Value(request.request_id)),
+            });
+            NodeResponseKind::NetworkList(networks)
+        }
+        Err(e) => {
+            error!("Failed to list networks: {}", e);
+            failed(RequestType::ListNetworks, request.request_id, &*e)
+        }
+    };
+
+    send_node_response(tx, kind).await
+}
+
+pub async fn handle_inspect_network(
+    tx: &mpsc::Sender<Envelope>,
+    request: InspectNetwork,
+) -> Result<(), String> {
+    let kind = match inspect_network(&request).await {
+        Ok(mut networks) => {
+            networks.request_key = Some(RequestKey {
+                request_type: RequestType::InspectNetwork as i32,
+                request_id: Some(RequestId::Value(request.request_id)),
+            });
+            NodeResponseKind::NetworkList(networks)
+        }
+        Err(e) => {
+            error!("Failed to inspect network {}: {}", request.network, e);
+            failed(RequestType::InspectNetwork, request.request_id, &*e)
+        }
+    };
+
+    send_node_response(tx, kind).await
+}
+
+pub async fn handle_create_network(
+    tx: &mpsc::Sender<Envelope>,
+    request: CreateNetwork,
+) -> Result<(), String> {
+    let kind = match create_network(&request).await {
+        Ok(mut created) => {
+            created.request_key = Some(RequestKey {
+                request_type: RequestType::CreateNetwork as i32,
+                request_id: Some(RequestId::Value(request.request_id)),
+            });
+            NodeResponseKind::NetworkCreated(created)
+        }
+        Err(e) => {
+            error!("Failed to create network {}: {}", request.name, e);
+            failed(RequestType::CreateNetwork, request.request_id, &*e)
+        }
+    };
+
+    send_node_response(tx, kind).await
+}
+
+pub async fn handle_remove_network(
+    tx: &mpsc::Sender<Envelope>,
+    request: RemoveNetwork,
+) -> Result<(), String> {
+    let kind = match remove_network(&request).await {
+        Ok(mut removed) => {
+            removed.request_key = Some(RequestKey {
+                request_type: RequestType::RemoveNetwork as i32,
+                request_id: Some(RequestId::Value(request.request_id)),
+            });
+            NodeResponseKind::NetworkRemoved(removed)
+        }
+        Err(e) => {
+            error!("Failed to remove network {}: {}", request.network, e);
+            failed(RequestType::RemoveNetwork, request.request_id, &*e)
+        }
+    };
+
+    send_node_response(tx, kind).await
+}
+
+pub async fn handle_create_container(
+    tx: &mpsc::Sender<Envelope>,
+    request: CreateContainer,
+) -> Result<(), String> {
+    let kind = match create_container(&request).await {
+        Ok(mut created) => {
+            created.request_key = Some(RequestKey {
+                request_type: RequestType::CreateContainer as i32,
+                request_id: Some(RequestId::Value(request.request_id)),
+            });
+            NodeResponseKind::ContainerCreated(created)
+        }
+        Err(e) => {
+            error!("Failed to create container from {}: {}", request.image, e);
+            failed(RequestType::CreateContainer, request.request_id, &*e)
+        }
+    };
+
+    send_node_response(tx, kind).await
+}
+
+pub async fn handle_clone_container(
+    tx: &mpsc::Sender<Envelope>,
+    request: CloneContainer,
+) -> Result<(), String> {
+    let kind = match clone_container(&request).await {
+        Ok(mut created) => {
+            created.request_key = Some(RequestKey {
+                request_type: RequestType::CloneContainer as i32,
+                request_id: Some(RequestId::Value(request.request_id)),
+            });
+            NodeResponseKind::ContainerCreated(created)
+        }
+        Err(e) => {
+            error!("Failed to clone container {}: {}", request.container_id, e);
+            failed(RequestType::CloneContainer, request.request_id, &*e)
+        }
+    };
+
+    send_node_response(tx, kind).await
+}
+
+pub async fn handle_export_container(
+    tx: &mpsc::Sender<Envelope>,
+    request: ExportContainer,
+) -> Result<(), String> {
+    let request_key = RequestKey {
+        request_type: RequestType::ExportContainer as i32,
+        request_id: Some(RequestId::Value(request.request_id.clone())),
+    };
+    let (output_tx, mut output_rx) = mpsc::channel::<MigrationChunk>(MIGRATION_CHANNEL_CAPACITY);
+
+    // Forward in the same task, so a cancelled request drops the export too
+    let key = &request_key;
+    let forward = async move {
+        while let Some(mut chunk) = output_rx.recv().await {
+            chunk.request_key = Some(key.clone());
+            send_node_response(tx, NodeResponseKind::MigrationChunk(chunk)).await?;
+        }
+        Ok::<_, String>(())
+    };
+    let (result, sent) = tokio::join!(export_container(&request, output_tx), forward);
+    sent?;
+
+    let kind = match result {
+        Ok(()) => NodeResponseKind::MigrationChunk(MigrationChunk {
+            request_key: Some(request_key),
+            done: true,
+            ..Default::default()
+        }),
+        Err(e) => {
+            error!("Failed to export container {}: {}", request.container_id, e);
+            failed(RequestType::ExportContainer, request.request_id, &*e)
+        }
+    };
+    send_node_response(tx, kind).await
+}
+
+pub async fn handle_import_container(
+    tx: &mpsc::Sender<Envelope>,
+    request: ImportContainer,
+) -> Result<(), String> {
+    let request_key = RequestKey {
+        request_type: RequestType::ImportContainer as i32,
+        request_id: Some(RequestId::Value(request.request_id.clone())),
+    };
+    let request_id = request.request_id.clone();
+    let kind = match import_container(request).await {
+        Ok(ImportReply::Received(mut action)) => {
+            action.request_key = Some(request_key);
+            NodeResponseKind::ContainerAction(action)
+        }
+        Ok(ImportReply::Created(mut created)) => {
+            created.request_key = Some(request_key);
+            NodeResponseKind::ContainerCreated(created)
+        }
+        Err(e) => {
+            error!("Failed to import migrated container: {}", e);
+            failed(RequestType::ImportContainer, request_id, &*e)
+        }
+    };
+    send_node_response(tx, kind).await
+}
+
+pub async fn handle_get_node_metrics(
+    tx: &mpsc::Sender<Envelope>,
+    request_id: String,
+) -> Result<(), String> {
+    let kind = match get_node_metrics().await {
+        Ok(mut metrics) => {
+            metrics.request_key = Some(RequestKey {
+                request_type: RequestType::GetNodeMetrics as i32,
+                request_id: Some(RequestId::Value(request_id)),
+            });
+            NodeResponseKind::NodeMetrics(metrics)
+        }
+        Err(e) => {
+            error!("Failed to collect node metrics: {}", e);
+            failed(RequestType::GetNodeMetrics, request_id, &*e)
+        }
+    };
+
+    send_node_response(tx, kind).await
+}
+
+pub async fn handle_get_system_info(
+    tx: &mpsc::Sender<Envelope>,
+    request_id: String,
+) -> Result<(), String> {
+    let kind = match get_system_info().await {
+        Ok(mut info) => {
+            info.request_key = Some(RequestKey {
+                request_type: RequestType::GetSystemInfo as i32,
+                request_id: Some(RequestId::Value(request_id)),
+            });
+            NodeResponseKind::SystemInfo(Box::new(info))
+        }
+        Err(e) => {
+            error!("Failed to read Docker system info: {}", e);
+            failed(RequestType::GetSystemInfo, request_id, &*e)
+        }
+    };
+
+    send_node_response(tx, kind).await
+}
+
+pub async fn handle_get_system_disk_usage(
+    tx: &mpsc::Sender<Envelope>,
+    request_id: String,
+) -> Result<(), String> {
+    let kind = match get_system_disk_usage().await {
+        Ok(mut usage) => {
+            usage.request_key = Some(RequestKey {
+                request_type: RequestType::GetSystemDiskUsage as i32,
+                request_id: Some(RequestId::Value(request_id)),
+            });
+            NodeResponseKind::SystemDiskUsage(usage)
+        }
+        Err(e) => {
+            error!("Failed to read Docker disk usage: {}", e);
+            failed(RequestType::GetSystemDiskUsage, request_id, &*e)
+        }
+    };
+
+    send_node_response(tx, kind).await
+}
+
+pub async fn handle_prune_system(
+    tx: &mpsc::Sender<Envelope>,
+    request: PruneSystem,
+) -> Result<(), String> {
+    let kind = match prune_system(&request).await {
+        Ok(mut pruned) => {
+            pruned.request_key = Some(RequestKey {
+                request_type: RequestType::PruneSystem as i32,
+                request_id: Some(RequestId::Value(request.request_id)),
+            });
+            NodeResponseKind::SystemPruned(pruned)
+        }
+        Err(e) => {
+            error!("Failed to prune the Docker system: {}", e);
+            failed(RequestType::PruneSystem, request.request_id, &*e)
+        }
+    };
+
+    send_node_response(tx, kind).await
+}
+
+pub async fn handle_get_container_stats(
+    tx: &mpsc::Sender<Envelope>,
+    request: GetContainerStats,
+) -> Result<(), String> {
+    let request_id = request.request_id;
+    let container_id = request.container_id;
+    let streaming = request.stream;
+    let (max_samples, duration) = if streaming {
+        let secs = match request.duration_secs {
+            0 => DEFAULT_STATS_STREAM_SECS,
+            secs => secs.min(MAX_STATS_STREAM_SECS),
+        };
+        (None, Duration::from_secs(secs.into()))
+    } else {
+        (Some(1), ONE_SHOT_STATS_TIMEOUT)
+    };
+
+    let request_key = RequestKey {
+        request_type: RequestType::GetContainerStats as i32,
+        request_id: Some(RequestId::Value(request_id.clone())),
+    };
+    let (output_tx, mut output_rx) = mpsc::channel::<ContainerStats>(16);
+
+    let key = &request_key;
+    let forward = async move {
+        let mut sent = 0;
+        while let Some(mut sample) = output_rx.recv().await {
+            sample.request_key = Some(key.clone());
+            send_node_response(tx, NodeResponseKind::ContainerStats(sample)).await?;
+            sent += 1;
+        }
+        Ok::<_, String>(sent)
+    };
+    let (result, sent) = tokio::join!(
+        stream_container_stats(&container_id, max_samples, duration, output_tx),
+        forward
+    );
+    let sent = sent?;
+
+    let kind = match result {
+        Ok(()) if streaming => NodeResponseKind::ContainerStats(ContainerStats {
+            request_key: Some(request_key),
+            done: true,
+            ..Default::default()
+        }),
+        Ok(()) if sent > 0 => return Ok(()),
+        Ok(()) => node_error(
+            RequestType::GetContainerStats,
+            request_id,
+            "No stats available, is the container running?".to_string(),
+        ),
+        Err(e) => {
+            error!("Failed to read container stats: {}", e);
+            failed(RequestType::GetContainerStats, request_id, &*e)
+        }
+    };
+
+    send_node_response(tx, kind).await
+}
+
+pub async fn handle_get_top_containers(
+    tx: &mpsc::Sender<Envelope>,
+    request_id: String,
+    by: String,
+    limit: u32,
+) -> Result<(), String> {
+    let kind = match get_top_containers(&by, limit as usize).await {
+        Ok(containers) => NodeResponseKind::TopContainers(proto::generated::TopContainers {
+            request_key: Some(RequestKey {
+                request_type: RequestType::GetTopContainers as i32,
+                request_id: Some(RequestId::Value(request_id)),
+            }),
+            by,
+            containers,
+        }),
+        Err(e) => {
+            error!("Failed to get top containers: {}", e);
+            failed(RequestType::GetTopContainers, request_id, &*e)
+        }
+    };
+
+    send_node_response(tx, kind).await
+}
+
+pub async fn handle_ping(
+    tx: &mpsc::Sender<Envelope>,
+    request_id: String,
+    sent_at_ms: i64,
+) -> Result<(), String> {
+    let node_time_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0);
+
+    let kind = NodeResponseKind::Pong(proto::generated::Pong {
+        request_key: Some(RequestKey {
+            request_type: RequestType::Ping as i32,
+            request_id: Some(RequestId::Value(request_id)),
+        }),
+        sent_at_ms,
+        node_time_ms,
+    });
+    send_node_response(tx, kind).await
+}
+
+/// Carries out an administrator's broadcast and acknowledges it.
+pub async fn handle_broadcast(
+    tx: &mpsc::Sender<Envelope>,
+    notice: BroadcastNotice,
+) -> Result<(), String> {
+    if !notice.message.is_empty() {
+        warn!(
+            "Broadcast {} from coordinator: {}",
+            notice.broadcast_id, notice.message
+        );
+    }
+    let error = match notice.action.as_str() {
+        "notice" => String::new(),
+        "refresh" if refresh_container_states(tx).await => String::new(),
+        "refresh" => "Failed to push container states".to_string(),
+        action => format!("Unknown broadcast action: {}", action),
+    };
+
+    let kind = NodeResponseKind::BroadcastAck(BroadcastAck {
+        request_key: Some(RequestKey {
+            request_type: RequestType::Broadcast as i32,
+            request_id: Some(RequestId::Value(notice.request_id)),
+        }),
+        error,
+    });
+    send_node_response(tx, kind).await
+}
+
+pub async fn handle_attach_container(
+    tx: &mpsc::Sender<Envelope>,
+    sessions: &Sessions,
+    request: AttachContainer,
+) -> Result<(), String> {
+    let session_id = request.request_id;
+    let (control_tx, control_rx) = mpsc::channel(SESSION_CHANNEL_CAPACITY);
+    let (output_tx, output_rx) = mpsc::channel(SESSION_CHANNEL_CAPACITY);
+    sessions
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(session_id.clone(), control_tx);
+
+    // The session lives as long as the attach, so run it off the message loop
+    let attach = tokio::spawn(async move {
+        attach_container(
+            &request.container_id,
+            request.cols,
+            request.rows,
+            control_rx,
+            output_tx,
+        )
+        .await
+        .map(|()| None)
+    });
+    relay_session(
+        tx,
+        sessions,
+        session_id,
+        RequestType::AttachContainer,
+        output_rx,
+        attach,
+    );
+
+    Ok(())
+}
+
+pub async fn handle_exec_container(
+    tx: &mpsc::Sender<Envelope>,
+    sessions: &Sessions,
+    request: ExecContainer,
+) -> Result<(), String> {
+    let session_id = request.request_id.clone();
+    let (control_tx, control_rx) = mpsc::channel(SESSION_CHANNEL_CAPACITY);
+    let (output_tx, output_rx) = mpsc::channel(SESSION_CHANNEL_CAPACITY);
+    sessions
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(session_id.clone(), control_tx);
+
+    let exec = tokio::spawn(async move { exec_container(&request, control_rx, output_tx).await });
+    relay_session(
+        tx,
+        sessions,
+        session_id,
+        RequestType::ExecContainer,
+        output_rx,
+        exec,
+    );
+
+    Ok(())
+}
+
+/// Sends a session's output to the coordinator, then a closing message once
+/// `session` has ended, with its exit code or error.
+fn relay_session(
+    tx: &mpsc::Sender<Envelope>,
+    sessions: &Sessions,
+    session_id: String,
+    request_type: RequestType,
+    mut output_rx: mpsc::Receiver<Vec<u8>>,
+    session: JoinHandle<Result<Option<i64>, Box<dyn Error + Send + Sync>>>,
+) {
+    let tx = tx.clone();
+    let sessions = sessions.clone();
+    tokio::spawn(async move {
+        let request_key = RequestKey {
+            request_type: request_type as i32,
+            request_id: Some(RequestId::Value(session_id.clone())),
+        };
+
+        while let Some(data) = output_rx.recv().await {
+            let output = SessionOutput {
+                request_key: Some(request_key.clone()),
+                data,
+                ..Default::default()
+            };
+            if send_node_response(&tx, NodeResponseKind::SessionOutput(output))
+                .await
+                .is_err()
+            {
+                break;
+            }
+        }
+
+        let (exit_code, error) = match session.await {
+            Ok(Ok(exit_code)) => (exit_code, String::new()),
+            Ok(Err(e)) => (None, e.to_string()),
+            Err(e) => (None, e.to_string()),
+        };
+        if !error.is_empty() {
+            error!("Session {} failed: {}", session_id, error);
+        }
+        sessions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&session_id);
+
+        let output = SessionOutput {
+            request_key: Some(request_key),
+            closed: true,
+            error,
+            exit_code,
+            ..Default::default()
+        };
+        if let Err(e) = send_node_response(&tx, NodeResponseKind::SessionOutput(output)).await {
+            error!("{}", e);
+        }
+    });
+}
+
+pub async fn handle_session_control(
+    sessions: &Sessions,
+    session_id: &str,
+    control: SessionControl,
+) -> Result<(), String> {
+    let control_tx = sessions
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(session_id)
+        .cloned();
+    let Some(control_tx) = control_tx else {
+        // Already ended, e.g. the process exited while input was in flight
+        return Ok(());
+    };
+
+    control_tx
+        .send(control)
+        .await
+        .map_err(|_| format!("Session {} is closed", session_id))
+}
+
+/// Compresses a large response if the node is set up to, and replaces one still
+/// too big for the transport with an error the caller can act on, instead of
+/// the whole stream failing with an opaque transport error.
+fn prepare_outbound(
+    envelope: Envelope,
+    compression: Compression,
+    max_message_size: usize,
+) -> Envelope {
+    let request_key = match &envelope.payload {
+        Some(Payload::NodeResponse(response)) => response.request_key().cloned(),
+        _ => return envelope,
+    };
+    let envelope = envelope.compress(compression);
+    let size = envelope.encoded_len();
+    if size <= max_message_size {
+        return envelope;
+    }
+
+    error!(
+        "Response of {} bytes exceeds the {} byte message limit",
+        size, max_message_size
+    );
+    Envelope {
+        payload: Some(Payload::NodeResponse(NodeResponse {
+            kind: Some(NodeResponseKind::Error(NodeError {
+                request_key,
+                message: format!(
+                    "Response of {} bytes exceeds the {} byte message limit; narrow the request (e.g. a smaller tail or a later since)",
+                    size, max_message_size
+                ),
+                code: ErrorCode::Unspecified.into(),
+            })),
+        })),
+        metadata: envelope.metadata,
+        ..Default::default()
+    }
+}
+
+fn node_error(request_type: RequestType, request_id: String, message: String) -> NodeResponseKind {
+    NodeResponseKind::Error(NodeError {
+        request_key: Some(RequestKey {
+            request_type: request_type as i32,
+            request_id: Some(RequestId::Value(request_id)),
+        }),
+        message,
+        code: ErrorCode::Unspecified.into(),
+    })
+}
+
+// A NodeError for `error`, with the code of the Docker failure behind it
+fn failed(
+    request_type: RequestType,
+    request_id: String,
+    error: &(dyn Error + 'static),
+) -> NodeResponseKind {
+    NodeResponseKind::Error(NodeError {
+        request_key: Some(RequestKey {
+            request_type: request_type as i32,
+            request_id: Some(RequestId::Value(request_id)),
+        }),
+        message: error.to_string(),
+        code: error_code(error).into(),
+    })
+}
+
+async fn send_node_response(
+    tx: &mpsc::Sender<Envelope>,
+    kind: NodeResponseKind,
+) -> Result<(), String> {
+    let response = Envelope {
+        payload: Some(Payload::NodeResponse(NodeResponse { kind: Some(kind) })),
+        ..Default::default()
+    };
+
+    tx.send(response)
+        .await
+        .map_err(|_| String::from("Failed to send response"))
+}
+
+/// Starts a request in its own task, so slow Docker calls do not hold up the message
+/// loop and the coordinator can abort the request with `CancelRequest`.
+fn spawn_request(
+    tx: &mpsc::Sender<Envelope>,
+    sessions: &Sessions,
+    in_flight: &InFlight,
+    command: NodeCommandKind,
+    metadata: HashMap<String, String>,
+) {
+    let request_id = command.request_id().to_string();
+    // Logs of the request carry the coordinator's ids for it
+    let span = match metadata.get(METADATA_TRACE_ID) {
+        Some(trace_id) => info_span!(
+            "request",
+            trace_id = %trace_id,
+            actor = %metadata.get(METADATA_ACTOR).map_or("-", String::as_str),
+        ),
+        None => Span::none(),
+    };
+    let tx = echo_metadata(tx, metadata);
+    let sessions = sessions.clone();
+    let tasks = in_flight.clone();
+    let task_request_id = request_id.clone();
+    // Queued here, in the order the commands arrived
+    let queued = lifecycle_container(&command).map(queue_on_container);
+
+    // Held while spawning, so a task that finishes at once cannot deregister
+    // itself before it is registered
+    let mut in_flight = in_flight.lock().unwrap_or_else(|e| e.into_inner());
+    let task = tokio::spawn(
+        async move {
+            // `_done` is held until the command is done, then lets the next one run
+            let (earlier, _done) = queued.unzip();
+            if let Some(earlier) = earlier.flatten() {
+                let _ = earlier.await;
+            }
+            if let Err(e) = handle_request(command, &tx, &sessions).await {
+                error!("Error processing request {}: {}", task_request_id, e);
+            }
+            tasks
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(&task_request_id);
+        }
+        .instrument(span),
+    );
+    in_flight.insert(request_id, task.abort_handle());
+}
+
+/// Container whose state `command` changes. Requests run concurrently, so these
+/// are queued per container: a stop sent right after a start waits for it
+/// instead of racing it in Docker.
+fn lifecycle_container(command: &NodeCommandKind) -> Option<&str> {
+    match command {
+        NodeCommandKind::StartContainer(c) => Some(&c.container_id),
+        NodeCommandKind::StopContainer(c) => Some(&c.container_id),
+        NodeCommandKind::RestartContainer(c) => Some(&c.container_id),
+        NodeCommandKind::PauseContainer(c) => Some(&c.container_id),
+        NodeCommandKind::UnpauseContainer(c) => Some(&c.container_id),
+        NodeCommandKind::DeleteContainer(c) => Some(&c.container_id),
+        _ => None,
+    }
+}
+
+/// Queues a lifecycle command on `container_id`. Returns the command it waits
+/// for, if one is still queued or running, and the sender to drop when done.
+fn queue_on_container(container_id: &str) -> (Option<oneshot::Receiver<()>>, oneshot::Sender<()>) {
+    let (done_tx, done_rx) = oneshot::channel();
+    let mut queues = CONTAINER_QUEUES.lock().unwrap_or_else(|e| e.into_inner());
+    queues.retain(|_, done| done.try_recv() == Err(TryRecvError::Empty));
+    let earlier = queues.insert(container_id.to_string(), done_rx);
+    (earlier, done_tx)
+}
+
+/// A sender for one request's responses that copies the metadata of its
+/// command onto each of them before they join the stream. Closes once the
+/// stream does, like the stream's own sender.
+fn echo_metadata(
+    tx: &mpsc::Sender<Envelope>,
+    metadata: HashMap<String, String>,
+) -> mpsc::Sender<Envelope> {
+    if metadata.is_empty() {
+        return tx.clone();
+    }
+    let tx = tx.clone();
+    let (echo_tx, mut echo_rx) = mpsc::channel::<Envelope>(ECHO_CHANNEL_CAPACITY);
+    tokio::spawn(async move {
+        while let Some(mut envelope) = echo_rx.recv().await {
+            envelope.metadata = metadata.clone();
+            if tx.send(envelope).await.is_err() {
+                break;
+            }
+        }
+    });
+    echo_tx
+}
+
+/// Aborts an in-flight request, which drops its Docker calls, or detaches an
+/// interactive session. Unknown ids have already finished.
+pub async fn handle_cancel_request(
+    sessions: &Sessions,
+    in_flight: &InFlight,
+    request_id: &str,
+) -> Result<(), String> {
+    let task = in_flight
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(request_id);
+    if let Some(task) = task {
+        info!("Cancelling request {}", request_id);
+        task.abort();
+        return Ok(());
+    }
+
+    handle_session_control(sessions, request_id, SessionControl::Close).await
+}
+
+async fn handle_request(
+    command: NodeCommandKind,
+    tx: &mpsc::Sender<Envelope>,
+    sessions: &Sessions,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    match command {
+        NodeCommandKind::GetNodeContainers(get_containers_request) => {
+            handle_get_client_containers(tx, get_containers_request.request_id).await?;
+        }
+        NodeCommandKind::GetNodeContainersWithStatus(get_containers_with_status_request) => {
+            handle_get_client_containers_with_status(tx, get_containers_with_status_request)
+                .await?;
+        }
+        NodeCommandKind::GetContainerStatus(get_status_request) => {
+            handle_get_container_status(
+                tx,
+                get_status_request.request_id,
+                get_status_request.container_id,
+            )
+            .await?;
+        }
+        NodeCommandKind::StartContainer(start_request) => {
+            handle_start_container(tx, start_request.request_id, start_request.container_id)
+                .await?;
+        }
+        NodeCommandKind::StopContainer(stop_request) => {
+            handle_stop_container(tx, stop_request.request_id, stop_request.container_id).await?;
+        }
+        NodeCommandKind::RestartContainer(restart_request) => {
+            handle_restart_container(
+                tx,
+                restart_request.request_id,
+                restart_request.container_id,
+                restart_request.timeout_secs,
+            )
+            .await?;
+        }
+        NodeCommandKind::PauseContainer(pause_request) => {
+            handle_pause_container(tx, pause_request.request_id, pause_request.container_id)
+                .await?;
+        }
+        NodeCommandKind::UnpauseContainer(unpause_request) => {
+            handle_unpause_container(tx, unpause_request.request_id, unpause_request.container_id)
+                .await?;
+        }
+        NodeCommandKind::GetContainerArchive(archive_request) => {
+            handle_get_container_archive(tx, archive_request).await?;
+        }
+        NodeCommandKind::PutContainerArchive(archive_request) => {
+            handle_put_container_archive(tx, archive_request).await?;
+        }
+        NodeCommandKind::ExportContainer(export_request) => {
+            handle_export_container(tx, export_request).await?;
+        }
+        NodeCommandKind::ImportContainer(import_request) => {
+            handle_import_container(tx, import_request).await?;
+        }
+        NodeCommandKind::DeleteContainer(delete_request) => {
+            handle_delete_container(tx, delete_request.request_id, delete_request.container_id)
+                .await?;
+        }
+        NodeCommandKind::GetContainerLogs(logs_request) => {
+            handle_get_container_logs(tx, logs_request).await?;
+        }
+        NodeCommandKind::BuildImage(build_request) => {
+            handle_build_image(tx, build_request).await?;
+        }
+        NodeCommandKind::InspectImageManifest(manifest_request) => {
+            handle_inspect_image_manifest(tx, manifest_request.request_id, manifest_request.image)
+                .await?;
+        }
+        NodeCommandKind::GetImageHistory(history_request) => {
+            handle_get_image_history(tx, history_request.request_id, history_request.image).await?;
+        }
+        NodeCommandKind::GetContainerGraph(graph_request) => {
+            handle_get_container_graph(tx, graph_request.request_id).await?;
+        }
+        NodeCommandKind::GetDiskUsage(usage_request) => {
+            handle_get_disk_usage(tx, usage_request.request_id).await?;
+        }
+        NodeCommandKind::ConfigureLogForwarding(forwarding_request) => {
+            handle_configure_log_forwarding(tx, forwarding_request).await?;
+        }
+        NodeCommandKind::GetLogForwarding(forwarding_request) => {
+            handle_get_log_forwarding(tx, forwarding_request.request_id).await?;
+        }
+        NodeCommandKind::ConfigureCleanup(cleanup_request) => {
+            handle_configure_cleanup(tx, cleanup_request).await?;
+        }
+        NodeCommandKind::ConfigureTrash(trash_request) => {
+            handle_configure_trash(tx, trash_request).await?;
+        }
+        NodeCommandKind::GetTrash(trash_request) => {
+            handle_get_trash(tx, trash_request.request_id).await?;
+        }
+        NodeCommandKind::RestoreContainer(restore_request) => {
+            handle_restore_container(tx, restore_request.request_id, restore_request.container_id)
+                .await?;
+        }
+        NodeCommandKind::GetCleanup(cleanup_request) => {
+            handle_get_cleanup(tx, cleanup_request.request_id).await?;
+        }
+        NodeCommandKind::RunHostScript(script_request) => {
+            handle_run_host_script(tx, script_request).await?;
+        }
+        NodeCommandKind::ListImages(images_request) => {
+            handle_list_images(tx, images_request).await?;
+        }
+        NodeCommandKind::PullImage(pull_request) => {
+            handle_pull_image(tx, pull_request).await?;
+        }
+        NodeCommandKind::RemoveImage(remove_request) => {
+            handle_remove_image(tx, remove_request).await?;
+        }
+        NodeCommandKind::PruneImages(prune_request) => {
+            handle_prune_images(tx, prune_request).await?;
+        }
+        NodeCommandKind::ListNetworks(networks_request) => {
+            handle_list_networks(tx, networks_request).await?;
+        }
+        NodeCommandKind::InspectNetwork(inspect_request) => {
+            handle_inspect_network(tx, inspect_request).await?;
+        }
+        NodeCommandKind::CreateNetwork(create_request) => {
+            handle_create_network(tx, create_request).await?;
+        }
+        NodeCommandKind::RemoveNetwork(remove_request) => {
+            handle_remove_network(tx, remove_request).await?;
+        }
+        NodeCommandKind::ApplyAutostart(autostart_request) => {
+            handle_apply_autostart(tx, autostart_request).await?;
+        }
+        NodeCommandKind::CreateContainer(create_request) => {
+            handle_create_container(tx, create_request).await?;
+        }
+        NodeCommandKind::CloneContainer(clone_request) => {
+            handle_clone_container(tx, clone_request).await?;
+        }
+        NodeCommandKind::GetNodeMetrics(metrics_request) => {
+            handle_get_node_metrics(tx, metrics_request.request_id).await?;
+        }
+        NodeCommandKind::GetSystemInfo(info_request) => {
+            handle_get_system_info(tx, info_request.request_id).await?;
+        }
+        NodeCommandKind::GetSystemDiskUsage(usage_request) => {
+            handle_get_system_disk_usage(tx, usage_request.request_id).await?;
+        }
+        NodeCommandKind::PruneSystem(prune_request) => {
+            handle_prune_system(tx, prune_request).await?;
+        }
+        NodeCommandKind::FollowContainerLogs(follow_request) => {
+            handle_follow_container_logs(tx, follow_request).await?;
+        }
+        NodeCommandKind::GetContainerStats(stats_request) => {
+            handle_get_container_stats(tx, stats_request).await?;
+        }
+        NodeCommandKind::GetTopContainers(top_request) => {
+            handle_get_top_containers(
+                tx,
+                top_request.request_id,
+                top_request.by,
+                top_request.limit,
+            )
+            .await?;
+        }
+        NodeCommandKind::Ping(ping) => {
+            handle_ping(tx, ping.request_id, ping.sent_at_ms).await?;
+        }
+        NodeCommandKind::AttachContainer(attach_request) => {
+            handle_attach_container(tx, sessions, attach_request).await?;
+        }
+        NodeCommandKind::ExecContainer(exec_request) => {
+            handle_exec_container(tx, sessions, exec_request).await?;
+        }
+        // Session messages and cancellations are handled on the message loop, in order
+        NodeCommandKind::SessionInput(_)
+        | NodeCommandKind::ResizeSession(_)
+        | NodeCommandKind::CloseSession(_)
+        | NodeCommandKind::CancelRequest(_) => info!("Unexpected request command"),
+    }
+    Ok(())
+}
+
+pub async fn process_incoming_message(
+    envelope: Envelope,
+    tx: &mpsc::Sender<Envelope>,
+    sessions: &Sessions,
+    in_flight: &InFlight,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    match envelope.payload {
+        Some(Payload::NodeCommand(cmd)) => match cmd.kind {
+            Some(NodeCommandKind::SessionInput(input)) => {
+                handle_session_control(
+                    sessions,
+                    &input.request_id,
+                    SessionControl::Input(input.data),
+                )
+                .await?;
+            }
+            Some(NodeCommandKind::ResizeSession(resize)) => {
+                handle_session_control(
+                    sessions,
+                    &resize.request_id,
+                    SessionControl::Resize {
+                        cols: resize.cols,
+                        rows: resize.rows,
+                    },
+                )
+                .await?;
+            }
+            Some(NodeCommandKind::CloseSession(close)) => {
+                handle_session_control(sessions, &close.request_id, SessionControl::Close).await?;
+            }
+            Some(NodeCommandKind::CancelRequest(cancel)) => {
+                handle_cancel_request(sessions, in_flight, &cancel.request_id).await?;
+            }
+            Some(command) => spawn_request(tx, sessions, in_flight, command, envelope.metadata),
+            None => info!("Unknown client command"),
+        },
+        Some(Payload::ServerCommand(ServerCommand {
+            kind: Some(ServerCommandKind::BroadcastNotice(notice)),
+        })) => {
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                if let Err(e) = handle_broadcast(&tx, notice).await {
+                    error!("Failed to acknowledge broadcast: {}", e);
+                }
+            });
+        }
+        Some(Payload::ServerResponse(resp)) => {
+            if let Some(ServerResponseKind::ServerStatus(status)) = &resp.kind {
+                info!(
+                    "Server status: {}, uptime: {}",
+                    status.status, status.uptime
+                );
+            }
+            if let Some(ServerResponseKind::AuthResponse(response)) = &resp.kind {
+                info!(
+                    "Auth result: {}, message: {}",
+                    response.success, response.message
+                );
+            }
+        }
+        _ => info!("Received unknown message"),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use proto::generated::StopContainer;
+
+    use super::*;
+
+    // The queues are shared by the whole process, so each test uses its own ids
+
+    #[test]
+    fn lifecycle_commands_wait_for_the_previous_one() {
+        let (earlier, first_done) = queue_on_container("queue-a");
+        assert!(earlier.is_none());
+        let (earlier, second_done) = queue_on_container("queue-a");
+        let mut waits_for_first = earlier.unwrap();
+        let (earlier, _third_done) = queue_on_container("queue-a");
+        let mut waits_for_second = earlier.unwrap();
+
+        assert_eq!(waits_for_first.try_recv(), Err(TryRecvError::Empty));
+        drop(first_done);
+        assert_eq!(waits_for_first.try_recv(), Err(TryRecvError::Closed));
+        assert_eq!(waits_for_second.try_recv(), Err(TryRecvError::Empty));
+        drop(second_done);
+        assert_eq!(waits_for_second.try_recv(), Err(TryRecvError::Closed));
+    }
+
+    #[test]
+    fn containers_are_queued_separately() {
+        let (_, _a_done) = queue_on_container("queue-b");
+        let (earlier, _c_done) = queue_on_container("queue-c");
+        assert!(earlier.is_none());
+    }
+
+    #[test]
+    fn finished_commands_leave_the_queue() {
+        let (_, done) = queue_on_container("queue-d");
+        drop(done);
+        let (earlier, _done) = queue_on_container("queue-d");
+        assert!(earlier.is_none());
+    }
+
+    #[test]
+    fn only_lifecycle_commands_are_queued() {
+        let stop = NodeCommandKind::StopContainer(StopContainer {
+            container_id: "web".to_string(),
+            ..Default::default()
+        });
+        assert_eq!(lifecycle_container(&stop), Some("web"));
+        let logs = NodeCommandKind::GetContainerLogs(GetContainerLogs {
+            container_id: "web".to_string(),
+            ..Default::default()
+        });
+        assert_eq!(lifecycle_container(&logs), None);
+    }
 }
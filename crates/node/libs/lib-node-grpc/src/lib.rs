@@ -1,3 +1,3 @@
 pub mod grpc_client;
 
-pub use grpc_client::run_grpc_client;
+pub use grpc_client::{GrpcClientTls, run_grpc_client};
@@ -1,9 +1,40 @@
+pub use lib_node_containers::{
+    DockerEndpoint, DockerLimits, DockerTls, EventWatchConfig, HostScripts, LogBufferConfig,
+    configure_docker_endpoint, configure_event_watch, configure_host_scripts, configure_log_buffer,
+    configure_secrets_dir,
+};
+
+pub use lib_node_grpc::GrpcClientTls;
+pub use proto::generated::Compression;
+
+use tokio_util::sync::CancellationToken;
+
+use lib_node_containers::{
+    DEFAULT_DOCKER_HEALTH_INTERVAL, spawn_docker_health_check, spawn_log_buffers,
+};
+
+// Node settings are passed one by one, as they come from the command line
+#[allow(clippy::too_many_arguments)]
 pub async fn run(
-    coordinator_address: &str,
+    coordinator_addresses: &[String],
     node_id: &str,
     password: &str,
     is_self_hosted: bool,
+    max_message_size: usize,
+    compression: Compression,
+    docker_limits: DockerLimits,
+    tls: Option<GrpcClientTls>,
+    shutdown: CancellationToken,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    lib_node_containers::configure_docker_limits(docker_limits);
+    spawn_docker_health_check(DEFAULT_DOCKER_HEALTH_INTERVAL);
+    spawn_log_buffers();
+    let coordinator_address = coordinator_addresses.join(", ");
+    let primary_address = coordinator_addresses
+        .first()
+        .map(String::as_str)
+        .unwrap_or_default();
+
     if is_self_hosted {
         println!(
             "==============================\n\
@@ -20,13 +51,13 @@ Example:\n\
         );
     } else {
         // Убираем порт, если есть
-        let host_only = coordinator_address
+        let host_only = primary_address
             .split("://")
             .last()
-            .unwrap_or(coordinator_address) // убираем протокол, если он есть
+            .unwrap_or(primary_address) // убираем протокол, если он есть
             .split(':')
             .next()
-            .unwrap_or(coordinator_address); // убираем порт
+            .unwrap_or(primary_address); // убираем порт
 
         // Replace docklord-coordinator with localhost in the example URL
         let example_host = if host_only == "docklord-coordinator" {
@@ -52,5 +83,14 @@ Example:\n\
 
     println!();
 
-    lib_node_grpc::run_grpc_client(coordinator_address, node_id, password).await
+    lib_node_grpc::run_grpc_client(
+        coordinator_addresses,
+        node_id,
+        password,
+        max_message_size,
+        compression,
+        tls.as_ref(),
+        shutdown,
+    )
+    .await
 }
@@ -11,6 +11,12 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .build_server(true)
         .build_client(true)
         .out_dir(&out_dir)
+        // Keeps every NodeResponse as small as its usual variants
+        .boxed(".conversation.NodeResponse.kind.system_info")
+        // REST handlers return node replies as they are, so the HTTP API
+        // cannot drift from the wire protocol; correlation keys stay internal
+        .type_attribute(".conversation", "#[derive(serde::Serialize)]")
+        .field_attribute("request_key", "#[serde(skip)]")
         .compile(&["conversation.proto"], &["."])?;
 
     println!("cargo:rerun-if-changed=conversation.proto");
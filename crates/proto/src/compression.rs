@@ -0,0 +1,149 @@
+use std::io::{self, Read, Write};
+
+use prost::Message;
+
+use crate::generated::{Compression, Envelope, NodeResponse, envelope::Payload};
+
+/// Node responses smaller than this, encoded, are sent as they are.
+pub const MIN_COMPRESSED_SIZE: usize = 64 * 1024;
+/// Most bytes a compressed payload may expand to, so a corrupt or hostile
+/// envelope cannot exhaust the receiver's memory.
+pub const MAX_DECOMPRESSED_SIZE: usize = 256 * 1024 * 1024;
+
+impl Envelope {
+    /// Replaces a NodeResponse payload of at least `MIN_COMPRESSED_SIZE`
+    /// encoded bytes with its compressed form. Anything else, and payloads
+    /// that would not get smaller, are left as they are.
+    pub fn compress(self, compression: Compression) -> Self {
+        let Some(Payload::NodeResponse(response)) = &self.payload else {
+            return self;
+        };
+        if compression == Compression::None || response.encoded_len() < MIN_COMPRESSED_SIZE {
+            return self;
+        }
+
+        let encoded = response.encode_to_vec();
+        let compressed = match compress(&encoded, compression) {
+            Ok(compressed) if compressed.len() < encoded.len() => compressed,
+            _ => return self,
+        };
+        Self {
+            payload: None,
+            metadata: self.metadata,
+            compression: compression.into(),
+            compressed_payload: compressed,
+        }
+    }
+
+    /// Restores a payload compressed by `compress`; other envelopes pass unchanged.
+    pub fn decompress(self) -> io::Result<Self> {
+        let compression = self.compression();
+        if compression == Compression::None {
+            return Ok(self);
+        }
+
+        let encoded = decompress(&self.compressed_payload, compression)?;
+        let response = NodeResponse::decode(encoded.as_slice())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(Self {
+            payload: Some(Payload::NodeResponse(response)),
+            metadata: self.metadata,
+            ..Default::default()
+        })
+    }
+}
+
+fn compress(data: &[u8], compression: Compression) -> io::Result<Vec<u8>> {
+    match compression {
+        Compression::None => Ok(data.to_vec()),
+        Compression::Gzip => {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(data)?;
+            encoder.finish()
+        }
+        Compression::Zstd => zstd::encode_all(data, 0),
+    }
+}
+
+fn decompress(data: &[u8], compression: Compression) -> io::Result<Vec<u8>> {
+    let reader: Box<dyn Read + '_> = match compression {
+        Compression::None => return Ok(data.to_vec()),
+        Compression::Gzip => Box::new(flate2::read::GzDecoder::new(data)),
+        Compression::Zstd => Box::new(zstd::Decoder::new(data)?),
+    };
+    let mut decompressed = Vec::new();
+    reader
+        .take(MAX_DECOMPRESSED_SIZE as u64 + 1)
+        .read_to_end(&mut decompressed)?;
+    if decompressed.len() > MAX_DECOMPRESSED_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "payload expands to more than {} bytes",
+                MAX_DECOMPRESSED_SIZE
+            ),
+        ));
+    }
+    Ok(decompressed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generated::{ContainerLogs, LogLine, node_response};
+
+    fn logs_response(bytes: usize) -> Envelope {
+        let line = LogLine {
+            text: "x".repeat(bytes),
+            stream: "stdout".to_string(),
+            timestamp: String::new(),
+        };
+        Envelope {
+            payload: Some(Payload::NodeResponse(NodeResponse {
+                kind: Some(node_response::Kind::ContainerLogs(ContainerLogs {
+                    lines: vec![line],
+                    ..Default::default()
+                })),
+            })),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn round_trips_large_responses() {
+        for compression in [Compression::Gzip, Compression::Zstd] {
+            let envelope = logs_response(MIN_COMPRESSED_SIZE);
+            let compressed = envelope.clone().compress(compression);
+            assert!(compressed.payload.is_none());
+            assert!(compressed.compressed_payload.len() < MIN_COMPRESSED_SIZE);
+            assert_eq!(compressed.decompress().unwrap(), envelope);
+        }
+    }
+
+    #[test]
+    fn leaves_small_responses_alone() {
+        let envelope = logs_response(100);
+        assert_eq!(envelope.clone().compress(Compression::Zstd), envelope);
+    }
+
+    // Zeros compress to a few KiB, so a tiny envelope could claim any size
+    #[test]
+    fn refuses_payloads_expanding_past_the_cap() {
+        let mut encoder = zstd::Encoder::new(Vec::new(), 0).unwrap();
+        let zeros = vec![0; 1024 * 1024];
+        for _ in 0..MAX_DECOMPRESSED_SIZE / zeros.len() {
+            encoder.write_all(&zeros).unwrap();
+        }
+        encoder.write_all(&[0]).unwrap();
+        let envelope = Envelope {
+            compression: Compression::Zstd.into(),
+            compressed_payload: encoder.finish().unwrap(),
+            ..Default::default()
+        };
+
+        let err = envelope.decompress().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("expands to more than"));
+    }
+}
@@ -1,13 +1,28 @@
 // This file is @generated by prost-build.
 /// Envelope wraps all possible messages between server and node
+#[derive(serde::Serialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Envelope {
+    /// Correlation ids set by the coordinator on node commands ("request_id",
+    /// "trace_id", "actor") and echoed by the node on every response to them
+    #[prost(map = "string, string", tag = "5")]
+    pub metadata: ::std::collections::HashMap<
+        ::prost::alloc::string::String,
+        ::prost::alloc::string::String,
+    >,
+    /// Set instead of `payload` by a node that compresses its large responses:
+    /// the encoded NodeResponse, compressed with `compression`
+    #[prost(enumeration = "Compression", tag = "6")]
+    pub compression: i32,
+    #[prost(bytes = "vec", tag = "7")]
+    pub compressed_payload: ::prost::alloc::vec::Vec<u8>,
     #[prost(oneof = "envelope::Payload", tags = "1, 2, 3, 4")]
     pub payload: ::core::option::Option<envelope::Payload>,
 }
 /// Nested message and enum types in `Envelope`.
 pub mod envelope {
+    #[derive(serde::Serialize)]
     #[allow(clippy::derive_partial_eq_without_eq)]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum Payload {
@@ -22,14 +37,16 @@ pub mod envelope {
     }
 }
 /// Commands sent from server to node
+#[derive(serde::Serialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ServerCommand {
-    #[prost(oneof = "server_command::Kind", tags = "1, 2")]
+    #[prost(oneof = "server_command::Kind", tags = "1, 2, 3, 4, 5")]
     pub kind: ::core::option::Option<server_command::Kind>,
 }
 /// Nested message and enum types in `ServerCommand`.
 pub mod server_command {
+    #[derive(serde::Serialize)]
     #[allow(clippy::derive_partial_eq_without_eq)]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum Kind {
@@ -37,17 +54,31 @@ pub mod server_command {
         GetServerStatus(super::GetServerStatus),
         #[prost(message, tag = "2")]
         AuthRequest(super::AuthRequest),
+        /// Administrator notice, coordinator to node
+        #[prost(message, tag = "3")]
+        BroadcastNotice(super::BroadcastNotice),
+        /// The coordinator is going away; reconnect
+        #[prost(message, tag = "4")]
+        CoordinatorShutdown(super::CoordinatorShutdown),
+        /// The node is alive, node to coordinator
+        #[prost(message, tag = "5")]
+        Heartbeat(super::Heartbeat),
     }
 }
 /// Commands sent from node to server (AI-extended)
+#[derive(serde::Serialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct NodeCommand {
-    #[prost(oneof = "node_command::Kind", tags = "1, 2, 3, 4, 5, 6, 7")]
+    #[prost(
+        oneof = "node_command::Kind",
+        tags = "1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47, 48, 49, 50, 51, 52"
+    )]
     pub kind: ::core::option::Option<node_command::Kind>,
 }
 /// Nested message and enum types in `NodeCommand`.
 pub mod node_command {
+    #[derive(serde::Serialize)]
     #[allow(clippy::derive_partial_eq_without_eq)]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum Kind {
@@ -72,9 +103,138 @@ pub mod node_command {
         /// Get logs with options
         #[prost(message, tag = "7")]
         GetContainerLogs(super::GetContainerLogs),
+        /// Build an image, streaming output
+        #[prost(message, tag = "8")]
+        BuildImage(super::BuildImage),
+        /// Resolve a registry manifest
+        #[prost(message, tag = "9")]
+        InspectImageManifest(super::InspectImageManifest),
+        /// Host-level metrics (PSI)
+        #[prost(message, tag = "10")]
+        GetNodeMetrics(super::GetNodeMetrics),
+        /// Resource stats, one-shot or streamed
+        #[prost(message, tag = "11")]
+        GetContainerStats(super::GetContainerStats),
+        /// Heaviest containers from one stats sweep
+        #[prost(message, tag = "12")]
+        GetTopContainers(super::GetTopContainers),
+        /// Latency probe
+        #[prost(message, tag = "13")]
+        Ping(super::Ping),
+        /// Attach to the main process of a TTY container
+        #[prost(message, tag = "14")]
+        AttachContainer(super::AttachContainer),
+        /// Bytes for the stdin of an interactive session
+        #[prost(message, tag = "15")]
+        SessionInput(super::SessionInput),
+        /// Detach from an interactive session
+        #[prost(message, tag = "16")]
+        CloseSession(super::CloseSession),
+        /// Resize the terminal of an interactive session
+        #[prost(message, tag = "17")]
+        ResizeSession(super::ResizeSession),
+        /// Abort an in-flight request the caller gave up on
+        #[prost(message, tag = "18")]
+        CancelRequest(super::CancelRequest),
+        #[prost(message, tag = "19")]
+        GetImageHistory(super::GetImageHistory),
+        #[prost(message, tag = "20")]
+        ApplyAutostart(super::ApplyAutostart),
+        #[prost(message, tag = "21")]
+        CreateContainer(super::CreateContainer),
+        #[prost(message, tag = "22")]
+        GetContainerGraph(super::GetContainerGraph),
+        #[prost(message, tag = "23")]
+        GetDiskUsage(super::GetDiskUsage),
+        #[prost(message, tag = "24")]
+        ConfigureLogForwarding(super::ConfigureLogForwarding),
+        #[prost(message, tag = "25")]
+        GetLogForwarding(super::GetLogForwarding),
+        /// Images on the node
+        #[prost(message, tag = "26")]
+        ListImages(super::ListImages),
+        /// Pull an image from its registry
+        #[prost(message, tag = "27")]
+        PullImage(super::PullImage),
+        /// Untag and delete an image
+        #[prost(message, tag = "28")]
+        RemoveImage(super::RemoveImage),
+        /// Delete unused images
+        #[prost(message, tag = "29")]
+        PruneImages(super::PruneImages),
+        /// Run a command in a container as an interactive session
+        #[prost(message, tag = "30")]
+        ExecContainer(super::ExecContainer),
+        /// Stream new log lines until the container stops
+        #[prost(message, tag = "31")]
+        FollowContainerLogs(super::FollowContainerLogs),
+        /// Run a script the node operator registered
+        #[prost(message, tag = "32")]
+        RunHostScript(super::RunHostScript),
+        /// Stop and start a container in one Docker call
+        #[prost(message, tag = "33")]
+        RestartContainer(super::RestartContainer),
+        /// Freeze a container's processes
+        #[prost(message, tag = "34")]
+        PauseContainer(super::PauseContainer),
+        /// Resume a paused container
+        #[prost(message, tag = "35")]
+        UnpauseContainer(super::UnpauseContainer),
+        /// Read a path out of a container as a tar archive
+        #[prost(message, tag = "36")]
+        GetContainerArchive(super::GetContainerArchive),
+        /// One chunk of a tar archive to extract into a container
+        #[prost(message, tag = "37")]
+        PutContainerArchive(super::PutContainerArchive),
+        /// Docker networks with their containers
+        #[prost(message, tag = "38")]
+        ListNetworks(super::ListNetworks),
+        /// One network with its containers
+        #[prost(message, tag = "39")]
+        InspectNetwork(super::InspectNetwork),
+        /// Create a Docker network
+        #[prost(message, tag = "40")]
+        CreateNetwork(super::CreateNetwork),
+        /// Remove a Docker network
+        #[prost(message, tag = "41")]
+        RemoveNetwork(super::RemoveNetwork),
+        /// Docker engine version, host and totals
+        #[prost(message, tag = "42")]
+        GetSystemInfo(super::GetSystemInfo),
+        /// Space used by images, containers, volumes and build cache
+        #[prost(message, tag = "43")]
+        GetSystemDiskUsage(super::GetSystemDiskUsage),
+        /// Delete unused containers, images, networks, volumes and build cache
+        #[prost(message, tag = "44")]
+        PruneSystem(super::PruneSystem),
+        /// Create a stopped copy of a container
+        #[prost(message, tag = "45")]
+        CloneContainer(super::CloneContainer),
+        /// Replace the node's cleanup rules for exited containers
+        #[prost(message, tag = "46")]
+        ConfigureCleanup(super::ConfigureCleanup),
+        /// Cleanup rules and recent sweeps
+        #[prost(message, tag = "47")]
+        GetCleanup(super::GetCleanup),
+        /// Commit a container and stream it out for a migration
+        #[prost(message, tag = "48")]
+        ExportContainer(super::ExportContainer),
+        /// One chunk of an exported container to recreate here
+        #[prost(message, tag = "49")]
+        ImportContainer(super::ImportContainer),
+        /// How long deleted containers stay in the trash
+        #[prost(message, tag = "50")]
+        ConfigureTrash(super::ConfigureTrash),
+        /// Containers in the trash
+        #[prost(message, tag = "51")]
+        GetTrash(super::GetTrash),
+        /// Take a container back out of the trash
+        #[prost(message, tag = "52")]
+        RestoreContainer(super::RestoreContainer),
     }
 }
 /// Responses from server to node
+#[derive(serde::Serialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ServerResponse {
@@ -83,6 +243,7 @@ pub struct ServerResponse {
 }
 /// Nested message and enum types in `ServerResponse`.
 pub mod server_response {
+    #[derive(serde::Serialize)]
     #[allow(clippy::derive_partial_eq_without_eq)]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum Kind {
@@ -93,14 +254,19 @@ pub mod server_response {
     }
 }
 /// Responses from node to server (AI-extended)
+#[derive(serde::Serialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct NodeResponse {
-    #[prost(oneof = "node_response::Kind", tags = "1, 2, 3, 4, 5, 6")]
+    #[prost(
+        oneof = "node_response::Kind",
+        tags = "1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37"
+    )]
     pub kind: ::core::option::Option<node_response::Kind>,
 }
 /// Nested message and enum types in `NodeResponse`.
 pub mod node_response {
+    #[derive(serde::Serialize)]
     #[allow(clippy::derive_partial_eq_without_eq)]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum Kind {
@@ -121,12 +287,93 @@ pub mod node_response {
         ContainerAction(super::ContainerAction),
         #[prost(message, tag = "6")]
         Error(super::NodeError),
+        /// Streamed build output
+        #[prost(message, tag = "7")]
+        ImageBuildOutput(super::ImageBuildOutput),
+        /// Registry manifest with platforms
+        #[prost(message, tag = "8")]
+        ImageManifest(super::ImageManifest),
+        /// Host-level metrics
+        #[prost(message, tag = "9")]
+        NodeMetrics(super::NodeMetrics),
+        /// Resource stats sample
+        #[prost(message, tag = "10")]
+        ContainerStats(super::ContainerStats),
+        /// Heaviest containers
+        #[prost(message, tag = "11")]
+        TopContainers(super::TopContainers),
+        /// Pushed Docker lifecycle event
+        #[prost(message, tag = "12")]
+        ContainerEvent(super::ContainerEvent),
+        /// Latency probe reply
+        #[prost(message, tag = "13")]
+        Pong(super::Pong),
+        /// Terminal output of an interactive session
+        #[prost(message, tag = "14")]
+        SessionOutput(super::SessionOutput),
+        #[prost(message, tag = "15")]
+        ImageHistory(super::ImageHistory),
+        #[prost(message, tag = "16")]
+        AutostartReport(super::AutostartReport),
+        #[prost(message, tag = "17")]
+        ContainerCreated(super::ContainerCreated),
+        #[prost(message, tag = "18")]
+        ContainerGraph(super::ContainerGraph),
+        #[prost(message, tag = "19")]
+        DiskUsage(super::DiskUsage),
+        #[prost(message, tag = "20")]
+        LogForwardingStatus(super::LogForwardingStatus),
+        #[prost(message, tag = "21")]
+        ImageList(super::ImageList),
+        #[prost(message, tag = "22")]
+        ImagePulled(super::ImagePulled),
+        /// Result of remove and prune
+        #[prost(message, tag = "23")]
+        ImagesRemoved(super::ImagesRemoved),
+        /// Containers changed since the last push
+        #[prost(message, tag = "24")]
+        ContainerDelta(super::ContainerDelta),
+        /// Node received a BroadcastNotice
+        #[prost(message, tag = "25")]
+        BroadcastAck(super::BroadcastAck),
+        #[prost(message, tag = "26")]
+        HostScriptResult(super::HostScriptResult),
+        /// Streamed tar archive of a container path
+        #[prost(message, tag = "27")]
+        ContainerArchive(super::ContainerArchive),
+        /// Networks, for list and inspect
+        #[prost(message, tag = "28")]
+        NetworkList(super::NetworkList),
+        #[prost(message, tag = "29")]
+        NetworkCreated(super::NetworkCreated),
+        #[prost(message, tag = "30")]
+        NetworkRemoved(super::NetworkRemoved),
+        #[prost(message, tag = "31")]
+        SystemInfo(::prost::alloc::boxed::Box<super::SystemInfo>),
+        #[prost(message, tag = "32")]
+        SystemDiskUsage(super::SystemDiskUsage),
+        #[prost(message, tag = "33")]
+        SystemPruned(super::SystemPruned),
+        /// Cleanup rules and recent sweeps
+        #[prost(message, tag = "34")]
+        CleanupStatus(super::CleanupStatus),
+        /// Streamed part of an exported container
+        #[prost(message, tag = "35")]
+        MigrationChunk(super::MigrationChunk),
+        /// Retention and the containers in the trash
+        #[prost(message, tag = "36")]
+        TrashStatus(super::TrashStatus),
+        /// Part of logs too large for one message
+        #[prost(message, tag = "37")]
+        ContainerLogsChunk(super::ContainerLogsChunk),
     }
 }
 /// --- Command/response message definitions ---
+#[derive(serde::Serialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct GetServerStatus {}
+#[derive(serde::Serialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct GetNodeContainers {
@@ -134,12 +381,20 @@ pub struct GetNodeContainers {
     pub request_id: ::prost::alloc::string::String,
 }
 /// New command to get containers with their statuses (AI-extended)
+#[derive(serde::Serialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct GetNodeContainersWithStatus {
     #[prost(string, tag = "1")]
     pub request_id: ::prost::alloc::string::String,
+    /// case-insensitive name substring, empty = no filter
+    #[prost(string, tag = "2")]
+    pub name_query: ::prost::alloc::string::String,
+    /// regex matched against the name, empty = no filter
+    #[prost(string, tag = "3")]
+    pub name_regex: ::prost::alloc::string::String,
 }
+#[derive(serde::Serialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct GetContainerStatus {
@@ -148,6 +403,7 @@ pub struct GetContainerStatus {
     #[prost(string, tag = "2")]
     pub container_id: ::prost::alloc::string::String,
 }
+#[derive(serde::Serialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct StartContainer {
@@ -156,6 +412,7 @@ pub struct StartContainer {
     #[prost(string, tag = "2")]
     pub container_id: ::prost::alloc::string::String,
 }
+#[derive(serde::Serialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct StopContainer {
@@ -164,6 +421,37 @@ pub struct StopContainer {
     #[prost(string, tag = "2")]
     pub container_id: ::prost::alloc::string::String,
 }
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RestartContainer {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub container_id: ::prost::alloc::string::String,
+    /// grace period before the container is killed, Docker's default when unset
+    #[prost(uint32, optional, tag = "3")]
+    pub timeout_secs: ::core::option::Option<u32>,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PauseContainer {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub container_id: ::prost::alloc::string::String,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UnpauseContainer {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub container_id: ::prost::alloc::string::String,
+}
+#[derive(serde::Serialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct DeleteContainer {
@@ -172,7 +460,80 @@ pub struct DeleteContainer {
     #[prost(string, tag = "2")]
     pub container_id: ::prost::alloc::string::String,
 }
+/// Streams `path` (a file or directory) as a tar archive in ContainerArchive chunks
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetContainerArchive {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub container_id: ::prost::alloc::string::String,
+    /// absolute path in the container
+    #[prost(string, tag = "3")]
+    pub path: ::prost::alloc::string::String,
+}
+/// A tar archive to extract into a container, sent as chunks with the same upload_id.
+/// Each chunk is its own request and is answered with a ContainerAction before the
+/// next is sent; the one with done = true is answered once Docker extracted the archive.
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PutContainerArchive {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub upload_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub container_id: ::prost::alloc::string::String,
+    /// directory in the container the archive is extracted into
+    #[prost(string, tag = "4")]
+    pub path: ::prost::alloc::string::String,
+    #[prost(bytes = "vec", tag = "5")]
+    pub data: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bool, tag = "6")]
+    pub done: bool,
+}
+/// Commits a container to an image and streams what recreating it takes in MigrationChunks:
+/// its config, then the image as `docker save` writes it, then each named volume
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ExportContainer {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub container_id: ::prost::alloc::string::String,
+    /// also stream the contents of the container's named volumes
+    #[prost(bool, tag = "3")]
+    pub include_volumes: bool,
+}
+/// Recreates an exported container from its MigrationChunks, relayed as chunks with the same
+/// upload_id. Each chunk is its own request and is answered with a ContainerAction before the
+/// next is sent; the one with done = true is answered with the stopped ContainerCreated.
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ImportContainer {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub upload_id: ::prost::alloc::string::String,
+    /// for the new container; empty for a Docker-generated name
+    #[prost(string, tag = "3")]
+    pub name: ::prost::alloc::string::String,
+    /// as in MigrationChunk
+    #[prost(string, tag = "4")]
+    pub section: ::prost::alloc::string::String,
+    #[prost(string, tag = "5")]
+    pub path: ::prost::alloc::string::String,
+    #[prost(bytes = "vec", tag = "6")]
+    pub data: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bool, tag = "7")]
+    pub done: bool,
+}
 /// Log request supports tail, follow, since (AI-extended)
+#[derive(serde::Serialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct GetContainerLogs {
@@ -180,7 +541,7 @@ pub struct GetContainerLogs {
     pub request_id: ::prost::alloc::string::String,
     #[prost(string, tag = "2")]
     pub container_id: ::prost::alloc::string::String,
-    /// number of last lines
+    /// number of last lines, negative for all
     #[prost(int32, tag = "3")]
     pub tail: i32,
     /// stream logs in real time
@@ -189,131 +550,1980 @@ pub struct GetContainerLogs {
     /// show logs since this time (RFC3339)
     #[prost(string, tag = "5")]
     pub since: ::prost::alloc::string::String,
+    /// fill in each line's RFC3339Nano timestamp
+    #[prost(bool, tag = "6")]
+    pub timestamps: bool,
+    /// stop reading after this many bytes of log lines, 0 for no limit
+    #[prost(uint64, tag = "7")]
+    pub max_bytes: u64,
+    /// read from Docker even if the node buffers the container's recent lines
+    #[prost(bool, tag = "8")]
+    pub fresh: bool,
+    /// which of the container's output streams to read
+    #[prost(enumeration = "LogStreams", tag = "9")]
+    pub streams: i32,
+    /// skip lines up to this paging cursor; needs timestamps
+    #[prost(message, optional, tag = "10")]
+    pub after: ::core::option::Option<LogPosition>,
 }
+/// Position of a log line: its timestamp and how many lines up to it have that
+/// same timestamp, counting from 1
+#[derive(serde::Serialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
-pub struct AuthRequest {
+pub struct LogPosition {
+    #[prost(int64, tag = "1")]
+    pub time_nanos: i64,
+    #[prost(uint32, tag = "2")]
+    pub line: u32,
+}
+/// Build an image from a remote context (git repository or tarball URL)
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BuildImage {
     #[prost(string, tag = "1")]
-    pub node_id: ::prost::alloc::string::String,
+    pub request_id: ::prost::alloc::string::String,
+    /// build context URL
     #[prost(string, tag = "2")]
-    pub password: ::prost::alloc::string::String,
+    pub remote: ::prost::alloc::string::String,
+    /// path to the Dockerfile inside the context
+    #[prost(string, tag = "3")]
+    pub dockerfile: ::prost::alloc::string::String,
+    /// name:tag for the resulting image
+    #[prost(string, tag = "4")]
+    pub tag: ::prost::alloc::string::String,
+    #[prost(map = "string, string", tag = "5")]
+    pub build_args: ::std::collections::HashMap<
+        ::prost::alloc::string::String,
+        ::prost::alloc::string::String,
+    >,
+    #[prost(bool, tag = "6")]
+    pub nocache: bool,
+    /// always attempt to pull newer base images
+    #[prost(bool, tag = "7")]
+    pub pull: bool,
 }
+/// Resolve an image reference against its registry (docker manifest inspect)
+#[derive(serde::Serialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
-pub struct AuthResponse {
-    #[prost(bool, tag = "1")]
-    pub success: bool,
+pub struct InspectImageManifest {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+    /// e.g. "nginx:1.27" or "ghcr.io/org/app:1.0"
     #[prost(string, tag = "2")]
-    pub message: ::prost::alloc::string::String,
+    pub image: ::prost::alloc::string::String,
 }
-/// --- Status/response message definitions ---
+#[derive(serde::Serialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
-pub struct ServerStatus {
-    /// example: "running"
+pub struct GetNodeMetrics {
     #[prost(string, tag = "1")]
-    pub status: ::prost::alloc::string::String,
-    /// example: 16 hours 5 min
-    #[prost(string, tag = "2")]
-    pub uptime: ::prost::alloc::string::String,
+    pub request_id: ::prost::alloc::string::String,
 }
+#[derive(serde::Serialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
-pub struct NodeContainers {
-    #[prost(message, optional, tag = "1")]
-    pub request_key: ::core::option::Option<RequestKey>,
-    #[prost(string, repeated, tag = "2")]
-    pub containers: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+pub struct GetSystemInfo {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
 }
-/// New response with containers and their statuses (AI-extended)
+#[derive(serde::Serialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
-pub struct NodeContainersWithStatus {
-    #[prost(message, optional, tag = "1")]
-    pub request_key: ::core::option::Option<RequestKey>,
-    #[prost(message, repeated, tag = "2")]
-    pub containers: ::prost::alloc::vec::Vec<ContainerStatus>,
+pub struct GetSystemDiskUsage {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
 }
-/// Detailed container status (AI-extended)
+/// Each kind is pruned only when set; filters apply to every kind that supports them
+#[derive(serde::Serialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
-pub struct ContainerStatus {
-    #[prost(message, optional, tag = "1")]
-    pub request_key: ::core::option::Option<RequestKey>,
+pub struct PruneSystem {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+    /// stopped containers
+    #[prost(bool, tag = "2")]
+    pub containers: bool,
+    /// dangling images
+    #[prost(bool, tag = "3")]
+    pub images: bool,
+    /// networks no container uses
+    #[prost(bool, tag = "4")]
+    pub networks: bool,
+    /// anonymous volumes no container uses
+    #[prost(bool, tag = "5")]
+    pub volumes: bool,
+    #[prost(bool, tag = "6")]
+    pub build_cache: bool,
+    /// every image no container uses, not only dangling ones
+    #[prost(bool, tag = "7")]
+    pub all_images: bool,
+    /// named volumes too
+    #[prost(bool, tag = "8")]
+    pub all_volumes: bool,
+    /// only objects created before this (unix seconds or a duration like "24h"); empty = any; not for volumes
+    #[prost(string, tag = "9")]
+    pub until: ::prost::alloc::string::String,
+    /// only objects with these labels: "key" or "key=value", "!key" or "!key=value" to exclude
+    #[prost(string, repeated, tag = "10")]
+    pub labels: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+/// Stats sampling; with stream = true the node keeps sending samples for duration_secs
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetContainerStats {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
     #[prost(string, tag = "2")]
     pub container_id: ::prost::alloc::string::String,
-    /// "running", "stopped", "exited", etc.
-    #[prost(string, tag = "3")]
-    pub status: ::prost::alloc::string::String,
-    /// creation timestamp
-    #[prost(int64, tag = "4")]
-    pub created: i64,
-    /// start timestamp
-    #[prost(int64, tag = "5")]
-    pub started_at: i64,
-    /// finish timestamp
-    #[prost(int64, tag = "6")]
-    pub finished_at: i64,
-    /// exit code if finished
-    #[prost(int32, tag = "7")]
-    pub exit_code: i32,
+    #[prost(bool, tag = "3")]
+    pub stream: bool,
+    #[prost(uint32, tag = "4")]
+    pub duration_secs: u32,
 }
-/// Container logs (AI-extended)
+#[derive(serde::Serialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
-pub struct ContainerLogs {
-    #[prost(message, optional, tag = "1")]
-    pub request_key: ::core::option::Option<RequestKey>,
+pub struct GetTopContainers {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+    /// "cpu" or "memory"
+    #[prost(string, tag = "2")]
+    pub by: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "3")]
+    pub limit: u32,
+}
+/// Latency probe, answered immediately with a Pong
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Ping {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+    /// coordinator time when sent (unix ms)
+    #[prost(int64, tag = "2")]
+    pub sent_at_ms: i64,
+}
+/// Opens an interactive session on PID 1 of a running container started with a TTY.
+/// request_id identifies the session in SessionInput, CloseSession and SessionOutput.
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AttachContainer {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
     #[prost(string, tag = "2")]
     pub container_id: ::prost::alloc::string::String,
-    #[prost(string, repeated, tag = "3")]
-    pub logs: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// initial terminal size, 0 keeps the current one
+    #[prost(uint32, tag = "3")]
+    pub cols: u32,
+    #[prost(uint32, tag = "4")]
+    pub rows: u32,
 }
-/// Result of start/stop/delete (AI-extended)
+#[derive(serde::Serialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
-pub struct ContainerAction {
-    #[prost(message, optional, tag = "1")]
-    pub request_key: ::core::option::Option<RequestKey>,
+pub struct SessionInput {
+    /// session id
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+    #[prost(bytes = "vec", tag = "2")]
+    pub data: ::prost::alloc::vec::Vec<u8>,
+}
+/// Ends the session without stopping the container
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CloseSession {
+    /// session id
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ResizeSession {
+    /// session id
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "2")]
+    pub cols: u32,
+    #[prost(uint32, tag = "3")]
+    pub rows: u32,
+}
+/// Sent when the caller went away or timed out, so the node stops the Docker work.
+/// For an interactive session it detaches like CloseSession.
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CancelRequest {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+}
+/// Layers of an image present on the node, newest first
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetImageHistory {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+    /// image id or reference, e.g. "nginx:1.27"
     #[prost(string, tag = "2")]
+    pub image: ::prost::alloc::string::String,
+}
+/// Starts the listed containers in order, each after its delay. Sent by the
+/// coordinator when the node (re)connects, or on request
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ApplyAutostart {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+    #[prost(message, repeated, tag = "2")]
+    pub containers: ::prost::alloc::vec::Vec<AutostartEntry>,
+    /// skip unless the host booted since last_boot_time
+    #[prost(bool, tag = "3")]
+    pub only_after_reboot: bool,
+    /// unix seconds, 0 if never applied
+    #[prost(int64, tag = "4")]
+    pub last_boot_time: i64,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AutostartEntry {
+    #[prost(string, tag = "1")]
     pub container_id: ::prost::alloc::string::String,
-    /// "start", "stop", "delete"
+    /// wait before starting this container
+    #[prost(uint32, tag = "2")]
+    pub delay_secs: u32,
+}
+/// A coordinator-stored secret decrypted for one container; exposed as an env var
+/// and/or a read-only file inside it, and never reported back to the coordinator.
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct InjectedSecret {
+    #[prost(string, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+    /// env var name, empty for none
+    #[prost(string, tag = "2")]
+    pub env: ::prost::alloc::string::String,
+    /// absolute path inside the container, empty for none
     #[prost(string, tag = "3")]
-    pub action: ::prost::alloc::string::String,
-    /// error message if success = false
+    pub file_path: ::prost::alloc::string::String,
+    #[prost(bytes = "vec", tag = "4")]
+    pub value: ::prost::alloc::vec::Vec<u8>,
+}
+/// Creates (and optionally starts) a container, pulling the image if it is missing
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CreateContainer {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub image: ::prost::alloc::string::String,
+    /// empty for a Docker-generated name
+    #[prost(string, tag = "3")]
+    pub name: ::prost::alloc::string::String,
+    /// KEY=value
+    #[prost(string, repeated, tag = "4")]
+    pub env: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(message, repeated, tag = "5")]
+    pub ports: ::prost::alloc::vec::Vec<PortMapping>,
+    /// host path or volume name, ":", container path, optional ":ro"
+    #[prost(string, repeated, tag = "6")]
+    pub volumes: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// "no", "always", "unless-stopped", "on-failure"; empty for Docker's default
+    #[prost(string, tag = "7")]
+    pub restart_policy: ::prost::alloc::string::String,
+    #[prost(message, repeated, tag = "8")]
+    pub secrets: ::prost::alloc::vec::Vec<InjectedSecret>,
+    #[prost(map = "string, string", tag = "9")]
+    pub labels: ::std::collections::HashMap<
+        ::prost::alloc::string::String,
+        ::prost::alloc::string::String,
+    >,
+    #[prost(bool, tag = "10")]
+    pub start: bool,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PortMapping {
+    #[prost(uint32, tag = "1")]
+    pub container_port: u32,
+    /// 0 for an ephemeral port
+    #[prost(uint32, tag = "2")]
+    pub host_port: u32,
+    /// "tcp" or "udp"
+    #[prost(string, tag = "3")]
+    pub protocol: ::prost::alloc::string::String,
+    /// empty for all interfaces
     #[prost(string, tag = "4")]
-    pub message: ::prost::alloc::string::String,
+    pub host_ip: ::prost::alloc::string::String,
 }
-/// Error message for failed operations
+/// Creates a stopped container with the config of an existing one; answered with ContainerCreated
+#[derive(serde::Serialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
-pub struct NodeError {
-    #[prost(message, optional, tag = "1")]
-    pub request_key: ::core::option::Option<RequestKey>,
+pub struct CloneContainer {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+    /// source, id or name
     #[prost(string, tag = "2")]
-    pub message: ::prost::alloc::string::String,
+    pub container_id: ::prost::alloc::string::String,
+    /// empty for a Docker-generated name
+    #[prost(string, tag = "3")]
+    pub name: ::prost::alloc::string::String,
+    /// KEY=value, replacing the source's value of KEY or added to its env
+    #[prost(string, repeated, tag = "4")]
+    pub env: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// published instead of the source's when replace_ports is set
+    #[prost(message, repeated, tag = "5")]
+    pub ports: ::prost::alloc::vec::Vec<PortMapping>,
+    /// false keeps the source's published ports
+    #[prost(bool, tag = "6")]
+    pub replace_ports: bool,
 }
-/// Used to correlate requests and responses
+/// Containers with their networks, mounts and compose relationships
+#[derive(serde::Serialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
-pub struct RequestKey {
-    #[prost(enumeration = "RequestType", tag = "1")]
-    pub request_type: i32,
-    #[prost(oneof = "request_key::RequestId", tags = "2, 3")]
-    pub request_id: ::core::option::Option<request_key::RequestId>,
+pub struct GetContainerGraph {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
 }
-/// Nested message and enum types in `RequestKey`.
-pub mod request_key {
-    #[allow(clippy::derive_partial_eq_without_eq)]
-    #[derive(Clone, PartialEq, ::prost::Oneof)]
-    pub enum RequestId {
-        #[prost(string, tag = "2")]
-        Value(::prost::alloc::string::String),
-        #[prost(bool, tag = "3")]
-        Unspecific(bool),
+/// Disk space per container: writable layer plus a share of its image
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetDiskUsage {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+}
+/// Replaces the containers whose logs the node ships to external sinks; an empty list stops forwarding.
+/// Sent by the coordinator when a node connects and whenever the configuration changes.
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ConfigureLogForwarding {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+    #[prost(message, repeated, tag = "2")]
+    pub forwards: ::prost::alloc::vec::Vec<LogForward>,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LogForward {
+    /// identifies the forward in the status
+    #[prost(string, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+    /// container names; a trailing '*' matches a prefix
+    #[prost(string, repeated, tag = "2")]
+    pub containers: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// "loki", "syslog" or "file"
+    #[prost(string, tag = "3")]
+    pub sink: ::prost::alloc::string::String,
+    /// Loki push URL, udp://host:port or tcp://host:port, or a file path on the host
+    #[prost(string, tag = "4")]
+    pub target: ::prost::alloc::string::String,
+    /// added to every line
+    #[prost(map = "string, string", tag = "5")]
+    pub labels: ::std::collections::HashMap<
+        ::prost::alloc::string::String,
+        ::prost::alloc::string::String,
+    >,
+}
+/// Current state of the node's log forwarding
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetLogForwarding {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+}
+/// Replaces the rules by which the node removes exited containers; no rules stops the sweeps.
+/// Sent by the coordinator when a node connects and whenever the policy changes.
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ConfigureCleanup {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+    #[prost(message, repeated, tag = "2")]
+    pub rules: ::prost::alloc::vec::Vec<CleanupRule>,
+    /// between sweeps
+    #[prost(uint32, tag = "3")]
+    pub interval_secs: u32,
+}
+/// Exited containers matching the rule are removed once they are old enough,
+/// except the most recent ones of each compose service
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CleanupRule {
+    /// identifies the rule in sweep reports
+    #[prost(string, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+    /// container names; a trailing '*' matches a prefix; empty for all
+    #[prost(string, repeated, tag = "2")]
+    pub containers: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// exited at least this long ago; 0 for any age
+    #[prost(uint64, tag = "3")]
+    pub older_than_secs: u64,
+    /// most recently exited kept per compose service, or per image outside compose
+    #[prost(uint32, tag = "4")]
+    pub keep_last: u32,
+}
+/// The node's cleanup rules and recent sweeps
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetCleanup {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+}
+/// Deleting a container stops it and renames it into the trash, where it stays
+/// for retention_secs before it is removed; 0 deletes right away.
+/// Sent by the coordinator when a node connects and whenever the setting changes.
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ConfigureTrash {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "2")]
+    pub retention_secs: u64,
+}
+/// The node's trash retention and the containers in it
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetTrash {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+}
+/// Renames a trashed container back, answered with a ContainerAction; it stays stopped
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RestoreContainer {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+    /// id or trash name, or the original name for its latest deletion
+    #[prost(string, tag = "2")]
+    pub container_id: ::prost::alloc::string::String,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListImages {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+    /// include intermediate images
+    #[prost(bool, tag = "2")]
+    pub all: bool,
+    /// only images matching this reference, e.g. "nginx" or "nginx:1.*"; empty = all
+    #[prost(string, tag = "3")]
+    pub reference: ::prost::alloc::string::String,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PullImage {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+    /// reference; ":latest" when untagged
+    #[prost(string, tag = "2")]
+    pub image: ::prost::alloc::string::String,
+    /// e.g. "linux/arm64"; empty = the node's platform
+    #[prost(string, tag = "3")]
+    pub platform: ::prost::alloc::string::String,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RemoveImage {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+    /// id or reference
+    #[prost(string, tag = "2")]
+    pub image: ::prost::alloc::string::String,
+    /// also remove when stopped containers use it
+    #[prost(bool, tag = "3")]
+    pub force: bool,
+    /// keep untagged parent images
+    #[prost(bool, tag = "4")]
+    pub no_prune: bool,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PruneImages {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+    /// every image no container uses, not only dangling ones
+    #[prost(bool, tag = "2")]
+    pub all: bool,
+    /// only images created before this (unix seconds or a duration like "24h"); empty = any
+    #[prost(string, tag = "3")]
+    pub until: ::prost::alloc::string::String,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListNetworks {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+    /// only networks whose name contains this; empty = all
+    #[prost(string, tag = "2")]
+    pub name: ::prost::alloc::string::String,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct InspectNetwork {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+    /// id or name
+    #[prost(string, tag = "2")]
+    pub network: ::prost::alloc::string::String,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CreateNetwork {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub name: ::prost::alloc::string::String,
+    /// empty = bridge
+    #[prost(string, tag = "3")]
+    pub driver: ::prost::alloc::string::String,
+    /// no route to the outside
+    #[prost(bool, tag = "4")]
+    pub internal: bool,
+    /// standalone containers may join (overlay only)
+    #[prost(bool, tag = "5")]
+    pub attachable: bool,
+    #[prost(bool, tag = "6")]
+    pub enable_ipv6: bool,
+    /// CIDR, e.g. "172.28.0.0/16"; empty = picked by Docker
+    #[prost(string, tag = "7")]
+    pub subnet: ::prost::alloc::string::String,
+    /// empty = first address of the subnet
+    #[prost(string, tag = "8")]
+    pub gateway: ::prost::alloc::string::String,
+    #[prost(map = "string, string", tag = "9")]
+    pub labels: ::std::collections::HashMap<
+        ::prost::alloc::string::String,
+        ::prost::alloc::string::String,
+    >,
+    /// driver options
+    #[prost(map = "string, string", tag = "10")]
+    pub options: ::std::collections::HashMap<
+        ::prost::alloc::string::String,
+        ::prost::alloc::string::String,
+    >,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RemoveNetwork {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+    /// id or name
+    #[prost(string, tag = "2")]
+    pub network: ::prost::alloc::string::String,
+}
+/// Starts a process in a running container. Input, resize and close use the
+/// session messages with request_id as the session id, like AttachContainer
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ExecContainer {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub container_id: ::prost::alloc::string::String,
+    #[prost(string, repeated, tag = "3")]
+    pub command: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// allocate a terminal, as shells and full-screen programs need
+    #[prost(bool, tag = "4")]
+    pub tty: bool,
+    /// initial terminal size, 0 keeps Docker's default
+    #[prost(uint32, tag = "5")]
+    pub cols: u32,
+    #[prost(uint32, tag = "6")]
+    pub rows: u32,
+    /// user\[:group\], the container's user when empty
+    #[prost(string, tag = "7")]
+    pub user: ::prost::alloc::string::String,
+    /// the container's working directory when empty
+    #[prost(string, tag = "8")]
+    pub working_dir: ::prost::alloc::string::String,
+    /// KEY=value
+    #[prost(string, repeated, tag = "9")]
+    pub env: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+/// Notice from an administrator, sent to every selected node. The node
+/// acknowledges it with a BroadcastAck
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BroadcastNotice {
+    /// unique per node, answered in BroadcastAck
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub broadcast_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub message: ::prost::alloc::string::String,
+    /// "notice" (log only) or "refresh" (push full container states)
+    #[prost(string, tag = "4")]
+    pub action: ::prost::alloc::string::String,
+}
+/// Sent to every node before the coordinator closes its streams, on shutdown
+/// or handover. The node ends the stream itself and reconnects
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CoordinatorShutdown {
+    #[prost(string, tag = "1")]
+    pub reason: ::prost::alloc::string::String,
+}
+/// Sent by the node every interval_secs while connected
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Heartbeat {
+    /// how often the node sends one
+    #[prost(uint32, tag = "1")]
+    pub interval_secs: u32,
+    /// node clock, unix milliseconds
+    #[prost(int64, tag = "2")]
+    pub sent_at_ms: i64,
+}
+/// Streams log lines as they are written, as a sequence of ContainerLogs.
+/// The last one has `done` set; it runs until the container stops or the
+/// coordinator cancels it
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct FollowContainerLogs {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub container_id: ::prost::alloc::string::String,
+    /// earlier lines sent first, negative for all
+    #[prost(int32, tag = "3")]
+    pub tail: i32,
+    /// fill in each line's RFC3339Nano timestamp
+    #[prost(bool, tag = "4")]
+    pub timestamps: bool,
+    /// which of the container's output streams to follow
+    #[prost(enumeration = "LogStreams", tag = "5")]
+    pub streams: i32,
+}
+/// Runs a host-side script the node operator registered by name. The node
+/// refuses names it does not know; no arguments are passed
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RunHostScript {
+    #[prost(string, tag = "1")]
+    pub request_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub name: ::prost::alloc::string::String,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AuthRequest {
+    #[prost(string, tag = "1")]
+    pub node_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub password: ::prost::alloc::string::String,
+    /// unset for nodes that predate it
+    #[prost(message, optional, tag = "3")]
+    pub capabilities: ::core::option::Option<NodeCapabilities>,
+    /// unset for nodes that predate it
+    #[prost(message, optional, tag = "4")]
+    pub link: ::core::option::Option<CoordinatorLink>,
+}
+/// Which of the coordinators a node is configured with this connection goes to
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CoordinatorLink {
+    /// as configured on the node
+    #[prost(string, tag = "1")]
+    pub address: ::prost::alloc::string::String,
+    /// position in the node's list, 0 for the primary
+    #[prost(uint32, tag = "2")]
+    pub priority: u32,
+    /// how many coordinators the node is configured with
+    #[prost(uint32, tag = "3")]
+    pub coordinators: u32,
+    /// times the node switched coordinators since it started
+    #[prost(uint32, tag = "4")]
+    pub failovers: u32,
+}
+/// What the node's platform and Docker engine can do, detected at connect time
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct NodeCapabilities {
+    /// the Docker engine's OS type, e.g. "linux" or "windows"
+    #[prost(string, tag = "1")]
+    pub os: ::prost::alloc::string::String,
+    /// e.g. "x86_64" or "aarch64"
+    #[prost(string, tag = "2")]
+    pub arch: ::prost::alloc::string::String,
+    /// exec sessions can allocate a TTY
+    #[prost(bool, tag = "3")]
+    pub exec_tty: bool,
+    /// container resource stats are available
+    #[prost(bool, tag = "4")]
+    pub stats: bool,
+    /// containers can be checkpointed (experimental daemon with CRIU)
+    #[prost(bool, tag = "5")]
+    pub checkpoint: bool,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AuthResponse {
+    #[prost(bool, tag = "1")]
+    pub success: bool,
+    #[prost(string, tag = "2")]
+    pub message: ::prost::alloc::string::String,
+}
+/// --- Status/response message definitions ---
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ServerStatus {
+    /// example: "running"
+    #[prost(string, tag = "1")]
+    pub status: ::prost::alloc::string::String,
+    /// example: 16 hours 5 min
+    #[prost(string, tag = "2")]
+    pub uptime: ::prost::alloc::string::String,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct NodeContainers {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    #[prost(string, repeated, tag = "2")]
+    pub containers: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+/// New response with containers and their statuses (AI-extended)
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct NodeContainersWithStatus {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    #[prost(message, repeated, tag = "2")]
+    pub containers: ::prost::alloc::vec::Vec<ContainerStatus>,
+}
+/// Detailed container status (AI-extended)
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ContainerStatus {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    #[prost(string, tag = "2")]
+    pub container_id: ::prost::alloc::string::String,
+    /// "running", "stopped", "exited", etc.
+    #[prost(string, tag = "3")]
+    pub status: ::prost::alloc::string::String,
+    /// creation timestamp
+    #[prost(int64, tag = "4")]
+    pub created: i64,
+    /// start timestamp
+    #[prost(int64, tag = "5")]
+    pub started_at: i64,
+    /// finish timestamp
+    #[prost(int64, tag = "6")]
+    pub finished_at: i64,
+    /// exit code if finished
+    #[prost(int32, tag = "7")]
+    pub exit_code: i32,
+    /// configured memory limit in bytes, 0 = unlimited
+    #[prost(int64, tag = "8")]
+    pub memory_limit: i64,
+    /// configured CPU limit in cores, 0 = unlimited
+    #[prost(double, tag = "9")]
+    pub cpu_limit: f64,
+    /// current cgroup memory usage in bytes (status endpoint only)
+    #[prost(int64, tag = "10")]
+    pub memory_usage: i64,
+    /// current CPU usage in cores (status endpoint only)
+    #[prost(double, tag = "11")]
+    pub cpu_usage: f64,
+    /// docklord.* labels (state pushes only)
+    #[prost(map = "string, string", tag = "12")]
+    pub labels: ::std::collections::HashMap<
+        ::prost::alloc::string::String,
+        ::prost::alloc::string::String,
+    >,
+}
+/// Container logs (AI-extended)
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ContainerLogs {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    #[prost(string, tag = "2")]
+    pub container_id: ::prost::alloc::string::String,
+    /// max_bytes was reached; later lines were not read
+    #[prost(bool, tag = "4")]
+    pub truncated: bool,
+    /// last message of a FollowContainerLogs stream
+    #[prost(bool, tag = "5")]
+    pub done: bool,
+    /// served from the node's log buffer instead of Docker
+    #[prost(bool, tag = "6")]
+    pub buffered: bool,
+    #[prost(message, repeated, tag = "7")]
+    pub lines: ::prost::alloc::vec::Vec<LogLine>,
+}
+/// One line of a container's output
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LogLine {
+    #[prost(string, tag = "1")]
+    pub text: ::prost::alloc::string::String,
+    /// "stdout" or "stderr"; a TTY container writes everything to stdout
+    #[prost(string, tag = "2")]
+    pub stream: ::prost::alloc::string::String,
+    /// RFC3339Nano from Docker, when the request asked for timestamps
+    #[prost(string, tag = "3")]
+    pub timestamp: ::prost::alloc::string::String,
+}
+/// GetContainerLogs answer split over several messages when the lines would not
+/// fit in one. The coordinator joins the chunks back into one ContainerLogs.
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ContainerLogsChunk {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    #[prost(string, tag = "2")]
+    pub container_id: ::prost::alloc::string::String,
+    /// 0 for the first chunk, then counting up
+    #[prost(uint32, tag = "3")]
+    pub sequence: u32,
+    #[prost(bool, tag = "5")]
+    pub is_last: bool,
+    /// on the last chunk, as in ContainerLogs
+    #[prost(bool, tag = "6")]
+    pub truncated: bool,
+    #[prost(bool, tag = "7")]
+    pub buffered: bool,
+    #[prost(message, repeated, tag = "8")]
+    pub lines: ::prost::alloc::vec::Vec<LogLine>,
+}
+/// Result of start/stop/delete (AI-extended)
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ContainerAction {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    #[prost(string, tag = "2")]
+    pub container_id: ::prost::alloc::string::String,
+    /// "start", "stop", "delete"
+    #[prost(string, tag = "3")]
+    pub action: ::prost::alloc::string::String,
+    /// error message if success = false
+    #[prost(string, tag = "4")]
+    pub message: ::prost::alloc::string::String,
+}
+/// One chunk of image build output; the last chunk has done = true
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ImageBuildOutput {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    /// step output ("Step 1/5 : FROM ...")
+    #[prost(string, tag = "2")]
+    pub stream: ::prost::alloc::string::String,
+    /// pull status
+    #[prost(string, tag = "3")]
+    pub status: ::prost::alloc::string::String,
+    /// pull progress bar
+    #[prost(string, tag = "4")]
+    pub progress: ::prost::alloc::string::String,
+    /// set when the build failed
+    #[prost(string, tag = "5")]
+    pub error: ::prost::alloc::string::String,
+    /// set on the last chunk of a successful build
+    #[prost(string, tag = "6")]
+    pub image_id: ::prost::alloc::string::String,
+    #[prost(bool, tag = "7")]
+    pub done: bool,
+}
+/// One chunk of a tar archive read from a container; the last chunk has done = true
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ContainerArchive {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub data: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bool, tag = "3")]
+    pub done: bool,
+}
+/// One chunk of an exported container. The sections come in order, each in one run of
+/// chunks; the last chunk has done = true.
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MigrationChunk {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    /// "config" (Docker create body as JSON), "image" (tar) or "volume" (tar)
+    #[prost(string, tag = "2")]
+    pub section: ::prost::alloc::string::String,
+    /// for a volume, where it is mounted in the container
+    #[prost(string, tag = "3")]
+    pub path: ::prost::alloc::string::String,
+    #[prost(bytes = "vec", tag = "4")]
+    pub data: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bool, tag = "5")]
+    pub done: bool,
+    /// on the config chunk, the container's name
+    #[prost(string, tag = "6")]
+    pub name: ::prost::alloc::string::String,
+    /// on the config chunk, whether the container was running
+    #[prost(bool, tag = "7")]
+    pub running: bool,
+}
+/// Manifest (list) descriptor and the platforms it covers
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ImageManifest {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    #[prost(string, tag = "2")]
+    pub image: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub digest: ::prost::alloc::string::String,
+    /// manifest list / OCI index for multi-arch images
+    #[prost(string, tag = "4")]
+    pub media_type: ::prost::alloc::string::String,
+    #[prost(int64, tag = "5")]
+    pub size: i64,
+    #[prost(message, repeated, tag = "6")]
+    pub platforms: ::prost::alloc::vec::Vec<ImagePlatform>,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ImagePlatform {
+    #[prost(string, tag = "1")]
+    pub os: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub architecture: ::prost::alloc::string::String,
+    /// e.g. "v8" for arm64
+    #[prost(string, tag = "3")]
+    pub variant: ::prost::alloc::string::String,
+    #[prost(string, tag = "4")]
+    pub os_version: ::prost::alloc::string::String,
+}
+/// Host metrics reported by the node
+/// Docker's `info` and `version` of the node's engine; empty strings and 0 when
+/// the engine does not report a value
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SystemInfo {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    #[prost(string, tag = "2")]
+    pub docker_version: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub api_version: ::prost::alloc::string::String,
+    #[prost(string, tag = "4")]
+    pub min_api_version: ::prost::alloc::string::String,
+    #[prost(string, tag = "5")]
+    pub git_commit: ::prost::alloc::string::String,
+    #[prost(string, tag = "6")]
+    pub go_version: ::prost::alloc::string::String,
+    /// e.g. "Ubuntu 24.04.1 LTS"
+    #[prost(string, tag = "7")]
+    pub os: ::prost::alloc::string::String,
+    /// linux or windows
+    #[prost(string, tag = "8")]
+    pub os_type: ::prost::alloc::string::String,
+    #[prost(string, tag = "9")]
+    pub os_version: ::prost::alloc::string::String,
+    #[prost(string, tag = "10")]
+    pub kernel_version: ::prost::alloc::string::String,
+    #[prost(string, tag = "11")]
+    pub architecture: ::prost::alloc::string::String,
+    #[prost(int64, tag = "12")]
+    pub ncpu: i64,
+    /// bytes
+    #[prost(int64, tag = "13")]
+    pub mem_total: i64,
+    #[prost(string, tag = "14")]
+    pub hostname: ::prost::alloc::string::String,
+    #[prost(string, tag = "15")]
+    pub storage_driver: ::prost::alloc::string::String,
+    #[prost(string, tag = "16")]
+    pub logging_driver: ::prost::alloc::string::String,
+    #[prost(string, tag = "17")]
+    pub cgroup_driver: ::prost::alloc::string::String,
+    #[prost(string, tag = "18")]
+    pub cgroup_version: ::prost::alloc::string::String,
+    #[prost(string, tag = "19")]
+    pub docker_root_dir: ::prost::alloc::string::String,
+    #[prost(string, tag = "20")]
+    pub default_runtime: ::prost::alloc::string::String,
+    #[prost(bool, tag = "21")]
+    pub experimental: bool,
+    #[prost(bool, tag = "22")]
+    pub live_restore: bool,
+    #[prost(int64, tag = "23")]
+    pub containers: i64,
+    #[prost(int64, tag = "24")]
+    pub containers_running: i64,
+    #[prost(int64, tag = "25")]
+    pub containers_paused: i64,
+    #[prost(int64, tag = "26")]
+    pub containers_stopped: i64,
+    #[prost(int64, tag = "27")]
+    pub images: i64,
+    #[prost(string, repeated, tag = "28")]
+    pub security_options: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(string, repeated, tag = "29")]
+    pub warnings: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct NodeMetrics {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    /// false on hosts without cgroup v2 / PSI support
+    #[prost(bool, tag = "2")]
+    pub psi_available: bool,
+    #[prost(message, optional, tag = "3")]
+    pub cpu_pressure: ::core::option::Option<PressureStats>,
+    #[prost(message, optional, tag = "4")]
+    pub memory_pressure: ::core::option::Option<PressureStats>,
+    #[prost(message, optional, tag = "5")]
+    pub io_pressure: ::core::option::Option<PressureStats>,
+    /// bytes, from /proc/meminfo; 0 when unknown
+    #[prost(uint64, tag = "6")]
+    pub memory_total: u64,
+    /// bytes the host can give out without swapping
+    #[prost(uint64, tag = "7")]
+    pub memory_available: u64,
+    /// memory limits of running containers, summed
+    #[prost(uint64, tag = "8")]
+    pub memory_reserved: u64,
+}
+/// Pressure stall information from /proc/pressure/*
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PressureStats {
+    /// share of time at least one task stalled
+    #[prost(message, optional, tag = "1")]
+    pub some: ::core::option::Option<PressureLine>,
+    /// share of time all non-idle tasks stalled
+    #[prost(message, optional, tag = "2")]
+    pub full: ::core::option::Option<PressureLine>,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PressureLine {
+    /// percent over the last 10 seconds
+    #[prost(double, tag = "1")]
+    pub avg10: f64,
+    #[prost(double, tag = "2")]
+    pub avg60: f64,
+    #[prost(double, tag = "3")]
+    pub avg300: f64,
+    /// total stall time in microseconds
+    #[prost(uint64, tag = "4")]
+    pub total: u64,
+}
+/// Resource counters plus per-second rates computed on the node between two samples
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ContainerStats {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    #[prost(string, tag = "2")]
+    pub container_id: ::prost::alloc::string::String,
+    /// network, summed over all interfaces
+    #[prost(uint64, tag = "3")]
+    pub rx_bytes: u64,
+    #[prost(uint64, tag = "4")]
+    pub tx_bytes: u64,
+    #[prost(uint64, tag = "5")]
+    pub rx_packets: u64,
+    #[prost(uint64, tag = "6")]
+    pub tx_packets: u64,
+    #[prost(uint64, tag = "7")]
+    pub block_read_bytes: u64,
+    #[prost(uint64, tag = "8")]
+    pub block_write_bytes: u64,
+    #[prost(double, tag = "9")]
+    pub rx_bytes_per_sec: f64,
+    #[prost(double, tag = "10")]
+    pub tx_bytes_per_sec: f64,
+    #[prost(double, tag = "11")]
+    pub rx_packets_per_sec: f64,
+    #[prost(double, tag = "12")]
+    pub tx_packets_per_sec: f64,
+    #[prost(double, tag = "13")]
+    pub block_read_bytes_per_sec: f64,
+    #[prost(double, tag = "14")]
+    pub block_write_bytes_per_sec: f64,
+    /// last message of a stream
+    #[prost(bool, tag = "15")]
+    pub done: bool,
+    /// as in `docker stats`: 100 per fully used core
+    #[prost(double, tag = "16")]
+    pub cpu_percent: f64,
+    #[prost(uint32, tag = "17")]
+    pub online_cpus: u32,
+    /// bytes in use, without reclaimable page cache
+    #[prost(uint64, tag = "18")]
+    pub memory_usage: u64,
+    /// cgroup limit, the host's memory when unlimited
+    #[prost(uint64, tag = "19")]
+    pub memory_limit: u64,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TopContainers {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    #[prost(string, tag = "2")]
+    pub by: ::prost::alloc::string::String,
+    /// sorted, heaviest first
+    #[prost(message, repeated, tag = "3")]
+    pub containers: ::prost::alloc::vec::Vec<ContainerUsage>,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ContainerUsage {
+    #[prost(string, tag = "1")]
+    pub container_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub name: ::prost::alloc::string::String,
+    /// cores
+    #[prost(double, tag = "3")]
+    pub cpu_usage: f64,
+    /// bytes
+    #[prost(uint64, tag = "4")]
+    pub memory_usage: u64,
+    /// bytes, effective limit reported by the cgroup
+    #[prost(uint64, tag = "5")]
+    pub memory_limit: u64,
+}
+/// Docker lifecycle event pushed by the node. Besides containers it covers
+/// image pulls and deletes and volume creates and destroys; for those
+/// container_id holds the image id or volume name
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ContainerEvent {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    /// Docker container id
+    #[prost(string, tag = "2")]
+    pub container_id: ::prost::alloc::string::String,
+    /// container name
+    #[prost(string, tag = "3")]
+    pub name: ::prost::alloc::string::String,
+    /// "start", "die", "restart", ...
+    #[prost(string, tag = "4")]
+    pub action: ::prost::alloc::string::String,
+    /// unix timestamp of the event
+    #[prost(int64, tag = "5")]
+    pub time: i64,
+    /// set for "die" events
+    #[prost(int32, tag = "6")]
+    pub exit_code: i32,
+    #[prost(string, tag = "7")]
+    pub image: ::prost::alloc::string::String,
+    /// "healthy", "unhealthy" or "starting" for "health_status" events
+    #[prost(string, tag = "8")]
+    pub health: ::prost::alloc::string::String,
+    /// "container", "image" or "volume"; empty from older nodes means "container"
+    #[prost(string, tag = "9")]
+    pub r#type: ::prost::alloc::string::String,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Pong {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    /// echoed from Ping
+    #[prost(int64, tag = "2")]
+    pub sent_at_ms: i64,
+    /// node clock when answering (unix ms)
+    #[prost(int64, tag = "3")]
+    pub node_time_ms: i64,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SessionOutput {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub data: ::prost::alloc::vec::Vec<u8>,
+    /// last message of the session
+    #[prost(bool, tag = "3")]
+    pub closed: bool,
+    /// why the session could not be opened or ended, if not by request
+    #[prost(string, tag = "4")]
+    pub error: ::prost::alloc::string::String,
+    /// exit code of an exec'd process, in the closing message
+    #[prost(int64, optional, tag = "5")]
+    pub exit_code: ::core::option::Option<i64>,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ImageHistory {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    #[prost(string, tag = "2")]
+    pub image: ::prost::alloc::string::String,
+    /// newest first, as in `docker history`
+    #[prost(message, repeated, tag = "3")]
+    pub layers: ::prost::alloc::vec::Vec<ImageLayer>,
+    #[prost(int64, tag = "4")]
+    pub total_size: i64,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ImageLayer {
+    /// "<missing>" for layers built elsewhere
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+    /// unix seconds
+    #[prost(int64, tag = "2")]
+    pub created: i64,
+    #[prost(string, tag = "3")]
+    pub created_by: ::prost::alloc::string::String,
+    #[prost(int64, tag = "4")]
+    pub size: i64,
+    #[prost(string, repeated, tag = "5")]
+    pub tags: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(string, tag = "6")]
+    pub comment: ::prost::alloc::string::String,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AutostartReport {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    /// host boot time, unix seconds; 0 if unknown
+    #[prost(int64, tag = "2")]
+    pub boot_time: i64,
+    /// no reboot since the last run
+    #[prost(bool, tag = "3")]
+    pub skipped: bool,
+    #[prost(message, repeated, tag = "4")]
+    pub results: ::prost::alloc::vec::Vec<AutostartResult>,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AutostartResult {
+    #[prost(string, tag = "1")]
+    pub container_id: ::prost::alloc::string::String,
+    /// "started", "already_running" or "failed"
+    #[prost(string, tag = "2")]
+    pub outcome: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub error: ::prost::alloc::string::String,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ContainerCreated {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    #[prost(string, tag = "2")]
+    pub container_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub name: ::prost::alloc::string::String,
+    #[prost(bool, tag = "4")]
+    pub started: bool,
+    /// the image was pulled first
+    #[prost(bool, tag = "5")]
+    pub pulled: bool,
+    #[prost(string, repeated, tag = "6")]
+    pub warnings: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ContainerGraph {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    #[prost(message, repeated, tag = "2")]
+    pub containers: ::prost::alloc::vec::Vec<GraphContainer>,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GraphContainer {
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub name: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub image: ::prost::alloc::string::String,
+    #[prost(string, tag = "4")]
+    pub state: ::prost::alloc::string::String,
+    /// network names
+    #[prost(string, repeated, tag = "5")]
+    pub networks: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// e.g. "bridge", "host", "container:<id>"
+    #[prost(string, tag = "6")]
+    pub network_mode: ::prost::alloc::string::String,
+    #[prost(message, repeated, tag = "7")]
+    pub mounts: ::prost::alloc::vec::Vec<GraphMount>,
+    #[prost(string, tag = "8")]
+    pub compose_project: ::prost::alloc::string::String,
+    #[prost(string, tag = "9")]
+    pub compose_service: ::prost::alloc::string::String,
+    /// compose service names
+    #[prost(string, repeated, tag = "10")]
+    pub depends_on: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GraphMount {
+    /// "volume", "bind", "tmpfs", ...
+    #[prost(string, tag = "1")]
+    pub r#type: ::prost::alloc::string::String,
+    /// volume name, or host path for binds
+    #[prost(string, tag = "2")]
+    pub source: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub destination: ::prost::alloc::string::String,
+    #[prost(bool, tag = "4")]
+    pub read_only: bool,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DiskUsage {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    #[prost(message, repeated, tag = "2")]
+    pub containers: ::prost::alloc::vec::Vec<ContainerDiskUsage>,
+    #[prost(message, repeated, tag = "3")]
+    pub images: ::prost::alloc::vec::Vec<ImageDiskUsage>,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ContainerDiskUsage {
+    #[prost(string, tag = "1")]
+    pub container_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub name: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub image: ::prost::alloc::string::String,
+    #[prost(string, tag = "4")]
+    pub image_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "5")]
+    pub state: ::prost::alloc::string::String,
+    /// bytes in the container's writable layer
+    #[prost(int64, tag = "6")]
+    pub writable_size: i64,
+    /// writable layer plus image, as `docker ps --size` shows it
+    #[prost(int64, tag = "7")]
+    pub root_fs_size: i64,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ImageDiskUsage {
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+    #[prost(string, repeated, tag = "2")]
+    pub tags: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(int64, tag = "3")]
+    pub size: i64,
+    /// bytes in layers shared with other images, -1 if unknown
+    #[prost(int64, tag = "4")]
+    pub shared_size: i64,
+}
+/// `docker system df`
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SystemDiskUsage {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    #[prost(message, optional, tag = "2")]
+    pub images: ::core::option::Option<DiskUsageSummary>,
+    #[prost(message, optional, tag = "3")]
+    pub containers: ::core::option::Option<DiskUsageSummary>,
+    #[prost(message, optional, tag = "4")]
+    pub volumes: ::core::option::Option<DiskUsageSummary>,
+    #[prost(message, optional, tag = "5")]
+    pub build_cache: ::core::option::Option<DiskUsageSummary>,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DiskUsageSummary {
+    /// objects
+    #[prost(int64, tag = "1")]
+    pub total: i64,
+    /// used by a container; running containers; build cache in use
+    #[prost(int64, tag = "2")]
+    pub active: i64,
+    /// bytes
+    #[prost(int64, tag = "3")]
+    pub size: i64,
+    /// bytes a prune could free
+    #[prost(int64, tag = "4")]
+    pub reclaimable: i64,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SystemPruned {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    #[prost(string, repeated, tag = "2")]
+    pub containers_deleted: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// image and layer ids
+    #[prost(string, repeated, tag = "3")]
+    pub images_deleted: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(string, repeated, tag = "4")]
+    pub images_untagged: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(string, repeated, tag = "5")]
+    pub networks_deleted: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(string, repeated, tag = "6")]
+    pub volumes_deleted: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(string, repeated, tag = "7")]
+    pub build_cache_deleted: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// bytes
+    #[prost(int64, tag = "8")]
+    pub space_reclaimed: i64,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CleanupStatus {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    /// names of the rules in force
+    #[prost(string, repeated, tag = "2")]
+    pub rules: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// unix seconds, 0 before the first sweep
+    #[prost(int64, tag = "3")]
+    pub last_sweep_at: i64,
+    /// recent sweeps that removed containers or failed, newest first
+    #[prost(message, repeated, tag = "4")]
+    pub sweeps: ::prost::alloc::vec::Vec<CleanupSweep>,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CleanupSweep {
+    /// unix seconds
+    #[prost(int64, tag = "1")]
+    pub swept_at: i64,
+    #[prost(message, repeated, tag = "2")]
+    pub removed: ::prost::alloc::vec::Vec<RemovedContainer>,
+    #[prost(string, repeated, tag = "3")]
+    pub errors: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RemovedContainer {
+    #[prost(string, tag = "1")]
+    pub container_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub name: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub rule: ::prost::alloc::string::String,
+    /// unix seconds
+    #[prost(int64, tag = "4")]
+    pub exited_at: i64,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TrashStatus {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    /// 0 when deletes are immediate
+    #[prost(uint64, tag = "2")]
+    pub retention_secs: u64,
+    /// most recently deleted first
+    #[prost(message, repeated, tag = "3")]
+    pub containers: ::prost::alloc::vec::Vec<TrashedContainer>,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TrashedContainer {
+    #[prost(string, tag = "1")]
+    pub container_id: ::prost::alloc::string::String,
+    /// the name it had and gets back on restore
+    #[prost(string, tag = "2")]
+    pub name: ::prost::alloc::string::String,
+    /// the name it has in the trash
+    #[prost(string, tag = "3")]
+    pub trash_name: ::prost::alloc::string::String,
+    /// unix seconds
+    #[prost(int64, tag = "4")]
+    pub deleted_at: i64,
+    /// unix seconds, when the node removes it for good
+    #[prost(int64, tag = "5")]
+    pub expires_at: i64,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LogForwardingStatus {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    #[prost(message, repeated, tag = "2")]
+    pub forwards: ::prost::alloc::vec::Vec<LogForwardStatus>,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LogForwardStatus {
+    #[prost(string, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+    /// containers currently tailed
+    #[prost(string, repeated, tag = "2")]
+    pub containers: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(uint64, tag = "3")]
+    pub lines_forwarded: u64,
+    /// lines given up on after the sink kept failing
+    #[prost(uint64, tag = "4")]
+    pub lines_dropped: u64,
+    #[prost(string, tag = "5")]
+    pub last_error: ::prost::alloc::string::String,
+    /// unix seconds, 0 if none
+    #[prost(int64, tag = "6")]
+    pub last_error_at: i64,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ImageList {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    #[prost(message, repeated, tag = "2")]
+    pub images: ::prost::alloc::vec::Vec<ImageInfo>,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ImageInfo {
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+    #[prost(string, repeated, tag = "2")]
+    pub repo_tags: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(string, repeated, tag = "3")]
+    pub repo_digests: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// unix seconds
+    #[prost(int64, tag = "4")]
+    pub created: i64,
+    #[prost(int64, tag = "5")]
+    pub size: i64,
+    /// containers (any state) using the image
+    #[prost(int64, tag = "6")]
+    pub containers: i64,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ImagePulled {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    #[prost(string, tag = "2")]
+    pub image: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub id: ::prost::alloc::string::String,
+    #[prost(string, repeated, tag = "4")]
+    pub repo_digests: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// the reference now points to a different image than before
+    #[prost(bool, tag = "5")]
+    pub updated: bool,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ImagesRemoved {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    #[prost(string, repeated, tag = "2")]
+    pub untagged: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// image and layer ids
+    #[prost(string, repeated, tag = "3")]
+    pub deleted: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// bytes; prune only
+    #[prost(int64, tag = "4")]
+    pub space_reclaimed: i64,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct NetworkList {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    #[prost(message, repeated, tag = "2")]
+    pub networks: ::prost::alloc::vec::Vec<NetworkInfo>,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct NetworkInfo {
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub name: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub driver: ::prost::alloc::string::String,
+    /// local, global or swarm
+    #[prost(string, tag = "4")]
+    pub scope: ::prost::alloc::string::String,
+    #[prost(bool, tag = "5")]
+    pub internal: bool,
+    #[prost(bool, tag = "6")]
+    pub attachable: bool,
+    #[prost(bool, tag = "7")]
+    pub enable_ipv6: bool,
+    /// unix seconds
+    #[prost(int64, tag = "8")]
+    pub created: i64,
+    #[prost(message, repeated, tag = "9")]
+    pub subnets: ::prost::alloc::vec::Vec<NetworkSubnet>,
+    #[prost(map = "string, string", tag = "10")]
+    pub labels: ::std::collections::HashMap<
+        ::prost::alloc::string::String,
+        ::prost::alloc::string::String,
+    >,
+    #[prost(map = "string, string", tag = "11")]
+    pub options: ::std::collections::HashMap<
+        ::prost::alloc::string::String,
+        ::prost::alloc::string::String,
+    >,
+    /// attached containers, by name
+    #[prost(message, repeated, tag = "12")]
+    pub containers: ::prost::alloc::vec::Vec<NetworkEndpoint>,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct NetworkSubnet {
+    #[prost(string, tag = "1")]
+    pub subnet: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub gateway: ::prost::alloc::string::String,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct NetworkEndpoint {
+    #[prost(string, tag = "1")]
+    pub container_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub name: ::prost::alloc::string::String,
+    /// CIDR
+    #[prost(string, tag = "3")]
+    pub ipv4_address: ::prost::alloc::string::String,
+    #[prost(string, tag = "4")]
+    pub ipv6_address: ::prost::alloc::string::String,
+    #[prost(string, tag = "5")]
+    pub mac_address: ::prost::alloc::string::String,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct NetworkCreated {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    #[prost(string, tag = "2")]
+    pub id: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub name: ::prost::alloc::string::String,
+    #[prost(string, tag = "4")]
+    pub warning: ::prost::alloc::string::String,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct NetworkRemoved {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    #[prost(string, tag = "2")]
+    pub network: ::prost::alloc::string::String,
+}
+/// Pushed instead of the full lists when only some containers changed. The
+/// node sends a full NodeContainersWithStatus when it (re)subscribes and
+/// periodically, so the coordinator can resync from it
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ContainerDelta {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    #[prost(message, repeated, tag = "2")]
+    pub added: ::prost::alloc::vec::Vec<ContainerStatus>,
+    /// names of removed containers
+    #[prost(string, repeated, tag = "3")]
+    pub removed: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// new state of containers that changed
+    #[prost(message, repeated, tag = "4")]
+    pub changed: ::prost::alloc::vec::Vec<ContainerStatus>,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BroadcastAck {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    /// set when the node could not carry out the action
+    #[prost(string, tag = "2")]
+    pub error: ::prost::alloc::string::String,
+}
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct HostScriptResult {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    #[prost(string, tag = "2")]
+    pub name: ::prost::alloc::string::String,
+    /// -1 when the script was killed by a signal
+    #[prost(int32, tag = "3")]
+    pub exit_code: i32,
+    #[prost(string, tag = "4")]
+    pub stdout: ::prost::alloc::string::String,
+    #[prost(string, tag = "5")]
+    pub stderr: ::prost::alloc::string::String,
+    /// stdout or stderr was cut to the node's limit
+    #[prost(bool, tag = "6")]
+    pub truncated: bool,
+    #[prost(uint64, tag = "7")]
+    pub duration_ms: u64,
+}
+/// Error message for failed operations
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct NodeError {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip)]
+    pub request_key: ::core::option::Option<RequestKey>,
+    #[prost(string, tag = "2")]
+    pub message: ::prost::alloc::string::String,
+    #[prost(enumeration = "ErrorCode", tag = "3")]
+    pub code: i32,
+}
+/// Used to correlate requests and responses
+#[derive(serde::Serialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RequestKey {
+    #[prost(enumeration = "RequestType", tag = "1")]
+    pub request_type: i32,
+    #[prost(oneof = "request_key::RequestId", tags = "2, 3")]
+    pub request_id: ::core::option::Option<request_key::RequestId>,
+}
+/// Nested message and enum types in `RequestKey`.
+pub mod request_key {
+    #[derive(serde::Serialize)]
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum RequestId {
+        #[prost(string, tag = "2")]
+        Value(::prost::alloc::string::String),
+        #[prost(bool, tag = "3")]
+        Unspecific(bool),
+    }
+}
+/// What kind of failure a NodeError is, so callers need not parse the message
+/// Application-level compression of an envelope's payload, for links whose
+/// proxies do not pass gRPC transport compression
+#[derive(serde::Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum Compression {
+    None = 0,
+    Gzip = 1,
+    Zstd = 2,
+}
+impl Compression {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Compression::None => "COMPRESSION_NONE",
+            Compression::Gzip => "COMPRESSION_GZIP",
+            Compression::Zstd => "COMPRESSION_ZSTD",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "COMPRESSION_NONE" => Some(Self::None),
+            "COMPRESSION_GZIP" => Some(Self::Gzip),
+            "COMPRESSION_ZSTD" => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+}
+#[derive(serde::Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum LogStreams {
+    Both = 0,
+    Stdout = 1,
+    Stderr = 2,
+}
+impl LogStreams {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            LogStreams::Both => "LOG_STREAMS_BOTH",
+            LogStreams::Stdout => "LOG_STREAMS_STDOUT",
+            LogStreams::Stderr => "LOG_STREAMS_STDERR",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "LOG_STREAMS_BOTH" => Some(Self::Both),
+            "LOG_STREAMS_STDOUT" => Some(Self::Stdout),
+            "LOG_STREAMS_STDERR" => Some(Self::Stderr),
+            _ => None,
+        }
+    }
+}
+#[derive(serde::Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum ErrorCode {
+    Unspecified = 0,
+    NotFound = 1,
+    Conflict = 2,
+    DaemonUnavailable = 3,
+    PermissionDenied = 4,
+    Timeout = 5,
+}
+impl ErrorCode {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            ErrorCode::Unspecified => "ERROR_CODE_UNSPECIFIED",
+            ErrorCode::NotFound => "ERROR_CODE_NOT_FOUND",
+            ErrorCode::Conflict => "ERROR_CODE_CONFLICT",
+            ErrorCode::DaemonUnavailable => "ERROR_CODE_DAEMON_UNAVAILABLE",
+            ErrorCode::PermissionDenied => "ERROR_CODE_PERMISSION_DENIED",
+            ErrorCode::Timeout => "ERROR_CODE_TIMEOUT",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "ERROR_CODE_UNSPECIFIED" => Some(Self::Unspecified),
+            "ERROR_CODE_NOT_FOUND" => Some(Self::NotFound),
+            "ERROR_CODE_CONFLICT" => Some(Self::Conflict),
+            "ERROR_CODE_DAEMON_UNAVAILABLE" => Some(Self::DaemonUnavailable),
+            "ERROR_CODE_PERMISSION_DENIED" => Some(Self::PermissionDenied),
+            "ERROR_CODE_TIMEOUT" => Some(Self::Timeout),
+            _ => None,
+        }
     }
 }
 /// --- Request type enum for all supported actions ---
+#[derive(serde::Serialize)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
 pub enum RequestType {
@@ -326,6 +2536,48 @@ pub enum RequestType {
     DeleteContainer = 6,
     GetContainerLogs = 7,
     GetContainersWithStatus = 8,
+    BuildImage = 9,
+    InspectImageManifest = 10,
+    GetNodeMetrics = 11,
+    GetContainerStats = 12,
+    GetTopContainers = 13,
+    Ping = 14,
+    AttachContainer = 15,
+    GetImageHistory = 16,
+    ApplyAutostart = 17,
+    CreateContainer = 18,
+    GetContainerGraph = 19,
+    GetDiskUsage = 20,
+    ConfigureLogForwarding = 21,
+    GetLogForwarding = 22,
+    ListImages = 23,
+    PullImage = 24,
+    RemoveImage = 25,
+    PruneImages = 26,
+    ExecContainer = 27,
+    Broadcast = 28,
+    FollowContainerLogs = 29,
+    RunHostScript = 30,
+    RestartContainer = 31,
+    PauseContainer = 32,
+    UnpauseContainer = 33,
+    GetContainerArchive = 34,
+    PutContainerArchive = 35,
+    ListNetworks = 36,
+    InspectNetwork = 37,
+    CreateNetwork = 38,
+    RemoveNetwork = 39,
+    GetSystemInfo = 40,
+    GetSystemDiskUsage = 41,
+    PruneSystem = 42,
+    CloneContainer = 43,
+    ConfigureCleanup = 44,
+    GetCleanup = 45,
+    ExportContainer = 46,
+    ImportContainer = 47,
+    ConfigureTrash = 48,
+    GetTrash = 49,
+    RestoreContainer = 50,
 }
 impl RequestType {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -343,6 +2595,48 @@ impl RequestType {
             RequestType::DeleteContainer => "DELETE_CONTAINER",
             RequestType::GetContainerLogs => "GET_CONTAINER_LOGS",
             RequestType::GetContainersWithStatus => "GET_CONTAINERS_WITH_STATUS",
+            RequestType::BuildImage => "BUILD_IMAGE",
+            RequestType::InspectImageManifest => "INSPECT_IMAGE_MANIFEST",
+            RequestType::GetNodeMetrics => "GET_NODE_METRICS",
+            RequestType::GetContainerStats => "GET_CONTAINER_STATS",
+            RequestType::GetTopContainers => "GET_TOP_CONTAINERS",
+            RequestType::Ping => "PING",
+            RequestType::AttachContainer => "ATTACH_CONTAINER",
+            RequestType::GetImageHistory => "GET_IMAGE_HISTORY",
+            RequestType::ApplyAutostart => "APPLY_AUTOSTART",
+            RequestType::CreateContainer => "CREATE_CONTAINER",
+            RequestType::GetContainerGraph => "GET_CONTAINER_GRAPH",
+            RequestType::GetDiskUsage => "GET_DISK_USAGE",
+            RequestType::ConfigureLogForwarding => "CONFIGURE_LOG_FORWARDING",
+            RequestType::GetLogForwarding => "GET_LOG_FORWARDING",
+            RequestType::ListImages => "LIST_IMAGES",
+            RequestType::PullImage => "PULL_IMAGE",
+            RequestType::RemoveImage => "REMOVE_IMAGE",
+            RequestType::PruneImages => "PRUNE_IMAGES",
+            RequestType::ExecContainer => "EXEC_CONTAINER",
+            RequestType::Broadcast => "BROADCAST",
+            RequestType::FollowContainerLogs => "FOLLOW_CONTAINER_LOGS",
+            RequestType::RunHostScript => "RUN_HOST_SCRIPT",
+            RequestType::RestartContainer => "RESTART_CONTAINER",
+            RequestType::PauseContainer => "PAUSE_CONTAINER",
+            RequestType::UnpauseContainer => "UNPAUSE_CONTAINER",
+            RequestType::GetContainerArchive => "GET_CONTAINER_ARCHIVE",
+            RequestType::PutContainerArchive => "PUT_CONTAINER_ARCHIVE",
+            RequestType::ListNetworks => "LIST_NETWORKS",
+            RequestType::InspectNetwork => "INSPECT_NETWORK",
+            RequestType::CreateNetwork => "CREATE_NETWORK",
+            RequestType::RemoveNetwork => "REMOVE_NETWORK",
+            RequestType::GetSystemInfo => "GET_SYSTEM_INFO",
+            RequestType::GetSystemDiskUsage => "GET_SYSTEM_DISK_USAGE",
+            RequestType::PruneSystem => "PRUNE_SYSTEM",
+            RequestType::CloneContainer => "CLONE_CONTAINER",
+            RequestType::ConfigureCleanup => "CONFIGURE_CLEANUP",
+            RequestType::GetCleanup => "GET_CLEANUP",
+            RequestType::ExportContainer => "EXPORT_CONTAINER",
+            RequestType::ImportContainer => "IMPORT_CONTAINER",
+            RequestType::ConfigureTrash => "CONFIGURE_TRASH",
+            RequestType::GetTrash => "GET_TRASH",
+            RequestType::RestoreContainer => "RESTORE_CONTAINER",
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
@@ -357,6 +2651,48 @@ impl RequestType {
             "DELETE_CONTAINER" => Some(Self::DeleteContainer),
             "GET_CONTAINER_LOGS" => Some(Self::GetContainerLogs),
             "GET_CONTAINERS_WITH_STATUS" => Some(Self::GetContainersWithStatus),
+            "BUILD_IMAGE" => Some(Self::BuildImage),
+            "INSPECT_IMAGE_MANIFEST" => Some(Self::InspectImageManifest),
+            "GET_NODE_METRICS" => Some(Self::GetNodeMetrics),
+            "GET_CONTAINER_STATS" => Some(Self::GetContainerStats),
+            "GET_TOP_CONTAINERS" => Some(Self::GetTopContainers),
+            "PING" => Some(Self::Ping),
+            "ATTACH_CONTAINER" => Some(Self::AttachContainer),
+            "GET_IMAGE_HISTORY" => Some(Self::GetImageHistory),
+            "APPLY_AUTOSTART" => Some(Self::ApplyAutostart),
+            "CREATE_CONTAINER" => Some(Self::CreateContainer),
+            "GET_CONTAINER_GRAPH" => Some(Self::GetContainerGraph),
+            "GET_DISK_USAGE" => Some(Self::GetDiskUsage),
+            "CONFIGURE_LOG_FORWARDING" => Some(Self::ConfigureLogForwarding),
+            "GET_LOG_FORWARDING" => Some(Self::GetLogForwarding),
+            "LIST_IMAGES" => Some(Self::ListImages),
+            "PULL_IMAGE" => Some(Self::PullImage),
+            "REMOVE_IMAGE" => Some(Self::RemoveImage),
+            "PRUNE_IMAGES" => Some(Self::PruneImages),
+            "EXEC_CONTAINER" => Some(Self::ExecContainer),
+            "BROADCAST" => Some(Self::Broadcast),
+            "FOLLOW_CONTAINER_LOGS" => Some(Self::FollowContainerLogs),
+            "RUN_HOST_SCRIPT" => Some(Self::RunHostScript),
+            "RESTART_CONTAINER" => Some(Self::RestartContainer),
+            "PAUSE_CONTAINER" => Some(Self::PauseContainer),
+            "UNPAUSE_CONTAINER" => Some(Self::UnpauseContainer),
+            "GET_CONTAINER_ARCHIVE" => Some(Self::GetContainerArchive),
+            "PUT_CONTAINER_ARCHIVE" => Some(Self::PutContainerArchive),
+            "LIST_NETWORKS" => Some(Self::ListNetworks),
+            "INSPECT_NETWORK" => Some(Self::InspectNetwork),
+            "CREATE_NETWORK" => Some(Self::CreateNetwork),
+            "REMOVE_NETWORK" => Some(Self::RemoveNetwork),
+            "GET_SYSTEM_INFO" => Some(Self::GetSystemInfo),
+            "GET_SYSTEM_DISK_USAGE" => Some(Self::GetSystemDiskUsage),
+            "PRUNE_SYSTEM" => Some(Self::PruneSystem),
+            "CLONE_CONTAINER" => Some(Self::CloneContainer),
+            "CONFIGURE_CLEANUP" => Some(Self::ConfigureCleanup),
+            "GET_CLEANUP" => Some(Self::GetCleanup),
+            "EXPORT_CONTAINER" => Some(Self::ExportContainer),
+            "IMPORT_CONTAINER" => Some(Self::ImportContainer),
+            "CONFIGURE_TRASH" => Some(Self::ConfigureTrash),
+            "GET_TRASH" => Some(Self::GetTrash),
+            "RESTORE_CONTAINER" => Some(Self::RestoreContainer),
             _ => None,
         }
     }
@@ -1,3 +1,123 @@
+pub mod compression;
+
 pub mod generated {
     include!("generated/conversation.rs");
 }
+
+/// Default limit for a single gRPC message, in bytes, on both the coordinator and the node.
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+/// `Envelope.metadata` keys: the id of the node command, the id of the REST or
+/// WebSocket request that sent it (its `X-Request-Id`), and who made that request.
+pub const METADATA_REQUEST_ID: &str = "request_id";
+pub const METADATA_TRACE_ID: &str = "trace_id";
+pub const METADATA_ACTOR: &str = "actor";
+
+impl generated::NodeResponse {
+    /// Key correlating this response with its request, if the kind carries one.
+    pub fn request_key(&self) -> Option<&generated::RequestKey> {
+        use generated::node_response::Kind;
+
+        match self.kind.as_ref()? {
+            Kind::NodeContainers(c) => c.request_key.as_ref(),
+            Kind::NodeContainersWithStatus(c) => c.request_key.as_ref(),
+            Kind::ContainerDelta(c) => c.request_key.as_ref(),
+            Kind::ContainerStatus(c) => c.request_key.as_ref(),
+            Kind::ContainerLogs(c) => c.request_key.as_ref(),
+            Kind::ContainerLogsChunk(c) => c.request_key.as_ref(),
+            Kind::ContainerAction(c) => c.request_key.as_ref(),
+            Kind::Error(c) => c.request_key.as_ref(),
+            Kind::ImageBuildOutput(c) => c.request_key.as_ref(),
+            Kind::ContainerArchive(c) => c.request_key.as_ref(),
+            Kind::ImageManifest(c) => c.request_key.as_ref(),
+            Kind::ImageHistory(c) => c.request_key.as_ref(),
+            Kind::ContainerGraph(c) => c.request_key.as_ref(),
+            Kind::DiskUsage(c) => c.request_key.as_ref(),
+            Kind::LogForwardingStatus(c) => c.request_key.as_ref(),
+            Kind::CleanupStatus(c) => c.request_key.as_ref(),
+            Kind::TrashStatus(c) => c.request_key.as_ref(),
+            Kind::MigrationChunk(c) => c.request_key.as_ref(),
+            Kind::ImageList(c) => c.request_key.as_ref(),
+            Kind::ImagePulled(c) => c.request_key.as_ref(),
+            Kind::ImagesRemoved(c) => c.request_key.as_ref(),
+            Kind::NetworkList(c) => c.request_key.as_ref(),
+            Kind::NetworkCreated(c) => c.request_key.as_ref(),
+            Kind::NetworkRemoved(c) => c.request_key.as_ref(),
+            Kind::SystemInfo(c) => c.request_key.as_ref(),
+            Kind::SystemDiskUsage(c) => c.request_key.as_ref(),
+            Kind::SystemPruned(c) => c.request_key.as_ref(),
+            Kind::AutostartReport(c) => c.request_key.as_ref(),
+            Kind::ContainerCreated(c) => c.request_key.as_ref(),
+            Kind::NodeMetrics(c) => c.request_key.as_ref(),
+            Kind::ContainerStats(c) => c.request_key.as_ref(),
+            Kind::TopContainers(c) => c.request_key.as_ref(),
+            Kind::ContainerEvent(c) => c.request_key.as_ref(),
+            Kind::Pong(c) => c.request_key.as_ref(),
+            Kind::SessionOutput(c) => c.request_key.as_ref(),
+            Kind::BroadcastAck(c) => c.request_key.as_ref(),
+            Kind::HostScriptResult(c) => c.request_key.as_ref(),
+        }
+    }
+}
+
+impl generated::node_command::Kind {
+    /// Request (or session) id the command belongs to.
+    pub fn request_id(&self) -> &str {
+        use generated::node_command::Kind;
+
+        match self {
+            Kind::GetNodeContainers(c) => &c.request_id,
+            Kind::GetNodeContainersWithStatus(c) => &c.request_id,
+            Kind::GetContainerStatus(c) => &c.request_id,
+            Kind::StartContainer(c) => &c.request_id,
+            Kind::StopContainer(c) => &c.request_id,
+            Kind::RestartContainer(c) => &c.request_id,
+            Kind::PauseContainer(c) => &c.request_id,
+            Kind::UnpauseContainer(c) => &c.request_id,
+            Kind::DeleteContainer(c) => &c.request_id,
+            Kind::GetContainerArchive(c) => &c.request_id,
+            Kind::PutContainerArchive(c) => &c.request_id,
+            Kind::GetContainerLogs(c) => &c.request_id,
+            Kind::BuildImage(c) => &c.request_id,
+            Kind::InspectImageManifest(c) => &c.request_id,
+            Kind::GetImageHistory(c) => &c.request_id,
+            Kind::GetContainerGraph(c) => &c.request_id,
+            Kind::GetDiskUsage(c) => &c.request_id,
+            Kind::ConfigureLogForwarding(c) => &c.request_id,
+            Kind::GetLogForwarding(c) => &c.request_id,
+            Kind::ConfigureCleanup(c) => &c.request_id,
+            Kind::GetCleanup(c) => &c.request_id,
+            Kind::ConfigureTrash(c) => &c.request_id,
+            Kind::GetTrash(c) => &c.request_id,
+            Kind::RestoreContainer(c) => &c.request_id,
+            Kind::ExportContainer(c) => &c.request_id,
+            Kind::ImportContainer(c) => &c.request_id,
+            Kind::ListImages(c) => &c.request_id,
+            Kind::PullImage(c) => &c.request_id,
+            Kind::RemoveImage(c) => &c.request_id,
+            Kind::PruneImages(c) => &c.request_id,
+            Kind::ListNetworks(c) => &c.request_id,
+            Kind::InspectNetwork(c) => &c.request_id,
+            Kind::CreateNetwork(c) => &c.request_id,
+            Kind::RemoveNetwork(c) => &c.request_id,
+            Kind::ApplyAutostart(c) => &c.request_id,
+            Kind::CreateContainer(c) => &c.request_id,
+            Kind::CloneContainer(c) => &c.request_id,
+            Kind::GetNodeMetrics(c) => &c.request_id,
+            Kind::GetSystemInfo(c) => &c.request_id,
+            Kind::GetSystemDiskUsage(c) => &c.request_id,
+            Kind::PruneSystem(c) => &c.request_id,
+            Kind::GetContainerStats(c) => &c.request_id,
+            Kind::GetTopContainers(c) => &c.request_id,
+            Kind::Ping(c) => &c.request_id,
+            Kind::AttachContainer(c) => &c.request_id,
+            Kind::ExecContainer(c) => &c.request_id,
+            Kind::FollowContainerLogs(c) => &c.request_id,
+            Kind::RunHostScript(c) => &c.request_id,
+            Kind::SessionInput(c) => &c.request_id,
+            Kind::CloseSession(c) => &c.request_id,
+            Kind::ResizeSession(c) => &c.request_id,
+            Kind::CancelRequest(c) => &c.request_id,
+        }
+    }
+}